@@ -1,4 +1,4 @@
-use alloc::sync::Arc;
+use alloc::{sync::Arc, vec::Vec};
 use core::ffi::c_int;
 
 use axerrno::{LinuxError, LinuxResult};
@@ -12,6 +12,11 @@ use crate::imp::stdio::{stdin, stdout};
 
 pub const AX_FILE_LIMIT: usize = 1024;
 
+#[cfg(feature = "pipe")]
+const F_SETPIPE_SZ: u32 = 1031;
+#[cfg(feature = "pipe")]
+const F_GETPIPE_SZ: u32 = 1032;
+
 #[allow(dead_code)]
 pub trait FileLike: Send + Sync {
     fn read(&self, buf: &mut [u8]) -> LinuxResult<usize>;
@@ -20,20 +25,140 @@ pub trait FileLike: Send + Sync {
     fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync>;
     fn poll(&self) -> LinuxResult<PollState>;
     fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult;
+
+    /// Whether the file is in non-blocking mode, which `F_GETFL` reports as
+    /// `O_NONBLOCK`.
+    fn nonblocking(&self) -> bool {
+        false
+    }
+
+    /// Whether the other end is gone, which `poll` reports as `POLLHUP`.
+    fn hung_up(&self) -> bool {
+        false
+    }
+
+    /// Whether the other end has shut down writing, so that no more data will
+    /// arrive, which `poll` reports as `POLLRDHUP`.
+    fn read_closed(&self) -> bool {
+        false
+    }
+
+    /// Whether the file is in an error state, which `poll` reports as
+    /// `POLLERR`.
+    fn has_error(&self) -> bool {
+        false
+    }
+
+    /// Whether the file wakes the tasks polling it whenever its readiness
+    /// changes, so that `poll` and `epoll_wait` may sleep on it rather than
+    /// check it over and over.
+    fn notifies_pollers(&self) -> bool {
+        false
+    }
+
+    /// The path of the directory this stands for, if the `*at` calls can
+    /// look up relative paths in it.
+    fn dir_path(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// The open files of a task by fd, along with the close-on-exec flag of each
+/// fd.
+pub struct FdTable {
+    files: FlattenObjects<Arc<dyn FileLike>, AX_FILE_LIMIT>,
+    /// One bit per fd, set if the fd is closed on `execve`.
+    cloexec: [u64; AX_FILE_LIMIT.div_ceil(64)],
+}
+
+impl FdTable {
+    /// Creates an empty table.
+    pub const fn new() -> Self {
+        Self {
+            files: FlattenObjects::new(),
+            cloexec: [0; AX_FILE_LIMIT.div_ceil(64)],
+        }
+    }
+
+    /// The file at `fd`.
+    pub fn get(&self, fd: usize) -> Option<&Arc<dyn FileLike>> {
+        self.files.get(fd)
+    }
+
+    /// The fds in use.
+    pub fn ids(&self) -> impl Iterator<Item = usize> + '_ {
+        self.files.ids()
+    }
+
+    /// Adds `file` at the lowest free fd, which is left open on exec.
+    pub fn add(&mut self, file: Arc<dyn FileLike>) -> Result<usize, Arc<dyn FileLike>> {
+        let fd = self.files.add(file)?;
+        self.set_cloexec(fd, false);
+        Ok(fd)
+    }
+
+    /// Adds `file` at `fd`, which is left open on exec.
+    pub fn add_at(
+        &mut self,
+        fd: usize,
+        file: Arc<dyn FileLike>,
+    ) -> Result<usize, Arc<dyn FileLike>> {
+        let fd = self.files.add_at(fd, file)?;
+        self.set_cloexec(fd, false);
+        Ok(fd)
+    }
+
+    /// Removes the file at `fd`.
+    pub fn remove(&mut self, fd: usize) -> Option<Arc<dyn FileLike>> {
+        let file = self.files.remove(fd)?;
+        self.set_cloexec(fd, false);
+        Some(file)
+    }
+
+    /// Whether `fd` is closed on exec.
+    pub fn cloexec(&self, fd: usize) -> bool {
+        fd < AX_FILE_LIMIT && self.cloexec[fd / 64] & (1 << (fd % 64)) != 0
+    }
+
+    /// Sets whether `fd` is closed on exec.
+    pub fn set_cloexec(&mut self, fd: usize, cloexec: bool) {
+        if fd >= AX_FILE_LIMIT {
+            return;
+        }
+        if cloexec {
+            self.cloexec[fd / 64] |= 1 << (fd % 64);
+        } else {
+            self.cloexec[fd / 64] &= !(1 << (fd % 64));
+        }
+    }
+
+    /// Removes the fds that are closed on exec, returning their files so that
+    /// they can be dropped after the table is unlocked.
+    pub fn close_on_exec(&mut self) -> Vec<Arc<dyn FileLike>> {
+        let fds: Vec<usize> = self.ids().filter(|&fd| self.cloexec(fd)).collect();
+        fds.into_iter().filter_map(|fd| self.remove(fd)).collect()
+    }
+}
+
+impl Default for FdTable {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 def_resource! {
-    pub static FD_TABLE: ResArc<RwLock<FlattenObjects<Arc<dyn FileLike>, AX_FILE_LIMIT>>> = ResArc::new();
+    pub static FD_TABLE: ResArc<RwLock<FdTable>> = ResArc::new();
 }
 
 impl FD_TABLE {
     /// Return a copy of the inner table.
-    pub fn copy_inner(&self) -> RwLock<FlattenObjects<Arc<dyn FileLike>, AX_FILE_LIMIT>> {
+    pub fn copy_inner(&self) -> RwLock<FdTable> {
         let table = self.read();
-        let mut new_table = FlattenObjects::new();
+        let mut new_table = FdTable::new();
         for id in table.ids() {
             let _ = new_table.add_at(id, table.get(id).unwrap().clone());
         }
+        new_table.cloexec = table.cloexec;
         RwLock::new(new_table)
     }
 }
@@ -112,16 +237,42 @@ pub fn sys_dup2(old_fd: c_int, new_fd: c_int) -> c_int {
 }
 
 /// Manipulate file descriptor.
-///
-/// TODO: `SET/GET` command is ignored, hard-code stdin/stdout
 pub fn sys_fcntl(fd: c_int, cmd: c_int, arg: usize) -> c_int {
     debug!("sys_fcntl <= fd: {} cmd: {} arg: {}", fd, cmd, arg);
     syscall_body!(sys_fcntl, {
         match cmd as u32 {
             ctypes::F_DUPFD => dup_fd(fd),
             ctypes::F_DUPFD_CLOEXEC => {
-                // TODO: Change fd flags
-                dup_fd(fd)
+                let new_fd = dup_fd(fd)?;
+                FD_TABLE.write().set_cloexec(new_fd as usize, true);
+                Ok(new_fd)
+            }
+            ctypes::F_GETFD => {
+                let table = FD_TABLE.read();
+                table.get(fd as usize).ok_or(LinuxError::EBADF)?;
+                if table.cloexec(fd as usize) {
+                    Ok(ctypes::FD_CLOEXEC as c_int)
+                } else {
+                    Ok(0)
+                }
+            }
+            ctypes::F_SETFD => {
+                let mut table = FD_TABLE.write();
+                table.get(fd as usize).ok_or(LinuxError::EBADF)?;
+                table.set_cloexec(fd as usize, arg & ctypes::FD_CLOEXEC as usize != 0);
+                Ok(0)
+            }
+            #[cfg(feature = "pipe")]
+            F_SETPIPE_SZ => Ok(super::pipe::Pipe::from_fd(fd)?.set_capacity(arg)? as c_int),
+            #[cfg(feature = "pipe")]
+            F_GETPIPE_SZ => Ok(super::pipe::Pipe::from_fd(fd)?.capacity() as c_int),
+            ctypes::F_GETFL => {
+                // Only `O_NONBLOCK` is tracked, not the access mode.
+                if get_file_like(fd)?.nonblocking() {
+                    Ok(ctypes::O_NONBLOCK as c_int)
+                } else {
+                    Ok(0)
+                }
             }
             ctypes::F_SETFL => {
                 if fd == 0 || fd == 1 || fd == 2 {
@@ -140,7 +291,7 @@ pub fn sys_fcntl(fd: c_int, cmd: c_int, arg: usize) -> c_int {
 
 #[ctor_bare::register_ctor]
 fn init_stdio() {
-    let mut fd_table = flatten_objects::FlattenObjects::new();
+    let mut fd_table = FdTable::new();
     fd_table
         .add_at(0, Arc::new(stdin()) as _)
         .unwrap_or_else(|_| panic!()); // stdin
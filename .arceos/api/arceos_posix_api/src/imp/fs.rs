@@ -369,4 +369,8 @@ impl FileLike for Directory {
     fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
         Ok(())
     }
+
+    fn dir_path(&self) -> Option<&str> {
+        Some(&self.path)
+    }
 }
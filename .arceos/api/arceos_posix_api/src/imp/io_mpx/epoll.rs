@@ -29,7 +29,7 @@ impl EpollInstance {
         }
     }
 
-    fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+    pub fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
         get_file_like(fd)?
             .into_any()
             .downcast::<EpollInstance>()
@@ -73,38 +73,68 @@ impl EpollInstance {
         Ok(0)
     }
 
+    /// Whether every file watched wakes its pollers, as
+    /// [`FileLike::notifies_pollers`] tells. Files closed since they were
+    /// added are not watched any more.
+    pub fn notifies_pollers(&self) -> bool {
+        self.events
+            .lock()
+            .keys()
+            .all(|&fd| get_file_like(fd as c_int).map_or(true, |file| file.notifies_pollers()))
+    }
+
     fn poll_all(&self, events: &mut [ctypes::epoll_event]) -> LinuxResult<usize> {
         let ready_list = self.events.lock();
         let mut events_num = 0;
 
         for (infd, ev) in ready_list.iter() {
-            match get_file_like(*infd as c_int)?.poll() {
-                Err(_) => {
-                    if (ev.events & ctypes::EPOLLERR) != 0 {
-                        events[events_num].events = ctypes::EPOLLERR;
-                        events[events_num].data = ev.data;
-                        events_num += 1;
-                    }
-                }
-                Ok(state) => {
-                    if state.readable && (ev.events & ctypes::EPOLLIN != 0) {
-                        events[events_num].events = ctypes::EPOLLIN;
-                        events[events_num].data = ev.data;
-                        events_num += 1;
-                    }
-
-                    if state.writable && (ev.events & ctypes::EPOLLOUT != 0) {
-                        events[events_num].events = ctypes::EPOLLOUT;
-                        events[events_num].data = ev.data;
-                        events_num += 1;
-                    }
-                }
+            if events_num == events.len() {
+                break;
+            }
+            // Errors and hangups are reported even if not asked for.
+            // A file closed since it was added is no longer watched.
+            let Ok(file) = get_file_like(*infd as c_int) else {
+                continue;
+            };
+            let revents = poll_events(&*file) & (ev.events | ctypes::EPOLLERR | ctypes::EPOLLHUP);
+            if revents != 0 {
+                events[events_num].events = revents;
+                events[events_num].data = ev.data;
+                events_num += 1;
             }
         }
         Ok(events_num)
     }
 }
 
+/// Returns the events that have occurred on `file`, in the bits of both
+/// `EPOLL*` and `POLL*` events, which are the same.
+pub fn poll_events(file: &dyn FileLike) -> u32 {
+    let mut revents = match file.poll() {
+        Ok(state) => {
+            let mut revents = 0;
+            if state.readable {
+                revents |= ctypes::EPOLLIN | ctypes::EPOLLRDNORM;
+            }
+            if state.writable {
+                revents |= ctypes::EPOLLOUT | ctypes::EPOLLWRNORM;
+            }
+            revents
+        }
+        Err(_) => ctypes::EPOLLERR,
+    };
+    if file.read_closed() {
+        revents |= ctypes::EPOLLRDHUP;
+    }
+    if file.hung_up() {
+        revents |= ctypes::EPOLLHUP;
+    }
+    if file.has_error() {
+        revents |= ctypes::EPOLLERR;
+    }
+    revents
+}
+
 impl FileLike for EpollInstance {
     fn read(&self, _buf: &mut [u8]) -> LinuxResult<usize> {
         Err(LinuxError::ENOSYS)
@@ -199,6 +229,9 @@ pub unsafe fn sys_epoll_wait(
                 debug!("    timeout!");
                 return Ok(0);
             }
+            if crate::imp::task::interrupted() {
+                return Err(LinuxError::EINTR);
+            }
             crate::sys_sched_yield();
         }
     })
@@ -11,6 +11,8 @@ mod epoll;
 mod select;
 
 #[cfg(feature = "epoll")]
-pub use self::epoll::{sys_epoll_create, sys_epoll_ctl, sys_epoll_wait};
+pub use self::epoll::{
+    EpollInstance, poll_events, sys_epoll_create, sys_epoll_ctl, sys_epoll_wait,
+};
 #[cfg(feature = "select")]
 pub use self::select::sys_select;
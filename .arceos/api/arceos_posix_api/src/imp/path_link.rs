@@ -341,20 +341,27 @@ fn handle_empty_path(dir_fd: isize) -> AxResult<String> {
         return Ok(String::from("."));
     }
 
-    super::fs::Directory::from_fd(dir_fd as i32)
-        .map(|dir| dir.path().to_string())
-        .map_err(|_| AxError::NotFound)
+    dir_path(dir_fd).ok_or(AxError::NotFound)
+}
+
+/// 文件描述符所指目录的路径，目录可以是打开的目录，也可以是 `O_PATH` 打开的目录
+fn dir_path(dir_fd: isize) -> Option<String> {
+    let file = super::fd_ops::get_file_like(dir_fd as i32).ok()?;
+    file.dir_path().map(String::from)
 }
 
 fn handle_relative_path(dir_fd: isize, path: &str) -> AxResult<String> {
-    match super::fs::Directory::from_fd(dir_fd as i32) {
-        Ok(dir) => {
-            // 假设目录路径以 '/' 结尾，无需手动添加
-            let combined_path = format!("{}{}", dir.path(), path);
-            axlog::info!("处理后的路径: {} (目录: {})", combined_path, dir.path());
+    match dir_path(dir_fd) {
+        Some(dir) => {
+            let combined_path = if dir.ends_with('/') {
+                format!("{}{}", dir, path)
+            } else {
+                format!("{}/{}", dir, path)
+            };
+            axlog::info!("处理后的路径: {} (目录: {})", combined_path, dir);
             Ok(combined_path)
         }
-        Err(_) => {
+        None => {
             axlog::warn!("文件描述符不存在");
             Err(AxError::NotFound)
         }
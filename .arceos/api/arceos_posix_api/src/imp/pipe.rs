@@ -1,5 +1,8 @@
-use alloc::sync::Arc;
-use core::ffi::c_int;
+use alloc::{collections::BTreeMap, sync::Arc, vec, vec::Vec};
+use core::{
+    ffi::c_int,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
 
 use axerrno::{LinuxError, LinuxResult};
 use axio::PollState;
@@ -15,29 +18,99 @@ enum RingBufferStatus {
     Normal,
 }
 
-const RING_BUFFER_SIZE: usize = 256;
+const PAGE_SIZE: usize = 4096;
+
+/// The capacity of a new pipe.
+const PIPE_DEFAULT_SIZE: usize = 16 * PAGE_SIZE;
+
+/// The largest capacity `F_SETPIPE_SZ` can set.
+pub const PIPE_MAX_SIZE: usize = 1024 * 1024;
+
+/// The most pages the buffers of one user's pipes may take up together, or 0
+/// for no limit. Unlike Linux, it holds for root too, as there is no memory
+/// set aside to let root go past it.
+static PIPE_USER_PAGES_LIMIT: AtomicUsize = AtomicUsize::new(8192);
+
+/// The pages taken up by the buffers of each user's pipes.
+static PIPE_USER_PAGES: Mutex<BTreeMap<u32, usize>> = Mutex::new(BTreeMap::new());
+
+/// The most pages the buffers of one user's pipes may take up, or 0 for no
+/// limit.
+pub fn pipe_user_pages_limit() -> usize {
+    PIPE_USER_PAGES_LIMIT.load(Ordering::Relaxed)
+}
+
+/// Sets the most pages the buffers of one user's pipes may take up. It only
+/// holds back new buffers, and those already over it are kept.
+pub fn set_pipe_user_pages_limit(pages: usize) {
+    PIPE_USER_PAGES_LIMIT.store(pages, Ordering::Relaxed);
+}
+
+/// The pages of a pipe buffer, charged to the user who made the pipe until
+/// it is dropped.
+struct PipeCharge {
+    user: u32,
+    pages: usize,
+}
+
+impl PipeCharge {
+    /// Charges `pages` to `user`, failing with `ENOMEM` if that would put the
+    /// user over the limit.
+    fn new(user: u32, pages: usize) -> LinuxResult<Self> {
+        let mut charge = Self { user, pages: 0 };
+        charge.set_pages(pages)?;
+        Ok(charge)
+    }
+
+    /// Charges `pages` in place of what was charged before. Only growing can
+    /// fail.
+    fn set_pages(&mut self, pages: usize) -> LinuxResult {
+        let mut usage = PIPE_USER_PAGES.lock();
+        let used = usage.entry(self.user).or_default();
+        let limit = pipe_user_pages_limit();
+        if pages > self.pages && limit != 0 && *used - self.pages + pages > limit {
+            return Err(LinuxError::ENOMEM);
+        }
+        *used = *used - self.pages + pages;
+        if *used == 0 {
+            usage.remove(&self.user);
+        }
+        self.pages = pages;
+        Ok(())
+    }
+}
+
+impl Drop for PipeCharge {
+    fn drop(&mut self) {
+        self.set_pages(0).ok();
+    }
+}
 
 pub struct PipeRingBuffer {
-    arr: [u8; RING_BUFFER_SIZE],
+    charge: PipeCharge,
+    arr: Vec<u8>,
     head: usize,
     tail: usize,
     status: RingBufferStatus,
 }
 
 impl PipeRingBuffer {
-    pub const fn new() -> Self {
-        Self {
-            arr: [0; RING_BUFFER_SIZE],
+    /// Makes a buffer of `capacity` bytes, a whole number of pages, charged
+    /// to `user`.
+    fn new(user: u32, capacity: usize) -> LinuxResult<Self> {
+        Ok(Self {
+            charge: PipeCharge::new(user, capacity / PAGE_SIZE)?,
+            arr: vec![0; capacity],
             head: 0,
             tail: 0,
             status: RingBufferStatus::Empty,
-        }
+        })
     }
 
     pub fn write_byte(&mut self, byte: u8) {
         self.status = RingBufferStatus::Normal;
         self.arr[self.tail] = byte;
-        self.tail = (self.tail + 1) % RING_BUFFER_SIZE;
+        self.tail = (self.tail + 1) % self.capacity();
         if self.tail == self.head {
             self.status = RingBufferStatus::Full;
         }
@@ -46,7 +119,7 @@ impl PipeRingBuffer {
     pub fn read_byte(&mut self) -> u8 {
         self.status = RingBufferStatus::Normal;
         let c = self.arr[self.head];
-        self.head = (self.head + 1) % RING_BUFFER_SIZE;
+        self.head = (self.head + 1) % self.capacity();
         if self.head == self.tail {
             self.status = RingBufferStatus::Empty;
         }
@@ -54,43 +127,102 @@ impl PipeRingBuffer {
     }
 
     /// Get the length of remaining data in the buffer
-    pub const fn available_read(&self) -> usize {
+    pub fn available_read(&self) -> usize {
         if matches!(self.status, RingBufferStatus::Empty) {
             0
         } else if self.tail > self.head {
             self.tail - self.head
         } else {
-            self.tail + RING_BUFFER_SIZE - self.head
+            self.tail + self.capacity() - self.head
         }
     }
 
     /// Get the length of remaining space in the buffer
-    pub const fn available_write(&self) -> usize {
+    pub fn available_write(&self) -> usize {
         if matches!(self.status, RingBufferStatus::Full) {
             0
         } else {
-            RING_BUFFER_SIZE - self.available_read()
+            self.capacity() - self.available_read()
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.arr.len()
+    }
+
+    /// Changes the capacity to `capacity`, a whole number of pages, keeping
+    /// the data in the buffer.
+    ///
+    /// Fails with `EBUSY` if the data would not fit, or with `EPERM` if the
+    /// owner of the pipe has too many pages in pipes to grow it.
+    pub fn resize(&mut self, capacity: usize) -> LinuxResult {
+        let len = self.available_read();
+        if capacity < len {
+            return Err(LinuxError::EBUSY);
+        }
+        self.charge
+            .set_pages(capacity / PAGE_SIZE)
+            .map_err(|_| LinuxError::EPERM)?;
+        let mut arr = vec![0; capacity];
+        for byte in arr.iter_mut().take(len) {
+            *byte = self.read_byte();
         }
+        self.arr = arr;
+        self.head = 0;
+        self.tail = len % capacity;
+        self.status = match len {
+            0 => RingBufferStatus::Empty,
+            _ if len == capacity => RingBufferStatus::Full,
+            _ => RingBufferStatus::Normal,
+        };
+        Ok(())
     }
 }
 
 pub struct Pipe {
     readable: bool,
+    /// The inode number both ends report, as in `pipe:[ino]`.
+    ino: u64,
     buffer: Arc<Mutex<PipeRingBuffer>>,
 }
 
 impl Pipe {
-    pub fn new() -> (Pipe, Pipe) {
-        let buffer = Arc::new(Mutex::new(PipeRingBuffer::new()));
+    /// Makes a pipe with a buffer of the default size, charged to `user`.
+    pub fn new(user: u32) -> LinuxResult<(Pipe, Pipe)> {
+        Self::with_capacity(user, PIPE_DEFAULT_SIZE)
+    }
+
+    /// Makes a pipe with a buffer of at least `size` bytes, rounded up to
+    /// whole pages and clamped to [`PIPE_MAX_SIZE`]. It is charged to `user`, and it fails with `ENOMEM` if
+    /// that would put them over [`pipe_user_pages_limit`].
+    pub fn with_capacity(user: u32, size: usize) -> LinuxResult<(Pipe, Pipe)> {
+        static NEXT_INO: AtomicU64 = AtomicU64::new(1);
+        let capacity = size
+            .clamp(PAGE_SIZE, PIPE_MAX_SIZE)
+            .next_multiple_of(PAGE_SIZE);
+        let buffer = Arc::new(Mutex::new(PipeRingBuffer::new(user, capacity)?));
+        let ino = NEXT_INO.fetch_add(1, Ordering::Relaxed);
         let read_end = Pipe {
             readable: true,
+            ino,
             buffer: buffer.clone(),
         };
         let write_end = Pipe {
             readable: false,
+            ino,
             buffer,
         };
-        (read_end, write_end)
+        Ok((read_end, write_end))
+    }
+
+    /// Makes another end for the other direction of the same pipe, e.g. for
+    /// a FIFO opened by another process.
+    pub fn other_end(&self) -> Pipe {
+        Pipe {
+            readable: !self.readable,
+            ino: self.ino,
+            buffer: self.buffer.clone(),
+        }
     }
 
     pub const fn readable(&self) -> bool {
@@ -101,9 +233,37 @@ impl Pipe {
         !self.readable
     }
 
+    pub const fn ino(&self) -> u64 {
+        self.ino
+    }
+
     pub fn write_end_close(&self) -> bool {
         Arc::strong_count(&self.buffer) == 1
     }
+
+    /// Returns the pipe `fd` refers to.
+    pub fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+        let f = super::fd_ops::get_file_like(fd)?;
+        f.into_any()
+            .downcast::<Self>()
+            .map_err(|_| LinuxError::EBADF)
+    }
+
+    /// The capacity of the pipe, in bytes.
+    pub fn capacity(&self) -> usize {
+        self.buffer.lock().capacity()
+    }
+
+    /// Sets the capacity of the pipe to at least `size` bytes, rounded up to
+    /// whole pages and clamped to [`PIPE_MAX_SIZE`], and returns the capacity
+    /// that was set.
+    pub fn set_capacity(&self, size: usize) -> LinuxResult<usize> {
+        let capacity = size
+            .clamp(PAGE_SIZE, PIPE_MAX_SIZE)
+            .next_multiple_of(PAGE_SIZE);
+        self.buffer.lock().resize(capacity)?;
+        Ok(capacity)
+    }
 }
 
 impl FileLike for Pipe {
@@ -121,6 +281,13 @@ impl FileLike for Pipe {
                     return Ok(read_size);
                 }
                 drop(ring_buffer);
+                if crate::imp::task::interrupted() {
+                    return if read_size > 0 {
+                        Ok(read_size)
+                    } else {
+                        Err(LinuxError::EINTR)
+                    };
+                }
                 // Data not ready, wait for write end
                 crate::sys_sched_yield(); // TODO: use synconize primitive
                 continue;
@@ -146,6 +313,13 @@ impl FileLike for Pipe {
             let loop_write = ring_buffer.available_write();
             if loop_write == 0 {
                 drop(ring_buffer);
+                if crate::imp::task::interrupted() {
+                    return if write_size > 0 {
+                        Ok(write_size)
+                    } else {
+                        Err(LinuxError::EINTR)
+                    };
+                }
                 // Buffer is full, wait for read end to consume
                 crate::sys_sched_yield(); // TODO: use synconize primitive
                 continue;
@@ -163,7 +337,7 @@ impl FileLike for Pipe {
     fn stat(&self) -> LinuxResult<ctypes::stat> {
         let st_mode = 0o10000 | 0o600u32; // S_IFIFO | rw-------
         Ok(ctypes::stat {
-            st_ino: 1,
+            st_ino: self.ino as _,
             st_nlink: 1,
             st_mode,
             st_uid: 1000,
@@ -188,19 +362,35 @@ impl FileLike for Pipe {
     fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
         Ok(())
     }
+
+    fn hung_up(&self) -> bool {
+        self.readable() && self.write_end_close()
+    }
+
+    /// Writing to a pipe that nobody can read from is an error.
+    fn has_error(&self) -> bool {
+        self.writable() && self.write_end_close()
+    }
 }
 
 /// Create a pipe
 ///
 /// Return 0 if succeed
 pub fn sys_pipe(fds: &mut [c_int]) -> c_int {
-    debug!("sys_pipe <= {:#x}", fds.as_ptr() as usize);
-    syscall_body!(sys_pipe, {
+    sys_pipe_as(fds, 0)
+}
+
+/// Create a pipe whose buffer is charged to `user`
+///
+/// Return 0 if succeed
+pub fn sys_pipe_as(fds: &mut [c_int], user: u32) -> c_int {
+    debug!("sys_pipe_as <= {:#x} {}", fds.as_ptr() as usize, user);
+    syscall_body!(sys_pipe_as, {
         if fds.len() != 2 {
             return Err(LinuxError::EFAULT);
         }
 
-        let (read_end, write_end) = Pipe::new();
+        let (read_end, write_end) = Pipe::new(user)?;
         let read_fd = add_file_like(Arc::new(read_end))?;
         let write_fd = add_file_like(Arc::new(write_end)).inspect_err(|_| {
             close_file_like(read_fd).ok();
@@ -53,16 +53,17 @@ pub struct Stdin {
 }
 
 impl Stdin {
+    // Returns `None` if there is nothing to read yet.
+    fn try_read(&self, buf: &mut [u8]) -> AxResult<Option<usize>> {
+        let read_len = self.inner.lock().read(buf)?;
+        Ok((buf.is_empty() || read_len > 0).then_some(read_len))
+    }
+
     // Block until at least one byte is read.
     fn read_blocked(&self, buf: &mut [u8]) -> AxResult<usize> {
-        let read_len = self.inner.lock().read(buf)?;
-        if buf.is_empty() || read_len > 0 {
-            return Ok(read_len);
-        }
         // try again until we get something
         loop {
-            let read_len = self.inner.lock().read(buf)?;
-            if read_len > 0 {
+            if let Some(read_len) = self.try_read(buf)? {
                 return Ok(read_len);
             }
             crate::sys_sched_yield();
@@ -105,7 +106,16 @@ pub fn stdout() -> Stdout {
 #[cfg(feature = "fd")]
 impl super::fd_ops::FileLike for Stdin {
     fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
-        Ok(self.read_blocked(buf)?)
+        // Like `read_blocked`, but a signal can interrupt the wait.
+        loop {
+            if let Some(read_len) = self.try_read(buf)? {
+                return Ok(read_len);
+            }
+            if crate::imp::task::interrupted() {
+                return Err(LinuxError::EINTR);
+            }
+            crate::sys_sched_yield();
+        }
     }
 
     fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
@@ -16,6 +16,27 @@ pub fn sys_sched_yield() -> c_int {
     0
 }
 
+/// Signal hooks of the kernel running on top of this crate.
+#[cfg(feature = "uspace")]
+#[crate_interface::def_interface]
+pub trait SignalIf {
+    /// Whether the current task has a signal to handle, which should
+    /// interrupt any blocking call it is in.
+    fn signal_pending() -> bool;
+}
+
+/// Whether the current blocking call should give up with `EINTR`.
+pub(crate) fn interrupted() -> bool {
+    #[cfg(feature = "uspace")]
+    {
+        crate_interface::call_interface!(SignalIf::signal_pending)
+    }
+    #[cfg(not(feature = "uspace"))]
+    {
+        false
+    }
+}
+
 /// Get current thread ID.
 pub fn sys_getpid() -> c_int {
     syscall_body!(sys_getpid,
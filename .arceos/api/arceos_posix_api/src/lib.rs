@@ -36,12 +36,15 @@ pub use imp::io::{sys_read, sys_write, sys_writev};
 pub use imp::path_link::{AT_FDCWD, FilePath, HARDLINK_MANAGER, handle_file_path};
 pub use imp::resources::{sys_getrlimit, sys_setrlimit};
 pub use imp::sys::sys_sysconf;
+#[cfg(feature = "uspace")]
+pub use imp::task::SignalIf;
 pub use imp::task::{sys_exit, sys_getpid, sys_sched_yield};
 pub use imp::time::{sys_clock_gettime, sys_get_time_of_day, sys_nanosleep};
 
 #[cfg(feature = "fd")]
 pub use imp::fd_ops::{
-    FD_TABLE, add_file_like, get_file_like, sys_close, sys_dup, sys_dup2, sys_fcntl,
+    FD_TABLE, FdTable, FileLike, add_file_like, get_file_like, sys_close, sys_dup, sys_dup2,
+    sys_fcntl,
 };
 #[cfg(feature = "fs")]
 pub use imp::fs::{
@@ -51,7 +54,9 @@ pub use imp::fs::{
 #[cfg(feature = "select")]
 pub use imp::io_mpx::sys_select;
 #[cfg(feature = "epoll")]
-pub use imp::io_mpx::{sys_epoll_create, sys_epoll_ctl, sys_epoll_wait};
+pub use imp::io_mpx::{
+    EpollInstance, poll_events, sys_epoll_create, sys_epoll_ctl, sys_epoll_wait,
+};
 #[cfg(feature = "net")]
 pub use imp::net::{
     sys_accept, sys_bind, sys_connect, sys_freeaddrinfo, sys_getaddrinfo, sys_getpeername,
@@ -59,10 +64,14 @@ pub use imp::net::{
     sys_socket,
 };
 #[cfg(feature = "pipe")]
-pub use imp::pipe::sys_pipe;
+pub use imp::pipe::{
+    Pipe, pipe_user_pages_limit, set_pipe_user_pages_limit, sys_pipe, sys_pipe_as,
+};
 #[cfg(feature = "multitask")]
 pub use imp::pthread::mutex::{
     sys_pthread_mutex_init, sys_pthread_mutex_lock, sys_pthread_mutex_unlock,
 };
 #[cfg(feature = "multitask")]
 pub use imp::pthread::{sys_pthread_create, sys_pthread_exit, sys_pthread_join, sys_pthread_self};
+#[cfg(feature = "fd")]
+pub use imp::stdio::{Stdin, Stdout, stdin, stdout};
@@ -3,6 +3,7 @@
 use axerrno::{AxError, AxResult, ax_err, ax_err_type};
 use axfs_vfs::{VfsError, VfsNodeRef};
 use axio::SeekFrom;
+use axsync::Mutex;
 use cap_access::{Cap, WithCap};
 use core::fmt;
 
@@ -20,6 +21,15 @@ pub type FileAttr = axfs_vfs::VfsNodeAttr;
 /// Alias of [`axfs_vfs::VfsNodePerm`].
 pub type FilePerm = axfs_vfs::VfsNodePerm;
 
+/// Held by appending writes from finding the end of the file to writing
+/// there, so that appends through different opened files never overwrite
+/// each other.
+///
+/// Some filesystems make a new node each time a file is opened, so the
+/// opened files of one file cannot be told apart from those of others, and
+/// appends to all files take this one lock.
+static APPEND_LOCK: Mutex<()> = Mutex::new(());
+
 /// An opened file object, with open permissions and a cursor.
 pub struct File {
     node: WithCap<VfsNodeRef>,
@@ -232,8 +242,10 @@ impl File {
     /// written.
     ///
     /// After the write, the cursor will be advanced by the number of bytes
-    /// written.
+    /// written. In append mode, it writes at the end of the file instead, in
+    /// one step with finding it.
     pub fn write(&mut self, buf: &[u8]) -> AxResult<usize> {
+        let _append_guard = self.is_append.then(|| APPEND_LOCK.lock());
         let offset = if self.is_append {
             self.get_attr()?.size()
         } else {
@@ -26,6 +26,8 @@
 extern crate log;
 extern crate alloc;
 
+#[cfg(feature = "devfs")]
+mod chardev;
 mod dev;
 mod fs;
 mod mounts;
@@ -7,11 +7,15 @@ use crate::fs;
 pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
     let null = fs::devfs::NullDev;
     let zero = fs::devfs::ZeroDev;
+    let full = crate::chardev::FullDev;
     let bar = fs::devfs::ZeroDev;
     let devfs = fs::devfs::DeviceFileSystem::new();
     let foo_dir = devfs.mkdir("foo");
+    // The slaves of pseudoterminals are opened by the kernel by path.
+    devfs.mkdir("pts");
     devfs.add("null", Arc::new(null));
     devfs.add("zero", Arc::new(zero));
+    devfs.add("full", Arc::new(full));
     foo_dir.add("bar", Arc::new(bar));
     Arc::new(devfs)
 }
@@ -34,11 +38,30 @@ pub(crate) fn procfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
     let file_somaxconn = proc_root.clone().lookup("./sys/net/core/somaxconn")?;
     file_somaxconn.write_at(0, b"4096\n")?;
 
-    // Create /proc/sys/vm/overcommit_memory
+    // Create placeholders for the tunables that starry opens itself
+    proc_root.create("sys/kernel", VfsNodeType::Dir)?;
+    proc_root.create("sys/kernel/hostname", VfsNodeType::File)?;
+    proc_root.create("sys/kernel/ostype", VfsNodeType::File)?;
+    proc_root.create("sys/kernel/pid_max", VfsNodeType::File)?;
+    proc_root.create("sys/fs", VfsNodeType::Dir)?;
+    proc_root.create("sys/fs/file-max", VfsNodeType::File)?;
+    proc_root.create("sys/fs/pipe-user-pages-hard", VfsNodeType::File)?;
     proc_root.create("sys/vm", VfsNodeType::Dir)?;
     proc_root.create("sys/vm/overcommit_memory", VfsNodeType::File)?;
-    let file_over = proc_root.clone().lookup("./sys/vm/overcommit_memory")?;
-    file_over.write_at(0, b"0\n")?;
+    proc_root.create("sys/vm/overcommit_ratio", VfsNodeType::File)?;
+
+    // Create placeholders for the socket tables that starry generates itself
+    proc_root.create("net", VfsNodeType::Dir)?;
+    proc_root.create("net/tcp", VfsNodeType::File)?;
+    proc_root.create("net/udp", VfsNodeType::File)?;
+
+    // Create a placeholder for the kernel log, which starry keeps itself
+    proc_root.create("kmsg", VfsNodeType::File)?;
+
+    // Create placeholders for the processor and memory information and counters
+    proc_root.create("cpuinfo", VfsNodeType::File)?;
+    proc_root.create("meminfo", VfsNodeType::File)?;
+    proc_root.create("stat", VfsNodeType::File)?;
 
     // Create /proc/self/stat
     proc_root.create("self", VfsNodeType::Dir)?;
@@ -4,7 +4,7 @@
 
 use alloc::{string::String, sync::Arc, vec::Vec};
 use axerrno::{AxError, AxResult, ax_err};
-use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps, VfsResult};
+use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps, VfsResult};
 use axns::{ResArc, def_resource};
 use axsync::Mutex;
 use lazyinit::LazyInit;
@@ -272,6 +272,23 @@ pub(crate) fn remove_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
     }
 }
 
+/// Whether the directory `node` has no entries other than `.` and `..`.
+fn dir_is_empty(node: &VfsNodeRef) -> AxResult<bool> {
+    let mut dirents = [const { VfsDirEntry::default() }; 8];
+    let mut start_idx = 0;
+    loop {
+        let n = node.read_dir(start_idx, &mut dirents)?;
+        if n == 0 {
+            return Ok(true);
+        }
+        let named = |entry: &VfsDirEntry| !matches!(entry.name_as_bytes(), b"." | b"..");
+        if dirents[..n].iter().any(named) {
+            return Ok(false);
+        }
+        start_idx += n;
+    }
+}
+
 pub(crate) fn remove_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
     if path.is_empty() {
         return ax_err!(NotFound);
@@ -296,6 +313,9 @@ pub(crate) fn remove_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
         ax_err!(NotADirectory)
     } else if !attr.perm().owner_writable() {
         ax_err!(PermissionDenied)
+    } else if !dir_is_empty(&node)? {
+        // Some filesystems would remove everything under it too.
+        ax_err!(DirectoryNotEmpty)
     } else {
         parent_node_of(dir, path).remove(path)
     }
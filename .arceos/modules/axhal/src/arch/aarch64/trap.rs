@@ -83,6 +83,13 @@ fn handle_data_abort(tf: &TrapFrame, iss: u64, is_user: bool) {
     }
     let vaddr = va!(FAR_EL1.get() as usize);
 
+    // Alignment fault (DFSC = 0b100001) from user space
+    #[cfg(feature = "uspace")]
+    if is_user && iss & 0b111111 == 0b100001 {
+        handle_user_exception(tf, crate::trap::UserException::Misaligned, vaddr.as_usize());
+        return;
+    }
+
     // Only handle Translation fault and Permission fault
     if !matches!(iss & 0b111100, 0b0100 | 0b1100) // IFSC or DFSC bits
         || !handle_trap!(PAGE_FAULT, vaddr, access_flags, is_user)
@@ -99,6 +106,16 @@ fn handle_data_abort(tf: &TrapFrame, iss: u64, is_user: bool) {
     }
 }
 
+#[cfg(feature = "uspace")]
+fn handle_user_exception(tf: &TrapFrame, kind: crate::trap::UserException, addr: usize) {
+    if !handle_trap!(USER_EXCEPTION, kind, va!(addr)) {
+        panic!(
+            "Unhandled user exception {:?} @ {:#x}, addr={:#x}:\n{:#x?}",
+            kind, tf.elr, addr, tf
+        );
+    }
+}
+
 #[unsafe(no_mangle)]
 fn handle_sync_exception(tf: &mut TrapFrame, source: TrapSource) {
     let esr = ESR_EL1.extract();
@@ -116,6 +133,30 @@ fn handle_sync_exception(tf: &mut TrapFrame, source: TrapSource) {
             debug!("BRK #{:#x} @ {:#x} ", iss, tf.elr);
             tf.elr += 4;
         }
+        #[cfg(feature = "uspace")]
+        Some(ESR_EL1::EC::Value::Unknown | ESR_EL1::EC::Value::IllegalExecutionState)
+            if source.is_from_user() =>
+        {
+            handle_user_exception(
+                tf,
+                crate::trap::UserException::IllegalInstruction,
+                tf.elr as usize,
+            )
+        }
+        #[cfg(feature = "uspace")]
+        Some(ESR_EL1::EC::Value::PCAlignmentFault | ESR_EL1::EC::Value::SPAlignmentFault)
+            if source.is_from_user() =>
+        {
+            handle_user_exception(
+                tf,
+                crate::trap::UserException::Misaligned,
+                FAR_EL1.get() as usize,
+            )
+        }
+        #[cfg(feature = "uspace")]
+        Some(ESR_EL1::EC::Value::TrappedFP64) if source.is_from_user() => {
+            handle_user_exception(tf, crate::trap::UserException::Arithmetic, tf.elr as usize)
+        }
         _ => {
             panic!(
                 "Unhandled synchronous exception @ {:#x}: ESR={:#x} (EC {:#08b}, ISS {:#x})",
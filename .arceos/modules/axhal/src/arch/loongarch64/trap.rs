@@ -33,6 +33,16 @@ fn handle_page_fault(tf: &TrapFrame, mut access_flags: MappingFlags, is_user: bo
     }
 }
 
+#[cfg(feature = "uspace")]
+fn handle_user_exception(tf: &TrapFrame, kind: crate::trap::UserException, addr: usize) {
+    if !handle_trap!(USER_EXCEPTION, kind, va!(addr)) {
+        panic!(
+            "Unhandled user exception {:?} @ {:#x}, addr={:#x}:\n{:#x?}",
+            kind, tf.era, addr, tf
+        );
+    }
+}
+
 #[unsafe(no_mangle)]
 fn loongarch64_trap_handler(tf: &mut TrapFrame, from_user: bool) {
     let estat = estat::read();
@@ -56,6 +66,19 @@ fn loongarch64_trap_handler(tf: &mut TrapFrame, from_user: bool) {
             handle_page_fault(tf, MappingFlags::EXECUTE, from_user);
         }
         Trap::Exception(Exception::Breakpoint) => handle_breakpoint(&mut tf.era),
+        #[cfg(feature = "uspace")]
+        Trap::Exception(Exception::InstructionNotExist)
+        | Trap::Exception(Exception::InstructionPrivilegeIllegal)
+            if from_user =>
+        {
+            handle_user_exception(tf, crate::trap::UserException::IllegalInstruction, tf.era)
+        }
+        #[cfg(feature = "uspace")]
+        Trap::Exception(Exception::AddressNotAligned) if from_user => handle_user_exception(
+            tf,
+            crate::trap::UserException::Misaligned,
+            badv::read().raw(),
+        ),
         Trap::Interrupt(_) => {
             let irq_num: usize = estat.is().trailing_zeros() as usize;
             handle_trap!(IRQ, irq_num);
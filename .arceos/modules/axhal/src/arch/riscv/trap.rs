@@ -33,6 +33,16 @@ fn handle_page_fault(tf: &TrapFrame, mut access_flags: MappingFlags, is_user: bo
     }
 }
 
+#[cfg(feature = "uspace")]
+fn handle_user_exception(tf: &TrapFrame, kind: crate::trap::UserException, addr: usize) {
+    if !handle_trap!(USER_EXCEPTION, kind, va!(addr)) {
+        panic!(
+            "Unhandled user exception {:?} @ {:#x}, addr={:#x}:\n{:#x?}",
+            kind, tf.sepc, addr, tf
+        );
+    }
+}
+
 #[unsafe(no_mangle)]
 fn riscv_trap_handler(tf: &mut TrapFrame, from_user: bool) {
     let scause = scause::read();
@@ -53,6 +63,20 @@ fn riscv_trap_handler(tf: &mut TrapFrame, from_user: bool) {
                 handle_page_fault(tf, MappingFlags::EXECUTE, from_user)
             }
             Trap::Exception(E::Breakpoint) => handle_breakpoint(&mut tf.sepc),
+            #[cfg(feature = "uspace")]
+            Trap::Exception(E::IllegalInstruction) if from_user => {
+                handle_user_exception(tf, crate::trap::UserException::IllegalInstruction, tf.sepc)
+            }
+            #[cfg(feature = "uspace")]
+            Trap::Exception(E::InstructionMisaligned | E::LoadMisaligned | E::StoreMisaligned)
+                if from_user =>
+            {
+                handle_user_exception(tf, crate::trap::UserException::Misaligned, stval::read())
+            }
+            #[cfg(feature = "uspace")]
+            Trap::Exception(E::InstructionFault | E::LoadFault | E::StoreFault) if from_user => {
+                handle_user_exception(tf, crate::trap::UserException::AccessFault, stval::read())
+            }
             Trap::Interrupt(_) => {
                 handle_trap!(IRQ, scause.bits());
             }
@@ -29,11 +29,43 @@ fn handle_page_fault(tf: &TrapFrame) {
     }
 }
 
+#[cfg(feature = "uspace")]
+fn handle_user_exception(tf: &TrapFrame, kind: crate::trap::UserException) {
+    // x86 does not report the data address of these exceptions, so use the
+    // address of the faulting instruction.
+    if !handle_trap!(USER_EXCEPTION, kind, va!(tf.rip as usize)) {
+        panic!(
+            "Unhandled user exception {:?} ({}, error_code={:#x}) @ {:#x}:\n{:#x?}",
+            kind,
+            vec_to_str(tf.vector),
+            tf.error_code,
+            tf.rip,
+            tf
+        );
+    }
+}
+
 #[unsafe(no_mangle)]
 fn x86_trap_handler(tf: &mut TrapFrame) {
     match tf.vector as u8 {
         PAGE_FAULT_VECTOR => handle_page_fault(tf),
         BREAKPOINT_VECTOR => debug!("#BP @ {:#x} ", tf.rip),
+        #[cfg(feature = "uspace")]
+        DIVIDE_ERROR_VECTOR | X87_FPU_VECTOR | SIMD_FLOATING_POINT_VECTOR if tf.is_user() => {
+            handle_user_exception(tf, crate::trap::UserException::Arithmetic)
+        }
+        #[cfg(feature = "uspace")]
+        INVALID_OPCODE_VECTOR if tf.is_user() => {
+            handle_user_exception(tf, crate::trap::UserException::IllegalInstruction)
+        }
+        #[cfg(feature = "uspace")]
+        ALIGNMENT_CHECK_VECTOR if tf.is_user() => {
+            handle_user_exception(tf, crate::trap::UserException::Misaligned)
+        }
+        #[cfg(feature = "uspace")]
+        GENERAL_PROTECTION_FAULT_VECTOR if tf.is_user() => {
+            handle_user_exception(tf, crate::trap::UserException::AccessFault)
+        }
         GENERAL_PROTECTION_FAULT_VECTOR => {
             panic!(
                 "#GP @ {:#x}, error_code={:#x}:\n{:#x?}",
@@ -21,6 +21,29 @@ pub static PAGE_FAULT: [fn(VirtAddr, MappingFlags, bool) -> bool];
 #[def_trap_handler]
 pub static SYSCALL: [fn(&mut TrapFrame, usize) -> isize];
 
+/// Kinds of synchronous exceptions, other than page faults, that can be
+/// raised by user code.
+#[cfg(feature = "uspace")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserException {
+    /// Illegal or privileged instruction.
+    IllegalInstruction,
+    /// Misaligned memory access.
+    Misaligned,
+    /// Arithmetic error, e.g. integer divide by zero or a floating-point trap.
+    Arithmetic,
+    /// Memory access rejected by the hardware outside of the paging system.
+    AccessFault,
+}
+
+/// A slice of handlers for exceptions raised in user mode.
+///
+/// The handler receives the exception kind and the faulting address (the data
+/// address if the hardware reports one, otherwise the instruction address).
+#[cfg(feature = "uspace")]
+#[def_trap_handler]
+pub static USER_EXCEPTION: [fn(UserException, VirtAddr) -> bool];
+
 /// A slice of callbacks to be invoked after a trap.
 #[linkme::distributed_slice]
 pub static POST_TRAP: [fn(&mut TrapFrame, bool)];
@@ -53,12 +53,12 @@ extern crate log;
 use core::fmt::{self, Write};
 use core::str::FromStr;
 
-use log::{Level, LevelFilter, Log, Metadata, Record};
+use log::{LevelFilter, Log, Metadata};
 
 #[cfg(not(feature = "std"))]
 use crate_interface::call_interface;
 
-pub use log::{debug, error, info, trace, warn};
+pub use log::{Level, Record, debug, error, info, trace, warn};
 
 /// Prints to the console.
 ///
@@ -152,6 +152,10 @@ impl Log for Logger {
         if !self.enabled(record.metadata()) {
             return;
         }
+        let sink = *RECORD_SINK.lock();
+        if let Some(sink) = sink {
+            sink(record);
+        }
 
         let level = record.level();
         let line = record.line().unwrap_or(0);
@@ -224,6 +228,16 @@ impl Log for Logger {
     fn flush(&self) {}
 }
 
+/// The function that also gets each record logged, if set.
+static RECORD_SINK: kspin::SpinNoIrq<Option<fn(&Record)>> = kspin::SpinNoIrq::new(None);
+
+/// Makes `sink` get each record that is logged, as well as the console.
+///
+/// `sink` must not log anything itself.
+pub fn set_record_sink(sink: fn(&Record)) {
+    *RECORD_SINK.lock() = Some(sink);
+}
+
 /// Prints the formatted string to the console.
 pub fn print_fmt(args: fmt::Arguments) -> fmt::Result {
     use kspin::SpinNoIrq; // TODO: more efficient
@@ -1,3 +1,4 @@
+use alloc::vec::Vec;
 use core::fmt;
 
 use axerrno::{AxError, AxResult, ax_err};
@@ -8,14 +9,17 @@ use memory_addr::{
 };
 use memory_set::{MemoryArea, MemorySet};
 
-use crate::backend::Backend;
-use crate::mapping_err_to_ax_err;
+use crate::backend::{Backend, HUGE_PAGE_SIZE};
+use crate::{map_err_to_ax_err, mapping_err_to_ax_err};
 
 /// The virtual memory address space.
 pub struct AddrSpace {
     va_range: VirtAddrRange,
     areas: MemorySet<Backend>,
     pt: PageTable,
+    /// The ranges where lazy mappings are backed with huge pages where they
+    /// can be, in ascending order and with none adjacent.
+    huge_advised: Vec<VirtAddrRange>,
 }
 
 impl AddrSpace {
@@ -44,6 +48,17 @@ impl AddrSpace {
         self.pt.root_paddr()
     }
 
+    /// Returns the address ranges and flags of the mapped areas, in ascending
+    /// order of address.
+    pub fn areas(&self) -> impl Iterator<Item = (VirtAddrRange, MappingFlags)> + '_ {
+        self.areas.iter().map(|area| {
+            (
+                VirtAddrRange::from_start_size(area.start(), area.size()),
+                area.flags(),
+            )
+        })
+    }
+
     /// Checks if the address space contains the given address range.
     pub fn contains_range(&self, start: VirtAddr, size: usize) -> bool {
         self.va_range
@@ -56,6 +71,7 @@ impl AddrSpace {
             va_range: VirtAddrRange::from_start_size(base, size),
             areas: MemorySet::new(),
             pt: PageTable::try_new().map_err(|_| AxError::NoMemory)?,
+            huge_advised: Vec::new(),
         })
     }
 
@@ -129,11 +145,11 @@ impl AddrSpace {
             return ax_err!(InvalidInput, "address not aligned");
         }
 
-        let offset = start_vaddr.as_usize() - start_paddr.as_usize();
+        let offset = start_vaddr.as_usize().wrapping_sub(start_paddr.as_usize());
         let area = MemoryArea::new(start_vaddr, size, flags, Backend::new_linear(offset));
         self.areas
             .map(area, &mut self.pt, false)
-            .map_err(mapping_err_to_ax_err)?;
+            .map_err(map_err_to_ax_err)?;
         Ok(())
     }
 
@@ -157,7 +173,24 @@ impl AddrSpace {
         let area = MemoryArea::new(start, size, flags, Backend::new_alloc(populate));
         self.areas
             .map(area, &mut self.pt, false)
-            .map_err(mapping_err_to_ax_err)?;
+            .map_err(map_err_to_ax_err)?;
+        Ok(())
+    }
+
+    /// Add a new shared mapping, whose frames the address spaces cloned from
+    /// this one map too.
+    ///
+    /// See [`Backend`] for more details about the mapping backends.
+    ///
+    /// Returns an error if the address range is out of the address space or not
+    /// aligned.
+    pub fn map_shared(&mut self, start: VirtAddr, size: usize, flags: MappingFlags) -> AxResult {
+        self.validate_region(start, size)?;
+
+        let area = MemoryArea::new(start, size, flags, Backend::new_shared(start));
+        self.areas
+            .map(area, &mut self.pt, false)
+            .map_err(map_err_to_ax_err)?;
         Ok(())
     }
 
@@ -169,20 +202,21 @@ impl AddrSpace {
 
         while let Some(area) = self.areas.find(start) {
             let backend = area.backend();
-            if let Backend::Alloc { populate } = backend {
-                if !*populate {
-                    for addr in PageIter4K::new(start, area.end().min(end)).unwrap() {
-                        match self.pt.query(addr) {
-                            Ok(_) => {}
-                            // If the page is not mapped, try map it.
-                            Err(PagingError::NotMapped) => {
-                                if !backend.handle_page_fault(addr, area.flags(), &mut self.pt) {
-                                    return Err(AxError::NoMemory);
-                                }
+            if matches!(
+                backend,
+                Backend::Alloc { populate: false } | Backend::Shared { .. }
+            ) {
+                for addr in PageIter4K::new(start, area.end().min(end)).unwrap() {
+                    match self.pt.query(addr) {
+                        Ok(_) => {}
+                        // If the page is not mapped, try map it.
+                        Err(PagingError::NotMapped) => {
+                            if !backend.handle_page_fault(addr, area.flags(), &mut self.pt) {
+                                return Err(AxError::NoMemory);
                             }
-                            Err(_) => return Err(AxError::BadAddress),
-                        };
-                    }
+                        }
+                        Err(_) => return Err(AxError::BadAddress),
+                    };
                 }
             }
             start = area.end();
@@ -210,9 +244,75 @@ impl AddrSpace {
         self.areas
             .unmap(start, size, &mut self.pt)
             .map_err(mapping_err_to_ax_err)?;
+        self.set_huge_advice(VirtAddrRange::from_start_size(start, size), false);
         Ok(())
     }
 
+    /// Gives back the frames of the lazy allocation mappings in the given
+    /// range, so that their pages are zero-filled again on the next fault. The
+    /// other mappings are left as they are.
+    pub fn discard_lazy(&mut self, start: VirtAddr, size: usize) -> AxResult {
+        self.validate_region(start, size)?;
+        let end = start + size;
+        for area in self.areas.iter() {
+            if !matches!(area.backend(), Backend::Alloc { populate: false }) {
+                continue;
+            }
+            let (start, end) = (area.start().max(start), area.end().min(end));
+            if start < end && !Backend::unmap_alloc(start, end - start, &mut self.pt, false) {
+                return ax_err!(BadState);
+            }
+        }
+        Ok(())
+    }
+
+    /// Advises backing the lazy mappings in the given range with huge pages,
+    /// or not if `huge` is false.
+    ///
+    /// A huge page is only used for a fault in a part of the range aligned to
+    /// its size that is all in one mapping and not yet touched. Otherwise, or
+    /// if there is no memory for one, base pages are used.
+    pub fn advise_huge(&mut self, start: VirtAddr, size: usize, huge: bool) -> AxResult {
+        self.validate_region(start, size)?;
+        self.set_huge_advice(VirtAddrRange::from_start_size(start, size), huge);
+        Ok(())
+    }
+
+    fn set_huge_advice(&mut self, range: VirtAddrRange, huge: bool) {
+        let mut ranges = Vec::new();
+        for advised in self.huge_advised.drain(..) {
+            if advised.start < range.start {
+                ranges.push(VirtAddrRange::new(
+                    advised.start,
+                    advised.end.min(range.start),
+                ));
+            }
+            if advised.end > range.end {
+                ranges.push(VirtAddrRange::new(
+                    advised.start.max(range.end),
+                    advised.end,
+                ));
+            }
+        }
+        if huge {
+            ranges.push(range);
+        }
+        ranges.sort_by_key(|advised| advised.start);
+        for advised in ranges {
+            match self.huge_advised.last_mut() {
+                Some(last) if last.end == advised.start => last.end = advised.end,
+                _ => self.huge_advised.push(advised),
+            }
+        }
+    }
+
+    /// Whether the lazy mappings in `range` are to be backed with huge pages.
+    fn is_huge_advised(&self, range: VirtAddrRange) -> bool {
+        self.huge_advised
+            .iter()
+            .any(|advised| advised.contains_range(range))
+    }
+
     /// To remove user area mappings from address space.
     pub fn unmap_user_areas(&mut self) -> AxResult {
         for area in self.areas.iter() {
@@ -314,8 +414,15 @@ impl AddrSpace {
     /// Returns an error if the address range is out of the address space or not
     /// aligned.
     pub fn protect(&mut self, start: VirtAddr, size: usize, flags: MappingFlags) -> AxResult {
-        // Populate the area first, which also checks the address range for us.
-        self.populate_area(start, size)?;
+        self.validate_region(start, size)?;
+        // Lazy mappings are left unpopulated, as the pages not yet allocated
+        // take the new flags when they are.
+        if !self.check_region_access(
+            VirtAddrRange::from_start_size(start, size),
+            MappingFlags::empty(),
+        ) {
+            return ax_err!(NoMemory);
+        }
 
         self.areas
             .protect(start, size, |_| Some(flags), &mut self.pt)
@@ -327,6 +434,7 @@ impl AddrSpace {
     /// Removes all mappings in the address space.
     pub fn clear(&mut self) {
         self.areas.clear(&mut self.pt).unwrap();
+        self.huge_advised.clear();
     }
 
     /// Checks whether an access to the specified memory region is valid.
@@ -373,6 +481,16 @@ impl AddrSpace {
         if let Some(area) = self.areas.find(vaddr) {
             let orig_flags = area.flags();
             if orig_flags.contains(access_flags) {
+                let block = vaddr.align_down(HUGE_PAGE_SIZE);
+                let block_range = VirtAddrRange::from_start_size(block, HUGE_PAGE_SIZE);
+                if matches!(area.backend(), Backend::Alloc { populate: false })
+                    && area.start() <= block
+                    && block_range.end <= area.end()
+                    && self.is_huge_advised(block_range)
+                    && Backend::handle_huge_page_fault_alloc(block, orig_flags, &mut self.pt)
+                {
+                    return true;
+                }
                 return area
                     .backend()
                     .handle_page_fault(vaddr, orig_flags, &mut self.pt);
@@ -384,6 +502,7 @@ impl AddrSpace {
     /// Clone a [`AddrSpace`] by re-mapping all [`MemoryArea`]s in a new page table and copying data in user space.
     pub fn clone_or_err(&mut self) -> AxResult<Self> {
         let mut new_aspace = Self::new_empty(self.base(), self.size())?;
+        new_aspace.huge_advised = self.huge_advised.clone();
 
         for area in self.areas.iter() {
             let backend = area.backend();
@@ -393,9 +512,11 @@ impl AddrSpace {
             new_aspace
                 .areas
                 .map(new_area, &mut new_aspace.pt, false)
-                .map_err(mapping_err_to_ax_err)?;
+                .map_err(map_err_to_ax_err)?;
 
-            if matches!(backend, Backend::Linear { .. }) {
+            // The frames of a shared mapping are not copied but mapped by
+            // the clone as it faults on them.
+            if matches!(backend, Backend::Linear { .. } | Backend::Shared { .. }) {
                 continue;
             }
             // Copy data from old memory area to new memory area.
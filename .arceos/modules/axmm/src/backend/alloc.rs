@@ -1,11 +1,18 @@
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 use axalloc::global_allocator;
 use axhal::mem::{phys_to_virt, virt_to_phys};
-use axhal::paging::{MappingFlags, PageSize, PageTable};
-use memory_addr::{PAGE_SIZE_4K, PageIter4K, PhysAddr, VirtAddr};
+use axhal::paging::{MappingFlags, PageSize, PageTable, PagingError};
+use kspin::SpinNoIrq;
+use memory_addr::{MemoryAddr, PAGE_SIZE_4K, PageIter4K, PhysAddr, VirtAddr};
+
+use super::{Backend, SharedPages};
 
-use super::Backend;
+/// The size of the huge pages that lazy mappings may be backed with.
+pub(crate) const HUGE_PAGE_SIZE: usize = PageSize::Size2M as usize;
 
-fn alloc_frame(zeroed: bool) -> Option<PhysAddr> {
+pub(super) fn alloc_frame(zeroed: bool) -> Option<PhysAddr> {
     let vaddr = VirtAddr::from(global_allocator().alloc_pages(1, PAGE_SIZE_4K).ok()?);
     if zeroed {
         unsafe { core::ptr::write_bytes(vaddr.as_mut_ptr(), 0, PAGE_SIZE_4K) };
@@ -14,9 +21,141 @@ fn alloc_frame(zeroed: bool) -> Option<PhysAddr> {
     Some(paddr)
 }
 
-fn dealloc_frame(frame: PhysAddr) {
+pub(super) fn dealloc_frame(frame: PhysAddr) {
+    free_pages(frame, 1);
+}
+
+/// The number of [`HeldFrees`] alive, only changed with `HELD` locked, so
+/// that freeing need not lock it when there are none.
+static HOLDS: AtomicUsize = AtomicUsize::new(0);
+/// The holds alive, by the root of the page table they are on.
+static HELD: SpinNoIrq<BTreeMap<PhysAddr, Held>> = SpinNoIrq::new(BTreeMap::new());
+
+/// What is held back from being freed for a page table.
+#[derive(Default)]
+struct Held {
+    /// The number of holds on the page table.
+    holds: usize,
+    /// The frames unmapped from it, by start and number of pages.
+    frames: Vec<(PhysAddr, usize)>,
+    /// The shared mappings it unmapped pages of, which free their frames if
+    /// these were the last references.
+    shared: Vec<Arc<SharedPages>>,
+}
+
+/// A hold on freeing the frames unmapped from a page table: those unmapped
+/// while it is alive are only freed once it, and any other taken on the
+/// same table meanwhile, is dropped. The frees of other page tables go on.
+///
+/// It is taken while unmapping pages that the TLBs of other CPUs may still
+/// hold, and dropped once they have been flushed, so that the frames are not
+/// reused while still reachable.
+pub struct HeldFrees {
+    root: PhysAddr,
+}
+
+/// Takes a hold on freeing the frames unmapped from the page table at `root`.
+pub fn hold_frees(root: PhysAddr) -> HeldFrees {
+    let mut held = HELD.lock();
+    held.entry(root).or_default().holds += 1;
+    HOLDS.fetch_add(1, Ordering::AcqRel);
+    HeldFrees { root }
+}
+
+impl Drop for HeldFrees {
+    fn drop(&mut self) {
+        let released = {
+            let mut held = HELD.lock();
+            HOLDS.fetch_sub(1, Ordering::AcqRel);
+            let entry = held.get_mut(&self.root).unwrap();
+            entry.holds -= 1;
+            if entry.holds != 0 {
+                return;
+            }
+            held.remove(&self.root).unwrap()
+        };
+        for (frame, num_pages) in released.frames {
+            free_pages(frame, num_pages);
+        }
+        // Dropped outside the lock, as the last one frees its frames.
+        drop(released.shared);
+    }
+}
+
+/// Frees the frames of `num_pages` pages that were mapped in `pt`, or holds
+/// them back if there is a hold on it.
+fn free_unmapped(frame: PhysAddr, num_pages: usize, pt: &PageTable) {
+    if HOLDS.load(Ordering::Acquire) > 0 {
+        if let Some(held) = HELD.lock().get_mut(&pt.root_paddr()) {
+            held.frames.push((frame, num_pages));
+            return;
+        }
+    }
+    free_pages(frame, num_pages);
+}
+
+/// Keeps `pages`, some of which were just unmapped from `pt`, from being
+/// dropped before any hold on `pt` is.
+pub(super) fn hold_shared(pages: &Arc<SharedPages>, pt: &PageTable) {
+    if HOLDS.load(Ordering::Acquire) > 0 {
+        if let Some(held) = HELD.lock().get_mut(&pt.root_paddr()) {
+            held.shared.push(pages.clone());
+        }
+    }
+}
+
+fn free_pages(frame: PhysAddr, num_pages: usize) {
     let vaddr = phys_to_virt(frame);
-    global_allocator().dealloc_pages(vaddr.as_usize(), 1);
+    global_allocator().dealloc_pages(vaddr.as_usize(), num_pages);
+}
+
+/// Allocates a zeroed frame for a huge page, made of contiguous base ones.
+fn alloc_huge_frame() -> Option<PhysAddr> {
+    let vaddr = VirtAddr::from(
+        global_allocator()
+            .alloc_pages(HUGE_PAGE_SIZE / PAGE_SIZE_4K, HUGE_PAGE_SIZE)
+            .ok()?,
+    );
+    unsafe { core::ptr::write_bytes(vaddr.as_mut_ptr(), 0, HUGE_PAGE_SIZE) };
+    Some(virt_to_phys(vaddr))
+}
+
+fn dealloc_huge_frame(frame: PhysAddr) {
+    free_pages(frame, HUGE_PAGE_SIZE / PAGE_SIZE_4K);
+}
+
+/// The start of the huge page that `addr` is in, if it is mapped by one.
+fn huge_page_at(addr: VirtAddr, pt: &PageTable) -> Option<VirtAddr> {
+    match pt.query(addr) {
+        Ok((_, _, PageSize::Size2M)) => Some(addr.align_down(HUGE_PAGE_SIZE)),
+        _ => None,
+    }
+}
+
+/// Breaks the huge page at `block` into base pages mapping the same frames,
+/// so that only part of it can be unmapped or protected.
+fn split_huge_page(block: VirtAddr, pt: &mut PageTable) -> bool {
+    let Ok((frame, flags, _)) = pt.query(block) else {
+        return false;
+    };
+    match pt.unmap(block) {
+        Ok((_, _, tlb)) => tlb.flush(),
+        Err(_) => return false,
+    }
+    for offset in (0..HUGE_PAGE_SIZE).step_by(PAGE_SIZE_4K) {
+        if pt
+            .map(block + offset, frame + offset, PageSize::Size4K, flags)
+            .is_err()
+        {
+            // Out of memory for the page table. The frames that could not be
+            // mapped again are lost, and given back.
+            for offset in (offset..HUGE_PAGE_SIZE).step_by(PAGE_SIZE_4K) {
+                free_unmapped(frame + offset, 1, pt);
+            }
+            return false;
+        }
+    }
+    true
 }
 
 impl Backend {
@@ -42,12 +181,23 @@ impl Backend {
         if populate {
             // allocate all possible physical frames for populated mapping.
             for addr in PageIter4K::new(start, start + size).unwrap() {
-                if let Some(frame) = alloc_frame(true) {
-                    if let Ok(tlb) = pt.map(addr, frame, PageSize::Size4K, flags) {
-                        tlb.ignore(); // TLB flush on map is unnecessary, as there are no outdated mappings.
-                    } else {
-                        return false;
+                let mapped = alloc_frame(true).is_some_and(|frame| {
+                    match pt.map(addr, frame, PageSize::Size4K, flags) {
+                        Ok(tlb) => {
+                            tlb.ignore(); // TLB flush on map is unnecessary, as there are no outdated mappings.
+                            true
+                        }
+                        Err(_) => {
+                            dealloc_frame(frame);
+                            false
+                        }
                     }
+                });
+                if !mapped {
+                    // Out of memory. The area is not added, so the frames
+                    // mapped so far are given back.
+                    Self::unmap_alloc(start, addr - start, pt, true);
+                    return false;
                 }
             }
         } else {
@@ -63,7 +213,25 @@ impl Backend {
         _populate: bool,
     ) -> bool {
         debug!("unmap_alloc: [{:#x}, {:#x})", start, start + size);
-        for addr in PageIter4K::new(start, start + size).unwrap() {
+        let end = start + size;
+        let mut addr = start;
+        while addr < end {
+            if let Some(block) = huge_page_at(addr, pt) {
+                if block >= start && block + HUGE_PAGE_SIZE <= end {
+                    match pt.unmap(block) {
+                        Ok((frame, _, tlb)) => {
+                            tlb.flush();
+                            free_unmapped(frame, HUGE_PAGE_SIZE / PAGE_SIZE_4K, pt);
+                        }
+                        Err(_) => return false,
+                    }
+                    addr = block + HUGE_PAGE_SIZE;
+                    continue;
+                }
+                if !split_huge_page(block, pt) {
+                    return false;
+                }
+            }
             if let Ok((frame, page_size, tlb)) = pt.unmap(addr) {
                 // Deallocate the physical frame if there is a mapping in the
                 // page table.
@@ -71,14 +239,79 @@ impl Backend {
                     return false;
                 }
                 tlb.flush();
-                dealloc_frame(frame);
+                free_unmapped(frame, 1, pt);
             } else {
                 // Deallocation is needn't if the page is not mapped.
             }
+            addr += PAGE_SIZE_4K;
         }
         true
     }
 
+    /// Changes the flags of the pages of a lazy mapping that have been
+    /// allocated. The others are mapped with the flags of the area when they
+    /// are faulted in.
+    pub(crate) fn protect_alloc(
+        start: VirtAddr,
+        size: usize,
+        flags: MappingFlags,
+        pt: &mut PageTable,
+    ) -> bool {
+        debug!(
+            "protect_alloc: [{:#x}, {:#x}) {:?}",
+            start,
+            start + size,
+            flags
+        );
+        let end = start + size;
+        let mut addr = start;
+        while addr < end {
+            if let Some(block) = huge_page_at(addr, pt) {
+                if block >= start && block + HUGE_PAGE_SIZE <= end {
+                    match pt.protect(block, flags) {
+                        Ok((_, tlb)) => tlb.flush(),
+                        Err(_) => return false,
+                    }
+                    addr = block + HUGE_PAGE_SIZE;
+                    continue;
+                }
+                if !split_huge_page(block, pt) {
+                    return false;
+                }
+            }
+            match pt.protect(addr, flags) {
+                Ok((_, tlb)) => tlb.flush(),
+                Err(PagingError::NotMapped) => {}
+                Err(_) => return false,
+            }
+            addr += PAGE_SIZE_4K;
+        }
+        true
+    }
+
+    /// Backs the whole of `block`, a huge page sized part of a lazy mapping,
+    /// with a huge page. It fails if there is no memory for one, or if part of
+    /// the block has been mapped already.
+    pub(crate) fn handle_huge_page_fault_alloc(
+        block: VirtAddr,
+        orig_flags: MappingFlags,
+        pt: &mut PageTable,
+    ) -> bool {
+        let Some(frame) = alloc_huge_frame() else {
+            return false;
+        };
+        match pt.map(block, frame, PageSize::Size2M, orig_flags) {
+            Ok(tlb) => {
+                tlb.flush();
+                true
+            }
+            Err(_) => {
+                dealloc_huge_frame(frame);
+                false
+            }
+        }
+    }
+
     pub(crate) fn handle_page_fault_alloc(
         vaddr: VirtAddr,
         orig_flags: MappingFlags,
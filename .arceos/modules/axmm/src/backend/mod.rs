@@ -1,20 +1,29 @@
 //! Memory mapping backends.
 
+use alloc::sync::Arc;
+
 use axhal::paging::{MappingFlags, PageTable};
 use memory_addr::VirtAddr;
 use memory_set::MappingBackend;
 
 mod alloc;
 mod linear;
+mod shared;
+
+pub(crate) use self::alloc::HUGE_PAGE_SIZE;
+pub use self::alloc::{HeldFrees, hold_frees};
+pub use self::shared::SharedPages;
 
 /// A unified enum type for different memory mapping backends.
 ///
-/// Currently, two backends are implemented:
+/// Currently, three backends are implemented:
 ///
 /// - **Linear**: used for linear mappings. The target physical frames are
 ///   contiguous and their addresses should be known when creating the mapping.
 /// - **Allocation**: used in general, or for lazy mappings. The target physical
 ///   frames are obtained from the global allocator.
+/// - **Shared**: used for anonymous mappings shared with the address spaces
+///   cloned from the one they are made in.
 #[derive(Clone)]
 pub enum Backend {
     /// Linear mapping backend.
@@ -36,6 +45,15 @@ pub enum Backend {
         /// Whether to populate the physical frames when creating the mapping.
         populate: bool,
     },
+    /// Shared mapping backend.
+    ///
+    /// The physical frames are allocated on demand, like those of a lazy
+    /// allocation mapping, but are kept in `pages`, which clones of the
+    /// mapping share so that they all map the same frames.
+    Shared {
+        /// The frames of the mapping.
+        pages: Arc<SharedPages>,
+    },
 }
 
 impl MappingBackend for Backend {
@@ -46,6 +64,7 @@ impl MappingBackend for Backend {
         match *self {
             Self::Linear { pa_va_offset } => Self::map_linear(start, size, flags, pt, pa_va_offset),
             Self::Alloc { populate } => Self::map_alloc(start, size, flags, pt, populate),
+            Self::Shared { .. } => Self::map_shared(start, size, flags),
         }
     }
 
@@ -53,6 +72,7 @@ impl MappingBackend for Backend {
         match *self {
             Self::Linear { pa_va_offset } => Self::unmap_linear(start, size, pt, pa_va_offset),
             Self::Alloc { populate } => Self::unmap_alloc(start, size, pt, populate),
+            Self::Shared { ref pages } => Self::unmap_shared(start, size, pt, pages),
         }
     }
 
@@ -63,10 +83,15 @@ impl MappingBackend for Backend {
         new_flags: Self::Flags,
         page_table: &mut Self::PageTable,
     ) -> bool {
-        page_table
-            .protect_region(start, size, new_flags, true)
-            .map(|tlb| tlb.ignore())
-            .is_ok()
+        match *self {
+            Self::Alloc { populate: false } | Self::Shared { .. } => {
+                Self::protect_alloc(start, size, new_flags, page_table)
+            }
+            _ => page_table
+                .protect_region(start, size, new_flags, true)
+                .map(|tlb| tlb.ignore())
+                .is_ok(),
+        }
     }
 }
 
@@ -77,10 +102,13 @@ impl Backend {
         orig_flags: MappingFlags,
         page_table: &mut PageTable,
     ) -> bool {
-        match *self {
+        match self {
             Self::Linear { .. } => false, // Linear mappings should not trigger page faults.
             Self::Alloc { populate } => {
-                Self::handle_page_fault_alloc(vaddr, orig_flags, page_table, populate)
+                Self::handle_page_fault_alloc(vaddr, orig_flags, page_table, *populate)
+            }
+            Self::Shared { pages } => {
+                Self::handle_page_fault_shared(vaddr, orig_flags, page_table, pages)
             }
         }
     }
@@ -0,0 +1,97 @@
+use alloc::{collections::BTreeMap, sync::Arc};
+
+use axhal::paging::{MappingFlags, PageSize, PageTable};
+use kspin::SpinNoIrq;
+use memory_addr::{MemoryAddr, PAGE_SIZE_4K, PageIter4K, PhysAddr, VirtAddr};
+
+use super::Backend;
+use super::alloc::{alloc_frame, dealloc_frame, hold_shared};
+
+/// The frames of a shared mapping, allocated as its pages are first touched
+/// through any of the address spaces mapping it, and freed along with it
+/// once none of them do.
+pub struct SharedPages {
+    /// Where the mapping started when it was made, which the pages are
+    /// numbered from.
+    base: VirtAddr,
+    /// The frames allocated so far, by page number.
+    frames: SpinNoIrq<BTreeMap<usize, PhysAddr>>,
+}
+
+impl SharedPages {
+    /// The frame for the page at `vaddr`, allocated if it has none yet.
+    fn frame(&self, vaddr: VirtAddr) -> Option<PhysAddr> {
+        let index = (vaddr.align_down_4k() - self.base) / PAGE_SIZE_4K;
+        let mut frames = self.frames.lock();
+        if let Some(&frame) = frames.get(&index) {
+            return Some(frame);
+        }
+        let frame = alloc_frame(true)?;
+        frames.insert(index, frame);
+        Some(frame)
+    }
+}
+
+impl Drop for SharedPages {
+    fn drop(&mut self) {
+        for &frame in self.frames.lock().values() {
+            dealloc_frame(frame);
+        }
+    }
+}
+
+impl Backend {
+    /// Creates a new shared mapping backend for a mapping starting at `base`.
+    pub fn new_shared(base: VirtAddr) -> Self {
+        Self::Shared {
+            pages: Arc::new(SharedPages {
+                base,
+                frames: SpinNoIrq::new(BTreeMap::new()),
+            }),
+        }
+    }
+
+    pub(crate) fn map_shared(start: VirtAddr, size: usize, flags: MappingFlags) -> bool {
+        debug!(
+            "map_shared: [{:#x}, {:#x}) {:?}",
+            start,
+            start + size,
+            flags
+        );
+        // Pages are mapped on demand later in `handle_page_fault_shared`.
+        true
+    }
+
+    /// Unmaps the pages without freeing their frames, which the other address
+    /// spaces may still map. Any hold on `pt` keeps them until it is dropped.
+    pub(crate) fn unmap_shared(
+        start: VirtAddr,
+        size: usize,
+        pt: &mut PageTable,
+        pages: &Arc<SharedPages>,
+    ) -> bool {
+        debug!("unmap_shared: [{:#x}, {:#x})", start, start + size);
+        for addr in PageIter4K::new(start, start + size).unwrap() {
+            if let Ok((_, _, tlb)) = pt.unmap(addr) {
+                tlb.flush();
+            }
+        }
+        hold_shared(pages, pt);
+        true
+    }
+
+    pub(crate) fn handle_page_fault_shared(
+        vaddr: VirtAddr,
+        orig_flags: MappingFlags,
+        pt: &mut PageTable,
+        pages: &SharedPages,
+    ) -> bool {
+        let Some(frame) = pages.frame(vaddr) else {
+            return false;
+        };
+        // A frame that cannot be mapped is still kept until the mapping goes.
+        pt.map(vaddr, frame, PageSize::Size4K, orig_flags)
+            .map(|tlb| tlb.flush())
+            .is_ok()
+    }
+}
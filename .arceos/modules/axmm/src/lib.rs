@@ -10,7 +10,7 @@ mod aspace;
 mod backend;
 
 pub use self::aspace::AddrSpace;
-pub use self::backend::Backend;
+pub use self::backend::{Backend, HeldFrees, hold_frees};
 
 use axerrno::{AxError, AxResult};
 use axhal::mem::phys_to_virt;
@@ -30,6 +30,15 @@ fn mapping_err_to_ax_err(err: MappingError) -> AxError {
     }
 }
 
+/// Converts the error of mapping a new area, which a backend only fails to
+/// map for want of frames, to an `AxError`.
+fn map_err_to_ax_err(err: MappingError) -> AxError {
+    match err {
+        MappingError::BadState => AxError::NoMemory,
+        err => mapping_err_to_ax_err(err),
+    }
+}
+
 /// Creates a new address space for kernel itself.
 pub fn new_kernel_aspace() -> AxResult<AddrSpace> {
     let mut aspace = AddrSpace::new_empty(
@@ -9,6 +9,9 @@ use kernel_guard::NoPreemptIrqSave;
 
 pub(crate) use crate::run_queue::{current_run_queue, select_run_queue};
 
+#[doc(cfg(feature = "multitask"))]
+pub use crate::run_queue::{context_switches, cpu_idle_nanos, cpu_running_task};
+
 #[doc(cfg(feature = "multitask"))]
 pub use crate::task::{CurrentTask, TaskId, TaskInner};
 #[doc(cfg(feature = "multitask"))]
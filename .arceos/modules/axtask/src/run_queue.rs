@@ -1,6 +1,7 @@
 use alloc::collections::VecDeque;
 use alloc::sync::Arc;
 use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
 #[cfg(feature = "smp")]
 use alloc::sync::Weak;
@@ -37,6 +38,9 @@ percpu_static! {
     /// Stores the weak reference to the previous task that is running on this CPU.
     #[cfg(feature = "smp")]
     PREV_TASK: Weak<crate::AxTask> = Weak::new(),
+    /// The timer ticks since this CPU last looked for tasks to take.
+    #[cfg(all(feature = "smp", feature = "irq"))]
+    BALANCE_TICKS: usize = 0,
 }
 
 /// An array of references to run queues, one for each CPU, indexed by cpu_id.
@@ -53,6 +57,57 @@ static mut RUN_QUEUES: [MaybeUninit<&'static mut AxRunQueue>; axconfig::SMP] =
 #[allow(clippy::declare_interior_mutable_const)] // It's ok because it's used only for initialization `RUN_QUEUES`.
 const ARRAY_REPEAT_VALUE: MaybeUninit<&'static mut AxRunQueue> = MaybeUninit::uninit();
 
+/// The time each CPU has spent running its idle task, in nanoseconds, up to
+/// when it last switched away from it.
+static IDLE_NANOS: [AtomicU64; axconfig::SMP] = [const { AtomicU64::new(0) }; axconfig::SMP];
+/// When each CPU switched to its idle task, or `u64::MAX` if it is running
+/// another task.
+static IDLE_SINCE: [AtomicU64; axconfig::SMP] = [const { AtomicU64::new(u64::MAX) }; axconfig::SMP];
+/// The number of context switches each CPU has made.
+static CONTEXT_SWITCHES: [AtomicU64; axconfig::SMP] = [const { AtomicU64::new(0) }; axconfig::SMP];
+/// The ID of the task each CPU has last switched to, or 0 if none yet.
+static RUNNING_TASKS: [AtomicU64; axconfig::SMP] = [const { AtomicU64::new(0) }; axconfig::SMP];
+/// The number of tasks waiting in the scheduler of each CPU.
+static QUEUED_TASKS: [AtomicUsize; axconfig::SMP] = [const { AtomicUsize::new(0) }; axconfig::SMP];
+/// Whether the run queue of each CPU is in `RUN_QUEUES` yet.
+#[cfg(feature = "smp")]
+static RUN_QUEUE_READY: [core::sync::atomic::AtomicBool; axconfig::SMP] =
+    [const { core::sync::atomic::AtomicBool::new(false) }; axconfig::SMP];
+
+/// How many timer ticks apart each CPU looks for tasks to take from a busier
+/// one, so that a task is not moved back and forth too often.
+#[cfg(all(feature = "smp", feature = "irq"))]
+const BALANCE_INTERVAL_TICKS: usize = 4;
+
+/// Returns the time CPU `cpu` has spent idle since boot, in nanoseconds.
+pub fn cpu_idle_nanos(cpu: usize) -> u64 {
+    let idle = IDLE_NANOS[cpu].load(Ordering::Acquire);
+    match IDLE_SINCE[cpu].load(Ordering::Acquire) {
+        u64::MAX => idle,
+        since => idle + axhal::time::monotonic_time_nanos().saturating_sub(since),
+    }
+}
+
+/// Returns the number of context switches made on all CPUs since boot.
+pub fn context_switches() -> u64 {
+    CONTEXT_SWITCHES
+        .iter()
+        .map(|switches| switches.load(Ordering::Relaxed))
+        .sum()
+}
+
+/// Returns the ID of the task CPU `cpu` is running, or `None` if it has not
+/// switched to any yet.
+///
+/// The ID is changed before the CPU switches to the address space of the new
+/// task.
+pub fn cpu_running_task(cpu: usize) -> Option<u64> {
+    match RUNNING_TASKS[cpu].load(Ordering::SeqCst) {
+        0 => None,
+        id => Some(id),
+    }
+}
+
 /// Returns a reference to the current run queue in [`CurrentRunQueueRef`].
 ///
 /// ## Safety
@@ -238,6 +293,7 @@ impl<G: BaseGuard> AxRunQueueRef<'_, G> {
         );
         assert!(task.is_ready());
         self.inner.scheduler.lock().add_task(task);
+        QUEUED_TASKS[self.inner.cpu_id].fetch_add(1, Ordering::AcqRel);
     }
 
     /// Unblock one task by inserting it into the run queue.
@@ -277,6 +333,27 @@ impl<G: BaseGuard> CurrentRunQueueRef<'_, G> {
             #[cfg(feature = "preempt")]
             curr.set_preempt_pending(true);
         }
+        #[cfg(feature = "smp")]
+        self.balance_tick();
+    }
+
+    /// Every [`BALANCE_INTERVAL_TICKS`] ticks, takes a task from the CPU with
+    /// the most tasks waiting if it has two more than this one, which moving
+    /// one task cannot turn around. An idle CPU takes one whenever another has
+    /// any waiting; the idle task reschedules on returning from the interrupt.
+    #[cfg(all(feature = "irq", feature = "smp"))]
+    fn balance_tick(&mut self) {
+        let ticks = unsafe { BALANCE_TICKS.current_ref_mut_raw() };
+        *ticks += 1;
+        if *ticks < BALANCE_INTERVAL_TICKS {
+            return;
+        }
+        *ticks = 0;
+        let margin = if self.current_task.is_idle() { 1 } else { 2 };
+        if let Some(task) = self.inner.pull_task(margin) {
+            self.inner.scheduler.lock().add_task(task);
+            QUEUED_TASKS[self.inner.cpu_id].fetch_add(1, Ordering::AcqRel);
+        }
     }
 
     /// Yield the current task and reschedule.
@@ -452,6 +529,7 @@ impl AxRunQueue {
 
         let mut scheduler = Scheduler::new();
         scheduler.add_task(gc_task);
+        QUEUED_TASKS[cpu_id].fetch_add(1, Ordering::AcqRel);
         Self {
             cpu_id,
             scheduler: SpinRaw::new(scheduler),
@@ -495,6 +573,7 @@ impl AxRunQueue {
             }
             // TODO: priority
             self.scheduler.lock().put_prev_task(task, preempt);
+            QUEUED_TASKS[self.cpu_id].fetch_add(1, Ordering::AcqRel);
             true
         } else {
             false
@@ -504,14 +583,17 @@ impl AxRunQueue {
     /// Core reschedule subroutine.
     /// Pick the next task to run and switch to it.
     fn resched(&mut self) {
-        let next = self
-            .scheduler
-            .lock()
-            .pick_next_task()
-            .unwrap_or_else(|| unsafe {
-                // Safety: IRQs must be disabled at this time.
-                IDLE_TASK.current_ref_raw().get_unchecked().clone()
-            });
+        let next = self.scheduler.lock().pick_next_task();
+        if next.is_some() {
+            QUEUED_TASKS[self.cpu_id].fetch_sub(1, Ordering::AcqRel);
+        }
+        // With nothing to run here, run a task waiting on another CPU.
+        #[cfg(feature = "smp")]
+        let next = next.or_else(|| self.pull_task(1));
+        let next = next.unwrap_or_else(|| unsafe {
+            // Safety: IRQs must be disabled at this time.
+            IDLE_TASK.current_ref_raw().get_unchecked().clone()
+        });
         assert!(
             next.is_ready(),
             "next {} is not ready: {:?}",
@@ -540,6 +622,21 @@ impl AxRunQueue {
             return;
         }
 
+        let now = axhal::time::monotonic_time_nanos();
+        prev_task.stop_running(now);
+        next_task.start_running(now);
+        if prev_task.is_idle() {
+            let since = IDLE_SINCE[self.cpu_id].swap(u64::MAX, Ordering::AcqRel);
+            if since != u64::MAX {
+                IDLE_NANOS[self.cpu_id].fetch_add(now.saturating_sub(since), Ordering::AcqRel);
+            }
+        }
+        if next_task.is_idle() {
+            IDLE_SINCE[self.cpu_id].store(now, Ordering::Release);
+        }
+        CONTEXT_SWITCHES[self.cpu_id].fetch_add(1, Ordering::Relaxed);
+        RUNNING_TASKS[self.cpu_id].store(next_task.id().as_u64(), Ordering::SeqCst);
+
         // Claim the task as running, we do this before switching to it
         // such that any running task will have this set.
         #[cfg(feature = "smp")]
@@ -572,6 +669,44 @@ impl AxRunQueue {
     }
 }
 
+#[cfg(feature = "smp")]
+impl AxRunQueue {
+    /// Takes a task that may run on this CPU out of the run queue of the CPU
+    /// with the most tasks waiting, if it has at least `margin` more waiting
+    /// than this one.
+    ///
+    /// Tasks that are still being switched away from on their CPU are left
+    /// where they are.
+    fn pull_task(&mut self, margin: usize) -> Option<AxTaskRef> {
+        let queued = |cpu: usize| QUEUED_TASKS[cpu].load(Ordering::Acquire);
+        let busiest = (0..axconfig::SMP)
+            .filter(|&cpu| cpu != self.cpu_id && RUN_QUEUE_READY[cpu].load(Ordering::Acquire))
+            .max_by_key(|&cpu| queued(cpu))?;
+        let waiting = queued(busiest);
+        if waiting < queued(self.cpu_id) + margin {
+            return None;
+        }
+        let mut scheduler = get_run_queue(busiest).scheduler.lock();
+        // Look at each waiting task at most once, putting back those that
+        // cannot be taken.
+        for _ in 0..waiting {
+            let task = scheduler.pick_next_task()?;
+            if task.cpumask().get(self.cpu_id) && !task.on_cpu() {
+                QUEUED_TASKS[busiest].fetch_sub(1, Ordering::AcqRel);
+                trace!(
+                    "task pull: {} from run_queue {} to {}",
+                    task.id_name(),
+                    busiest,
+                    self.cpu_id
+                );
+                return Some(task);
+            }
+            scheduler.put_prev_task(task, false);
+        }
+        None
+    }
+}
+
 fn gc_entry() {
     loop {
         // Drop all exited tasks and recycle resources.
@@ -644,6 +779,8 @@ pub(crate) fn init() {
     unsafe {
         RUN_QUEUES[cpu_id].write(RUN_QUEUE.current_ref_mut_raw());
     }
+    #[cfg(feature = "smp")]
+    RUN_QUEUE_READY[cpu_id].store(true, Ordering::Release);
 }
 
 pub(crate) fn init_secondary() {
@@ -663,4 +800,6 @@ pub(crate) fn init_secondary() {
     unsafe {
         RUN_QUEUES[cpu_id].write(RUN_QUEUE.current_ref_mut_raw());
     }
+    #[cfg(feature = "smp")]
+    RUN_QUEUE_READY[cpu_id].store(true, Ordering::Release);
 }
@@ -38,6 +38,8 @@ pub enum TaskState {
 /// The inner task structure.
 pub struct TaskInner {
     id: TaskId,
+    /// The ID of the thread group (process) this task belongs to.
+    tgid: u64,
     name: UnsafeCell<String>,
     is_idle: bool,
     is_init: bool,
@@ -51,6 +53,12 @@ pub struct TaskInner {
     /// Mark whether the task is in the wait queue.
     in_wait_queue: AtomicBool,
 
+    /// The time the task has run on a CPU in nanoseconds, up to when it last
+    /// started running.
+    cpu_nanos: AtomicU64,
+    /// When the task last started running on a CPU.
+    running_since: AtomicU64,
+
     /// Used to indicate whether the task is running on a CPU.
     #[cfg(feature = "smp")]
     on_cpu: AtomicBool,
@@ -134,6 +142,17 @@ impl TaskInner {
         self.id
     }
 
+    /// Gets the ID of the thread group the task belongs to, which defaults to
+    /// the task's own ID.
+    pub const fn tgid(&self) -> u64 {
+        self.tgid
+    }
+
+    /// Sets the ID of the thread group, before the task is spawned.
+    pub fn set_tgid(&mut self, tgid: u64) {
+        self.tgid = tgid;
+    }
+
     /// Gets the name of the task.
     pub fn name(&self) -> &str {
         unsafe { (*self.name.get()).as_str() }
@@ -217,6 +236,30 @@ impl TaskInner {
         *self.cpumask.lock() = cpumask
     }
 
+    /// Returns the time the task has run on a CPU so far, in nanoseconds.
+    pub fn cpu_time_nanos(&self) -> u64 {
+        let cpu_nanos = self.cpu_nanos.load(Ordering::Acquire);
+        if self.is_running() {
+            let since = self.running_since.load(Ordering::Acquire);
+            cpu_nanos + axhal::time::monotonic_time_nanos().saturating_sub(since)
+        } else {
+            cpu_nanos
+        }
+    }
+
+    /// Counts the time since the task started running towards its time on
+    /// a CPU, as it is switched away from at `now`.
+    pub(crate) fn stop_running(&self, now: u64) {
+        let since = self.running_since.load(Ordering::Acquire);
+        self.cpu_nanos
+            .fetch_add(now.saturating_sub(since), Ordering::AcqRel);
+    }
+
+    /// Notes that the task starts running at `now`.
+    pub(crate) fn start_running(&self, now: u64) {
+        self.running_since.store(now, Ordering::Release);
+    }
+
     /// Read the top address of the kernel stack for the task.
     #[inline]
     pub fn get_kernel_stack_top(&self) -> Option<usize> {
@@ -237,6 +280,7 @@ impl TaskInner {
     fn new_common(id: TaskId, name: String) -> Self {
         Self {
             id,
+            tgid: id.as_u64(),
             name: UnsafeCell::new(name),
             is_idle: false,
             is_init: false,
@@ -245,6 +289,8 @@ impl TaskInner {
             // By default, the task is allowed to run on all CPUs.
             cpumask: SpinNoIrq::new(AxCpuMask::full()),
             in_wait_queue: AtomicBool::new(false),
+            cpu_nanos: AtomicU64::new(0),
+            running_since: AtomicU64::new(0),
             #[cfg(feature = "irq")]
             timer_ticket_id: AtomicU64::new(0),
             #[cfg(feature = "smp")]
@@ -416,8 +462,15 @@ impl TaskInner {
         self.wait_for_exit.notify_all(false);
     }
 
+    /// Returns the pointer to the task context.
+    ///
+    /// # Safety
+    ///
+    /// The context is only to be changed by the task itself, with interrupts
+    /// disabled, as it is read and written when the task is switched from or
+    /// to.
     #[inline]
-    pub(crate) const unsafe fn ctx_mut_ptr(&self) -> *mut TaskContext {
+    pub const unsafe fn ctx_mut_ptr(&self) -> *mut TaskContext {
         self.ctx.get()
     }
 
@@ -0,0 +1,32 @@
+use core::ffi::c_char;
+
+use arceos_posix_api::AT_FDCWD;
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{TaskExtRef, current};
+use starry_core::acct::set_acct_file;
+
+use super::link;
+use crate::ptr::{PtrWrapper, UserConstPtr};
+
+/// Turns process accounting on with records appended to the regular file
+/// `filename`, or off if it is null. Only root can do either.
+pub fn sys_acct(filename: UserConstPtr<c_char>) -> LinuxResult<isize> {
+    if current().task_ext().cred().euid != 0 {
+        return Err(LinuxError::EPERM);
+    }
+    let file = filename.nullable(|filename| {
+        let path = filename.get_as_path()?;
+        let path =
+            arceos_posix_api::handle_file_path(AT_FDCWD as _, Some(path.as_ptr() as _), false)?;
+        let path = link::resolve(&path, true)?;
+        if !axfs::api::metadata(&path)?.is_file() {
+            return Err(LinuxError::EACCES);
+        }
+        Ok(axfs::api::File::options()
+            .write(true)
+            .append(true)
+            .open(&path)?)
+    })?;
+    set_acct_file(file);
+    Ok(0)
+}
@@ -0,0 +1,265 @@
+//! The legacy asynchronous I/O interface: `io_setup`, `io_submit` and
+//! `io_getevents`.
+//!
+//! Only regular files are supported, and their I/O never blocks, so each
+//! operation is done when it is submitted and its event is already there for
+//! `io_getevents`. No ring buffer is mapped at the context ID, so events can
+//! only be read with the syscall.
+
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+};
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use arceos_posix_api::{self as api, get_file_like};
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::monotonic_time;
+use axsync::Mutex;
+use axtask::current;
+use starry_core::signal::signal_pending;
+
+use crate::ptr::{PtrWrapper, UserConstPtr, UserPtr};
+
+const IOCB_CMD_PREAD: u16 = 0;
+const IOCB_CMD_PWRITE: u16 = 1;
+
+/// The most events all contexts can hold, as the default of `aio-max-nr`.
+const AIO_MAX_NR: u32 = 65536;
+
+/// `struct iocb`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct IoCb {
+    data: u64,
+    key: u32,
+    rw_flags: i32,
+    opcode: u16,
+    reqprio: i16,
+    fildes: u32,
+    buf: u64,
+    nbytes: u64,
+    offset: i64,
+    reserved2: u64,
+    flags: u32,
+    resfd: u32,
+}
+
+/// `struct io_event`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct IoEvent {
+    data: u64,
+    /// The address of the `struct iocb` the event is for.
+    obj: u64,
+    /// The result of the operation, or a negated error number.
+    res: i64,
+    res2: i64,
+}
+
+struct AioContext {
+    /// The process that set the context up, which all its threads share.
+    owner: u64,
+    /// The most events that can be held for `io_getevents`.
+    capacity: usize,
+    events: Mutex<VecDeque<IoEvent>>,
+}
+
+/// The contexts that have been set up, by ID.
+static CONTEXTS: Mutex<BTreeMap<u64, Arc<AioContext>>> = Mutex::new(BTreeMap::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// The events all contexts can hold together.
+static RESERVED: Mutex<u32> = Mutex::new(0);
+
+/// Finds the context `id` of the calling process.
+fn find_context(id: u64) -> LinuxResult<Arc<AioContext>> {
+    let tgid = current().tgid();
+    CONTEXTS
+        .lock()
+        .get(&id)
+        .filter(|ctx| ctx.owner == tgid)
+        .cloned()
+        .ok_or(LinuxError::EINVAL)
+}
+
+/// Does the operation of `iocb` on `file`, returning the number of bytes
+/// transferred.
+fn perform(file: &api::File, iocb: &IoCb) -> LinuxResult<usize> {
+    let len = iocb.nbytes as usize;
+    let offset = iocb.offset as u64;
+    let buf = UserPtr::<u8>::from(iocb.buf as usize).get_as_bytes(len)?;
+    let file = file.inner().lock();
+    let len = match iocb.opcode {
+        IOCB_CMD_PREAD => {
+            let buf = unsafe { core::slice::from_raw_parts_mut(buf, len) };
+            file.read_at(offset, buf)?
+        }
+        _ => {
+            let buf = unsafe { core::slice::from_raw_parts(buf, len) };
+            file.write_at(offset, buf)?
+        }
+    };
+    Ok(len)
+}
+
+pub fn sys_io_setup(nr_events: u32, ctx_idp: UserPtr<u64>) -> LinuxResult<isize> {
+    let ctx_idp = ctx_idp.get()?;
+    if nr_events == 0 || unsafe { *ctx_idp } != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    {
+        let mut reserved = RESERVED.lock();
+        if nr_events > AIO_MAX_NR - *reserved {
+            return Err(LinuxError::EAGAIN);
+        }
+        *reserved += nr_events;
+    }
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let ctx = AioContext {
+        owner: current().tgid(),
+        capacity: nr_events as usize,
+        events: Mutex::new(VecDeque::new()),
+    };
+    CONTEXTS.lock().insert(id, Arc::new(ctx));
+    unsafe { *ctx_idp = id };
+    Ok(0)
+}
+
+pub fn sys_io_destroy(ctx_id: u64) -> LinuxResult<isize> {
+    let tgid = current().tgid();
+    let ctx = {
+        let mut contexts = CONTEXTS.lock();
+        match contexts.get(&ctx_id) {
+            Some(ctx) if ctx.owner == tgid => contexts.remove(&ctx_id).unwrap(),
+            _ => return Err(LinuxError::EINVAL),
+        }
+    };
+    *RESERVED.lock() -= ctx.capacity as u32;
+    Ok(0)
+}
+
+/// Destroys the contexts the process `tgid` has left as it exits, giving
+/// their events back to `aio-max-nr`.
+pub fn release_aio_contexts(tgid: u64) {
+    let mut released = 0;
+    CONTEXTS.lock().retain(|_, ctx| {
+        if ctx.owner != tgid {
+            return true;
+        }
+        released += ctx.capacity as u32;
+        false
+    });
+    *RESERVED.lock() -= released;
+}
+
+/// Submits the `nr` operations at `iocbpp`, and returns how many were
+/// submitted. An error is only returned if the first one fails.
+pub fn sys_io_submit(ctx_id: u64, nr: isize, iocbpp: UserConstPtr<usize>) -> LinuxResult<isize> {
+    if nr < 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let ctx = find_context(ctx_id)?;
+    if nr == 0 {
+        return Ok(0);
+    }
+    let iocbpp = iocbpp.get_as_array(nr as usize)?;
+    let mut submitted = 0;
+    for i in 0..nr as usize {
+        match submit(&ctx, unsafe { *iocbpp.add(i) }) {
+            Ok(()) => submitted += 1,
+            Err(err) if submitted == 0 => return Err(err),
+            Err(_) => break,
+        }
+    }
+    Ok(submitted)
+}
+
+fn submit(ctx: &AioContext, iocbp: usize) -> LinuxResult {
+    let iocb = unsafe { *UserConstPtr::<IoCb>::from(iocbp).get()? };
+    // Completion through an eventfd with `IOCB_FLAG_RESFD` is not supported.
+    if iocb.flags != 0 || iocb.rw_flags != 0 || iocb.reserved2 != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    if !matches!(iocb.opcode, IOCB_CMD_PREAD | IOCB_CMD_PWRITE)
+        || iocb.offset < 0
+        || isize::try_from(iocb.nbytes).is_err()
+    {
+        return Err(LinuxError::EINVAL);
+    }
+    let file = get_file_like(iocb.fildes as i32)?
+        .into_any()
+        .downcast::<api::File>()
+        .map_err(|_| LinuxError::EINVAL)?;
+    let mut events = ctx.events.lock();
+    if events.len() >= ctx.capacity {
+        return Err(LinuxError::EAGAIN);
+    }
+    let res = match perform(&file, &iocb) {
+        Ok(len) => len as i64,
+        Err(err) => -(err.code() as i64),
+    };
+    events.push_back(IoEvent {
+        data: iocb.data,
+        obj: iocbp as u64,
+        res,
+        res2: 0,
+    });
+    Ok(())
+}
+
+/// Takes at least `min_nr` and at most `nr` events to `events`, waiting for
+/// them until `timeout` if it is not null.
+pub fn sys_io_getevents(
+    ctx_id: u64,
+    min_nr: isize,
+    nr: isize,
+    events: UserPtr<IoEvent>,
+    timeout: UserConstPtr<api::ctypes::timespec>,
+) -> LinuxResult<isize> {
+    if min_nr < 0 || nr < 0 || min_nr > nr {
+        return Err(LinuxError::EINVAL);
+    }
+    let ctx = find_context(ctx_id)?;
+    let timeout = timeout.nullable(|ts| ts.get().map(|ts| unsafe { *ts }))?;
+    if let Some(ts) = timeout {
+        if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) {
+            return Err(LinuxError::EINVAL);
+        }
+    }
+    if nr == 0 {
+        return Ok(0);
+    }
+    let events = events.get_as_array(nr as usize)?;
+    let deadline = timeout.map(|ts| monotonic_time() + Duration::from(ts));
+    let mut taken = 0;
+    loop {
+        {
+            let mut queue = ctx.events.lock();
+            while taken < nr as usize {
+                let Some(event) = queue.pop_front() else {
+                    break;
+                };
+                unsafe { *events.add(taken) = event };
+                taken += 1;
+            }
+        }
+        if taken >= min_nr as usize {
+            return Ok(taken as _);
+        }
+        if deadline.is_some_and(|deadline| monotonic_time() >= deadline) {
+            return Ok(taken as _);
+        }
+        if signal_pending() {
+            return if taken > 0 {
+                Ok(taken as _)
+            } else {
+                Err(LinuxError::EINTR)
+            };
+        }
+        axtask::yield_now();
+    }
+}
@@ -0,0 +1,172 @@
+//! The permission bits and owners of files.
+//!
+//! The filesystems underneath do not keep them, so they are kept here by the
+//! canonical path of the file, for the files made or changed since boot. The
+//! others have what the filesystem makes up for them.
+
+use alloc::{collections::BTreeMap, string::String};
+use core::ffi::c_char;
+
+use arceos_posix_api::{AT_FDCWD, ctypes};
+use axerrno::{LinuxError, LinuxResult};
+use axsync::Mutex;
+use axtask::{TaskExtRef, current};
+
+use super::{link, stat::stat_path};
+use crate::ptr::UserConstPtr;
+
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFLNK: u32 = 0o120000;
+const S_ISUID: u32 = 0o4000;
+const S_ISGID: u32 = 0o2000;
+const AT_SYMLINK_NOFOLLOW: i32 = 0x100;
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct FileAttr {
+    /// The permission bits, with those for setuid, setgid and sticky.
+    pub perm: u32,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+static ATTRS: Mutex<BTreeMap<String, FileAttr>> = Mutex::new(BTreeMap::new());
+
+/// Records that the current process has just made a file at `path` with
+/// `mode`, less its umask.
+pub(super) fn set_created(path: &str, mode: u32) {
+    let curr = current();
+    let cred = curr.task_ext().cred();
+    let attr = FileAttr {
+        perm: mode & 0o7777 & !curr.task_ext().umask(),
+        uid: cred.euid,
+        gid: cred.egid,
+    };
+    ATTRS.lock().insert(path.into(), attr);
+}
+
+/// Forgets the file at `path` once it has been removed.
+pub(super) fn remove(path: &str) {
+    ATTRS.lock().remove(path);
+}
+
+/// Moves what is kept for the file at `old`, and for those under it if it is
+/// a directory, to `new`.
+pub(super) fn rename(old: &str, new: &str) {
+    super::rename_entries(&mut ATTRS.lock(), old, new);
+}
+
+/// Puts what is kept for the file at `path`, if anything, in `statbuf`.
+pub(super) fn apply(path: &str, statbuf: &mut ctypes::stat) {
+    if let Some(attr) = ATTRS.lock().get(path) {
+        statbuf.st_mode = (statbuf.st_mode & !0o7777) | attr.perm;
+        statbuf.st_uid = attr.uid;
+        statbuf.st_gid = attr.gid;
+    }
+}
+
+/// The canonical path of the file at `path` from `dirfd`, following a link
+/// in the last component if `follow` is set, with its status.
+fn lookup(
+    dirfd: i32,
+    path: UserConstPtr<c_char>,
+    follow: bool,
+) -> LinuxResult<(String, ctypes::stat)> {
+    let path = link::resolve_at(dirfd, path, follow)?;
+    let statbuf = stat_path(&path, false)?;
+    Ok((path, statbuf))
+}
+
+/// Changes the permission bits of a file. Only its owner or root may, and
+/// the setgid bit is dropped unless the caller is root or in the group of
+/// the file. Links have no permission bits of their own.
+pub fn sys_fchmodat(
+    dirfd: i32,
+    path: UserConstPtr<c_char>,
+    mode: u32,
+    flags: i32,
+) -> LinuxResult<isize> {
+    if flags & !AT_SYMLINK_NOFOLLOW != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let (path, statbuf) = lookup(dirfd, path, flags & AT_SYMLINK_NOFOLLOW == 0)?;
+    if statbuf.st_mode & S_IFMT == S_IFLNK {
+        return Err(LinuxError::EOPNOTSUPP);
+    }
+    let cred = current().task_ext().cred();
+    if cred.euid != 0 && cred.euid != statbuf.st_uid {
+        return Err(LinuxError::EPERM);
+    }
+    let mut perm = mode & 0o7777;
+    if cred.euid != 0 && !cred.in_group(statbuf.st_gid) {
+        perm &= !S_ISGID;
+    }
+    let attr = FileAttr {
+        perm,
+        uid: statbuf.st_uid,
+        gid: statbuf.st_gid,
+    };
+    ATTRS.lock().insert(path, attr);
+    Ok(0)
+}
+
+/// Changes the owner and group of a file, leaving either as it is if it is
+/// -1. Only root may give a file away; its owner may only move it between
+/// groups they are in. Either way, a file that is not a directory loses its
+/// setuid and setgid bits.
+pub fn sys_fchownat(
+    dirfd: i32,
+    path: UserConstPtr<c_char>,
+    owner: u32,
+    group: u32,
+    flags: i32,
+) -> LinuxResult<isize> {
+    if flags & !AT_SYMLINK_NOFOLLOW != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let (path, statbuf) = lookup(dirfd, path, flags & AT_SYMLINK_NOFOLLOW == 0)?;
+    let uid = if owner == u32::MAX {
+        statbuf.st_uid
+    } else {
+        owner
+    };
+    let gid = if group == u32::MAX {
+        statbuf.st_gid
+    } else {
+        group
+    };
+    let cred = current().task_ext().cred();
+    if cred.euid != 0
+        && (cred.euid != statbuf.st_uid
+            || uid != statbuf.st_uid
+            || (gid != statbuf.st_gid && !cred.in_group(gid)))
+    {
+        return Err(LinuxError::EPERM);
+    }
+    let mut perm = statbuf.st_mode & 0o7777;
+    if statbuf.st_mode & S_IFMT != S_IFDIR {
+        perm &= !(S_ISUID | S_ISGID);
+    }
+    ATTRS.lock().insert(path, FileAttr { perm, uid, gid });
+    Ok(0)
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_chmod(path: UserConstPtr<c_char>, mode: u32) -> LinuxResult<isize> {
+    sys_fchmodat(AT_FDCWD as _, path, mode, 0)
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_chown(path: UserConstPtr<c_char>, owner: u32, group: u32) -> LinuxResult<isize> {
+    sys_fchownat(AT_FDCWD as _, path, owner, group, 0)
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_lchown(path: UserConstPtr<c_char>, owner: u32, group: u32) -> LinuxResult<isize> {
+    sys_fchownat(AT_FDCWD as _, path, owner, group, AT_SYMLINK_NOFOLLOW)
+}
+
+/// Sets the umask of the calling process, returning the old one.
+pub fn sys_umask(mask: u32) -> LinuxResult<isize> {
+    Ok(current().task_ext().swap_umask(mask & 0o777) as _)
+}
@@ -1,15 +1,27 @@
 use core::ffi::{c_char, c_void};
 
-use alloc::string::ToString;
-use arceos_posix_api::AT_FDCWD;
+use alloc::{format, string::ToString};
+use arceos_posix_api::{AT_FDCWD, FilePath};
 use axerrno::{AxError, LinuxError, LinuxResult};
 use macro_rules_attribute::apply;
 
+use super::{
+    attr,
+    fifo::{self, fifo_perm},
+    link,
+    proc::ProcDir,
+    tty,
+};
 use crate::{
     ptr::{PtrWrapper, UserConstPtr, UserPtr},
     syscall_instrument,
 };
 
+const AT_REMOVEDIR: usize = 0x200;
+const AT_SYMLINK_FOLLOW: i32 = 0x400;
+
+const RENAME_NOREPLACE: u32 = 1;
+
 /// The ioctl() system call manipulates the underlying device parameters
 /// of special files.
 ///
@@ -19,40 +31,59 @@ use crate::{
 ///   and of type int in musl and other UNIX systems.
 /// * `argp` - The argument to the request. It is a pointer to a memory location
 #[apply(syscall_instrument)]
-pub fn sys_ioctl(_fd: i32, _op: usize, _argp: UserPtr<c_void>) -> LinuxResult<isize> {
-    warn!("Unimplemented syscall: SYS_IOCTL");
+pub fn sys_ioctl(fd: i32, op: usize, argp: UserPtr<c_void>) -> LinuxResult<isize> {
+    let file = arceos_posix_api::get_file_like(fd)?;
+    // Like Linux, only the low 32 bits count, as musl sign-extends an int.
+    let op = op as u32 as usize;
+    if let Some(result) = tty::ioctl(file, op, argp) {
+        return result;
+    }
+    warn!("Unimplemented ioctl {:#x} on fd {}", op, fd);
     Ok(0)
 }
 
 pub fn sys_chdir(path: UserConstPtr<c_char>) -> LinuxResult<isize> {
-    let path = path.get_as_str()?;
-    axfs::api::set_current_dir(path).map(|_| 0).map_err(|err| {
+    let path = link::resolve_at(AT_FDCWD as _, path, true)?;
+    axfs::api::set_current_dir(&path).map(|_| 0).map_err(|err| {
         warn!("Failed to change directory: {err:?}");
         err.into()
     })
 }
 
+/// Changes the working directory to the directory `fd` stands for, which may
+/// have been opened with `O_PATH`.
+pub fn sys_fchdir(fd: i32) -> LinuxResult<isize> {
+    let file = arceos_posix_api::get_file_like(fd)?;
+    let path = file.dir_path().ok_or(LinuxError::ENOTDIR)?;
+    axfs::api::set_current_dir(path)?;
+    Ok(0)
+}
+
 #[cfg(target_arch = "x86_64")]
 pub fn sys_unlink(path: UserConstPtr<c_char>) -> LinuxResult<isize> {
     sys_unlinkat(AT_FDCWD, path, 0)
 }
 
-pub fn sys_mkdirat(dirfd: i32, path: UserConstPtr<c_char>, mode: u32) -> LinuxResult<isize> {
-    let path = path.get_as_str()?;
-
-    if !path.starts_with("/") && dirfd != AT_FDCWD as i32 {
-        warn!("unsupported.");
-        return Err(LinuxError::EINVAL);
-    }
+#[cfg(target_arch = "x86_64")]
+pub fn sys_rmdir(path: UserConstPtr<c_char>) -> LinuxResult<isize> {
+    sys_unlinkat(AT_FDCWD, path, AT_REMOVEDIR)
+}
 
-    if mode != 0 {
-        info!("directory mode not supported.");
-    }
+/// Makes a directory at `path` with `mode`, less the umask of the caller.
+pub fn sys_mkdirat(dirfd: i32, path: UserConstPtr<c_char>, mode: u32) -> LinuxResult<isize> {
+    let path = link::resolve_at(dirfd, path, false)?;
 
-    axfs::api::create_dir(path).map(|_| 0).map_err(|err| {
+    axfs::api::create_dir(&path).map_err(|err| {
         warn!("Failed to create directory {path}: {err:?}");
-        err.into()
-    })
+        LinuxError::from(err)
+    })?;
+    attr::set_created(&path, mode);
+    Ok(0)
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_mkdir(path: UserConstPtr<c_char>, mode: u32) -> LinuxResult<isize> {
+    sys_mkdirat(AT_FDCWD as _, path, mode)
 }
 
 #[repr(C)]
@@ -82,10 +113,15 @@ pub enum FileType {
 
 impl From<axfs::api::FileType> for FileType {
     fn from(ft: axfs::api::FileType) -> Self {
+        use axfs::api::FileType as NodeType;
         match ft {
-            ft if ft.is_dir() => FileType::Dir,
-            ft if ft.is_file() => FileType::Reg,
-            _ => FileType::Unknown,
+            NodeType::Fifo => FileType::Fifo,
+            NodeType::CharDevice => FileType::Chr,
+            NodeType::Dir => FileType::Dir,
+            NodeType::BlockDevice => FileType::Blk,
+            NodeType::File => FileType::Reg,
+            NodeType::SymLink => FileType::Lnk,
+            NodeType::Socket => FileType::Socket,
         }
     }
 }
@@ -147,6 +183,27 @@ impl<'a> DirBuffer<'a> {
     }
 }
 
+/// Reads the entries of the directory `dir` under `/proc/[pid]` from where
+/// the last read left off.
+fn read_proc_dir(dir: &ProcDir, buffer: &mut DirBuffer) -> LinuxResult<isize> {
+    let mut pos = dir.pos.lock();
+    let mut total_size = 0;
+    while let Some((name, file_type)) = dir.entries.get(*pos) {
+        let name = format!("{name}\0");
+        let entry_size = (DirEnt::FIXED_SIZE + name.len()).next_multiple_of(8);
+        let dirent = DirEnt::new(1, *pos as i64 + 1, entry_size, (*file_type).into());
+        if buffer.write_entry(dirent, name.as_bytes()).is_err() {
+            break;
+        }
+        total_size += entry_size;
+        *pos += 1;
+    }
+    if total_size == 0 && *pos < dir.entries.len() {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(total_size as _)
+}
+
 pub fn sys_getdents64(fd: i32, buf: UserPtr<c_void>, len: usize) -> LinuxResult<isize> {
     let buf = buf.get_as_bytes(len)?;
 
@@ -155,6 +212,15 @@ pub fn sys_getdents64(fd: i32, buf: UserPtr<c_void>, len: usize) -> LinuxResult<
         return Err(LinuxError::EINVAL);
     }
 
+    if let Ok(dir) = arceos_posix_api::get_file_like(fd)?
+        .into_any()
+        .downcast::<ProcDir>()
+    {
+        let mut buffer =
+            unsafe { DirBuffer::new(core::slice::from_raw_parts_mut(buf as *mut u8, len)) };
+        return read_proc_dir(&dir, &mut buffer);
+    }
+
     let path = match arceos_posix_api::Directory::from_fd(fd).map(|dir| dir.path().to_string()) {
         Ok(path) => path,
         Err(err) => {
@@ -195,12 +261,15 @@ pub fn sys_getdents64(fd: i32, buf: UserPtr<c_void>, len: usize) -> LinuxResult<
                 let entry_size = DirEnt::FIXED_SIZE + name_bytes.len();
                 current_offset += entry_size as i64;
 
-                let dirent = DirEnt::new(
-                    1,
-                    current_offset,
-                    entry_size,
-                    FileType::from(entry.file_type()),
-                );
+                let entry_path = format!("{}/{}", path.trim_end_matches('/'), entry.file_name());
+                let file_type = if link::is_symlink(&entry_path) {
+                    FileType::Lnk
+                } else if fifo_perm(&entry_path).is_some() {
+                    FileType::Fifo
+                } else {
+                    FileType::from(entry.file_type())
+                };
+                let dirent = DirEnt::new(1, current_offset, entry_size, file_type);
 
                 if buffer.write_entry(dirent, name_bytes).is_err() {
                     break;
@@ -224,6 +293,10 @@ pub fn sys_getdents64(fd: i32, buf: UserPtr<c_void>, len: usize) -> LinuxResult<
 /// new_path: new file path
 /// flags: link flags
 /// return value: return 0 when success, else return -1.
+///
+/// A symbolic link at `old_path` is only followed with `AT_SYMLINK_FOLLOW`.
+/// Otherwise the new link is another symbolic link to the same target, as
+/// links are not files of their own to link to.
 pub fn sys_linkat(
     old_dirfd: i32,
     old_path: UserConstPtr<c_char>,
@@ -231,34 +304,80 @@ pub fn sys_linkat(
     new_path: UserConstPtr<c_char>,
     flags: i32,
 ) -> LinuxResult<isize> {
-    let old_path = old_path.get_as_null_terminated()?;
-    let new_path = new_path.get_as_null_terminated()?;
-
-    if flags != 0 {
-        warn!("Unsupported flags: {flags}");
+    if flags & !AT_SYMLINK_FOLLOW != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let old_path = link::resolve_at(old_dirfd, old_path, flags & AT_SYMLINK_FOLLOW != 0)?;
+    let new_path = link::resolve_at(new_dirfd, new_path, false)?;
+    if let Some(target) = link::read_link(&old_path) {
+        link::create(new_path, target)?;
+        return Ok(0);
     }
 
-    // handle old path
-    arceos_posix_api::handle_file_path(old_dirfd as isize, Some(old_path.as_ptr() as _), false)
-        .inspect_err(|err| warn!("Failed to convert new path: {err:?}"))
-        .and_then(|old_path| {
-            //handle new path
-            arceos_posix_api::handle_file_path(
-                new_dirfd as isize,
-                Some(new_path.as_ptr() as _),
-                false,
-            )
-            .inspect_err(|err| warn!("Failed to convert new path: {err:?}"))
-            .map(|new_path| (old_path, new_path))
-        })
-        .and_then(|(old_path, new_path)| {
-            arceos_posix_api::HARDLINK_MANAGER
-                .create_link(&new_path, &old_path)
-                .inspect_err(|err| warn!("Failed to create link: {err:?}"))
-                .map_err(Into::into)
-        })
+    arceos_posix_api::HARDLINK_MANAGER
+        .create_link(&FilePath::new(new_path)?, &FilePath::new(old_path)?)
+        .inspect_err(|err| warn!("Failed to create link: {err:?}"))
         .map(|_| 0)
-        .map_err(|err| err.into())
+        .map_err(|err| LinuxError::from(AxError::from(err)))
+}
+
+/// Moves the file at `old_path` to `new_path`, replacing what is there unless
+/// `RENAME_NOREPLACE` is set. A directory can only replace an empty
+/// directory, and only a directory can replace one. Exchanging the two
+/// files and leaving whiteouts are not supported.
+pub fn sys_renameat2(
+    old_dirfd: i32,
+    old_path: UserConstPtr<c_char>,
+    new_dirfd: i32,
+    new_path: UserConstPtr<c_char>,
+    flags: u32,
+) -> LinuxResult<isize> {
+    if flags & !RENAME_NOREPLACE != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let old_path = link::resolve_at(old_dirfd, old_path, false)?;
+    let new_path = link::resolve_at(new_dirfd, new_path, false)?;
+
+    let old_is_dir = axfs::api::metadata(&old_path)?.is_dir();
+    if old_path == new_path {
+        return Ok(0);
+    }
+    if old_is_dir
+        && new_path
+            .strip_prefix(old_path.as_str())
+            .is_some_and(|rest| rest.starts_with('/'))
+    {
+        // A directory cannot be moved into itself.
+        return Err(LinuxError::EINVAL);
+    }
+    if let Ok(new_metadata) = axfs::api::metadata(&new_path) {
+        if flags & RENAME_NOREPLACE != 0 {
+            return Err(LinuxError::EEXIST);
+        }
+        match (old_is_dir, new_metadata.is_dir()) {
+            (true, false) => return Err(LinuxError::ENOTDIR),
+            (false, true) => return Err(LinuxError::EISDIR),
+            (true, true) => axfs::api::remove_dir(&new_path)?,
+            // A file in the way is removed as the file is moved over it.
+            (false, false) => {}
+        }
+        link::remove(&new_path);
+        fifo::remove(&new_path);
+        attr::remove(&new_path);
+    }
+    axfs::api::rename(&old_path, &new_path)?;
+    link::rename(&old_path, &new_path);
+    fifo::rename(&old_path, &new_path);
+    attr::rename(&old_path, &new_path);
+    Ok(0)
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_rename(
+    old_path: UserConstPtr<c_char>,
+    new_path: UserConstPtr<c_char>,
+) -> LinuxResult<isize> {
+    sys_renameat2(AT_FDCWD as _, old_path, AT_FDCWD as _, new_path, 0)
 }
 
 /// remove link of specific file (can be used to delete file)
@@ -267,9 +386,7 @@ pub fn sys_linkat(
 /// flags: can be 0 or AT_REMOVEDIR
 /// return 0 when success, else return -1
 pub fn sys_unlinkat(dir_fd: isize, path: UserConstPtr<c_char>, flags: usize) -> LinuxResult<isize> {
-    let path = path.get_as_null_terminated()?;
-
-    const AT_REMOVEDIR: usize = 0x200;
+    let path = path.get_as_path()?;
 
     arceos_posix_api::handle_file_path(dir_fd, Some(path.as_ptr() as _), false)
         .inspect_err(|e| warn!("unlinkat error: {:?}", e))
@@ -277,7 +394,10 @@ pub fn sys_unlinkat(dir_fd: isize, path: UserConstPtr<c_char>, flags: usize) ->
             if flags == AT_REMOVEDIR {
                 axfs::api::remove_dir(path.as_str())
                     .inspect_err(|e| warn!("unlinkat error: {:?}", e))
-                    .map(|_| 0)
+                    .map(|_| {
+                        attr::remove(&path);
+                        0
+                    })
             } else {
                 axfs::api::metadata(path.as_str()).and_then(|metadata| {
                     if metadata.is_dir() {
@@ -290,7 +410,12 @@ pub fn sys_unlinkat(dir_fd: isize, path: UserConstPtr<c_char>, flags: usize) ->
                                 debug!("unlink file error");
                                 AxError::NotFound
                             })
-                            .map(|_| 0)
+                            .map(|_| {
+                                link::remove(&path);
+                                fifo::remove(&path);
+                                attr::remove(&path);
+                                0
+                            })
                     }
                 })
             }
@@ -299,5 +424,6 @@ pub fn sys_unlinkat(dir_fd: isize, path: UserConstPtr<c_char>, flags: usize) ->
 }
 
 pub fn sys_getcwd(buf: UserPtr<c_char>, size: usize) -> LinuxResult<isize> {
-    Ok(arceos_posix_api::sys_getcwd(buf.get_as_null_terminated()?.as_ptr() as _, size) as _)
+    // The buffer is only written to, so it need not hold a string yet.
+    Ok(arceos_posix_api::sys_getcwd(buf.get_as_bytes(size)? as _, size) as _)
 }
@@ -0,0 +1,166 @@
+//! Character devices that are backed by kernel state rather than the
+//! filesystem, and so are opened by path before falling back to the VFS.
+
+use alloc::{format, string::String, sync::Arc};
+
+use arceos_posix_api::{FileLike, ctypes};
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use starry_core::{
+    kmsg::log_user,
+    rand::{add_entropy, fill_random},
+    tty::{TtyFile, current_tty, open_master, open_slave},
+};
+
+const S_IFCHR: u32 = 0o020000;
+
+const fn makedev(major: u32, minor: u32) -> u64 {
+    (((major & 0xfff) << 8) | (minor & 0xff)) as u64
+}
+
+fn char_device_stat(rdev: u64) -> ctypes::stat {
+    ctypes::stat {
+        st_ino: 1,
+        st_nlink: 1,
+        st_mode: S_IFCHR | 0o666,
+        st_rdev: rdev as _,
+        st_blksize: 4096,
+        ..Default::default()
+    }
+}
+
+/// `/dev/random` and `/dev/urandom`.
+///
+/// Both draw from the same generator as `getrandom`, which is always
+/// considered initialized, so `/dev/random` never blocks either.
+struct RandomDev {
+    minor: u32,
+}
+
+impl FileLike for RandomDev {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        fill_random(buf);
+        Ok(buf.len())
+    }
+
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        add_entropy(buf);
+        Ok(buf.len())
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        Ok(char_device_stat(makedev(1, self.minor)))
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: true,
+            writable: true,
+        })
+    }
+
+    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+        Ok(())
+    }
+}
+
+/// `/dev/kmsg`, to which a message is written to be logged.
+///
+/// Reading the records back from it is not supported; they are read with
+/// `syslog` or from `/proc/kmsg`.
+struct KmsgDev;
+
+impl FileLike for KmsgDev {
+    fn read(&self, _buf: &mut [u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        log_user(&String::from_utf8_lossy(buf));
+        Ok(buf.len())
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        Ok(char_device_stat(makedev(1, 11)))
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: false,
+            writable: true,
+        })
+    }
+
+    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+        Ok(())
+    }
+}
+
+/// The path of the device file for the character device `rdev`, if it is one
+/// of ours or a terminal.
+pub(super) fn device_path(rdev: u64) -> Option<String> {
+    let path = match ((rdev >> 8) & 0xfff, rdev & 0xff) {
+        (1, 8) => "/dev/random",
+        (1, 9) => "/dev/urandom",
+        (1, 11) => "/dev/kmsg",
+        (5, 1) => "/dev/console",
+        (5, 2) => "/dev/ptmx",
+        (136, index) => return Some(format!("/dev/pts/{index}")),
+        _ => return None,
+    };
+    Some(String::from(path))
+}
+
+/// Opens the device at the absolute, canonical `path`, or returns `None` if
+/// it is not one of ours.
+pub(crate) fn open_device(path: &str) -> Option<LinuxResult<Arc<dyn FileLike>>> {
+    let dev: Arc<dyn FileLike> = match path {
+        "/dev/random" => Arc::new(RandomDev { minor: 8 }),
+        "/dev/urandom" => Arc::new(RandomDev { minor: 9 }),
+        "/dev/kmsg" => Arc::new(KmsgDev),
+        "/dev/tty" => match current_tty() {
+            Some(tty) => Arc::new(TtyFile::new(tty)),
+            None => return Some(Err(LinuxError::ENXIO)),
+        },
+        "/dev/ptmx" => match open_master() {
+            Ok(master) => Arc::new(master),
+            Err(err) => return Some(Err(err)),
+        },
+        _ => {
+            let index = path.strip_prefix("/dev/pts/")?.parse().ok()?;
+            match open_slave(index) {
+                Ok(slave) => Arc::new(slave),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    };
+    Some(Ok(dev))
+}
+
+/// Returns the status of the device at the absolute, canonical `path`, which
+/// unlike opening it does not depend on the calling process.
+///
+/// This covers the devices the VFS provides as well, which have no device
+/// numbers of their own.
+pub(crate) fn device_stat(path: &str) -> Option<ctypes::stat> {
+    let rdev = match path {
+        "/dev/null" => makedev(1, 3),
+        "/dev/zero" => makedev(1, 5),
+        "/dev/full" => makedev(1, 7),
+        "/dev/random" => makedev(1, 8),
+        "/dev/urandom" => makedev(1, 9),
+        "/dev/kmsg" => makedev(1, 11),
+        "/dev/tty" => makedev(5, 0),
+        "/dev/ptmx" => makedev(5, 2),
+        _ => return None,
+    };
+    Some(char_device_stat(rdev))
+}
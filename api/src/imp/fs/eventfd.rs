@@ -0,0 +1,150 @@
+//! Event counters that can be waited for like files, from `eventfd`.
+
+use alloc::sync::Arc;
+use core::{
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
+
+use arceos_posix_api::{self as api, FileLike, ctypes};
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axsync::Mutex;
+use axtask::WaitQueue;
+use starry_core::signal::signal_pending;
+
+use super::{fd_ops::set_cloexec, poll::wake_pollers};
+
+const EFD_SEMAPHORE: u32 = 1;
+const EFD_NONBLOCK: u32 = 0o4000;
+const EFD_CLOEXEC: u32 = 0o2000000;
+
+/// The largest value the counter can hold.
+const MAX_COUNT: u64 = u64::MAX - 1;
+
+/// How long a blocked read or write sleeps at most before it checks for
+/// signals, which do not wake it.
+const WAIT_SLICE: Duration = Duration::from_millis(10);
+
+pub struct EventFd {
+    count: Mutex<u64>,
+    /// Whether a read takes 1 from the counter instead of all of it.
+    semaphore: bool,
+    nonblocking: AtomicBool,
+    /// Counts the changes to the counter, for the blocked readers and writers
+    /// in `wq` to tell whether to go back to sleep.
+    changes: AtomicU64,
+    wq: WaitQueue,
+}
+
+impl EventFd {
+    /// Waits until `f` succeeds on the counter, or fails with `EAGAIN` right
+    /// away in non-blocking mode. The readers, writers and pollers waiting
+    /// for the counter to change are woken once `f` succeeds.
+    fn wait_for<T>(&self, mut f: impl FnMut(&mut u64) -> Option<T>) -> LinuxResult<T> {
+        loop {
+            let seen = self.changes.load(Ordering::Acquire);
+            if let Some(result) = f(&mut self.count.lock()) {
+                self.changes.fetch_add(1, Ordering::AcqRel);
+                self.wq.notify_all(false);
+                wake_pollers();
+                return Ok(result);
+            }
+            if self.nonblocking() {
+                return Err(LinuxError::EAGAIN);
+            }
+            if signal_pending() {
+                return Err(LinuxError::EINTR);
+            }
+            self.wq
+                .wait_timeout_until(WAIT_SLICE, || self.changes.load(Ordering::Acquire) != seen);
+        }
+    }
+}
+
+impl FileLike for EventFd {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        if buf.len() < size_of::<u64>() {
+            return Err(LinuxError::EINVAL);
+        }
+        let value = self.wait_for(|count| {
+            if *count == 0 {
+                return None;
+            }
+            let value = if self.semaphore { 1 } else { *count };
+            *count -= value;
+            Some(value)
+        })?;
+        buf[..size_of::<u64>()].copy_from_slice(&value.to_ne_bytes());
+        Ok(size_of::<u64>())
+    }
+
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        let Some(value) = buf
+            .first_chunk::<8>()
+            .map(|value| u64::from_ne_bytes(*value))
+        else {
+            return Err(LinuxError::EINVAL);
+        };
+        if value > MAX_COUNT {
+            return Err(LinuxError::EINVAL);
+        }
+        self.wait_for(|count| (MAX_COUNT - *count >= value).then(|| *count += value))?;
+        Ok(size_of::<u64>())
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode: 0o600, // rw-------
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        let count = *self.count.lock();
+        Ok(PollState {
+            readable: count > 0,
+            writable: count < MAX_COUNT,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Release);
+        Ok(())
+    }
+
+    fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Acquire)
+    }
+
+    fn notifies_pollers(&self) -> bool {
+        true
+    }
+}
+
+pub fn sys_eventfd2(initval: u32, flags: u32) -> LinuxResult<isize> {
+    if flags & !(EFD_SEMAPHORE | EFD_NONBLOCK | EFD_CLOEXEC) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let eventfd = EventFd {
+        count: Mutex::new(initval as u64),
+        semaphore: flags & EFD_SEMAPHORE != 0,
+        nonblocking: AtomicBool::new(flags & EFD_NONBLOCK != 0),
+        changes: AtomicU64::new(0),
+        wq: WaitQueue::new(),
+    };
+    let fd = api::add_file_like(Arc::new(eventfd))?;
+    set_cloexec(fd, flags & EFD_CLOEXEC != 0);
+    Ok(fd as _)
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_eventfd(initval: u32) -> LinuxResult<isize> {
+    sys_eventfd2(initval, 0)
+}
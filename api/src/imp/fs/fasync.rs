@@ -0,0 +1,180 @@
+//! Signal-driven I/O: `O_ASYNC` and the owner of a file set with `F_SETOWN`.
+//!
+//! Files do not report when they become ready, so the files with `O_ASYNC`
+//! are polled whenever a syscall returns, which is when a pipe or terminal
+//! gets written to, as long as any file has it. Their owner is signalled when
+//! one turns readable or writable.
+
+use alloc::{
+    collections::btree_map::BTreeMap,
+    sync::{Arc, Weak},
+};
+use core::{
+    ffi::c_int,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use arceos_posix_api::{FileLike, get_file_like};
+use axerrno::{LinuxError, LinuxResult};
+use axsync::Mutex;
+use starry_core::signal::{
+    NSIG, SI_KERNEL, SIGIO, SigInfo, send_signal_to_group, send_signal_to_process,
+};
+
+pub(crate) const O_ASYNC: usize = 0o20000;
+
+const POLL_IN: i32 = 1;
+const POLL_OUT: i32 = 2;
+const POLLIN: u64 = 0x001;
+const POLLOUT: u64 = 0x004;
+const POLLRDNORM: u64 = 0x040;
+const POLLWRNORM: u64 = 0x100;
+
+/// The signal-driven I/O settings of an open file.
+struct AsyncFile {
+    file: Weak<dyn FileLike>,
+    /// The fd the settings were last changed through, reported in `si_fd`.
+    fd: c_int,
+    /// The process, or the process group if negative, to signal.
+    owner: i32,
+    /// The signal to send, or 0 to send `SIGIO` without details.
+    sig: usize,
+    /// Whether `O_ASYNC` is set.
+    enabled: bool,
+    readable: bool,
+    writable: bool,
+}
+
+impl AsyncFile {
+    fn new(file: &Arc<dyn FileLike>, fd: c_int) -> Self {
+        Self {
+            file: Arc::downgrade(file),
+            fd,
+            owner: 0,
+            sig: 0,
+            enabled: false,
+            readable: false,
+            writable: false,
+        }
+    }
+}
+
+/// The open files that have had any of the settings changed, by the address
+/// of the file.
+static ASYNC_FILES: Mutex<BTreeMap<usize, AsyncFile>> = Mutex::new(BTreeMap::new());
+/// Whether any of them may have `O_ASYNC` set, so that syscalls need not
+/// take the lock otherwise.
+static ANY_ASYNC: AtomicBool = AtomicBool::new(false);
+
+fn key(file: &Arc<dyn FileLike>) -> usize {
+    Arc::as_ptr(file) as *const () as usize
+}
+
+/// Changes the settings of the file `fd` with `f`.
+///
+/// An entry keeps the memory of its file, so the address is not reused by
+/// another file while the entry is there.
+fn update(fd: c_int, f: impl FnOnce(&mut AsyncFile)) -> LinuxResult {
+    let file = get_file_like(fd)?;
+    let mut files = ASYNC_FILES.lock();
+    // Closed files are otherwise only forgotten while any has `O_ASYNC`.
+    files.retain(|_, entry| entry.file.strong_count() > 0);
+    let entry = files
+        .entry(key(&file))
+        .or_insert_with(|| AsyncFile::new(&file, fd));
+    entry.fd = fd;
+    f(entry);
+    if entry.enabled {
+        ANY_ASYNC.store(true, Ordering::Release);
+    }
+    // Only changes from now on are signalled.
+    if let Ok(state) = file.poll() {
+        entry.readable = state.readable;
+        entry.writable = state.writable;
+    }
+    Ok(())
+}
+
+/// Reads a setting of the file `fd` with `f`, or returns `default` if it has
+/// never been changed.
+fn query<T>(fd: c_int, default: T, f: impl FnOnce(&AsyncFile) -> T) -> LinuxResult<T> {
+    let file = get_file_like(fd)?;
+    let files = ASYNC_FILES.lock();
+    Ok(files.get(&key(&file)).map_or(default, f))
+}
+
+pub(crate) fn set_owner(fd: c_int, owner: i32) -> LinuxResult<isize> {
+    update(fd, |entry| entry.owner = owner)?;
+    Ok(0)
+}
+
+pub(crate) fn get_owner(fd: c_int) -> LinuxResult<isize> {
+    query(fd, 0, |entry| entry.owner as isize)
+}
+
+pub(crate) fn set_signal(fd: c_int, sig: usize) -> LinuxResult<isize> {
+    if sig > NSIG {
+        return Err(LinuxError::EINVAL);
+    }
+    update(fd, |entry| entry.sig = sig)?;
+    Ok(0)
+}
+
+pub(crate) fn get_signal(fd: c_int) -> LinuxResult<isize> {
+    query(fd, 0, |entry| entry.sig as isize)
+}
+
+pub(crate) fn set_async(fd: c_int, enabled: bool) -> LinuxResult {
+    if !enabled && !is_async(fd)? {
+        return Ok(());
+    }
+    update(fd, |entry| entry.enabled = enabled)
+}
+
+pub(crate) fn is_async(fd: c_int) -> LinuxResult<bool> {
+    query(fd, false, |entry| entry.enabled)
+}
+
+/// Sends the signal of `entry` to its owner for the event `code`.
+fn notify(entry: &AsyncFile, code: i32, band: u64) {
+    let info = if entry.sig == 0 {
+        SigInfo::new(SIGIO, SI_KERNEL)
+    } else {
+        SigInfo::new_poll(entry.sig, code, band, entry.fd)
+    };
+    match entry.owner {
+        0 => {}
+        1.. => send_signal_to_process(entry.owner as u64, info),
+        _ => send_signal_to_group(entry.owner.unsigned_abs() as u64, info),
+    }
+}
+
+/// Signals the owners of the files with `O_ASYNC` that have become ready
+/// since the last check, and forgets the files that have been closed.
+pub fn poll_async_files() {
+    if !ANY_ASYNC.load(Ordering::Acquire) {
+        return;
+    }
+    let mut files = ASYNC_FILES.lock();
+    files.retain(|_, entry| {
+        let Some(file) = entry.file.upgrade() else {
+            return false;
+        };
+        if !entry.enabled {
+            return true;
+        }
+        let Ok(state) = file.poll() else {
+            return true;
+        };
+        if state.readable && !entry.readable {
+            notify(entry, POLL_IN, POLLIN | POLLRDNORM);
+        }
+        if state.writable && !entry.writable {
+            notify(entry, POLL_OUT, POLLOUT | POLLWRNORM);
+        }
+        entry.readable = state.readable;
+        entry.writable = state.writable;
+        true
+    });
+    ANY_ASYNC.store(files.values().any(|entry| entry.enabled), Ordering::Release);
+}
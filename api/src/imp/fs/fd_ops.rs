@@ -1,26 +1,72 @@
 use core::ffi::c_int;
 
 use arceos_posix_api as api;
-use axerrno::LinuxResult;
+use axerrno::{LinuxError, LinuxResult};
 use axtask::{TaskExtMut, TaskExtRef, current};
 
+use super::fasync::{self, O_ASYNC};
+
+pub(crate) const O_CLOEXEC: c_int = 0o2000000;
+
+/// Sets whether `fd` is closed on exec, as the `*_CLOEXEC` flag of the call
+/// that made it asks.
+pub(crate) fn set_cloexec(fd: c_int, cloexec: bool) {
+    api::FD_TABLE.write().set_cloexec(fd as usize, cloexec);
+}
+
 pub fn sys_dup(old_fd: c_int) -> LinuxResult<isize> {
     // Ok(api::sys_dup(old_fd) as _)
     let new_fd = api::sys_dup(old_fd);
     if new_fd >= current().task_ext().get_fd_limit() as _ {
-        return Err(axerrno::LinuxError::EMFILE);
+        api::sys_close(new_fd);
+        return Err(LinuxError::EMFILE);
     }
     Ok(new_fd as _)
 }
 
-pub fn sys_dup3(old_fd: c_int, new_fd: c_int) -> LinuxResult<isize> {
-    Ok(api::sys_dup2(old_fd, new_fd) as _)
+pub fn sys_dup3(old_fd: c_int, new_fd: c_int, flags: c_int) -> LinuxResult<isize> {
+    if flags & !O_CLOEXEC != 0 || old_fd == new_fd {
+        return Err(LinuxError::EINVAL);
+    }
+    let fd = api::sys_dup2(old_fd, new_fd);
+    if fd >= 0 {
+        set_cloexec(fd, flags & O_CLOEXEC != 0);
+    }
+    Ok(fd as _)
 }
 
 pub fn sys_close(fd: c_int) -> LinuxResult<isize> {
     Ok(api::sys_close(fd) as _)
 }
 
+const F_GETFL: c_int = 3;
+const F_SETFL: c_int = 4;
+const F_SETOWN: c_int = 8;
+const F_GETOWN: c_int = 9;
+const F_SETSIG: c_int = 10;
+const F_GETSIG: c_int = 11;
+
 pub fn sys_fcntl(fd: c_int, cmd: c_int, arg: usize) -> LinuxResult<isize> {
-    Ok(api::sys_fcntl(fd, cmd, arg) as _)
+    match cmd {
+        F_SETOWN => fasync::set_owner(fd, arg as i32),
+        F_GETOWN => fasync::get_owner(fd),
+        F_SETSIG => fasync::set_signal(fd, arg),
+        F_GETSIG => fasync::get_signal(fd),
+        F_GETFL => {
+            let flags = api::sys_fcntl(fd, cmd, arg) as isize;
+            if flags >= 0 && fasync::is_async(fd)? {
+                Ok(flags | O_ASYNC as isize)
+            } else {
+                Ok(flags)
+            }
+        }
+        F_SETFL => {
+            let ret = api::sys_fcntl(fd, cmd, arg);
+            if ret >= 0 {
+                fasync::set_async(fd, arg & O_ASYNC != 0)?;
+            }
+            Ok(ret as _)
+        }
+        _ => Ok(api::sys_fcntl(fd, cmd, arg) as _),
+    }
 }
@@ -0,0 +1,184 @@
+//! FIFOs, or named pipes, made with `mknod`.
+//!
+//! Like symbolic links, they are kept here by path with an empty placeholder
+//! file in the filesystem. Opening one gives an end of the pipe it stands
+//! for, made when it is first opened and shared until all its ends are
+//! closed.
+
+use alloc::{
+    collections::BTreeMap,
+    string::String,
+    sync::{Arc, Weak},
+};
+use core::ffi::c_char;
+
+use arceos_posix_api::{FileLike, Pipe};
+use axerrno::{LinuxError, LinuxResult};
+use axsync::Mutex;
+use axtask::{TaskExtRef, current};
+use starry_core::signal::signal_pending;
+
+use super::link;
+use crate::ptr::UserConstPtr;
+
+const S_IFMT: u32 = 0o170000;
+const S_IFIFO: u32 = 0o010000;
+const S_IFCHR: u32 = 0o020000;
+const S_IFBLK: u32 = 0o060000;
+const S_IFREG: u32 = 0o100000;
+const S_IFSOCK: u32 = 0o140000;
+
+const O_ACCMODE: i32 = 0o3;
+const O_RDONLY: i32 = 0o0;
+const O_WRONLY: i32 = 0o1;
+const O_CREAT: i32 = 0o100;
+const O_EXCL: i32 = 0o200;
+const O_NONBLOCK: i32 = 0o4000;
+
+struct Fifo {
+    /// The permission bits it was made with.
+    perm: u32,
+    read_end: Weak<Pipe>,
+    write_end: Weak<Pipe>,
+    /// How many times it has been opened for reading and for writing, so that
+    /// an open waiting for the other side sees one that has come and gone.
+    readers: usize,
+    writers: usize,
+}
+
+impl Fifo {
+    /// The end for reading if `readable`, or else for writing, made along
+    /// with a new pipe if neither end is open.
+    fn end(&mut self, readable: bool) -> LinuxResult<Arc<Pipe>> {
+        let (end, other) = if readable {
+            (&mut self.read_end, &self.write_end)
+        } else {
+            (&mut self.write_end, &self.read_end)
+        };
+        if let Some(end) = end.upgrade() {
+            return Ok(end);
+        }
+        let pipe = match other.upgrade() {
+            Some(other) => Arc::new(other.other_end()),
+            None => {
+                let (read_end, write_end) = Pipe::new(current().task_ext().cred().uid)?;
+                Arc::new(if readable { read_end } else { write_end })
+            }
+        };
+        *end = Arc::downgrade(&pipe);
+        Ok(pipe)
+    }
+}
+
+static FIFOS: Mutex<BTreeMap<String, Fifo>> = Mutex::new(BTreeMap::new());
+
+/// The permission bits of the FIFO at the canonical path `path`, if it is
+/// one.
+pub(super) fn fifo_perm(path: &str) -> Option<u32> {
+    FIFOS.lock().get(path).map(|fifo| fifo.perm)
+}
+
+/// Forgets the FIFO at `path` once its placeholder has been removed.
+pub(super) fn remove(path: &str) {
+    FIFOS.lock().remove(path);
+}
+
+/// Moves the FIFOs at `old` and under it along with their placeholders.
+pub(super) fn rename(old: &str, new: &str) {
+    super::rename_entries(&mut FIFOS.lock(), old, new);
+}
+
+/// Opens the FIFO at the canonical `path`, or returns `None` if it is not
+/// one.
+///
+/// Unless `O_NONBLOCK` is set, opening one side waits for the other to be
+/// opened. Without a reader, a non-blocking open for writing fails with
+/// `ENXIO`. Opening for both reading and writing is not supported, as an end
+/// of a pipe only goes one way.
+pub(super) fn open_fifo(path: &str, flags: i32) -> Option<LinuxResult<Arc<dyn FileLike>>> {
+    let readable = match flags & O_ACCMODE {
+        O_RDONLY => true,
+        O_WRONLY => false,
+        _ => return Some(Err(LinuxError::EINVAL)),
+    };
+    let (pipe, opened) = {
+        let mut fifos = FIFOS.lock();
+        let fifo = fifos.get_mut(path)?;
+        if flags & (O_CREAT | O_EXCL) == O_CREAT | O_EXCL {
+            return Some(Err(LinuxError::EEXIST));
+        }
+        if !readable && flags & O_NONBLOCK != 0 && fifo.read_end.strong_count() == 0 {
+            return Some(Err(LinuxError::ENXIO));
+        }
+        let pipe = match fifo.end(readable) {
+            Ok(pipe) => pipe,
+            Err(err) => return Some(Err(err)),
+        };
+        if readable {
+            fifo.readers += 1;
+            (pipe, fifo.writers)
+        } else {
+            fifo.writers += 1;
+            (pipe, fifo.readers)
+        }
+    };
+    if flags & O_NONBLOCK == 0 {
+        loop {
+            {
+                let fifos = FIFOS.lock();
+                let Some(fifo) = fifos.get(path) else {
+                    break;
+                };
+                let (other, others) = if readable {
+                    (&fifo.write_end, fifo.writers)
+                } else {
+                    (&fifo.read_end, fifo.readers)
+                };
+                if other.strong_count() > 0 || others != opened {
+                    break;
+                }
+            }
+            if signal_pending() {
+                return Some(Err(LinuxError::EINTR));
+            }
+            axtask::yield_now();
+        }
+    }
+    Some(Ok(pipe))
+}
+
+/// Makes a regular file or a FIFO at `path`. Devices and sockets cannot be
+/// kept on the filesystems here, so making them fails with `EPERM`.
+pub fn sys_mknodat(
+    dirfd: i32,
+    path: UserConstPtr<c_char>,
+    mode: u32,
+    _dev: u64,
+) -> LinuxResult<isize> {
+    let path = link::resolve_at(dirfd, path, false)?;
+    match mode & S_IFMT {
+        0 | S_IFREG => {
+            axfs::api::File::create_new(&path)?;
+        }
+        S_IFIFO => {
+            axfs::api::File::create_new(&path)?;
+            let fifo = Fifo {
+                perm: mode & 0o7777,
+                read_end: Weak::new(),
+                write_end: Weak::new(),
+                readers: 0,
+                writers: 0,
+            };
+            FIFOS.lock().insert(path, fifo);
+        }
+        S_IFCHR | S_IFBLK | S_IFSOCK => return Err(LinuxError::EPERM),
+        _ => return Err(LinuxError::EINVAL),
+    }
+    Ok(0)
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_mknod(path: UserConstPtr<c_char>, mode: u32, dev: u64) -> LinuxResult<isize> {
+    use arceos_posix_api::AT_FDCWD;
+    sys_mknodat(AT_FDCWD as _, path, mode, dev)
+}
@@ -1,27 +1,101 @@
+use alloc::{format, string::String};
 use core::ffi::{c_char, c_void};
 
 use arceos_posix_api::{self as api, ctypes::mode_t};
-use axerrno::LinuxResult;
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{TaskExtRef, current};
+use starry_core::cred::{R_OK, W_OK, X_OK};
 
+use super::{
+    attr,
+    dev::open_device,
+    fd_ops::{O_CLOEXEC, set_cloexec},
+    fifo::open_fifo,
+    link,
+    opath::PathFd,
+    proc::open_proc_file,
+    stat::stat_path,
+    sysctl::open_tunable,
+};
 use crate::ptr::{PtrWrapper, UserConstPtr, UserPtr};
 
+const O_ACCMODE: i32 = 0o3;
+const O_RDONLY: i32 = 0o0;
+const O_WRONLY: i32 = 0o1;
+const O_CREAT: i32 = 0o100;
+const O_EXCL: i32 = 0o200;
+const O_TRUNC: i32 = 0o1000;
+const O_NONBLOCK: i32 = 0o4000;
+#[cfg(not(target_arch = "aarch64"))]
+const O_DIRECTORY: i32 = 0o200000;
+#[cfg(target_arch = "aarch64")]
+const O_DIRECTORY: i32 = 0o40000;
+#[cfg(not(target_arch = "aarch64"))]
+const O_NOFOLLOW: i32 = 0o400000;
+#[cfg(target_arch = "aarch64")]
+const O_NOFOLLOW: i32 = 0o100000;
+const O_PATH: i32 = 0o10000000;
+
+const AT_SYMLINK_NOFOLLOW: u32 = 0x100;
+const AT_EMPTY_PATH: u32 = 0x1000;
+
+/// The most buffers `readv` and `writev` take.
+const UIO_MAXIOV: usize = 1024;
+
+/// Reads into the buffer up to the first byte of it that cannot be written,
+/// so that the count read falls short as it does on Linux.
 pub fn sys_read(fd: i32, buf: UserPtr<c_void>, count: usize) -> LinuxResult<isize> {
-    let buf = buf.get_as_bytes(count)?;
+    let (buf, count) = buf.get_as_partial_bytes(count)?;
     Ok(api::sys_read(fd, buf, count))
 }
 
+/// Writes the buffer up to the first byte of it that cannot be read.
 pub fn sys_write(fd: i32, buf: UserConstPtr<c_void>, count: usize) -> LinuxResult<isize> {
-    let buf = buf.get_as_bytes(count)?;
+    let (buf, count) = buf.get_as_partial_bytes(count)?;
     Ok(api::sys_write(fd, buf, count))
 }
 
+/// Does `transfer` on each of the `iocnt` buffers at `iov` in turn, given
+/// its address and length, until one is not done in full. A buffer that
+/// cannot be accessed, or any other error after some bytes have been
+/// transferred, ends it with the count of those.
+fn transfer_iovecs(
+    iov: UserConstPtr<api::ctypes::iovec>,
+    iocnt: i32,
+    mut transfer: impl FnMut(usize, usize) -> LinuxResult<isize>,
+) -> LinuxResult<isize> {
+    if iocnt < 0 || iocnt as usize > UIO_MAXIOV {
+        return Err(LinuxError::EINVAL);
+    }
+    let mut done = 0;
+    for iov in iov.get_as_slice(iocnt as usize)? {
+        if iov.iov_len == 0 {
+            continue;
+        }
+        // The file fails with a negative count rather than an error.
+        let len = match transfer(iov.iov_base as usize, iov.iov_len) {
+            Ok(len) if len >= 0 => len,
+            Ok(_) | Err(_) if done > 0 => break,
+            result => return result,
+        };
+        done += len;
+        if (len as usize) < iov.iov_len {
+            break;
+        }
+    }
+    Ok(done)
+}
+
+pub fn sys_readv(fd: i32, iov: UserConstPtr<api::ctypes::iovec>, iocnt: i32) -> LinuxResult<isize> {
+    transfer_iovecs(iov, iocnt, |base, len| sys_read(fd, base.into(), len))
+}
+
 pub fn sys_writev(
     fd: i32,
     iov: UserConstPtr<api::ctypes::iovec>,
     iocnt: i32,
 ) -> LinuxResult<isize> {
-    let iov = iov.get_as_bytes(iocnt as _)?;
-    unsafe { Ok(api::sys_writev(fd, iov, iocnt)) }
+    transfer_iovecs(iov, iocnt, |base, len| sys_write(fd, base.into(), len))
 }
 
 pub fn sys_openat(
@@ -30,15 +104,184 @@ pub fn sys_openat(
     flags: i32,
     modes: mode_t,
 ) -> LinuxResult<isize> {
-    let path = path.get_as_null_terminated()?;
+    let fd = openat(dirfd, path, flags, modes)?;
+    if fd >= 0 {
+        set_cloexec(fd as _, flags & O_CLOEXEC != 0);
+    }
+    Ok(fd)
+}
+
+/// Opens the file as `sys_openat` does, but leaves close-on-exec unset.
+fn openat(dirfd: i32, path: UserConstPtr<c_char>, flags: i32, modes: mode_t) -> LinuxResult<isize> {
+    let path = path.get_as_path()?;
+    if let Ok(file_path) = api::handle_file_path(dirfd as _, Some(path.as_ptr() as _), false) {
+        let link_path = link::resolve(&file_path, false)?;
+        let nofollow = flags & O_NOFOLLOW != 0;
+        if flags & O_PATH != 0 {
+            // Other flags than these do not matter without the file opened.
+            let path = if nofollow {
+                link_path
+            } else {
+                link::resolve(&file_path, true)?
+            };
+            let file = PathFd::open(path, flags & O_DIRECTORY != 0)?;
+            return Ok(api::add_file_like(file)? as _);
+        }
+        if nofollow && link::is_symlink(&link_path) {
+            return Err(LinuxError::ELOOP);
+        }
+        // Resolved again from the start, so that the links on the way to the
+        // last one count towards the same limit.
+        let file_path = link::resolve(&file_path, true)?;
+        if let Some(fifo) = open_fifo(&file_path, flags) {
+            return Ok(api::add_file_like(fifo?)? as _);
+        }
+        if let Some(dev) = open_device(file_path.as_str()) {
+            let dev = dev?;
+            dev.set_nonblocking(flags & O_NONBLOCK != 0)?;
+            return Ok(api::add_file_like(dev)? as _);
+        }
+        if let Some(tunable) = open_tunable(file_path.as_str(), flags) {
+            return Ok(api::add_file_like(tunable?)? as _);
+        }
+        if let Some(file) = open_proc_file(file_path.as_str(), flags) {
+            return Ok(api::add_file_like(file?)? as _);
+        }
+        check_open_access(&file_path, flags)?;
+        let created = flags & O_CREAT != 0 && axfs::api::metadata(&file_path).is_err();
+        // The path is opened whole, as `dirfd` may not be a directory that
+        // was opened for reading.
+        let c_path = format!("{file_path}\0");
+        let fd = api::sys_openat(api::AT_FDCWD as _, c_path.as_ptr() as _, flags, modes);
+        if created && fd >= 0 {
+            attr::set_created(&file_path, modes);
+        }
+        return Ok(fd as _);
+    }
     Ok(api::sys_openat(dirfd, path.as_ptr(), flags, modes) as _)
 }
 
+/// Checks that the caller may open the file at the canonical `path` as
+/// `flags` asks: read or write it as the access mode and `O_TRUNC` need if it
+/// exists, or else write to and search its directory to create it.
+///
+/// Errors other than these are left for the open to report.
+fn check_open_access(path: &str, flags: i32) -> LinuxResult {
+    let (statbuf, mask) = match stat_path(path, false) {
+        Ok(_) if flags & (O_CREAT | O_EXCL) == O_CREAT | O_EXCL => return Ok(()),
+        Ok(statbuf) => {
+            let mask = match flags & O_ACCMODE {
+                O_RDONLY => R_OK,
+                O_WRONLY => W_OK,
+                _ => R_OK | W_OK,
+            };
+            // Truncating is writing, whatever the access mode.
+            let truncate = if flags & O_TRUNC != 0 { W_OK } else { 0 };
+            (statbuf, mask | truncate)
+        }
+        Err(LinuxError::ENOENT) if flags & O_CREAT != 0 => {
+            let parent = match path.rfind('/') {
+                Some(0) | None => "/",
+                Some(end) => &path[..end],
+            };
+            match stat_path(parent, true) {
+                Ok(statbuf) => (statbuf, W_OK | X_OK),
+                Err(_) => return Ok(()),
+            }
+        }
+        Err(_) => return Ok(()),
+    };
+    let cred = current().task_ext().cred();
+    if !cred.permits(statbuf.st_mode, statbuf.st_uid, statbuf.st_gid, mask) {
+        return Err(LinuxError::EACCES);
+    }
+    Ok(())
+}
+
+/// Finds the program `execveat` is to run: `path` relative to `dirfd`, or
+/// with `AT_EMPTY_PATH` and an empty `path`, the file open at `dirfd`.
+///
+/// It is checked to be a regular file here, as once the old program is torn
+/// down there is nothing left to return an error to.
+pub(crate) fn exec_path(dirfd: i32, path: UserConstPtr<c_char>, flags: u32) -> LinuxResult<String> {
+    if flags & !(AT_SYMLINK_NOFOLLOW | AT_EMPTY_PATH) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let path = path.get_as_path()?;
+    let path = if path.is_empty() {
+        if flags & AT_EMPTY_PATH == 0 {
+            return Err(LinuxError::ENOENT);
+        }
+        // The file may have been opened with `O_PATH`, as by `fexecve`.
+        let file = api::get_file_like(dirfd)?.into_any();
+        match file.downcast::<api::File>() {
+            Ok(file) => String::from(file.path()),
+            Err(file) => match file.downcast::<PathFd>() {
+                Ok(file) => String::from(file.path()),
+                Err(_) => return Err(LinuxError::EACCES),
+            },
+        }
+    } else {
+        // A relative path needs `dirfd` to be an open directory.
+        if path[0] != b'/' as c_char
+            && dirfd != api::AT_FDCWD as i32
+            && !api::get_file_like(dirfd)?.into_any().is::<api::Directory>()
+        {
+            return Err(LinuxError::ENOTDIR);
+        }
+        let path = api::handle_file_path(dirfd as _, Some(path.as_ptr() as _), false)?;
+        let link_path = link::resolve(&path, false)?;
+        if flags & AT_SYMLINK_NOFOLLOW != 0 && link::is_symlink(&link_path) {
+            return Err(LinuxError::ELOOP);
+        }
+        link::resolve(&path, true)?
+    };
+    if !axfs::api::metadata(&path)?.is_file() {
+        return Err(LinuxError::EACCES);
+    }
+    Ok(path)
+}
+
 pub fn sys_open(path: UserConstPtr<c_char>, flags: i32, modes: mode_t) -> LinuxResult<isize> {
     use arceos_posix_api::AT_FDCWD;
     sys_openat(AT_FDCWD as _, path, flags, modes)
 }
 
+/// Creates the file at `path`, or truncates it if it exists, and opens it for
+/// writing.
+#[cfg(target_arch = "x86_64")]
+pub fn sys_creat(path: UserConstPtr<c_char>, modes: mode_t) -> LinuxResult<isize> {
+    sys_open(path, O_CREAT | O_WRONLY | O_TRUNC, modes)
+}
+
 pub fn sys_lseek(fd: i32, offset: isize, whence: i32) -> LinuxResult<isize> {
     Ok(api::sys_lseek(fd, offset as _, whence) as _)
 }
+
+const SYNC_FILE_RANGE_WAIT_BEFORE: u32 = 1;
+const SYNC_FILE_RANGE_WRITE: u32 = 2;
+const SYNC_FILE_RANGE_WAIT_AFTER: u32 = 4;
+
+/// Writes back the part of a file from `offset` of `nbytes` bytes, or to the
+/// end of the file if `nbytes` is 0.
+///
+/// There is no page cache, so nothing is dirty beyond what the filesystem
+/// buffers itself, and that can only be flushed for the whole file, whatever
+/// the range. Waiting is a no-op since writeback completes before this
+/// returns.
+pub fn sys_sync_file_range(fd: i32, offset: i64, nbytes: i64, flags: u32) -> LinuxResult<isize> {
+    let valid = SYNC_FILE_RANGE_WAIT_BEFORE | SYNC_FILE_RANGE_WRITE | SYNC_FILE_RANGE_WAIT_AFTER;
+    if flags & !valid != 0 || offset < 0 || nbytes < 0 || offset.checked_add(nbytes).is_none() {
+        return Err(LinuxError::EINVAL);
+    }
+    let file = api::get_file_like(fd)?.into_any();
+    let file = match file.downcast::<api::File>() {
+        Ok(file) => file,
+        Err(file) if file.is::<api::Directory>() => return Ok(0),
+        Err(_) => return Err(LinuxError::ESPIPE),
+    };
+    if flags & SYNC_FILE_RANGE_WRITE != 0 {
+        file.inner().lock().flush()?;
+    }
+    Ok(0)
+}
@@ -0,0 +1,152 @@
+//! Symbolic links.
+//!
+//! The filesystems underneath have no symbolic links, so they are kept here by
+//! the canonical path of the link. An empty file stands in for each link in
+//! the filesystem, so that its name shows up in its directory and cannot be
+//! taken by another file.
+
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+};
+use core::ffi::c_char;
+
+use axerrno::{LinuxError, LinuxResult};
+use axsync::Mutex;
+
+use super::proc::fd_link;
+use crate::ptr::{PATH_MAX, PtrWrapper, UserConstPtr, UserPtr};
+
+/// The most links followed while resolving a path before giving up with
+/// `ELOOP`.
+const MAXSYMLINKS: usize = 40;
+
+/// The target of each link, by the path of the link.
+static SYMLINKS: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+/// Resolves the links in the canonical path `path`. The last component is
+/// only followed with `follow` set, or if the path ends with a slash.
+pub(crate) fn resolve(path: &str, follow: bool) -> LinuxResult<String> {
+    let links = SYMLINKS.lock();
+    if links.is_empty() {
+        return Ok(path.to_string());
+    }
+    let follow = follow || path.ends_with('/');
+    let mut path = axfs::api::canonicalize(path)?;
+    let mut followed = 0;
+    loop {
+        let Some((end, target)) = path
+            .match_indices('/')
+            .skip(1)
+            .map(|(end, _)| end)
+            .chain([path.len()])
+            .filter(|&end| follow || end < path.len())
+            .find_map(|end| links.get(&path[..end]).map(|target| (end, target)))
+        else {
+            return Ok(path);
+        };
+        followed += 1;
+        if followed > MAXSYMLINKS {
+            return Err(LinuxError::ELOOP);
+        }
+        let rest = &path[end..];
+        let target = if target.starts_with('/') {
+            format!("{target}{rest}")
+        } else {
+            let parent = &path[..path[..end].rfind('/').unwrap()];
+            format!("{parent}/{target}{rest}")
+        };
+        if target.len() >= PATH_MAX {
+            return Err(LinuxError::ENAMETOOLONG);
+        }
+        path = axfs::api::canonicalize(&target)?;
+    }
+}
+
+/// Resolves `path` relative to `dirfd` as the `*at` calls do, into the
+/// canonical path of the file it names. A link in the last component is only
+/// followed with `follow` set, as without `AT_SYMLINK_NOFOLLOW`.
+pub(super) fn resolve_at(
+    dirfd: i32,
+    path: UserConstPtr<c_char>,
+    follow: bool,
+) -> LinuxResult<String> {
+    let path = path.get_as_path()?;
+    let path = arceos_posix_api::handle_file_path(dirfd as _, Some(path.as_ptr() as _), false)?;
+    resolve(&path, follow)
+}
+
+/// Makes a link at the canonical `path` that points to `target`.
+pub(super) fn create(path: String, target: String) -> LinuxResult {
+    axfs::api::File::create_new(&path)?;
+    SYMLINKS.lock().insert(path, target);
+    Ok(())
+}
+
+/// The target of the link at the canonical path `path`, if it is one.
+pub(crate) fn read_link(path: &str) -> Option<String> {
+    SYMLINKS.lock().get(path).cloned()
+}
+
+pub(crate) fn is_symlink(path: &str) -> bool {
+    SYMLINKS.lock().contains_key(path)
+}
+
+/// Forgets the link at `path` once its placeholder has been removed.
+pub(crate) fn remove(path: &str) {
+    SYMLINKS.lock().remove(path);
+}
+
+/// Moves the links at `old` and under it along with their placeholders.
+pub(super) fn rename(old: &str, new: &str) {
+    super::rename_entries(&mut SYMLINKS.lock(), old, new);
+}
+
+/// Creates a link at `linkpath` that points to `target`, which is not checked
+/// to exist.
+pub fn sys_symlinkat(
+    target: UserConstPtr<c_char>,
+    newdirfd: i32,
+    linkpath: UserConstPtr<c_char>,
+) -> LinuxResult<isize> {
+    let target = target.get_as_str()?;
+    if target.is_empty() {
+        return Err(LinuxError::ENOENT);
+    }
+    let path = resolve_at(newdirfd, linkpath, false)?;
+    create(path, target.to_string())?;
+    Ok(0)
+}
+
+/// Stores the target of the link at `path`, truncated to `size` bytes and not
+/// NUL-terminated, to `buf`.
+pub fn sys_readlinkat(
+    dirfd: i32,
+    path: UserConstPtr<c_char>,
+    buf: UserPtr<u8>,
+    size: usize,
+) -> LinuxResult<isize> {
+    if size == 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let path = resolve_at(dirfd, path, false)?;
+    let Some(target) = read_link(&path).or_else(|| fd_link(&path)) else {
+        axfs::api::metadata(&path)?;
+        return Err(LinuxError::EINVAL);
+    };
+    let len = target.len().min(size);
+    let buf = buf.get_as_bytes(len)?;
+    unsafe { core::ptr::copy_nonoverlapping(target.as_ptr(), buf, len) };
+    Ok(len as _)
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_readlink(
+    path: UserConstPtr<c_char>,
+    buf: UserPtr<u8>,
+    size: usize,
+) -> LinuxResult<isize> {
+    use arceos_posix_api::AT_FDCWD;
+    sys_readlinkat(AT_FDCWD as _, path, buf, size)
+}
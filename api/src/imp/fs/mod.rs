@@ -1,13 +1,61 @@
+use alloc::{collections::BTreeMap, format, string::String, vec::Vec};
+
+mod acct;
+mod aio;
+mod attr;
 mod ctl;
+mod dev;
+mod eventfd;
+mod fasync;
 mod fd_ops;
+mod fifo;
 mod io;
+mod link;
 mod mount;
+mod opath;
 mod pipe;
+mod poll;
+mod proc;
+mod signalfd;
 mod stat;
+mod sysctl;
+mod timerfd;
+mod tty;
 
+pub use self::acct::*;
+pub use self::aio::*;
+#[cfg(target_arch = "x86_64")]
+pub use self::attr::{sys_chmod, sys_chown, sys_lchown};
+pub use self::attr::{sys_fchmodat, sys_fchownat, sys_umask};
 pub use self::ctl::*;
+pub use self::eventfd::*;
+pub use self::fasync::poll_async_files;
 pub use self::fd_ops::*;
+pub use self::fifo::*;
 pub use self::io::*;
+#[cfg(target_arch = "x86_64")]
+pub use self::link::sys_readlink;
+pub use self::link::{sys_readlinkat, sys_symlinkat};
 pub use self::mount::*;
 pub use self::pipe::*;
+pub use self::poll::*;
+pub use self::signalfd::*;
 pub use self::stat::*;
+pub use self::timerfd::*;
+
+/// Moves what `table` keeps for the canonical path `old`, and for the paths
+/// under it, to `new`.
+fn rename_entries<V>(table: &mut BTreeMap<String, V>, old: &str, new: &str) {
+    let moved = table
+        .keys()
+        .filter(|path| {
+            path.strip_prefix(old)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    for path in moved {
+        let value = table.remove(&path).unwrap();
+        table.insert(format!("{new}{}", &path[old.len()..]), value);
+    }
+}
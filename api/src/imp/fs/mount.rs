@@ -1,21 +1,43 @@
 use alloc::vec::Vec;
-use arceos_posix_api::{AT_FDCWD, FilePath, handle_file_path};
+use arceos_posix_api::{self as api, AT_FDCWD, FilePath, handle_file_path};
 use axerrno::{LinuxError, LinuxResult};
 use axsync::Mutex;
 use core::ffi::{c_char, c_void};
 
-use crate::ptr::UserConstPtr;
+use super::link;
+use crate::ptr::{PtrWrapper, UserConstPtr, UserPtr};
+
+/// The mount flags kept for a mount, which `statfs` reports as `ST_RDONLY`,
+/// `ST_NOSUID`, `ST_NODEV` and `ST_NOEXEC` with the same bits.
+const MS_RDONLY: u32 = 1;
+const MS_NOSUID: u32 = 2;
+const MS_NODEV: u32 = 4;
+const MS_NOEXEC: u32 = 8;
+
+const MSDOS_SUPER_MAGIC: i64 = 0x4d44;
+const TMPFS_MAGIC: i64 = 0x01021994;
+const RAMFS_MAGIC: i64 = 0x858458f6;
+const PROC_SUPER_MAGIC: i64 = 0x9fa0;
+const SYSFS_MAGIC: i64 = 0x62656572;
+
+/// The filesystems mounted at boot, besides the FAT root.
+const BOOT_MOUNTS: &[(&str, i64)] = &[
+    ("/dev", TMPFS_MAGIC),
+    ("/tmp", RAMFS_MAGIC),
+    ("/proc", PROC_SUPER_MAGIC),
+    ("/sys", SYSFS_MAGIC),
+];
 
 pub fn sys_mount(
     source: UserConstPtr<c_char>,
     target: UserConstPtr<c_char>,
     fs_type: UserConstPtr<c_char>,
-    _flags: i32,
+    flags: u32,
     _data: UserConstPtr<c_void>,
 ) -> LinuxResult<isize> {
     info!("sys_mount");
-    let source = source.get_as_null_terminated()?;
-    let target = target.get_as_null_terminated()?;
+    let source = source.get_as_path()?;
+    let target = target.get_as_path()?;
     let fs_type = fs_type.get_as_str()?;
     let device_path = handle_file_path(AT_FDCWD, Some(source.as_ptr() as _), false)?;
     let mount_path = handle_file_path(AT_FDCWD, Some(target.as_ptr() as _), true)?;
@@ -39,7 +61,8 @@ pub fn sys_mount(
         return Err(LinuxError::EPERM);
     }
 
-    if !mount_fat_fs(&device_path, &mount_path) {
+    let flags = flags & (MS_RDONLY | MS_NOSUID | MS_NODEV | MS_NOEXEC);
+    if !mount_fat_fs(&device_path, &mount_path, flags) {
         debug!("mount error");
         return Err(LinuxError::EPERM);
     }
@@ -48,7 +71,7 @@ pub fn sys_mount(
 
 pub fn sys_umount2(target: UserConstPtr<c_char>, flags: i32) -> LinuxResult<isize> {
     info!("sys_umount2");
-    let target = target.get_as_null_terminated()?;
+    let target = target.get_as_path()?;
     let mount_path = handle_file_path(AT_FDCWD, Some(target.as_ptr() as _), true)?;
     if flags != 0 {
         debug!("flags unimplemented");
@@ -73,10 +96,12 @@ pub struct MountedFs {
     //pub inner: Arc<Mutex<FATFileSystem>>,
     pub device: FilePath,
     pub mnt_dir: FilePath,
+    /// The `MS_*` flags it was mounted with.
+    pub flags: u32,
 }
 
 impl MountedFs {
-    pub fn new(device: &FilePath, mnt_dir: &FilePath) -> Self {
+    pub fn new(device: &FilePath, mnt_dir: &FilePath, flags: u32) -> Self {
         assert!(
             device.is_file() && mnt_dir.is_dir(),
             "device must be a file and mnt_dir must be a dir"
@@ -84,6 +109,7 @@ impl MountedFs {
         Self {
             device: device.clone(),
             mnt_dir: mnt_dir.clone(),
+            flags,
         }
     }
     #[allow(unused)]
@@ -101,13 +127,15 @@ impl MountedFs {
 static MOUNTED: Mutex<Vec<MountedFs>> = Mutex::new(Vec::new());
 
 /// Mount a fatfs device
-pub fn mount_fat_fs(device_path: &FilePath, mount_path: &FilePath) -> bool {
+pub fn mount_fat_fs(device_path: &FilePath, mount_path: &FilePath, flags: u32) -> bool {
     // device_path needs symlink lookup, but mount_path does not
     // only opened files will be added to the symlink table for now, so do not convert now
     // debug!("mounting {} to {}", device_path.path(), mount_path.path());
     // if let Some(true_device_path) = real_path(device_path) {
     if mount_path.exists() {
-        MOUNTED.lock().push(MountedFs::new(device_path, mount_path));
+        MOUNTED
+            .lock()
+            .push(MountedFs::new(device_path, mount_path, flags));
         info!(
             "mounted {} to {}",
             device_path.as_str(),
@@ -136,3 +164,91 @@ pub fn check_mounted(path: &FilePath) -> bool {
     let mounted = MOUNTED.lock();
     mounted.iter().any(|m| path.starts_with(&m.mnt_dir()))
 }
+
+/// Whether `path` is `dir` or in it.
+fn is_within(path: &str, dir: &str) -> bool {
+    let dir = dir.trim_end_matches('/');
+    path.strip_prefix(dir)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// `struct statfs`.
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct StatFs {
+    f_type: i64,
+    f_bsize: i64,
+    f_blocks: u64,
+    f_bfree: u64,
+    f_bavail: u64,
+    f_files: u64,
+    f_ffree: u64,
+    f_fsid: [i32; 2],
+    f_namelen: i64,
+    f_frsize: i64,
+    f_flags: i64,
+    f_spare: [i64; 4],
+}
+
+impl StatFs {
+    fn new(f_type: i64, flags: u32) -> Self {
+        Self {
+            f_type,
+            f_bsize: 4096,
+            f_namelen: 255,
+            f_frsize: 4096,
+            f_flags: flags as _,
+            ..Default::default()
+        }
+    }
+}
+
+/// Describes the filesystem the canonical path `path` is on. The sizes of
+/// the filesystems are not known, so no blocks or inodes are reported.
+fn statfs_of(path: &str) -> StatFs {
+    let (f_type, flags) = match MOUNTED
+        .lock()
+        .iter()
+        .filter(|m| is_within(path, &m.mnt_dir))
+        .max_by_key(|m| m.mnt_dir.len())
+    {
+        Some(m) => (MSDOS_SUPER_MAGIC, m.flags),
+        None => {
+            let f_type = BOOT_MOUNTS
+                .iter()
+                .find(|(dir, _)| is_within(path, dir))
+                .map_or(MSDOS_SUPER_MAGIC, |&(_, f_type)| f_type);
+            (f_type, 0)
+        }
+    };
+    StatFs::new(f_type, flags)
+}
+
+/// Whether the canonical path `path` is on a filesystem mounted read-only.
+pub(super) fn is_read_only(path: &str) -> bool {
+    statfs_of(path).f_flags & MS_RDONLY as i64 != 0
+}
+
+pub fn sys_statfs(path: UserConstPtr<c_char>, buf: UserPtr<StatFs>) -> LinuxResult<isize> {
+    let path = path.get_as_path()?;
+    let path = handle_file_path(AT_FDCWD, Some(path.as_ptr() as _), false)?;
+    let path = link::resolve(&path, true)?;
+    axfs::api::metadata(&path)?;
+    unsafe { *buf.get()? = statfs_of(&path) };
+    Ok(0)
+}
+
+/// Describes the filesystem the file `fd` is on. Pipes, sockets and other
+/// files that are not on a filesystem all report an unnamed one.
+pub fn sys_fstatfs(fd: i32, buf: UserPtr<StatFs>) -> LinuxResult<isize> {
+    let file = api::get_file_like(fd)?.into_any();
+    let statfs = match file.downcast::<api::File>() {
+        Ok(file) => statfs_of(file.path()),
+        Err(file) => match file.downcast::<api::Directory>() {
+            Ok(dir) => statfs_of(dir.path()),
+            Err(_) => StatFs::new(0, 0),
+        },
+    };
+    unsafe { *buf.get()? = statfs };
+    Ok(0)
+}
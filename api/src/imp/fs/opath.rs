@@ -0,0 +1,70 @@
+//! Descriptors opened with `O_PATH`, which only stand for a place in the
+//! filesystem.
+//!
+//! Nothing is opened for one, so it cannot be read or written. It can be
+//! given to the calls that only need to know which file is meant: `fstat`,
+//! `fchdir`, `execveat`, and the `*at` calls as the directory to start from.
+
+use alloc::{string::String, sync::Arc};
+
+use arceos_posix_api::{FileLike, ctypes};
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+
+use super::stat::stat_path;
+
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+
+pub(super) struct PathFd {
+    /// The canonical path, with the links in it resolved. The last component
+    /// may be a link itself.
+    path: String,
+    is_dir: bool,
+}
+
+impl PathFd {
+    /// Makes one for the file at the canonical `path`, which must exist.
+    /// With `directory`, it must be a directory.
+    pub(super) fn open(path: String, directory: bool) -> LinuxResult<Arc<Self>> {
+        let is_dir = stat_path(&path, false)?.st_mode & S_IFMT == S_IFDIR;
+        if directory && !is_dir {
+            return Err(LinuxError::ENOTDIR);
+        }
+        Ok(Arc::new(Self { path, is_dir }))
+    }
+
+    pub(super) fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl FileLike for PathFd {
+    fn read(&self, _buf: &mut [u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EBADF)
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EBADF)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        stat_path(&self.path, false)
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Err(LinuxError::EBADF)
+    }
+
+    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+        Err(LinuxError::EBADF)
+    }
+
+    fn dir_path(&self) -> Option<&str> {
+        self.is_dir.then_some(self.path.as_str())
+    }
+}
@@ -1,12 +1,88 @@
-use core::ffi::c_int;
+use core::{ffi::c_int, slice};
 
-use arceos_posix_api as api;
-use axerrno::LinuxResult;
+use arceos_posix_api::{self as api, FileLike, Pipe, ctypes};
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{TaskExtRef, current};
 
-use crate::ptr::{PtrWrapper, UserPtr};
+use super::fd_ops::{O_CLOEXEC, set_cloexec};
+use crate::ptr::{PtrWrapper, UserConstPtr, UserPtr};
 
-pub fn sys_pipe2(fds: UserPtr<i32>) -> LinuxResult<isize> {
-    let fds = fds.get_as_array(2)?;
-    let fds_slice: &mut [c_int] = unsafe { core::slice::from_raw_parts_mut(fds, 2) };
-    Ok(api::sys_pipe(fds_slice) as _)
+const SPLICE_F_MOVE: u32 = 1;
+const SPLICE_F_NONBLOCK: u32 = 2;
+const SPLICE_F_MORE: u32 = 4;
+const SPLICE_F_GIFT: u32 = 8;
+
+/// The most segments one call takes.
+const UIO_MAXIOV: usize = 1024;
+
+pub fn sys_pipe2(fds: UserPtr<i32>, flags: c_int) -> LinuxResult<isize> {
+    let fds_slice: &mut [c_int] = fds.get_as_mut_slice(2)?;
+    // The buffer is charged to the real user, as on Linux.
+    let user = current().task_ext().cred().uid;
+    let ret = api::sys_pipe_as(fds_slice, user);
+    if ret == 0 {
+        for &fd in fds_slice.iter() {
+            set_cloexec(fd, flags & O_CLOEXEC != 0);
+        }
+    }
+    Ok(ret as _)
+}
+
+/// Appends the `nr_segs` buffers of `iov` to the pipe `fd` refers to, and
+/// returns how many bytes went in.
+///
+/// A pipe buffer is its own memory rather than a list of pages, so the data
+/// is always copied, and `SPLICE_F_GIFT` only tells that the caller will not
+/// touch the pages again. With `SPLICE_F_NONBLOCK`, it fails with `EAGAIN`
+/// if the pipe is full to begin with. Only the write end can be given, as
+/// reading a pipe into memory is what `readv` is for.
+pub fn sys_vmsplice(
+    fd: i32,
+    iov: UserConstPtr<ctypes::iovec>,
+    nr_segs: usize,
+    flags: u32,
+) -> LinuxResult<isize> {
+    if flags & !(SPLICE_F_MOVE | SPLICE_F_NONBLOCK | SPLICE_F_MORE | SPLICE_F_GIFT) != 0
+        || nr_segs > UIO_MAXIOV
+    {
+        return Err(LinuxError::EINVAL);
+    }
+    let pipe = Pipe::from_fd(fd)?;
+    if !pipe.writable() {
+        return Err(LinuxError::EBADF);
+    }
+    if nr_segs == 0 {
+        return Ok(0);
+    }
+    let iovs = iov.get_as_slice(nr_segs)?;
+    if flags & SPLICE_F_NONBLOCK != 0 && !pipe.poll()?.writable {
+        return Err(LinuxError::EAGAIN);
+    }
+    let mut written = 0;
+    for iov in iovs {
+        if iov.iov_len == 0 {
+            continue;
+        }
+        // What can be read of the buffer goes in, up to the first byte that
+        // cannot.
+        let (base, len) = match UserConstPtr::<u8>::from(iov.iov_base as usize)
+            .get_as_partial_bytes(iov.iov_len)
+        {
+            Ok(partial) => partial,
+            Err(_) if written > 0 => break,
+            Err(err) => return Err(err),
+        };
+        let data = unsafe { slice::from_raw_parts(base, len) };
+        let len = match pipe.write(data) {
+            Ok(len) => len,
+            // Report what went in before being interrupted.
+            Err(_) if written > 0 => break,
+            Err(err) => return Err(err),
+        };
+        written += len;
+        if len < iov.iov_len {
+            break;
+        }
+    }
+    Ok(written as _)
 }
@@ -0,0 +1,217 @@
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use arceos_posix_api::{self as api, get_file_like};
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::monotonic_time;
+use axtask::{TaskExtRef, WaitQueue, current};
+use starry_core::signal::{SignalSet, signal_pending};
+
+use super::fd_ops::set_cloexec;
+use crate::{
+    imp::task::slack_deadline,
+    ptr::{PtrWrapper, UserConstPtr, UserPtr},
+};
+
+const POLLERR: i16 = 0x008;
+const POLLHUP: i16 = 0x010;
+const POLLNVAL: i16 = 0x020;
+
+/// How long a sleeping poller waits at most before it checks for signals,
+/// which do not wake it.
+const POLL_SLICE: Duration = Duration::from_millis(10);
+
+/// Counts the readiness changes of the files that wake their pollers.
+static READINESS_CHANGES: AtomicU64 = AtomicU64::new(0);
+static POLLERS: WaitQueue = WaitQueue::new();
+
+/// Wakes the tasks in `poll` and `epoll_wait`, as the readiness of a file
+/// that [`FileLike::notifies_pollers`] may have changed.
+///
+/// [`FileLike::notifies_pollers`]: api::FileLike::notifies_pollers
+pub(crate) fn wake_pollers() {
+    READINESS_CHANGES.fetch_add(1, Ordering::AcqRel);
+    POLLERS.notify_all(false);
+}
+
+/// Waits for the polled files to change after [`READINESS_CHANGES`] read
+/// `seen`, but not past `deadline`: it sleeps if all of them wake their
+/// pollers, as `notify` tells, or else only yields.
+fn wait_for_change(seen: u64, notify: bool, deadline: Option<Duration>) {
+    if !notify {
+        axtask::yield_now();
+        return;
+    }
+    let mut slice = POLL_SLICE;
+    if let Some(deadline) = deadline {
+        slice = slice.min(deadline.saturating_sub(monotonic_time()));
+    }
+    POLLERS.wait_timeout_until(slice, || READINESS_CHANGES.load(Ordering::Acquire) != seen);
+}
+
+/// `struct pollfd`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PollFd {
+    fd: i32,
+    events: i16,
+    revents: i16,
+}
+
+/// Returns the events of `fd` that `events` asks for, besides the errors and
+/// hangups that are always reported.
+fn poll_fd(fd: i32, events: i16) -> i16 {
+    if fd < 0 {
+        return 0;
+    }
+    let Ok(file) = get_file_like(fd) else {
+        return POLLNVAL;
+    };
+    let revents = api::poll_events(&*file) as i16;
+    revents & (events | POLLERR | POLLHUP)
+}
+
+fn do_poll(fds: UserPtr<PollFd>, nfds: usize, timeout: Option<Duration>) -> LinuxResult<isize> {
+    if nfds > current().task_ext().get_fd_limit() as usize {
+        return Err(LinuxError::EINVAL);
+    }
+    let fds = fds.get_as_mut_slice(nfds)?;
+    // A zero timeout only takes a look, with no slack.
+    let deadline = timeout.map(|timeout| match timeout {
+        Duration::ZERO => monotonic_time(),
+        timeout => slack_deadline(monotonic_time() + timeout),
+    });
+    let notify = fds
+        .iter()
+        .all(|pfd| pfd.fd < 0 || get_file_like(pfd.fd).is_ok_and(|file| file.notifies_pollers()));
+    loop {
+        let seen = READINESS_CHANGES.load(Ordering::Acquire);
+        let mut ready = 0;
+        for pfd in fds.iter_mut() {
+            pfd.revents = poll_fd(pfd.fd, pfd.events);
+            if pfd.revents != 0 {
+                ready += 1;
+            }
+        }
+        if ready > 0 {
+            return Ok(ready);
+        }
+        if deadline.is_some_and(|deadline| monotonic_time() >= deadline) {
+            return Ok(0);
+        }
+        if signal_pending() {
+            return Err(LinuxError::EINTR);
+        }
+        wait_for_change(seen, notify, deadline);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_poll(fds: UserPtr<PollFd>, nfds: usize, timeout_ms: i32) -> LinuxResult<isize> {
+    // A negative timeout waits forever.
+    let timeout = u64::try_from(timeout_ms).ok().map(Duration::from_millis);
+    do_poll(fds, nfds, timeout)
+}
+
+pub fn sys_ppoll(
+    fds: UserPtr<PollFd>,
+    nfds: usize,
+    timeout: UserConstPtr<api::ctypes::timespec>,
+    sigmask: UserConstPtr<SignalSet>,
+    sigsetsize: usize,
+) -> LinuxResult<isize> {
+    let timeout = timeout.nullable(|ts| ts.get().map(|ts| unsafe { *ts }))?;
+    if let Some(ts) = timeout {
+        if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) {
+            return Err(LinuxError::EINVAL);
+        }
+    }
+    set_temporary_mask(sigmask, sigsetsize)?;
+    do_poll(fds, nfds, timeout.map(Duration::from))
+}
+
+/// Blocks the signals at `sigmask` until the syscall returns, if not null.
+fn set_temporary_mask(sigmask: UserConstPtr<SignalSet>, sigsetsize: usize) -> LinuxResult {
+    if let Some(mask) = sigmask.nullable(|mask| mask.get().map(|mask| unsafe { *mask }))? {
+        if sigsetsize != size_of::<SignalSet>() {
+            return Err(LinuxError::EINVAL);
+        }
+        current().task_ext().signal.lock().set_temporary_mask(mask);
+    }
+    Ok(())
+}
+
+pub fn sys_epoll_create1(flags: i32) -> LinuxResult<isize> {
+    const EPOLL_CLOEXEC: i32 = 0o2000000;
+    if flags & !EPOLL_CLOEXEC != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let fd = api::sys_epoll_create(1);
+    if fd >= 0 {
+        set_cloexec(fd, flags & EPOLL_CLOEXEC != 0);
+    }
+    Ok(fd as _)
+}
+
+pub fn sys_epoll_ctl(
+    epfd: i32,
+    op: i32,
+    fd: i32,
+    event: UserPtr<api::ctypes::epoll_event>,
+) -> LinuxResult<isize> {
+    const EPOLL_CTL_DEL: i32 = 2;
+    // The event is ignored when deleting, and may be null.
+    let mut unused: api::ctypes::epoll_event = unsafe { core::mem::zeroed() };
+    let event = match op {
+        EPOLL_CTL_DEL => &mut unused,
+        _ => event.get()?,
+    };
+    Ok(unsafe { api::sys_epoll_ctl(epfd, op, fd, event) } as _)
+}
+
+pub fn sys_epoll_pwait(
+    epfd: i32,
+    events: UserPtr<api::ctypes::epoll_event>,
+    maxevents: i32,
+    timeout: i32,
+    sigmask: UserConstPtr<SignalSet>,
+    sigsetsize: usize,
+) -> LinuxResult<isize> {
+    if maxevents <= 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let events = events.get_as_array(maxevents as usize)?;
+    let epoll = api::EpollInstance::from_fd(epfd)?;
+    set_temporary_mask(sigmask, sigsetsize)?;
+    // A negative timeout waits forever.
+    let deadline = u64::try_from(timeout)
+        .ok()
+        .map(|timeout| monotonic_time() + Duration::from_millis(timeout));
+    loop {
+        let seen = READINESS_CHANGES.load(Ordering::Acquire);
+        // Only takes a look, so that the waiting can be done here.
+        let ready = unsafe { api::sys_epoll_wait(epfd, events, maxevents, 0) };
+        if ready != 0 {
+            return Ok(ready as _);
+        }
+        if deadline.is_some_and(|deadline| monotonic_time() >= deadline) {
+            return Ok(0);
+        }
+        if signal_pending() {
+            return Err(LinuxError::EINTR);
+        }
+        wait_for_change(seen, epoll.notifies_pollers(), deadline);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_epoll_wait(
+    epfd: i32,
+    events: UserPtr<api::ctypes::epoll_event>,
+    maxevents: i32,
+    timeout: i32,
+) -> LinuxResult<isize> {
+    sys_epoll_pwait(epfd, events, maxevents, timeout, 0.into(), 0)
+}
@@ -0,0 +1,594 @@
+//! The files under `/proc` whose content is generated from kernel state.
+//!
+//! As with the tunables, the VFS only holds empty placeholders for them, and
+//! they are opened by path before it. The content is taken when a file is
+//! opened, so one open file always reads consistently.
+//!
+//! The files under `/proc/[pid]` have no placeholders at all, as there is a
+//! set of them for every process. Among them, `/proc/[pid]/fd` is a directory
+//! listing the descriptors of the process as links to what they refer to, and
+//! `/proc/[pid]/task` one with a directory for each thread, holding the same
+//! files for the thread alone.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
+use core::fmt::Write;
+
+use arceos_posix_api::{self as api, FD_TABLE, FileLike, ctypes};
+use axerrno::{LinuxError, LinuxResult};
+use axfs::api::FileType;
+use axhal::time::{NANOS_PER_SEC, monotonic_time, monotonic_time_nanos, wall_time};
+use axio::PollState;
+use axsync::Mutex;
+use axtask::{AxTaskRef, TaskExtRef, TaskState, current};
+use starry_core::{
+    ctypes::USER_HZ,
+    kmsg,
+    mm::{committed_size, huge_page_size, resident_size},
+    task::{all_tasks, cpu_user_nanos, find_task},
+};
+
+use super::{dev::device_path, eventfd::EventFd, signalfd::SignalFd, timerfd::TimerFd};
+use crate::imp::{
+    mm::{commit_limit, mem_usage},
+    net::{tcp_table, udp_table},
+};
+
+const S_IFMT: u32 = 0o170000;
+const S_IFIFO: u32 = 0o010000;
+const S_IFCHR: u32 = 0o020000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFSOCK: u32 = 0o140000;
+
+const O_ACCMODE: i32 = 0o3;
+const O_RDONLY: i32 = 0o0;
+
+/// The generated files, by their path under `/proc`.
+static FILES: &[(&str, fn() -> String)] = &[
+    ("cpuinfo", cpuinfo),
+    ("kmsg", kmsg_contents),
+    ("meminfo", meminfo),
+    ("net/tcp", tcp_table),
+    ("net/udp", udp_table),
+    ("stat", stat_contents),
+];
+
+/// The generated files under each `/proc/[pid]`, by name.
+static PROCESS_FILES: &[(&str, fn(&AxTaskRef) -> LinuxResult<Vec<u8>>)] = &[
+    ("auxv", auxv),
+    ("cmdline", cmdline),
+    ("environ", environ),
+    ("stat", stat),
+    ("status", status),
+];
+
+/// The strings each followed by a NUL.
+fn nul_terminated(strings: Vec<String>) -> Vec<u8> {
+    let mut content = Vec::new();
+    for string in strings {
+        content.extend_from_slice(string.as_bytes());
+        content.push(0);
+    }
+    content
+}
+
+/// The arguments the program of `task` was started with.
+fn cmdline(task: &AxTaskRef) -> LinuxResult<Vec<u8>> {
+    Ok(nul_terminated(task.task_ext().args()))
+}
+
+/// Fails with `EACCES` unless the caller is root or owns `task`, for what
+/// only the owner of a process may read.
+fn check_owner(task: &AxTaskRef) -> LinuxResult {
+    let euid = current().task_ext().cred().euid;
+    if euid != 0 && euid != task.task_ext().cred().uid {
+        return Err(LinuxError::EACCES);
+    }
+    Ok(())
+}
+
+/// The environment the program of `task` was started with.
+fn environ(task: &AxTaskRef) -> LinuxResult<Vec<u8>> {
+    check_owner(task)?;
+    Ok(nul_terminated(task.task_ext().envs()))
+}
+
+/// The auxiliary vector the program of `task` was started with, as the words
+/// of each type and value.
+fn auxv(task: &AxTaskRef) -> LinuxResult<Vec<u8>> {
+    check_owner(task)?;
+    Ok(task
+        .task_ext()
+        .auxv()
+        .into_iter()
+        .flat_map(|(key, value)| [key, value])
+        .flat_map(usize::to_ne_bytes)
+        .collect())
+}
+
+/// The threads in the process of `task` that have not exited, in order of
+/// their IDs.
+fn threads(task: &AxTaskRef) -> Vec<AxTaskRef> {
+    all_tasks()
+        .into_iter()
+        .filter(|thread| thread.tgid() == task.tgid() && thread.state() != TaskState::Exited)
+        .collect()
+}
+
+/// The name of the program `task` runs, cut to the 15 bytes Linux keeps.
+fn comm(task: &AxTaskRef) -> &str {
+    let name = task.name().rsplit('/').next().unwrap();
+    let mut len = name.len().min(15);
+    while !name.is_char_boundary(len) {
+        len -= 1;
+    }
+    &name[..len]
+}
+
+/// The letter for the state of `task`, with its name in `status`.
+fn state(task: &AxTaskRef) -> (char, &'static str) {
+    match task.state() {
+        TaskState::Running | TaskState::Ready => ('R', "running"),
+        TaskState::Blocked => ('S', "sleeping"),
+        TaskState::Exited => ('Z', "zombie"),
+    }
+}
+
+/// The status of `task` as the fields of one line. Those for memory, page
+/// faults and the start time are not kept, and are 0.
+fn stat(task: &AxTaskRef) -> LinuxResult<Vec<u8>> {
+    let ext = task.task_ext();
+    let (utime, stime) = ext.time_stat_output();
+    let nice = ext.nice();
+    let mut stat = format!(
+        "{} ({}) {} {} {} {} 0 -1 0 0 0 0 0 {} {} 0 0 {} {} {} 0 0 0 0",
+        task.id().as_u64(),
+        comm(task),
+        state(task).0,
+        ext.get_parent(),
+        ext.pgid(),
+        ext.sid(),
+        nanos_to_ticks(utime as u64),
+        nanos_to_ticks(stime as u64),
+        20 + nice,
+        nice,
+        threads(task).len(),
+    );
+    stat.push('\n');
+    Ok(stat.into_bytes())
+}
+
+/// The status of `task` as named fields, one to a line.
+fn status(task: &AxTaskRef) -> LinuxResult<Vec<u8>> {
+    let ext = task.task_ext();
+    let cred = ext.cred();
+    let (state, state_name) = state(task);
+    let mut status = String::new();
+    let _ = writeln!(status, "Name:\t{}", comm(task));
+    let _ = writeln!(status, "State:\t{} ({})", state, state_name);
+    let _ = writeln!(status, "Tgid:\t{}", task.tgid());
+    let _ = writeln!(status, "Pid:\t{}", task.id().as_u64());
+    let _ = writeln!(status, "PPid:\t{}", ext.get_parent());
+    let _ = writeln!(status, "Uid:\t{0}\t{1}\t{1}\t{1}", cred.uid, cred.euid);
+    let _ = writeln!(status, "Gid:\t{0}\t{1}\t{1}\t{1}", cred.gid, cred.egid);
+    // Lazy mappings count in full towards the size, but only by the pages
+    // touched towards what is resident.
+    let (size, resident) = {
+        let aspace = ext.aspace.lock();
+        let size = aspace.areas().map(|(range, _)| range.size()).sum::<usize>();
+        (size, resident_size(&aspace))
+    };
+    let _ = writeln!(status, "VmSize:\t{:>8} kB", size / 1024);
+    let _ = writeln!(status, "VmRSS:\t{:>8} kB", resident / 1024);
+    let _ = writeln!(status, "Threads:\t{}", threads(task).len());
+    Ok(status.into_bytes())
+}
+
+/// The kernel log buffer as it is, which is not used up by reading it.
+fn kmsg_contents() -> String {
+    let mut log = vec![0; kmsg::LOG_BUF_LEN];
+    let len = kmsg::read_all(&mut log);
+    String::from_utf8_lossy(&log[..len]).into_owned()
+}
+
+/// The fields of `/proc/cpuinfo` that describe each processor, which are all
+/// alike.
+#[cfg(target_arch = "x86_64")]
+fn cpu_fields() -> String {
+    use core::arch::x86_64::__cpuid;
+
+    // The bits of `cpuid` leaf 1 in EDX, then in ECX, by flag.
+    const EDX_FLAGS: &[(u32, &str)] = &[
+        (0, "fpu"),
+        (4, "tsc"),
+        (5, "msr"),
+        (6, "pae"),
+        (8, "cx8"),
+        (9, "apic"),
+        (15, "cmov"),
+        (16, "pat"),
+        (19, "clflush"),
+        (23, "mmx"),
+        (24, "fxsr"),
+        (25, "sse"),
+        (26, "sse2"),
+        (28, "ht"),
+    ];
+    const ECX_FLAGS: &[(u32, &str)] = &[
+        (0, "pni"),
+        (9, "ssse3"),
+        (13, "cx16"),
+        (19, "sse4_1"),
+        (20, "sse4_2"),
+        (23, "popcnt"),
+        (25, "aes"),
+        (26, "xsave"),
+        (28, "avx"),
+        (30, "rdrand"),
+        (31, "hypervisor"),
+    ];
+
+    let regs_bytes =
+        |regs: &[u32]| -> Vec<u8> { regs.iter().flat_map(|reg| reg.to_le_bytes()).collect() };
+    let leaf0 = unsafe { __cpuid(0) };
+    let vendor = regs_bytes(&[leaf0.ebx, leaf0.edx, leaf0.ecx]);
+    let mut model = Vec::new();
+    if unsafe { __cpuid(0x8000_0000) }.eax >= 0x8000_0004 {
+        for leaf in 0x8000_0002..=0x8000_0004 {
+            let regs = unsafe { __cpuid(leaf) };
+            model.extend(regs_bytes(&[regs.eax, regs.ebx, regs.ecx, regs.edx]));
+        }
+    }
+    let model = String::from_utf8_lossy(&model);
+    let leaf1 = unsafe { __cpuid(1) };
+    let flags = EDX_FLAGS
+        .iter()
+        .filter(|(bit, _)| leaf1.edx & (1 << bit) != 0)
+        .chain(
+            ECX_FLAGS
+                .iter()
+                .filter(|(bit, _)| leaf1.ecx & (1 << bit) != 0),
+        )
+        .map(|(_, flag)| *flag)
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "vendor_id\t: {}\nmodel name\t: {}\nflags\t\t: {}\n",
+        String::from_utf8_lossy(&vendor),
+        model.trim_matches(|c: char| c == '\0' || c == ' '),
+        flags,
+    )
+}
+
+#[cfg(target_arch = "riscv64")]
+fn cpu_fields() -> String {
+    String::from("isa\t\t: rv64imafdc\nmmu\t\t: sv39\n")
+}
+
+#[cfg(target_arch = "loongarch64")]
+fn cpu_fields() -> String {
+    String::from("cpu family\t: Loongson-64bit\nisa\t\t: loongarch32 loongarch64\n")
+}
+
+#[cfg(target_arch = "aarch64")]
+fn cpu_fields() -> String {
+    String::from("Features\t: fp asimd\nCPU architecture: 8\n")
+}
+
+/// A paragraph for each processor, starting with its number.
+fn cpuinfo() -> String {
+    let fields = cpu_fields();
+    let mut info = String::new();
+    for cpu in 0..axconfig::SMP {
+        let _ = writeln!(info, "processor\t: {}\n{}", cpu, fields);
+    }
+    info
+}
+
+/// The sizes of the physical memory, of what is committed to user space and of
+/// its huge pages, in kB. There is no page cache, so nothing is counted as
+/// cached.
+fn meminfo() -> String {
+    let usage = mem_usage();
+    let mut info = String::new();
+    for (name, bytes) in [
+        ("MemTotal", usage.total),
+        ("MemFree", usage.free),
+        ("MemAvailable", usage.available),
+        ("Buffers", 0),
+        ("Cached", 0),
+        ("Slab", usage.slab),
+        ("CommitLimit", commit_limit()),
+        ("Committed_AS", committed_size()),
+        ("AnonHugePages", huge_page_size()),
+    ] {
+        let _ = writeln!(info, "{:<16}{:>8} kB", format!("{name}:"), bytes / 1024);
+    }
+    info
+}
+
+/// Converts to the clock ticks times in `/proc` are counted in.
+fn nanos_to_ticks(nanos: u64) -> u64 {
+    nanos * USER_HZ / NANOS_PER_SEC
+}
+
+/// The time each processor has spent in user mode, in the kernel and idle,
+/// then the context switches and the time of boot.
+///
+/// Time in the kernel is what is left of the uptime after the other two, and
+/// there is no niceness, waiting for I/O or interrupt time counted apart.
+fn stat_contents() -> String {
+    let uptime = monotonic_time_nanos();
+    let times = (0..axconfig::SMP)
+        .map(|cpu| {
+            let idle = axtask::cpu_idle_nanos(cpu).min(uptime);
+            let user = cpu_user_nanos(cpu).min(uptime - idle);
+            [user, uptime - idle - user, idle]
+        })
+        .collect::<Vec<_>>();
+    let total = times.iter().fold([0; 3], |total, time| {
+        [total[0] + time[0], total[1] + time[1], total[2] + time[2]]
+    });
+
+    let mut stat = String::new();
+    let mut cpu_line = |name: &str, [user, system, idle]: [u64; 3]| {
+        let _ = writeln!(
+            stat,
+            "{} {} 0 {} {} 0 0 0 0 0 0",
+            name,
+            nanos_to_ticks(user),
+            nanos_to_ticks(system),
+            nanos_to_ticks(idle),
+        );
+    };
+    cpu_line("cpu ", total);
+    for (cpu, time) in times.into_iter().enumerate() {
+        cpu_line(&format!("cpu{}", cpu), time);
+    }
+    let _ = writeln!(stat, "ctxt {}", axtask::context_switches());
+    let _ = writeln!(stat, "btime {}", (wall_time() - monotonic_time()).as_secs());
+    stat
+}
+
+struct ProcFile {
+    content: Vec<u8>,
+    offset: Mutex<usize>,
+}
+
+impl FileLike for ProcFile {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        let mut offset = self.offset.lock();
+        let remaining = self.content.get(*offset..).unwrap_or_default();
+        let len = remaining.len().min(buf.len());
+        buf[..len].copy_from_slice(&remaining[..len]);
+        *offset += len;
+        Ok(len)
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EBADF)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode: 0o100000 | 0o444, // S_IFREG | r--r--r--
+            st_blksize: 1024,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: true,
+            writable: false,
+        })
+    }
+
+    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+        Ok(())
+    }
+}
+
+/// The process `pid` names under `/proc`, `self` being the caller.
+fn proc_task(pid: &str) -> Option<AxTaskRef> {
+    match pid {
+        "self" => Some(current().clone()),
+        pid => find_task(pid.parse().ok()?),
+    }
+}
+
+/// The thread `tid` names under `/proc/[pid]/task`, if it is one of the
+/// process `pid` names.
+fn proc_thread(pid: &str, tid: &str) -> Option<AxTaskRef> {
+    let task = proc_task(pid)?;
+    threads(&task)
+        .into_iter()
+        .find(|thread| tid.parse() == Ok(thread.id().as_u64()))
+}
+
+/// The open descriptors of the process `pid` names under `/proc`, in order.
+fn open_files(pid: &str) -> Option<Vec<(usize, Arc<dyn FileLike>)>> {
+    let task = proc_task(pid)?;
+    let table = FD_TABLE.deref_from(&task.task_ext().ns).read();
+    Some(
+        table
+            .ids()
+            .map(|fd| (fd, table.get(fd).unwrap().clone()))
+            .collect(),
+    )
+}
+
+/// What `file` refers to, as the link of a descriptor for it reads.
+fn fd_target(file: Arc<dyn FileLike>) -> String {
+    let stat = file.stat().unwrap_or_default();
+    let file = file.into_any();
+    if let Some(file) = file.downcast_ref::<api::File>() {
+        return file.path().to_string();
+    }
+    if let Some(dir) = file.downcast_ref::<api::Directory>() {
+        return dir.path().to_string();
+    }
+    if let Some(dir) = file.downcast_ref::<ProcDir>() {
+        return dir.path.clone();
+    }
+    let name = if file.is::<EventFd>() {
+        "eventfd"
+    } else if file.is::<SignalFd>() {
+        "signalfd"
+    } else if file.is::<TimerFd>() {
+        "timerfd"
+    } else if file.is::<api::EpollInstance>() {
+        "eventpoll"
+    } else {
+        match stat.st_mode & S_IFMT {
+            S_IFIFO => return format!("pipe:[{}]", stat.st_ino),
+            S_IFSOCK => return format!("socket:[{}]", stat.st_ino),
+            S_IFCHR => match device_path(stat.st_rdev as u64) {
+                Some(path) => return path,
+                None => "chrdev",
+            },
+            _ => "unknown",
+        }
+    };
+    format!("anon_inode:[{name}]")
+}
+
+/// The target of the link at the canonical `path` if it is `/proc/[pid]/fd/N`
+/// for an open descriptor `N`.
+pub(crate) fn fd_link(path: &str) -> Option<String> {
+    let (pid, fd) = path.strip_prefix("/proc/")?.split_once("/fd/")?;
+    let fd: usize = fd.parse().ok()?;
+    let (_, file) = open_files(pid)?.into_iter().find(|(open, _)| *open == fd)?;
+    Some(fd_target(file))
+}
+
+/// A directory under `/proc/[pid]`, which lists what was there when it was
+/// opened.
+pub(super) struct ProcDir {
+    path: String,
+    perm: u32,
+    /// The names and types of the entries, starting with `.` and `..`.
+    pub(super) entries: Vec<(String, FileType)>,
+    /// How many of the entries have been read.
+    pub(super) pos: Mutex<usize>,
+}
+
+impl FileLike for ProcDir {
+    fn read(&self, _buf: &mut [u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EISDIR)
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EBADF)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 2,
+            st_mode: S_IFDIR | self.perm,
+            st_blksize: 1024,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: true,
+            writable: false,
+        })
+    }
+
+    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+        Ok(())
+    }
+}
+
+/// Opens the directory at `path` with the permission bits `perm`, listing
+/// `names` as entries of `file_type`.
+fn open_dir(
+    path: &str,
+    perm: u32,
+    names: impl Iterator<Item = String>,
+    file_type: FileType,
+    flags: i32,
+) -> LinuxResult<Arc<dyn FileLike>> {
+    if flags & O_ACCMODE != O_RDONLY {
+        return Err(LinuxError::EISDIR);
+    }
+    let entries = [".", ".."]
+        .into_iter()
+        .map(|name| (String::from(name), FileType::Dir))
+        .chain(names.map(|name| (name, file_type)))
+        .collect();
+    Ok(Arc::new(ProcDir {
+        path: path.to_string(),
+        perm,
+        entries,
+        pos: Mutex::new(0),
+    }))
+}
+
+/// Opens the directory under `/proc/[pid]` at `path`, the part after
+/// `/proc/` being `name`, or returns `None` if it is not one.
+fn open_process_dir(path: &str, name: &str, flags: i32) -> Option<LinuxResult<Arc<dyn FileLike>>> {
+    if let Some(pid) = name.strip_suffix("/fd") {
+        let fds = open_files(pid)?.into_iter().map(|(fd, _)| fd.to_string());
+        return Some(open_dir(path, 0o500, fds, FileType::SymLink, flags));
+    }
+    if let Some(pid) = name.strip_suffix("/task") {
+        let tids = threads(&proc_task(pid)?)
+            .into_iter()
+            .map(|thread| thread.id().as_u64().to_string());
+        return Some(open_dir(path, 0o555, tids, FileType::Dir, flags));
+    }
+    let (pid, tid) = name.split_once("/task/")?;
+    proc_thread(pid, tid)?;
+    let files = PROCESS_FILES.iter().map(|(file, _)| file.to_string());
+    Some(open_dir(path, 0o555, files, FileType::File, flags))
+}
+
+/// Opens the generated file at the absolute, canonical `path` with the open
+/// `flags`, or returns `None` if it is not one of ours.
+pub(crate) fn open_proc_file(path: &str, flags: i32) -> Option<LinuxResult<Arc<dyn FileLike>>> {
+    let name = path.strip_prefix("/proc/")?;
+    if let Some(dir) = open_process_dir(path, name, flags) {
+        return Some(dir);
+    }
+    let content = match FILES.iter().find(|(file, _)| *file == name) {
+        Some((_, generate)) => Ok(generate().into_bytes()),
+        None => {
+            let (dir, name) = name.rsplit_once('/')?;
+            let (_, generate) = PROCESS_FILES.iter().find(|(file, _)| *file == name)?;
+            let task = match dir.split_once("/task/") {
+                Some((pid, tid)) => proc_thread(pid, tid)?,
+                None => proc_task(dir)?,
+            };
+            generate(&task)
+        }
+    };
+    if flags & O_ACCMODE != O_RDONLY {
+        return Some(Err(LinuxError::EACCES));
+    }
+    Some(content.map(|content| {
+        Arc::new(ProcFile {
+            content,
+            offset: Mutex::new(0),
+        }) as _
+    }))
+}
@@ -0,0 +1,191 @@
+//! Files to read pending signals from instead of having them delivered, from
+//! `signalfd`.
+
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use arceos_posix_api::{self as api, FileLike, ctypes, get_file_like};
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axsync::Mutex;
+use axtask::{TaskExtRef, current};
+use starry_core::signal::{
+    SIGBUS, SIGCHLD, SIGFPE, SIGILL, SIGIO, SIGSEGV, SigInfo, SignalSet, signal_pending,
+};
+
+use super::fd_ops::set_cloexec;
+use crate::ptr::{PtrWrapper, UserConstPtr};
+
+const SFD_NONBLOCK: i32 = 0o4000;
+const SFD_CLOEXEC: i32 = 0o2000000;
+
+/// `struct signalfd_siginfo`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct SignalFdSigInfo {
+    ssi_signo: u32,
+    ssi_errno: i32,
+    ssi_code: i32,
+    ssi_pid: u32,
+    ssi_uid: u32,
+    ssi_fd: i32,
+    ssi_tid: u32,
+    ssi_band: u32,
+    ssi_overrun: u32,
+    ssi_trapno: u32,
+    ssi_status: i32,
+    ssi_int: i32,
+    ssi_ptr: u64,
+    ssi_utime: u64,
+    ssi_stime: u64,
+    ssi_addr: u64,
+    ssi_addr_lsb: u16,
+    _pad2: u16,
+    ssi_syscall: i32,
+    ssi_call_addr: u64,
+    ssi_arch: u32,
+    _pad: [u8; 28],
+}
+
+impl From<SigInfo> for SignalFdSigInfo {
+    /// Picks the fields out of the union of `siginfo_t` that the signal
+    /// fills.
+    fn from(info: SigInfo) -> Self {
+        let mut ssi = Self {
+            ssi_signo: info.signo as u32,
+            ssi_errno: info.errno,
+            ssi_code: info.code,
+            ..Default::default()
+        };
+        let [first, second, ..] = info.fields;
+        match info.signo() {
+            SIGSEGV | SIGBUS | SIGILL | SIGFPE => ssi.ssi_addr = first,
+            SIGIO => {
+                ssi.ssi_band = first as u32;
+                ssi.ssi_fd = second as i32;
+            }
+            sig => {
+                ssi.ssi_pid = first as u32;
+                ssi.ssi_uid = (first >> 32) as u32;
+                if sig == SIGCHLD {
+                    ssi.ssi_status = second as i32;
+                } else {
+                    ssi.ssi_int = second as i32;
+                    ssi.ssi_ptr = second;
+                }
+            }
+        }
+        ssi
+    }
+}
+
+/// Reads the signals of a mask that are pending for the calling thread.
+pub struct SignalFd {
+    mask: Mutex<SignalSet>,
+    nonblocking: AtomicBool,
+}
+
+impl FileLike for SignalFd {
+    /// Takes as many pending signals as fit in `buf`, waiting for one if
+    /// there is none.
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        const SIZE: usize = size_of::<SignalFdSigInfo>();
+        if buf.len() < SIZE {
+            return Err(LinuxError::EINVAL);
+        }
+        let mask = *self.mask.lock();
+        loop {
+            let mut len = 0;
+            {
+                let curr = current();
+                let mut signal = curr.task_ext().signal.lock();
+                while len + SIZE <= buf.len() {
+                    let Some(info) = signal.dequeue_from(mask) else {
+                        break;
+                    };
+                    let ssi = SignalFdSigInfo::from(info);
+                    let bytes =
+                        unsafe { core::slice::from_raw_parts(&ssi as *const _ as *const u8, SIZE) };
+                    buf[len..len + SIZE].copy_from_slice(bytes);
+                    len += SIZE;
+                }
+            }
+            if len > 0 {
+                return Ok(len);
+            }
+            if self.nonblocking() {
+                return Err(LinuxError::EAGAIN);
+            }
+            if signal_pending() {
+                return Err(LinuxError::EINTR);
+            }
+            axtask::yield_now();
+        }
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode: 0o600, // rw-------
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        let mask = *self.mask.lock();
+        let pending = current().task_ext().signal.lock().pending();
+        Ok(PollState {
+            readable: pending.0 & mask.0 != 0,
+            writable: false,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Release);
+        Ok(())
+    }
+
+    fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Acquire)
+    }
+}
+
+/// Creates a signalfd for the signals at `mask` if `fd` is -1, or changes the
+/// mask of the signalfd `fd`. `SIGKILL` and `SIGSTOP` are left out of the
+/// mask.
+pub fn sys_signalfd4(
+    fd: i32,
+    mask: UserConstPtr<SignalSet>,
+    sizemask: usize,
+    flags: i32,
+) -> LinuxResult<isize> {
+    if flags & !(SFD_NONBLOCK | SFD_CLOEXEC) != 0 || sizemask != size_of::<SignalSet>() {
+        return Err(LinuxError::EINVAL);
+    }
+    let mut mask = unsafe { *mask.get()? };
+    mask.0 &= !SignalSet::UNBLOCKABLE.0;
+    if fd != -1 {
+        let signalfd = get_file_like(fd)?
+            .into_any()
+            .downcast::<SignalFd>()
+            .map_err(|_| LinuxError::EINVAL)?;
+        *signalfd.mask.lock() = mask;
+        return Ok(fd as _);
+    }
+    let signalfd = SignalFd {
+        mask: Mutex::new(mask),
+        nonblocking: AtomicBool::new(flags & SFD_NONBLOCK != 0),
+    };
+    let fd = api::add_file_like(Arc::new(signalfd))?;
+    set_cloexec(fd, flags & SFD_CLOEXEC != 0);
+    Ok(fd as _)
+}
@@ -1,13 +1,27 @@
+use alloc::format;
 use core::ffi::c_char;
 
+use arceos_posix_api::AT_FDCWD;
 use axerrno::{LinuxError, LinuxResult};
+use axtask::{TaskExtRef, current};
 use macro_rules_attribute::apply;
+use starry_core::cred::{R_OK, W_OK, X_OK};
 
+use super::{attr, dev::device_stat, fifo::fifo_perm, link, mount::is_read_only};
 use crate::{
     ptr::{PtrWrapper, UserConstPtr, UserPtr},
     syscall_instrument,
 };
 
+const S_IFMT: u32 = 0o170000;
+const S_IFIFO: u32 = 0o010000;
+const S_IFCHR: u32 = 0o020000;
+const S_IFBLK: u32 = 0o060000;
+const S_IFSOCK: u32 = 0o140000;
+const S_IFLNK: u32 = 0o120000;
+const AT_SYMLINK_NOFOLLOW: i32 = 0x100;
+const AT_EACCESS: i32 = 0x200;
+
 #[derive(Debug, Clone, Copy, Default)]
 #[repr(C)]
 pub struct Kstat {
@@ -76,6 +90,15 @@ impl From<arceos_posix_api::ctypes::stat> for Kstat {
 
 pub fn sys_fstat(fd: i32, kstatbuf: UserPtr<Kstat>) -> LinuxResult<isize> {
     let kstatbuf = kstatbuf.get()?;
+    let file = arceos_posix_api::get_file_like(fd)?
+        .into_any()
+        .downcast::<arceos_posix_api::File>()
+        .ok();
+    if let Some(statbuf) = file.as_ref().and_then(|file| device_stat(file.path())) {
+        unsafe { kstatbuf.write(Kstat::from(statbuf)) };
+        return Ok(0);
+    }
+
     let mut statbuf = arceos_posix_api::ctypes::stat::default();
 
     let result = unsafe {
@@ -84,6 +107,9 @@ pub fn sys_fstat(fd: i32, kstatbuf: UserPtr<Kstat>) -> LinuxResult<isize> {
     if result < 0 {
         return Ok(result as _);
     }
+    if let Some(file) = file {
+        attr::apply(file.path(), &mut statbuf);
+    }
 
     unsafe {
         let kstat = Kstat::from(statbuf);
@@ -92,29 +118,41 @@ pub fn sys_fstat(fd: i32, kstatbuf: UserPtr<Kstat>) -> LinuxResult<isize> {
     Ok(0)
 }
 
+/// Gets the status of the file at the canonical path `path`, or of the link
+/// itself if the last component is one and `follow` is not set.
+pub(super) fn stat_path(path: &str, follow: bool) -> LinuxResult<arceos_posix_api::ctypes::stat> {
+    let path = link::resolve(path, follow)?;
+    if let Some(statbuf) = device_stat(&path) {
+        return Ok(statbuf);
+    }
+    let mut statbuf = arceos_posix_api::ctypes::stat::default();
+    let c_path = format!("{path}\0");
+    let result = unsafe { arceos_posix_api::sys_stat(c_path.as_ptr() as _, &mut statbuf) };
+    if result < 0 {
+        return Err(LinuxError::try_from(-result).unwrap());
+    }
+    if let Some(target) = link::read_link(&path) {
+        statbuf.st_mode = S_IFLNK | 0o777;
+        statbuf.st_size = target.len() as _;
+    } else if let Some(perm) = fifo_perm(&path) {
+        statbuf.st_mode = S_IFIFO | perm;
+    }
+    // What is kept for a link is only its owner, with the bits it always has.
+    attr::apply(&path, &mut statbuf);
+    Ok(statbuf)
+}
+
 #[apply(syscall_instrument)]
 pub fn sys_fstatat(
     dir_fd: isize,
     path: UserConstPtr<c_char>,
     kstatbuf: UserPtr<Kstat>,
-    _flags: i32,
+    flags: i32,
 ) -> LinuxResult<isize> {
-    let path = path.get_as_null_terminated()?;
-    let path = arceos_posix_api::handle_file_path(dir_fd, Some(path.as_ptr() as _), false)?;
+    let path = link::resolve_at(dir_fd as _, path, flags & AT_SYMLINK_NOFOLLOW == 0)?;
 
     let kstatbuf = kstatbuf.get()?;
-
-    let mut statbuf = arceos_posix_api::ctypes::stat::default();
-    let result = unsafe {
-        arceos_posix_api::sys_stat(
-            path.as_ptr() as _,
-            &mut statbuf as *mut arceos_posix_api::ctypes::stat,
-        )
-    };
-    if result < 0 {
-        return Ok(result as _);
-    }
-
+    let statbuf = stat_path(&path, false)?;
     unsafe {
         let kstat = Kstat::from(statbuf);
         kstatbuf.write(kstat);
@@ -123,6 +161,58 @@ pub fn sys_fstatat(
     Ok(0)
 }
 
+#[cfg(target_arch = "x86_64")]
+pub fn sys_stat(path: UserConstPtr<c_char>, kstatbuf: UserPtr<Kstat>) -> LinuxResult<isize> {
+    sys_fstatat(AT_FDCWD, path, kstatbuf, 0)
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_lstat(path: UserConstPtr<c_char>, kstatbuf: UserPtr<Kstat>) -> LinuxResult<isize> {
+    sys_fstatat(AT_FDCWD, path, kstatbuf, AT_SYMLINK_NOFOLLOW)
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_access(path: UserConstPtr<c_char>, mode: u32) -> LinuxResult<isize> {
+    sys_faccessat(AT_FDCWD, path, mode, 0)
+}
+
+/// Checks whether the calling process could access the file at `path` as
+/// `mode` asks, with its real IDs unless `AT_EACCESS` is set. Writing is
+/// refused with `EROFS` on a read-only filesystem, but for the devices,
+/// FIFOs and sockets, which are not written on it.
+#[apply(syscall_instrument)]
+pub fn sys_faccessat(
+    dir_fd: isize,
+    path: UserConstPtr<c_char>,
+    mode: u32,
+    flags: i32,
+) -> LinuxResult<isize> {
+    if mode & !(R_OK | W_OK | X_OK) != 0 || flags & !(AT_EACCESS | AT_SYMLINK_NOFOLLOW) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let path = link::resolve_at(dir_fd as _, path, flags & AT_SYMLINK_NOFOLLOW == 0)?;
+    let statbuf = stat_path(&path, false)?;
+    if mode & W_OK != 0
+        && !matches!(
+            statbuf.st_mode & S_IFMT,
+            S_IFCHR | S_IFBLK | S_IFIFO | S_IFSOCK
+        )
+        && is_read_only(&path)
+    {
+        return Err(LinuxError::EROFS);
+    }
+    let cred = current().task_ext().cred();
+    let cred = if flags & AT_EACCESS != 0 {
+        cred
+    } else {
+        cred.real()
+    };
+    if !cred.permits(statbuf.st_mode, statbuf.st_uid, statbuf.st_gid, mode) {
+        return Err(LinuxError::EACCES);
+    }
+    Ok(0)
+}
+
 #[repr(C)]
 #[derive(Debug, Default)]
 pub struct FsStatxTimestamp {
@@ -217,10 +307,10 @@ pub fn sys_statx(
     //        below), then the target file is the one referred to by the
     //        file descriptor dirfd.
 
-    let path = pathname.get_as_str()?;
+    let path = pathname.get_as_path()?;
 
     const AT_EMPTY_PATH: u32 = 0x1000;
-    if path.is_empty() {
+    let status = if path.is_empty() {
         if flags & AT_EMPTY_PATH == 0 {
             return Err(LinuxError::EINVAL);
         }
@@ -230,25 +320,27 @@ pub fn sys_statx(
         if res < 0 {
             return Err(LinuxError::try_from(-res).unwrap());
         }
-        let statx = unsafe { &mut *statxbuf.get()? };
-        statx.stx_blksize = status.st_blksize as u32;
-        statx.stx_attributes = status.st_mode as u64;
-        statx.stx_nlink = status.st_nlink;
-        statx.stx_uid = status.st_uid;
-        statx.stx_gid = status.st_gid;
-        statx.stx_mode = status.st_mode as u16;
-        statx.stx_ino = status.st_ino;
-        statx.stx_size = status.st_size as u64;
-        statx.stx_blocks = status.st_blocks as u64;
-        statx.stx_attributes_mask = 0x7FF;
-        statx.stx_atime.tv_sec = status.st_atime.tv_sec;
-        statx.stx_atime.tv_nsec = status.st_atime.tv_nsec as u32;
-        statx.stx_ctime.tv_sec = status.st_ctime.tv_sec;
-        statx.stx_ctime.tv_nsec = status.st_ctime.tv_nsec as u32;
-        statx.stx_mtime.tv_sec = status.st_mtime.tv_sec;
-        statx.stx_mtime.tv_nsec = status.st_mtime.tv_nsec as u32;
-        Ok(0)
+        status
     } else {
-        Err(LinuxError::ENOSYS)
-    }
+        let follow = flags & AT_SYMLINK_NOFOLLOW as u32 == 0;
+        stat_path(&link::resolve_at(dirfd, pathname, follow)?, false)?
+    };
+    let statx = unsafe { &mut *statxbuf.get()? };
+    statx.stx_blksize = status.st_blksize as u32;
+    statx.stx_attributes = status.st_mode as u64;
+    statx.stx_nlink = status.st_nlink;
+    statx.stx_uid = status.st_uid;
+    statx.stx_gid = status.st_gid;
+    statx.stx_mode = status.st_mode as u16;
+    statx.stx_ino = status.st_ino;
+    statx.stx_size = status.st_size as u64;
+    statx.stx_blocks = status.st_blocks as u64;
+    statx.stx_attributes_mask = 0x7FF;
+    statx.stx_atime.tv_sec = status.st_atime.tv_sec;
+    statx.stx_atime.tv_nsec = status.st_atime.tv_nsec as u32;
+    statx.stx_ctime.tv_sec = status.st_ctime.tv_sec;
+    statx.stx_ctime.tv_nsec = status.st_ctime.tv_nsec as u32;
+    statx.stx_mtime.tv_sec = status.st_mtime.tv_sec;
+    statx.stx_mtime.tv_nsec = status.st_mtime.tv_nsec as u32;
+    Ok(0)
 }
@@ -0,0 +1,181 @@
+//! The tunables under `/proc/sys` that are backed by kernel state.
+//!
+//! Like the devices, they are opened by path before the VFS, which only holds
+//! empty placeholders so that they show up in directory listings.
+
+use alloc::{
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+
+use arceos_posix_api::{FileLike, ctypes};
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axsync::Mutex;
+
+use crate::imp::{
+    mm::{
+        OVERCOMMIT_NEVER, overcommit_memory, overcommit_ratio, set_overcommit_memory,
+        set_overcommit_ratio,
+    },
+    sys::{hostname, set_hostname},
+};
+
+const O_ACCMODE: i32 = 0o3;
+const O_RDONLY: i32 = 0o0;
+const O_WRONLY: i32 = 0o1;
+
+/// The largest process ID Linux allows. IDs here are never reused and have no
+/// limit of their own, so this is what is reported.
+const PID_MAX_LIMIT: u64 = 4 * 1024 * 1024;
+
+struct Tunable {
+    /// The path under `/proc/sys`.
+    name: &'static str,
+    get: fn() -> String,
+    /// Parses and applies a new value, or `None` if the tunable is read-only.
+    set: Option<fn(&str) -> LinuxResult>,
+}
+
+fn parse_u64(value: &str, range: core::ops::RangeInclusive<u64>) -> LinuxResult<u64> {
+    value
+        .parse()
+        .ok()
+        .filter(|value| range.contains(value))
+        .ok_or(LinuxError::EINVAL)
+}
+
+static TUNABLES: &[Tunable] = &[
+    Tunable {
+        name: "kernel/hostname",
+        get: || String::from_utf8_lossy(&hostname()).into_owned(),
+        set: Some(|value| set_hostname(value.as_bytes())),
+    },
+    Tunable {
+        name: "kernel/ostype",
+        get: || String::from("Linux"),
+        set: None,
+    },
+    Tunable {
+        name: "kernel/pid_max",
+        get: || PID_MAX_LIMIT.to_string(),
+        set: None,
+    },
+    Tunable {
+        // Only each process's table is limited, so the system has no limit.
+        name: "fs/file-max",
+        get: || i64::MAX.to_string(),
+        set: None,
+    },
+    Tunable {
+        name: "fs/pipe-user-pages-hard",
+        get: || arceos_posix_api::pipe_user_pages_limit().to_string(),
+        set: Some(|value| {
+            let value = parse_u64(value, 0..=usize::MAX as u64)?;
+            arceos_posix_api::set_pipe_user_pages_limit(value as usize);
+            Ok(())
+        }),
+    },
+    Tunable {
+        name: "vm/overcommit_memory",
+        get: || overcommit_memory().to_string(),
+        set: Some(|value| {
+            set_overcommit_memory(parse_u64(value, 0..=OVERCOMMIT_NEVER)?);
+            Ok(())
+        }),
+    },
+    Tunable {
+        name: "vm/overcommit_ratio",
+        get: || overcommit_ratio().to_string(),
+        set: Some(|value| {
+            set_overcommit_ratio(parse_u64(value, 0..=i32::MAX as u64)?);
+            Ok(())
+        }),
+    },
+];
+
+/// An open tunable. Reads see the value at the time it was opened, and each
+/// write sets a whole new value.
+struct TunableFile {
+    tunable: &'static Tunable,
+    content: Vec<u8>,
+    offset: Mutex<usize>,
+    readable: bool,
+    writable: bool,
+}
+
+impl FileLike for TunableFile {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        if !self.readable {
+            return Err(LinuxError::EBADF);
+        }
+        let mut offset = self.offset.lock();
+        let remaining = self.content.get(*offset..).unwrap_or_default();
+        let len = remaining.len().min(buf.len());
+        buf[..len].copy_from_slice(&remaining[..len]);
+        *offset += len;
+        Ok(len)
+    }
+
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        if !self.writable {
+            return Err(LinuxError::EBADF);
+        }
+        let set = self.tunable.set.ok_or(LinuxError::EACCES)?;
+        let value = core::str::from_utf8(buf).map_err(|_| LinuxError::EINVAL)?;
+        set(value.trim())?;
+        Ok(buf.len())
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        let perm = if self.tunable.set.is_some() {
+            0o644
+        } else {
+            0o444
+        };
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode: 0o100000 | perm, // S_IFREG
+            st_blksize: 1024,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: true,
+            writable: true,
+        })
+    }
+
+    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+        Ok(())
+    }
+}
+
+/// Opens the tunable at the absolute, canonical `path` with the open `flags`,
+/// or returns `None` if it is not one of ours.
+pub(crate) fn open_tunable(path: &str, flags: i32) -> Option<LinuxResult<Arc<dyn FileLike>>> {
+    let name = path.strip_prefix("/proc/sys/")?;
+    let tunable = TUNABLES.iter().find(|tunable| tunable.name == name)?;
+    let access = flags & O_ACCMODE;
+    let writable = access != O_RDONLY;
+    if writable && tunable.set.is_none() {
+        return Some(Err(LinuxError::EACCES));
+    }
+    let mut content = (tunable.get)().into_bytes();
+    content.push(b'\n');
+    Some(Ok(Arc::new(TunableFile {
+        tunable,
+        content,
+        offset: Mutex::new(0),
+        readable: access != O_WRONLY,
+        writable,
+    })))
+}
@@ -0,0 +1,240 @@
+//! Timers that can be waited for like files, from `timerfd_create`.
+//!
+//! Every clock ticks at the same rate, so a timer always runs on the
+//! monotonic clock, and an absolute time on the real-time clock is turned
+//! into a monotonic one when it is set.
+
+use alloc::sync::Arc;
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use arceos_posix_api::{self as api, FileLike, ctypes, get_file_like};
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::{monotonic_time, wall_time};
+use axio::PollState;
+use axsync::Mutex;
+use starry_core::signal::signal_pending;
+
+use super::fd_ops::set_cloexec;
+use crate::ptr::{PtrWrapper, UserConstPtr, UserPtr};
+
+const CLOCK_REALTIME: i32 = 0;
+const CLOCK_MONOTONIC: i32 = 1;
+const CLOCK_BOOTTIME: i32 = 7;
+
+const TFD_NONBLOCK: i32 = 0o4000;
+const TFD_CLOEXEC: i32 = 0o2000000;
+
+const TFD_TIMER_ABSTIME: i32 = 1;
+const TFD_TIMER_CANCEL_ON_SET: i32 = 2;
+
+/// `struct itimerspec`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ITimerSpec {
+    it_interval: ctypes::timespec,
+    it_value: ctypes::timespec,
+}
+
+fn to_timespec(duration: Duration) -> ctypes::timespec {
+    ctypes::timespec {
+        tv_sec: duration.as_secs() as _,
+        tv_nsec: duration.subsec_nanos() as _,
+    }
+}
+
+fn from_timespec(ts: ctypes::timespec) -> LinuxResult<Duration> {
+    if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(Duration::from(ts))
+}
+
+#[derive(Default)]
+struct Timer {
+    /// When the timer expires next on the monotonic clock, if it is armed.
+    deadline: Option<Duration>,
+    interval: Duration,
+    /// The expirations since the timer was last read.
+    expirations: u64,
+}
+
+impl Timer {
+    /// Counts the expirations up to now.
+    fn update(&mut self) {
+        let Some(deadline) = self.deadline else {
+            return;
+        };
+        let now = monotonic_time();
+        if now < deadline {
+            return;
+        }
+        if self.interval.is_zero() {
+            self.expirations += 1;
+            self.deadline = None;
+        } else {
+            let interval = self.interval.as_nanos();
+            let expired = (now - deadline).as_nanos() / interval + 1;
+            self.expirations += expired as u64;
+            self.deadline = Some(deadline + Duration::from_nanos((expired * interval) as u64));
+        }
+    }
+
+    fn get(&self) -> ITimerSpec {
+        let remaining = self.deadline.map_or(Duration::ZERO, |deadline| {
+            deadline.saturating_sub(monotonic_time())
+        });
+        ITimerSpec {
+            it_interval: to_timespec(self.interval),
+            it_value: to_timespec(remaining),
+        }
+    }
+}
+
+pub struct TimerFd {
+    clock: i32,
+    timer: Mutex<Timer>,
+    nonblocking: AtomicBool,
+}
+
+impl TimerFd {
+    fn from_fd(fd: i32) -> LinuxResult<Arc<Self>> {
+        get_file_like(fd)?
+            .into_any()
+            .downcast::<Self>()
+            .map_err(|_| LinuxError::EINVAL)
+    }
+}
+
+impl FileLike for TimerFd {
+    /// Takes the expirations since the last read, waiting for one if there
+    /// is none.
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        if buf.len() < size_of::<u64>() {
+            return Err(LinuxError::EINVAL);
+        }
+        loop {
+            let expirations = {
+                let mut timer = self.timer.lock();
+                timer.update();
+                core::mem::take(&mut timer.expirations)
+            };
+            if expirations > 0 {
+                buf[..size_of::<u64>()].copy_from_slice(&expirations.to_ne_bytes());
+                return Ok(size_of::<u64>());
+            }
+            if self.nonblocking() {
+                return Err(LinuxError::EAGAIN);
+            }
+            if signal_pending() {
+                return Err(LinuxError::EINTR);
+            }
+            axtask::yield_now();
+        }
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode: 0o600, // rw-------
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        let mut timer = self.timer.lock();
+        timer.update();
+        Ok(PollState {
+            readable: timer.expirations > 0,
+            writable: false,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Release);
+        Ok(())
+    }
+
+    fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Acquire)
+    }
+}
+
+pub fn sys_timerfd_create(clockid: i32, flags: i32) -> LinuxResult<isize> {
+    if !matches!(clockid, CLOCK_REALTIME | CLOCK_MONOTONIC | CLOCK_BOOTTIME) {
+        return Err(LinuxError::EINVAL);
+    }
+    if flags & !(TFD_NONBLOCK | TFD_CLOEXEC) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let timerfd = TimerFd {
+        clock: clockid,
+        timer: Mutex::new(Timer::default()),
+        nonblocking: AtomicBool::new(flags & TFD_NONBLOCK != 0),
+    };
+    let fd = api::add_file_like(Arc::new(timerfd))?;
+    set_cloexec(fd, flags & TFD_CLOEXEC != 0);
+    Ok(fd as _)
+}
+
+/// Arms the timer `fd` to expire at `new_value`, or disarms it if that is
+/// zero, and stores the setting it replaces to `old_value` if not null.
+///
+/// The real-time clock is never set, so `TFD_TIMER_CANCEL_ON_SET` has
+/// nothing to cancel on.
+pub fn sys_timerfd_settime(
+    fd: i32,
+    flags: i32,
+    new_value: UserConstPtr<ITimerSpec>,
+    old_value: UserPtr<ITimerSpec>,
+) -> LinuxResult<isize> {
+    if flags & !(TFD_TIMER_ABSTIME | TFD_TIMER_CANCEL_ON_SET) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let timerfd = TimerFd::from_fd(fd)?;
+    let new_value = unsafe { *new_value.get()? };
+    let interval = from_timespec(new_value.it_interval)?;
+    let value = from_timespec(new_value.it_value)?;
+    let deadline = if value.is_zero() {
+        None
+    } else if flags & TFD_TIMER_ABSTIME == 0 {
+        Some(monotonic_time() + value)
+    } else if timerfd.clock == CLOCK_REALTIME {
+        Some((monotonic_time() + value).saturating_sub(wall_time()))
+    } else {
+        Some(value)
+    };
+
+    let mut timer = timerfd.timer.lock();
+    timer.update();
+    old_value.nullable(|old_value| {
+        old_value
+            .get()
+            .map(|old_value| unsafe { *old_value = timer.get() })
+    })?;
+    *timer = Timer {
+        deadline,
+        interval,
+        expirations: 0,
+    };
+    Ok(0)
+}
+
+pub fn sys_timerfd_gettime(fd: i32, curr_value: UserPtr<ITimerSpec>) -> LinuxResult<isize> {
+    let timerfd = TimerFd::from_fd(fd)?;
+    let mut timer = timerfd.timer.lock();
+    timer.update();
+    unsafe { *curr_value.get()? = timer.get() };
+    Ok(0)
+}
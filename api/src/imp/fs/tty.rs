@@ -0,0 +1,248 @@
+//! Terminal ioctls.
+
+use alloc::{sync::Arc, vec::Vec};
+use core::ffi::c_void;
+
+use arceos_posix_api::FileLike;
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{TaskExtRef, current};
+use starry_core::{
+    signal::{SI_KERNEL, SIGCONT, SIGHUP, SIGTTOU, SigInfo, send_signal_to_group},
+    task::{all_tasks, threads_of},
+    tty::{Pty, PtyMaster, Tty, TtyFile, WinSize, current_tty, termios::Termios},
+};
+
+use crate::ptr::{PtrWrapper, UserPtr};
+
+const TCGETS: usize = 0x5401;
+const TCSETS: usize = 0x5402;
+const TCSETSW: usize = 0x5403;
+const TCSETSF: usize = 0x5404;
+const TIOCSCTTY: usize = 0x540E;
+const TIOCGPGRP: usize = 0x540F;
+const TIOCSPGRP: usize = 0x5410;
+const TIOCSTI: usize = 0x5412;
+const TIOCGWINSZ: usize = 0x5413;
+const TIOCSWINSZ: usize = 0x5414;
+const TIOCNOTTY: usize = 0x5422;
+const TIOCGSID: usize = 0x5429;
+const TIOCGPTN: usize = 0x80045430;
+const TIOCSPTLCK: usize = 0x40045431;
+const TIOCGPTLCK: usize = 0x80045439;
+
+/// Whether `tty` is the controlling terminal of the calling process.
+fn is_current_tty(tty: &'static Tty) -> bool {
+    current_tty().is_some_and(|ctty| core::ptr::eq(ctty, tty))
+}
+
+/// Makes `tty` the controlling terminal of the calling session leader.
+///
+/// A terminal that controls another session can only be taken away with
+/// `steal` set, which requires privileges the caller is assumed to have.
+fn set_controlling_tty(tty: &'static Tty, steal: bool) -> LinuxResult<isize> {
+    let curr = current();
+    let ext = curr.task_ext();
+    let sid = ext.sid();
+    if sid != curr.tgid() {
+        return Err(LinuxError::EPERM);
+    }
+    if is_current_tty(tty) {
+        return Ok(0);
+    }
+    if tty.session() != 0 && !steal {
+        return Err(LinuxError::EPERM);
+    }
+    tty.attach(sid, ext.pgid());
+    for thread in threads_of(curr.tgid()) {
+        thread.task_ext().set_has_tty(true);
+    }
+    Ok(0)
+}
+
+/// Gives up the controlling terminal of the calling process. If it is the
+/// session leader, the whole session loses the terminal and its foreground
+/// process group is hung up.
+fn release_controlling_tty(tty: &'static Tty) -> LinuxResult<isize> {
+    if !is_current_tty(tty) {
+        return Err(LinuxError::ENOTTY);
+    }
+    let curr = current();
+    for thread in threads_of(curr.tgid()) {
+        thread.task_ext().set_has_tty(false);
+    }
+    if curr.task_ext().sid() == curr.tgid() {
+        let foreground = tty.foreground();
+        tty.detach();
+        send_signal_to_group(foreground, SigInfo::new(SIGHUP, SI_KERNEL));
+        send_signal_to_group(foreground, SigInfo::new(SIGCONT, SI_KERNEL));
+    }
+    Ok(0)
+}
+
+/// Stores the session `tty` controls to `argp`.
+fn get_session(tty: &'static Tty, argp: UserPtr<i32>) -> LinuxResult<isize> {
+    if !is_current_tty(tty) {
+        return Err(LinuxError::ENOTTY);
+    }
+    unsafe { *argp.get()? = tty.session() as i32 };
+    Ok(0)
+}
+
+fn get_termios(tty: &'static Tty, argp: UserPtr<Termios>) -> LinuxResult<isize> {
+    unsafe { *argp.get()? = tty.termios() };
+    Ok(0)
+}
+
+fn set_termios(tty: &'static Tty, argp: UserPtr<Termios>, flush: bool) -> LinuxResult<isize> {
+    let termios = unsafe { *argp.get()? };
+    tty.check_background(SIGTTOU)?;
+    tty.set_termios(termios, flush);
+    Ok(0)
+}
+
+fn get_winsize(tty: &'static Tty, argp: UserPtr<WinSize>) -> LinuxResult<isize> {
+    unsafe { *argp.get()? = tty.winsize() };
+    Ok(0)
+}
+
+fn set_winsize(tty: &'static Tty, argp: UserPtr<WinSize>) -> LinuxResult<isize> {
+    let winsize = unsafe { *argp.get()? };
+    tty.set_winsize(winsize);
+    Ok(0)
+}
+
+/// Pushes the byte at `argp` into the input of `tty`, as if it was typed.
+fn simulate_input(tty: &'static Tty, argp: UserPtr<u8>) -> LinuxResult<isize> {
+    let c = unsafe { *argp.get()? };
+    tty.receive(&[c]);
+    Ok(0)
+}
+
+/// Stores the foreground process group of `tty` to `argp`.
+fn get_foreground(tty: &'static Tty, argp: UserPtr<i32>) -> LinuxResult<isize> {
+    if !is_current_tty(tty) {
+        return Err(LinuxError::ENOTTY);
+    }
+    unsafe { *argp.get()? = tty.foreground() as i32 };
+    Ok(0)
+}
+
+/// Puts the process group at `argp`, which must be in the session `tty`
+/// controls, in the foreground.
+fn set_foreground(tty: &'static Tty, argp: UserPtr<i32>) -> LinuxResult<isize> {
+    if !is_current_tty(tty) {
+        return Err(LinuxError::ENOTTY);
+    }
+    let pgid = unsafe { *argp.get()? };
+    tty.check_background(SIGTTOU)?;
+    if pgid < 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let pgid = pgid as u64;
+    let sid = current().task_ext().sid();
+    let group = all_tasks()
+        .into_iter()
+        .filter(|task| task.task_ext().pgid() == pgid)
+        .map(|task| task.task_ext().sid())
+        .collect::<Vec<_>>();
+    if group.is_empty() {
+        return Err(LinuxError::ESRCH);
+    }
+    if group.iter().any(|&other| other != sid) {
+        return Err(LinuxError::EPERM);
+    }
+    tty.set_foreground(pgid);
+    Ok(0)
+}
+
+/// Whether `op` is one of the terminal ioctls, which fail with `ENOTTY` on
+/// anything but a terminal. That is how `isatty` tells a terminal apart,
+/// with `TCGETS`.
+fn is_tty_ioctl(op: usize) -> bool {
+    matches!(
+        op,
+        TCGETS
+            | TCSETS
+            | TCSETSW
+            | TCSETSF
+            | TIOCSCTTY
+            | TIOCGPGRP
+            | TIOCSPGRP
+            | TIOCSTI
+            | TIOCGWINSZ
+            | TIOCSWINSZ
+            | TIOCNOTTY
+            | TIOCGSID
+            | TIOCGPTN
+            | TIOCSPTLCK
+            | TIOCGPTLCK
+    )
+}
+
+/// Handles the ioctl `op` on `file` if it is a terminal or the master of a
+/// pseudoterminal, or returns `None` if it is not a terminal ioctl.
+///
+/// Terminal ioctls on a master apply to its slave.
+pub(crate) fn ioctl(
+    file: Arc<dyn FileLike>,
+    op: usize,
+    argp: UserPtr<c_void>,
+) -> Option<LinuxResult<isize>> {
+    let file = file.into_any();
+    let tty = match file.downcast::<PtyMaster>() {
+        Ok(master) => {
+            let pty = master.pty();
+            if let Some(result) = pty_ioctl(pty, op, argp) {
+                return Some(result);
+            }
+            pty.slave()
+        }
+        Err(file) => match file.downcast::<TtyFile>() {
+            Ok(file) => file.tty(),
+            Err(_) => return is_tty_ioctl(op).then_some(Err(LinuxError::ENOTTY)),
+        },
+    };
+    tty_ioctl(tty, op, argp)
+}
+
+/// Handles the ioctls specific to the master of `pty`.
+fn pty_ioctl(pty: &'static Pty, op: usize, argp: UserPtr<c_void>) -> Option<LinuxResult<isize>> {
+    let argp = UserPtr::<i32>::from(argp.address().as_usize());
+    let result = match op {
+        TIOCGPTN => argp.get().map(|ptr| {
+            unsafe { *ptr = pty.index() as i32 };
+            0
+        }),
+        TIOCSPTLCK => argp.get().map(|ptr| {
+            pty.set_locked(unsafe { *ptr } != 0);
+            0
+        }),
+        TIOCGPTLCK => argp.get().map(|ptr| {
+            unsafe { *ptr = pty.locked() as i32 };
+            0
+        }),
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// Handles the ioctl `op` on the terminal `tty`, or returns `None` if it is
+/// not a terminal ioctl.
+fn tty_ioctl(tty: &'static Tty, op: usize, argp: UserPtr<c_void>) -> Option<LinuxResult<isize>> {
+    let result = match op {
+        TCGETS => get_termios(tty, argp.address().as_usize().into()),
+        // Output is never queued, so waiting for it to drain is a no-op.
+        TCSETS | TCSETSW => set_termios(tty, argp.address().as_usize().into(), false),
+        TCSETSF => set_termios(tty, argp.address().as_usize().into(), true),
+        TIOCGWINSZ => get_winsize(tty, argp.address().as_usize().into()),
+        TIOCSWINSZ => set_winsize(tty, argp.address().as_usize().into()),
+        TIOCSTI => simulate_input(tty, argp.address().as_usize().into()),
+        TIOCGPGRP => get_foreground(tty, argp.address().as_usize().into()),
+        TIOCSPGRP => set_foreground(tty, argp.address().as_usize().into()),
+        TIOCSCTTY => set_controlling_tty(tty, argp.address().as_usize() == 1),
+        TIOCNOTTY => release_controlling_tty(tty),
+        TIOCGSID => get_session(tty, argp.address().as_usize().into()),
+        _ => return None,
+    };
+    Some(result)
+}
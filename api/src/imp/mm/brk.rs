@@ -1,16 +1,49 @@
 use axerrno::LinuxResult;
 use axtask::{TaskExtRef, current};
 use macro_rules_attribute::apply;
+use starry_core::ctypes::{RLIMIT_AS, RLIMIT_DATA};
 
+use super::check_commit;
 use crate::syscall_instrument;
 
+/// The part of the heap area above the program break, which is mapped ahead
+/// of time but not counted towards `RLIMIT_AS` until the break moves into it.
+pub(super) fn unused_heap() -> usize {
+    let curr = current();
+    let heap_end = curr.task_ext().get_heap_bottom() as usize + axconfig::plat::USER_HEAP_SIZE;
+    heap_end.saturating_sub(curr.task_ext().get_heap_top() as usize)
+}
+
 #[apply(syscall_instrument)]
 pub fn sys_brk(addr: usize) -> LinuxResult<isize> {
     let current_task = current();
     let mut return_val: isize = current_task.task_ext().get_heap_top() as isize;
     let heap_bottom = current_task.task_ext().get_heap_bottom() as usize;
-    if addr != 0 && addr >= heap_bottom && addr <= heap_bottom + axconfig::plat::USER_HEAP_SIZE {
+    // The heap is the data segment that `RLIMIT_DATA` limits the growth of.
+    let data_limit = current_task.task_ext().rlimit(RLIMIT_DATA).rlim_cur;
+    // Growing it also grows the address space that `RLIMIT_AS` limits.
+    let fits_address_space = || {
+        let mapped: usize = current_task
+            .task_ext()
+            .aspace
+            .lock()
+            .areas()
+            .map(|(range, _)| range.size())
+            .sum();
+        let size = mapped.saturating_sub(unused_heap()) + (addr - return_val as usize);
+        size as u64 <= current_task.task_ext().rlimit(RLIMIT_AS).rlim_cur
+    };
+    // The heap it grows into is committed as well.
+    let fits_commit = || check_commit(addr - return_val as usize, false).is_ok();
+    if addr != 0
+        && addr >= heap_bottom
+        && addr <= heap_bottom + axconfig::plat::USER_HEAP_SIZE
+        && (addr <= return_val as usize
+            || (addr - heap_bottom) as u64 <= data_limit && fits_address_space() && fits_commit())
+    {
+        let aspace = current_task.task_ext().aspace.lock();
         current_task.task_ext().set_heap_top(addr as u64);
+        current_task.task_ext().recount_commit(&aspace);
         return_val = addr as isize;
     }
     Ok(return_val)
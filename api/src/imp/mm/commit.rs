@@ -0,0 +1,61 @@
+//! The overcommit policy, which decides whether memory can be committed to
+//! user space by `mmap` or `brk` before any of it is touched. It is set through
+//! `/proc/sys/vm/overcommit_memory` and `overcommit_ratio`.
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use axerrno::{LinuxError, LinuxResult};
+use starry_core::mm::committed_size;
+
+use super::mem_usage;
+
+/// Refuse only what could never fit: more than all of the physical memory at
+/// once.
+pub(crate) const OVERCOMMIT_GUESS: u64 = 0;
+/// Refuse nothing.
+pub(crate) const OVERCOMMIT_ALWAYS: u64 = 1;
+/// Refuse what would take the committed memory over the commit limit.
+pub(crate) const OVERCOMMIT_NEVER: u64 = 2;
+
+static OVERCOMMIT_MEMORY: AtomicU64 = AtomicU64::new(OVERCOMMIT_GUESS);
+/// The percentage of the physical memory that can be committed with
+/// `OVERCOMMIT_NEVER`.
+static OVERCOMMIT_RATIO: AtomicU64 = AtomicU64::new(50);
+
+pub(crate) fn overcommit_memory() -> u64 {
+    OVERCOMMIT_MEMORY.load(Ordering::Relaxed)
+}
+
+pub(crate) fn set_overcommit_memory(mode: u64) {
+    OVERCOMMIT_MEMORY.store(mode, Ordering::Relaxed);
+}
+
+pub(crate) fn overcommit_ratio() -> u64 {
+    OVERCOMMIT_RATIO.load(Ordering::Relaxed)
+}
+
+pub(crate) fn set_overcommit_ratio(ratio: u64) {
+    OVERCOMMIT_RATIO.store(ratio, Ordering::Relaxed);
+}
+
+/// The most that can be committed with `OVERCOMMIT_NEVER`, in bytes. There is
+/// no swap to add to it.
+pub(crate) fn commit_limit() -> usize {
+    (mem_usage().total as u128 * overcommit_ratio() as u128 / 100) as usize
+}
+
+/// Checks that `size` more bytes can be committed under the policy, or fails
+/// with `ENOMEM`. With `noreserve`, as for `MAP_NORESERVE`, only
+/// `OVERCOMMIT_NEVER` counts them.
+pub(crate) fn check_commit(size: usize, noreserve: bool) -> LinuxResult {
+    let fits = match overcommit_memory() {
+        OVERCOMMIT_NEVER => committed_size().saturating_add(size) <= commit_limit(),
+        OVERCOMMIT_ALWAYS => true,
+        _ => noreserve || size <= mem_usage().total,
+    };
+    if fits {
+        Ok(())
+    } else {
+        Err(LinuxError::ENOMEM)
+    }
+}
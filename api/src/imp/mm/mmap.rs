@@ -1,10 +1,14 @@
-use alloc::vec;
 use axerrno::{LinuxError, LinuxResult};
 use axhal::paging::MappingFlags;
 use axtask::{TaskExtRef, current};
 use macro_rules_attribute::apply;
-use memory_addr::{VirtAddr, VirtAddrRange};
+use memory_addr::{PAGE_SIZE_4K, VirtAddr, VirtAddrRange};
+use starry_core::{
+    ctypes::{RLIMIT_AS, RLIMIT_DATA},
+    mm::update_user_mappings,
+};
 
+use super::{brk::unused_heap, check_commit};
 use crate::{
     ptr::{PtrWrapper, UserPtr},
     syscall_instrument,
@@ -80,7 +84,6 @@ pub fn sys_mmap(
 
     let curr = current();
     let curr_ext = curr.task_ext();
-    let mut aspace = curr_ext.aspace.lock();
     let permission_flags = MmapProt::from_bits_truncate(prot);
     // TODO: check illegal flags for mmap
     // An example is the flags contained none of MAP_PRIVATE, MAP_SHARED, or MAP_SHARED_VALIDATE.
@@ -102,12 +105,81 @@ pub fn sys_mmap(
         addr, length, permission_flags, map_flags, fd, offset
     );
 
+    // Private writable mappings count as data along with the heap. The other
+    // mappings are not kept count of, so each is checked as if it was the
+    // only one.
+    if map_flags.contains(MmapFlags::MAP_PRIVATE)
+        && !map_flags.contains(MmapFlags::MAP_STACK)
+        && permission_flags.contains(MmapProt::PROT_WRITE)
+    {
+        let heap_size = curr_ext.get_heap_top() - curr_ext.get_heap_bottom();
+        if heap_size + aligned_length as u64 > curr_ext.rlimit(RLIMIT_DATA).rlim_cur {
+            return Err(LinuxError::ENOMEM);
+        }
+    }
+    // Writable private mappings are what is committed.
+    if map_flags.contains(MmapFlags::MAP_PRIVATE) && permission_flags.contains(MmapProt::PROT_WRITE)
+    {
+        check_commit(aligned_length, map_flags.contains(MmapFlags::MAP_NORESERVE))?;
+    }
+
+    let populate = if fd == -1 {
+        false
+    } else {
+        !map_flags.contains(MmapFlags::MAP_ANONYMOUS)
+    };
+    // The file is checked before anything is unmapped or mapped.
+    let file = if populate {
+        let file = arceos_posix_api::get_file_like(fd)?;
+        let file_size = file.stat()?.st_size as usize;
+        let file = file
+            .into_any()
+            .downcast::<arceos_posix_api::File>()
+            .map_err(|_| LinuxError::EBADF)?;
+        if offset < 0 || offset as usize >= file_size {
+            return Err(LinuxError::EINVAL);
+        }
+        Some((file, file_size))
+    } else {
+        None
+    };
+
+    // Declared before the lock, so that the flush is waited for once it is
+    // released, even on errors.
+    let mut _flush = None;
+    let mut aspace = curr_ext.aspace.lock();
+
+    // What a fixed mapping replaces is not counted towards `RLIMIT_AS`, nor
+    // is the heap above the break.
+    let replaced = VirtAddrRange::from_start_size(VirtAddr::from(addr as usize), aligned_length);
+    let mapped: usize = aspace
+        .areas()
+        .map(|(range, _)| {
+            let overlap = if map_flags.contains(MmapFlags::MAP_FIXED) {
+                let start = range.start.max(replaced.start);
+                let end = range.end.min(replaced.end);
+                end.as_usize().saturating_sub(start.as_usize())
+            } else {
+                0
+            };
+            range.size() - overlap
+        })
+        .sum::<usize>()
+        .saturating_sub(unused_heap());
+    if (mapped + aligned_length) as u64 > curr_ext.rlimit(RLIMIT_AS).rlim_cur {
+        return Err(LinuxError::ENOMEM);
+    }
+
     let start_addr = if map_flags.contains(MmapFlags::MAP_FIXED) {
         if addr.is_null() {
             return Err(LinuxError::EINVAL);
         }
         let dst_addr = VirtAddr::from(addr as usize);
-        aspace.unmap(dst_addr, aligned_length)?;
+        let (unmapped, pending) = update_user_mappings(&curr_ext.aspace, &mut aspace, |aspace| {
+            aspace.unmap(dst_addr, aligned_length)
+        });
+        _flush = Some(pending);
+        unmapped?;
         dst_addr
     } else {
         aspace
@@ -124,39 +196,61 @@ pub fn sys_mmap(
             .ok_or(LinuxError::ENOMEM)?
     };
 
-    let populate = if fd == -1 {
-        false
+    // Anonymous shared mappings stay shared with the children forked later.
+    // Those of files are copies still, as the pages are not shared with the
+    // file.
+    if map_flags.contains(MmapFlags::MAP_SHARED) && file.is_none() {
+        aspace.map_shared(start_addr, aligned_length, permission_flags.into())?;
     } else {
-        !map_flags.contains(MmapFlags::MAP_ANONYMOUS)
-    };
-
-    aspace.map_alloc(
-        start_addr,
-        aligned_length,
-        permission_flags.into(),
-        populate,
-    )?;
+        // Running out of memory for a populated mapping fails with `ENOMEM`.
+        aspace.map_alloc(
+            start_addr,
+            aligned_length,
+            permission_flags.into(),
+            populate,
+        )?;
+    }
+    curr_ext.recount_commit(&aspace);
 
-    if populate {
-        let file = arceos_posix_api::get_file_like(fd)?;
-        let file_size = file.stat()?.st_size as usize;
-        let file = file
-            .into_any()
-            .downcast::<arceos_posix_api::File>()
-            .map_err(|_| LinuxError::EBADF)?;
-        let file = file.inner().lock();
-        if offset < 0 || offset as usize >= file_size {
-            return Err(LinuxError::EINVAL);
-        }
+    if let Some((file, file_size)) = file {
         let offset = offset as usize;
         let length = core::cmp::min(length, file_size - offset);
-        let mut buf = vec![0u8; length];
-        file.read_at(offset as u64, &mut buf)?;
-        aspace.write(start_addr, &buf)?;
+        let read = read_file_into(&file, offset, length, |done, data| {
+            Ok(aspace.write(start_addr + done, data)?)
+        });
+        if let Err(err) = read {
+            aspace.unmap(start_addr, aligned_length)?;
+            curr_ext.recount_commit(&aspace);
+            return Err(err);
+        }
     }
     Ok(start_addr.as_usize() as _)
 }
 
+/// Reads `length` bytes of `file` from `offset`, handing each piece to
+/// `write` along with how far into them it is. It goes a page at a time
+/// rather than through a buffer of the whole length on the kernel heap.
+fn read_file_into(
+    file: &arceos_posix_api::File,
+    offset: usize,
+    length: usize,
+    mut write: impl FnMut(usize, &[u8]) -> LinuxResult<()>,
+) -> LinuxResult<()> {
+    let file = file.inner().lock();
+    let mut buf = [0u8; PAGE_SIZE_4K];
+    let mut done = 0;
+    while done < length {
+        let chunk = (length - done).min(PAGE_SIZE_4K);
+        let read = file.read_at((offset + done) as u64, &mut buf[..chunk])?;
+        if read == 0 {
+            break;
+        }
+        write(done, &buf[..read])?;
+        done += read;
+    }
+    Ok(())
+}
+
 #[apply(syscall_instrument)]
 pub fn sys_munmap(addr: UserPtr<usize>, length: usize) -> LinuxResult<isize> {
     // Safety: addr is used for mapping, and we won't directly access it.
@@ -167,8 +261,13 @@ pub fn sys_munmap(addr: UserPtr<usize>, length: usize) -> LinuxResult<isize> {
     let mut aspace = curr_ext.aspace.lock();
     let length = memory_addr::align_up_4k(length);
     let start_addr = VirtAddr::from(addr as usize);
-    aspace.unmap(start_addr, length)?;
-    axhal::arch::flush_tlb(None);
+    let (unmapped, flush) = update_user_mappings(&curr_ext.aspace, &mut aspace, |aspace| {
+        aspace.unmap(start_addr, length)
+    });
+    curr_ext.recount_commit(&aspace);
+    drop(aspace);
+    drop(flush);
+    unmapped?;
     Ok(0)
 }
 
@@ -190,7 +289,119 @@ pub fn sys_mprotect(addr: UserPtr<usize>, length: usize, prot: i32) -> LinuxResu
     let mut aspace = curr_ext.aspace.lock();
     let length = memory_addr::align_up_4k(length);
     let start_addr = VirtAddr::from(addr as usize);
-    aspace.protect(start_addr, length, permission_flags.into())?;
+    let (protected, flush) = update_user_mappings(&curr_ext.aspace, &mut aspace, |aspace| {
+        aspace.protect(start_addr, length, permission_flags.into())
+    });
+    curr_ext.recount_commit(&aspace);
+    drop(aspace);
+    drop(flush);
+    protected?;
 
     Ok(0)
 }
+
+const MADV_NORMAL: i32 = 0;
+const MADV_RANDOM: i32 = 1;
+const MADV_SEQUENTIAL: i32 = 2;
+const MADV_WILLNEED: i32 = 3;
+const MADV_DONTNEED: i32 = 4;
+const MADV_FREE: i32 = 8;
+const MADV_HUGEPAGE: i32 = 14;
+const MADV_NOHUGEPAGE: i32 = 15;
+
+/// Takes advice on how the pages at `addr` are used.
+///
+/// `MADV_HUGEPAGE` has untouched parts of the range that are aligned to huge
+/// pages backed with them on the next fault, and `MADV_NOHUGEPAGE` stops
+/// that; pages already there are kept. `MADV_DONTNEED` gives back the pages
+/// of private anonymous mappings, which then read back as zeros, and
+/// `MADV_FREE` does the same at once rather than under memory pressure. The
+/// other mappings keep their contents. The hints on the access pattern are
+/// accepted and ignored, as there is no paging to disk. It fails with
+/// `ENOMEM` if part of the range is not mapped.
+#[apply(syscall_instrument)]
+pub fn sys_madvise(addr: usize, length: usize, advice: i32) -> LinuxResult<isize> {
+    if !memory_addr::is_aligned_4k(addr) {
+        return Err(LinuxError::EINVAL);
+    }
+    if !matches!(
+        advice,
+        MADV_NORMAL
+            | MADV_RANDOM
+            | MADV_SEQUENTIAL
+            | MADV_WILLNEED
+            | MADV_DONTNEED
+            | MADV_FREE
+            | MADV_HUGEPAGE
+            | MADV_NOHUGEPAGE
+    ) {
+        return Err(LinuxError::EINVAL);
+    }
+    let length = memory_addr::align_up_4k(length);
+    if length == 0 {
+        return Ok(0);
+    }
+
+    let curr = current();
+    let mut aspace = curr.task_ext().aspace.lock();
+    let start = VirtAddr::from(addr);
+    if !aspace.contains_range(start, length)
+        || !aspace.check_region_access(
+            VirtAddrRange::from_start_size(start, length),
+            MappingFlags::empty(),
+        )
+    {
+        return Err(LinuxError::ENOMEM);
+    }
+    match advice {
+        MADV_DONTNEED | MADV_FREE => {
+            let (discarded, flush) =
+                update_user_mappings(&curr.task_ext().aspace, &mut aspace, |aspace| {
+                    aspace.discard_lazy(start, length)
+                });
+            drop(aspace);
+            drop(flush);
+            discarded?
+        }
+        MADV_HUGEPAGE => aspace.advise_huge(start, length, true)?,
+        MADV_NOHUGEPAGE => aspace.advise_huge(start, length, false)?,
+        _ => {}
+    }
+    Ok(0)
+}
+
+/// Stores to `vec` a byte for each page at `addr` in the `length` bytes,
+/// with bit 0 set if the page is resident, that is, backed by a frame. Lazy
+/// pages are not until first touched. It fails with `ENOMEM` if part of the
+/// range is not mapped.
+#[apply(syscall_instrument)]
+pub fn sys_mincore(addr: usize, length: usize, vec: UserPtr<u8>) -> LinuxResult<isize> {
+    if !memory_addr::is_aligned_4k(addr) {
+        return Err(LinuxError::EINVAL);
+    }
+    let pages = length.div_ceil(PAGE_SIZE_4K);
+    // The vector is checked before the address space is locked.
+    let vec = vec.get_as_mut_slice(pages)?;
+    if pages == 0 {
+        return Ok(0);
+    }
+
+    let curr = current();
+    let aspace = curr.task_ext().aspace.lock();
+    let start = VirtAddr::from(addr);
+    let length = pages * PAGE_SIZE_4K;
+    if addr.checked_add(length).is_none()
+        || !aspace.contains_range(start, length)
+        || !aspace.check_region_access(
+            VirtAddrRange::from_start_size(start, length),
+            MappingFlags::empty(),
+        )
+    {
+        return Err(LinuxError::ENOMEM);
+    }
+    let pt = aspace.page_table();
+    for (index, resident) in vec.iter_mut().enumerate() {
+        *resident = pt.query(start + index * PAGE_SIZE_4K).is_ok() as u8;
+    }
+    Ok(0)
+}
@@ -1,5 +1,9 @@
 mod brk;
+mod commit;
 mod mmap;
+mod usage;
 
 pub use self::brk::*;
+pub(crate) use self::commit::*;
 pub use self::mmap::*;
+pub(crate) use self::usage::mem_usage;
@@ -0,0 +1,29 @@
+//! How much of the physical memory is in use, as `sysinfo` and
+//! `/proc/meminfo` report it.
+
+use memory_addr::PAGE_SIZE_4K;
+
+/// The sizes of the physical memory, in bytes.
+pub(crate) struct MemUsage {
+    /// All that the page allocator was given.
+    pub total: usize,
+    /// The pages not allocated.
+    pub free: usize,
+    /// What could be allocated without taking memory from anyone: the free
+    /// pages, and the free space of those the kernel heap has taken.
+    pub available: usize,
+    /// The kernel heap in use.
+    pub slab: usize,
+}
+
+pub(crate) fn mem_usage() -> MemUsage {
+    let allocator = axalloc::global_allocator();
+    let free_pages = allocator.available_pages();
+    let free = free_pages * PAGE_SIZE_4K;
+    MemUsage {
+        total: (allocator.used_pages() + free_pages) * PAGE_SIZE_4K,
+        free,
+        available: free + allocator.available_bytes(),
+        slab: allocator.used_bytes(),
+    }
+}
@@ -1,8 +1,9 @@
 mod fs;
 mod mm;
+mod net;
 mod signal;
 mod sys;
 mod task;
 mod utils;
 
-pub use self::{fs::*, mm::*, signal::*, sys::*, task::*, utils::*};
+pub use self::{fs::*, mm::*, net::*, signal::*, sys::*, task::*, utils::*};
@@ -0,0 +1,113 @@
+//! Addresses of TCP and UDP sockets on the loopback network.
+//!
+//! There is no other network to reach, and all of `127.0.0.0/8` is taken as
+//! one host, so a socket is known by its port alone.
+
+use alloc::vec::Vec;
+
+use axerrno::{LinuxError, LinuxResult};
+
+use super::stream::Address;
+use crate::ptr::{PtrWrapper, UserConstPtr};
+
+pub(super) const AF_INET: i32 = 2;
+pub(super) const IPPROTO_TCP: i32 = 6;
+pub(super) const IPPROTO_UDP: i32 = 17;
+
+/// The length of `struct sockaddr_in`.
+const SOCKADDR_IN_LEN: usize = 16;
+
+const INADDR_ANY: [u8; 4] = [0, 0, 0, 0];
+const INADDR_LOOPBACK: [u8; 4] = [127, 0, 0, 1];
+
+/// The ports handed out to sockets that are not bound to one.
+const EPHEMERAL_PORTS: core::ops::RangeInclusive<u16> = 32768..=60999;
+
+/// The address `ip:port`.
+fn address(ip: [u8; 4], port: u16) -> Address {
+    let mut name = Vec::with_capacity(SOCKADDR_IN_LEN - 2);
+    name.extend_from_slice(&port.to_be_bytes());
+    name.extend_from_slice(&ip);
+    name.resize(SOCKADDR_IN_LEN - 2, 0);
+    let mut key = Vec::from([AF_INET as u8]);
+    key.extend_from_slice(&port.to_be_bytes());
+    Address { name, key }
+}
+
+/// What an unbound socket reports as its address: `0.0.0.0:0`.
+pub(super) const UNBOUND: [u8; SOCKADDR_IN_LEN - 2] = [0; SOCKADDR_IN_LEN - 2];
+
+/// Parses the `struct sockaddr_in` at `addr` into the IP address and port.
+fn parse(addr: UserConstPtr<u8>, addrlen: u32) -> LinuxResult<([u8; 4], u16)> {
+    let addrlen = addrlen as usize;
+    if addrlen < SOCKADDR_IN_LEN {
+        return Err(LinuxError::EINVAL);
+    }
+    let addr = addr.get_as_bytes(SOCKADDR_IN_LEN)?;
+    let addr = unsafe { core::slice::from_raw_parts(addr, SOCKADDR_IN_LEN) };
+    if u16::from_ne_bytes([addr[0], addr[1]]) != AF_INET as u16 {
+        return Err(LinuxError::EAFNOSUPPORT);
+    }
+    let port = u16::from_be_bytes([addr[2], addr[3]]);
+    Ok(([addr[4], addr[5], addr[6], addr[7]], port))
+}
+
+/// Parses an address to bind to, which must be on this host. A port of 0
+/// is left for [`ephemeral`] to fill in.
+pub(super) fn parse_local(addr: UserConstPtr<u8>, addrlen: u32) -> LinuxResult<Address> {
+    let (ip, port) = parse(addr, addrlen)?;
+    if ip != INADDR_ANY && ip[0] != 127 {
+        return Err(LinuxError::EADDRNOTAVAIL);
+    }
+    Ok(address(ip, port))
+}
+
+/// Parses an address to connect to, where connecting to `0.0.0.0` means this
+/// host, as on Linux.
+pub(super) fn parse_peer(addr: UserConstPtr<u8>, addrlen: u32) -> LinuxResult<Address> {
+    let (ip, port) = parse(addr, addrlen)?;
+    if ip == INADDR_ANY {
+        return Ok(address(INADDR_LOOPBACK, port));
+    }
+    if ip[0] != 127 {
+        return Err(LinuxError::ENETUNREACH);
+    }
+    Ok(address(ip, port))
+}
+
+/// The address a socket is bound to when it listens or connects without
+/// being bound: any address for a listener, or else the loopback one.
+pub(super) fn unspecified(listening: bool) -> Address {
+    address(
+        if listening {
+            INADDR_ANY
+        } else {
+            INADDR_LOOPBACK
+        },
+        0,
+    )
+}
+
+/// The name that datagrams sent from a socket bound to `addr` carry as their
+/// source. Bound to any address, they leave from the loopback one.
+pub(super) fn source(addr: &Address) -> Vec<u8> {
+    if addr.name[2..6] == INADDR_ANY {
+        address(INADDR_LOOPBACK, port(addr)).name
+    } else {
+        addr.name.clone()
+    }
+}
+
+/// The port of `addr`.
+pub(super) fn port(addr: &Address) -> u16 {
+    u16::from_be_bytes([addr.name[0], addr.name[1]])
+}
+
+/// Gives `addr` the first ephemeral port that is not `in_use`.
+pub(super) fn ephemeral(addr: &Address, in_use: impl Fn(&[u8]) -> bool) -> LinuxResult<Address> {
+    let ip = [addr.name[2], addr.name[3], addr.name[4], addr.name[5]];
+    EPHEMERAL_PORTS
+        .map(|port| address(ip, port))
+        .find(|addr| !in_use(&addr.key))
+        .ok_or(LinuxError::EADDRINUSE)
+}
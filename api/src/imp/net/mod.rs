@@ -0,0 +1,11 @@
+mod inet;
+mod msg;
+mod proc;
+mod socket;
+mod sockopt;
+mod stream;
+mod udp;
+mod unix;
+
+pub(crate) use self::proc::{tcp_table, udp_table};
+pub use self::{msg::*, socket::*, sockopt::*};
@@ -0,0 +1,260 @@
+//! Messages with ancillary data, for `sendmsg` and `recvmsg` on Unix sockets.
+
+use alloc::{sync::Arc, vec, vec::Vec};
+use core::{ffi::c_int, mem, slice};
+
+use arceos_posix_api::{FD_TABLE, FileLike, add_file_like, ctypes, get_file_like};
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{TaskExtRef, current};
+
+use super::stream::{BUFFER_SIZE, Control, Family, SOL_SOCKET, StreamSocket, UCred};
+use crate::ptr::{PtrWrapper, UserConstPtr, UserPtr};
+
+const SCM_RIGHTS: i32 = 1;
+const SCM_CREDENTIALS: i32 = 2;
+
+const MSG_CTRUNC: i32 = 0x8;
+const MSG_DONTWAIT: i32 = 0x40;
+const MSG_NOSIGNAL: i32 = 0x4000;
+const MSG_CMSG_CLOEXEC: i32 = 0x40000000;
+
+/// The most iovecs a message can have.
+const UIO_MAXIOV: usize = 1024;
+/// The most files a control message can carry.
+const SCM_MAX_FD: usize = 253;
+
+/// A message for `sendmsg` and `recvmsg`, in the layout of `struct msghdr`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct MsgHdr {
+    msg_name: usize,
+    msg_namelen: u32,
+    msg_iov: usize,
+    msg_iovlen: usize,
+    msg_control: usize,
+    msg_controllen: usize,
+    msg_flags: i32,
+}
+
+/// The header of a control message, `struct cmsghdr`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct CmsgHdr {
+    cmsg_len: usize,
+    cmsg_level: i32,
+    cmsg_type: i32,
+}
+
+const CMSG_HDR_LEN: usize = mem::size_of::<CmsgHdr>();
+
+/// Rounds `len` up to the alignment of control messages, as `CMSG_ALIGN`.
+const fn cmsg_align(len: usize) -> usize {
+    (len + mem::size_of::<usize>() - 1) & !(mem::size_of::<usize>() - 1)
+}
+
+fn iovecs(msg: &MsgHdr) -> LinuxResult<&[ctypes::iovec]> {
+    if msg.msg_iovlen > UIO_MAXIOV {
+        return Err(LinuxError::EMSGSIZE);
+    }
+    UserConstPtr::<ctypes::iovec>::from(msg.msg_iov).get_as_slice(msg.msg_iovlen)
+}
+
+/// Parses the control messages of `msg` to send.
+///
+/// Credentials are checked as for an unprivileged process: a sender can only
+/// pass its own.
+fn parse_control(msg: &MsgHdr) -> LinuxResult<Control> {
+    let mut control = Control::default();
+    if msg.msg_controllen == 0 {
+        return Ok(control);
+    }
+    let buf = UserConstPtr::<u8>::from(msg.msg_control).get_as_bytes(msg.msg_controllen)?;
+    let buf = unsafe { slice::from_raw_parts(buf, msg.msg_controllen) };
+    let mut offset = 0;
+    while offset + CMSG_HDR_LEN <= buf.len() {
+        let header = unsafe { buf.as_ptr().add(offset).cast::<CmsgHdr>().read_unaligned() };
+        if header.cmsg_len < CMSG_HDR_LEN || header.cmsg_len > buf.len() - offset {
+            return Err(LinuxError::EINVAL);
+        }
+        let data = &buf[offset + CMSG_HDR_LEN..offset + header.cmsg_len];
+        // Messages for other levels are not meant for this socket.
+        match (header.cmsg_level, header.cmsg_type) {
+            (SOL_SOCKET, SCM_RIGHTS) => {
+                for fd in data.chunks_exact(mem::size_of::<i32>()) {
+                    let fd = i32::from_ne_bytes(fd.try_into().unwrap());
+                    control.files.push(get_file_like(fd)?);
+                }
+                if control.files.len() > SCM_MAX_FD {
+                    return Err(LinuxError::EINVAL);
+                }
+            }
+            (SOL_SOCKET, SCM_CREDENTIALS) => {
+                if data.len() != mem::size_of::<UCred>() {
+                    return Err(LinuxError::EINVAL);
+                }
+                let cred = unsafe { data.as_ptr().cast::<UCred>().read_unaligned() };
+                if cred != UCred::current() {
+                    return Err(LinuxError::EPERM);
+                }
+                control.cred = Some(cred);
+            }
+            (SOL_SOCKET, _) => return Err(LinuxError::EINVAL),
+            _ => {}
+        }
+        offset += cmsg_align(header.cmsg_len);
+    }
+    Ok(control)
+}
+
+/// Fills the control buffer of a received message.
+struct ControlWriter {
+    buf: *mut u8,
+    capacity: usize,
+    len: usize,
+    flags: i32,
+}
+
+impl ControlWriter {
+    fn new(msg: &MsgHdr) -> LinuxResult<Self> {
+        let buf = if msg.msg_controllen > 0 {
+            UserPtr::<u8>::from(msg.msg_control).get_as_bytes(msg.msg_controllen)?
+        } else {
+            core::ptr::null_mut()
+        };
+        Ok(Self {
+            buf,
+            capacity: msg.msg_controllen,
+            len: 0,
+            flags: 0,
+        })
+    }
+
+    /// The room left for the data of another message.
+    fn room(&self) -> usize {
+        (self.capacity - self.len).saturating_sub(CMSG_HDR_LEN)
+    }
+
+    /// Appends a message carrying `data`, truncated to the room left.
+    fn put<T: Copy>(&mut self, level: i32, ty: i32, data: &[T]) {
+        let data_len = mem::size_of_val(data);
+        let space = self.capacity - self.len;
+        if space < CMSG_HDR_LEN {
+            self.flags |= MSG_CTRUNC;
+            return;
+        }
+        let len = (CMSG_HDR_LEN + data_len).min(space);
+        if len < CMSG_HDR_LEN + data_len {
+            self.flags |= MSG_CTRUNC;
+        }
+        let header = CmsgHdr {
+            cmsg_len: len,
+            cmsg_level: level,
+            cmsg_type: ty,
+        };
+        unsafe {
+            let dst = self.buf.add(self.len);
+            dst.cast::<CmsgHdr>().write_unaligned(header);
+            dst.add(CMSG_HDR_LEN)
+                .copy_from_nonoverlapping(data.as_ptr().cast(), len - CMSG_HDR_LEN);
+        }
+        self.len += cmsg_align(len).min(space);
+    }
+}
+
+/// Installs the received `files` in the fd table and passes their fds in an
+/// `SCM_RIGHTS` message, to be closed on exec if `cloexec` is set.
+///
+/// Files that do not fit in the buffer or the fd table are closed, and the
+/// message is flagged as truncated.
+fn install_files(writer: &mut ControlWriter, files: Vec<Arc<dyn FileLike>>, cloexec: bool) {
+    if files.is_empty() {
+        return;
+    }
+    let count = files.len().min(writer.room() / mem::size_of::<i32>());
+    if count < files.len() {
+        writer.flags |= MSG_CTRUNC;
+    }
+    let fd_limit = current().task_ext().get_fd_limit() as c_int;
+    let mut fds = Vec::with_capacity(count);
+    for file in files.into_iter().take(count) {
+        match add_file_like(file) {
+            Ok(fd) if fd < fd_limit => {
+                FD_TABLE.write().set_cloexec(fd as usize, cloexec);
+                fds.push(fd);
+            }
+            Ok(fd) => {
+                FD_TABLE.write().remove(fd as usize);
+                writer.flags |= MSG_CTRUNC;
+                break;
+            }
+            Err(_) => {
+                writer.flags |= MSG_CTRUNC;
+                break;
+            }
+        }
+    }
+    if !fds.is_empty() {
+        writer.put(SOL_SOCKET, SCM_RIGHTS, &fds);
+    }
+}
+
+pub fn sys_sendmsg(fd: i32, msg: UserConstPtr<MsgHdr>, flags: i32) -> LinuxResult<isize> {
+    let socket = StreamSocket::from_fd(fd)?;
+    let msg = unsafe { *msg.get()? };
+    let mut data = Vec::new();
+    for iov in iovecs(&msg)? {
+        if iov.iov_len == 0 {
+            continue;
+        }
+        let base = UserConstPtr::<u8>::from(iov.iov_base as usize).get_as_bytes(iov.iov_len)?;
+        data.extend_from_slice(unsafe { slice::from_raw_parts(base, iov.iov_len) });
+    }
+    let control = parse_control(&msg)?;
+    // Only Unix sockets pass files and credentials.
+    if socket.family() != Family::Unix && (!control.files.is_empty() || control.cred.is_some()) {
+        return Err(LinuxError::EINVAL);
+    }
+    let len = socket.send(
+        &data,
+        control,
+        flags & MSG_DONTWAIT != 0,
+        flags & MSG_NOSIGNAL != 0,
+    )?;
+    Ok(len as _)
+}
+
+pub fn sys_recvmsg(fd: i32, msg: UserPtr<MsgHdr>, flags: i32) -> LinuxResult<isize> {
+    let socket = StreamSocket::from_fd(fd)?;
+    let msg = msg.get()?;
+    let header = unsafe { *msg };
+    let iovecs = iovecs(&header)?;
+    let mut writer = ControlWriter::new(&header)?;
+    let capacity = iovecs.iter().map(|iov| iov.iov_len).sum::<usize>();
+    // A stream socket may return less than asked for, so there is no need to
+    // take more than a full buffer at once.
+    let mut buf = vec![0; capacity.min(BUFFER_SIZE)];
+    let (len, control) = socket.recv(&mut buf, flags & MSG_DONTWAIT != 0)?;
+
+    let mut copied = 0;
+    for iov in iovecs {
+        if copied == len {
+            break;
+        }
+        let n = iov.iov_len.min(len - copied);
+        let base = UserPtr::<u8>::from(iov.iov_base as usize).get_as_bytes(n)?;
+        unsafe { base.copy_from_nonoverlapping(buf[copied..].as_ptr(), n) };
+        copied += n;
+    }
+
+    let pass_cred = socket.family() == Family::Unix && socket.pass_cred();
+    if let Some(cred) = control.cred.filter(|_| pass_cred) {
+        writer.put(SOL_SOCKET, SCM_CREDENTIALS, &[cred]);
+    }
+    install_files(&mut writer, control.files, flags & MSG_CMSG_CLOEXEC != 0);
+    unsafe {
+        (*msg).msg_namelen = 0;
+        (*msg).msg_controllen = writer.len;
+        (*msg).msg_flags = writer.flags;
+    }
+    Ok(len as _)
+}
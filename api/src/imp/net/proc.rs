@@ -0,0 +1,72 @@
+//! The socket tables `/proc/net/tcp` and `/proc/net/udp`.
+
+use alloc::{format, string::String, vec::Vec};
+use core::fmt::Write;
+
+use super::{stream, udp};
+
+pub(super) const TCP_ESTABLISHED: u8 = 0x01;
+pub(super) const TCP_TIME_WAIT: u8 = 0x06;
+pub(super) const TCP_CLOSE: u8 = 0x07;
+pub(super) const TCP_CLOSE_WAIT: u8 = 0x08;
+pub(super) const TCP_LISTEN: u8 = 0x0a;
+
+/// A line of a socket table.
+pub(super) struct TableEntry {
+    /// The addresses of the socket and of its peer, as a `struct sockaddr_in`
+    /// after the family, or `None` if there is none.
+    pub local: Option<Vec<u8>>,
+    pub remote: Option<Vec<u8>>,
+    /// The state, in the numbering of TCP states even for UDP.
+    pub state: u8,
+    /// The bytes waiting to be received by the peer and by the socket.
+    pub tx_queue: usize,
+    pub rx_queue: usize,
+    /// The effective user ID of the creator.
+    pub uid: u32,
+    pub ino: u64,
+}
+
+/// Formats an IPv4 address as the hexadecimal IP, in the byte order of
+/// memory on a little-endian machine, and port.
+fn format_addr(name: Option<&[u8]>) -> String {
+    match name {
+        Some(name) => format!(
+            "{:08X}:{:04X}",
+            u32::from_le_bytes([name[2], name[3], name[4], name[5]]),
+            u16::from_be_bytes([name[0], name[1]]),
+        ),
+        None => String::from("00000000:0000"),
+    }
+}
+
+fn format_table(entries: Vec<TableEntry>) -> String {
+    let mut table = String::from(
+        "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n",
+    );
+    for (sl, entry) in entries.into_iter().enumerate() {
+        // There are no timers or retransmits on loopback.
+        let _ = writeln!(
+            table,
+            "{:4}: {} {} {:02X} {:08X}:{:08X} 00:00000000 00000000 {:5} {:8} {}",
+            sl,
+            format_addr(entry.local.as_deref()),
+            format_addr(entry.remote.as_deref()),
+            entry.state,
+            entry.tx_queue,
+            entry.rx_queue,
+            entry.uid,
+            0,
+            entry.ino,
+        );
+    }
+    table
+}
+
+pub(crate) fn tcp_table() -> String {
+    format_table(stream::tcp_entries())
+}
+
+pub(crate) fn udp_table() -> String {
+    format_table(udp::udp_entries())
+}
@@ -0,0 +1,295 @@
+//! The syscalls that create sockets and deal with their addresses, for all
+//! kinds of sockets.
+
+use alloc::sync::Arc;
+use core::{
+    ffi::c_int,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use arceos_posix_api::{self as api, FD_TABLE, FileLike, add_file_like};
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{TaskExtRef, current};
+
+use super::{
+    inet::{self, AF_INET, IPPROTO_TCP, IPPROTO_UDP},
+    stream::{Control, Family, SHUT_RD, SHUT_RDWR, StreamSocket},
+    udp::UdpSocket,
+    unix::AF_UNIX,
+};
+use crate::ptr::{PtrWrapper, UserConstPtr, UserPtr};
+
+const SOCK_STREAM: i32 = 1;
+const SOCK_DGRAM: i32 = 2;
+const SOCK_NONBLOCK: i32 = 0o4000;
+const SOCK_CLOEXEC: i32 = 0o2000000;
+
+const MSG_TRUNC: i32 = 0x20;
+const MSG_DONTWAIT: i32 = 0x40;
+const MSG_NOSIGNAL: i32 = 0x4000;
+
+/// Hands out the inode numbers that sockets report, which are what
+/// `/proc/net` lists them by.
+pub(super) fn next_ino() -> u64 {
+    static NEXT_INO: AtomicU64 = AtomicU64::new(1);
+    NEXT_INO.fetch_add(1, Ordering::Relaxed)
+}
+
+/// A socket of any kind.
+pub(super) enum Socket {
+    Stream(Arc<StreamSocket>),
+    Udp(Arc<UdpSocket>),
+}
+
+impl Socket {
+    pub(super) fn from_fd(fd: c_int) -> LinuxResult<Self> {
+        match StreamSocket::from_fd(fd) {
+            Err(LinuxError::ENOTSOCK) => UdpSocket::from_fd(fd).map(Self::Udp),
+            result => result.map(Self::Stream),
+        }
+    }
+
+    fn family(&self) -> Family {
+        match self {
+            Self::Stream(socket) => socket.family(),
+            Self::Udp(_) => Family::Inet,
+        }
+    }
+
+    pub(super) fn reuse_addr(&self) -> bool {
+        match self {
+            Self::Stream(socket) => socket.reuse_addr(),
+            Self::Udp(socket) => socket.reuse_addr(),
+        }
+    }
+
+    pub(super) fn set_reuse_addr(&self, reuse_addr: bool) {
+        match self {
+            Self::Stream(socket) => socket.set_reuse_addr(reuse_addr),
+            Self::Udp(socket) => socket.set_reuse_addr(reuse_addr),
+        }
+    }
+}
+
+/// Splits the type of a new socket into the type proper, whether it is to be
+/// non-blocking and whether it is closed on exec.
+fn split_type(ty: i32) -> (i32, bool, bool) {
+    (
+        ty & !(SOCK_NONBLOCK | SOCK_CLOEXEC),
+        ty & SOCK_NONBLOCK != 0,
+        ty & SOCK_CLOEXEC != 0,
+    )
+}
+
+/// Adds `socket` to the fd table, within the fd limit, to be closed on exec
+/// if `cloexec` is set.
+fn add_socket(socket: Arc<dyn FileLike>, cloexec: bool) -> LinuxResult<c_int> {
+    let fd = add_file_like(socket)?;
+    let mut table = FD_TABLE.write();
+    if fd >= current().task_ext().get_fd_limit() as c_int {
+        table.remove(fd as usize);
+        return Err(LinuxError::EMFILE);
+    }
+    table.set_cloexec(fd as usize, cloexec);
+    Ok(fd)
+}
+
+/// Stores `name` of `family`, or an unbound address if it is `None`, as a
+/// `struct sockaddr` to `addr`, truncated to the length at `addrlen`, which is
+/// updated to the full length.
+fn put_addr(
+    family: Family,
+    name: Option<&[u8]>,
+    addr: UserPtr<u8>,
+    addrlen: UserPtr<u32>,
+) -> LinuxResult {
+    let addrlen = addrlen.get()?;
+    let mut full = (family.domain() as u16).to_ne_bytes().to_vec();
+    full.extend_from_slice(name.unwrap_or(family.unbound()));
+    let len = unsafe { *addrlen } as i32;
+    if len < 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let len = (len as usize).min(full.len());
+    let addr = addr.get_as_bytes(len)?;
+    unsafe {
+        addr.copy_from_nonoverlapping(full.as_ptr(), len);
+        *addrlen = full.len() as u32;
+    }
+    Ok(())
+}
+
+/// Creates a Unix stream socket, or a TCP or UDP one, which only reaches the
+/// loopback network.
+pub fn sys_socket(domain: i32, ty: i32, protocol: i32) -> LinuxResult<isize> {
+    let (ty, nonblocking, cloexec) = split_type(ty);
+    let socket: Arc<dyn FileLike> = match (domain, ty, protocol) {
+        (AF_UNIX, SOCK_STREAM, 0) => Arc::new(StreamSocket::new(Family::Unix, nonblocking)),
+        (AF_INET, SOCK_STREAM, 0 | IPPROTO_TCP) => {
+            Arc::new(StreamSocket::new(Family::Inet, nonblocking))
+        }
+        (AF_INET, SOCK_DGRAM, 0 | IPPROTO_UDP) => Arc::new(UdpSocket::new(nonblocking)),
+        (AF_UNIX | AF_INET, SOCK_STREAM | SOCK_DGRAM, _) => {
+            return Err(LinuxError::EPROTONOSUPPORT);
+        }
+        (AF_UNIX | AF_INET, ..) => return Err(LinuxError::EOPNOTSUPP),
+        _ => return Err(LinuxError::EAFNOSUPPORT),
+    };
+    Ok(add_socket(socket, cloexec)? as _)
+}
+
+pub fn sys_socketpair(domain: i32, ty: i32, protocol: i32, sv: UserPtr<i32>) -> LinuxResult<isize> {
+    if domain != AF_UNIX {
+        return Err(LinuxError::EAFNOSUPPORT);
+    }
+    let (ty, nonblocking, cloexec) = split_type(ty);
+    if ty != SOCK_STREAM {
+        return Err(LinuxError::EOPNOTSUPP);
+    }
+    if protocol != 0 {
+        return Err(LinuxError::EPROTONOSUPPORT);
+    }
+    let sv = sv.get_as_mut_slice(2)?;
+    let (a, b) = StreamSocket::pair(nonblocking);
+    let fd_a = add_socket(Arc::new(a), cloexec)?;
+    let fd_b = add_socket(Arc::new(b), cloexec).inspect_err(|_| {
+        api::sys_close(fd_a);
+    })?;
+    sv.copy_from_slice(&[fd_a, fd_b]);
+    Ok(0)
+}
+
+pub fn sys_bind(fd: i32, addr: UserConstPtr<u8>, addrlen: u32) -> LinuxResult<isize> {
+    match Socket::from_fd(fd)? {
+        Socket::Stream(socket) => socket.bind(socket.family().parse_local(addr, addrlen)?)?,
+        Socket::Udp(socket) => socket.bind(inet::parse_local(addr, addrlen)?)?,
+    }
+    Ok(0)
+}
+
+pub fn sys_listen(fd: i32, backlog: i32) -> LinuxResult<isize> {
+    match Socket::from_fd(fd)? {
+        Socket::Stream(socket) => socket.listen(backlog)?,
+        Socket::Udp(_) => return Err(LinuxError::EOPNOTSUPP),
+    }
+    Ok(0)
+}
+
+pub fn sys_connect(fd: i32, addr: UserConstPtr<u8>, addrlen: u32) -> LinuxResult<isize> {
+    match Socket::from_fd(fd)? {
+        Socket::Stream(socket) => socket.connect(socket.family().parse_peer(addr, addrlen)?)?,
+        Socket::Udp(socket) => socket.connect(inet::parse_peer(addr, addrlen)?)?,
+    }
+    Ok(0)
+}
+
+pub fn sys_accept4(
+    fd: i32,
+    addr: UserPtr<u8>,
+    addrlen: UserPtr<u32>,
+    flags: i32,
+) -> LinuxResult<isize> {
+    if flags & !(SOCK_NONBLOCK | SOCK_CLOEXEC) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let Socket::Stream(socket) = Socket::from_fd(fd)? else {
+        return Err(LinuxError::EOPNOTSUPP);
+    };
+    let accepted = socket.accept()?;
+    accepted.set_nonblocking(flags & SOCK_NONBLOCK != 0)?;
+    let peer_name = accepted.peer_name()?;
+    addr.nullable(|addr| put_addr(accepted.family(), peer_name.as_deref(), addr, addrlen))?;
+    Ok(add_socket(accepted, flags & SOCK_CLOEXEC != 0)? as _)
+}
+
+pub fn sys_accept(fd: i32, addr: UserPtr<u8>, addrlen: UserPtr<u32>) -> LinuxResult<isize> {
+    sys_accept4(fd, addr, addrlen, 0)
+}
+
+pub fn sys_getsockname(fd: i32, addr: UserPtr<u8>, addrlen: UserPtr<u32>) -> LinuxResult<isize> {
+    let socket = Socket::from_fd(fd)?;
+    let name = match &socket {
+        Socket::Stream(socket) => socket.local_name(),
+        Socket::Udp(socket) => socket.local_name(),
+    };
+    put_addr(socket.family(), name.as_deref(), addr, addrlen)?;
+    Ok(0)
+}
+
+pub fn sys_getpeername(fd: i32, addr: UserPtr<u8>, addrlen: UserPtr<u32>) -> LinuxResult<isize> {
+    let socket = Socket::from_fd(fd)?;
+    let name = match &socket {
+        Socket::Stream(socket) => socket.peer_name()?,
+        Socket::Udp(socket) => Some(socket.peer_name()?),
+    };
+    put_addr(socket.family(), name.as_deref(), addr, addrlen)?;
+    Ok(0)
+}
+
+pub fn sys_shutdown(fd: i32, how: i32) -> LinuxResult<isize> {
+    if !(SHUT_RD..=SHUT_RDWR).contains(&how) {
+        return Err(LinuxError::EINVAL);
+    }
+    match Socket::from_fd(fd)? {
+        Socket::Stream(socket) => socket.shutdown(how)?,
+        Socket::Udp(_) => return Err(LinuxError::ENOTCONN),
+    }
+    Ok(0)
+}
+
+/// Sends `len` bytes at `buf` to `dest_addr`, which a stream socket ignores
+/// as it can only send to its peer.
+pub fn sys_sendto(
+    fd: i32,
+    buf: UserConstPtr<u8>,
+    len: usize,
+    flags: i32,
+    dest_addr: UserConstPtr<u8>,
+    addrlen: u32,
+) -> LinuxResult<isize> {
+    let buf = buf.get_as_bytes(len)?;
+    let buf = unsafe { core::slice::from_raw_parts(buf, len) };
+    let len = match Socket::from_fd(fd)? {
+        Socket::Stream(socket) => socket.send(
+            buf,
+            Control::default(),
+            flags & MSG_DONTWAIT != 0,
+            flags & MSG_NOSIGNAL != 0,
+        )?,
+        Socket::Udp(socket) => {
+            let to = dest_addr.nullable(|addr| inet::parse_peer(addr, addrlen))?;
+            socket.send_to(buf, to)?
+        }
+    };
+    Ok(len as _)
+}
+
+/// Receives at most `len` bytes to `buf`, and stores where they came from to
+/// `src_addr` if the socket is not a stream one, which has no use for it.
+pub fn sys_recvfrom(
+    fd: i32,
+    buf: UserPtr<u8>,
+    len: usize,
+    flags: i32,
+    src_addr: UserPtr<u8>,
+    addrlen: UserPtr<u32>,
+) -> LinuxResult<isize> {
+    let buf = buf.get_as_bytes(len)?;
+    let buf = unsafe { core::slice::from_raw_parts_mut(buf, len) };
+    let dontwait = flags & MSG_DONTWAIT != 0;
+    let len = match Socket::from_fd(fd)? {
+        Socket::Stream(socket) => socket.recv(buf, dontwait)?.0,
+        Socket::Udp(socket) => {
+            let (full, from) = socket.recv_from(buf, dontwait)?;
+            src_addr.nullable(|addr| put_addr(Family::Inet, Some(&from), addr, addrlen))?;
+            // With `MSG_TRUNC`, the full length of the datagram is returned
+            // even if it did not fit.
+            if flags & MSG_TRUNC != 0 {
+                full
+            } else {
+                full.min(len)
+            }
+        }
+    };
+    Ok(len as _)
+}
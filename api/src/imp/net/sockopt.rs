@@ -0,0 +1,102 @@
+//! Socket options.
+
+use core::{ffi::c_void, mem};
+
+use axerrno::{LinuxError, LinuxResult};
+
+use super::{
+    socket::Socket,
+    stream::{BUFFER_SIZE, SOL_SOCKET},
+};
+use crate::ptr::{PtrWrapper, UserConstPtr, UserPtr};
+
+const SO_REUSEADDR: i32 = 2;
+const SO_ERROR: i32 = 4;
+const SO_SNDBUF: i32 = 7;
+const SO_RCVBUF: i32 = 8;
+const SO_PASSCRED: i32 = 16;
+const SO_PEERCRED: i32 = 17;
+
+/// The smallest buffers a socket can be given.
+const SOCK_MIN_SNDBUF: usize = 4608;
+const SOCK_MIN_RCVBUF: usize = 2304;
+
+/// Stores `value` to the option buffer `optval`, truncated to the length at
+/// `optlen`, which is updated to the length stored.
+fn put_option<T>(value: &T, optval: UserPtr<c_void>, optlen: UserPtr<u32>) -> LinuxResult<isize> {
+    let optlen = optlen.get()?;
+    let len = unsafe { *optlen } as i32;
+    if len < 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let len = (len as usize).min(mem::size_of::<T>());
+    let dst = UserPtr::<u8>::from(optval.address().as_usize()).get_as_bytes(len)?;
+    unsafe {
+        dst.copy_from_nonoverlapping((value as *const T).cast(), len);
+        *optlen = len as u32;
+    }
+    Ok(0)
+}
+
+pub fn sys_getsockopt(
+    fd: i32,
+    level: i32,
+    optname: i32,
+    optval: UserPtr<c_void>,
+    optlen: UserPtr<u32>,
+) -> LinuxResult<isize> {
+    let socket = Socket::from_fd(fd)?;
+    if level != SOL_SOCKET {
+        return Err(LinuxError::ENOPROTOOPT);
+    }
+    match optname {
+        SO_REUSEADDR => return put_option(&(socket.reuse_addr() as i32), optval, optlen),
+        // Loopback sockets have no asynchronous errors to report.
+        SO_ERROR => return put_option(&0i32, optval, optlen),
+        _ => {}
+    }
+    let Socket::Stream(socket) = socket else {
+        return Err(LinuxError::ENOPROTOOPT);
+    };
+    match optname {
+        SO_SNDBUF => put_option(&(socket.buffer_size(false) as i32), optval, optlen),
+        SO_RCVBUF => put_option(&(socket.buffer_size(true) as i32), optval, optlen),
+        SO_PASSCRED => put_option(&(socket.pass_cred() as i32), optval, optlen),
+        SO_PEERCRED => put_option(&socket.peer_cred(), optval, optlen),
+        _ => Err(LinuxError::ENOPROTOOPT),
+    }
+}
+
+pub fn sys_setsockopt(
+    fd: i32,
+    level: i32,
+    optname: i32,
+    optval: UserConstPtr<c_void>,
+    optlen: u32,
+) -> LinuxResult<isize> {
+    let socket = Socket::from_fd(fd)?;
+    if level != SOL_SOCKET {
+        return Err(LinuxError::ENOPROTOOPT);
+    }
+    if (optlen as usize) < mem::size_of::<i32>() {
+        return Err(LinuxError::EINVAL);
+    }
+    let value = unsafe { *UserConstPtr::<i32>::from(optval.address().as_usize()).get()? };
+    // Like Linux, a requested buffer size is limited to the default and then
+    // doubled to leave room for bookkeeping.
+    let buffer_size = (value.max(0) as usize).min(BUFFER_SIZE) * 2;
+    if optname == SO_REUSEADDR {
+        socket.set_reuse_addr(value != 0);
+        return Ok(0);
+    }
+    let Socket::Stream(socket) = socket else {
+        return Err(LinuxError::ENOPROTOOPT);
+    };
+    match optname {
+        SO_SNDBUF => socket.set_buffer_size(false, buffer_size.max(SOCK_MIN_SNDBUF)),
+        SO_RCVBUF => socket.set_buffer_size(true, buffer_size.max(SOCK_MIN_RCVBUF)),
+        SO_PASSCRED => socket.set_pass_cred(value != 0),
+        _ => return Err(LinuxError::ENOPROTOOPT),
+    }
+    Ok(0)
+}
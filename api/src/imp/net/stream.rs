@@ -0,0 +1,858 @@
+//! Stream sockets: Unix domain sockets, and TCP sockets on the loopback
+//! network, which carry data the same way.
+
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::{
+    ffi::c_int,
+    mem,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use arceos_posix_api::{FileLike, ctypes, get_file_like};
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::monotonic_time;
+use axio::PollState;
+use axsync::Mutex;
+use axtask::{TaskExtRef, current};
+use starry_core::signal::{SI_KERNEL, SIGPIPE, SigInfo, send_signal_to_current, signal_pending};
+
+use super::{
+    inet::{self, AF_INET},
+    proc::{TCP_CLOSE, TCP_CLOSE_WAIT, TCP_ESTABLISHED, TCP_LISTEN, TCP_TIME_WAIT, TableEntry},
+    socket::next_ino,
+    unix::{self, AF_UNIX},
+};
+use crate::ptr::UserConstPtr;
+
+/// The most connections a listening socket can queue.
+const SOMAXCONN: usize = 4096;
+
+pub(super) const SHUT_RD: i32 = 0;
+pub(super) const SHUT_WR: i32 = 1;
+pub(super) const SHUT_RDWR: i32 = 2;
+
+pub(super) const SOL_SOCKET: i32 = 1;
+
+/// The default capacity of each direction of a connection.
+pub(super) const BUFFER_SIZE: usize = 212992;
+
+/// The ancillary data sent along with some bytes.
+#[derive(Default)]
+pub(super) struct Control {
+    /// The files passed with `SCM_RIGHTS`.
+    pub files: Vec<Arc<dyn FileLike>>,
+    /// The credentials of the sender, passed with `SCM_CREDENTIALS` or added
+    /// for a receiver that has set `SO_PASSCRED`.
+    pub cred: Option<UCred>,
+}
+
+/// The bytes written by one `write` or `sendmsg`, and the ancillary data sent
+/// along with them.
+struct Segment {
+    data: VecDeque<u8>,
+    control: Control,
+}
+
+/// The data flowing in one direction of a connection.
+struct Channel {
+    segments: VecDeque<Segment>,
+    /// The number of bytes queued in `segments`.
+    len: usize,
+    capacity: usize,
+    /// The writer has shut down writing or closed its end.
+    write_closed: bool,
+    /// The reader has shut down reading or closed its end.
+    read_closed: bool,
+    /// The reader wants the credentials of the writer, with `SO_PASSCRED`.
+    pass_cred: bool,
+}
+
+impl Channel {
+    fn new() -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self {
+            segments: VecDeque::new(),
+            len: 0,
+            capacity: BUFFER_SIZE,
+            write_closed: false,
+            read_closed: false,
+            pass_cred: false,
+        }))
+    }
+
+    /// Whether the direction is shut down at either end.
+    fn shut_down(&self) -> bool {
+        self.write_closed || self.read_closed
+    }
+
+    /// Queues `data`, which must fit, with `control` attached to it.
+    fn push(&mut self, data: &[u8], control: Control) {
+        self.len += data.len();
+        match self.segments.back_mut() {
+            Some(last)
+                if control.files.is_empty()
+                    && last.control.files.is_empty()
+                    && last.control.cred == control.cred =>
+            {
+                last.data.extend(data)
+            }
+            _ => self.segments.push_back(Segment {
+                data: data.iter().copied().collect(),
+                control,
+            }),
+        }
+    }
+
+    /// Takes queued bytes into `buf`, along with the ancillary data attached
+    /// to them.
+    ///
+    /// Like Linux, a read never spans two segments carrying files, so that
+    /// each batch of files arrives with the data it was sent with, nor mixes
+    /// data sent with different credentials.
+    fn pop(&mut self, buf: &mut [u8]) -> (usize, Control) {
+        let mut read = 0;
+        let mut control = Control::default();
+        while read < buf.len() {
+            let Some(segment) = self.segments.front_mut() else {
+                break;
+            };
+            if read == 0 {
+                control.cred = segment.control.cred;
+            } else if !segment.control.files.is_empty() || segment.control.cred != control.cred {
+                break;
+            }
+            control.files.append(&mut segment.control.files);
+            let len = segment.data.len().min(buf.len() - read);
+            for (dst, src) in buf[read..].iter_mut().zip(segment.data.drain(..len)) {
+                *dst = src;
+            }
+            read += len;
+            if segment.data.is_empty() {
+                self.segments.pop_front();
+            }
+            if !control.files.is_empty() {
+                break;
+            }
+        }
+        self.len -= read;
+        (read, control)
+    }
+}
+
+/// The credentials of a process, in the layout of `struct ucred`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UCred {
+    pub pid: i32,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl UCred {
+    /// The credentials of the calling process, with its effective IDs.
+    pub fn current() -> Self {
+        let curr = current();
+        let cred = curr.task_ext().cred();
+        Self {
+            pid: curr.tgid() as _,
+            uid: cred.euid,
+            gid: cred.egid,
+        }
+    }
+
+    /// What is reported for a socket without a peer.
+    const NONE: Self = Self {
+        pid: 0,
+        uid: 65534,
+        gid: 65534,
+    };
+}
+
+/// One end of a connection: the channel it reads from and the one it writes
+/// to, which the other end has the other way around.
+struct Connection {
+    rx: Arc<Mutex<Channel>>,
+    tx: Arc<Mutex<Channel>>,
+    /// The credentials of the peer when the connection was made.
+    peer_cred: UCred,
+    /// The address of the peer, if it is bound.
+    peer_name: Option<Vec<u8>>,
+}
+
+impl Connection {
+    /// Connects two ends, which see each other as `(cred, name)`.
+    fn pair(a: (UCred, Option<Vec<u8>>), b: (UCred, Option<Vec<u8>>)) -> (Self, Self) {
+        let (ab, ba) = (Channel::new(), Channel::new());
+        let conn_a = Self {
+            rx: ba.clone(),
+            tx: ab.clone(),
+            peer_cred: b.0,
+            peer_name: b.1,
+        };
+        let conn_b = Self {
+            rx: ab,
+            tx: ba,
+            peer_cred: a.0,
+            peer_name: a.1,
+        };
+        (conn_a, conn_b)
+    }
+
+    fn shutdown(&self, how: i32) {
+        if how == SHUT_RD || how == SHUT_RDWR {
+            self.rx.lock().read_closed = true;
+        }
+        if how == SHUT_WR || how == SHUT_RDWR {
+            self.tx.lock().write_closed = true;
+        }
+    }
+
+    fn recv(&self, buf: &mut [u8], nonblocking: bool) -> LinuxResult<(usize, Control)> {
+        if buf.is_empty() {
+            return Ok((0, Control::default()));
+        }
+        loop {
+            let mut rx = self.rx.lock();
+            if rx.len > 0 {
+                return Ok(rx.pop(buf));
+            }
+            if rx.shut_down() {
+                return Ok((0, Control::default()));
+            }
+            drop(rx);
+            if nonblocking {
+                return Err(LinuxError::EAGAIN);
+            }
+            if signal_pending() {
+                return Err(LinuxError::EINTR);
+            }
+            axtask::yield_now();
+        }
+    }
+
+    fn send(
+        &self,
+        buf: &[u8],
+        mut control: Control,
+        nonblocking: bool,
+        nosignal: bool,
+    ) -> LinuxResult<usize> {
+        let cred = control.cred.unwrap_or_else(UCred::current);
+        let mut written = 0;
+        loop {
+            let mut tx = self.tx.lock();
+            if tx.shut_down() {
+                if !nosignal {
+                    send_signal_to_current(SigInfo::new(SIGPIPE, SI_KERNEL));
+                }
+                return Err(LinuxError::EPIPE);
+            }
+            // The capacity may have been lowered below what is queued.
+            let len = tx.capacity.saturating_sub(tx.len).min(buf.len() - written);
+            if len > 0 {
+                let control = Control {
+                    files: mem::take(&mut control.files),
+                    cred: (control.cred.is_some() || tx.pass_cred).then_some(cred),
+                };
+                tx.push(&buf[written..written + len], control);
+                written += len;
+            }
+            if written == buf.len() {
+                return Ok(written);
+            }
+            drop(tx);
+            if nonblocking {
+                return if written > 0 {
+                    Ok(written)
+                } else {
+                    Err(LinuxError::EAGAIN)
+                };
+            }
+            if signal_pending() {
+                return if written > 0 {
+                    Ok(written)
+                } else {
+                    Err(LinuxError::EINTR)
+                };
+            }
+            axtask::yield_now();
+        }
+    }
+
+    fn poll(&self) -> PollState {
+        // The two channels are locked one at a time, as the peer locks them
+        // the other way around.
+        let readable = {
+            let rx = self.rx.lock();
+            rx.len > 0 || rx.shut_down()
+        };
+        let writable = {
+            let tx = self.tx.lock();
+            tx.len < tx.capacity || tx.shut_down()
+        };
+        PollState { readable, writable }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.rx.lock().read_closed = true;
+        self.tx.lock().write_closed = true;
+    }
+}
+
+/// The address a socket is bound to.
+#[derive(Clone)]
+pub(super) struct Address {
+    /// The address as in a `struct sockaddr`, after the family. The path of a
+    /// Unix socket ends with its NUL, while an abstract name starts with one.
+    pub name: Vec<u8>,
+    /// The key in [`BOUND`]: the family, then what tells the address apart
+    /// from others of that family.
+    pub key: Vec<u8>,
+}
+
+/// The address family of a socket.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum Family {
+    Unix,
+    Inet,
+}
+
+impl Family {
+    pub(super) fn domain(self) -> i32 {
+        match self {
+            Family::Unix => AF_UNIX,
+            Family::Inet => AF_INET,
+        }
+    }
+
+    /// Parses an address to bind to.
+    pub(super) fn parse_local(self, addr: UserConstPtr<u8>, addrlen: u32) -> LinuxResult<Address> {
+        match self {
+            Family::Unix => unix::parse_addr(addr, addrlen),
+            Family::Inet => inet::parse_local(addr, addrlen),
+        }
+    }
+
+    /// Parses an address to connect to.
+    pub(super) fn parse_peer(self, addr: UserConstPtr<u8>, addrlen: u32) -> LinuxResult<Address> {
+        match self {
+            Family::Unix => unix::parse_addr(addr, addrlen),
+            Family::Inet => inet::parse_peer(addr, addrlen),
+        }
+    }
+
+    /// What an unbound socket reports as its address, after the family.
+    pub(super) fn unbound(self) -> &'static [u8] {
+        match self {
+            Family::Unix => &[],
+            Family::Inet => &inet::UNBOUND,
+        }
+    }
+}
+
+/// The sockets bound to an address, by [`Address::key`].
+static BOUND: Mutex<BTreeMap<Vec<u8>, Weak<StreamSocket>>> = Mutex::new(BTreeMap::new());
+
+fn in_use(bound: &BTreeMap<Vec<u8>, Weak<StreamSocket>>, key: &[u8]) -> bool {
+    bound.get(key).is_some_and(|other| other.strong_count() > 0)
+}
+
+/// How long the address of a TCP connection stays in `TIME_WAIT` after it is
+/// closed, which is twice the maximum segment lifetime on Linux.
+const TIME_WAIT_LEN: Duration = Duration::from_secs(60);
+
+/// The end of a TCP connection that was closed before its peer.
+struct TimeWait {
+    /// When the address leaves `TIME_WAIT`.
+    deadline: Duration,
+    /// Whether the socket had `SO_REUSEADDR` set.
+    reuse_addr: bool,
+    /// The addresses of the socket and of its peer.
+    name: Vec<u8>,
+    peer_name: Option<Vec<u8>>,
+}
+
+/// The addresses in `TIME_WAIT`, by [`Address::key`].
+///
+/// No segments are in flight on loopback, but the addresses are held back
+/// all the same. As on Linux, a socket can only bind to one if both it and
+/// the closed socket have `SO_REUSEADDR` set.
+static TIME_WAIT: Mutex<BTreeMap<Vec<u8>, TimeWait>> = Mutex::new(BTreeMap::new());
+
+fn expire_time_wait(time_wait: &mut BTreeMap<Vec<u8>, TimeWait>) {
+    let now = monotonic_time();
+    time_wait.retain(|_, entry| entry.deadline > now);
+}
+
+/// Whether the address `key` is in `TIME_WAIT` for a socket that has
+/// `SO_REUSEADDR` set if `reuse_addr`.
+fn in_time_wait(time_wait: &mut BTreeMap<Vec<u8>, TimeWait>, key: &[u8], reuse_addr: bool) -> bool {
+    expire_time_wait(time_wait);
+    time_wait
+        .get(key)
+        .is_some_and(|entry| !(reuse_addr && entry.reuse_addr))
+}
+
+/// The TCP sockets that have an address, to list in `/proc/net/tcp`.
+static INET_SOCKETS: Mutex<Vec<Weak<StreamSocket>>> = Mutex::new(Vec::new());
+
+fn register_inet(socket: &Arc<StreamSocket>) {
+    let mut sockets = INET_SOCKETS.lock();
+    sockets.retain(|socket| socket.strong_count() > 0);
+    sockets.push(Arc::downgrade(socket));
+}
+
+/// The lines of `/proc/net/tcp`: the TCP sockets, then the connections in
+/// `TIME_WAIT`.
+pub(super) fn tcp_entries() -> Vec<TableEntry> {
+    let sockets = INET_SOCKETS.lock().clone();
+    let mut entries = sockets
+        .iter()
+        .filter_map(Weak::upgrade)
+        .map(|socket| socket.table_entry())
+        .collect::<Vec<_>>();
+    let mut time_wait = TIME_WAIT.lock();
+    expire_time_wait(&mut time_wait);
+    entries.extend(time_wait.values().map(|entry| TableEntry {
+        local: Some(entry.name.clone()),
+        remote: entry.peer_name.clone(),
+        state: TCP_TIME_WAIT,
+        tx_queue: 0,
+        rx_queue: 0,
+        uid: 0,
+        ino: 0,
+    }));
+    entries
+}
+
+/// A listening socket.
+struct Listener {
+    /// The most connections to queue, beyond the first.
+    backlog: usize,
+    /// Connections waiting to be accepted, as the sockets they will be.
+    pending: VecDeque<Arc<StreamSocket>>,
+    /// The credentials of the process that started listening.
+    cred: UCred,
+}
+
+enum State {
+    Unconnected,
+    Listening(Listener),
+    Connected(Arc<Connection>),
+}
+
+/// A stream socket.
+pub struct StreamSocket {
+    family: Family,
+    state: Mutex<State>,
+    addr: Mutex<Option<Address>>,
+    nonblocking: AtomicBool,
+    /// Whether `SO_PASSCRED` is set, and so what a connection starts with.
+    pass_cred: AtomicBool,
+    /// The sizes set with `SO_RCVBUF` and `SO_SNDBUF`.
+    rcvbuf: AtomicUsize,
+    sndbuf: AtomicUsize,
+    /// Whether `SO_REUSEADDR` is set, which lets the socket bind to addresses
+    /// in `TIME_WAIT`.
+    reuse_addr: AtomicBool,
+    /// The effective user ID of the process that created the socket.
+    uid: u32,
+    ino: u64,
+}
+
+impl StreamSocket {
+    /// Creates a socket that is neither connected nor listening.
+    pub(super) fn new(family: Family, nonblocking: bool) -> Self {
+        Self::with_state(family, State::Unconnected, nonblocking)
+    }
+
+    fn with_state(family: Family, state: State, nonblocking: bool) -> Self {
+        Self {
+            family,
+            state: Mutex::new(state),
+            addr: Mutex::new(None),
+            nonblocking: AtomicBool::new(nonblocking),
+            pass_cred: AtomicBool::new(false),
+            rcvbuf: AtomicUsize::new(BUFFER_SIZE),
+            sndbuf: AtomicUsize::new(BUFFER_SIZE),
+            reuse_addr: AtomicBool::new(false),
+            uid: current().task_ext().cred().euid,
+            ino: next_ino(),
+        }
+    }
+
+    /// Creates a pair of connected sockets, each of which sees the caller as
+    /// its peer.
+    pub(super) fn pair(nonblocking: bool) -> (Self, Self) {
+        let cred = UCred::current();
+        let (a, b) = Connection::pair((cred, None), (cred, None));
+        (
+            Self::with_state(Family::Unix, State::Connected(Arc::new(a)), nonblocking),
+            Self::with_state(Family::Unix, State::Connected(Arc::new(b)), nonblocking),
+        )
+    }
+
+    pub(super) fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+        get_file_like(fd)?
+            .into_any()
+            .downcast::<Self>()
+            .map_err(|_| LinuxError::ENOTSOCK)
+    }
+
+    pub(super) fn family(&self) -> Family {
+        self.family
+    }
+
+    fn connection(&self) -> LinuxResult<Arc<Connection>> {
+        match &*self.state.lock() {
+            State::Connected(conn) => Ok(conn.clone()),
+            _ => Err(LinuxError::ENOTCONN),
+        }
+    }
+
+    pub(super) fn bind(self: &Arc<Self>, addr: Address) -> LinuxResult {
+        let mut own = self.addr.lock();
+        if own.is_some() {
+            return Err(LinuxError::EINVAL);
+        }
+        let mut bound = BOUND.lock();
+        let mut time_wait = TIME_WAIT.lock();
+        let addr = if self.family == Family::Inet && inet::port(&addr) == 0 {
+            inet::ephemeral(&addr, |key| {
+                in_use(&bound, key) || in_time_wait(&mut time_wait, key, false)
+            })?
+        } else {
+            addr
+        };
+        if in_use(&bound, &addr.key) || in_time_wait(&mut time_wait, &addr.key, self.reuse_addr()) {
+            return Err(LinuxError::EADDRINUSE);
+        }
+        bound.insert(addr.key.clone(), Arc::downgrade(self));
+        *own = Some(addr);
+        if self.family == Family::Inet {
+            register_inet(self);
+        }
+        Ok(())
+    }
+
+    /// Binds a TCP socket that is about to listen or connect to an ephemeral
+    /// port, as Unix sockets must be bound explicitly.
+    fn autobind(self: &Arc<Self>, listening: bool) -> LinuxResult {
+        if self.addr.lock().is_some() {
+            return Ok(());
+        }
+        match self.family {
+            Family::Unix if listening => Err(LinuxError::EINVAL),
+            Family::Unix => Ok(()),
+            Family::Inet => self.bind(inet::unspecified(listening)),
+        }
+    }
+
+    pub(super) fn listen(self: &Arc<Self>, backlog: i32) -> LinuxResult {
+        self.autobind(true)?;
+        let backlog = (backlog.max(0) as usize).min(SOMAXCONN);
+        let mut state = self.state.lock();
+        match &mut *state {
+            State::Unconnected => {
+                *state = State::Listening(Listener {
+                    backlog,
+                    pending: VecDeque::new(),
+                    cred: UCred::current(),
+                });
+            }
+            State::Listening(listener) => listener.backlog = backlog,
+            State::Connected(_) => return Err(LinuxError::EINVAL),
+        }
+        Ok(())
+    }
+
+    pub(super) fn connect(self: &Arc<Self>, addr: Address) -> LinuxResult {
+        match &*self.state.lock() {
+            State::Unconnected => {}
+            State::Listening(_) => return Err(LinuxError::EINVAL),
+            State::Connected(_) => return Err(LinuxError::EISCONN),
+        }
+        let target = BOUND.lock().get(&addr.key).and_then(Weak::upgrade);
+        let target = match target {
+            Some(target) => target,
+            None if self.family == Family::Unix => return Err(LinuxError::ENOENT),
+            None => return Err(LinuxError::ECONNREFUSED),
+        };
+        self.autobind(false)?;
+        // The server end of a TCP connection is at the address connected to,
+        // while a Unix one is at that of the listener.
+        let local = match self.family {
+            Family::Unix => target.addr.lock().clone(),
+            Family::Inet => Some(addr),
+        };
+        let own_name = self.addr.lock().as_ref().map(|addr| addr.name.clone());
+        loop {
+            let mut state = target.state.lock();
+            let State::Listening(listener) = &mut *state else {
+                return Err(LinuxError::ECONNREFUSED);
+            };
+            if listener.pending.len() <= listener.backlog {
+                let peer_name = local.as_ref().map(|addr| addr.name.clone());
+                let (client, server) =
+                    Connection::pair((UCred::current(), own_name), (listener.cred, peer_name));
+                let mut accepted = StreamSocket::with_state(
+                    self.family,
+                    State::Connected(Arc::new(server)),
+                    false,
+                );
+                accepted.uid = target.uid;
+                *accepted.addr.lock() = local.clone();
+                accepted.set_pass_cred(target.pass_cred());
+                accepted.set_reuse_addr(target.reuse_addr());
+                let accepted = Arc::new(accepted);
+                if self.family == Family::Inet {
+                    register_inet(&accepted);
+                }
+                listener.pending.push_back(accepted);
+                drop(state);
+                {
+                    let mut rx = client.rx.lock();
+                    rx.pass_cred = self.pass_cred();
+                    rx.capacity = self.buffer_size(true);
+                }
+                client.tx.lock().capacity = self.buffer_size(false);
+                *self.state.lock() = State::Connected(Arc::new(client));
+                return Ok(());
+            }
+            drop(state);
+            if self.nonblocking() {
+                return Err(LinuxError::EAGAIN);
+            }
+            if signal_pending() {
+                return Err(LinuxError::EINTR);
+            }
+            axtask::yield_now();
+        }
+    }
+
+    pub(super) fn accept(&self) -> LinuxResult<Arc<StreamSocket>> {
+        loop {
+            match &mut *self.state.lock() {
+                State::Listening(listener) => {
+                    if let Some(socket) = listener.pending.pop_front() {
+                        return Ok(socket);
+                    }
+                }
+                _ => return Err(LinuxError::EINVAL),
+            }
+            if self.nonblocking() {
+                return Err(LinuxError::EAGAIN);
+            }
+            if signal_pending() {
+                return Err(LinuxError::EINTR);
+            }
+            axtask::yield_now();
+        }
+    }
+
+    pub(super) fn local_name(&self) -> Option<Vec<u8>> {
+        self.addr.lock().as_ref().map(|addr| addr.name.clone())
+    }
+
+    /// The address of the peer, or `None` if it is not bound.
+    pub(super) fn peer_name(&self) -> LinuxResult<Option<Vec<u8>>> {
+        Ok(self.connection()?.peer_name.clone())
+    }
+
+    pub(super) fn shutdown(&self, how: i32) -> LinuxResult {
+        self.connection()?.shutdown(how);
+        Ok(())
+    }
+
+    /// Receives data into `buf`, with the ancillary data sent along with it.
+    pub(super) fn recv(&self, buf: &mut [u8], dontwait: bool) -> LinuxResult<(usize, Control)> {
+        self.connection()?.recv(buf, dontwait || self.nonblocking())
+    }
+
+    /// Sends `buf`, with the files of `control` attached to its first byte
+    /// and its credentials, if any, to all of it.
+    ///
+    /// Unless `nosignal` is set, writing to a connection that is shut down
+    /// raises `SIGPIPE`.
+    pub(super) fn send(
+        &self,
+        buf: &[u8],
+        control: Control,
+        dontwait: bool,
+        nosignal: bool,
+    ) -> LinuxResult<usize> {
+        self.connection()?
+            .send(buf, control, dontwait || self.nonblocking(), nosignal)
+    }
+
+    /// The size of the receiving buffer if `rx`, or else of the sending one.
+    /// Sizes set before connecting apply to the connection.
+    pub(super) fn buffer_size(&self, rx: bool) -> usize {
+        let size = if rx { &self.rcvbuf } else { &self.sndbuf };
+        size.load(Ordering::Acquire)
+    }
+
+    pub(super) fn set_buffer_size(&self, rx: bool, size: usize) {
+        let (atomic, channel) = match self.connection() {
+            Ok(conn) if rx => (&self.rcvbuf, Some(conn.rx.clone())),
+            Ok(conn) => (&self.sndbuf, Some(conn.tx.clone())),
+            Err(_) if rx => (&self.rcvbuf, None),
+            Err(_) => (&self.sndbuf, None),
+        };
+        atomic.store(size, Ordering::Release);
+        if let Some(channel) = channel {
+            channel.lock().capacity = size;
+        }
+    }
+
+    /// The credentials of the peer, or of the listening process itself.
+    pub(super) fn peer_cred(&self) -> UCred {
+        match &*self.state.lock() {
+            State::Unconnected => UCred::NONE,
+            State::Listening(listener) => listener.cred,
+            State::Connected(conn) => conn.peer_cred,
+        }
+    }
+
+    pub(super) fn pass_cred(&self) -> bool {
+        self.pass_cred.load(Ordering::Acquire)
+    }
+
+    fn table_entry(&self) -> TableEntry {
+        let (state, remote, tx_queue, rx_queue) = match &*self.state.lock() {
+            State::Unconnected => (TCP_CLOSE, None, 0, 0),
+            State::Listening(listener) => (TCP_LISTEN, None, 0, listener.pending.len()),
+            State::Connected(conn) => {
+                let (rx_queue, peer_closed) = {
+                    let rx = conn.rx.lock();
+                    (rx.len, rx.write_closed)
+                };
+                let state = if peer_closed {
+                    TCP_CLOSE_WAIT
+                } else {
+                    TCP_ESTABLISHED
+                };
+                (state, conn.peer_name.clone(), conn.tx.lock().len, rx_queue)
+            }
+        };
+        TableEntry {
+            local: self.local_name(),
+            remote,
+            state,
+            tx_queue,
+            rx_queue,
+            uid: self.uid,
+            ino: self.ino,
+        }
+    }
+
+    pub(super) fn reuse_addr(&self) -> bool {
+        self.reuse_addr.load(Ordering::Acquire)
+    }
+
+    pub(super) fn set_reuse_addr(&self, reuse_addr: bool) {
+        self.reuse_addr.store(reuse_addr, Ordering::Release);
+    }
+
+    pub(super) fn set_pass_cred(&self, pass_cred: bool) {
+        self.pass_cred.store(pass_cred, Ordering::Release);
+        if let Ok(conn) = self.connection() {
+            conn.rx.lock().pass_cred = pass_cred;
+        }
+    }
+}
+
+impl Drop for StreamSocket {
+    fn drop(&mut self) {
+        // The end of a TCP connection that closes first goes into `TIME_WAIT`.
+        let active_close = match self.state.get_mut() {
+            State::Connected(conn) if !conn.rx.lock().write_closed => Some(conn.peer_name.clone()),
+            _ => None,
+        };
+        if let Some(addr) = self.addr.get_mut().take() {
+            if let Some(peer_name) = active_close.filter(|_| self.family == Family::Inet) {
+                let entry = TimeWait {
+                    deadline: monotonic_time() + TIME_WAIT_LEN,
+                    reuse_addr: self.reuse_addr(),
+                    name: addr.name.clone(),
+                    peer_name,
+                };
+                TIME_WAIT.lock().insert(addr.key.clone(), entry);
+            }
+            let mut bound = BOUND.lock();
+            if bound
+                .get(&addr.key)
+                .is_some_and(|other| other.strong_count() == 0)
+            {
+                bound.remove(&addr.key);
+            }
+        }
+    }
+}
+
+impl FileLike for StreamSocket {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        // Files sent along with the data are closed, as nobody can take them.
+        self.recv(buf, false).map(|(len, _)| len)
+    }
+
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        self.send(buf, Control::default(), false, false)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        Ok(ctypes::stat {
+            st_ino: self.ino as _,
+            st_nlink: 1,
+            st_mode: 0o140000 | 0o777, // S_IFSOCK | rwxrwxrwx
+            st_blksize: 4096,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(match &*self.state.lock() {
+            State::Unconnected => PollState {
+                readable: false,
+                writable: false,
+            },
+            State::Listening(listener) => PollState {
+                readable: !listener.pending.is_empty(),
+                writable: false,
+            },
+            State::Connected(conn) => conn.poll(),
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Release);
+        Ok(())
+    }
+
+    fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Acquire)
+    }
+
+    fn read_closed(&self) -> bool {
+        self.connection()
+            .is_ok_and(|conn| conn.rx.lock().write_closed)
+    }
+
+    /// Both directions are shut down, whichever end did it.
+    fn hung_up(&self) -> bool {
+        self.connection().is_ok_and(|conn| {
+            let rx_shut_down = conn.rx.lock().shut_down();
+            rx_shut_down && conn.tx.lock().shut_down()
+        })
+    }
+}
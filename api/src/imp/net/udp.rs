@@ -0,0 +1,290 @@
+//! UDP sockets on the loopback network.
+//!
+//! A datagram is queued straight onto the socket bound to the port it is sent
+//! to. Datagrams that find no socket, or no room in its queue, are dropped,
+//! as there is nothing to report either with on loopback.
+
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::{
+    ffi::c_int,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use arceos_posix_api::{FileLike, ctypes, get_file_like};
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axsync::Mutex;
+use axtask::{TaskExtRef, current};
+use starry_core::signal::signal_pending;
+
+use super::{
+    inet,
+    proc::{TCP_CLOSE, TCP_ESTABLISHED, TableEntry},
+    socket::next_ino,
+    stream::{Address, BUFFER_SIZE},
+};
+
+/// The largest payload of a datagram over IPv4.
+const MAX_PAYLOAD: usize = 65507;
+
+struct Datagram {
+    /// The address of the sender, after the family.
+    from: Vec<u8>,
+    data: Vec<u8>,
+}
+
+#[derive(Default)]
+struct Queue {
+    datagrams: VecDeque<Datagram>,
+    /// The bytes of all datagrams queued.
+    len: usize,
+}
+
+/// The sockets bound to a port, by [`Address::key`].
+static BOUND: Mutex<BTreeMap<Vec<u8>, Weak<UdpSocket>>> = Mutex::new(BTreeMap::new());
+
+fn in_use(bound: &BTreeMap<Vec<u8>, Weak<UdpSocket>>, key: &[u8]) -> bool {
+    bound.get(key).is_some_and(|other| other.strong_count() > 0)
+}
+
+/// The lines of `/proc/net/udp`, one for each bound socket.
+pub(super) fn udp_entries() -> Vec<TableEntry> {
+    let sockets = BOUND.lock().values().cloned().collect::<Vec<_>>();
+    sockets
+        .iter()
+        .filter_map(Weak::upgrade)
+        .map(|socket| TableEntry {
+            local: socket.local_name(),
+            remote: socket.peer_name().ok(),
+            state: if socket.peer.lock().is_some() {
+                TCP_ESTABLISHED
+            } else {
+                TCP_CLOSE
+            },
+            tx_queue: 0,
+            rx_queue: socket.queue.lock().len,
+            uid: socket.uid,
+            ino: socket.ino,
+        })
+        .collect()
+}
+
+/// A UDP socket.
+pub struct UdpSocket {
+    addr: Mutex<Option<Address>>,
+    /// The address set with `connect`, which is where datagrams go by default
+    /// and the only one they are taken from.
+    peer: Mutex<Option<Address>>,
+    queue: Mutex<Queue>,
+    nonblocking: AtomicBool,
+    /// Whether `SO_REUSEADDR` is set, which has no effect as UDP has no
+    /// `TIME_WAIT` to skip.
+    reuse_addr: AtomicBool,
+    /// The effective user ID of the process that created the socket.
+    uid: u32,
+    ino: u64,
+}
+
+impl UdpSocket {
+    pub(super) fn new(nonblocking: bool) -> Self {
+        Self {
+            addr: Mutex::new(None),
+            peer: Mutex::new(None),
+            queue: Mutex::new(Queue::default()),
+            nonblocking: AtomicBool::new(nonblocking),
+            reuse_addr: AtomicBool::new(false),
+            uid: current().task_ext().cred().euid,
+            ino: next_ino(),
+        }
+    }
+
+    pub(super) fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+        get_file_like(fd)?
+            .into_any()
+            .downcast::<Self>()
+            .map_err(|_| LinuxError::ENOTSOCK)
+    }
+
+    pub(super) fn bind(self: &Arc<Self>, addr: Address) -> LinuxResult {
+        let mut own = self.addr.lock();
+        if own.is_some() {
+            return Err(LinuxError::EINVAL);
+        }
+        let mut bound = BOUND.lock();
+        let addr = if inet::port(&addr) == 0 {
+            inet::ephemeral(&addr, |key| in_use(&bound, key))?
+        } else {
+            addr
+        };
+        if in_use(&bound, &addr.key) {
+            return Err(LinuxError::EADDRINUSE);
+        }
+        bound.insert(addr.key.clone(), Arc::downgrade(self));
+        *own = Some(addr);
+        Ok(())
+    }
+
+    /// Binds the socket to an ephemeral port if it is not bound yet, on the
+    /// loopback address that the route to any destination leaves from.
+    fn autobind(self: &Arc<Self>) -> LinuxResult {
+        if self.addr.lock().is_some() {
+            return Ok(());
+        }
+        self.bind(inet::unspecified(false))
+    }
+
+    pub(super) fn connect(self: &Arc<Self>, peer: Address) -> LinuxResult {
+        self.autobind()?;
+        *self.peer.lock() = Some(peer);
+        Ok(())
+    }
+
+    pub(super) fn local_name(&self) -> Option<Vec<u8>> {
+        self.addr.lock().as_ref().map(|addr| addr.name.clone())
+    }
+
+    pub(super) fn peer_name(&self) -> LinuxResult<Vec<u8>> {
+        let peer = self.peer.lock();
+        peer.as_ref()
+            .map(|addr| addr.name.clone())
+            .ok_or(LinuxError::ENOTCONN)
+    }
+
+    pub(super) fn reuse_addr(&self) -> bool {
+        self.reuse_addr.load(Ordering::Acquire)
+    }
+
+    pub(super) fn set_reuse_addr(&self, reuse_addr: bool) {
+        self.reuse_addr.store(reuse_addr, Ordering::Release);
+    }
+
+    /// Sends `buf` as one datagram to `to`, or to the connected peer if it is
+    /// `None`.
+    pub(super) fn send_to(self: &Arc<Self>, buf: &[u8], to: Option<Address>) -> LinuxResult<usize> {
+        match to {
+            Some(to) => {
+                self.autobind()?;
+                self.deliver(buf, &to)
+            }
+            None => self.send(buf),
+        }
+    }
+
+    /// Sends `buf` to the connected peer. A connected socket is always bound.
+    fn send(&self, buf: &[u8]) -> LinuxResult<usize> {
+        let peer = self.peer.lock().clone().ok_or(LinuxError::EDESTADDRREQ)?;
+        self.deliver(buf, &peer)
+    }
+
+    fn deliver(&self, buf: &[u8], to: &Address) -> LinuxResult<usize> {
+        if buf.len() > MAX_PAYLOAD {
+            return Err(LinuxError::EMSGSIZE);
+        }
+        let from = self.addr.lock().clone().unwrap();
+        let Some(target) = BOUND.lock().get(&to.key).and_then(Weak::upgrade) else {
+            return Ok(buf.len());
+        };
+        let accepted = target
+            .peer
+            .lock()
+            .as_ref()
+            .is_none_or(|peer| peer.key == from.key);
+        let mut queue = target.queue.lock();
+        if accepted && queue.len + buf.len() <= BUFFER_SIZE {
+            queue.len += buf.len();
+            queue.datagrams.push_back(Datagram {
+                from: inet::source(&from),
+                data: buf.to_vec(),
+            });
+        }
+        Ok(buf.len())
+    }
+
+    /// Receives a datagram into `buf`, returning its full length and the
+    /// address of the sender. What does not fit in `buf` is discarded.
+    pub(super) fn recv_from(
+        &self,
+        buf: &mut [u8],
+        dontwait: bool,
+    ) -> LinuxResult<(usize, Vec<u8>)> {
+        loop {
+            let datagram = {
+                let mut queue = self.queue.lock();
+                let datagram = queue.datagrams.pop_front();
+                queue.len -= datagram.as_ref().map_or(0, |datagram| datagram.data.len());
+                datagram
+            };
+            if let Some(datagram) = datagram {
+                let len = datagram.data.len().min(buf.len());
+                buf[..len].copy_from_slice(&datagram.data[..len]);
+                return Ok((datagram.data.len(), datagram.from));
+            }
+            if dontwait || self.nonblocking() {
+                return Err(LinuxError::EAGAIN);
+            }
+            if signal_pending() {
+                return Err(LinuxError::EINTR);
+            }
+            axtask::yield_now();
+        }
+    }
+}
+
+impl Drop for UdpSocket {
+    fn drop(&mut self) {
+        if let Some(addr) = self.addr.get_mut().take() {
+            let mut bound = BOUND.lock();
+            if bound
+                .get(&addr.key)
+                .is_some_and(|other| other.strong_count() == 0)
+            {
+                bound.remove(&addr.key);
+            }
+        }
+    }
+}
+
+impl FileLike for UdpSocket {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        let (len, _) = self.recv_from(buf, false)?;
+        Ok(len.min(buf.len()))
+    }
+
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        self.send(buf)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        Ok(ctypes::stat {
+            st_ino: self.ino as _,
+            st_nlink: 1,
+            st_mode: 0o140000 | 0o777, // S_IFSOCK | rwxrwxrwx
+            st_blksize: 4096,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: !self.queue.lock().datagrams.is_empty(),
+            writable: true,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Release);
+        Ok(())
+    }
+
+    fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Acquire)
+    }
+}
@@ -0,0 +1,49 @@
+//! Addresses of Unix domain sockets.
+
+use alloc::vec::Vec;
+use core::mem;
+
+use arceos_posix_api as api;
+use axerrno::{LinuxError, LinuxResult};
+
+use super::stream::Address;
+use crate::ptr::{PtrWrapper, UserConstPtr};
+
+pub(super) const AF_UNIX: i32 = 1;
+
+/// The length of `struct sockaddr_un`.
+const SOCKADDR_UN_LEN: usize = 110;
+
+/// Parses the `struct sockaddr_un` at `addr`.
+///
+/// Addresses are kept in a table of the kernel rather than as socket files,
+/// so a path is free to be bound again once its socket is closed.
+pub(super) fn parse_addr(addr: UserConstPtr<u8>, addrlen: u32) -> LinuxResult<Address> {
+    let addrlen = addrlen as usize;
+    if !(mem::size_of::<u16>()..=SOCKADDR_UN_LEN).contains(&addrlen) {
+        return Err(LinuxError::EINVAL);
+    }
+    let addr = addr.get_as_bytes(addrlen)?;
+    let addr = unsafe { core::slice::from_raw_parts(addr, addrlen) };
+    if u16::from_ne_bytes([addr[0], addr[1]]) != AF_UNIX as u16 {
+        return Err(LinuxError::EINVAL);
+    }
+    let path = &addr[2..];
+    let mut key = Vec::from([AF_UNIX as u8]);
+    if path.first() == Some(&0) {
+        key.extend_from_slice(path);
+        return Ok(Address {
+            name: path.to_vec(),
+            key,
+        });
+    }
+    let len = path.iter().position(|&c| c == 0).unwrap_or(path.len());
+    if len == 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let mut name = path[..len].to_vec();
+    name.push(0);
+    let path = api::handle_file_path(api::AT_FDCWD, Some(name.as_ptr()), false)?;
+    key.extend_from_slice(path.as_str().as_bytes());
+    Ok(Address { name, key })
+}
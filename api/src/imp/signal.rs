@@ -1,26 +1,212 @@
-use core::ffi::c_void;
+use core::{ffi::c_void, mem::size_of};
 
-use axerrno::LinuxResult;
+use axerrno::{LinuxError, LinuxResult};
+use axhal::arch::TrapFrame;
+use axtask::{TaskExtRef, current};
+use macro_rules_attribute::apply;
+use starry_core::{
+    signal::{
+        MINSIGSTKSZ, NSIG, RestartBlock, SI_TKILL, SI_USER, SIGKILL, SIGSEGV, SIGSTOP, SS_DISABLE,
+        SS_ONSTACK, SigAction, SigInfo, SignalSet, SignalStack, exit_with_signal, processes_with,
+        restore_frame, send_signal, send_signal_to_process, take_restart_block,
+    },
+    task::{find_task, read_trapframe_from_kstack},
+};
 
-use crate::ptr::{UserConstPtr, UserPtr};
+use crate::{
+    imp::task::nanosleep_until,
+    ptr::{PtrWrapper, UserConstPtr, UserPtr},
+    syscall_instrument,
+};
 
+const SIG_BLOCK: i32 = 0;
+const SIG_UNBLOCK: i32 = 1;
+const SIG_SETMASK: i32 = 2;
+
+#[apply(syscall_instrument)]
 pub fn sys_rt_sigprocmask(
-    _how: i32,
-    _set: UserConstPtr<c_void>,
-    _oldset: UserPtr<c_void>,
-    _sigsetsize: usize,
+    how: i32,
+    set: UserConstPtr<SignalSet>,
+    oldset: UserPtr<SignalSet>,
+    sigsetsize: usize,
 ) -> LinuxResult<isize> {
-    warn!("sys_rt_sigprocmask: not implemented");
+    if sigsetsize != size_of::<SignalSet>() {
+        return Err(LinuxError::EINVAL);
+    }
+    let set = set.nullable(|set| set.get().map(|set| unsafe { *set }))?;
+    let oldset = oldset.nullable(UserPtr::get)?;
+
+    let curr = current();
+    let mut state = curr.task_ext().signal.lock();
+    if let Some(oldset) = oldset {
+        unsafe { *oldset = state.blocked };
+    }
+    if let Some(set) = set {
+        let mut blocked = state.blocked;
+        match how {
+            SIG_BLOCK => blocked.0 |= set.0,
+            SIG_UNBLOCK => blocked.0 &= !set.0,
+            SIG_SETMASK => blocked = set,
+            _ => return Err(LinuxError::EINVAL),
+        }
+        blocked.0 &= !SignalSet::UNBLOCKABLE.0;
+        state.blocked = blocked;
+    }
     Ok(0)
 }
 
+#[apply(syscall_instrument)]
 pub fn sys_rt_sigaction(
-    _signum: i32,
-    _act: UserConstPtr<c_void>,
-    _oldact: UserPtr<c_void>,
-    _sigsetsize: usize,
+    signum: i32,
+    act: UserConstPtr<SigAction>,
+    oldact: UserPtr<SigAction>,
+    sigsetsize: usize,
+) -> LinuxResult<isize> {
+    if sigsetsize != size_of::<SignalSet>() {
+        return Err(LinuxError::EINVAL);
+    }
+    let sig = signum as usize;
+    if !(1..=NSIG).contains(&sig) {
+        return Err(LinuxError::EINVAL);
+    }
+    let act = act.nullable(|act| act.get().map(|act| unsafe { *act }))?;
+    if act.is_some() && (sig == SIGKILL || sig == SIGSTOP) {
+        return Err(LinuxError::EINVAL);
+    }
+    let oldact = oldact.nullable(UserPtr::get)?;
+
+    let curr = current();
+    let mut actions = curr.task_ext().signal_actions.lock();
+    if let Some(oldact) = oldact {
+        unsafe { *oldact = actions.get(sig) };
+    }
+    if let Some(mut act) = act {
+        act.mask.0 &= !SignalSet::UNBLOCKABLE.0;
+        actions.set(sig, act);
+    }
+    Ok(0)
+}
+
+/// Returns from a signal handler, restoring the context saved when the signal
+/// was delivered.
+pub fn sys_rt_sigreturn(tf: &mut TrapFrame) -> LinuxResult<isize> {
+    if !restore_frame(tf) {
+        warn!("sys_rt_sigreturn: bad signal frame at {:#x}", tf.sp());
+        exit_with_signal(SIGSEGV, true);
+    }
+    // The syscall return value is written back to the return value register,
+    // so hand back the one restored.
+    Ok(tf.retval() as isize)
+}
+
+/// Resumes the syscall that a signal last interrupted without a handler
+/// running, from what it left in the restart block. With nothing to resume,
+/// it fails with `EINTR`.
+pub fn sys_restart_syscall() -> LinuxResult<isize> {
+    match take_restart_block() {
+        Some(RestartBlock::Nanosleep { deadline, rem }) => nanosleep_until(deadline, rem.into()),
+        None => Err(LinuxError::EINTR),
+    }
+}
+
+#[apply(syscall_instrument)]
+pub fn sys_sigaltstack(
+    ss: UserConstPtr<SignalStack>,
+    old_ss: UserPtr<SignalStack>,
 ) -> LinuxResult<isize> {
-    warn!("sys_rt_sigaction: not implemented");
+    let ss = ss.nullable(|ss| ss.get().map(|ss| unsafe { *ss }))?;
+    let old_ss = old_ss.nullable(UserPtr::get)?;
+
+    let curr = current();
+    let user_sp = read_trapframe_from_kstack(curr.get_kernel_stack_top().unwrap()).sp();
+    let mut state = curr.task_ext().signal.lock();
+    let on_stack = state.altstack.contains(user_sp);
+    if let Some(old_ss) = old_ss {
+        let mut old = state.altstack;
+        if on_stack {
+            old.flags = SS_ONSTACK;
+        }
+        unsafe { *old_ss = old };
+    }
+    if let Some(ss) = ss {
+        if on_stack {
+            return Err(LinuxError::EPERM);
+        }
+        state.altstack = match ss.flags {
+            SS_DISABLE => SignalStack::default(),
+            0 | SS_ONSTACK => {
+                if ss.size < MINSIGSTKSZ {
+                    return Err(LinuxError::ENOMEM);
+                }
+                SignalStack { flags: 0, ..ss }
+            }
+            _ => return Err(LinuxError::EINVAL),
+        };
+    }
+    Ok(0)
+}
+
+/// Sends `sig` to the process `pid`, to the process group of the caller if
+/// `pid` is 0, to the process group `-pid` if it is below -1, or to every
+/// other process if it is -1.
+#[apply(syscall_instrument)]
+pub fn sys_kill(pid: i32, sig: i32) -> LinuxResult<isize> {
+    let sig = sig as usize;
+    if sig > NSIG {
+        return Err(LinuxError::EINVAL);
+    }
+    let curr = current();
+    let targets = match pid {
+        1.. => processes_with(|task| task.tgid() == pid as u64),
+        0 | i32::MIN..-1 => {
+            let pgid = match pid {
+                0 => curr.task_ext().pgid(),
+                _ => pid.unsigned_abs() as u64,
+            };
+            processes_with(|task| task.task_ext().pgid() == pgid)
+        }
+        -1 => processes_with(|task| task.tgid() != curr.tgid()),
+    };
+    if targets.is_empty() {
+        return Err(LinuxError::ESRCH);
+    }
+    // Signal 0 only checks that the targets exist.
+    if sig != 0 {
+        let info = SigInfo::new_user(sig, SI_USER, curr.tgid() as u32, 0);
+        for tgid in targets {
+            send_signal_to_process(tgid, info);
+        }
+    }
+    Ok(0)
+}
+
+/// Sends `sig` to the thread `tid` alone.
+#[apply(syscall_instrument)]
+pub fn sys_tkill(tid: i32, sig: i32) -> LinuxResult<isize> {
+    send_to_thread(None, tid, sig)
+}
+
+/// Sends `sig` to the thread `tid`, which must be in the thread group `tgid`.
+#[apply(syscall_instrument)]
+pub fn sys_tgkill(tgid: i32, tid: i32, sig: i32) -> LinuxResult<isize> {
+    if tgid <= 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    send_to_thread(Some(tgid as u64), tid, sig)
+}
+
+fn send_to_thread(tgid: Option<u64>, tid: i32, sig: i32) -> LinuxResult<isize> {
+    let sig = sig as usize;
+    if tid <= 0 || sig > NSIG {
+        return Err(LinuxError::EINVAL);
+    }
+    let task = find_task(tid as u64)
+        .filter(|task| tgid.is_none_or(|tgid| task.tgid() == tgid))
+        .ok_or(LinuxError::ESRCH)?;
+    if sig != 0 {
+        let pid = current().tgid() as u32;
+        send_signal(&task, SigInfo::new_user(sig, SI_TKILL, pid, 0));
+    }
     Ok(0)
 }
 
@@ -1,10 +1,16 @@
-use axerrno::LinuxResult;
+use alloc::{vec, vec::Vec};
+use core::ffi::c_void;
 
-use crate::ptr::{PtrWrapper, UserPtr};
+use axerrno::{LinuxError, LinuxResult};
+use axsync::Mutex;
+use axtask::{TaskExtRef, current};
+use macro_rules_attribute::apply;
+use starry_core::{kmsg, rand::fill_random};
 
-pub fn sys_getuid() -> LinuxResult<isize> {
-    Ok(0)
-}
+use crate::{
+    ptr::{PtrWrapper, UserConstPtr, UserPtr},
+    syscall_instrument,
+};
 
 #[repr(C)]
 pub struct UtsName {
@@ -26,7 +32,7 @@ impl Default for UtsName {
     fn default() -> Self {
         Self {
             sysname: Self::from_str("Starry"),
-            nodename: Self::from_str("Starry - machine[0]"),
+            nodename: *NODENAME.lock(),
             release: Self::from_str("10.0.0"),
             version: Self::from_str("10.0.0"),
             machine: Self::from_str("10.0.0"),
@@ -36,14 +42,117 @@ impl Default for UtsName {
 }
 
 impl UtsName {
-    fn from_str(info: &str) -> [u8; 65] {
+    const fn from_str(info: &str) -> [u8; 65] {
         let mut data: [u8; 65] = [0; 65];
-        data[..info.len()].copy_from_slice(info.as_bytes());
+        let bytes = info.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            data[i] = bytes[i];
+            i += 1;
+        }
         data
     }
 }
 
+/// The host name, null-terminated.
+static NODENAME: Mutex<[u8; 65]> = Mutex::new(UtsName::from_str("Starry - machine[0]"));
+
+/// The longest host name, without the null terminator.
+const HOST_NAME_MAX: usize = 64;
+
+pub(crate) fn hostname() -> Vec<u8> {
+    let nodename = NODENAME.lock();
+    let len = nodename
+        .iter()
+        .position(|&c| c == 0)
+        .unwrap_or(HOST_NAME_MAX);
+    nodename[..len].to_vec()
+}
+
+/// Sets the host name, which takes root.
+pub(crate) fn set_hostname(name: &[u8]) -> LinuxResult {
+    if current().task_ext().cred().euid != 0 {
+        return Err(LinuxError::EPERM);
+    }
+    if name.len() > HOST_NAME_MAX {
+        return Err(LinuxError::EINVAL);
+    }
+    let mut nodename = NODENAME.lock();
+    nodename.fill(0);
+    nodename[..name.len()].copy_from_slice(name);
+    Ok(())
+}
+
 pub fn sys_uname(name: UserPtr<UtsName>) -> LinuxResult<isize> {
     unsafe { *name.get()? = UtsName::default() };
     Ok(0)
 }
+
+pub fn sys_sethostname(name: UserConstPtr<u8>, len: usize) -> LinuxResult<isize> {
+    if len > HOST_NAME_MAX {
+        return Err(LinuxError::EINVAL);
+    }
+    let name = name.get_as_bytes(len)?;
+    set_hostname(unsafe { core::slice::from_raw_parts(name, len) })?;
+    Ok(0)
+}
+
+const GRND_NONBLOCK: u32 = 0x1;
+const GRND_RANDOM: u32 = 0x2;
+const GRND_INSECURE: u32 = 0x4;
+
+#[apply(syscall_instrument)]
+pub fn sys_getrandom(buf: UserPtr<c_void>, len: usize, flags: u32) -> LinuxResult<isize> {
+    if flags & !(GRND_NONBLOCK | GRND_RANDOM | GRND_INSECURE) != 0
+        || flags & (GRND_RANDOM | GRND_INSECURE) == GRND_RANDOM | GRND_INSECURE
+    {
+        return Err(LinuxError::EINVAL);
+    }
+    // Like Linux, it fills the buffer up to the first byte it cannot.
+    let (buf, len) = buf.get_as_partial_bytes(len)?;
+    // The generator is seeded at first use, so it never has to block.
+    fill_random(unsafe { core::slice::from_raw_parts_mut(buf as *mut u8, len) });
+    Ok(len as isize)
+}
+
+const SYSLOG_ACTION_CLOSE: i32 = 0;
+const SYSLOG_ACTION_OPEN: i32 = 1;
+const SYSLOG_ACTION_READ_ALL: i32 = 3;
+const SYSLOG_ACTION_READ_CLEAR: i32 = 4;
+const SYSLOG_ACTION_CLEAR: i32 = 5;
+const SYSLOG_ACTION_SIZE_BUFFER: i32 = 10;
+
+/// Reads or clears the kernel log buffer. Anyone may read it or ask for its
+/// size, but the other actions take root.
+pub fn sys_syslog(log_type: i32, buf: UserPtr<u8>, len: i32) -> LinuxResult<isize> {
+    if !matches!(log_type, SYSLOG_ACTION_READ_ALL | SYSLOG_ACTION_SIZE_BUFFER)
+        && current().task_ext().cred().euid != 0
+    {
+        return Err(LinuxError::EPERM);
+    }
+    match log_type {
+        SYSLOG_ACTION_CLOSE | SYSLOG_ACTION_OPEN => Ok(0),
+        SYSLOG_ACTION_READ_ALL | SYSLOG_ACTION_READ_CLEAR => {
+            if buf.address().as_usize() == 0 || len < 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            let len = (len as usize).min(kmsg::LOG_BUF_LEN);
+            let dst = buf.get_as_bytes(len)?;
+            // Taken into the kernel first, as the buffer is read with
+            // interrupts off.
+            let mut log = vec![0; len];
+            let read = kmsg::read_all(&mut log);
+            if log_type == SYSLOG_ACTION_READ_CLEAR {
+                kmsg::clear();
+            }
+            unsafe { core::ptr::copy_nonoverlapping(log.as_ptr(), dst, read) };
+            Ok(read as _)
+        }
+        SYSLOG_ACTION_CLEAR => {
+            kmsg::clear();
+            Ok(0)
+        }
+        SYSLOG_ACTION_SIZE_BUFFER => Ok(kmsg::LOG_BUF_LEN as _),
+        _ => Err(LinuxError::EINVAL),
+    }
+}
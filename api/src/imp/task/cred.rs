@@ -0,0 +1,92 @@
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{TaskExtRef, current};
+use macro_rules_attribute::apply;
+use starry_core::cred::NGROUPS_MAX;
+
+use crate::{
+    ptr::{PtrWrapper, UserConstPtr, UserPtr},
+    syscall_instrument,
+};
+
+pub fn sys_getuid() -> LinuxResult<isize> {
+    Ok(current().task_ext().cred().uid as _)
+}
+
+pub fn sys_geteuid() -> LinuxResult<isize> {
+    Ok(current().task_ext().cred().euid as _)
+}
+
+pub fn sys_getgid() -> LinuxResult<isize> {
+    Ok(current().task_ext().cred().gid as _)
+}
+
+pub fn sys_getegid() -> LinuxResult<isize> {
+    Ok(current().task_ext().cred().egid as _)
+}
+
+/// Sets all user IDs to `uid` if the caller is root, or else only the
+/// effective one, to the real one.
+#[apply(syscall_instrument)]
+pub fn sys_setuid(uid: u32) -> LinuxResult<isize> {
+    let curr = current();
+    let mut cred = curr.task_ext().cred();
+    if cred.euid == 0 {
+        cred.uid = uid;
+    } else if uid != cred.uid {
+        return Err(LinuxError::EPERM);
+    }
+    cred.euid = uid;
+    curr.task_ext().set_cred(cred);
+    Ok(0)
+}
+
+/// Sets all group IDs to `gid` if the caller is root, or else only the
+/// effective one, to the real one.
+#[apply(syscall_instrument)]
+pub fn sys_setgid(gid: u32) -> LinuxResult<isize> {
+    let curr = current();
+    let mut cred = curr.task_ext().cred();
+    if cred.euid == 0 {
+        cred.gid = gid;
+    } else if gid != cred.gid {
+        return Err(LinuxError::EPERM);
+    }
+    cred.egid = gid;
+    curr.task_ext().set_cred(cred);
+    Ok(0)
+}
+
+/// Stores the supplementary groups to `list`, or only returns how many there
+/// are if `size` is 0.
+#[apply(syscall_instrument)]
+pub fn sys_getgroups(size: i32, list: UserPtr<u32>) -> LinuxResult<isize> {
+    let groups = current().task_ext().cred().groups;
+    if size < 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    if size == 0 {
+        return Ok(groups.len() as _);
+    }
+    if (size as usize) < groups.len() {
+        return Err(LinuxError::EINVAL);
+    }
+    let list = list.get_as_array(groups.len())?;
+    unsafe { list.copy_from_nonoverlapping(groups.as_ptr(), groups.len()) };
+    Ok(groups.len() as _)
+}
+
+#[apply(syscall_instrument)]
+pub fn sys_setgroups(size: usize, list: UserConstPtr<u32>) -> LinuxResult<isize> {
+    if size > NGROUPS_MAX {
+        return Err(LinuxError::EINVAL);
+    }
+    let curr = current();
+    let mut cred = curr.task_ext().cred();
+    if cred.euid != 0 {
+        return Err(LinuxError::EPERM);
+    }
+    let groups = list.get_as_slice(size)?.to_vec();
+    cred.set_groups(groups);
+    curr.task_ext().set_cred(cred);
+    Ok(0)
+}
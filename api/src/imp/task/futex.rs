@@ -0,0 +1,390 @@
+//! Fast user-space locking, with `futex`.
+//!
+//! Waiters are kept in a fixed table of queues indexed by a hash of the
+//! futex, so that finding those of a futex only takes a look at one queue.
+//! Each queue is in the order the waiters came, and the ones that came first
+//! are woken first, so that none is passed over for good under contention.
+//!
+//! PI futexes hold the TID of their owner, and are handed straight to the
+//! waiter with the most favorable nice value when unlocked. Until then the
+//! owner runs with that nice value, and so does the owner of any PI futex it
+//! waits for in turn.
+
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+    vec::Vec,
+};
+use core::{
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+    time::Duration,
+};
+
+use arceos_posix_api::ctypes;
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::{monotonic_time, wall_time};
+use axsync::Mutex;
+use axtask::{TaskExtRef, current};
+use starry_core::{signal::signal_pending, task::find_task};
+
+use super::slack_deadline;
+use crate::ptr::{PtrWrapper, UserConstPtr, UserPtr};
+
+const FUTEX_WAIT: i32 = 0;
+const FUTEX_WAKE: i32 = 1;
+const FUTEX_LOCK_PI: i32 = 6;
+const FUTEX_UNLOCK_PI: i32 = 7;
+const FUTEX_TRYLOCK_PI: i32 = 8;
+const FUTEX_WAIT_BITSET: i32 = 9;
+const FUTEX_WAKE_BITSET: i32 = 10;
+const FUTEX_PRIVATE_FLAG: i32 = 128;
+const FUTEX_CLOCK_REALTIME: i32 = 256;
+const FUTEX_CMD_MASK: i32 = !(FUTEX_PRIVATE_FLAG | FUTEX_CLOCK_REALTIME);
+
+const FUTEX_BITSET_MATCH_ANY: u32 = u32::MAX;
+
+/// Set in a PI futex while tasks wait for it, so that the owner unlocks it
+/// through the kernel.
+const FUTEX_WAITERS: u32 = 0x8000_0000;
+const FUTEX_TID_MASK: u32 = 0x3fff_ffff;
+
+/// The number of queues in the table, a power of two.
+const FUTEX_QUEUES: usize = 256;
+
+/// A futex, told apart by the address space it is in and its address there.
+///
+/// Memory is never shared between address spaces, so a futex is private to
+/// one whether `FUTEX_PRIVATE_FLAG` is set or not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct FutexKey {
+    aspace: usize,
+    uaddr: usize,
+}
+
+impl FutexKey {
+    fn new(uaddr: usize) -> Self {
+        Self {
+            aspace: Arc::as_ptr(&current().task_ext().aspace) as usize,
+            uaddr,
+        }
+    }
+
+    fn queue(&self) -> &'static Mutex<VecDeque<Waiter>> {
+        // Futexes are 4-byte aligned, so the low bits of the address are
+        // always 0.
+        let hash = (self.uaddr >> 2) ^ (self.aspace >> 4);
+        &QUEUES[hash.wrapping_mul(0x9e37_79b9) % FUTEX_QUEUES]
+    }
+}
+
+struct Waiter {
+    key: FutexKey,
+    bitset: u32,
+    woken: Arc<AtomicBool>,
+}
+
+static QUEUES: [Mutex<VecDeque<Waiter>>; FUTEX_QUEUES] =
+    [const { Mutex::new(VecDeque::new()) }; FUTEX_QUEUES];
+
+/// Waits on the futex at `uaddr` for a wakeup matching `bitset` if it still
+/// holds `val`, or until `deadline` on the monotonic clock.
+fn futex_wait(
+    uaddr: UserPtr<u32>,
+    val: u32,
+    deadline: Option<Duration>,
+    bitset: u32,
+) -> LinuxResult<isize> {
+    let key = FutexKey::new(uaddr.address().as_usize());
+    let queue = key.queue();
+    let woken = Arc::new(AtomicBool::new(false));
+    {
+        // The value is checked with the queue locked, so that a wakeup after
+        // the value changes cannot be missed.
+        let mut queue = queue.lock();
+        if unsafe { *uaddr.get()? } != val {
+            return Err(LinuxError::EAGAIN);
+        }
+        queue.push_back(Waiter {
+            key,
+            bitset,
+            woken: woken.clone(),
+        });
+    }
+    loop {
+        if woken.load(Ordering::Acquire) {
+            return Ok(0);
+        }
+        let err = if deadline.is_some_and(|deadline| monotonic_time() >= deadline) {
+            LinuxError::ETIMEDOUT
+        } else if signal_pending() {
+            LinuxError::EINTR
+        } else {
+            axtask::yield_now();
+            continue;
+        };
+        let mut queue = queue.lock();
+        match queue
+            .iter()
+            .position(|waiter| Arc::ptr_eq(&waiter.woken, &woken))
+        {
+            Some(index) => {
+                queue.remove(index);
+                return Err(err);
+            }
+            // Woken right before giving up.
+            None => return Ok(0),
+        }
+    }
+}
+
+/// Wakes up to `count` of the waiters of the futex at `uaddr` whose bitset
+/// shares a bit with `bitset`, those that have waited longest first.
+fn futex_wake(uaddr: usize, count: u32, bitset: u32) -> usize {
+    let key = FutexKey::new(uaddr);
+    let mut queue = key.queue().lock();
+    let mut woken = 0;
+    queue.retain(|waiter| {
+        if woken >= count as usize || waiter.key != key || waiter.bitset & bitset == 0 {
+            return true;
+        }
+        waiter.woken.store(true, Ordering::Release);
+        woken += 1;
+        false
+    });
+    woken
+}
+
+struct FutexIfImpl;
+
+#[crate_interface::impl_interface]
+impl starry_core::task::FutexIf for FutexIfImpl {
+    fn futex_wake(uaddr: usize, count: u32) {
+        futex_wake(uaddr, count, FUTEX_BITSET_MATCH_ANY);
+    }
+}
+
+/// A task waiting for a PI futex.
+struct PiWaiter {
+    tid: u64,
+    /// The nice value it waits with, boosts included.
+    nice: i32,
+    woken: Arc<AtomicBool>,
+}
+
+/// A PI futex with waiters, kept until the last of them leaves.
+struct PiState {
+    owner: u64,
+    waiters: Vec<PiWaiter>,
+}
+
+static PI_STATES: Mutex<BTreeMap<FutexKey, PiState>> = Mutex::new(BTreeMap::new());
+
+/// Sets the boost of the task `tid` from the waiters of the PI futexes it
+/// owns, then passes its new nice value on to the owner of the PI futex it
+/// waits for, if any, and so on along the chain.
+fn update_boosts(states: &mut BTreeMap<FutexKey, PiState>, mut tid: u64) {
+    // A chain goes through each futex at most once, unless tasks wait for
+    // each other in a cycle.
+    for _ in 0..=states.len() {
+        let Some(task) = find_task(tid) else {
+            return;
+        };
+        let boost = states
+            .values()
+            .filter(|state| state.owner == tid)
+            .flat_map(|state| &state.waiters)
+            .map(|waiter| waiter.nice)
+            .min();
+        task.task_ext().set_pi_boost(boost);
+        let Some((state, waiter)) = states.values_mut().find_map(|state| {
+            let waiter = state.waiters.iter().position(|waiter| waiter.tid == tid)?;
+            Some((state, waiter))
+        }) else {
+            return;
+        };
+        state.waiters[waiter].nice = task.task_ext().effective_nice();
+        tid = state.owner;
+    }
+}
+
+/// Locks the PI futex at `uaddr`, waiting for its owner to unlock it unless
+/// `try_only`, or until `deadline` on the monotonic clock.
+fn futex_lock_pi(
+    uaddr: UserPtr<u32>,
+    deadline: Option<Duration>,
+    try_only: bool,
+) -> LinuxResult<isize> {
+    let word = unsafe { AtomicU32::from_ptr(uaddr.get()?) };
+    let key = FutexKey::new(uaddr.address().as_usize());
+    let curr = current();
+    let tid = curr.id().as_u64();
+    let woken = Arc::new(AtomicBool::new(false));
+    {
+        let mut states = PI_STATES.lock();
+        let mut val = word.load(Ordering::Acquire);
+        let owner = loop {
+            let owner = (val & FUTEX_TID_MASK) as u64;
+            let new = if owner == 0 {
+                // Taken over with the other bits kept, as one left by an
+                // owner that died still tells so.
+                tid as u32 | (val & !FUTEX_TID_MASK)
+            } else if owner == tid {
+                return Err(LinuxError::EDEADLK);
+            } else if try_only {
+                return Err(LinuxError::EAGAIN);
+            } else if find_task(owner).is_none() {
+                return Err(LinuxError::ESRCH);
+            } else {
+                val | FUTEX_WAITERS
+            };
+            match word.compare_exchange(val, new, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) if owner == 0 => return Ok(0),
+                Ok(_) => break owner,
+                Err(actual) => val = actual,
+            }
+        };
+        let state = states.entry(key).or_insert_with(|| PiState {
+            owner,
+            waiters: Vec::new(),
+        });
+        state.owner = owner;
+        state.waiters.push(PiWaiter {
+            tid,
+            nice: curr.task_ext().effective_nice(),
+            woken: woken.clone(),
+        });
+        update_boosts(&mut states, owner);
+    }
+    loop {
+        if woken.load(Ordering::Acquire) {
+            return Ok(0);
+        }
+        let err = if deadline.is_some_and(|deadline| monotonic_time() >= deadline) {
+            LinuxError::ETIMEDOUT
+        } else if signal_pending() {
+            LinuxError::EINTR
+        } else {
+            axtask::yield_now();
+            continue;
+        };
+        let mut states = PI_STATES.lock();
+        // Handed the futex right before giving up.
+        if woken.load(Ordering::Acquire) {
+            return Ok(0);
+        }
+        let state = states.get_mut(&key).unwrap();
+        state.waiters.retain(|waiter| waiter.tid != tid);
+        let owner = state.owner;
+        if state.waiters.is_empty() {
+            states.remove(&key);
+            word.fetch_and(!FUTEX_WAITERS, Ordering::AcqRel);
+        }
+        update_boosts(&mut states, owner);
+        return Err(err);
+    }
+}
+
+/// Unlocks the PI futex at `uaddr`, which the current task must own, handing
+/// it to the waiter with the most favorable nice value if there is one.
+fn futex_unlock_pi(uaddr: UserPtr<u32>) -> LinuxResult<isize> {
+    let word = unsafe { AtomicU32::from_ptr(uaddr.get()?) };
+    let key = FutexKey::new(uaddr.address().as_usize());
+    let tid = current().id().as_u64();
+    let mut states = PI_STATES.lock();
+    if (word.load(Ordering::Acquire) & FUTEX_TID_MASK) as u64 != tid {
+        return Err(LinuxError::EPERM);
+    }
+    let Some(state) = states.get_mut(&key) else {
+        word.store(0, Ordering::Release);
+        return Ok(0);
+    };
+    // Among waiters just as favorable, the one that came first.
+    let next = (0..state.waiters.len())
+        .min_by_key(|&index| state.waiters[index].nice)
+        .unwrap();
+    let next = state.waiters.remove(next);
+    let waiters = if state.waiters.is_empty() {
+        states.remove(&key);
+        0
+    } else {
+        state.owner = next.tid;
+        FUTEX_WAITERS
+    };
+    word.store(next.tid as u32 | waiters, Ordering::Release);
+    next.woken.store(true, Ordering::Release);
+    update_boosts(&mut states, tid);
+    update_boosts(&mut states, next.tid);
+    Ok(0)
+}
+
+fn from_timespec(ts: ctypes::timespec) -> LinuxResult<Duration> {
+    if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(Duration::from(ts))
+}
+
+/// Only the waiting and waking operations are supported, with and without a
+/// bitset, and those on PI futexes.
+pub fn sys_futex(
+    uaddr: UserPtr<u32>,
+    op: i32,
+    val: u32,
+    timeout: UserConstPtr<ctypes::timespec>,
+    _uaddr2: usize,
+    val3: u32,
+) -> LinuxResult<isize> {
+    if uaddr.address().as_usize() % 4 != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let cmd = op & FUTEX_CMD_MASK;
+    if op & FUTEX_CLOCK_REALTIME != 0 && !matches!(cmd, FUTEX_WAIT | FUTEX_WAIT_BITSET) {
+        return Err(LinuxError::ENOSYS);
+    }
+    match cmd {
+        FUTEX_WAIT | FUTEX_WAIT_BITSET => {
+            let bitset = if cmd == FUTEX_WAIT {
+                FUTEX_BITSET_MATCH_ANY
+            } else {
+                val3
+            };
+            if bitset == 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            let timeout = timeout.nullable(|timeout| from_timespec(unsafe { *timeout.get()? }))?;
+            // `FUTEX_WAIT` takes a relative timeout, and `FUTEX_WAIT_BITSET`
+            // an absolute one on the clock `FUTEX_CLOCK_REALTIME` picks.
+            let deadline = timeout.map(|timeout| {
+                slack_deadline(if cmd == FUTEX_WAIT {
+                    monotonic_time() + timeout
+                } else if op & FUTEX_CLOCK_REALTIME != 0 {
+                    (monotonic_time() + timeout).saturating_sub(wall_time())
+                } else {
+                    timeout
+                })
+            });
+            futex_wait(uaddr, val, deadline, bitset)
+        }
+        FUTEX_WAKE | FUTEX_WAKE_BITSET => {
+            let bitset = if cmd == FUTEX_WAKE {
+                FUTEX_BITSET_MATCH_ANY
+            } else {
+                val3
+            };
+            if bitset == 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            Ok(futex_wake(uaddr.address().as_usize(), val, bitset) as _)
+        }
+        FUTEX_LOCK_PI | FUTEX_TRYLOCK_PI => {
+            let timeout = timeout.nullable(|timeout| from_timespec(unsafe { *timeout.get()? }))?;
+            // The timeout is absolute, on the realtime clock.
+            let deadline = timeout.map(|timeout| {
+                slack_deadline((monotonic_time() + timeout).saturating_sub(wall_time()))
+            });
+            futex_lock_pi(uaddr, deadline, cmd == FUTEX_TRYLOCK_PI)
+        }
+        FUTEX_UNLOCK_PI => futex_unlock_pi(uaddr),
+        _ => Err(LinuxError::ENOSYS),
+    }
+}
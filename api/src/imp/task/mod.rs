@@ -1,5 +1,13 @@
+mod cred;
+mod futex;
+mod prctl;
 mod schedule;
+mod session;
 mod thread;
 
+pub use self::cred::*;
+pub use self::futex::*;
+pub use self::prctl::*;
 pub use self::schedule::*;
+pub use self::session::*;
 pub use self::thread::*;
@@ -0,0 +1,86 @@
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{TaskExtRef, current};
+use macro_rules_attribute::apply;
+use starry_core::task::{CAP_LAST_CAP, DEFAULT_TIMER_SLACK};
+
+use crate::{
+    ptr::{PtrWrapper, UserPtr},
+    syscall_instrument,
+};
+
+const PR_GET_DUMPABLE: i32 = 3;
+const PR_SET_DUMPABLE: i32 = 4;
+const PR_CAPBSET_READ: i32 = 23;
+const PR_CAPBSET_DROP: i32 = 24;
+const PR_SET_TIMERSLACK: i32 = 29;
+const PR_GET_TIMERSLACK: i32 = 30;
+const PR_SET_CHILD_SUBREAPER: i32 = 36;
+const PR_GET_CHILD_SUBREAPER: i32 = 37;
+const PR_GET_TID_ADDRESS: i32 = 40;
+
+#[apply(syscall_instrument)]
+pub fn sys_prctl(
+    option: i32,
+    arg2: usize,
+    _arg3: usize,
+    _arg4: usize,
+    _arg5: usize,
+) -> LinuxResult<isize> {
+    match option {
+        PR_GET_DUMPABLE => Ok(current().task_ext().dumpable() as _),
+        PR_SET_DUMPABLE => {
+            if arg2 > 1 {
+                return Err(LinuxError::EINVAL);
+            }
+            current().task_ext().set_dumpable(arg2 == 1);
+            Ok(0)
+        }
+        PR_CAPBSET_READ => {
+            let cap = capability(arg2)?;
+            Ok(((current().task_ext().cap_bset() >> cap) & 1) as _)
+        }
+        // Dropping takes `CAP_SETPCAP`, which root always has.
+        PR_CAPBSET_DROP => {
+            let cap = capability(arg2)?;
+            if current().task_ext().cred().euid != 0 {
+                return Err(LinuxError::EPERM);
+            }
+            current().task_ext().drop_cap(cap);
+            Ok(0)
+        }
+        PR_SET_TIMERSLACK => {
+            let slack = if arg2 == 0 {
+                DEFAULT_TIMER_SLACK
+            } else {
+                arg2 as u64
+            };
+            current().task_ext().set_timer_slack(slack);
+            Ok(0)
+        }
+        PR_GET_TIMERSLACK => Ok(current().task_ext().timer_slack() as _),
+        PR_SET_CHILD_SUBREAPER => {
+            current().task_ext().set_child_subreaper(arg2 != 0);
+            Ok(0)
+        }
+        PR_GET_CHILD_SUBREAPER => {
+            let subreaper = current().task_ext().child_subreaper() as i32;
+            unsafe { *UserPtr::<i32>::from(arg2).get()? = subreaper };
+            Ok(0)
+        }
+        // The address last given to `set_tid_address` or `CLONE_CHILD_CLEARTID`.
+        PR_GET_TID_ADDRESS => {
+            let tid_address = current().task_ext().clear_child_tid() as usize;
+            unsafe { *UserPtr::<usize>::from(arg2).get()? = tid_address };
+            Ok(0)
+        }
+        _ => Err(LinuxError::EINVAL),
+    }
+}
+
+/// Checks that `cap` is a valid capability number.
+fn capability(cap: usize) -> LinuxResult<u32> {
+    u32::try_from(cap)
+        .ok()
+        .filter(|&cap| cap <= CAP_LAST_CAP)
+        .ok_or(LinuxError::EINVAL)
+}
@@ -1,8 +1,26 @@
+use alloc::vec::Vec;
+use core::{ops::RangeInclusive, time::Duration};
+
 use arceos_posix_api as api;
-use axerrno::LinuxResult;
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::monotonic_time;
+use axtask::{AxCpuMask, AxTaskRef, TaskExtRef, current};
+use starry_core::{
+    signal::{RestartBlock, signal_pending, syscall_interrupted_with_block},
+    task::{TaskExt, all_tasks, find_task},
+};
 
 use crate::ptr::{PtrWrapper, UserConstPtr, UserPtr};
 
+/// How long a sleeping task goes without checking for signals.
+const SLEEP_SLICE: Duration = Duration::from_millis(10);
+
+/// Returns `deadline` put off by the timer slack of the current thread, the
+/// latest a timeout ending then may be noticed.
+pub(crate) fn slack_deadline(deadline: Duration) -> Duration {
+    deadline + Duration::from_nanos(current().task_ext().timer_slack())
+}
+
 pub fn sys_sched_yield() -> LinuxResult<isize> {
     Ok(api::sys_sched_yield() as _)
 }
@@ -11,5 +29,183 @@ pub fn sys_nanosleep(
     req: UserConstPtr<api::ctypes::timespec>,
     rem: UserPtr<api::ctypes::timespec>,
 ) -> LinuxResult<isize> {
-    unsafe { Ok(api::sys_nanosleep(req.get()?, rem.get()?) as _) }
+    let req = unsafe { *req.get()? };
+    if req.tv_sec < 0 || !(0..1_000_000_000).contains(&req.tv_nsec) {
+        return Err(LinuxError::EINVAL);
+    }
+    nanosleep_until(monotonic_time() + Duration::from(req), rem)
+}
+
+/// Sleeps until `deadline` on the monotonic clock, storing the time left to
+/// `rem` if a signal interrupts it.
+///
+/// Unless a handler runs for the signal, the sleep is resumed afterwards with
+/// `restart_syscall`.
+pub(crate) fn nanosleep_until(
+    deadline: Duration,
+    rem: UserPtr<api::ctypes::timespec>,
+) -> LinuxResult<isize> {
+    let wakeup = slack_deadline(deadline);
+    loop {
+        let now = monotonic_time();
+        if now >= deadline {
+            return Ok(0);
+        }
+        if signal_pending() {
+            if let Some(rem) = rem.nullable(UserPtr::get)? {
+                unsafe { *rem = (deadline - now).into() };
+            }
+            syscall_interrupted_with_block(RestartBlock::Nanosleep {
+                deadline,
+                rem: rem.address().as_usize(),
+            });
+            return Err(LinuxError::EINTR);
+        }
+        axtask::sleep((wakeup - now).min(SLEEP_SLICE));
+    }
+}
+
+const PRIO_PROCESS: i32 = 0;
+const PRIO_PGRP: i32 = 1;
+const PRIO_USER: i32 = 2;
+
+/// The range of nice values, from the most favorable to the least.
+const NICE_RANGE: RangeInclusive<i32> = -20..=19;
+
+/// Finds the processes `which` and `who` pick for `getpriority` and
+/// `setpriority`, failing with `ESRCH` if there are none.
+fn priority_targets(which: i32, who: u32) -> LinuxResult<Vec<AxTaskRef>> {
+    let curr = current();
+    let ext = curr.task_ext();
+    let (key, id): (fn(&TaskExt) -> u64, u64) = match which {
+        PRIO_PROCESS => (|ext| ext.proc_id as u64, ext.proc_id as u64),
+        PRIO_PGRP => (TaskExt::pgid, ext.pgid()),
+        PRIO_USER => (|ext| ext.cred().uid as u64, ext.cred().uid as u64),
+        _ => return Err(LinuxError::EINVAL),
+    };
+    let id = if who == 0 { id } else { who as u64 };
+    let targets = all_tasks()
+        .into_iter()
+        .filter(|task| key(task.task_ext()) == id)
+        .collect::<Vec<_>>();
+    if targets.is_empty() {
+        return Err(LinuxError::ESRCH);
+    }
+    Ok(targets)
+}
+
+/// Returns the highest priority of the processes picked, as `20 - nice` so
+/// that it is never negative, which the C library turns back into the nice
+/// value.
+pub fn sys_getpriority(which: i32, who: u32) -> LinuxResult<isize> {
+    let nice = priority_targets(which, who)?
+        .iter()
+        .map(|task| task.task_ext().nice())
+        .min()
+        .unwrap();
+    Ok((20 - nice) as _)
+}
+
+/// Sets the nice value of the processes picked to `nice`, clamped to
+/// [`NICE_RANGE`].
+///
+/// Without privileges, only processes of the same user can be changed, and
+/// their nice values only raised.
+pub fn sys_setpriority(which: i32, who: u32, nice: i32) -> LinuxResult<isize> {
+    let nice = nice.clamp(*NICE_RANGE.start(), *NICE_RANGE.end());
+    let cred = current().task_ext().cred();
+    for task in priority_targets(which, who)? {
+        let ext = task.task_ext();
+        if cred.euid != 0 {
+            let target = ext.cred();
+            if cred.euid != target.uid && cred.euid != target.euid {
+                return Err(LinuxError::EACCES);
+            }
+            if nice < ext.nice() {
+                return Err(LinuxError::EACCES);
+            }
+        }
+        ext.set_nice(nice);
+    }
+    Ok(0)
+}
+
+/// The size in bytes of a mask with a bit for every CPU, as a whole number of
+/// `unsigned long`s like Linux uses.
+const CPU_MASK_SIZE: usize = axconfig::SMP.div_ceil(usize::BITS as usize) * size_of::<usize>();
+
+/// Finds the thread `tid` whose affinity is asked for, with 0 meaning the
+/// calling thread.
+fn affinity_target(tid: i32) -> LinuxResult<AxTaskRef> {
+    match tid {
+        0 => Ok(current().clone()),
+        tid if tid < 0 => Err(LinuxError::ESRCH),
+        tid => find_task(tid as u64).ok_or(LinuxError::ESRCH),
+    }
+}
+
+/// Stores the CPUs the thread `pid` may run on to `mask`, returning how many
+/// bytes were stored.
+pub fn sys_sched_getaffinity(pid: i32, cpusetsize: usize, mask: UserPtr<u8>) -> LinuxResult<isize> {
+    if cpusetsize < CPU_MASK_SIZE || cpusetsize % size_of::<usize>() != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let cpumask = affinity_target(pid)?.cpumask();
+    let mask = mask.get_as_mut_slice(CPU_MASK_SIZE)?;
+    mask.fill(0);
+    for cpu in (0..axconfig::SMP).filter(|&cpu| cpumask.get(cpu)) {
+        mask[cpu / 8] |= 1 << (cpu % 8);
+    }
+    Ok(CPU_MASK_SIZE as _)
+}
+
+/// Lets the thread `pid` run only on the CPUs in `mask` that are online,
+/// failing with `EINVAL` if there are none.
+///
+/// The calling thread moves to one of them right away if it is not on one.
+/// Another thread moves the next time it is woken up.
+pub fn sys_sched_setaffinity(
+    pid: i32,
+    cpusetsize: usize,
+    mask: UserConstPtr<u8>,
+) -> LinuxResult<isize> {
+    let len = cpusetsize.min(CPU_MASK_SIZE);
+    let mask = mask.get_as_slice(len)?;
+    let mut cpumask = AxCpuMask::new();
+    for cpu in 0..axconfig::SMP {
+        if mask
+            .get(cpu / 8)
+            .is_some_and(|byte| byte & (1 << (cpu % 8)) != 0)
+        {
+            cpumask.set(cpu, true);
+        }
+    }
+    if cpumask.is_empty() {
+        return Err(LinuxError::EINVAL);
+    }
+
+    let target = affinity_target(pid)?;
+    let cred = current().task_ext().cred();
+    let target_cred = target.task_ext().cred();
+    if cred.euid != 0 && cred.euid != target_cred.uid && cred.euid != target_cred.euid {
+        return Err(LinuxError::EPERM);
+    }
+    if target.id() == current().id() {
+        axtask::set_current_affinity(cpumask);
+    } else {
+        target.set_cpumask(cpumask);
+    }
+    Ok(0)
+}
+
+/// Stores the CPU the calling thread is running on to `cpu`, and its NUMA
+/// node, always 0, to `node`.
+pub fn sys_getcpu(cpu: UserPtr<u32>, node: UserPtr<u32>) -> LinuxResult<isize> {
+    if let Some(cpu) = cpu.nullable(UserPtr::get)? {
+        unsafe { *cpu = axhal::cpu::this_cpu_id() as u32 };
+    }
+    if let Some(node) = node.nullable(UserPtr::get)? {
+        unsafe { *node = 0 };
+    }
+    Ok(0)
 }
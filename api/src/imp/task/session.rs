@@ -0,0 +1,87 @@
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{AxTaskRef, TaskExtRef, current};
+use macro_rules_attribute::apply;
+use starry_core::task::threads_of;
+
+use crate::syscall_instrument;
+
+/// Finds the process `pid` among the current process and its children, with
+/// 0 meaning the current process.
+fn find_process(pid: i32) -> LinuxResult<AxTaskRef> {
+    let curr = current();
+    if pid < 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    if pid == 0 || pid as u64 == curr.tgid() {
+        return Ok(curr.clone());
+    }
+    curr.task_ext()
+        .children
+        .lock()
+        .iter()
+        .find(|child| child.tgid() == pid as u64)
+        .cloned()
+        .ok_or(LinuxError::ESRCH)
+}
+
+#[apply(syscall_instrument)]
+pub fn sys_getpgid(pid: i32) -> LinuxResult<isize> {
+    Ok(find_process(pid)?.task_ext().pgid() as _)
+}
+
+#[apply(syscall_instrument)]
+pub fn sys_setpgid(pid: i32, pgid: i32) -> LinuxResult<isize> {
+    if pgid < 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let curr = current();
+    let target = find_process(pid)?;
+    let ext = target.task_ext();
+    if ext.sid() != curr.task_ext().sid() || ext.sid() == target.tgid() {
+        return Err(LinuxError::EPERM);
+    }
+    let pgid = match pgid {
+        0 => target.tgid(),
+        pgid => pgid as u64,
+    };
+    // Joining an existing group requires it to be in the same session, which
+    // can only be checked against the processes we know of.
+    if pgid != target.tgid()
+        && pgid != curr.task_ext().pgid()
+        && !curr
+            .task_ext()
+            .children
+            .lock()
+            .iter()
+            .any(|child| child.task_ext().pgid() == pgid)
+    {
+        return Err(LinuxError::EPERM);
+    }
+    for thread in threads_of(target.tgid()) {
+        thread.task_ext().set_pgid(pgid);
+    }
+    Ok(0)
+}
+
+#[apply(syscall_instrument)]
+pub fn sys_getsid(pid: i32) -> LinuxResult<isize> {
+    Ok(find_process(pid)?.task_ext().sid() as _)
+}
+
+/// Starts a new session, without a controlling terminal, led by the current
+/// process.
+#[apply(syscall_instrument)]
+pub fn sys_setsid() -> LinuxResult<isize> {
+    let curr = current();
+    let pid = curr.tgid();
+    if curr.task_ext().pgid() == pid {
+        return Err(LinuxError::EPERM);
+    }
+    for thread in threads_of(pid) {
+        let ext = thread.task_ext();
+        ext.set_sid(pid);
+        ext.set_pgid(pid);
+        ext.set_has_tty(false);
+    }
+    Ok(pid as _)
+}
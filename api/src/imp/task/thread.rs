@@ -1,16 +1,26 @@
-use core::{ffi::c_char, ptr};
+use core::{ffi::c_char, ptr, time::Duration};
 
 use alloc::vec::Vec;
+use arceos_posix_api::AT_FDCWD;
 use axerrno::{LinuxError, LinuxResult};
-use axtask::{TaskExtMut, TaskExtRef, current, yield_now};
+use axhal::time::monotonic_time;
+use axtask::{TaskExtMut, TaskExtRef, TaskState, current};
 use macro_rules_attribute::apply;
 use num_enum::TryFromPrimitive;
 use starry_core::{
-    ctypes::{RLIMIT_AS, RLIMIT_NOFILE, RLIMIT_STACK, RLimit, WaitFlags, WaitStatus},
-    task::{exec, wait_pid},
+    ctypes::{
+        CloneFlags, RLIMIT_AS, RLIMIT_CORE, RLIMIT_DATA, RLIMIT_NOFILE, RLIMIT_RSS, RLIMIT_STACK,
+        RLimit, Rusage, WaitFlags, WaitStatus,
+    },
+    signal::signal_pending,
+    task::{NR_OPEN, exec, exit_current, exit_group, find_task, wait_pid, waits_for},
 };
 
 use crate::{
+    imp::{
+        fs::{exec_path, release_aio_contexts},
+        utils::nanos_to_timeval,
+    },
     ptr::{PtrWrapper, UserConstPtr, UserPtr},
     syscall_instrument,
 };
@@ -38,7 +48,9 @@ enum ArchPrctlCode {
 
 #[apply(syscall_instrument)]
 pub fn sys_getpid() -> LinuxResult<isize> {
-    Ok(axtask::current().task_ext().proc_id as _)
+    // The thread group ID lives in the task itself, saving the `task_ext()`
+    // lookup for programs that call this in a loop.
+    Ok(current().tgid() as _)
 }
 
 #[apply(syscall_instrument)]
@@ -46,23 +58,24 @@ pub fn sys_getppid() -> LinuxResult<isize> {
     Ok(axtask::current().task_ext().get_parent() as _)
 }
 
+/// Exits the current thread. Its `clear_child_tid` word is cleared on the
+/// way out, waking a thread joining it.
 pub fn sys_exit(status: i32) -> ! {
-    let curr = current();
-    let clear_child_tid = curr.task_ext().clear_child_tid() as *mut i32;
-    if !clear_child_tid.is_null() {
-        // TODO: check whether the address is valid
-        unsafe {
-            // TODO: Encapsulate all operations that access user-mode memory into a unified function
-            *(clear_child_tid) = 0;
-        }
-        // TODO: wake up threads, which are blocked by futex, and waiting for the address pointed by clear_child_tid
-    }
-    axtask::exit(status);
+    exit_current(status);
 }
 
+/// Exits all the threads of the current process.
 pub fn sys_exit_group(status: i32) -> ! {
-    warn!("Temporarily replace sys_exit_group with sys_exit");
-    axtask::exit(status);
+    exit_group(status);
+}
+
+struct ProcessIfImpl;
+
+#[crate_interface::impl_interface]
+impl starry_core::task::ProcessIf for ProcessIfImpl {
+    fn process_exit(tgid: u64) {
+        release_aio_contexts(tgid);
+    }
 }
 
 /// To set the clear_child_tid field in the task extended data.
@@ -121,8 +134,11 @@ pub fn sys_clone(
     arg3: usize,
     arg4: usize,
 ) -> LinuxResult<isize> {
-    let tls = arg3;
-    let ctid = arg4;
+    // x86_64 takes the address of the child's thread ID before the TLS.
+    #[cfg(target_arch = "x86_64")]
+    let (ctid, tls) = (arg3, arg4);
+    #[cfg(not(target_arch = "x86_64"))]
+    let (tls, ctid) = (arg3, arg4);
 
     let stack = if user_stack == 0 {
         None
@@ -130,30 +146,63 @@ pub fn sys_clone(
         Some(user_stack)
     };
 
-    let curr_task = current();
+    // The thread ID addresses are only written to once the child is made,
+    // so a bad one is caught here instead.
+    let clone_flags = CloneFlags::from_bits_truncate(flags as u32);
+    if clone_flags.contains(CloneFlags::CLONE_PARENT_SETTID) {
+        UserPtr::<u32>::from(ptid).get()?;
+    }
+    if clone_flags.intersects(CloneFlags::CLONE_CHILD_SETTID | CloneFlags::CLONE_CHILD_CLEARTID) {
+        UserPtr::<u32>::from(ctid).get()?;
+    }
 
-    if let Ok(new_task_id) = curr_task
+    let curr_task = current();
+    let new_task_id = curr_task
         .task_ext()
-        .clone_task(flags, stack, ptid, tls, ctid)
-    {
-        Ok(new_task_id as isize)
-    } else {
-        Err(LinuxError::ENOMEM)
-    }
+        .clone_task(flags, stack, ptid, tls, ctid)?;
+    Ok(new_task_id as isize)
 }
 
+/// Waits for a child that [`waits_for`] `pid` takes to exit, reaping it and
+/// storing its wait status and the time it used. With `WUNTRACED` or
+/// `WCONTINUED`, a child that stops or continues is reported too.
 #[apply(syscall_instrument)]
-pub fn sys_wait4(pid: i32, exit_code_ptr: UserPtr<i32>, option: u32) -> LinuxResult<isize> {
-    let option_flag = WaitFlags::from_bits(option).unwrap();
+pub fn sys_wait4(
+    pid: i32,
+    exit_code_ptr: UserPtr<i32>,
+    option: u32,
+    rusage: UserPtr<Rusage>,
+) -> LinuxResult<isize> {
+    let option_flag = WaitFlags::from_bits(option).ok_or(LinuxError::EINVAL)?;
     let exit_code_ptr = exit_code_ptr.nullable(UserPtr::get)?;
+    let rusage = rusage.nullable(UserPtr::get)?;
     info!(
         "wait4: pid: {}, exit_code_ptr: {:?}, option: {}",
         pid, exit_code_ptr, option
     );
+    let curr = current();
+    let mut progress = ChildProgress::new(pid);
     loop {
-        let answer = unsafe { wait_pid(pid, exit_code_ptr.unwrap_or_else(ptr::null_mut)) };
+        // Read before checking, so that an exit in between is not missed.
+        let seen = curr.task_ext().child_exits();
+        let answer = unsafe {
+            wait_pid(
+                pid,
+                exit_code_ptr.unwrap_or_else(ptr::null_mut),
+                option_flag,
+            )
+        };
         match answer {
-            Ok(pid) => {
+            Ok((pid, (utime, stime))) => {
+                if let Some(rusage) = rusage {
+                    unsafe {
+                        *rusage = Rusage {
+                            ru_utime: nanos_to_timeval(utime),
+                            ru_stime: nanos_to_timeval(stime),
+                            ..Default::default()
+                        };
+                    }
+                }
                 return Ok(pid as isize);
             }
             Err(status) => match status {
@@ -163,51 +212,156 @@ pub fn sys_wait4(pid: i32, exit_code_ptr: UserPtr<i32>, option: u32) -> LinuxRes
                 WaitStatus::Running => {
                     if option_flag.contains(WaitFlags::WNOHANG) {
                         return Ok(0);
+                    } else if signal_pending() {
+                        return Err(LinuxError::EINTR);
                     } else {
-                        yield_now();
+                        // Signals do not wake us, so they are checked in slices.
+                        curr.task_ext().wait_child_exit(seen, WAIT_SLICE);
+                        progress.check();
                     }
                 }
-                _ => {
-                    panic!("Shouldn't reach here!");
-                }
+                // An exited child is reaped and returned above.
+                WaitStatus::Exited => return Err(LinuxError::ECHILD),
             },
         }
     }
 }
 
+/// How long `wait4` sleeps before checking for signals.
+const WAIT_SLICE: Duration = Duration::from_millis(10);
+
+/// How long a child can go without running before `wait4` reports it.
+const STALL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Watches the children a `wait4` is waiting for, to report those that cannot
+/// make progress. The parent would wait forever for them, which usually means
+/// the scheduler is broken or the children are deadlocked.
+struct ChildProgress {
+    pid: i32,
+    since: Duration,
+    /// The ID, state and CPU time of each child, as of `since`.
+    children: Vec<(u64, TaskState, usize)>,
+    reported: bool,
+}
+
+impl ChildProgress {
+    fn new(pid: i32) -> Self {
+        Self {
+            pid,
+            since: monotonic_time(),
+            children: Self::snapshot(pid),
+            reported: false,
+        }
+    }
+
+    fn snapshot(pid: i32) -> Vec<(u64, TaskState, usize)> {
+        current()
+            .task_ext()
+            .children
+            .lock()
+            .iter()
+            .filter(|child| waits_for(pid, child))
+            .map(|child| {
+                let ext = child.task_ext();
+                (child.id().as_u64(), child.state(), ext.cpu_time())
+            })
+            .collect()
+    }
+
+    /// Reports the children that have not run for [`STALL_TIMEOUT`], once.
+    fn check(&mut self) {
+        let now = monotonic_time();
+        if self.reported || now - self.since < STALL_TIMEOUT {
+            return;
+        }
+        let children = Self::snapshot(self.pid);
+        let stalled = children
+            .iter()
+            .filter(|child| self.children.contains(child))
+            .collect::<Vec<_>>();
+        if !stalled.is_empty() && stalled.len() == children.len() {
+            self.reported = true;
+            let id = current().id().as_u64();
+            for &&(child, state, _) in &stalled {
+                match state {
+                    TaskState::Ready | TaskState::Running => warn!(
+                        "wait4: task {} waits for child {}, which is {:?} but has not run for {:?}",
+                        id, child, state, STALL_TIMEOUT
+                    ),
+                    _ => warn!(
+                        "wait4: task {} waits for child {}, which has been {:?} for {:?}, \
+                         possibly deadlocked",
+                        id, child, state, STALL_TIMEOUT
+                    ),
+                }
+            }
+        }
+        self.since = now;
+        self.children = children;
+    }
+}
+
 #[apply(syscall_instrument)]
 pub fn sys_execve(
     path: UserConstPtr<c_char>,
     argv: UserConstPtr<usize>,
     envp: UserConstPtr<usize>,
 ) -> LinuxResult<isize> {
-    let path_str = path.get_as_str()?;
+    let path = exec_path(AT_FDCWD as _, path, 0)?;
+    execve(&path, argv, envp)
+}
+
+/// Runs the program at `path` relative to `dirfd`, or with `AT_EMPTY_PATH`
+/// and an empty `path`, the one open at `dirfd`, as `fexecve` does.
+#[apply(syscall_instrument)]
+pub fn sys_execveat(
+    dirfd: i32,
+    path: UserConstPtr<c_char>,
+    argv: UserConstPtr<usize>,
+    envp: UserConstPtr<usize>,
+    flags: u32,
+) -> LinuxResult<isize> {
+    let path = exec_path(dirfd, path, flags)?;
+    execve(&path, argv, envp)
+}
 
+/// Replaces the program of the current process with the one at the canonical
+/// `path`, for both `execve` and `execveat`.
+fn execve(path: &str, argv: UserConstPtr<usize>, envp: UserConstPtr<usize>) -> LinuxResult<isize> {
+    // Too many arguments or too long a one is `E2BIG` here.
+    let too_big = |err| match err {
+        LinuxError::ENAMETOOLONG => LinuxError::E2BIG,
+        err => err,
+    };
     let args = argv
-        .get_as_null_terminated()?
+        .get_as_null_terminated()
+        .map_err(too_big)?
         .iter()
         .map(|arg| {
             UserConstPtr::<c_char>::from(*arg)
                 .get_as_str()
                 .map(Into::into)
+                .map_err(too_big)
         })
         .collect::<Result<Vec<_>, _>>()?;
     let envs = envp
-        .get_as_null_terminated()?
+        .get_as_null_terminated()
+        .map_err(too_big)?
         .iter()
         .map(|env| {
             UserConstPtr::<c_char>::from(*env)
                 .get_as_str()
                 .map(Into::into)
+                .map_err(too_big)
         })
         .collect::<Result<Vec<_>, _>>()?;
 
     info!(
         "execve: path: {:?}, args: {:?}, envs: {:?}",
-        path_str, args, envs
+        path, args, envs
     );
 
-    if let Err(e) = exec(path_str, &args, &envs) {
+    if let Err(e) = exec(path, &args, &envs) {
         error!("Failed to exec: {:?}", e);
         return Err::<isize, _>(LinuxError::ENOSYS);
     }
@@ -215,6 +369,11 @@ pub fn sys_execve(
     unreachable!("execve should never return");
 }
 
+/// Gets and sets the limits on `resource` of the process `pid`, or of the
+/// current one if it is 0, taking effect where the kernel enforces them.
+///
+/// Unless the caller is root, another process must have the same user and
+/// group IDs throughout as its real ones.
 #[apply(syscall_instrument)]
 pub fn sys_prlimit64(
     pid: i32,
@@ -222,82 +381,51 @@ pub fn sys_prlimit64(
     new_limit: UserConstPtr<RLimit>,
     old_limit: UserPtr<RLimit>,
 ) -> LinuxResult<isize> {
-    // 检查资源类型是否有效
-    // let curr_process = current().task_ext_mut();
-    info!("sys_prlimit64 pid: {}, resource: {}", pid, resource);
-    let curr_process = current();
-    let task_ext = curr_process.task_ext();
-    if pid == 0 || pid == task_ext.proc_id as i32 {
-        // 仅支持当前进程
-        match resource {
-            // RLIMIT_AS => {
-            //     let new_limit = new_limit.get()?;
-            //     let old_limit = old_limit.get_mut()?;
-            //     let old_limit = curr_process.task_ext().set_rlimit(RLIMIT_AS, new_limit, old_limit);
-            //     Ok(0)
-            // }
-            RLIMIT_STACK => {
-                info!("RLIMIT_STACK");
-                // let new_limit = new_limit.get()?;
-                let old_limit_ptr = old_limit.address().as_ptr();
-                let new_limit_ptr = new_limit.address().as_ptr();
-                // let old_limit = curr_process.task_ext().set_rlimit(RLIMIT_STACK, new_limit, old_limit);
-                // Ok(0)
-                // let mut stack_limit = curr_process
-                let mut stack_limit: u64 = task_ext.get_stack_size();
-                if old_limit_ptr as usize != 0 {
-                    info!("RLIMIT_STACK: old_limit as usize != 0");
-                    let old_limit = old_limit_ptr as *mut RLimit;
-                    unsafe {
-                        *old_limit = RLimit {
-                            rlim_cur: stack_limit,
-                            rlim_max: stack_limit,
-                        };
-                    }
-                }
-                if new_limit_ptr as usize != 0 {
-                    info!("RLIMIT_STACK: new_limit as usize != 0");
-                    let new_limit = new_limit_ptr as *const RLimit;
-                    stack_limit = unsafe { (*new_limit).rlim_cur };
-                    task_ext.set_stack_size(stack_limit);
-                }
-                info!("RLIMIT_STACK: {}", stack_limit);
-            }
-            RLIMIT_NOFILE => {
-                info!("RLIMIT_NOFILE");
-                // let new_limit = new_limit.get()?;
-                let old_limit_ptr = old_limit.address().as_ptr();
-                let new_limit_ptr = new_limit.address().as_ptr();
-                // let old_limit = curr_process.task_ext().set_rlimit(RLIMIT_STACK, new_limit, old_limit);
-                // Ok(0)
-                // let mut stack_limit = curr_process
-                let mut fd_limit: u64 = task_ext.get_fd_limit();
-                if old_limit_ptr as usize != 0 {
-                    info!("RLIMIT_NOFILE: old_limit as usize != 0");
-                    let old_limit = old_limit_ptr as *mut RLimit;
-                    unsafe {
-                        *old_limit = RLimit {
-                            rlim_cur: fd_limit,
-                            rlim_max: fd_limit,
-                        };
-                    }
-                }
-                if new_limit_ptr as usize != 0 {
-                    info!("RLIMIT_NOFILE: new_limit as usize != 0");
-                    let new_limit = new_limit_ptr as *const RLimit;
-                    fd_limit = unsafe { (*new_limit).rlim_cur };
-                    task_ext.set_fd_limit(fd_limit);
-                }
-                info!("RLIMIT_NOFILE: {}", fd_limit);
-            }
-            // _ => Err(LinuxError::EINVAL),
-            _ => {}
-        }
-    } else {
-        info!("sys_prlimit64 pid: {}, resource: {}", pid, resource);
+    if !matches!(
+        resource,
+        RLIMIT_DATA | RLIMIT_STACK | RLIMIT_CORE | RLIMIT_RSS | RLIMIT_NOFILE | RLIMIT_AS
+    ) {
         return Err(LinuxError::EINVAL);
     }
-
+    let curr = current();
+    let target = match pid {
+        0 => curr.clone(),
+        pid if pid < 0 => return Err(LinuxError::ESRCH),
+        pid => find_task(pid as u64).ok_or(LinuxError::ESRCH)?,
+    };
+    let cred = curr.task_ext().cred();
+    let task_ext = target.task_ext();
+    if target.tgid() != curr.tgid() && cred.euid != 0 {
+        let target_cred = task_ext.cred();
+        if [target_cred.uid, target_cred.euid] != [cred.uid; 2]
+            || [target_cred.gid, target_cred.egid] != [cred.gid; 2]
+        {
+            return Err(LinuxError::EPERM);
+        }
+    }
+    let limit = task_ext.rlimit(resource);
+    // The new limit is checked before anything is stored, so that a call that
+    // fails leaves the old one in user space untouched.
+    let new_limit = new_limit.nullable(|new_limit| new_limit.get())?;
+    if let Some(new_limit) = new_limit {
+        let new_limit = unsafe { *new_limit };
+        if new_limit.rlim_cur > new_limit.rlim_max {
+            return Err(LinuxError::EINVAL);
+        }
+        // Only root may raise the hard limit, and not even root may have more
+        // fds than the table holds.
+        if new_limit.rlim_max > limit.rlim_max && cred.euid != 0
+            || resource == RLIMIT_NOFILE && new_limit.rlim_max > NR_OPEN
+        {
+            return Err(LinuxError::EPERM);
+        }
+    }
+    if let Some(old_limit) = old_limit.nullable(|old_limit| old_limit.get())? {
+        unsafe { *old_limit = limit };
+    }
+    if let Some(new_limit) = new_limit {
+        task_ext.set_rlimit(resource, unsafe { *new_limit });
+    }
     Ok(0)
 }
 
@@ -1,3 +1,4 @@
 mod time;
 
 pub use self::time::*;
+pub(crate) use self::time::nanos_to_timeval;
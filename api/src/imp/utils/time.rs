@@ -1,29 +1,103 @@
 use arceos_posix_api::{self as api, ctypes::timeval};
-use axerrno::LinuxResult;
-use axhal::time::{NANOS_PER_SEC, monotonic_time_nanos, nanos_to_ticks};
-use starry_core::{ctypes::SysInfo, ctypes::Tms, task::time_stat_output};
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::{NANOS_PER_MICROS, NANOS_PER_SEC, monotonic_time_nanos};
+use axtask::{TaskExtRef, current};
+use starry_core::{
+    ctypes::SysInfo,
+    ctypes::Tms,
+    ctypes::{RUSAGE_CHILDREN, RUSAGE_SELF, RUSAGE_THREAD, Rusage, USER_HZ},
+    task::{all_tasks, process_time_stat},
+};
 
-use crate::ptr::{PtrWrapper, UserPtr};
+use crate::{
+    imp::mm::mem_usage,
+    ptr::{PtrWrapper, UserPtr},
+};
+
+const CLOCK_PROCESS_CPUTIME_ID: i32 = 2;
+const CLOCK_THREAD_CPUTIME_ID: i32 = 3;
+
+/// The CPU time the threads of the calling process have used, in
+/// nanoseconds.
+fn process_cpu_time() -> u64 {
+    let tgid = current().tgid();
+    all_tasks()
+        .iter()
+        .filter(|task| task.tgid() == tgid)
+        .map(|task| task.cpu_time_nanos())
+        .sum()
+}
 
 pub fn sys_clock_gettime(clock_id: i32, tp: UserPtr<api::ctypes::timespec>) -> LinuxResult<isize> {
-    unsafe { Ok(api::sys_clock_gettime(clock_id, tp.get()?) as _) }
+    let nanos = match clock_id {
+        CLOCK_PROCESS_CPUTIME_ID => process_cpu_time(),
+        CLOCK_THREAD_CPUTIME_ID => current().cpu_time_nanos(),
+        _ => return unsafe { Ok(api::sys_clock_gettime(clock_id, tp.get()?) as _) },
+    };
+    unsafe {
+        *tp.get()? = api::ctypes::timespec {
+            tv_sec: (nanos / NANOS_PER_SEC) as _,
+            tv_nsec: (nanos % NANOS_PER_SEC) as _,
+        };
+    }
+    Ok(0)
 }
 
 pub fn sys_get_time_of_day(ts: UserPtr<timeval>) -> LinuxResult<isize> {
     unsafe { Ok(api::sys_get_time_of_day(ts.get()?) as _) }
 }
 
+/// Converts to the clock ticks `times` counts in, `USER_HZ` a second.
+fn clock_ticks(nanos: u64) -> u64 {
+    nanos * USER_HZ / NANOS_PER_SEC
+}
+
+/// Reports the times the calling process has used in clock ticks, and returns
+/// the clock ticks since boot.
+///
+/// As with `getrusage`, the times of reaped children are not kept, so they
+/// are reported as zero.
 pub fn sys_times(tms: UserPtr<Tms>) -> LinuxResult<isize> {
-    let (_, utime_us, _, stime_us) = time_stat_output();
+    let (utime, stime) = process_time_stat(current().tgid());
     unsafe {
         *tms.get()? = Tms {
-            tms_utime: utime_us,
-            tms_stime: stime_us,
-            tms_cutime: utime_us,
-            tms_cstime: stime_us,
+            tms_utime: clock_ticks(utime as u64) as _,
+            tms_stime: clock_ticks(stime as u64) as _,
+            tms_cutime: 0,
+            tms_cstime: 0,
         }
     }
-    Ok(nanos_to_ticks(monotonic_time_nanos()) as _)
+    Ok(clock_ticks(monotonic_time_nanos()) as _)
+}
+
+pub(crate) fn nanos_to_timeval(nanos: usize) -> timeval {
+    let micros = nanos / NANOS_PER_MICROS as usize;
+    timeval {
+        tv_sec: (micros / 1_000_000) as _,
+        tv_usec: (micros % 1_000_000) as _,
+    }
+}
+
+/// Reports the user and system time used by the calling process, summed over
+/// its threads, or by the calling thread alone with `RUSAGE_THREAD`.
+///
+/// The time of children is not kept once they are reaped, so it is reported
+/// as zero, as are the counters other than the times.
+pub fn sys_getrusage(who: i32, usage: UserPtr<Rusage>) -> LinuxResult<isize> {
+    let (utime, stime) = match who {
+        RUSAGE_SELF => process_time_stat(current().tgid()),
+        RUSAGE_THREAD => current().task_ext().time_stat_output(),
+        RUSAGE_CHILDREN => (0, 0),
+        _ => return Err(LinuxError::EINVAL),
+    };
+    unsafe {
+        *usage.get()? = Rusage {
+            ru_utime: nanos_to_timeval(utime),
+            ru_stime: nanos_to_timeval(stime),
+            ..Default::default()
+        };
+    }
+    Ok(0)
 }
 
 /// get the system uptime and memory information.
@@ -36,12 +110,13 @@ pub fn sys_sysinfo(sysinfo: UserPtr<SysInfo>) -> LinuxResult<isize> {
     //     return Err(axerrno::LinuxError::EFAULT);
     // }
     // get the system uptime
+    let usage = mem_usage();
     unsafe {
         *sysinfo.get()? = SysInfo {
             uptime: (monotonic_time_nanos() / NANOS_PER_SEC) as isize,
             loads: [0; 3],
-            totalram: 0,
-            freeram: 0,
+            totalram: usage.total,
+            freeram: usage.free,
             sharedram: 0,
             bufferram: 0,
             totalswap: 0,
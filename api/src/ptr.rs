@@ -3,14 +3,39 @@ use core::{alloc::Layout, ffi::c_char, mem, slice, str};
 use axerrno::{LinuxError, LinuxResult};
 use axhal::paging::MappingFlags;
 use axtask::{TaskExtRef, current};
-use memory_addr::{MemoryAddr, PAGE_SIZE_4K, VirtAddr, VirtAddrRange};
+use memory_addr::{MemoryAddr, PAGE_SIZE_4K, PageIter4K, VirtAddr, VirtAddrRange};
 use starry_core::mm::access_user_memory;
 
+/// The longest path taken from user space, counting the terminating null.
+pub const PATH_MAX: usize = 4096;
+/// The longest a single component of a path may be.
+pub const NAME_MAX: usize = 255;
+/// The most elements of any other array ending with a null value taken from
+/// user space, counting the null. For strings, it is the `MAX_ARG_STRLEN`
+/// Linux allows each argument of `execve`.
+pub const NULL_TERMINATED_MAX: usize = 32 * PAGE_SIZE_4K;
+
+/// Fails with `EFAULT` unless the `size` bytes at `start` are all in user
+/// space, which also keeps the arithmetic on their addresses from
+/// overflowing.
+fn check_user_range(start: VirtAddr, size: usize) -> LinuxResult<()> {
+    let base = axconfig::plat::USER_SPACE_BASE;
+    let end = start
+        .as_usize()
+        .checked_add(size)
+        .ok_or(LinuxError::EFAULT)?;
+    if start.as_usize() < base || end > base + axconfig::plat::USER_SPACE_SIZE {
+        return Err(LinuxError::EFAULT);
+    }
+    Ok(())
+}
+
 fn check_region(start: VirtAddr, layout: Layout, access_flags: MappingFlags) -> LinuxResult<()> {
     let align = layout.align();
     if start.as_usize() & (align - 1) != 0 {
         return Err(LinuxError::EFAULT);
     }
+    check_user_range(start, layout.size())?;
 
     let task = current();
     let mut aspace = task.task_ext().aspace.lock();
@@ -26,17 +51,74 @@ fn check_region(start: VirtAddr, layout: Layout, access_flags: MappingFlags) ->
     let page_end = (start + layout.size()).align_up_4k();
     aspace.populate_area(page_start, page_end - page_start)?;
 
+    // The kernel writing to a page mapped read-only would fault, whatever the
+    // flags of its area.
+    if access_flags.contains(MappingFlags::WRITE) {
+        let pt = aspace.page_table();
+        for page in PageIter4K::new(page_start, page_end).unwrap() {
+            match pt.query(page) {
+                Ok((_, flags, _)) if flags.contains(MappingFlags::WRITE) => {}
+                _ => return Err(LinuxError::EFAULT),
+            }
+        }
+    }
+
     Ok(())
 }
 
+/// How many of the `len` bytes at `start` can be accessed as `access_flags`
+/// allows, counting up to the first page that cannot be. The pages counted
+/// are populated. It fails with `EFAULT` if not even the first byte can be,
+/// unless `len` is 0.
+fn accessible_len(start: VirtAddr, len: usize, access_flags: MappingFlags) -> LinuxResult<usize> {
+    if len == 0 {
+        return Ok(0);
+    }
+    check_user_range(start, 1)?;
+    let user_end = axconfig::plat::USER_SPACE_BASE + axconfig::plat::USER_SPACE_SIZE;
+    let end = start.as_usize().saturating_add(len).min(user_end);
+
+    let task = current();
+    let mut aspace = task.task_ext().aspace.lock();
+    let mut page = start.align_down_4k();
+    while page.as_usize() < end {
+        if !aspace.check_region_access(
+            VirtAddrRange::from_start_size(page, PAGE_SIZE_4K),
+            access_flags,
+        ) || aspace.populate_area(page, PAGE_SIZE_4K).is_err()
+        {
+            break;
+        }
+        if access_flags.contains(MappingFlags::WRITE)
+            && !aspace
+                .page_table()
+                .query(page)
+                .is_ok_and(|(_, flags, _)| flags.contains(MappingFlags::WRITE))
+        {
+            break;
+        }
+        page += PAGE_SIZE_4K;
+    }
+
+    let accessible = page.as_usize().min(end).saturating_sub(start.as_usize());
+    if accessible == 0 {
+        return Err(LinuxError::EFAULT);
+    }
+    Ok(accessible)
+}
+
+/// Finds the null value ending the array at `start`, failing with
+/// `ENAMETOOLONG` if there is none in the first `max_len` elements.
 fn check_null_terminated<T: Eq + Default>(
     start: VirtAddr,
     access_flags: MappingFlags,
+    max_len: usize,
 ) -> LinuxResult<(*const T, usize)> {
     let align = Layout::new::<T>().align();
     if start.as_usize() & (align - 1) != 0 {
         return Err(LinuxError::EFAULT);
     }
+    check_user_range(start, size_of::<T>())?;
 
     let zero = T::default();
 
@@ -47,6 +129,9 @@ fn check_null_terminated<T: Eq + Default>(
 
     access_user_memory(|| {
         loop {
+            if len == max_len {
+                return Err(LinuxError::ENAMETOOLONG);
+            }
             // SAFETY: This won't overflow the address space since we'll check
             // it below.
             let ptr = unsafe { start.add(len) };
@@ -129,14 +214,19 @@ pub trait PtrWrapper<T>: Sized {
     /// Get the pointer as a raw pointer to `T`, validating the memory
     /// region given by the layout of `[T; len]`.
     fn get_as_array(self, len: usize) -> LinuxResult<Self::Ptr> {
-        check_region(
-            self.address(),
-            Layout::array::<T>(len).unwrap(),
-            Self::ACCESS_FLAGS,
-        )?;
+        let layout = Layout::array::<T>(len).map_err(|_| LinuxError::EFAULT)?;
+        check_region(self.address(), layout, Self::ACCESS_FLAGS)?;
         unsafe { Ok(self.into_inner()) }
     }
 
+    /// Get the pointer as a raw pointer to `T` along with how many of the
+    /// `size` bytes there can be accessed, up to the first that cannot. Only
+    /// those are validated, and at least one must be unless `size` is 0.
+    fn get_as_partial_bytes(self, size: usize) -> LinuxResult<(Self::Ptr, usize)> {
+        let len = accessible_len(self.address(), size, Self::ACCESS_FLAGS)?;
+        unsafe { Ok((self.into_inner(), len)) }
+    }
+
     fn nullable<R>(self, f: impl FnOnce(Self) -> LinuxResult<R>) -> LinuxResult<Option<R>> {
         if self.address().as_ptr().is_null() {
             Ok(None)
@@ -174,15 +264,29 @@ impl<T> PtrWrapper<T> for UserPtr<T> {
 
 impl<T> UserPtr<T> {
     /// Get the pointer as `&mut [T]`, terminated by a null value, validating
-    /// the memory region.
+    /// the memory region. With no null value in the first
+    /// [`NULL_TERMINATED_MAX`] elements, it fails with `ENAMETOOLONG`.
     pub fn get_as_null_terminated(self) -> LinuxResult<&'static mut [T]>
     where
         T: Eq + Default,
     {
-        let (ptr, len) = check_null_terminated::<T>(self.address(), Self::ACCESS_FLAGS)?;
+        let (ptr, len) =
+            check_null_terminated::<T>(self.address(), Self::ACCESS_FLAGS, NULL_TERMINATED_MAX)?;
         // SAFETY: We've validated the memory region.
         unsafe { Ok(slice::from_raw_parts_mut(ptr as *mut _, len)) }
     }
+
+    /// Get the pointer as `&mut [T]` of `len` elements, validating the whole
+    /// memory region. An empty slice is returned for a `len` of 0, whatever
+    /// the pointer.
+    pub fn get_as_mut_slice(self, len: usize) -> LinuxResult<&'static mut [T]> {
+        if len == 0 {
+            return Ok(&mut []);
+        }
+        let ptr = self.get_as_array(len)?;
+        // SAFETY: We've validated the memory region.
+        unsafe { Ok(slice::from_raw_parts_mut(ptr, len)) }
+    }
 }
 
 /// An immutable pointer to user space memory.
@@ -213,12 +317,26 @@ impl<T> PtrWrapper<T> for UserConstPtr<T> {
 
 impl<T> UserConstPtr<T> {
     /// Get the pointer as `&[T]`, terminated by a null value, validating the
-    /// memory region.
+    /// memory region. With no null value in the first [`NULL_TERMINATED_MAX`]
+    /// elements, it fails with `ENAMETOOLONG`.
     pub fn get_as_null_terminated(self) -> LinuxResult<&'static [T]>
     where
         T: Eq + Default,
     {
-        let (ptr, len) = check_null_terminated::<T>(self.address(), Self::ACCESS_FLAGS)?;
+        let (ptr, len) =
+            check_null_terminated::<T>(self.address(), Self::ACCESS_FLAGS, NULL_TERMINATED_MAX)?;
+        // SAFETY: We've validated the memory region.
+        unsafe { Ok(slice::from_raw_parts(ptr, len)) }
+    }
+
+    /// Get the pointer as `&[T]` of `len` elements, validating the whole
+    /// memory region. An empty slice is returned for a `len` of 0, whatever
+    /// the pointer.
+    pub fn get_as_slice(self, len: usize) -> LinuxResult<&'static [T]> {
+        if len == 0 {
+            return Ok(&[]);
+        }
+        let ptr = self.get_as_array(len)?;
         // SAFETY: We've validated the memory region.
         unsafe { Ok(slice::from_raw_parts(ptr, len)) }
     }
@@ -227,7 +345,9 @@ impl<T> UserConstPtr<T> {
 static_assertions::const_assert_eq!(size_of::<c_char>(), size_of::<u8>());
 
 impl UserConstPtr<c_char> {
-    /// Get the pointer as `&str`, validating the memory region.
+    /// Get the pointer as `&str`, validating the memory region. Like
+    /// [`Self::get_as_null_terminated`], it reads no further than
+    /// [`NULL_TERMINATED_MAX`].
     pub fn get_as_str(self) -> LinuxResult<&'static str> {
         let slice = self.get_as_null_terminated()?;
         // SAFETY: c_char is u8
@@ -235,4 +355,24 @@ impl UserConstPtr<c_char> {
 
         str::from_utf8(slice).map_err(|_| LinuxError::EILSEQ)
     }
+
+    /// Get the pointer as a path, terminated by a null value, validating the
+    /// memory region.
+    ///
+    /// A path of [`PATH_MAX`] or more, which is not read further than that,
+    /// or with a component longer than [`NAME_MAX`] fails with
+    /// `ENAMETOOLONG`.
+    pub fn get_as_path(self) -> LinuxResult<&'static [c_char]> {
+        let (ptr, len) =
+            check_null_terminated::<c_char>(self.address(), Self::ACCESS_FLAGS, PATH_MAX)?;
+        // SAFETY: We've validated the memory region.
+        let path = unsafe { slice::from_raw_parts(ptr, len) };
+        if path
+            .split(|&c| c == b'/' as c_char)
+            .any(|name| name.len() > NAME_MAX)
+        {
+            return Err(LinuxError::ENAMETOOLONG);
+        }
+        Ok(path)
+    }
 }
@@ -0,0 +1,71 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <sys/un.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    struct sockaddr_un addr = { .sun_family = AF_UNIX };
+    struct sockaddr_un peer;
+    socklen_t len = sizeof(peer);
+    int server, client, conn;
+    char c;
+
+    strcpy(addr.sun_path, "accept4.sock");
+    server = socket(AF_UNIX, SOCK_STREAM | SOCK_NONBLOCK, 0);
+    if (server < 0)
+        return 1;
+    if (bind(server, (struct sockaddr *)&addr, sizeof(addr)) != 0)
+        return 2;
+    if (listen(server, 4) != 0)
+        return 3;
+
+    /* Nothing is pending yet. */
+    if (accept4(server, NULL, NULL, 0) != -1 || errno != EAGAIN)
+        return 4;
+    if (accept4(server, NULL, NULL, 0x40000000) != -1 || errno != EINVAL)
+        return 5;
+
+    client = socket(AF_UNIX, SOCK_STREAM, 0);
+    if (client < 0)
+        return 6;
+    if (connect(client, (struct sockaddr *)&addr, sizeof(addr)) != 0)
+        return 7;
+
+    conn = accept4(server, (struct sockaddr *)&peer, &len, SOCK_NONBLOCK | SOCK_CLOEXEC);
+    if (conn < 0)
+        return 8;
+    /* The client is not bound, so only its family is reported. */
+    if (len != sizeof(sa_family_t) || peer.sun_family != AF_UNIX)
+        return 9;
+    if (!(fcntl(conn, F_GETFL) & O_NONBLOCK))
+        return 10;
+    if (read(conn, &c, 1) != -1 || errno != EAGAIN)
+        return 11;
+    if (write(client, "x", 1) != 1 || read(conn, &c, 1) != 1 || c != 'x')
+        return 12;
+
+    /* The listening socket is left as it was. */
+    if (!(fcntl(server, F_GETFL) & O_NONBLOCK))
+        return 13;
+    close(conn);
+    close(client);
+    close(server);
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("accept4: failed at %d\n", ret);
+        return 1;
+    }
+    puts("accept4: ok");
+    return 0;
+}
@@ -0,0 +1,64 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/mount.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#define FILE_PATH "access.file"
+#define MNT_PATH "access.mnt"
+
+static int check(void)
+{
+    int fd, ret = 0;
+
+    fd = open(FILE_PATH, O_WRONLY | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0)
+        return 1;
+    close(fd);
+    if (access(FILE_PATH, F_OK) != 0)
+        return 2;
+    if (access(FILE_PATH, R_OK | W_OK) != 0)
+        return 3;
+    if (access("access.missing", F_OK) == 0 || errno != ENOENT)
+        return 4;
+
+    /* Nothing on a read-only mount can be written, whatever its mode. */
+    if (mkdirat(AT_FDCWD, MNT_PATH, 0755) != 0) {
+        ret = 5;
+        goto out_file;
+    }
+    if (access(MNT_PATH, W_OK) != 0) {
+        ret = 6;
+        goto out_mnt;
+    }
+    if (mount(FILE_PATH, MNT_PATH, "vfat", MS_RDONLY, NULL) != 0) {
+        ret = 7;
+        goto out_mnt;
+    }
+    if (access(MNT_PATH, W_OK) == 0 || errno != EROFS)
+        ret = 8;
+    else if (access(MNT_PATH, R_OK | X_OK) != 0)
+        ret = 9;
+    if (umount2(MNT_PATH, 0) != 0 && !ret)
+        ret = 10;
+    if (!ret && access(MNT_PATH, W_OK) != 0)
+        ret = 11;
+
+out_mnt:
+    unlinkat(AT_FDCWD, MNT_PATH, AT_REMOVEDIR);
+out_file:
+    unlink(FILE_PATH);
+    return ret;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("access: failed at %d\n", failed);
+        return 1;
+    }
+    puts("access: ok");
+    return 0;
+}
@@ -0,0 +1,79 @@
+#define _DEFAULT_SOURCE
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/acct.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    struct acct_v3 rec;
+    struct stat st;
+    int fd, status, ret = 0;
+    pid_t pid;
+
+    fd = open("acct_file", O_WRONLY | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0)
+        return 1;
+    close(fd);
+
+    if (acct("acct_file") != 0) {
+        ret = 2;
+        goto out;
+    }
+    pid = fork();
+    if (pid < 0) {
+        acct(NULL);
+        ret = 3;
+        goto out;
+    }
+    if (pid == 0)
+        _exit(7);
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status)) {
+        acct(NULL);
+        ret = 4;
+        goto out;
+    }
+    if (acct(NULL) != 0) {
+        ret = 5;
+        goto out;
+    }
+
+    /* The child's record is the first, and nothing is written once it is off. */
+    if (fstatat(AT_FDCWD, "acct_file", &st, 0) != 0 || st.st_size < (off_t)sizeof(rec)) {
+        ret = 6;
+        goto out;
+    }
+    fd = open("acct_file", O_RDONLY);
+    if (fd < 0 || read(fd, &rec, sizeof(rec)) != sizeof(rec)) {
+        ret = 7;
+        goto out_fd;
+    }
+    if (rec.ac_version != 3 || rec.ac_pid != (unsigned)pid || rec.ac_ppid != (unsigned)getpid()) {
+        ret = 8;
+        goto out_fd;
+    }
+    if (rec.ac_exitcode != 7 << 8) {
+        ret = 9;
+        goto out_fd;
+    }
+
+out_fd:
+    if (fd >= 0)
+        close(fd);
+out:
+    unlinkat(AT_FDCWD, "acct_file", 0);
+    return ret;
+}
+
+int main(void)
+{
+    int ret = check();
+    if (ret) {
+        printf("acct: failed at %d\n", ret);
+        return 1;
+    }
+    puts("acct: ok");
+    return 0;
+}
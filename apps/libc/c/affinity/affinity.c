@@ -0,0 +1,53 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <sched.h>
+#include <stdio.h>
+
+static int check(void)
+{
+    cpu_set_t set, orig;
+    int cpu, last = -1, ret = 0;
+
+    if (sched_getaffinity(0, sizeof(orig), &orig) != 0)
+        return 1;
+    for (cpu = 0; cpu < CPU_SETSIZE; cpu++)
+        if (CPU_ISSET(cpu, &orig))
+            last = cpu;
+    if (last < 0)
+        return 2;
+
+    /* A mask of CPUs that do not exist is rejected. */
+    CPU_ZERO(&set);
+    CPU_SET(CPU_SETSIZE - 1, &set);
+    errno = 0;
+    if (sched_setaffinity(0, sizeof(set), &set) != -1 || errno != EINVAL)
+        return 3;
+
+    /* Narrowing to a single CPU moves the task there. */
+    CPU_ZERO(&set);
+    CPU_SET(last, &set);
+    if (sched_setaffinity(0, sizeof(set), &set) != 0)
+        return 4;
+    if (sched_getcpu() != last) {
+        ret = 5;
+        goto out;
+    }
+    if (sched_getaffinity(0, sizeof(set), &set) != 0 || CPU_COUNT(&set) != 1
+        || !CPU_ISSET(last, &set))
+        ret = 6;
+
+out:
+    sched_setaffinity(0, sizeof(orig), &orig);
+    return ret;
+}
+
+int main(void)
+{
+    int ret = check();
+    if (ret) {
+        printf("affinity: failed at %d\n", ret);
+        return 1;
+    }
+    puts("affinity: ok");
+    return 0;
+}
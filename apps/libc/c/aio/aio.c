@@ -0,0 +1,80 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <linux/aio_abi.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <time.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    aio_context_t ctx = 0;
+    struct iocb cb, *cbs[1] = { &cb };
+    struct io_event ev;
+    struct timespec ts = { 0, 0 };
+    char buf[8] = { 0 };
+    int fd;
+
+    fd = open("aio.txt", O_RDWR | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0)
+        return 1;
+    if (write(fd, "0123456789", 10) != 10)
+        return 2;
+    if (syscall(SYS_io_setup, 4, &ctx) != 0 || ctx == 0)
+        return 3;
+
+    memset(&cb, 0, sizeof(cb));
+    cb.aio_data = 42;
+    cb.aio_lio_opcode = IOCB_CMD_PREAD;
+    cb.aio_fildes = fd;
+    cb.aio_buf = (uintptr_t)buf;
+    cb.aio_nbytes = 4;
+    cb.aio_offset = 3;
+    if (syscall(SYS_io_submit, ctx, 1, cbs) != 1)
+        return 4;
+    if (syscall(SYS_io_getevents, ctx, 1, 1, &ev, NULL) != 1)
+        return 5;
+    if (ev.data != 42 || ev.obj != (uintptr_t)&cb || ev.res != 4 || memcmp(buf, "3456", 4))
+        return 6;
+
+    /* Nothing is left, so a zero timeout returns at once. */
+    if (syscall(SYS_io_getevents, ctx, 1, 1, &ev, &ts) != 0)
+        return 7;
+
+    cb.aio_lio_opcode = IOCB_CMD_PWRITE;
+    cb.aio_buf = (uintptr_t)"ab";
+    cb.aio_nbytes = 2;
+    cb.aio_offset = 8;
+    if (syscall(SYS_io_submit, ctx, 1, cbs) != 1)
+        return 8;
+    if (syscall(SYS_io_getevents, ctx, 1, 1, &ev, NULL) != 1 || ev.res != 2)
+        return 9;
+    if (lseek(fd, 8, SEEK_SET) != 8 || read(fd, buf, 2) != 2 || memcmp(buf, "ab", 2))
+        return 10;
+
+    cb.aio_fildes = -1;
+    if (syscall(SYS_io_submit, ctx, 1, cbs) != -1 || errno != EBADF)
+        return 11;
+    if (syscall(SYS_io_destroy, ctx) != 0)
+        return 12;
+    if (syscall(SYS_io_submit, ctx, 1, cbs) != -1 || errno != EINVAL)
+        return 13;
+    close(fd);
+    unlink("aio.txt");
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("aio: failed at %d\n", ret);
+        return 1;
+    }
+    puts("aio: ok");
+    return 0;
+}
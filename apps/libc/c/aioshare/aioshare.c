@@ -0,0 +1,103 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <linux/aio_abi.h>
+#include <sched.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static char thread_stack[65536] __attribute__((aligned(16)));
+
+static aio_context_t ctx;
+static int fd;
+static char buf[4];
+static volatile long thread_result = -1;
+static volatile int thread_done;
+
+/* Only makes system calls directly, as it has no TLS of its own. */
+static int thread_main(void *arg)
+{
+    struct iocb cb, *cbs[1] = { &cb };
+    struct io_event ev;
+
+    (void)arg;
+    memset(&cb, 0, sizeof(cb));
+    cb.aio_lio_opcode = IOCB_CMD_PREAD;
+    cb.aio_fildes = fd;
+    cb.aio_buf = (uintptr_t)buf;
+    cb.aio_nbytes = 4;
+    if (syscall(SYS_io_submit, ctx, 1, cbs) != 1)
+        thread_result = -2;
+    else if (syscall(SYS_io_getevents, ctx, 1, 1, &ev, NULL) != 1)
+        thread_result = -3;
+    else
+        thread_result = ev.res;
+    __atomic_store_n(&thread_done, 1, __ATOMIC_RELEASE);
+    syscall(SYS_exit, 0);
+    return 0;
+}
+
+static int check(void)
+{
+    int status;
+    pid_t pid;
+
+    fd = open("aioshare.txt", O_RDWR | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0 || write(fd, "abcd", 4) != 4)
+        return __LINE__;
+    if (syscall(SYS_io_setup, 4, &ctx) != 0)
+        return __LINE__;
+
+    /* Another thread of the process can use the context. */
+    if (clone(thread_main, thread_stack + sizeof(thread_stack),
+              CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND | CLONE_THREAD, NULL) < 0)
+        return __LINE__;
+    while (!__atomic_load_n(&thread_done, __ATOMIC_ACQUIRE))
+        sched_yield();
+    if (thread_result != 4 || memcmp(buf, "abcd", 4))
+        return __LINE__;
+
+    /* But another process cannot. */
+    pid = fork();
+    if (pid < 0)
+        return __LINE__;
+    if (pid == 0)
+        _exit(syscall(SYS_io_destroy, ctx) == -1 && errno == EINVAL ? 0 : 1);
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+        return __LINE__;
+    if (syscall(SYS_io_destroy, ctx) != 0)
+        return __LINE__;
+
+    /* The contexts a process leaves behind are destroyed as it exits, so
+     * their events do not count against the limit for good. */
+    for (int i = 0; i < 8; i++) {
+        pid = fork();
+        if (pid < 0)
+            return __LINE__;
+        if (pid == 0) {
+            aio_context_t leaked = 0;
+            _exit(syscall(SYS_io_setup, 16384, &leaked) == 0 ? 0 : 1);
+        }
+        if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+            return __LINE__;
+    }
+    close(fd);
+    unlink("aioshare.txt");
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+
+    if (failed) {
+        printf("aio_share: failed at %d\n", failed);
+        return 1;
+    }
+    puts("aio_share: ok");
+    return 0;
+}
@@ -0,0 +1,81 @@
+#include <fcntl.h>
+#include <sched.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define PATH "appendw.tmp"
+#define RECORDS 200
+#define RECORD_LEN 64
+
+/* Appends records of `c` through a file opened on its own. */
+static int append_records(char c)
+{
+    int fd = open(PATH, O_WRONLY | O_APPEND);
+    if (fd < 0)
+        return 1;
+    char record[RECORD_LEN];
+    memset(record, c, sizeof(record) - 1);
+    record[RECORD_LEN - 1] = '\n';
+    for (int i = 0; i < RECORDS; i++) {
+        if (write(fd, record, sizeof(record)) != sizeof(record))
+            return 1;
+        if (i % 16 == 0)
+            sched_yield();
+    }
+    close(fd);
+    return 0;
+}
+
+static int check(void)
+{
+    int fd = open(PATH, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0)
+        return 1;
+    pid_t pid = fork();
+    if (pid < 0)
+        return 2;
+    if (pid == 0)
+        _exit(append_records('a'));
+    if (append_records('b') != 0)
+        return 3;
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+        return 4;
+
+    /* Every record is whole, and none is lost. */
+    int counts[2] = {0, 0};
+    char record[RECORD_LEN];
+    ssize_t len;
+    while ((len = read(fd, record, sizeof(record))) == sizeof(record)) {
+        char c = record[0];
+        if (c != 'a' && c != 'b')
+            return 5;
+        for (int i = 1; i < RECORD_LEN - 1; i++) {
+            if (record[i] != c)
+                return 6;
+        }
+        if (record[RECORD_LEN - 1] != '\n')
+            return 7;
+        counts[c - 'a']++;
+    }
+    if (len != 0)
+        return 8;
+    if (counts[0] != RECORDS || counts[1] != RECORDS)
+        return 9;
+    close(fd);
+    unlink(PATH);
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("append_write: failed at %d\n", failed);
+        return 1;
+    }
+    puts("append_write: ok");
+    return 0;
+}
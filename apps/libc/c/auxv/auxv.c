@@ -0,0 +1,76 @@
+#include <stdio.h>
+#include <string.h>
+#include <sys/auxv.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+/* The 16 bytes behind AT_RANDOM of a run of this program with "child". */
+static int child_random(const char *self, unsigned char *bytes)
+{
+    char *const args[] = {(char *)self, "child", NULL};
+    int fds[2], status;
+    pid_t pid;
+    ssize_t n;
+
+    if (pipe(fds))
+        return -1;
+    pid = fork();
+    if (pid == 0) {
+        dup2(fds[1], STDOUT_FILENO);
+        close(fds[0]);
+        close(fds[1]);
+        execv(self, args);
+        _exit(127);
+    }
+    close(fds[1]);
+    n = read(fds[0], bytes, 16);
+    close(fds[0]);
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status))
+        return -1;
+    return n == 16 ? 0 : -1;
+}
+
+static int check(const char *self)
+{
+    static const unsigned char zero[16];
+    const unsigned char *random = (const unsigned char *)getauxval(AT_RANDOM);
+    unsigned char first[16], second[16];
+
+    if (!random || !memcmp(random, zero, 16))
+        return 1;
+    if (getauxval(AT_PAGESZ) != 4096 || sysconf(_SC_PAGESIZE) != 4096)
+        return 2;
+    if (!getauxval(AT_PHDR) || !getauxval(AT_ENTRY) || getauxval(AT_PHENT) == 0 ||
+        getauxval(AT_PHNUM) == 0)
+        return 3;
+    if (getauxval(AT_UID) != getuid() || getauxval(AT_EUID) != geteuid() ||
+        getauxval(AT_GID) != getgid() || getauxval(AT_EGID) != getegid())
+        return 4;
+    if (getauxval(AT_SECURE) != 0)
+        return 5;
+    if (getauxval(AT_CLKTCK) != 100)
+        return 6;
+    /* Every exec gets fresh bytes. */
+    if (child_random(self, first) || child_random(self, second))
+        return 7;
+    if (!memcmp(first, second, 16) || !memcmp(first, random, 16) || !memcmp(first, zero, 16))
+        return 8;
+    return 0;
+}
+
+int main(int argc, char *argv[])
+{
+    int failed;
+
+    if (argc > 1 && !strcmp(argv[1], "child")) {
+        const void *random = (const void *)getauxval(AT_RANDOM);
+        return !random || write(STDOUT_FILENO, random, 16) != 16;
+    }
+    failed = check(argv[0]);
+    if (failed) {
+        printf("auxv: failed at %d\n", failed);
+        return 1;
+    }
+    puts("auxv: ok");
+    return 0;
+}
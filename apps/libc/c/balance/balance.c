@@ -0,0 +1,89 @@
+#define _GNU_SOURCE
+#include <pthread.h>
+#include <sched.h>
+#include <stdio.h>
+#include <time.h>
+#include <unistd.h>
+
+#define MAX_THREADS 64
+#define SPIN_NANOS 300000000LL
+
+struct worker {
+    pthread_t thread;
+    int pinned;
+    unsigned long long cpus_seen;
+    int failed;
+};
+
+static long long now_nanos(void)
+{
+    struct timespec ts;
+    clock_gettime(CLOCK_MONOTONIC, &ts);
+    return ts.tv_sec * 1000000000LL + ts.tv_nsec;
+}
+
+/* Spins for a while, noting each CPU it finds itself on. */
+static void *worker_main(void *arg)
+{
+    struct worker *worker = arg;
+    if (worker->pinned) {
+        cpu_set_t set;
+        CPU_ZERO(&set);
+        CPU_SET(0, &set);
+        if (sched_setaffinity(0, sizeof(set), &set) != 0) {
+            worker->failed = 1;
+            return NULL;
+        }
+    }
+    long long end = now_nanos() + SPIN_NANOS;
+    while (now_nanos() < end) {
+        int cpu = sched_getcpu();
+        if (cpu >= 0 && cpu < 64)
+            worker->cpus_seen |= 1ULL << cpu;
+    }
+    return NULL;
+}
+
+static int check(void)
+{
+    static struct worker workers[MAX_THREADS];
+    long cpus = sysconf(_SC_NPROCESSORS_ONLN);
+    unsigned long long all_seen = 0;
+    int count, i;
+
+    if (cpus < 1)
+        return 1;
+    if (cpus > 32)
+        cpus = 32;
+    /* Twice as many as the CPUs can run at once, the first pinned to CPU 0. */
+    count = cpus * 2;
+    for (i = 0; i < count; i++) {
+        workers[i].pinned = i == 0;
+        if (pthread_create(&workers[i].thread, NULL, worker_main, &workers[i]) != 0)
+            return 2;
+    }
+    for (i = 0; i < count; i++) {
+        pthread_join(workers[i].thread, NULL);
+        if (workers[i].failed)
+            return 3;
+        all_seen |= workers[i].cpus_seen;
+    }
+
+    if (workers[0].cpus_seen != 1)
+        return 4;
+    /* Every CPU ran some of them. */
+    if (all_seen != (1ULL << cpus) - 1)
+        return 5;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("balance: failed at %d\n", failed);
+        return 1;
+    }
+    puts("balance: ok");
+    return 0;
+}
@@ -0,0 +1,59 @@
+#define _GNU_SOURCE
+#include <sched.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static char child_stack[65536] __attribute__((aligned(16)));
+
+/* Only makes system calls directly, as it shares the TLS of its parent. */
+static int child_main(void *arg)
+{
+    syscall(SYS_brk, (long)arg);
+    syscall(SYS_exit, 0);
+    return 0;
+}
+
+static int check(void)
+{
+    long start = syscall(SYS_brk, 0);
+    int status;
+    pid_t pid;
+
+    /* The break moved in a task sharing the address space moves here too. */
+    pid = clone(child_main, child_stack + sizeof(child_stack), CLONE_VM | SIGCHLD,
+                (void *)(start + 8192));
+    if (pid < 0)
+        return __LINE__;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status))
+        return __LINE__;
+    if (syscall(SYS_brk, 0) != start + 8192)
+        return __LINE__;
+
+    /* It is not moved by a forked child. */
+    pid = fork();
+    if (pid < 0)
+        return __LINE__;
+    if (pid == 0)
+        _exit(syscall(SYS_brk, start + 16384) == start + 16384 ? 0 : 1);
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+        return __LINE__;
+    if (syscall(SYS_brk, 0) != start + 8192)
+        return __LINE__;
+    syscall(SYS_brk, start);
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+
+    if (failed) {
+        printf("brk_vm: failed at %d\n", failed);
+        return 1;
+    }
+    puts("brk_vm: ok");
+    return 0;
+}
@@ -0,0 +1,50 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <stdio.h>
+#include <sys/prctl.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define CAP_NET_RAW 13
+
+static int check(void)
+{
+    int status;
+    pid_t pid;
+
+    if (prctl(PR_CAPBSET_READ, CAP_NET_RAW, 0, 0, 0) != 1)
+        return 1;
+    if (prctl(PR_CAPBSET_DROP, CAP_NET_RAW, 0, 0, 0) != 0)
+        return 2;
+    if (prctl(PR_CAPBSET_READ, CAP_NET_RAW, 0, 0, 0) != 0)
+        return 3;
+    /* Dropping it again is no error. */
+    if (prctl(PR_CAPBSET_DROP, CAP_NET_RAW, 0, 0, 0) != 0)
+        return 4;
+    if (prctl(PR_CAPBSET_READ, CAP_NET_RAW - 1, 0, 0, 0) != 1)
+        return 5;
+    if (prctl(PR_CAPBSET_READ, 1000, 0, 0, 0) != -1 || errno != EINVAL)
+        return 6;
+
+    /* Children inherit the bounding set. */
+    pid = fork();
+    if (pid < 0)
+        return 7;
+    if (pid == 0)
+        _exit(prctl(PR_CAPBSET_READ, CAP_NET_RAW, 0, 0, 0) == 0 ? 0 : 1);
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+        return 8;
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("capbset: failed at %d\n", ret);
+        return 1;
+    }
+    puts("capbset: ok");
+    return 0;
+}
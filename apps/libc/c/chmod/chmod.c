@@ -0,0 +1,76 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define FILE_PATH "chmod.file"
+#define LINK_PATH "chmod.link"
+
+static int check(void)
+{
+    struct stat st;
+    int fd = open(FILE_PATH, O_CREAT | O_WRONLY | O_TRUNC, 0644);
+    if (fd < 0)
+        return 1;
+    close(fd);
+
+    if (chmod(FILE_PATH, 0751) != 0)
+        return 2;
+    if (stat(FILE_PATH, &st) != 0 || (st.st_mode & 07777) != 0751 || !S_ISREG(st.st_mode))
+        return 3;
+
+    /* Changing the owner of the link leaves the file it points to alone. */
+    if (symlink(FILE_PATH, LINK_PATH) != 0)
+        return 4;
+    if (lchown(LINK_PATH, 1000, 1000) != 0)
+        return 5;
+    if (lstat(LINK_PATH, &st) != 0 || !S_ISLNK(st.st_mode) || st.st_uid != 1000 ||
+        st.st_gid != 1000)
+        return 6;
+    if (stat(FILE_PATH, &st) != 0 || st.st_uid != 0 || st.st_gid != 0)
+        return 7;
+    /* Through the link, it is the file that changes. */
+    if (chown(LINK_PATH, 1001, -1) != 0)
+        return 8;
+    if (stat(FILE_PATH, &st) != 0 || st.st_uid != 1001 || st.st_gid != 0)
+        return 9;
+    if (lstat(LINK_PATH, &st) != 0 || st.st_uid != 1000)
+        return 10;
+
+    /* Only the owner may change the mode, and only root may give it away. */
+    if (fork() == 0) {
+        if (setuid(1001) != 0)
+            _exit(11);
+        if (chmod(FILE_PATH, 0600) != 0)
+            _exit(12);
+        if (chown(FILE_PATH, 1002, -1) == 0 || errno != EPERM)
+            _exit(13);
+        if (lchown(LINK_PATH, -1, 1001) == 0 || errno != EPERM)
+            _exit(14);
+        _exit(0);
+    }
+    int status;
+    if (wait(&status) < 0 || !WIFEXITED(status))
+        return 15;
+    if (WEXITSTATUS(status))
+        return WEXITSTATUS(status);
+    if (stat(FILE_PATH, &st) != 0 || (st.st_mode & 07777) != 0600)
+        return 16;
+
+    unlink(LINK_PATH);
+    unlink(FILE_PATH);
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("chmod: failed at %d\n", failed);
+        return 1;
+    }
+    puts("chmod: ok");
+    return 0;
+}
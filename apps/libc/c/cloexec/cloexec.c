@@ -0,0 +1,106 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+/* Run after the exec, with the fds that should be gone and the one that
+ * should be left as arguments. */
+static int child(int argc, char *argv[])
+{
+    for (int i = 2; i < argc - 1; i++) {
+        int fd = atoi(argv[i]);
+        if (fcntl(fd, F_GETFD) != -1 || errno != EBADF)
+            return i;
+    }
+    int kept = atoi(argv[argc - 1]);
+    if (fcntl(kept, F_GETFD) != 0)
+        return 50;
+    return 0;
+}
+
+static int check(char *argv[])
+{
+    int fds[8];
+    int n = 0;
+
+    fds[n++] = socket(AF_UNIX, SOCK_STREAM | SOCK_CLOEXEC, 0);
+    if (fds[0] < 0 || fcntl(fds[0], F_GETFD) != FD_CLOEXEC)
+        return __LINE__;
+    int sv[2];
+    if (socketpair(AF_UNIX, SOCK_STREAM | SOCK_CLOEXEC, 0, sv) != 0)
+        return __LINE__;
+    fds[n++] = sv[0];
+    fds[n++] = sv[1];
+    int pfd[2];
+    if (pipe2(pfd, O_CLOEXEC) != 0 || fcntl(pfd[1], F_GETFD) != FD_CLOEXEC)
+        return __LINE__;
+    fds[n++] = pfd[0];
+    fds[n++] = pfd[1];
+    fds[n] = open("/", O_RDONLY | O_DIRECTORY | O_CLOEXEC);
+    if (fds[n++] < 0)
+        return __LINE__;
+
+    int plain = socket(AF_UNIX, SOCK_STREAM, 0);
+    if (plain < 0 || fcntl(plain, F_GETFD) != 0)
+        return __LINE__;
+    fds[n] = fcntl(plain, F_DUPFD_CLOEXEC, 0);
+    if (fds[n] < 0 || fcntl(fds[n++], F_GETFD) != FD_CLOEXEC)
+        return __LINE__;
+    fds[n] = dup3(plain, 100, O_CLOEXEC);
+    if (fds[n] != 100 || fcntl(fds[n++], F_GETFD) != FD_CLOEXEC)
+        return __LINE__;
+
+    /* The flag can be set and cleared, and does not follow the file to a
+     * new fd. */
+    if (fcntl(plain, F_SETFD, FD_CLOEXEC) != 0 || fcntl(plain, F_GETFD) != FD_CLOEXEC)
+        return __LINE__;
+    if (dup2(plain, 101) != 101 || fcntl(101, F_GETFD) != 0)
+        return __LINE__;
+    if (fcntl(plain, F_SETFD, 0) != 0 || fcntl(plain, F_GETFD) != 0)
+        return __LINE__;
+    if (dup3(plain, plain, O_CLOEXEC) != -1 || errno != EINVAL)
+        return __LINE__;
+    if (fcntl(999, F_GETFD) != -1 || errno != EBADF)
+        return __LINE__;
+
+    pid_t pid = fork();
+    if (pid < 0)
+        return __LINE__;
+    if (pid == 0) {
+        char strs[9][16];
+        char *args[12] = {argv[0], "child"};
+        for (int i = 0; i < n; i++) {
+            snprintf(strs[i], sizeof(strs[i]), "%d", fds[i]);
+            args[2 + i] = strs[i];
+        }
+        snprintf(strs[n], sizeof(strs[n]), "%d", plain);
+        args[2 + n] = strs[n];
+        args[3 + n] = NULL;
+        execv(argv[0], args);
+        _exit(100);
+    }
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status))
+        return __LINE__;
+    if (WEXITSTATUS(status) != 0)
+        return 1000 + WEXITSTATUS(status);
+    return 0;
+}
+
+int main(int argc, char *argv[])
+{
+    if (argc > 1 && strcmp(argv[1], "child") == 0)
+        return child(argc, argv);
+    int failed = check(argv);
+    if (failed) {
+        printf("cloexec: failed at %d\n", failed);
+        return 1;
+    }
+    puts("cloexec: ok");
+    return 0;
+}
@@ -0,0 +1,133 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <sched.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+#ifdef __x86_64__
+#include <asm/prctl.h>
+#endif
+
+static char thread_stack[65536] __attribute__((aligned(16)));
+/* What the thread pointer of the thread is set to. */
+static unsigned long fake_tls[64] __attribute__((aligned(64)));
+
+static volatile pid_t thread_tid = -1;
+static volatile pid_t seen_tid;
+static volatile unsigned long seen_tls;
+static volatile int thread_done;
+
+static unsigned long thread_pointer(void)
+{
+    unsigned long tp = 0;
+
+#if defined(__x86_64__)
+    syscall(SYS_arch_prctl, ARCH_GET_FS, &tp);
+#elif defined(__aarch64__)
+    __asm__ volatile("mrs %0, tpidr_el0" : "=r"(tp));
+#elif defined(__riscv)
+    __asm__ volatile("mv %0, tp" : "=r"(tp));
+#elif defined(__loongarch__)
+    __asm__ volatile("move %0, $tp" : "=r"(tp));
+#endif
+    return tp;
+}
+
+/* Only makes system calls directly, as its TLS is not a real one. */
+static int thread_main(void *arg)
+{
+    (void)arg;
+    seen_tls = thread_pointer();
+    seen_tid = thread_tid;
+    __atomic_store_n(&thread_done, 1, __ATOMIC_RELEASE);
+    syscall(SYS_exit, 0);
+    return 0;
+}
+
+/* clone with the thread ID addresses in the order of the architecture. */
+static long raw_clone(unsigned long flags, void *ptid, void *ctid)
+{
+#ifdef __x86_64__
+    return syscall(SYS_clone, flags, 0, ptid, ctid, 0);
+#else
+    return syscall(SYS_clone, flags, 0, ptid, 0, ctid);
+#endif
+}
+
+/* Whether the clone failed with EFAULT. A child made anyway exits at once. */
+static int bad_clone(unsigned long flags, void *ptid, void *ctid)
+{
+    long pid;
+
+    errno = 0;
+    pid = raw_clone(flags, ptid, ctid);
+    if (pid == 0)
+        _exit(0);
+    if (pid > 0) {
+        waitpid(pid, NULL, 0);
+        return 1;
+    }
+    return errno != EFAULT;
+}
+
+static int check(void)
+{
+    pid_t parent_tid = 0, child_tid = 0;
+    int spins, status;
+    long pid;
+
+    /* With no stack given, the child goes on with a copy of this one. */
+    pid = raw_clone(CLONE_PARENT_SETTID | CLONE_CHILD_SETTID | SIGCHLD, &parent_tid, &child_tid);
+    if (pid < 0)
+        return 1;
+    if (pid == 0)
+        _exit(child_tid == getpid() && parent_tid == 0 ? 0 : 1);
+    if (parent_tid != pid || child_tid != 0)
+        return 2;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+        return 3;
+
+    /* Bad thread ID addresses fail the clone. */
+    if (bad_clone(CLONE_PARENT_SETTID | SIGCHLD, (void *)8, NULL))
+        return 4;
+    if (bad_clone(CLONE_CHILD_CLEARTID | SIGCHLD, NULL, (void *)8))
+        return 5;
+
+    /* A thread gets the TLS it is given, and its thread ID slot is set and
+     * then cleared as it exits. */
+    pid = clone(thread_main, thread_stack + sizeof(thread_stack),
+                CLONE_VM | CLONE_SIGHAND | CLONE_THREAD | CLONE_SETTLS | CLONE_CHILD_SETTID |
+                    CLONE_CHILD_CLEARTID,
+                NULL, NULL, fake_tls + 32, &thread_tid);
+    if (pid < 0)
+        return 6;
+    for (spins = 0; thread_tid != 0; spins++) {
+        if (spins == 100000)
+            return 7;
+        sched_yield();
+    }
+    if (!__atomic_load_n(&thread_done, __ATOMIC_ACQUIRE))
+        return 8;
+    if (seen_tid != pid)
+        return 9;
+    if (seen_tls != (unsigned long)(fake_tls + 32))
+        return 10;
+    /* The caller's own is untouched. */
+    if (thread_pointer() == seen_tls)
+        return 11;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+
+    if (failed) {
+        printf("clone_tls: failed at %d\n", failed);
+        return 1;
+    }
+    puts("clone_tls: ok");
+    return 0;
+}
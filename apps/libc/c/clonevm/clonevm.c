@@ -0,0 +1,109 @@
+#define _GNU_SOURCE
+#include <sched.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/resource.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define PAGE 4096
+
+static char child_stack[65536] __attribute__((aligned(16)));
+
+/* How far along each side is, and the pages they hand to each other. */
+static int stage;
+static int *parent_page;
+static int *child_page;
+
+static void wait_stage(int value)
+{
+    while (__atomic_load_n(&stage, __ATOMIC_ACQUIRE) != value)
+        syscall(SYS_sched_yield);
+}
+
+static void set_stage(int value)
+{
+    __atomic_store_n(&stage, value, __ATOMIC_RELEASE);
+}
+
+/* Pins the caller to `cpu` if there is one. */
+static void pin(int cpu)
+{
+    cpu_set_t set;
+
+    if (cpu >= sysconf(_SC_NPROCESSORS_ONLN))
+        return;
+    CPU_ZERO(&set);
+    CPU_SET(cpu, &set);
+    syscall(SYS_sched_setaffinity, 0, sizeof(set), &set);
+}
+
+/* Only makes system calls directly, as it shares the TLS of its parent. */
+static int child_main(void *arg)
+{
+    (void)arg;
+    pin(1);
+    wait_stage(1);
+    /* The page the parent mapped after the clone is there. */
+    if (parent_page[0] != 42)
+        syscall(SYS_exit, 1);
+    parent_page[0] = 43;
+    child_page = (int *)syscall(SYS_mmap, NULL, PAGE, PROT_READ | PROT_WRITE,
+                                MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (child_page == MAP_FAILED)
+        syscall(SYS_exit, 2);
+    child_page[0] = 7;
+    set_stage(2);
+    wait_stage(3);
+    /* After the unmap, the page is gone for this CPU too. */
+    syscall(SYS_sched_yield);
+    *(volatile int *)parent_page = 44;
+    syscall(SYS_exit, 3);
+    return 0;
+}
+
+static int check(void)
+{
+    struct rlimit no_core = {0, 0};
+    int status;
+    pid_t pid;
+
+    setrlimit(RLIMIT_CORE, &no_core);
+    pin(0);
+    pid = clone(child_main, child_stack + sizeof(child_stack), CLONE_VM | SIGCHLD, NULL);
+    if (pid < 0)
+        return 1;
+    parent_page = mmap(NULL, PAGE, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (parent_page == MAP_FAILED)
+        return 2;
+    parent_page[0] = 42;
+    set_stage(1);
+    wait_stage(2);
+    if (parent_page[0] != 43)
+        return 3;
+    /* And the one the child mapped is here. */
+    if (child_page[0] != 7)
+        return 4;
+    if (munmap(parent_page, PAGE))
+        return 5;
+    set_stage(3);
+    if (waitpid(pid, &status, 0) != pid)
+        return 6;
+    if (!WIFSIGNALED(status) || WTERMSIG(status) != SIGSEGV)
+        return 7;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+
+    if (failed) {
+        printf("clone_vm: failed at %d\n", failed);
+        return 1;
+    }
+    puts("clone_vm: ok");
+    return 0;
+}
@@ -0,0 +1,93 @@
+#include <elf.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/prctl.h>
+#include <sys/resource.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+/* Crashes in a child, returning its wait status. */
+static int crash(int dumpable)
+{
+    struct rlimit rl = { RLIM_INFINITY, RLIM_INFINITY };
+    int status;
+    pid_t pid = fork();
+
+    if (pid < 0)
+        return -1;
+    if (pid == 0) {
+        if (setrlimit(RLIMIT_CORE, &rl) != 0 || prctl(PR_SET_DUMPABLE, dumpable) != 0)
+            _exit(1);
+        *(volatile int *)0 = 0;
+        _exit(2);
+    }
+    if (waitpid(pid, &status, 0) != pid)
+        return -1;
+    return status;
+}
+
+static int check(void)
+{
+    Elf64_Ehdr ehdr;
+    Elf64_Phdr phdr;
+    struct stat st;
+    int fd, i, loads = 0, status, ret = 0;
+
+    unlinkat(AT_FDCWD, "core", 0);
+    status = crash(1);
+    if (status == -1 || !WIFSIGNALED(status) || WTERMSIG(status) != SIGSEGV)
+        return 1;
+    if (!WCOREDUMP(status))
+        return 2;
+
+    fd = open("core", O_RDONLY);
+    if (fd < 0)
+        return 3;
+    if (read(fd, &ehdr, sizeof(ehdr)) != sizeof(ehdr) || memcmp(ehdr.e_ident, ELFMAG, SELFMAG)
+        || ehdr.e_ident[EI_CLASS] != ELFCLASS64 || ehdr.e_type != ET_CORE) {
+        ret = 4;
+        goto out;
+    }
+    for (i = 0; i < ehdr.e_phnum; i++) {
+        if (pread(fd, &phdr, sizeof(phdr), ehdr.e_phoff + i * ehdr.e_phentsize) != sizeof(phdr)) {
+            ret = 5;
+            goto out;
+        }
+        if (i == 0 && phdr.p_type != PT_NOTE) {
+            ret = 6;
+            goto out;
+        }
+        if (phdr.p_type == PT_LOAD)
+            loads++;
+    }
+    if (loads == 0)
+        ret = 7;
+
+out:
+    close(fd);
+    unlinkat(AT_FDCWD, "core", 0);
+    if (ret)
+        return ret;
+
+    /* A non-dumpable process leaves no core behind. */
+    status = crash(0);
+    if (status == -1 || !WIFSIGNALED(status) || WCOREDUMP(status))
+        return 8;
+    if (fstatat(AT_FDCWD, "core", &st, 0) == 0)
+        return 9;
+    return 0;
+}
+
+int main(void)
+{
+    int ret = check();
+    if (ret) {
+        printf("coredump: failed at %d\n", ret);
+        return 1;
+    }
+    puts("coredump: ok");
+    return 0;
+}
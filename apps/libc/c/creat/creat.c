@@ -0,0 +1,51 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    struct stat st;
+
+    /* A new file gets the mode less the umask. */
+    umask(022);
+    int fd = creat("creat.tmp", 0666);
+    if (fd < 0)
+        return 1;
+    if (fstat(fd, &st) != 0 || (st.st_mode & 0777) != 0644 || st.st_size != 0)
+        return 2;
+    if (write(fd, "hello, world", 12) != 12)
+        return 3;
+    close(fd);
+    if (stat("creat.tmp", &st) != 0 || st.st_size != 12)
+        return 4;
+
+    /* An existing file is truncated, and opened for writing only. */
+    fd = creat("creat.tmp", 0600);
+    if (fd < 0)
+        return 5;
+    if (fstat(fd, &st) != 0 || st.st_size != 0 || (st.st_mode & 0777) != 0644)
+        return 6;
+    char c;
+    if (read(fd, &c, 1) >= 0)
+        return 7;
+    if (write(fd, "x", 1) != 1)
+        return 8;
+    close(fd);
+    if (stat("creat.tmp", &st) != 0 || st.st_size != 1)
+        return 9;
+
+    unlink("creat.tmp");
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("creat: failed at %d\n", failed);
+        return 1;
+    }
+    puts("creat: ok");
+    return 0;
+}
@@ -0,0 +1,47 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/ioctl.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    pid_t sid = 0;
+
+    /* Only a session leader can acquire a controlling terminal. */
+    if (ioctl(STDIN_FILENO, TIOCSCTTY, 0) != -1 || errno != EPERM)
+        return 1;
+    if (setsid() < 0)
+        return 2;
+    /* The console still controls the session of our parent. */
+    if (ioctl(STDIN_FILENO, TIOCSCTTY, 0) != -1 || errno != EPERM)
+        return 3;
+    if (ioctl(STDIN_FILENO, TIOCSCTTY, 1) != 0)
+        return 4;
+    if (ioctl(STDIN_FILENO, TIOCGSID, &sid) != 0 || sid != getpid())
+        return 5;
+    /* Giving it up hangs up the foreground process group, which is us. */
+    signal(SIGHUP, SIG_IGN);
+    if (ioctl(STDIN_FILENO, TIOCNOTTY) != 0)
+        return 6;
+    if (ioctl(STDIN_FILENO, TIOCGSID, &sid) != -1 || errno != ENOTTY)
+        return 7;
+    return 0;
+}
+
+int main()
+{
+    int status;
+    pid_t pid = fork();
+
+    if (pid == 0) {
+        _exit(check());
+    }
+    waitpid(pid, &status, 0);
+    if (WIFEXITED(status) && WEXITSTATUS(status) == 0)
+        printf("ctty: ok\n");
+    else
+        printf("ctty: failed at step %d\n", WEXITSTATUS(status));
+    return 0;
+}
@@ -0,0 +1,34 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <unistd.h>
+
+int main()
+{
+    char buf[10];
+    int fd, i, ok;
+
+    fd = open("/dev/zero", O_RDONLY);
+    for (i = 0; i < 10; i++)
+        buf[i] = 0x5a;
+    ok = fd >= 0 && read(fd, buf, sizeof(buf)) == sizeof(buf);
+    for (i = 0; ok && i < 10; i++)
+        ok = buf[i] == 0;
+    close(fd);
+    printf("dev_zero: %s\n", ok ? "ok" : "failed");
+
+    fd = open("/dev/null", O_WRONLY);
+    ok = fd >= 0 && write(fd, "discarded", 9) == 9;
+    close(fd);
+    fd = open("/dev/null", O_RDONLY);
+    ok = ok && fd >= 0 && read(fd, buf, sizeof(buf)) == 0;
+    close(fd);
+    printf("dev_null: %s\n", ok ? "ok" : "failed");
+
+    fd = open("/dev/full", O_WRONLY);
+    errno = 0;
+    ok = fd >= 0 && write(fd, "x", 1) == -1 && errno == ENOSPC;
+    close(fd);
+    printf("dev_full: %s\n", ok ? "ok" : "failed");
+    return 0;
+}
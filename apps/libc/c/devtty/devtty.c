@@ -0,0 +1,35 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+int main()
+{
+    const char *msg = "dev_tty: written to console\n";
+    int fd, status;
+    pid_t pid;
+
+    fd = open("/dev/tty", O_WRONLY);
+    if (fd < 0 || write(fd, msg, strlen(msg)) != (ssize_t)strlen(msg))
+        printf("dev_tty: open failed\n");
+    close(fd);
+    fflush(stdout);
+
+    pid = fork();
+    if (pid == 0) {
+        /* A new session has no controlling terminal. */
+        errno = 0;
+        if (setsid() < 0)
+            printf("dev_tty: setsid failed\n");
+        else if (open("/dev/tty", O_RDWR) < 0 && errno == ENXIO)
+            printf("dev_tty: no tty ok\n");
+        else
+            printf("dev_tty: no tty failed\n");
+        fflush(stdout);
+        _exit(0);
+    }
+    waitpid(pid, &status, 0);
+    return 0;
+}
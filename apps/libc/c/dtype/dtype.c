@@ -0,0 +1,71 @@
+#define _GNU_SOURCE
+#include <dirent.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+struct linux_dirent64 {
+    unsigned long long d_ino;
+    long long d_off;
+    unsigned short d_reclen;
+    unsigned char d_type;
+    char d_name[];
+};
+
+static int check(void)
+{
+    char buf[1024];
+    int fd, seen_dir = 0, seen_file = 0;
+    long len, pos;
+
+    if (mkdirat(AT_FDCWD, "dtype_dir", 0755) != 0 || mkdirat(AT_FDCWD, "dtype_dir/sub", 0755) != 0)
+        return 1;
+    fd = open("dtype_dir/file", O_WRONLY | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0)
+        return 2;
+    close(fd);
+
+    fd = open("dtype_dir", O_RDONLY | O_DIRECTORY);
+    if (fd < 0)
+        return 3;
+    len = syscall(SYS_getdents64, fd, buf, sizeof(buf));
+    if (len <= 0)
+        return 4;
+    for (pos = 0; pos < len;) {
+        struct linux_dirent64 *ent = (struct linux_dirent64 *)(buf + pos);
+
+        if (strcmp(ent->d_name, "sub") == 0) {
+            if (ent->d_type != DT_DIR)
+                return 5;
+            seen_dir = 1;
+        } else if (strcmp(ent->d_name, "file") == 0) {
+            if (ent->d_type != DT_REG)
+                return 6;
+            seen_file = 1;
+        }
+        pos += ent->d_reclen;
+    }
+    close(fd);
+    if (!seen_dir || !seen_file)
+        return 7;
+
+    unlink("dtype_dir/file");
+    unlinkat(AT_FDCWD, "dtype_dir/sub", AT_REMOVEDIR);
+    unlinkat(AT_FDCWD, "dtype_dir", AT_REMOVEDIR);
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("dtype: failed at %d\n", ret);
+        return 1;
+    }
+    puts("dtype: ok");
+    return 0;
+}
@@ -0,0 +1,85 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#define DIR "/tmp/eloop"
+#define CHAIN 41
+
+static void cleanup(void)
+{
+    char path[64];
+
+    unlink(DIR "/a");
+    unlink(DIR "/b");
+    unlink(DIR "/file");
+    for (int i = 0; i < CHAIN; i++) {
+        snprintf(path, sizeof(path), DIR "/l%d", i);
+        unlink(path);
+    }
+    rmdir(DIR);
+}
+
+static int check(void)
+{
+    char path[64], target[64];
+    struct stat st;
+    int fd;
+
+    mkdir(DIR, 0755);
+    if (symlink("b", DIR "/a") || symlink("a", DIR "/b"))
+        return 1;
+    if (open(DIR "/a", O_RDONLY) != -1 || errno != ELOOP)
+        return 2;
+    if (open(DIR "/a", O_WRONLY | O_CREAT, 0644) != -1 || errno != ELOOP)
+        return 3;
+    if (stat(DIR "/a", &st) != -1 || errno != ELOOP)
+        return 4;
+    if (lstat(DIR "/a", &st) || !S_ISLNK(st.st_mode))
+        return 5;
+    /* A loop on the way to the last component. */
+    if (open(DIR "/a/x", O_RDONLY) != -1 || errno != ELOOP)
+        return 6;
+    if (chdir(DIR "/a") != -1 || errno != ELOOP)
+        return 7;
+    if (access(DIR "/b", F_OK) != -1 || errno != ELOOP)
+        return 8;
+
+    /* A chain of 40 links is followed, but not one of 41. */
+    fd = open(DIR "/file", O_WRONLY | O_CREAT, 0644);
+    if (fd < 0)
+        return 9;
+    close(fd);
+    for (int i = 0; i < CHAIN; i++) {
+        if (i == 0)
+            snprintf(target, sizeof(target), "file");
+        else
+            snprintf(target, sizeof(target), "l%d", i - 1);
+        snprintf(path, sizeof(path), DIR "/l%d", i);
+        if (symlink(target, path))
+            return 10;
+    }
+    fd = open(DIR "/l39", O_RDONLY);
+    if (fd < 0)
+        return 11;
+    close(fd);
+    if (open(DIR "/l40", O_RDONLY) != -1 || errno != ELOOP)
+        return 12;
+    return 0;
+}
+
+int main(void)
+{
+    int failed;
+
+    cleanup();
+    failed = check();
+    cleanup();
+    if (failed) {
+        printf("eloop: failed at %d\n", failed);
+        return 1;
+    }
+    puts("eloop: ok");
+    return 0;
+}
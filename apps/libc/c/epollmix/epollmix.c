@@ -0,0 +1,83 @@
+#include <stdint.h>
+#include <stdio.h>
+#include <sys/epoll.h>
+#include <sys/eventfd.h>
+#include <sys/timerfd.h>
+#include <unistd.h>
+
+enum { PIPE_EV = 1, EVENT_EV = 2, TIMER_EV = 4 };
+
+static int add(int epfd, int fd, int tag)
+{
+    struct epoll_event ev = { .events = EPOLLIN, .data.u32 = tag };
+
+    return epoll_ctl(epfd, EPOLL_CTL_ADD, fd, &ev);
+}
+
+static int check(void)
+{
+    struct itimerspec its = { .it_value = { .tv_nsec = 10000000 } };
+    struct epoll_event evs[4];
+    int pipefd[2], efd, tfd, epfd, seen = 0, tries, i, n;
+    uint64_t value = 1;
+
+    if (pipe(pipefd) != 0)
+        return 1;
+    efd = eventfd(0, EFD_NONBLOCK);
+    tfd = timerfd_create(CLOCK_MONOTONIC, TFD_NONBLOCK);
+    epfd = epoll_create1(0);
+    if (efd < 0 || tfd < 0 || epfd < 0)
+        return 2;
+    if (add(epfd, pipefd[0], PIPE_EV) || add(epfd, efd, EVENT_EV) || add(epfd, tfd, TIMER_EV))
+        return 3;
+
+    if (epoll_wait(epfd, evs, 4, 0) != 0)
+        return 4;
+    if (write(pipefd[1], "x", 1) != 1 || write(efd, &value, sizeof(value)) != sizeof(value))
+        return 5;
+    if (timerfd_settime(tfd, 0, &its, NULL) != 0)
+        return 6;
+
+    /* Each file is read once it is reported, so that it is not again. */
+    for (tries = 0; seen != (PIPE_EV | EVENT_EV | TIMER_EV) && tries < 10; tries++) {
+        n = epoll_wait(epfd, evs, 4, 1000);
+        if (n < 0)
+            return 7;
+        for (i = 0; i < n; i++) {
+            int tag = evs[i].data.u32;
+
+            if (!(evs[i].events & EPOLLIN) || (seen & tag))
+                return 8;
+            seen |= tag;
+            if (tag == PIPE_EV && read(pipefd[0], &value, 1) != 1)
+                return 9;
+            if (tag == EVENT_EV && (read(efd, &value, sizeof(value)) != sizeof(value) || value != 1))
+                return 10;
+            if (tag == TIMER_EV && (read(tfd, &value, sizeof(value)) != sizeof(value) || value != 1))
+                return 11;
+        }
+    }
+    if (seen != (PIPE_EV | EVENT_EV | TIMER_EV))
+        return 12;
+    if (epoll_wait(epfd, evs, 4, 0) != 0)
+        return 13;
+
+    close(epfd);
+    close(tfd);
+    close(efd);
+    close(pipefd[0]);
+    close(pipefd[1]);
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("epoll_mix: failed at %d\n", ret);
+        return 1;
+    }
+    puts("epoll_mix: ok");
+    return 0;
+}
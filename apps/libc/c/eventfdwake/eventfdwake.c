@@ -0,0 +1,94 @@
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <poll.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <sys/epoll.h>
+#include <sys/eventfd.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+/* Forks a child that adds `value` to `efd` after a short while. */
+static pid_t add_later(int efd, uint64_t value)
+{
+    pid_t pid = fork();
+    if (pid == 0) {
+        struct timespec ts = { 0, 20 * 1000 * 1000 };
+        nanosleep(&ts, NULL);
+        _exit(write(efd, &value, sizeof(value)) == sizeof(value) ? 0 : 1);
+    }
+    return pid;
+}
+
+static int reap(pid_t pid)
+{
+    int status;
+    return waitpid(pid, &status, 0) == pid && WIFEXITED(status) && WEXITSTATUS(status) == 0;
+}
+
+static int check(void)
+{
+    int efd = eventfd(0, EFD_CLOEXEC);
+    if (efd < 0 || fcntl(efd, F_GETFD) != FD_CLOEXEC)
+        return __LINE__;
+    uint64_t value;
+
+    /* A poll with no timeout returns once the counter is written. */
+    pid_t pid = add_later(efd, 3);
+    if (pid < 0)
+        return __LINE__;
+    struct pollfd pfd = { .fd = efd, .events = POLLIN };
+    if (poll(&pfd, 1, -1) != 1 || !(pfd.revents & POLLIN))
+        return __LINE__;
+    if (read(efd, &value, sizeof(value)) != sizeof(value) || value != 3)
+        return __LINE__;
+    if (!reap(pid))
+        return __LINE__;
+
+    /* So does an epoll_wait. */
+    int epfd = epoll_create1(EPOLL_CLOEXEC);
+    if (epfd < 0 || fcntl(epfd, F_GETFD) != FD_CLOEXEC)
+        return __LINE__;
+    struct epoll_event ev = { .events = EPOLLIN, .data.fd = efd };
+    if (epoll_ctl(epfd, EPOLL_CTL_ADD, efd, &ev) != 0)
+        return __LINE__;
+    if (epoll_wait(epfd, &ev, 1, 0) != 0)
+        return __LINE__;
+    pid = add_later(efd, 5);
+    if (pid < 0)
+        return __LINE__;
+    if (epoll_wait(epfd, &ev, 1, -1) != 1 || ev.data.fd != efd || !(ev.events & EPOLLIN))
+        return __LINE__;
+    if (!reap(pid))
+        return __LINE__;
+
+    /* And a blocking read, which takes all of it. */
+    if (read(efd, &value, sizeof(value)) != sizeof(value) || value != 5)
+        return __LINE__;
+    pid = add_later(efd, 7);
+    if (pid < 0)
+        return __LINE__;
+    if (read(efd, &value, sizeof(value)) != sizeof(value) || value != 7)
+        return __LINE__;
+    if (!reap(pid))
+        return __LINE__;
+
+    /* A timeout still ends the wait when nothing is written. */
+    if (poll(&pfd, 1, 20) != 0 || epoll_wait(epfd, &ev, 1, 20) != 0)
+        return __LINE__;
+    close(epfd);
+    close(efd);
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("eventfd_wake: failed at %d\n", failed);
+        return 1;
+    }
+    puts("eventfd_wake: ok");
+    return 0;
+}
@@ -0,0 +1,75 @@
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define PAGE 4096
+
+/* Calls execve directly, so that libc does not look at the arrays first. */
+static long execve_(const char *path, const void *argv, const void *envp)
+{
+    return syscall(SYS_execve, path, argv, envp);
+}
+
+static int check(const char *self)
+{
+    extern char **environ;
+    char *const kernel = (char *)-256L;
+    char **page;
+
+    {
+        char *args[] = {(char *)self, (char *)1, NULL};
+        if (execve_(self, args, environ) != -1 || errno != EFAULT)
+            return 1;
+    }
+    {
+        char *args[] = {(char *)self, kernel, NULL};
+        if (execve_(self, args, environ) != -1 || errno != EFAULT)
+            return 2;
+    }
+    {
+        char *args[] = {(char *)self, NULL};
+        char *envs[] = {"A=1", (char *)1, NULL};
+        if (execve_(self, args, envs) != -1 || errno != EFAULT)
+            return 3;
+    }
+    /* The arrays themselves. */
+    if (execve_(self, (char **)1, environ) != -1 || errno != EFAULT)
+        return 4;
+    if (execve_(self, (char **)kernel, environ) != -1 || errno != EFAULT)
+        return 5;
+    {
+        char *args[] = {(char *)self, NULL};
+        if (execve_(self, args, (char **)kernel) != -1 || errno != EFAULT)
+            return 6;
+    }
+    /* One running into unmapped memory with no null. */
+    page = mmap(NULL, 2 * PAGE, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (page == MAP_FAILED || munmap((char *)page + PAGE, PAGE))
+        return 7;
+    for (size_t i = 0; i < PAGE / sizeof(char *); i++)
+        page[i] = (char *)self;
+    if (execve_(self, page, environ) != -1 || errno != EFAULT)
+        return 8;
+    munmap(page, PAGE);
+    return 0;
+}
+
+int main(int argc, char *argv[])
+{
+    int failed;
+
+    if (argc > 1) {
+        puts("exec_fault: exec went through");
+        return 1;
+    }
+    failed = check(argv[0]);
+    if (failed) {
+        printf("exec_fault: failed at %d\n", failed);
+        return 1;
+    }
+    puts("exec_fault: ok");
+    return 0;
+}
@@ -0,0 +1,88 @@
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <poll.h>
+#include <sched.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#define THREAD_FLAGS (CLONE_VM | CLONE_SIGHAND | CLONE_THREAD)
+
+static char sleeper_stack[65536] __attribute__((aligned(16)));
+
+static int started;
+
+/* Only makes system calls directly, as it shares the TLS of its parent. It
+ * keeps its own copy of the fd table, and so the write end of the pipe, until
+ * it exits. */
+static int sleeper(void *arg)
+{
+    struct timespec long_sleep = {3600, 0};
+
+    (void)arg;
+    __atomic_store_n(&started, 1, __ATOMIC_RELEASE);
+    syscall(SYS_nanosleep, &long_sleep, NULL);
+    syscall(SYS_exit, 0);
+    return 0;
+}
+
+/* Runs in the new program: the thread is gone once the write end of the pipe
+ * is closed everywhere. */
+static int run_child(int fd)
+{
+    struct pollfd pfd = {fd, POLLIN, 0};
+    char c;
+
+    if (poll(&pfd, 1, 2000) != 1 || read(fd, &c, 1) != 0)
+        return 43;
+    return 42;
+}
+
+static int check(char *argv[])
+{
+    char fd_arg[16];
+    char *const args[] = {argv[0], "child", fd_arg, NULL};
+    char *const envs[] = {NULL};
+    int fds[2];
+    int status;
+    pid_t pid;
+
+    if (pipe(fds) || fcntl(fds[1], F_SETFD, FD_CLOEXEC))
+        return __LINE__;
+    snprintf(fd_arg, sizeof(fd_arg), "%d", fds[0]);
+    pid = fork();
+    if (pid < 0)
+        return __LINE__;
+    if (pid == 0) {
+        if (clone(sleeper, sleeper_stack + sizeof(sleeper_stack), THREAD_FLAGS, NULL) < 0)
+            _exit(100);
+        while (!__atomic_load_n(&started, __ATOMIC_ACQUIRE))
+            syscall(SYS_sched_yield);
+        execve(argv[0], args, envs);
+        _exit(101);
+    }
+    close(fds[1]);
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 42)
+        return __LINE__;
+    close(fds[0]);
+    return 0;
+}
+
+int main(int argc, char *argv[])
+{
+    int failed;
+
+    if (argc > 2 && strcmp(argv[1], "child") == 0)
+        return run_child(atoi(argv[2]));
+    failed = check(argv);
+    if (failed) {
+        printf("exec_thread: failed at %d\n", failed);
+        return 1;
+    }
+    puts("exec_thread: ok");
+    return 0;
+}
@@ -0,0 +1,77 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define LINK_PATH "/tmp/execveat_link"
+
+static int execveat_(int dirfd, const char *path, char *const argv[], char *const envp[], int flags)
+{
+    return syscall(SYS_execveat, dirfd, path, argv, envp, flags);
+}
+
+static int check(char *argv[])
+{
+    char *const args[] = {argv[0], "child", NULL};
+    char *const envs[] = {NULL};
+    int status;
+    int fd;
+    pid_t pid;
+
+    fd = open(argv[0], O_RDONLY);
+    if (fd < 0)
+        return 1;
+    pid = fork();
+    if (pid == 0) {
+        /* The open file itself is run. */
+        execveat_(fd, "", args, envs, AT_EMPTY_PATH);
+        _exit(1);
+    }
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 42)
+        return 2;
+    /* Without AT_EMPTY_PATH, an empty path names nothing. */
+    if (execveat_(fd, "", args, envs, 0) != -1 || errno != ENOENT)
+        return 3;
+    if (execveat_(AT_FDCWD, argv[0], args, envs, 0x8000) != -1 || errno != EINVAL)
+        return 4;
+    close(fd);
+    if (execveat_(fd, "", args, envs, AT_EMPTY_PATH) != -1 || errno != EBADF)
+        return 5;
+    if (execveat_(fd, "relative", args, envs, 0) != -1 || errno != EBADF)
+        return 6;
+    /* A link is not followed with AT_SYMLINK_NOFOLLOW. */
+    unlink(LINK_PATH);
+    if (argv[0][0] == '/' && symlink(argv[0], LINK_PATH) == 0) {
+        int ret = 0;
+
+        if (execveat_(AT_FDCWD, LINK_PATH, args, envs, AT_SYMLINK_NOFOLLOW) != -1 ||
+            errno != ELOOP)
+            ret = 7;
+        unlink(LINK_PATH);
+        if (ret)
+            return ret;
+    }
+    /* A directory cannot be run. */
+    if (execveat_(AT_FDCWD, "/", args, envs, 0) != -1 || errno != EACCES)
+        return 8;
+    return 0;
+}
+
+int main(int argc, char *argv[])
+{
+    int ret;
+
+    if (argc > 1 && strcmp(argv[1], "child") == 0)
+        return 42;
+    ret = check(argv);
+    if (ret) {
+        printf("execveat: failed at %d\n", ret);
+        return 1;
+    }
+    puts("execveat: ok");
+    return 0;
+}
@@ -0,0 +1,53 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static int check(char *argv[])
+{
+    char *const args[] = {argv[0], "child", NULL};
+    char *const envs[] = {NULL};
+    struct stat st;
+    char c;
+    int status;
+    int fd;
+    pid_t pid;
+
+    fd = open(argv[0], O_PATH);
+    if (fd < 0)
+        return 1;
+    /* The descriptor gives no access to what is in the file. */
+    if (read(fd, &c, 1) != -1 || errno != EBADF)
+        return 2;
+    if (fstat(fd, &st) != 0 || !S_ISREG(st.st_mode))
+        return 3;
+    pid = fork();
+    if (pid == 0) {
+        syscall(SYS_execveat, fd, "", args, envs, AT_EMPTY_PATH);
+        _exit(1);
+    }
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 42)
+        return 4;
+    close(fd);
+    return 0;
+}
+
+int main(int argc, char *argv[])
+{
+    int ret;
+
+    if (argc > 1 && strcmp(argv[1], "child") == 0)
+        return 42;
+    ret = check(argv);
+    if (ret) {
+        printf("fexecve: failed at %d\n", ret);
+        return 1;
+    }
+    puts("fexecve: ok");
+    return 0;
+}
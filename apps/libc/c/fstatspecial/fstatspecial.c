@@ -0,0 +1,52 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/socket.h>
+#include <sys/stat.h>
+#include <sys/sysmacros.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    struct stat st;
+    int fds[2], fd, ret = 0;
+
+    if (pipe(fds) != 0)
+        return 1;
+    if (fstat(fds[0], &st) != 0 || !S_ISFIFO(st.st_mode) || st.st_size != 0)
+        ret = 2;
+    close(fds[0]);
+    close(fds[1]);
+    if (ret)
+        return ret;
+
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, fds) != 0)
+        return 3;
+    if (fstat(fds[0], &st) != 0 || !S_ISSOCK(st.st_mode))
+        ret = 4;
+    close(fds[0]);
+    close(fds[1]);
+    if (ret)
+        return ret;
+
+    fd = open("/dev/null", O_RDWR);
+    if (fd < 0)
+        return 5;
+    if (fstat(fd, &st) != 0 || !S_ISCHR(st.st_mode) || st.st_size != 0)
+        ret = 6;
+    else if (major(st.st_rdev) != 1 || minor(st.st_rdev) != 3)
+        ret = 7;
+    close(fd);
+    return ret;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("fstat_special: failed at %d\n", ret);
+        return 1;
+    }
+    puts("fstat_special: ok");
+    return 0;
+}
@@ -0,0 +1,78 @@
+#include <errno.h>
+#include <signal.h>
+#include <linux/futex.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+static long futex(uint32_t *uaddr, int op, const struct timespec *timeout)
+{
+    return syscall(SYS_futex, uaddr, op, 0, timeout, NULL, 0);
+}
+
+static int check(void)
+{
+    uint32_t tid = syscall(SYS_gettid);
+    uint32_t word = 0;
+
+    /* An unowned futex is taken with the owner's TID. */
+    if (futex(&word, FUTEX_LOCK_PI, NULL) != 0 || word != tid)
+        return 1;
+    if (futex(&word, FUTEX_LOCK_PI, NULL) != -1 || errno != EDEADLK)
+        return 2;
+    if (futex(&word, FUTEX_TRYLOCK_PI, NULL) != -1 || errno != EDEADLK)
+        return 3;
+    if (futex(&word, FUTEX_UNLOCK_PI, NULL) != 0 || word != 0)
+        return 4;
+    /* Only the owner can unlock it. */
+    if (futex(&word, FUTEX_UNLOCK_PI, NULL) != -1 || errno != EPERM)
+        return 5;
+
+    /* One left by an owner that died is taken over, still telling so. */
+    word = FUTEX_OWNER_DIED;
+    if (futex(&word, FUTEX_TRYLOCK_PI, NULL) != 0 || word != (tid | FUTEX_OWNER_DIED))
+        return 6;
+
+    /* One owned by another task is busy, and waiting for it times out. */
+    pid_t owner = fork();
+    if (owner < 0)
+        return 7;
+    if (owner == 0) {
+        pause();
+        _exit(0);
+    }
+    int failed = 0;
+    word = owner;
+    if (futex(&word, FUTEX_TRYLOCK_PI | FUTEX_PRIVATE_FLAG, NULL) != -1 || errno != EAGAIN)
+        failed = 8;
+    struct timespec deadline;
+    clock_gettime(CLOCK_REALTIME, &deadline);
+    deadline.tv_nsec += 20000000;
+    if (deadline.tv_nsec >= 1000000000) {
+        deadline.tv_sec++;
+        deadline.tv_nsec -= 1000000000;
+    }
+    if (!failed && (futex(&word, FUTEX_LOCK_PI, &deadline) != -1 || errno != ETIMEDOUT))
+        failed = 9;
+    if (!failed && (word & FUTEX_TID_MASK) != (uint32_t)owner)
+        failed = 10;
+    if (!failed && (futex(&word, FUTEX_UNLOCK_PI, NULL) != -1 || errno != EPERM))
+        failed = 11;
+    kill(owner, SIGKILL);
+    waitpid(owner, NULL, 0);
+    return failed;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("futex_pi: failed at %d\n", failed);
+        return 1;
+    }
+    puts("futex_pi: ok");
+    return 0;
+}
@@ -0,0 +1,68 @@
+#include <errno.h>
+#include <linux/futex.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <time.h>
+#include <unistd.h>
+
+static long futex(uint32_t *uaddr, int op, uint32_t val, const struct timespec *timeout,
+                  uint32_t val3)
+{
+    return syscall(SYS_futex, uaddr, op, val, timeout, NULL, val3);
+}
+
+static int check(void)
+{
+    static uint32_t word;
+    struct timespec ts = { 0, 1000000 }, abs;
+    int i;
+
+    /* A changed value fails at once, before queueing. */
+    errno = 0;
+    if (futex(&word, FUTEX_WAIT_PRIVATE, 1, NULL, 0) != -1 || errno != EAGAIN)
+        return 1;
+    errno = 0;
+    if (futex((uint32_t *)((char *)&word + 1), FUTEX_WAKE, 1, NULL, 0) != -1 || errno != EINVAL)
+        return 2;
+
+    /*
+     * Every waiter that gives up leaves the queue, so later wakeups find no
+     * one left behind, however many times it goes round.
+     */
+    for (i = 0; i < 100; i++) {
+        errno = 0;
+        if (futex(&word, FUTEX_WAIT_PRIVATE, 0, &ts, 0) != -1 || errno != ETIMEDOUT)
+            return 3;
+        if (futex(&word, FUTEX_WAKE_PRIVATE, 1, NULL, 0) != 0)
+            return 4;
+    }
+
+    /* The bitset variant takes an absolute time. */
+    if (clock_gettime(CLOCK_MONOTONIC, &abs) != 0)
+        return 5;
+    abs.tv_nsec += 1000000;
+    if (abs.tv_nsec >= 1000000000) {
+        abs.tv_sec++;
+        abs.tv_nsec -= 1000000000;
+    }
+    errno = 0;
+    if (futex(&word, FUTEX_WAIT_BITSET_PRIVATE, 0, &abs, FUTEX_BITSET_MATCH_ANY) != -1
+        || errno != ETIMEDOUT)
+        return 6;
+    errno = 0;
+    if (futex(&word, FUTEX_WAKE_BITSET_PRIVATE, 1, NULL, 0) != -1 || errno != EINVAL)
+        return 7;
+    return 0;
+}
+
+int main(void)
+{
+    int ret = check();
+    if (ret) {
+        printf("futex_queue: failed at %d\n", ret);
+        return 1;
+    }
+    puts("futex_queue: ok");
+    return 0;
+}
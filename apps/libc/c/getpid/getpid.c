@@ -0,0 +1,74 @@
+#define _GNU_SOURCE
+#include <sched.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define ITERATIONS 1000000
+
+static char thread_stack[65536] __attribute__((aligned(16)));
+static volatile long thread_pid, thread_tid, thread_child;
+static volatile int thread_done;
+
+/* Only makes system calls directly, as it shares the TLS of its parent. */
+static int thread_main(void *arg)
+{
+    thread_pid = syscall(SYS_getpid);
+    thread_tid = syscall(SYS_gettid);
+    /* What it forks is a child of the process, not of the thread. */
+    thread_child = syscall(SYS_clone, SIGCHLD, 0, 0, 0, 0);
+    if (thread_child == 0)
+        syscall(SYS_exit_group, syscall(SYS_getppid) == thread_pid ? 0 : 1);
+    thread_done = 1;
+    syscall(SYS_exit, 0);
+    return 0;
+}
+
+int main()
+{
+    long pid = syscall(SYS_getpid);
+    int i, ok = 1, status, fds[2];
+    long child_pid = 0;
+    pid_t child;
+
+    /* The main thread leads its group, so its ID is the process ID. */
+    ok &= syscall(SYS_gettid) == pid;
+    /* Called over and over, it keeps giving the same answer. How fast it is
+     * varies too much by machine to be checked here. */
+    for (i = 0; i < ITERATIONS; i++)
+        ok &= syscall(SYS_getpid) == pid;
+    printf("getpid: loop %s\n", ok ? "ok" : "failed");
+    fflush(stdout);
+
+    /* A forked child has the ID fork returned, not one left from its parent. */
+    if (pipe(fds) != 0)
+        return 1;
+    child = fork();
+    if (child == 0) {
+        long own = syscall(SYS_getpid);
+
+        ok = own != pid && syscall(SYS_getppid) == pid && syscall(SYS_gettid) == own;
+        write(fds[1], &own, sizeof(own));
+        _exit(ok ? 0 : 1);
+    }
+    close(fds[1]);
+    if (read(fds[0], &child_pid, sizeof(child_pid)) != sizeof(child_pid))
+        child_pid = 0;
+    close(fds[0]);
+    waitpid(child, &status, 0);
+    printf("getpid: fork %s\n",
+           WIFEXITED(status) && WEXITSTATUS(status) == 0 && child_pid == child ? "ok" : "failed");
+
+    /* Another thread has the process ID of the group, and an ID of its own. */
+    ok = clone(thread_main, thread_stack + sizeof(thread_stack),
+               CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND | CLONE_THREAD, NULL) > 0;
+    while (ok && !thread_done)
+        sched_yield();
+    ok &= thread_pid == pid && thread_tid != pid && syscall(SYS_getpid) == pid;
+    ok &= thread_child > 0 && waitpid(thread_child, &status, 0) == thread_child &&
+          WIFEXITED(status) && WEXITSTATUS(status) == 0;
+    printf("getpid: thread %s\n", ok ? "ok" : "failed");
+    return 0;
+}
@@ -0,0 +1,90 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <grp.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define FILE_NAME "groupaccess.txt"
+
+/* Whether faccessat() allows `mode` exactly when the permission bits `bits` do. */
+static int agrees(int mode, mode_t bits)
+{
+    int ret = faccessat(AT_FDCWD, FILE_NAME, mode, 0);
+
+    if (bits)
+        return ret == 0;
+    return ret == -1 && errno == EACCES;
+}
+
+/* Runs as a user who does not own the file but is in its group. */
+static int child(const struct stat *st)
+{
+    gid_t group = st->st_gid;
+    gid_t groups[2];
+
+    if (setgroups(1, &group) != 0)
+        return 1;
+    if (setgid(group + 1) != 0 || setuid(st->st_uid + 1) != 0)
+        return 2;
+    if (getuid() != st->st_uid + 1 || geteuid() != st->st_uid + 1 || getegid() != group + 1)
+        return 3;
+    if (getgroups(0, NULL) != 1 || getgroups(2, groups) != 1 || groups[0] != group)
+        return 4;
+
+    /* The group bits apply, not the ones for others. */
+    if (!agrees(R_OK, st->st_mode & S_IRGRP))
+        return 5;
+    if (!agrees(W_OK, st->st_mode & S_IWGRP))
+        return 6;
+    if (!agrees(X_OK, st->st_mode & S_IXGRP))
+        return 7;
+
+    /* Only root can change the groups. */
+    if (setgroups(0, NULL) != -1 || errno != EPERM)
+        return 8;
+    return 0;
+}
+
+static int check(void)
+{
+    struct stat st;
+    int fd, status;
+    pid_t pid;
+
+    fd = open(FILE_NAME, O_RDWR | O_CREAT | O_TRUNC, 0640);
+    if (fd < 0)
+        return 1;
+    close(fd);
+    if (stat(FILE_NAME, &st) != 0)
+        return 2;
+    if (faccessat(AT_FDCWD, FILE_NAME, R_OK | W_OK, 0) != 0)
+        return 3;
+
+    pid = fork();
+    if (pid < 0)
+        return 4;
+    if (pid == 0)
+        _exit(child(&st));
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status))
+        return 5;
+    if (WEXITSTATUS(status))
+        return 10 + WEXITSTATUS(status);
+
+    unlink(FILE_NAME);
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("group_access: failed at %d\n", ret);
+        return 1;
+    }
+    puts("group_access: ok");
+    return 0;
+}
@@ -0,0 +1,102 @@
+#define _GNU_SOURCE
+#include <poll.h>
+#include <sched.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+#define THREAD_FLAGS (CLONE_VM | CLONE_SIGHAND | CLONE_THREAD)
+
+static char sleeper_stack[65536] __attribute__((aligned(16)));
+static char spinner_stack[65536] __attribute__((aligned(16)));
+
+static int started;
+static volatile unsigned long spins;
+
+/* The threads only make system calls directly, as they share the TLS of the
+ * main thread. */
+static int sleeper(void *arg)
+{
+    struct timespec long_sleep = {3600, 0};
+
+    (void)arg;
+    __atomic_add_fetch(&started, 1, __ATOMIC_RELEASE);
+    syscall(SYS_nanosleep, &long_sleep, NULL);
+    syscall(SYS_exit, 0);
+    return 0;
+}
+
+static int spinner(void *arg)
+{
+    (void)arg;
+    __atomic_add_fetch(&started, 1, __ATOMIC_RELEASE);
+    for (;;)
+        spins++;
+    return 0;
+}
+
+/* Runs in the child: starts a blocked and a busy thread, then exits them all
+ * with exit_group. The end of the pipe each thread holds is only closed once
+ * it has exited. */
+static void run_group(void)
+{
+    if (clone(sleeper, sleeper_stack + sizeof(sleeper_stack), THREAD_FLAGS, NULL) < 0)
+        _exit(100);
+    if (clone(spinner, spinner_stack + sizeof(spinner_stack), THREAD_FLAGS, NULL) < 0)
+        _exit(101);
+    while (__atomic_load_n(&started, __ATOMIC_ACQUIRE) != 2 || spins == 0)
+        syscall(SYS_sched_yield);
+    syscall(SYS_exit_group, 7);
+    _exit(102);
+}
+
+static int check(void)
+{
+    struct pollfd pfd;
+    int fds[2];
+    int status;
+    char c;
+    pid_t pid;
+
+    if (pipe(fds))
+        return 1;
+    pid = fork();
+    if (pid < 0)
+        return 2;
+    if (pid == 0) {
+        close(fds[0]);
+        run_group();
+    }
+    close(fds[1]);
+    if (waitpid(pid, &status, 0) != pid)
+        return 3;
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 7)
+        return 4;
+    /* No thread is left running with the pipe open. */
+    pfd.fd = fds[0];
+    pfd.events = POLLIN;
+    if (poll(&pfd, 1, 2000) != 1)
+        return 5;
+    if (read(fds[0], &c, 1) != 0)
+        return 6;
+    close(fds[0]);
+    /* Nor is any left to be waited for. */
+    if (waitpid(-1, &status, WNOHANG) != -1)
+        return 7;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+
+    if (failed) {
+        printf("group_exit: failed at %d\n", failed);
+        return 1;
+    }
+    puts("group_exit: ok");
+    return 0;
+}
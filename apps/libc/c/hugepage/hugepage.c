@@ -0,0 +1,104 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+#define PAGE 4096
+#define HUGE (2 << 20)
+
+/* AnonHugePages of /proc/meminfo in kB, or -1 if it is not there. */
+static long anon_huge_kb(void)
+{
+    char line[128];
+    long kb = -1;
+    FILE *f = fopen("/proc/meminfo", "r");
+
+    if (!f)
+        return -1;
+    while (fgets(line, sizeof(line), f))
+        if (sscanf(line, "AnonHugePages: %ld kB", &kb) == 1)
+            break;
+    fclose(f);
+    return kb;
+}
+
+/* Whether huge pages are turned off, which is only up to the system on
+ * Linux. */
+static int thp_disabled(void)
+{
+    char mode[128] = "";
+    FILE *f = fopen("/sys/kernel/mm/transparent_hugepage/enabled", "r");
+
+    if (!f)
+        return 0;
+    if (!fgets(mode, sizeof(mode), f))
+        mode[0] = 0;
+    fclose(f);
+    return strstr(mode, "[never]") != NULL;
+}
+
+static int check(void)
+{
+    char *map, *block;
+    long before, after;
+    size_t i;
+
+    map = mmap(NULL, 2 * HUGE, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (map == MAP_FAILED)
+        return 1;
+    block = (char *)(((uintptr_t)map + HUGE - 1) & ~(uintptr_t)(HUGE - 1));
+
+    errno = 0;
+    if (madvise(block + 1, PAGE, MADV_HUGEPAGE) != -1 || errno != EINVAL)
+        return 2;
+    errno = 0;
+    if (madvise(block, PAGE, 999) != -1 || errno != EINVAL)
+        return 3;
+    if (madvise(block, HUGE, MADV_HUGEPAGE))
+        return 4;
+
+    /* The first touch brings in the whole of the block at once. */
+    before = anon_huge_kb();
+    block[0] = 1;
+    after = anon_huge_kb();
+    if (before >= 0 && !thp_disabled() && after - before < HUGE / 1024)
+        return 5;
+    for (i = 0; i < HUGE; i++)
+        if (block[i] != (i == 0))
+            return 6;
+    memset(block, 0xab, HUGE);
+
+    /* Changing part of it keeps the rest as it was. */
+    if (mprotect(block, PAGE, PROT_READ))
+        return 7;
+    block[PAGE] = 2;
+    if (munmap(block + HUGE / 2, HUGE / 2))
+        return 8;
+    if ((unsigned char)block[0] != 0xab || block[PAGE] != 2 ||
+        (unsigned char)block[HUGE / 2 - 1] != 0xab)
+        return 9;
+
+    /* The range must be mapped. */
+    errno = 0;
+    if (madvise(block + HUGE / 2, PAGE, MADV_NOHUGEPAGE) != -1 || errno != ENOMEM)
+        return 10;
+    if (madvise(block, PAGE, MADV_NOHUGEPAGE))
+        return 11;
+    munmap(map, 2 * HUGE);
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+
+    if (failed) {
+        printf("huge_page: failed at %d\n", failed);
+        return 1;
+    }
+    puts("huge_page: ok");
+    return 0;
+}
@@ -0,0 +1,57 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/ioctl.h>
+#include <termios.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    struct termios tio;
+    int fds[2], fd, ret = 0;
+
+    /* Standard output is the console. */
+    if (ioctl(STDOUT_FILENO, TCGETS, &tio) != 0 || !isatty(STDOUT_FILENO))
+        return 1;
+
+    if (pipe(fds) != 0)
+        return 2;
+    errno = 0;
+    if (ioctl(fds[0], TCGETS, &tio) != -1 || errno != ENOTTY) {
+        ret = 3;
+        goto out_pipe;
+    }
+    if (isatty(fds[1])) {
+        ret = 4;
+        goto out_pipe;
+    }
+
+    fd = open("isatty_file", O_RDWR | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0) {
+        ret = 5;
+        goto out_pipe;
+    }
+    errno = 0;
+    if (ioctl(fd, TCGETS, &tio) != -1 || errno != ENOTTY)
+        ret = 6;
+    else if (isatty(fd))
+        ret = 7;
+    close(fd);
+    unlinkat(AT_FDCWD, "isatty_file", 0);
+
+out_pipe:
+    close(fds[0]);
+    close(fds[1]);
+    return ret;
+}
+
+int main(void)
+{
+    int ret = check();
+    if (ret) {
+        printf("isatty: failed at %d\n", ret);
+        return 1;
+    }
+    puts("isatty: ok");
+    return 0;
+}
@@ -0,0 +1,98 @@
+#define _GNU_SOURCE
+#include <sched.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <time.h>
+#include <unistd.h>
+
+static char thread_stack[65536] __attribute__((aligned(16)));
+
+static volatile int received;
+static volatile pid_t sender;
+static volatile int stage;
+
+static void handler(int sig, siginfo_t *info, void *ctx)
+{
+    received++;
+    sender = info->si_pid;
+}
+
+/* Only makes system calls directly, as it shares the TLS of its parent. */
+static int thread_main(void *arg)
+{
+    while (stage == 0)
+        syscall(SYS_sched_yield);
+    syscall(SYS_kill, (long)arg, SIGUSR1);
+    stage = 2;
+    while (stage != 3)
+        syscall(SYS_sched_yield);
+    stage = 4;
+    syscall(SYS_exit, 0);
+    return 0;
+}
+
+/* Waits a little for stray copies of a signal to arrive. */
+static void settle(void)
+{
+    struct timespec ts = { 0, 20 * 1000 * 1000 };
+    for (int i = 0; i < 5; i++)
+        nanosleep(&ts, NULL);
+}
+
+static int check(void)
+{
+    struct sigaction sa = { .sa_sigaction = handler, .sa_flags = SA_SIGINFO };
+    if (sigaction(SIGUSR1, &sa, NULL) != 0)
+        return __LINE__;
+    pid_t pid = getpid();
+    /* In a group of its own, so as not to signal whoever runs it. */
+    if (setpgid(0, 0) != 0)
+        return __LINE__;
+    int flags = CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND | CLONE_THREAD;
+    if (clone(thread_main, thread_stack + sizeof(thread_stack), flags, (void *)(long)pid) < 0)
+        return __LINE__;
+
+    /* A process with two threads takes one copy of a signal for the
+     * process, its group or everyone. */
+    if (kill(pid, SIGUSR1) != 0)
+        return __LINE__;
+    settle();
+    if (received != 1)
+        return __LINE__;
+    if (kill(0, SIGUSR1) != 0)
+        return __LINE__;
+    settle();
+    if (received != 2)
+        return __LINE__;
+    if (kill(-getpgrp(), SIGUSR1) != 0)
+        return __LINE__;
+    settle();
+    if (received != 3)
+        return __LINE__;
+
+    /* Sent from the other thread, it names the process as the sender. */
+    stage = 1;
+    while (stage != 2)
+        sched_yield();
+    settle();
+    if (received != 4 || sender != pid)
+        return __LINE__;
+
+    stage = 3;
+    while (stage != 4)
+        sched_yield();
+    settle();
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("kill_process: failed at %d\n", failed);
+        return 1;
+    }
+    puts("kill_process: ok");
+    return 0;
+}
@@ -0,0 +1,57 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/klog.h>
+#include <unistd.h>
+
+#define MARKER "kmsg test marker 5c1e"
+
+static int check(void)
+{
+    int fd = open("/dev/kmsg", O_WRONLY);
+    if (fd < 0)
+        return 1;
+    const char *msg = "<6>" MARKER "\n";
+    if (write(fd, msg, strlen(msg)) != (ssize_t)strlen(msg))
+        return 2;
+    close(fd);
+
+    int size = klogctl(10, NULL, 0);
+    if (size <= 0)
+        return 3;
+    char *buf = malloc(size + 1);
+    if (!buf)
+        return 4;
+    int len = klogctl(3, buf, size);
+    if (len < 0)
+        return 5;
+    buf[len] = '\0';
+
+    /* The message is kept as a line with its level, the user facility, and a
+     * timestamp. */
+    char *found = strstr(buf, MARKER);
+    if (!found)
+        return 6;
+    char *line = found;
+    while (line > buf && line[-1] != '\n')
+        line--;
+    if (strncmp(line, "<14>[", 5) != 0)
+        return 7;
+    char *end = strchr(line, ']');
+    if (end + 2 != found || found[strlen(MARKER)] != '\n')
+        return 8;
+    free(buf);
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("kmsg: failed at %d\n", failed);
+        return 1;
+    }
+    puts("kmsg: ok");
+    return 0;
+}
@@ -0,0 +1,90 @@
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <time.h>
+
+#define GIB (1L << 30)
+#define MIB (1L << 20)
+#define TOUCHED 16
+
+/* The field `name` of /proc/self/status, in kB, or -1. */
+static long status_kb(const char *name)
+{
+    char line[256];
+    long value = -1;
+    size_t len = strlen(name);
+    FILE *f = fopen("/proc/self/status", "r");
+
+    if (!f)
+        return -1;
+    while (fgets(line, sizeof(line), f))
+        if (strncmp(line, name, len) == 0 && line[len] == ':') {
+            if (sscanf(line + len + 1, "%ld kB", &value) != 1)
+                value = -1;
+            break;
+        }
+    fclose(f);
+    return value;
+}
+
+static double seconds(void)
+{
+    struct timespec ts;
+
+    clock_gettime(CLOCK_MONOTONIC, &ts);
+    return ts.tv_sec + ts.tv_nsec / 1e9;
+}
+
+static int check(void)
+{
+    long size_before = status_kb("VmSize"), rss_before = status_kb("VmRSS");
+    long rss;
+    double start;
+    char *region;
+
+    if (size_before < 0 || rss_before < 0)
+        return 1;
+    start = seconds();
+    /* Without MAP_NORESERVE, it would be more than all the memory there is. */
+    region = mmap(NULL, GIB, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS | MAP_NORESERVE,
+                  -1, 0);
+    if (region == MAP_FAILED)
+        return 2;
+    if (seconds() - start > 0.5)
+        return 3;
+    if (status_kb("VmSize") - size_before < GIB / 1024)
+        return 4;
+    /* Nothing is allocated before it is touched. */
+    if (status_kb("VmRSS") - rss_before > 4 * MIB / 1024)
+        return 5;
+
+    for (int i = 0; i < TOUCHED; i++)
+        region[i * 64 * MIB] = 1;
+    rss = status_kb("VmRSS") - rss_before;
+    if (rss < TOUCHED * 4 || rss > 4 * MIB / 1024)
+        return 6;
+    /* Pages come zeroed, and changing the protection allocates none. */
+    if (region[GIB - 1] != 0 || region[0] != 1)
+        return 7;
+    if (mprotect(region, GIB, PROT_READ))
+        return 8;
+    if (status_kb("VmRSS") - rss_before > 4 * MIB / 1024)
+        return 9;
+    if (region[GIB / 2 + 123] != 0 || region[64 * MIB] != 1)
+        return 10;
+    if (munmap(region, GIB))
+        return 11;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+
+    if (failed) {
+        printf("lazy_mmap: failed at %d\n", failed);
+        return 1;
+    }
+    puts("lazy_mmap: ok");
+    return 0;
+}
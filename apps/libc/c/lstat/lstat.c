@@ -0,0 +1,82 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    static const char target[] = "lstat_target";
+    static const char data[] = "hello, link";
+    struct stat st;
+    char buf[64];
+    int fd, ret = 0;
+
+    fd = open(target, O_WRONLY | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0)
+        return 1;
+    if (write(fd, data, sizeof(data) - 1) != sizeof(data) - 1)
+        ret = 2;
+    close(fd);
+    if (ret)
+        goto out_target;
+    if (symlinkat(target, AT_FDCWD, "lstat_link") != 0) {
+        ret = 3;
+        goto out_target;
+    }
+
+    if (fstatat(AT_FDCWD, "lstat_link", &st, AT_SYMLINK_NOFOLLOW) != 0) {
+        ret = 4;
+        goto out;
+    }
+    if (!S_ISLNK(st.st_mode) || st.st_size != (off_t)strlen(target)) {
+        ret = 5;
+        goto out;
+    }
+    if (fstatat(AT_FDCWD, "lstat_link", &st, 0) != 0) {
+        ret = 6;
+        goto out;
+    }
+    if (!S_ISREG(st.st_mode) || st.st_size != sizeof(data) - 1) {
+        ret = 7;
+        goto out;
+    }
+    if (readlinkat(AT_FDCWD, "lstat_link", buf, sizeof(buf)) != (ssize_t)strlen(target)
+        || memcmp(buf, target, strlen(target)) != 0) {
+        ret = 8;
+        goto out;
+    }
+    if (readlinkat(AT_FDCWD, target, buf, sizeof(buf)) != -1) {
+        ret = 9;
+        goto out;
+    }
+
+    fd = open("lstat_link", O_RDONLY);
+    if (fd < 0) {
+        ret = 10;
+        goto out;
+    }
+    if (read(fd, buf, sizeof(buf)) != sizeof(data) - 1 || memcmp(buf, data, sizeof(data) - 1) != 0)
+        ret = 11;
+    close(fd);
+
+out:
+    unlinkat(AT_FDCWD, "lstat_link", 0);
+    if (!ret && fstatat(AT_FDCWD, target, &st, 0) != 0)
+        ret = 12;
+out_target:
+    unlinkat(AT_FDCWD, target, 0);
+    return ret;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("lstat: failed at %d\n", ret);
+        return 1;
+    }
+    puts("lstat: ok");
+    return 0;
+}
@@ -0,0 +1,74 @@
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+#define PAGES 4
+
+/* Whether the `len` bytes at `p` are all `c`. */
+static int all(const unsigned char *p, size_t len, unsigned char c)
+{
+    for (size_t i = 0; i < len; i++)
+        if (p[i] != c)
+            return 0;
+    return 1;
+}
+
+static int check(void)
+{
+    size_t page = sysconf(_SC_PAGESIZE), len = PAGES * page;
+    unsigned char *p, *shared;
+
+    p = mmap(NULL, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (p == MAP_FAILED)
+        return __LINE__;
+    memset(p, 0xaa, len);
+
+    /* Private anonymous pages read back as zeros, and the rest is kept. */
+    if (madvise(p + page, 2 * page, MADV_DONTNEED) != 0)
+        return __LINE__;
+    if (!all(p, page, 0xaa) || !all(p + page, 2 * page, 0)
+        || !all(p + 3 * page, page, 0xaa))
+        return __LINE__;
+    /* They can be written again. */
+    p[page] = 1;
+    if (p[page] != 1)
+        return __LINE__;
+
+    if (madvise(p, len, MADV_FREE) != 0)
+        return __LINE__;
+    memset(p, 0x55, len);
+    if (!all(p, len, 0x55))
+        return __LINE__;
+
+    /* Shared pages keep what was written to them. */
+    shared = mmap(NULL, len, PROT_READ | PROT_WRITE, MAP_SHARED | MAP_ANONYMOUS, -1, 0);
+    if (shared == MAP_FAILED)
+        return __LINE__;
+    memset(shared, 0x11, len);
+    if (madvise(shared, len, MADV_DONTNEED) != 0 || !all(shared, len, 0x11))
+        return __LINE__;
+
+    errno = 0;
+    if (madvise(p, len, 12345) != -1 || errno != EINVAL)
+        return __LINE__;
+    munmap(p, len);
+    errno = 0;
+    if (madvise(p, len, MADV_DONTNEED) != -1 || errno != ENOMEM)
+        return __LINE__;
+    munmap(shared, len);
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+
+    if (failed) {
+        printf("madvise: failed at %d\n", failed);
+        return 1;
+    }
+    puts("madvise: ok");
+    return 0;
+}
@@ -0,0 +1,53 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+#define PAGES 8
+
+static int check(void)
+{
+    long page = sysconf(_SC_PAGESIZE);
+    unsigned char vec[PAGES];
+    char *buf = mmap(NULL, PAGES * page, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS,
+                     -1, 0);
+    if (buf == MAP_FAILED)
+        return __LINE__;
+    for (int i = 0; i < PAGES; i += 2)
+        buf[i * page] = 1;
+
+    if (mincore(buf, PAGES * page, vec) != 0)
+        return __LINE__;
+    for (int i = 0; i < PAGES; i++)
+        if ((vec[i] & 1) != (i % 2 == 0))
+            return __LINE__;
+    // A length that is not a whole number of pages counts the last in part.
+    vec[1] = 0xff;
+    if (mincore(buf, page + 1, vec) != 0 || (vec[0] & 1) != 1 || (vec[1] & 1) != 0)
+        return __LINE__;
+    if (mincore(buf, 0, vec) != 0)
+        return __LINE__;
+
+    if (mincore(buf + 1, page, vec) != -1 || errno != EINVAL)
+        return __LINE__;
+    if (munmap(buf + 3 * page, page) != 0)
+        return __LINE__;
+    if (mincore(buf, PAGES * page, vec) != -1 || errno != ENOMEM)
+        return __LINE__;
+    if (mincore(buf, page, (unsigned char *)8) != -1 || errno != EFAULT)
+        return __LINE__;
+    munmap(buf, PAGES * page);
+    return 0;
+}
+
+int main(void)
+{
+    int line = check();
+    if (line) {
+        printf("mincore: failed at %d\n", line);
+        return 1;
+    }
+    puts("mincore: ok");
+    return 0;
+}
@@ -0,0 +1,38 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#define DIR_PATH "mkdir.dir"
+
+static int check(void)
+{
+    struct stat st;
+    int ret = 0;
+
+    umask(022);
+    if (mkdir(DIR_PATH, 0777) != 0)
+        return 1;
+    if (stat(DIR_PATH, &st) != 0 || !S_ISDIR(st.st_mode))
+        ret = 2;
+    else if ((st.st_mode & 07777) != 0755)
+        ret = 3;
+    else if (mkdir(DIR_PATH, 0777) == 0 || errno != EEXIST)
+        ret = 4;
+    else if (mkdir("mkdir.missing/dir", 0777) == 0 || errno != ENOENT)
+        ret = 5;
+    rmdir(DIR_PATH);
+    return ret;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("mkdir: failed at %d\n", failed);
+        return 1;
+    }
+    puts("mkdir: ok");
+    return 0;
+}
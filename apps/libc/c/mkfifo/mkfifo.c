@@ -0,0 +1,63 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    struct stat st;
+    char buf[8];
+    int fd, status, ret = 0;
+    pid_t pid;
+
+    if (mknod("mkfifo_test", S_IFIFO | 0644, 0) != 0)
+        return 1;
+    if (fstatat(AT_FDCWD, "mkfifo_test", &st, 0) != 0 || !S_ISFIFO(st.st_mode)) {
+        ret = 2;
+        goto out;
+    }
+
+    pid = fork();
+    if (pid < 0) {
+        ret = 3;
+        goto out;
+    }
+    if (pid == 0) {
+        fd = open("mkfifo_test", O_WRONLY);
+        if (fd < 0 || write(fd, "hi", 2) != 2)
+            _exit(1);
+        close(fd);
+        _exit(0);
+    }
+
+    fd = open("mkfifo_test", O_RDONLY);
+    if (fd < 0) {
+        ret = 4;
+        goto out;
+    }
+    if (read(fd, buf, sizeof(buf)) != 2 || memcmp(buf, "hi", 2) != 0)
+        ret = 5;
+    else if (read(fd, buf, sizeof(buf)) != 0)
+        ret = 6;
+    close(fd);
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+        ret = ret ? ret : 7;
+
+out:
+    unlinkat(AT_FDCWD, "mkfifo_test", 0);
+    return ret;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("mkfifo: failed at %d\n", ret);
+        return 1;
+    }
+    puts("mkfifo: ok");
+    return 0;
+}
@@ -0,0 +1,59 @@
+#include <errno.h>
+#include <stdio.h>
+#include <sys/resource.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    int status;
+    pid_t pid;
+
+    if (setpriority(PRIO_PROCESS, 0, 100) != 0)
+        return 1;
+    errno = 0;
+    if (getpriority(PRIO_PROCESS, 0) != 19 || errno != 0)
+        return 2;
+    if (setpriority(PRIO_PROCESS, getpid(), -100) != 0)
+        return 3;
+    if (getpriority(PRIO_PGRP, 0) != -20)
+        return 4;
+
+    pid = fork();
+    if (pid < 0)
+        return 5;
+    if (pid == 0) {
+        /* Unprivileged, the inherited nice value can be raised but not lowered. */
+        if (setuid(1000) != 0)
+            _exit(1);
+        if (getpriority(PRIO_PROCESS, 0) != -20)
+            _exit(2);
+        if (setpriority(PRIO_PROCESS, 0, 5) != 0)
+            _exit(3);
+        errno = 0;
+        if (setpriority(PRIO_PROCESS, 0, 0) != -1 || errno != EACCES)
+            _exit(4);
+        _exit(0);
+    }
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+        return 6;
+
+    /* The child has been reaped, so there is no such process any more. */
+    errno = 0;
+    if (getpriority(PRIO_PROCESS, pid) != -1 || errno != ESRCH)
+        return 7;
+    if (setpriority(PRIO_PROCESS, 0, 0) != 0)
+        return 8;
+    return 0;
+}
+
+int main(void)
+{
+    int ret = check();
+    if (ret) {
+        printf("nice: failed at %d\n", ret);
+        return 1;
+    }
+    puts("nice: ok");
+    return 0;
+}
@@ -0,0 +1,70 @@
+#include <errno.h>
+#include <stdio.h>
+#include <sys/resource.h>
+#include <unistd.h>
+
+#define LIMIT 8
+
+static int check(void)
+{
+    struct rlimit old, lim;
+    if (getrlimit(RLIMIT_NOFILE, &old) != 0 || old.rlim_cur > old.rlim_max)
+        return __LINE__;
+
+    // A soft limit above the hard one is rejected, and so is a hard limit
+    // beyond what the kernel allows.
+    lim.rlim_cur = old.rlim_max + 1;
+    lim.rlim_max = old.rlim_max;
+    if (setrlimit(RLIMIT_NOFILE, &lim) == 0 && getrlimit(RLIMIT_NOFILE, &lim) == 0 &&
+        lim.rlim_cur > lim.rlim_max)
+        return __LINE__;
+    lim.rlim_cur = LIMIT;
+    lim.rlim_max = RLIM_INFINITY;
+    if (setrlimit(RLIMIT_NOFILE, &lim) == 0 || errno != EPERM)
+        return __LINE__;
+
+    lim.rlim_cur = LIMIT;
+    lim.rlim_max = old.rlim_max;
+    if (setrlimit(RLIMIT_NOFILE, &lim) != 0)
+        return __LINE__;
+    if (getrlimit(RLIMIT_NOFILE, &lim) != 0 || lim.rlim_cur != LIMIT ||
+        lim.rlim_max != old.rlim_max)
+        return __LINE__;
+
+    // Fill every fd below the limit, then the next dup fails.
+    int fd;
+    while ((fd = dup(0)) >= 0)
+        if (fd >= LIMIT)
+            return __LINE__;
+    if (errno != EMFILE)
+        return __LINE__;
+    if (dup(0) != -1 || errno != EMFILE)
+        return __LINE__;
+    for (fd = 3; fd < LIMIT; fd++)
+        if (close(fd) != 0)
+            return __LINE__;
+
+    // With one fd free again, dup gets it.
+    for (fd = 3; fd < LIMIT - 1; fd++)
+        if (dup(0) != fd)
+            return __LINE__;
+    if (dup(0) != LIMIT - 1 || dup(0) != -1 || errno != EMFILE)
+        return __LINE__;
+    for (fd = 3; fd < LIMIT; fd++)
+        close(fd);
+
+    if (setrlimit(RLIMIT_NOFILE, &old) != 0)
+        return __LINE__;
+    return 0;
+}
+
+int main(void)
+{
+    int line = check();
+    if (line) {
+        printf("nofile: failed at %d\n", line);
+        return 1;
+    }
+    puts("nofile: ok");
+    return 0;
+}
@@ -0,0 +1,79 @@
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define FILE_PATH "/tmp/nofollow_file"
+#define LINK_PATH "/tmp/nofollow_link"
+#define HARD_PATH "/tmp/nofollow_hard"
+
+/* The mode from statx, whose struct not every libc has. */
+static int statx_mode(const char *path, int flags)
+{
+    unsigned char buf[256];
+    unsigned short mode;
+
+    if (syscall(SYS_statx, AT_FDCWD, path, flags, 0x7ff, buf) != 0)
+        return -1;
+    memcpy(&mode, buf + 28, sizeof(mode));
+    return mode;
+}
+
+static void cleanup(void)
+{
+    unlink(HARD_PATH);
+    unlink(LINK_PATH);
+    unlink(FILE_PATH);
+}
+
+static int check(void)
+{
+    struct stat st;
+    int fd;
+
+    cleanup();
+    fd = open(FILE_PATH, O_WRONLY | O_CREAT, 0644);
+    if (fd < 0)
+        return 1;
+    close(fd);
+    if (symlink(FILE_PATH, LINK_PATH) != 0)
+        return 2;
+
+    /* The link itself only with AT_SYMLINK_NOFOLLOW, else its target. */
+    if (fstatat(AT_FDCWD, LINK_PATH, &st, AT_SYMLINK_NOFOLLOW) != 0 || !S_ISLNK(st.st_mode))
+        return 3;
+    if (fstatat(AT_FDCWD, LINK_PATH, &st, 0) != 0 || !S_ISREG(st.st_mode))
+        return 4;
+    if (!S_ISLNK(statx_mode(LINK_PATH, AT_SYMLINK_NOFOLLOW)))
+        return 5;
+    if (!S_ISREG(statx_mode(LINK_PATH, 0)))
+        return 6;
+
+    /* linkat only follows with AT_SYMLINK_FOLLOW. */
+    if (linkat(AT_FDCWD, LINK_PATH, AT_FDCWD, HARD_PATH, 0) != 0)
+        return 7;
+    if (lstat(HARD_PATH, &st) != 0 || !S_ISLNK(st.st_mode))
+        return 8;
+    unlink(HARD_PATH);
+    if (linkat(AT_FDCWD, LINK_PATH, AT_FDCWD, HARD_PATH, AT_SYMLINK_FOLLOW) != 0)
+        return 9;
+    if (lstat(HARD_PATH, &st) != 0 || !S_ISREG(st.st_mode))
+        return 10;
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    cleanup();
+    if (ret) {
+        printf("nofollow: failed at %d\n", ret);
+        return 1;
+    }
+    puts("nofollow: ok");
+    return 0;
+}
@@ -0,0 +1,80 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/resource.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define CHUNK (1 << 20)
+/* Far more than the memory of the machine the tests run on. */
+#define MAX_CHUNKS 4096
+
+/* Maps and touches memory until the kernel runs out, which ends with mmap
+ * failing or with a signal on touching a page. */
+static void hog(void)
+{
+    struct rlimit no_core = {0, 0};
+    int i;
+
+    setrlimit(RLIMIT_CORE, &no_core);
+    for (i = 0; i < MAX_CHUNKS; i++) {
+        char *p = mmap(NULL, CHUNK, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+        if (p == MAP_FAILED)
+            _exit(errno == ENOMEM ? 0 : 1);
+        memset(p, 0x5a, CHUNK);
+    }
+    _exit(2);
+}
+
+static int check(void)
+{
+    int status;
+    char *p;
+    pid_t pid;
+
+    pid = fork();
+    if (pid < 0)
+        return 1;
+    if (pid == 0)
+        hog();
+    if (waitpid(pid, &status, 0) != pid)
+        return 2;
+    if (WIFEXITED(status) && WEXITSTATUS(status) != 0)
+        return 3;
+    /* SIGKILL is what the OOM killer of Linux sends. */
+    if (WIFSIGNALED(status) && WTERMSIG(status) != SIGSEGV && WTERMSIG(status) != SIGBUS &&
+        WTERMSIG(status) != SIGKILL)
+        return 4;
+
+    /* The memory is back once it is gone, and this one goes on. */
+    p = mmap(NULL, CHUNK, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (p == MAP_FAILED)
+        return 5;
+    memset(p, 1, CHUNK);
+    if (p[CHUNK - 1] != 1)
+        return 6;
+    munmap(p, CHUNK);
+    pid = fork();
+    if (pid < 0)
+        return 7;
+    if (pid == 0)
+        _exit(42);
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 42)
+        return 8;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+
+    if (failed) {
+        printf("oom: failed at %d\n", failed);
+        return 1;
+    }
+    puts("oom: ok");
+    return 0;
+}
@@ -0,0 +1,68 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#define DIR_PATH "/tmp/opath_dir"
+#define FILE_PATH DIR_PATH "/file"
+#define LINK_PATH DIR_PATH "/link"
+
+static int check(void)
+{
+    struct stat st;
+    char buf[16];
+    int dirfd, fd, linkfd;
+
+    mkdir(DIR_PATH, 0755);
+    fd = open(FILE_PATH, O_WRONLY | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0 || write(fd, "hello", 5) != 5)
+        return 1;
+    close(fd);
+    unlink(LINK_PATH);
+    if (symlink("file", LINK_PATH) != 0)
+        return 2;
+
+    dirfd = open(DIR_PATH, O_PATH | O_DIRECTORY);
+    if (dirfd < 0)
+        return 3;
+    /* The directory can be looked up in, but not read. */
+    fd = openat(dirfd, "file", O_RDONLY);
+    if (fd < 0 || read(fd, buf, sizeof(buf)) != 5 || memcmp(buf, "hello", 5) != 0)
+        return 4;
+    close(fd);
+    if (read(dirfd, buf, sizeof(buf)) != -1 || errno != EBADF)
+        return 5;
+    if (fstat(dirfd, &st) != 0 || !S_ISDIR(st.st_mode))
+        return 6;
+    /* Only a directory passes O_DIRECTORY. */
+    if (open(FILE_PATH, O_PATH | O_DIRECTORY) != -1 || errno != ENOTDIR)
+        return 7;
+    /* With O_NOFOLLOW, it is the link itself. */
+    linkfd = open(LINK_PATH, O_PATH | O_NOFOLLOW);
+    if (linkfd < 0 || fstat(linkfd, &st) != 0 || !S_ISLNK(st.st_mode))
+        return 8;
+    close(linkfd);
+    if (fchdir(dirfd) != 0 || access("file", F_OK) != 0)
+        return 9;
+    chdir("/");
+    close(dirfd);
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    unlink(LINK_PATH);
+    unlink(FILE_PATH);
+    rmdir(DIR_PATH);
+    if (ret) {
+        printf("opath: failed at %d\n", ret);
+        return 1;
+    }
+    puts("opath: ok");
+    return 0;
+}
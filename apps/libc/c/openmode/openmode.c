@@ -0,0 +1,60 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#define DIR_PATH "openmode.dir"
+
+static int check(void)
+{
+    /* A new file gets the mode asked for, less the umask. */
+    umask(027);
+    int fd = open("openmode.tmp", O_CREAT | O_WRONLY | O_CLOEXEC, 0666);
+    if (fd < 0)
+        return 1;
+    struct stat st;
+    if (fstat(fd, &st) != 0 || (st.st_mode & 0777) != 0640)
+        return 2;
+    close(fd);
+    if (stat("openmode.tmp", &st) != 0 || (st.st_mode & 0777) != 0640)
+        return 3;
+    /* An existing file keeps its mode. */
+    umask(0);
+    fd = open("openmode.tmp", O_CREAT | O_WRONLY, 0666);
+    if (fd < 0)
+        return 4;
+    close(fd);
+    if (stat("openmode.tmp", &st) != 0 || (st.st_mode & 0777) != 0640)
+        return 5;
+    unlink("openmode.tmp");
+    if (umask(022) != 0)
+        return 6;
+
+    /* A relative path is looked up from the working directory. */
+    if (mkdirat(AT_FDCWD, DIR_PATH, 0755) != 0)
+        return 7;
+    if (chdir(DIR_PATH) != 0)
+        return 8;
+    fd = open("inner.tmp", O_CREAT | O_RDWR, 0644);
+    if (fd < 0)
+        return 9;
+    close(fd);
+    if (chdir("..") != 0)
+        return 10;
+    if (faccessat(AT_FDCWD, DIR_PATH "/inner.tmp", F_OK, 0) != 0)
+        return 11;
+    unlink(DIR_PATH "/inner.tmp");
+    unlinkat(AT_FDCWD, DIR_PATH, AT_REMOVEDIR);
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("open_mode: failed at %d\n", failed);
+        return 1;
+    }
+    puts("open_mode: ok");
+    return 0;
+}
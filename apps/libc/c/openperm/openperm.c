@@ -0,0 +1,102 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+/* Whether opening `path` with `flags` fails with `EACCES`. */
+static int denied(const char *path, int flags)
+{
+    int fd = open(path, flags, 0644);
+
+    if (fd >= 0) {
+        close(fd);
+        return 0;
+    }
+    return errno == EACCES;
+}
+
+static int allowed(const char *path, int flags)
+{
+    int fd = open(path, flags, 0644);
+
+    if (fd < 0)
+        return 0;
+    close(fd);
+    return 1;
+}
+
+/* Runs as an unprivileged user. */
+static int check_user(void)
+{
+    if (setgid(1000) != 0 || setuid(1000) != 0)
+        return __LINE__;
+    if (!denied("op_dir/secret", O_RDONLY))
+        return __LINE__;
+    if (!denied("op_dir/secret", O_WRONLY))
+        return __LINE__;
+    if (!allowed("op_dir/public", O_RDONLY))
+        return __LINE__;
+    if (!denied("op_dir/public", O_RDWR))
+        return __LINE__;
+    if (!denied("op_dir/public", O_RDONLY | O_TRUNC))
+        return __LINE__;
+    /* Creating needs write permission on the directory. */
+    if (!denied("op_dir/new", O_WRONLY | O_CREAT))
+        return __LINE__;
+    if (!allowed("op_open/new", O_WRONLY | O_CREAT))
+        return __LINE__;
+    if (!allowed("op_open/new", O_RDWR))
+        return __LINE__;
+    return 0;
+}
+
+static int check(void)
+{
+    int fd, status, failed;
+    pid_t pid;
+
+    if (mkdir("op_dir", 0755) != 0 || chmod("op_dir", 0755) != 0)
+        return __LINE__;
+    if (mkdir("op_open", 0777) != 0 || chmod("op_open", 0777) != 0)
+        return __LINE__;
+    fd = open("op_dir/secret", O_WRONLY | O_CREAT, 0600);
+    if (fd < 0 || close(fd) != 0 || chmod("op_dir/secret", 0600) != 0)
+        return __LINE__;
+    fd = open("op_dir/public", O_WRONLY | O_CREAT, 0644);
+    if (fd < 0 || close(fd) != 0 || chmod("op_dir/public", 0644) != 0)
+        return __LINE__;
+
+    pid = fork();
+    if (pid < 0)
+        return __LINE__;
+    if (pid == 0)
+        _exit(check_user());
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status))
+        return __LINE__;
+    failed = WEXITSTATUS(status);
+
+    /* Root is not held back. */
+    if (!failed && !allowed("op_dir/secret", O_RDWR))
+        failed = __LINE__;
+    unlink("op_open/new");
+    unlink("op_dir/secret");
+    unlink("op_dir/public");
+    rmdir("op_open");
+    rmdir("op_dir");
+    return failed;
+}
+
+int main(void)
+{
+    int failed = check();
+
+    if (failed) {
+        printf("open_perm: failed at %d\n", failed);
+        return 1;
+    }
+    puts("open_perm: ok");
+    return 0;
+}
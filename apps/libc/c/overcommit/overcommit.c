@@ -0,0 +1,134 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define MODE "/proc/sys/vm/overcommit_memory"
+#define RATIO "/proc/sys/vm/overcommit_ratio"
+
+/* The field `name` of /proc/meminfo, in kB, or -1. */
+static long meminfo_kb(const char *name)
+{
+    char line[256];
+    long value = -1;
+    size_t len = strlen(name);
+    FILE *f = fopen("/proc/meminfo", "r");
+
+    if (!f)
+        return -1;
+    while (fgets(line, sizeof(line), f))
+        if (strncmp(line, name, len) == 0 && line[len] == ':') {
+            if (sscanf(line + len + 1, "%ld kB", &value) != 1)
+                value = -1;
+            break;
+        }
+    fclose(f);
+    return value;
+}
+
+static long read_value(const char *path)
+{
+    char buf[32] = {0};
+    int fd = open(path, O_RDONLY);
+    long value = -1;
+
+    if (fd < 0)
+        return -1;
+    if (read(fd, buf, sizeof(buf) - 1) > 0)
+        sscanf(buf, "%ld", &value);
+    close(fd);
+    return value;
+}
+
+static int write_value(const char *path, const char *value)
+{
+    int fd = open(path, O_WRONLY);
+    int ret;
+
+    if (fd < 0)
+        return -1;
+    ret = write(fd, value, strlen(value)) == (ssize_t)strlen(value) ? 0 : -1;
+    close(fd);
+    return ret;
+}
+
+/* Whether a private mapping of `size` bytes with `prot` and `flags` is made. */
+static int can_map(size_t size, int prot, int flags)
+{
+    void *p = mmap(NULL, size, prot, MAP_PRIVATE | MAP_ANONYMOUS | flags, -1, 0);
+
+    if (p == MAP_FAILED)
+        return errno == ENOMEM ? 0 : -1;
+    munmap(p, size);
+    return 1;
+}
+
+static int check(void)
+{
+    long total = meminfo_kb("MemTotal");
+    long brk;
+    size_t huge;
+
+    if (total <= 0 || read_value(MODE) != 0 || read_value(RATIO) != 50)
+        return 1;
+    huge = (size_t)total * 1024 * 2;
+
+    /* Heuristic: only what could never fit is refused. */
+    if (can_map(huge, PROT_READ | PROT_WRITE, 0) != 0)
+        return 2;
+    if (can_map(huge, PROT_READ | PROT_WRITE, MAP_NORESERVE) != 1)
+        return 3;
+    if (can_map(1 << 20, PROT_READ | PROT_WRITE, 0) != 1)
+        return 4;
+
+    /* Always: nothing is refused. */
+    if (write_value(MODE, "1") || read_value(MODE) != 1)
+        return 5;
+    if (can_map(huge, PROT_READ | PROT_WRITE, 0) != 1)
+        return 6;
+
+    /* Never: the commit limit holds, MAP_NORESERVE or not. */
+    if (write_value(MODE, "2") || read_value(MODE) != 2)
+        return 7;
+    if (meminfo_kb("CommitLimit") != total / 2 || meminfo_kb("Committed_AS") < 0)
+        return 8;
+    if (can_map(huge, PROT_READ | PROT_WRITE, 0) != 0)
+        return 9;
+    if (can_map(huge, PROT_READ | PROT_WRITE, MAP_NORESERVE) != 0)
+        return 10;
+    if (can_map(1 << 20, PROT_READ | PROT_WRITE, 0) != 1)
+        return 11;
+    /* Read-only mappings commit nothing. */
+    if (can_map(huge, PROT_READ, 0) != 1)
+        return 12;
+
+    if (write_value(MODE, "3") != -1 || read_value(MODE) != 2)
+        return 13;
+    /* With nothing left to commit, the heap cannot grow either. */
+    if (write_value(RATIO, "0") || read_value(RATIO) != 0)
+        return 14;
+    brk = syscall(SYS_brk, 0);
+    if (can_map(1 << 20, PROT_READ | PROT_WRITE, 0) != 0
+        || syscall(SYS_brk, brk + 4096) != brk)
+        return 15;
+    if (write_value(RATIO, "100") || read_value(RATIO) != 100)
+        return 16;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+
+    write_value(MODE, "0");
+    write_value(RATIO, "50");
+    if (failed) {
+        printf("overcommit: failed at %d\n", failed);
+        return 1;
+    }
+    puts("overcommit: ok");
+    return 0;
+}
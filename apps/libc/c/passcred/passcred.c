@@ -0,0 +1,133 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <sched.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static char thread_stack[65536] __attribute__((aligned(16)));
+
+static int send_cred(int sock, struct ucred cred)
+{
+    char buf[CMSG_SPACE(sizeof(cred))] = { 0 };
+    struct iovec iov = { .iov_base = "c", .iov_len = 1 };
+    struct msghdr msg = {
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = buf,
+        .msg_controllen = sizeof(buf),
+    };
+    struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+
+    cmsg->cmsg_level = SOL_SOCKET;
+    cmsg->cmsg_type = SCM_CREDENTIALS;
+    cmsg->cmsg_len = CMSG_LEN(sizeof(cred));
+    memcpy(CMSG_DATA(cmsg), &cred, sizeof(cred));
+    return sendmsg(sock, &msg, 0);
+}
+
+/* Receives one byte and the credentials sent with it. */
+static int recv_cred(int sock, struct ucred *cred)
+{
+    char buf[CMSG_SPACE(sizeof(*cred))], c;
+    struct iovec iov = { .iov_base = &c, .iov_len = 1 };
+    struct msghdr msg = {
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = buf,
+        .msg_controllen = sizeof(buf),
+    };
+    struct cmsghdr *cmsg;
+
+    if (recvmsg(sock, &msg, 0) != 1)
+        return -1;
+    cmsg = CMSG_FIRSTHDR(&msg);
+    if (!cmsg || cmsg->cmsg_level != SOL_SOCKET || cmsg->cmsg_type != SCM_CREDENTIALS
+        || cmsg->cmsg_len != CMSG_LEN(sizeof(*cred)))
+        return -1;
+    memcpy(cred, CMSG_DATA(cmsg), sizeof(*cred));
+    return 0;
+}
+
+static int child(int sock)
+{
+    struct ucred cred = { .pid = getpid(), .uid = getuid(), .gid = getgid() };
+    struct ucred lie = cred;
+
+    /* Nobody can claim to be another process. */
+    lie.pid = getppid();
+    if (send_cred(sock, lie) != -1 || errno != EPERM)
+        return 1;
+    if (send_cred(sock, cred) != 1)
+        return 2;
+    /* A receiver with SO_PASSCRED gets them without asking the sender. */
+    if (write(sock, "w", 1) != 1)
+        return 3;
+    return 0;
+}
+
+/* Only makes system calls directly, as it shares the TLS of its parent. */
+static int thread_main(void *arg)
+{
+    syscall(SYS_write, (long)arg, "t", 1);
+    syscall(SYS_exit, 0);
+    return 0;
+}
+
+static int check(void)
+{
+    struct ucred cred;
+    int sv[2], one = 1, status;
+    pid_t pid;
+
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, sv) != 0)
+        return 1;
+    if (setsockopt(sv[0], SOL_SOCKET, SO_PASSCRED, &one, sizeof(one)) != 0)
+        return 2;
+    pid = fork();
+    if (pid < 0)
+        return 3;
+    if (pid == 0) {
+        close(sv[0]);
+        _exit(child(sv[1]));
+    }
+    close(sv[1]);
+
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status))
+        return 4;
+    if (WEXITSTATUS(status) != 0)
+        return 10 + WEXITSTATUS(status);
+    for (int i = 0; i < 2; i++) {
+        if (recv_cred(sv[0], &cred) != 0)
+            return 5;
+        if (cred.pid != pid || cred.uid != getuid() || cred.gid != getgid())
+            return 6;
+    }
+
+    /* What another thread sends names its process. */
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, sv) != 0)
+        return 7;
+    if (setsockopt(sv[0], SOL_SOCKET, SO_PASSCRED, &one, sizeof(one)) != 0)
+        return 8;
+    if (clone(thread_main, thread_stack + sizeof(thread_stack),
+              CLONE_VM | CLONE_FILES | CLONE_SIGHAND | CLONE_THREAD, (void *)(long)sv[1]) < 0)
+        return 9;
+    if (recv_cred(sv[0], &cred) != 0 || cred.pid != getpid())
+        return 20;
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("pass_cred: failed at %d\n", ret);
+        return 1;
+    }
+    puts("pass_cred: ok");
+    return 0;
+}
@@ -0,0 +1,74 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <limits.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+static char path[PATH_MAX + 16];
+
+/* Fills `path` with "/tmp" and then "/." up to `len` characters. */
+static void dots(size_t len)
+{
+    strcpy(path, "/tmp");
+    for (size_t i = 4; i < len; i++)
+        path[i] = i % 2 ? '.' : '/';
+    path[len] = '\0';
+}
+
+/* Fills `path` with "/tmp/" and a name of `len` characters. */
+static void long_name(size_t len)
+{
+    strcpy(path, "/tmp/");
+    memset(path + 5, 'n', len);
+    path[5 + len] = '\0';
+}
+
+static int check(void)
+{
+    struct stat st;
+    int fd;
+
+    /* The longest path there can be still resolves. */
+    dots(PATH_MAX - 1);
+    fd = openat(AT_FDCWD, path, O_RDONLY | O_DIRECTORY);
+    if (fd < 0)
+        return 1;
+    close(fd);
+    dots(PATH_MAX);
+    if (openat(AT_FDCWD, path, O_RDONLY) != -1 || errno != ENAMETOOLONG)
+        return 2;
+    if (stat(path, &st) != -1 || errno != ENAMETOOLONG)
+        return 3;
+    if (chdir(path) != -1 || errno != ENAMETOOLONG)
+        return 4;
+    dots(PATH_MAX + 8);
+    if (openat(AT_FDCWD, path, O_RDONLY) != -1 || errno != ENAMETOOLONG)
+        return 5;
+
+    /* A name of up to NAME_MAX is merely missing. */
+    long_name(NAME_MAX);
+    if (openat(AT_FDCWD, path, O_RDONLY) != -1 || errno != ENOENT)
+        return 6;
+    long_name(NAME_MAX + 1);
+    if (openat(AT_FDCWD, path, O_RDONLY) != -1 || errno != ENAMETOOLONG)
+        return 7;
+    if (openat(AT_FDCWD, path, O_WRONLY | O_CREAT, 0644) != -1 || errno != ENAMETOOLONG)
+        return 8;
+    if (mkdir(path, 0755) != -1 || errno != ENAMETOOLONG)
+        return 9;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+
+    if (failed) {
+        printf("path_max: failed at %d\n", failed);
+        return 1;
+    }
+    puts("path_max: ok");
+    return 0;
+}
@@ -0,0 +1,96 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+#define LIMIT_PATH "/proc/sys/fs/pipe-user-pages-hard"
+#define MAX_PIPES 200
+
+static int fds[MAX_PIPES][2];
+static int npipes;
+
+static int read_limit(char *buf, int len)
+{
+    int fd = open(LIMIT_PATH, O_RDONLY);
+    int n;
+
+    if (fd < 0)
+        return -1;
+    n = read(fd, buf, len - 1);
+    close(fd);
+    if (n <= 0)
+        return -1;
+    buf[n] = '\0';
+    return 0;
+}
+
+static int write_limit(const char *value)
+{
+    int fd = open(LIMIT_PATH, O_WRONLY);
+    int ok;
+
+    if (fd < 0)
+        return -1;
+    ok = write(fd, value, strlen(value)) > 0;
+    close(fd);
+    return ok ? 0 : -1;
+}
+
+static int check(void)
+{
+    int fds2[2];
+    int failed = 0;
+
+    /* 1024 pages hold four pipes grown to 1 MiB and no more. */
+    if (write_limit("1024\n") != 0)
+        return 1;
+    for (npipes = 0; npipes < MAX_PIPES; npipes++) {
+        if (pipe(fds[npipes]) != 0) {
+            if (errno != ENOMEM)
+                return 2;
+            failed = 1;
+            break;
+        }
+        /* Growing past the limit is refused, but the pipe is kept. */
+        if (fcntl(fds[npipes][1], F_SETPIPE_SZ, 1 << 20) < 0 && errno != EPERM)
+            return 3;
+    }
+    if (!failed || npipes < 4)
+        return 4;
+    /* Closing both ends gives the pages back. */
+    close(fds[--npipes][0]);
+    close(fds[npipes][1]);
+    close(fds[--npipes][0]);
+    close(fds[npipes][1]);
+    if (pipe(fds2) != 0)
+        return 5;
+    close(fds2[0]);
+    close(fds2[1]);
+    return 0;
+}
+
+int main()
+{
+    char old[32];
+    int ret;
+
+    if (read_limit(old, sizeof(old)) != 0) {
+        puts("pipe_mem: no limit to set");
+        return 1;
+    }
+    ret = check();
+    while (npipes > 0) {
+        npipes--;
+        close(fds[npipes][0]);
+        close(fds[npipes][1]);
+    }
+    write_limit(old);
+    if (ret) {
+        printf("pipe_mem: failed at %d\n", ret);
+        return 1;
+    }
+    puts("pipe_mem: ok");
+    return 0;
+}
@@ -0,0 +1,50 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+static int check(int fds[2])
+{
+    char buf[8192];
+    int size;
+
+    if (fcntl(fds[0], F_GETPIPE_SZ) != 65536)
+        return 1;
+    /* Sizes are rounded up to whole pages. */
+    size = fcntl(fds[1], F_SETPIPE_SZ, 200000);
+    if (size != 204800 || fcntl(fds[0], F_GETPIPE_SZ) != size)
+        return 2;
+    /* The larger buffer takes more than the default without blocking. */
+    memset(buf, 'x', sizeof(buf));
+    for (int i = 0; i < 100000 / (int)sizeof(buf); i++)
+        if (write(fds[1], buf, sizeof(buf)) != sizeof(buf))
+            return 3;
+    /* Data in the pipe survives resizing, but cannot be cut off. */
+    if (fcntl(fds[1], F_SETPIPE_SZ, 4096) != -1 || errno != EBUSY)
+        return 4;
+    if (fcntl(fds[1], F_SETPIPE_SZ, 131072) != 131072)
+        return 5;
+    if (read(fds[0], buf, sizeof(buf)) != sizeof(buf) || buf[0] != 'x')
+        return 6;
+    return 0;
+}
+
+int main()
+{
+    int fds[2];
+    int ret;
+
+    if (pipe(fds) != 0) {
+        puts("pipe_sz: pipe failed");
+        return 1;
+    }
+    ret = check(fds);
+    if (ret) {
+        printf("pipe_sz: failed at %d\n", ret);
+        return 1;
+    }
+    puts("pipe_sz: ok");
+    return 0;
+}
@@ -0,0 +1,58 @@
+#include <poll.h>
+#include <stdio.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    struct pollfd pfd;
+    int fds[2];
+
+    if (pipe(fds) != 0)
+        return 1;
+    /* An open, empty pipe is not ready to read. */
+    pfd.fd = fds[0];
+    pfd.events = POLLIN;
+    if (poll(&pfd, 1, 0) != 0 || pfd.revents != 0)
+        return 2;
+    write(fds[1], "x", 1);
+    if (poll(&pfd, 1, 0) != 1 || pfd.revents != POLLIN)
+        return 3;
+    /* The hangup is reported even though only POLLIN was asked for. */
+    close(fds[1]);
+    if (poll(&pfd, 1, 0) != 1 || pfd.revents != (POLLIN | POLLHUP))
+        return 4;
+    char c;
+    read(fds[0], &c, 1);
+    if (poll(&pfd, 1, -1) != 1 || pfd.revents != POLLHUP)
+        return 5;
+    close(fds[0]);
+
+    /* Writing with no reader left is an error. */
+    if (pipe(fds) != 0)
+        return 6;
+    close(fds[0]);
+    pfd.fd = fds[1];
+    pfd.events = 0;
+    if (poll(&pfd, 1, 0) != 1 || pfd.revents != POLLERR)
+        return 7;
+    close(fds[1]);
+
+    /* A closed fd is invalid. */
+    pfd.fd = fds[1];
+    pfd.events = POLLIN;
+    if (poll(&pfd, 1, 0) != 1 || pfd.revents != POLLNVAL)
+        return 8;
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("pollhup: failed at %d\n", ret);
+        return 1;
+    }
+    puts("pollhup: ok");
+    return 0;
+}
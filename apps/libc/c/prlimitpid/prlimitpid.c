@@ -0,0 +1,80 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <stdio.h>
+#include <sys/resource.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define MIB (1UL << 20)
+
+static int check(void)
+{
+    int ready[2], done[2];
+    if (pipe(ready) != 0 || pipe(done) != 0)
+        return __LINE__;
+    pid_t pid = fork();
+    if (pid < 0)
+        return __LINE__;
+    if (pid == 0) {
+        struct rlimit lim;
+        char c;
+        if (getrlimit(RLIMIT_STACK, &lim) != 0)
+            _exit(1);
+        lim.rlim_cur = 2 * MIB;
+        if (setrlimit(RLIMIT_STACK, &lim) != 0 || write(ready[1], "x", 1) != 1)
+            _exit(2);
+        // The parent sets the limit on fds in the meantime.
+        if (read(done[0], &c, 1) != 1)
+            _exit(3);
+        if (getrlimit(RLIMIT_NOFILE, &lim) != 0 || lim.rlim_cur != 32)
+            _exit(4);
+        _exit(0);
+    }
+
+    char c;
+    if (read(ready[0], &c, 1) != 1)
+        return __LINE__;
+    struct rlimit lim, old;
+    if (prlimit(pid, RLIMIT_STACK, NULL, &lim) != 0 || lim.rlim_cur != 2 * MIB)
+        return __LINE__;
+    // The parent's own limit is left as it was.
+    if (getrlimit(RLIMIT_STACK, &old) != 0 || old.rlim_cur == 2 * MIB)
+        return __LINE__;
+    if (prlimit(pid, RLIMIT_NOFILE, NULL, &old) != 0)
+        return __LINE__;
+    lim.rlim_cur = 32;
+    lim.rlim_max = old.rlim_max;
+    if (prlimit(pid, RLIMIT_NOFILE, &lim, &old) != 0 || old.rlim_cur == 32)
+        return __LINE__;
+    // A call that fails leaves the old limit it would have stored alone.
+    struct rlimit bad = {.rlim_cur = 64, .rlim_max = 16}, kept = {.rlim_cur = 1, .rlim_max = 2};
+    if (prlimit(pid, RLIMIT_NOFILE, &bad, &kept) != -1 || errno != EINVAL)
+        return __LINE__;
+    if (kept.rlim_cur != 1 || kept.rlim_max != 2)
+        return __LINE__;
+    if (write(done[1], "x", 1) != 1)
+        return __LINE__;
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status))
+        return __LINE__;
+    if (WEXITSTATUS(status))
+        return 100 + WEXITSTATUS(status);
+
+    // Once reaped, the child is gone.
+    if (prlimit(pid, RLIMIT_STACK, NULL, &lim) != -1 || errno != ESRCH)
+        return __LINE__;
+    if (prlimit(-1, RLIMIT_STACK, NULL, &lim) != -1 || errno != ESRCH)
+        return __LINE__;
+    return 0;
+}
+
+int main(void)
+{
+    int line = check();
+    if (line) {
+        printf("prlimit_pid: failed at %d\n", line);
+        return 1;
+    }
+    puts("prlimit_pid: ok");
+    return 0;
+}
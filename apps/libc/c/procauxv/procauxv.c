@@ -0,0 +1,58 @@
+#include <elf.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/auxv.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    unsigned long auxv[256];
+    int fd = open("/proc/self/auxv", O_RDONLY);
+    if (fd < 0)
+        return 1;
+    int total = 0, len;
+    while (total < (int)sizeof(auxv) &&
+           (len = read(fd, (char *)auxv + total, sizeof(auxv) - total)) > 0)
+        total += len;
+    close(fd);
+    if (total <= 0 || total % (2 * sizeof(unsigned long)) != 0)
+        return 2;
+
+    /* The vector is pairs of type and value ending with AT_NULL, the same as
+     * the one on the stack that getauxval looks in. */
+    int entries = total / (2 * sizeof(unsigned long));
+    if (auxv[2 * (entries - 1)] != AT_NULL)
+        return 3;
+    int found_pagesz = 0, found_entry = 0;
+    for (int i = 0; i < entries - 1; i++) {
+        unsigned long type = auxv[2 * i], value = auxv[2 * i + 1];
+        if (type == AT_NULL)
+            return 4;
+        if (type == AT_PAGESZ) {
+            if (value != (unsigned long)sysconf(_SC_PAGESIZE) || value != getauxval(AT_PAGESZ))
+                return 5;
+            found_pagesz = 1;
+        }
+        if (type == AT_ENTRY) {
+            if (value != getauxval(AT_ENTRY))
+                return 6;
+            found_entry = 1;
+        }
+    }
+    if (!found_pagesz)
+        return 7;
+    if (!found_entry)
+        return 8;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("proc_auxv: failed at %d\n", failed);
+        return 1;
+    }
+    puts("proc_auxv: ok");
+    return 0;
+}
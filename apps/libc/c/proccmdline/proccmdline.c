@@ -0,0 +1,96 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+/* Reads the whole of `path` to `buf`, returning its length. */
+static int read_file(const char *path, char *buf, int size)
+{
+    int fd = open(path, O_RDONLY);
+    if (fd < 0)
+        return -1;
+    int total = 0, len;
+    while (total < size && (len = read(fd, buf + total, size - total)) > 0)
+        total += len;
+    close(fd);
+    return total;
+}
+
+/* Whether `buf` holds exactly `strings`, each followed by a NUL. */
+static int matches(const char *buf, int len, char *const strings[])
+{
+    int pos = 0;
+    for (; *strings; strings++) {
+        int n = strlen(*strings) + 1;
+        if (pos + n > len || memcmp(buf + pos, *strings, n) != 0)
+            return 0;
+        pos += n;
+    }
+    return pos == len;
+}
+
+/* Run after the exec, with the arguments and environment known. */
+static int child(char *argv[])
+{
+    char buf[1024];
+    int len = read_file("/proc/self/cmdline", buf, sizeof(buf));
+    char *const args[] = {argv[0], "child", "two words", NULL};
+    if (len < 0 || !matches(buf, len, args))
+        return 1;
+    len = read_file("/proc/self/environ", buf, sizeof(buf));
+    char *const envs[] = {"PROCCMD=1", "HOME=/", NULL};
+    if (len < 0 || !matches(buf, len, envs))
+        return 2;
+
+    /* Someone else may read the parent's arguments but not its
+     * environment. */
+    char path[64];
+    snprintf(path, sizeof(path), "/proc/%d/cmdline", getppid());
+    if (read_file(path, buf, sizeof(buf)) <= 0)
+        return 3;
+    if (setuid(1000) != 0)
+        return 4;
+    snprintf(path, sizeof(path), "/proc/%d/environ", getppid());
+    if (read_file(path, buf, sizeof(buf)) >= 0 || errno != EACCES)
+        return 5;
+    return 0;
+}
+
+static int check(char *argv[])
+{
+    char buf[1024];
+    int len = read_file("/proc/self/cmdline", buf, sizeof(buf));
+    if (len <= 0 || strcmp(buf, argv[0]) != 0)
+        return 1;
+
+    pid_t pid = fork();
+    if (pid < 0)
+        return 2;
+    if (pid == 0) {
+        char *const args[] = {argv[0], "child", "two words", NULL};
+        char *const envs[] = {"PROCCMD=1", "HOME=/", NULL};
+        execve(argv[0], args, envs);
+        _exit(100);
+    }
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status))
+        return 3;
+    if (WEXITSTATUS(status) != 0)
+        return 10 + WEXITSTATUS(status);
+    return 0;
+}
+
+int main(int argc, char *argv[])
+{
+    if (argc > 1 && strcmp(argv[1], "child") == 0)
+        return child(argv);
+    int failed = check(argv);
+    if (failed) {
+        printf("proc_cmdline: failed at %d\n", failed);
+        return 1;
+    }
+    puts("proc_cmdline: ok");
+    return 0;
+}
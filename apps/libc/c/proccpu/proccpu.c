@@ -0,0 +1,64 @@
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+/* How many lines of the file at path start with prefix, or -1. */
+static int count_lines(const char *path, const char *prefix)
+{
+    char line[512];
+    int count = 0;
+    FILE *f = fopen(path, "r");
+    if (!f)
+        return -1;
+    while (fgets(line, sizeof(line), f))
+        if (strncmp(line, prefix, strlen(prefix)) == 0)
+            count++;
+    fclose(f);
+    return count;
+}
+
+static int check(void)
+{
+    long cpus = sysconf(_SC_NPROCESSORS_ONLN);
+    char line[512];
+    unsigned long long user, nice, system, idle;
+    FILE *f;
+
+    if (cpus < 1)
+        return 1;
+    if (count_lines("/proc/cpuinfo", "processor") != cpus)
+        return 2;
+
+    /* One aggregate line, then one for each processor. */
+    if (count_lines("/proc/stat", "cpu") != cpus + 1)
+        return 3;
+    if (count_lines("/proc/stat", "ctxt ") != 1)
+        return 4;
+    if (count_lines("/proc/stat", "btime ") != 1)
+        return 5;
+
+    f = fopen("/proc/stat", "r");
+    if (!f)
+        return 6;
+    if (!fgets(line, sizeof(line), f) ||
+        sscanf(line, "cpu %llu %llu %llu %llu", &user, &nice, &system, &idle) != 4) {
+        fclose(f);
+        return 7;
+    }
+    if (!fgets(line, sizeof(line), f) || strncmp(line, "cpu0 ", 5) != 0) {
+        fclose(f);
+        return 8;
+    }
+    fclose(f);
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed)
+        printf("proc_cpu: failed at %d\n", failed);
+    else
+        puts("proc_cpu: ok");
+    return 0;
+}
@@ -0,0 +1,92 @@
+#include <dirent.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+static int fd_link(int fd, char *buf, size_t size)
+{
+    char path[64];
+    snprintf(path, sizeof(path), "/proc/self/fd/%d", fd);
+    ssize_t len = readlink(path, buf, size - 1);
+    if (len < 0)
+        return -1;
+    buf[len] = '\0';
+    return 0;
+}
+
+/* Whether `fd` is listed in /proc/self/fd. */
+static int listed(int fd)
+{
+    DIR *dir = opendir("/proc/self/fd");
+    if (!dir)
+        return 0;
+    int found = 0;
+    struct dirent *entry;
+    while ((entry = readdir(dir)))
+        if (entry->d_name[0] != '.' && atoi(entry->d_name) == fd)
+            found = 1;
+    closedir(dir);
+    return found;
+}
+
+static int check(void)
+{
+    char cwd[256], expected[512], target[512];
+    if (!getcwd(cwd, sizeof(cwd)))
+        return 1;
+    snprintf(expected, sizeof(expected), "%s/procfd.tmp", strcmp(cwd, "/") ? cwd : "");
+
+    /* A file links to its path. */
+    int fd = open("procfd.tmp", O_CREAT | O_RDWR, 0644);
+    if (fd < 0)
+        return 2;
+    if (fd_link(fd, target, sizeof(target)) != 0 || strcmp(target, expected) != 0)
+        return 3;
+    if (!listed(fd))
+        return 4;
+
+    /* A pipe links to pipe:[inode], with the inode fstat reports. */
+    int fds[2];
+    if (pipe(fds) != 0)
+        return 5;
+    struct stat st;
+    if (fstat(fds[0], &st) != 0)
+        return 6;
+    snprintf(expected, sizeof(expected), "pipe:[%lu]", (unsigned long)st.st_ino);
+    if (fd_link(fds[0], target, sizeof(target)) != 0 || strcmp(target, expected) != 0)
+        return 7;
+    if (fd_link(fds[1], target, sizeof(target)) != 0 || strcmp(target, expected) != 0)
+        return 8;
+
+    /* A closed descriptor has no link and is not listed. A high one is used
+     * so that opening the directory does not take it again. */
+    if (dup2(fd, 100) != 100)
+        return 9;
+    if (!listed(100))
+        return 10;
+    close(100);
+    if (fd_link(100, target, sizeof(target)) == 0)
+        return 11;
+    if (listed(100))
+        return 12;
+
+    close(fd);
+    close(fds[0]);
+    close(fds[1]);
+    unlink("procfd.tmp");
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("proc_fd: failed at %d\n", failed);
+        return 1;
+    }
+    puts("proc_fd: ok");
+    return 0;
+}
@@ -0,0 +1,56 @@
+#include <stdio.h>
+#include <string.h>
+#include <sys/sysinfo.h>
+
+/* The value of the field name in /proc/meminfo, in kB, or -1. */
+static long long meminfo_field(const char *name)
+{
+    char line[256];
+    long long value = -1;
+    size_t len = strlen(name);
+    FILE *f = fopen("/proc/meminfo", "r");
+    if (!f)
+        return -1;
+    while (fgets(line, sizeof(line), f))
+        if (strncmp(line, name, len) == 0 && line[len] == ':') {
+            if (sscanf(line + len + 1, "%lld kB", &value) != 1)
+                value = -1;
+            break;
+        }
+    fclose(f);
+    return value;
+}
+
+static int check(void)
+{
+    struct sysinfo info;
+    long long total = meminfo_field("MemTotal");
+    long long free_kb = meminfo_field("MemFree");
+    long long available = meminfo_field("MemAvailable");
+
+    if (total <= 0)
+        return 1;
+    if (free_kb < 0 || free_kb > total)
+        return 2;
+    if (available < 0 || available > total)
+        return 3;
+    if (meminfo_field("Buffers") < 0 || meminfo_field("Cached") < 0 || meminfo_field("Slab") < 0)
+        return 4;
+
+    if (sysinfo(&info) != 0)
+        return 5;
+    if ((long long)info.totalram * info.mem_unit / 1024 != total)
+        return 6;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("proc_meminfo: failed at %d\n", failed);
+        return 1;
+    }
+    puts("proc_meminfo: ok");
+    return 0;
+}
@@ -0,0 +1,95 @@
+#include <dirent.h>
+#include <pthread.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+static volatile pid_t thread_tid;
+static volatile int done;
+
+static void *thread_main(void *arg)
+{
+    (void)arg;
+    thread_tid = syscall(SYS_gettid);
+    while (!done)
+        sched_yield();
+    return NULL;
+}
+
+/* Whether the file at path has a line that is exactly line. */
+static int has_line(const char *path, const char *line)
+{
+    char buf[256];
+    int found = 0;
+    FILE *f = fopen(path, "r");
+    if (!f)
+        return 0;
+    while (!found && fgets(buf, sizeof(buf), f)) {
+        buf[strcspn(buf, "\n")] = '\0';
+        found = strcmp(buf, line) == 0;
+    }
+    fclose(f);
+    return found;
+}
+
+static int check_threads(void)
+{
+    pid_t pid = getpid();
+    int entries = 0, seen_main = 0, seen_thread = 0;
+    char path[64], line[64];
+    struct dirent *entry;
+    DIR *dir = opendir("/proc/self/task");
+    if (!dir)
+        return 1;
+    while ((entry = readdir(dir))) {
+        if (entry->d_name[0] == '.')
+            continue;
+        entries++;
+        if (entry->d_type != DT_DIR)
+            return 2;
+        pid_t tid = atoi(entry->d_name);
+        seen_main |= tid == pid;
+        seen_thread |= tid == thread_tid;
+    }
+    closedir(dir);
+    if (entries != 2 || !seen_main || !seen_thread)
+        return 3;
+
+    /* Each thread has its own status, in the same process. */
+    snprintf(path, sizeof(path), "/proc/%d/task/%d/status", pid, thread_tid);
+    snprintf(line, sizeof(line), "Pid:\t%d", thread_tid);
+    if (!has_line(path, line))
+        return 4;
+    snprintf(line, sizeof(line), "Tgid:\t%d", pid);
+    if (!has_line(path, line))
+        return 5;
+    if (!has_line("/proc/self/status", "Threads:\t2"))
+        return 6;
+    return 0;
+}
+
+static int check(void)
+{
+    pthread_t thread;
+    if (pthread_create(&thread, NULL, thread_main, NULL) != 0)
+        return 10;
+    while (!thread_tid)
+        sched_yield();
+    int failed = check_threads();
+    done = 1;
+    pthread_join(thread, NULL);
+    return failed;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("proc_task: failed at %d\n", failed);
+        return 1;
+    }
+    puts("proc_task: ok");
+    return 0;
+}
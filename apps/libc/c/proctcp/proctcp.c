@@ -0,0 +1,93 @@
+#include <arpa/inet.h>
+#include <fcntl.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+#define PORT 5557
+
+static char table[4096];
+
+/* Reads /proc/net/tcp into `table`. */
+static int read_table(void)
+{
+    ssize_t len, total = 0;
+    int fd = open("/proc/net/tcp", O_RDONLY);
+
+    if (fd < 0)
+        return -1;
+    while ((len = read(fd, table + total, sizeof(table) - 1 - total)) > 0)
+        total += len;
+    close(fd);
+    table[total] = '\0';
+    return len < 0 ? -1 : 0;
+}
+
+/* Whether a line of the table is for `local` and `remote` in state `st`. */
+static int has_line(const char *local, const char *remote, const char *st)
+{
+    char want[64];
+
+    snprintf(want, sizeof(want), "%s %s %s ", local, remote, st);
+    return strstr(table, want) != NULL;
+}
+
+static int check(void)
+{
+    struct sockaddr_in addr = {
+        .sin_family = AF_INET,
+        .sin_port = htons(PORT),
+        .sin_addr.s_addr = htonl(INADDR_LOOPBACK),
+    };
+    struct sockaddr_in name;
+    socklen_t len = sizeof(name);
+    char server_addr[16], client_addr[16];
+    int server, client, conn;
+
+    server = socket(AF_INET, SOCK_STREAM, 0);
+    if (server < 0)
+        return 1;
+    if (bind(server, (struct sockaddr *)&addr, sizeof(addr)) != 0 || listen(server, 1) != 0)
+        return 2;
+    client = socket(AF_INET, SOCK_STREAM, 0);
+    if (client < 0 || connect(client, (struct sockaddr *)&addr, sizeof(addr)) != 0)
+        return 3;
+    conn = accept(server, NULL, NULL);
+    if (conn < 0)
+        return 4;
+    if (getsockname(client, (struct sockaddr *)&name, &len) != 0)
+        return 5;
+
+    snprintf(server_addr, sizeof(server_addr), "0100007F:%04X", PORT);
+    snprintf(client_addr, sizeof(client_addr), "0100007F:%04X", ntohs(name.sin_port));
+    if (read_table() != 0)
+        return 6;
+    if (strncmp(table, "  sl  local_address rem_address   st ", 37) != 0)
+        return 7;
+    /* The listener, then both ends of the connection. */
+    if (!has_line(server_addr, "00000000:0000", "0A"))
+        return 8;
+    if (!has_line(client_addr, server_addr, "01"))
+        return 9;
+    if (!has_line(server_addr, client_addr, "01"))
+        return 10;
+
+    close(client);
+    close(conn);
+    close(server);
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("proc_net_tcp: failed at %d\n", ret);
+        return 1;
+    }
+    puts("proc_net_tcp: ok");
+    return 0;
+}
@@ -0,0 +1,70 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <termios.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    struct termios t;
+    char buf[32], *name;
+    int master, slave, n;
+
+    master = posix_openpt(O_RDWR | O_NOCTTY);
+    if (master < 0)
+        return 1;
+    name = ptsname(master);
+    if (!name || strncmp(name, "/dev/pts/", 9) != 0)
+        return 2;
+    /* The slave cannot be opened until it is unlocked. */
+    if (open(name, O_RDWR | O_NOCTTY) != -1 || errno != EIO)
+        return 3;
+    if (grantpt(master) != 0 || unlockpt(master) != 0)
+        return 4;
+    slave = open(name, O_RDWR | O_NOCTTY);
+    if (slave < 0)
+        return 5;
+    if (!isatty(slave))
+        return 6;
+
+    tcgetattr(slave, &t);
+    t.c_lflag &= ~ECHO;
+    tcsetattr(slave, TCSANOW, &t);
+
+    /* What is written to the master is typed on the slave, a line at a time. */
+    if (write(master, "hel", 3) != 3 || write(master, "lo\n", 3) != 3)
+        return 7;
+    n = read(slave, buf, sizeof(buf));
+    if (n != 6 || memcmp(buf, "hello\n", 6) != 0)
+        return 8;
+
+    /* And what the slave writes is read from the master. */
+    if (write(slave, "world", 5) != 5)
+        return 9;
+    n = read(master, buf, sizeof(buf));
+    if (n != 5 || memcmp(buf, "world", 5) != 0)
+        return 10;
+
+    /* Once the slave is closed, the master is hung up. */
+    close(slave);
+    if (read(master, buf, sizeof(buf)) != -1 || errno != EIO)
+        return 11;
+    close(master);
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("pty: failed at %d\n", ret);
+        return 1;
+    }
+    puts("pty: ok");
+    return 0;
+}
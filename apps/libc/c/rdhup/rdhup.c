@@ -0,0 +1,53 @@
+#define _GNU_SOURCE
+#include <stdio.h>
+#include <sys/epoll.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    struct epoll_event ev = { .events = EPOLLIN | EPOLLRDHUP }, out;
+    int sv[2], ep;
+    char c;
+
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, sv) != 0)
+        return 1;
+    ep = epoll_create1(0);
+    if (ep < 0)
+        return 2;
+    ev.data.fd = sv[0];
+    if (epoll_ctl(ep, EPOLL_CTL_ADD, sv[0], &ev) != 0)
+        return 3;
+    if (epoll_wait(ep, &out, 1, 0) != 0)
+        return 4;
+
+    /* The peer stops writing but can still read. */
+    if (shutdown(sv[1], SHUT_WR) != 0)
+        return 5;
+    if (epoll_wait(ep, &out, 1, 0) != 1 || out.data.fd != sv[0])
+        return 6;
+    if (!(out.events & EPOLLRDHUP) || !(out.events & EPOLLIN) || (out.events & EPOLLHUP))
+        return 7;
+    if (read(sv[0], &c, 1) != 0)
+        return 8;
+    if (write(sv[0], "x", 1) != 1 || read(sv[1], &c, 1) != 1 || c != 'x')
+        return 9;
+
+    /* Once the peer is gone, both directions are shut down. */
+    close(sv[1]);
+    if (epoll_wait(ep, &out, 1, 0) != 1 || !(out.events & EPOLLHUP))
+        return 10;
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("rdhup: failed at %d\n", ret);
+        return 1;
+    }
+    puts("rdhup: ok");
+    return 0;
+}
@@ -0,0 +1,92 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#define OLD_PATH "rename.new"
+#define NEW_PATH "rename.file"
+#define DIR_PATH "rename.dir"
+#define DIR_FILE DIR_PATH "/file"
+
+static int write_file(const char *path, const char *content)
+{
+    int fd = open(path, O_CREAT | O_WRONLY | O_TRUNC, 0644);
+    if (fd < 0)
+        return -1;
+    ssize_t len = write(fd, content, strlen(content));
+    close(fd);
+    return len == (ssize_t)strlen(content) ? 0 : -1;
+}
+
+static int check(void)
+{
+    char buf[16] = {0};
+    struct stat st;
+    int fd;
+
+    if (write_file(NEW_PATH, "old contents") != 0)
+        return 1;
+    if (write_file(OLD_PATH, "new") != 0)
+        return 2;
+
+    /* The file in the way is replaced by the one moved over it. */
+    if (rename(OLD_PATH, NEW_PATH) != 0)
+        return 3;
+    if (stat(OLD_PATH, &st) == 0 || errno != ENOENT)
+        return 4;
+    fd = open(NEW_PATH, O_RDONLY);
+    if (fd < 0)
+        return 5;
+    if (read(fd, buf, sizeof(buf) - 1) != 3 || strcmp(buf, "new") != 0) {
+        close(fd);
+        return 6;
+    }
+    close(fd);
+
+    /* A directory with something in it cannot be replaced, nor a file with a
+     * directory or a directory with a file. */
+    if (mkdirat(AT_FDCWD, DIR_PATH, 0755) != 0)
+        return 7;
+    if (write_file(DIR_FILE, "x") != 0)
+        return 8;
+    if (mkdirat(AT_FDCWD, OLD_PATH, 0755) != 0)
+        return 9;
+    if (rename(OLD_PATH, DIR_PATH) == 0 || errno != ENOTEMPTY)
+        return 10;
+    if (rename(NEW_PATH, OLD_PATH) == 0 || errno != EISDIR)
+        return 11;
+    if (rename(OLD_PATH, NEW_PATH) == 0 || errno != ENOTDIR)
+        return 12;
+    if (stat(DIR_FILE, &st) != 0)
+        return 13;
+
+    /* An empty one can, and what was in the moved directory goes with it. */
+    if (unlinkat(AT_FDCWD, OLD_PATH, AT_REMOVEDIR) != 0)
+        return 14;
+    if (mkdirat(AT_FDCWD, OLD_PATH, 0755) != 0)
+        return 15;
+    if (rename(DIR_PATH, OLD_PATH) != 0)
+        return 16;
+    if (stat(OLD_PATH "/file", &st) != 0 || !S_ISREG(st.st_mode))
+        return 17;
+    if (stat(DIR_PATH, &st) == 0 || errno != ENOENT)
+        return 18;
+
+    unlink(OLD_PATH "/file");
+    unlinkat(AT_FDCWD, OLD_PATH, AT_REMOVEDIR);
+    unlink(NEW_PATH);
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("rename: failed at %d\n", failed);
+        return 1;
+    }
+    puts("rename: ok");
+    return 0;
+}
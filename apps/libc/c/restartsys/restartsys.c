@@ -0,0 +1,85 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+static void handler(int sig)
+{
+    (void)sig;
+}
+
+static long elapsed_ms(const struct timespec *start)
+{
+    struct timespec now;
+    clock_gettime(CLOCK_MONOTONIC, &now);
+    return (now.tv_sec - start->tv_sec) * 1000 + (now.tv_nsec - start->tv_nsec) / 1000000;
+}
+
+/* Forks a child that sends `sig` to this process after 50ms. */
+static pid_t signal_later(int sig)
+{
+    pid_t parent = getpid();
+    pid_t pid = fork();
+    if (pid == 0) {
+        usleep(50000);
+        kill(parent, sig);
+        _exit(0);
+    }
+    return pid;
+}
+
+static int check(void)
+{
+    /* With nothing interrupted, there is nothing to resume. */
+    if (syscall(SYS_restart_syscall) != -1 || errno != EINTR)
+        return 1;
+
+    /* A signal that runs no handler leaves the sleep to be resumed, for just
+     * the time that was left. */
+    struct timespec start;
+    struct timespec req = {.tv_sec = 0, .tv_nsec = 300000000};
+    struct timespec rem = {0};
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    pid_t pid = signal_later(SIGCONT);
+    if (pid < 0)
+        return 2;
+    if (nanosleep(&req, &rem) != 0)
+        return 3;
+    long ms = elapsed_ms(&start);
+    if (ms < 300 || ms > 1000)
+        return 4;
+    if (waitpid(pid, NULL, 0) != pid)
+        return 5;
+
+    /* A handler sees the sleep fail, even with SA_RESTART. */
+    struct sigaction sa = {.sa_handler = handler, .sa_flags = SA_RESTART};
+    sigemptyset(&sa.sa_mask);
+    if (sigaction(SIGUSR1, &sa, NULL) != 0)
+        return 6;
+    pid = signal_later(SIGUSR1);
+    if (pid < 0)
+        return 7;
+    if (nanosleep(&req, &rem) != -1 || errno != EINTR)
+        return 8;
+    if (rem.tv_sec != 0 || rem.tv_nsec <= 0 || rem.tv_nsec >= 300000000)
+        return 9;
+    waitpid(pid, NULL, 0);
+    /* Nor is it left to be resumed. */
+    if (syscall(SYS_restart_syscall) != -1 || errno != EINTR)
+        return 10;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("restart_syscall: failed at %d\n", failed);
+        return 1;
+    }
+    puts("restart_syscall: ok");
+    return 0;
+}
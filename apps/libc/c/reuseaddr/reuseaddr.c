@@ -0,0 +1,84 @@
+#include <arpa/inet.h>
+#include <errno.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+#define PORT 5556
+
+static struct sockaddr_in addr;
+
+/* Binds a new TCP socket to the port, with SO_REUSEADDR set to `reuse`. */
+static int bind_port(int reuse)
+{
+    int fd = socket(AF_INET, SOCK_STREAM, 0);
+
+    if (fd < 0)
+        return -1;
+    if (setsockopt(fd, SOL_SOCKET, SO_REUSEADDR, &reuse, sizeof(reuse)) != 0
+        || bind(fd, (struct sockaddr *)&addr, sizeof(addr)) != 0) {
+        int err = errno;
+
+        close(fd);
+        errno = err;
+        return -1;
+    }
+    return fd;
+}
+
+static int check(void)
+{
+    int server, client, conn, fd, reuse = 0;
+    socklen_t len = sizeof(reuse);
+
+    addr.sin_family = AF_INET;
+    addr.sin_port = htons(PORT);
+    addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+
+    server = bind_port(1);
+    if (server < 0 || listen(server, 1) != 0)
+        return 1;
+    client = socket(AF_INET, SOCK_STREAM, 0);
+    if (client < 0 || connect(client, (struct sockaddr *)&addr, sizeof(addr)) != 0)
+        return 2;
+    conn = accept(server, NULL, NULL);
+    if (conn < 0)
+        return 3;
+
+    /*
+     * The server closes first, which leaves its end in TIME_WAIT. Only
+     * sockets that set SO_REUSEADDR, as the server did, may take the port.
+     */
+    close(conn);
+    close(server);
+    close(client);
+
+    if (bind_port(0) != -1 || errno != EADDRINUSE)
+        return 4;
+    fd = bind_port(1);
+    if (fd < 0)
+        return 5;
+    if (getsockopt(fd, SOL_SOCKET, SO_REUSEADDR, &reuse, &len) != 0 || !reuse)
+        return 6;
+    if (listen(fd, 1) != 0)
+        return 7;
+
+    /* A bound socket holds the port whatever its options. */
+    if (bind_port(1) != -1 || errno != EADDRINUSE)
+        return 8;
+    close(fd);
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("reuseaddr: failed at %d\n", ret);
+        return 1;
+    }
+    puts("reuseaddr: ok");
+    return 0;
+}
@@ -0,0 +1,93 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/resource.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define MIB (1UL << 20)
+
+static int check_unprivileged(void)
+{
+    struct rlimit lim = {32, 64};
+    if (setuid(65534) != 0)
+        return __LINE__;
+    // Lowering either limit is allowed, raising the hard one back is not.
+    if (setrlimit(RLIMIT_NOFILE, &lim) != 0)
+        return __LINE__;
+    lim.rlim_cur = 64;
+    if (setrlimit(RLIMIT_NOFILE, &lim) != 0)
+        return __LINE__;
+    lim.rlim_max = 128;
+    if (setrlimit(RLIMIT_NOFILE, &lim) == 0 || errno != EPERM)
+        return __LINE__;
+    if (getrlimit(RLIMIT_NOFILE, &lim) != 0 || lim.rlim_cur != 64 || lim.rlim_max != 64)
+        return __LINE__;
+    return 0;
+}
+
+static int check(void)
+{
+    struct rlimit old, lim;
+
+    // The stack limit can be read back as it was set.
+    if (getrlimit(RLIMIT_STACK, &old) != 0 || old.rlim_cur == 0)
+        return __LINE__;
+    lim.rlim_cur = 4 * MIB;
+    lim.rlim_max = old.rlim_max;
+    if (setrlimit(RLIMIT_STACK, &lim) != 0)
+        return __LINE__;
+    if (getrlimit(RLIMIT_STACK, &lim) != 0 || lim.rlim_cur != 4 * MIB ||
+        lim.rlim_max != old.rlim_max)
+        return __LINE__;
+    if (setrlimit(RLIMIT_STACK, &old) != 0)
+        return __LINE__;
+
+    // A soft limit above the hard one is invalid, and so is an unknown resource.
+    lim.rlim_cur = 2 * MIB;
+    lim.rlim_max = MIB;
+    if (setrlimit(RLIMIT_STACK, &lim) == 0 || errno != EINVAL)
+        return __LINE__;
+    if (prlimit(0, 100, NULL, &lim) == 0 || errno != EINVAL)
+        return __LINE__;
+
+    // The old limits come back from the call that sets the new ones.
+    struct rlimit as = {256 * MIB, RLIM_INFINITY};
+    if (prlimit(0, RLIMIT_AS, &as, &old) != 0 || old.rlim_cur != RLIM_INFINITY)
+        return __LINE__;
+    if (getrlimit(RLIMIT_AS, &lim) != 0 || lim.rlim_cur != 256 * MIB)
+        return __LINE__;
+    void *p = mmap(NULL, 512 * MIB, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (p != MAP_FAILED || errno != ENOMEM)
+        return __LINE__;
+    p = mmap(NULL, MIB, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (p == MAP_FAILED)
+        return __LINE__;
+    munmap(p, MIB);
+    if (setrlimit(RLIMIT_AS, &old) != 0)
+        return __LINE__;
+
+    pid_t pid = fork();
+    if (pid < 0)
+        return __LINE__;
+    if (pid == 0)
+        _exit(check_unprivileged());
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status))
+        return __LINE__;
+    if (WEXITSTATUS(status))
+        return WEXITSTATUS(status);
+    return 0;
+}
+
+int main(void)
+{
+    int line = check();
+    if (line) {
+        printf("rlimit: failed at %d\n", line);
+        return 1;
+    }
+    puts("rlimit: ok");
+    return 0;
+}
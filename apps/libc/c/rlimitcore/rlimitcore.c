@@ -0,0 +1,61 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/resource.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    struct rlimit rl, bad;
+    struct stat st;
+    int status;
+    pid_t pid;
+
+    if (getrlimit(RLIMIT_CORE, &rl) != 0)
+        return 1;
+    bad.rlim_cur = 2;
+    bad.rlim_max = 1;
+    errno = 0;
+    if (setrlimit(RLIMIT_CORE, &bad) != -1 || errno != EINVAL)
+        return 2;
+
+    rl.rlim_cur = 0;
+    if (setrlimit(RLIMIT_CORE, &rl) != 0)
+        return 3;
+    rl.rlim_cur = 1;
+    if (getrlimit(RLIMIT_CORE, &rl) != 0 || rl.rlim_cur != 0)
+        return 4;
+
+    /* The limit is inherited, so the child dies of SIGABRT without a dump. */
+    unlinkat(AT_FDCWD, "core", 0);
+    pid = fork();
+    if (pid < 0)
+        return 5;
+    if (pid == 0) {
+        raise(SIGABRT);
+        _exit(0);
+    }
+    if (waitpid(pid, &status, 0) != pid)
+        return 6;
+    if (!WIFSIGNALED(status) || WTERMSIG(status) != SIGABRT)
+        return 7;
+    if (WCOREDUMP(status))
+        return 8;
+    if (fstatat(AT_FDCWD, "core", &st, 0) == 0)
+        return 9;
+    return 0;
+}
+
+int main(void)
+{
+    int ret = check();
+    if (ret) {
+        printf("rlimit_core: failed at %d\n", ret);
+        return 1;
+    }
+    puts("rlimit_core: ok");
+    return 0;
+}
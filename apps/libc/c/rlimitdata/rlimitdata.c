@@ -0,0 +1,61 @@
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/resource.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    struct rlimit old, rl;
+    unsigned long brk, grown;
+    void *p;
+    int ret = 0;
+
+    if (getrlimit(RLIMIT_DATA, &old) != 0)
+        return 1;
+    rl.rlim_cur = 4096;
+    rl.rlim_max = old.rlim_max;
+    if (setrlimit(RLIMIT_DATA, &rl) != 0)
+        return 2;
+    if (getrlimit(RLIMIT_DATA, &rl) != 0 || rl.rlim_cur != 4096)
+        return 3;
+
+    /* A failed brk leaves the break where it was. */
+    brk = syscall(SYS_brk, 0);
+    if (syscall(SYS_brk, brk + 8192) != (long)brk) {
+        ret = 4;
+        goto out;
+    }
+    p = mmap(NULL, 8192, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (p != MAP_FAILED) {
+        munmap(p, 8192);
+        ret = 5;
+        goto out;
+    }
+
+    if (setrlimit(RLIMIT_DATA, &old) != 0) {
+        ret = 6;
+        goto out;
+    }
+    grown = syscall(SYS_brk, brk + 8192);
+    if (grown != brk + 8192) {
+        ret = 7;
+        goto out;
+    }
+    syscall(SYS_brk, brk);
+
+out:
+    setrlimit(RLIMIT_DATA, &old);
+    return ret;
+}
+
+int main(void)
+{
+    int ret = check();
+    if (ret) {
+        printf("rlimit_data: failed at %d\n", ret);
+        return 1;
+    }
+    puts("rlimit_data: ok");
+    return 0;
+}
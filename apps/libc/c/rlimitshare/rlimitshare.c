@@ -0,0 +1,81 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <sched.h>
+#include <stdio.h>
+#include <sys/resource.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static char thread_stack[65536] __attribute__((aligned(16)));
+static volatile int thread_done;
+
+/* Only makes system calls directly, as it shares the TLS of its parent. */
+static int thread_main(void *arg)
+{
+    syscall(SYS_prlimit64, 0, RLIMIT_NOFILE, arg, NULL);
+    thread_done = 1;
+    syscall(SYS_exit, 0);
+    return 0;
+}
+
+/* Run in a child, as it lowers the limits for good. */
+static int child(void)
+{
+    /* A limit set by one thread holds for the whole process. */
+    struct rlimit rl = { 8, 8 };
+    if (clone(thread_main, thread_stack + sizeof(thread_stack),
+              CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND | CLONE_THREAD, &rl) < 0)
+        return 1;
+    while (!thread_done)
+        sched_yield();
+    if (getrlimit(RLIMIT_NOFILE, &rl) != 0 || rl.rlim_cur != 8)
+        return 2;
+    int fd;
+    while ((fd = dup(0)) >= 0) {
+        if (fd >= 8)
+            return 3;
+    }
+    if (errno != EMFILE)
+        return 3;
+
+    /* The break cannot take the address space over its limit. */
+    long brk = syscall(SYS_brk, 0);
+    rl.rlim_cur = rl.rlim_max = 4096;
+    if (setrlimit(RLIMIT_AS, &rl) != 0)
+        return 4;
+    if (syscall(SYS_brk, brk + 8192) != brk)
+        return 5;
+    return 0;
+}
+
+static int check(void)
+{
+    int status;
+    pid_t pid = fork();
+    if (pid < 0)
+        return __LINE__;
+    if (pid == 0)
+        _exit(child());
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status))
+        return __LINE__;
+    if (WEXITSTATUS(status) != 0)
+        return 1000 + WEXITSTATUS(status);
+
+    /* Nor does it leak to the parent. */
+    struct rlimit rl;
+    if (getrlimit(RLIMIT_NOFILE, &rl) != 0 || rl.rlim_cur == 8)
+        return __LINE__;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("rlimit_share: failed at %d\n", failed);
+        return 1;
+    }
+    puts("rlimit_share: ok");
+    return 0;
+}
@@ -0,0 +1,53 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#define DIR_PATH "rmdir.dir"
+#define FILE_PATH DIR_PATH "/file"
+
+static int check(void)
+{
+    struct stat st;
+    if (mkdirat(AT_FDCWD, DIR_PATH, 0755) != 0)
+        return 1;
+    int fd = open(FILE_PATH, O_CREAT | O_WRONLY, 0644);
+    if (fd < 0)
+        return 2;
+    close(fd);
+
+    /* A directory with something in it stays, and so does what is in it. */
+    if (rmdir(DIR_PATH) == 0 || errno != ENOTEMPTY)
+        return 3;
+    if (stat(FILE_PATH, &st) != 0)
+        return 4;
+    /* Each call only removes its own kind. */
+    if (unlink(DIR_PATH) == 0 || errno != EISDIR)
+        return 5;
+    if (rmdir(FILE_PATH) == 0 || errno != ENOTDIR)
+        return 6;
+
+    if (unlink(FILE_PATH) != 0)
+        return 7;
+    if (stat(FILE_PATH, &st) == 0 || errno != ENOENT)
+        return 8;
+    if (unlink(FILE_PATH) == 0 || errno != ENOENT)
+        return 9;
+    if (rmdir(DIR_PATH) != 0)
+        return 10;
+    if (stat(DIR_PATH, &st) == 0 || errno != ENOENT)
+        return 11;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("rmdir: failed at %d\n", failed);
+        return 1;
+    }
+    puts("rmdir: ok");
+    return 0;
+}
@@ -0,0 +1,74 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/resource.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <time.h>
+#include <unistd.h>
+
+// Every output argument pointing into `page` fails with EFAULT, leaving it
+// as it was.
+static int check_outputs(char *page)
+{
+    if (syscall(SYS_prlimit64, 0, RLIMIT_NOFILE, NULL, page) != -1 || errno != EFAULT)
+        return __LINE__;
+    if (stat("/", (struct stat *)page) != -1 || errno != EFAULT)
+        return __LINE__;
+    if (syscall(SYS_clock_gettime, CLOCK_MONOTONIC, page) != -1 || errno != EFAULT)
+        return __LINE__;
+    if (syscall(SYS_pipe2, page, 0) != -1 || errno != EFAULT)
+        return __LINE__;
+    int fd = open("/dev/zero", O_RDONLY);
+    if (fd < 0)
+        return __LINE__;
+    if (read(fd, page, 16) != -1 || errno != EFAULT)
+        return __LINE__;
+    close(fd);
+    for (int i = 0; i < 64; i++)
+        if (page[i] != 0)
+            return __LINE__;
+    return 0;
+}
+
+static int check(void)
+{
+    long size = sysconf(_SC_PAGESIZE);
+    char *page = mmap(NULL, size, PROT_READ, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (page == MAP_FAILED)
+        return __LINE__;
+    int line = check_outputs(page);
+    if (line)
+        return line;
+    munmap(page, size);
+
+    // A page made read-only after it was written to is refused the same way.
+    page = mmap(NULL, size, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (page == MAP_FAILED)
+        return __LINE__;
+    page[size - 1] = 1;
+    if (mprotect(page, size, PROT_READ) != 0)
+        return __LINE__;
+    line = check_outputs(page);
+    if (line)
+        return line;
+    if (mprotect(page, size, PROT_READ | PROT_WRITE) != 0)
+        return __LINE__;
+    struct rlimit *lim = (struct rlimit *)page;
+    if (getrlimit(RLIMIT_NOFILE, lim) != 0 || lim->rlim_cur == 0)
+        return __LINE__;
+    munmap(page, size);
+    return 0;
+}
+
+int main(void)
+{
+    int line = check();
+    if (line) {
+        printf("ro_fault: failed at %d\n", line);
+        return 1;
+    }
+    puts("ro_fault: ok");
+    return 0;
+}
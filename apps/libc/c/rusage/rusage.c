@@ -0,0 +1,71 @@
+#define _GNU_SOURCE
+#include <pthread.h>
+#include <stdio.h>
+#include <sys/resource.h>
+
+#define BUSY_MICROS 200000L
+
+static long micros(const struct rusage *usage)
+{
+    return (usage->ru_utime.tv_sec + usage->ru_stime.tv_sec) * 1000000L +
+           usage->ru_utime.tv_usec + usage->ru_stime.tv_usec;
+}
+
+/* Spins until the thread has run for a while, then reports its own time. */
+static void *busy(void *arg)
+{
+    long *out = arg;
+    struct rusage usage;
+
+    do {
+        if (getrusage(RUSAGE_THREAD, &usage) != 0) {
+            *out = -1;
+            return NULL;
+        }
+    } while (micros(&usage) < BUSY_MICROS);
+    *out = micros(&usage);
+    return NULL;
+}
+
+static int check(void)
+{
+    pthread_t threads[2];
+    long thread_micros[2];
+    struct rusage usage;
+    long total;
+
+    for (int i = 0; i < 2; i++)
+        if (pthread_create(&threads[i], NULL, busy, &thread_micros[i]) != 0)
+            return 1;
+    for (int i = 0; i < 2; i++)
+        pthread_join(threads[i], NULL);
+    if (thread_micros[0] < 0 || thread_micros[1] < 0)
+        return 2;
+    if (getrusage(RUSAGE_SELF, &usage) != 0)
+        return 3;
+    total = micros(&usage);
+    /* Each thread has about half of what the process used together. */
+    if (thread_micros[0] + thread_micros[1] > total + 10000)
+        return 4;
+    for (int i = 0; i < 2; i++)
+        if (thread_micros[i] * 4 < total || thread_micros[i] * 4 > total * 3)
+            return 5;
+    /* The main thread mostly waited. */
+    if (getrusage(RUSAGE_THREAD, &usage) != 0 || micros(&usage) * 4 > total)
+        return 6;
+    if (getrusage(12345, &usage) != -1)
+        return 7;
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("rusage: failed at %d\n", ret);
+        return 1;
+    }
+    puts("rusage: ok");
+    return 0;
+}
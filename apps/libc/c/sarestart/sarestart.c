@@ -0,0 +1,64 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static volatile sig_atomic_t handled;
+
+static void handler(int sig)
+{
+    (void)sig;
+    handled++;
+}
+
+/*
+ * Blocks in read() on a pipe while a child sends SIGUSR1 and only then
+ * writes a byte. Returns what read() returned, with errno in *err.
+ */
+static ssize_t interrupted_read(int flags, int *err)
+{
+    struct sigaction sa;
+    pid_t parent = getpid(), child;
+    int fds[2];
+    ssize_t n;
+    char c;
+
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_handler = handler;
+    sa.sa_flags = flags;
+    sigaction(SIGUSR1, &sa, NULL);
+    handled = 0;
+
+    pipe(fds);
+    child = fork();
+    if (child == 0) {
+        close(fds[0]);
+        usleep(100000);
+        kill(parent, SIGUSR1);
+        usleep(100000);
+        write(fds[1], "x", 1);
+        _exit(0);
+    }
+    close(fds[1]);
+    errno = 0;
+    n = read(fds[0], &c, 1);
+    *err = errno;
+    waitpid(child, NULL, 0);
+    close(fds[0]);
+    return n;
+}
+
+int main()
+{
+    ssize_t n;
+    int err;
+
+    n = interrupted_read(SA_RESTART, &err);
+    printf("sa_restart: %s\n", n == 1 && handled == 1 ? "ok" : "failed");
+
+    n = interrupted_read(0, &err);
+    printf("eintr: %s\n", n == -1 && err == EINTR && handled == 1 ? "ok" : "failed");
+    return 0;
+}
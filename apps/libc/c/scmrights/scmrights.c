@@ -0,0 +1,117 @@
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static int send_fd(int sock, int fd)
+{
+    char buf[CMSG_SPACE(sizeof(int))] = { 0 };
+    struct iovec iov = { .iov_base = "f", .iov_len = 1 };
+    struct msghdr msg = {
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = buf,
+        .msg_controllen = sizeof(buf),
+    };
+    struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+
+    cmsg->cmsg_level = SOL_SOCKET;
+    cmsg->cmsg_type = SCM_RIGHTS;
+    cmsg->cmsg_len = CMSG_LEN(sizeof(int));
+    memcpy(CMSG_DATA(cmsg), &fd, sizeof(int));
+    return sendmsg(sock, &msg, 0) == 1 ? 0 : -1;
+}
+
+static int recv_fd(int sock)
+{
+    char buf[CMSG_SPACE(sizeof(int))], c;
+    struct iovec iov = { .iov_base = &c, .iov_len = 1 };
+    struct msghdr msg = {
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = buf,
+        .msg_controllen = sizeof(buf),
+    };
+    struct cmsghdr *cmsg;
+    int fd;
+
+    if (recvmsg(sock, &msg, MSG_CMSG_CLOEXEC) != 1 || c != 'f')
+        return -1;
+    if (msg.msg_flags & MSG_CTRUNC)
+        return -1;
+    cmsg = CMSG_FIRSTHDR(&msg);
+    if (!cmsg || cmsg->cmsg_level != SOL_SOCKET || cmsg->cmsg_type != SCM_RIGHTS
+        || cmsg->cmsg_len != CMSG_LEN(sizeof(int)))
+        return -1;
+    memcpy(&fd, CMSG_DATA(cmsg), sizeof(int));
+    return fd;
+}
+
+/* Receives a pipe from the parent and reads from it. */
+static int child(int sock)
+{
+    char buf[16] = { 0 };
+    int fd = recv_fd(sock);
+
+    if (fd < 0)
+        return 1;
+    /* It was received with `MSG_CMSG_CLOEXEC`. */
+    if (fcntl(fd, F_GETFD) != FD_CLOEXEC)
+        return 3;
+    if (read(fd, buf, sizeof(buf) - 1) != 5 || strcmp(buf, "hello") != 0)
+        return 2;
+    close(fd);
+    return 0;
+}
+
+static int check(void)
+{
+    int sv[2], fds[2], status;
+    char c;
+    pid_t pid;
+
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, sv) != 0 || pipe(fds) != 0)
+        return 1;
+    pid = fork();
+    if (pid < 0)
+        return 2;
+    if (pid == 0) {
+        close(sv[0]);
+        close(fds[0]);
+        close(fds[1]);
+        _exit(child(sv[1]));
+    }
+    close(sv[1]);
+
+    /* The pipe stays open in the child after the parent closes its end. */
+    if (send_fd(sv[0], fds[0]) != 0)
+        return 3;
+    close(fds[0]);
+    if (write(fds[1], "hello", 5) != 5)
+        return 4;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status))
+        return 5;
+    if (WEXITSTATUS(status) != 0)
+        return 10 + WEXITSTATUS(status);
+
+    /* The child closed its socket on exit. */
+    close(fds[1]);
+    if (read(sv[0], &c, 1) != 0)
+        return 6;
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("scm_rights: failed at %d\n", ret);
+        return 1;
+    }
+    puts("scm_rights: ok");
+    return 0;
+}
@@ -0,0 +1,74 @@
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define PAGES 4
+
+static int check(void)
+{
+    long page = sysconf(_SC_PAGESIZE);
+    volatile int *shared = mmap(NULL, PAGES * page, PROT_READ | PROT_WRITE,
+                                MAP_SHARED | MAP_ANONYMOUS, -1, 0);
+    int *private = mmap(NULL, page, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (shared == MAP_FAILED || private == MAP_FAILED)
+        return __LINE__;
+    // One page is touched before the fork, the others only after it.
+    shared[0] = 1;
+    *private = 1;
+
+    int to_child[2];
+    if (pipe(to_child) != 0)
+        return __LINE__;
+    pid_t pid = fork();
+    if (pid < 0)
+        return __LINE__;
+    if (pid == 0) {
+        char c;
+        if (shared[0] != 1)
+            _exit(1);
+        shared[0] = 2;
+        shared[page / sizeof(int)] = 3;
+        *private = 4;
+        // Wait for the parent's write to a page neither has touched.
+        if (read(to_child[0], &c, 1) != 1)
+            _exit(2);
+        if (shared[2 * page / sizeof(int)] != 5)
+            _exit(3);
+        shared[3 * page / sizeof(int)] = 6;
+        _exit(0);
+    }
+    // The child's writes are seen once it is waiting.
+    while (shared[page / sizeof(int)] != 3)
+        usleep(1000);
+    if (shared[0] != 2 || *private != 1)
+        return __LINE__;
+    shared[2 * page / sizeof(int)] = 5;
+    if (write(to_child[1], "x", 1) != 1)
+        return __LINE__;
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+        return __LINE__;
+    if (shared[3 * page / sizeof(int)] != 6)
+        return __LINE__;
+
+    // Unmapping part of it in the parent leaves the rest as it was.
+    if (munmap((void *)shared, page) != 0)
+        return __LINE__;
+    if (shared[page / sizeof(int)] != 3 || shared[3 * page / sizeof(int)] != 6)
+        return __LINE__;
+    munmap((void *)(shared + page / sizeof(int)), (PAGES - 1) * page);
+    munmap(private, page);
+    return 0;
+}
+
+int main(void)
+{
+    int line = check();
+    if (line) {
+        printf("shm_anon: failed at %d\n", line);
+        return 1;
+    }
+    puts("shm_anon: ok");
+    return 0;
+}
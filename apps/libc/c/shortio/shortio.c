@@ -0,0 +1,63 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/random.h>
+#include <sys/uio.h>
+#include <unistd.h>
+
+#define FILE_NAME "shortio.tmp"
+
+static int check(void)
+{
+    long page = sysconf(_SC_PAGESIZE);
+    // Only the first of the two pages stays mapped.
+    char *buf = mmap(NULL, 2 * page, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (buf == MAP_FAILED || munmap(buf + page, page) != 0)
+        return __LINE__;
+    memset(buf, 'a', page);
+
+    int fd = open(FILE_NAME, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0)
+        return __LINE__;
+    // Writing stops at the end of the first page.
+    if (write(fd, buf, 2 * page) != page)
+        return __LINE__;
+    if (write(fd, buf + page, page) != -1 || errno != EFAULT)
+        return __LINE__;
+    struct iovec iov[2] = {{buf, page}, {buf + page, page}};
+    if (writev(fd, iov, 2) != page)
+        return __LINE__;
+    if (lseek(fd, 0, SEEK_END) != 2 * page)
+        return __LINE__;
+
+    // Reading does too, and only as much of the file is consumed.
+    memset(buf, 0, page);
+    if (lseek(fd, 0, SEEK_SET) != 0 || read(fd, buf, 2 * page) != page)
+        return __LINE__;
+    if (buf[0] != 'a' || buf[page - 1] != 'a' || lseek(fd, 0, SEEK_CUR) != page)
+        return __LINE__;
+    if (read(fd, buf + page, page) != -1 || errno != EFAULT)
+        return __LINE__;
+    if (lseek(fd, 0, SEEK_SET) != 0 || readv(fd, iov, 2) != page)
+        return __LINE__;
+    close(fd);
+    unlink(FILE_NAME);
+
+    if (getrandom(buf + page / 2, page, 0) != page / 2)
+        return __LINE__;
+    munmap(buf, page);
+    return 0;
+}
+
+int main(void)
+{
+    int line = check();
+    if (line) {
+        printf("short_io: failed at %d\n", line);
+        return 1;
+    }
+    puts("short_io: ok");
+    return 0;
+}
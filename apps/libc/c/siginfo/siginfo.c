@@ -0,0 +1,54 @@
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+static char altstack[16384];
+static char *page;
+static int on_altstack;
+
+static void handler(int sig, siginfo_t *info, void *ucontext)
+{
+    char *sp = (char *)&sig;
+
+    (void)ucontext;
+    on_altstack = sp >= altstack && sp < altstack + sizeof(altstack);
+    if (sig != SIGSEGV || info->si_signo != SIGSEGV || info->si_addr != page
+        || info->si_code != SEGV_ACCERR) {
+        printf("siginfo: failed, sig=%d si_addr=%p\n", sig, info->si_addr);
+        _exit(1);
+    }
+    /* Make the page writable so the faulting store succeeds on return. */
+    mprotect(page, 4096, PROT_READ | PROT_WRITE);
+}
+
+int main()
+{
+    stack_t ss;
+    struct sigaction sa;
+
+    page = mmap(NULL, 4096, PROT_NONE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (page == MAP_FAILED) {
+        printf("siginfo: mmap failed\n");
+        return 1;
+    }
+
+    ss.ss_sp = altstack;
+    ss.ss_size = sizeof(altstack);
+    ss.ss_flags = 0;
+    sigaltstack(&ss, NULL);
+
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_sigaction = handler;
+    sa.sa_flags = SA_SIGINFO | SA_ONSTACK;
+    sigemptyset(&sa.sa_mask);
+    sigaction(SIGSEGV, &sa, NULL);
+
+    *(volatile char *)page = 42;
+    if (*(volatile char *)page == 42 && on_altstack)
+        printf("siginfo: ok\n");
+    else
+        printf("siginfo: failed, on_altstack=%d\n", on_altstack);
+    return 0;
+}
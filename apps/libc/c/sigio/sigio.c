@@ -0,0 +1,81 @@
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+static volatile sig_atomic_t got_sigio, got_usr1, usr1_fd, usr1_code;
+
+static void on_sigio(int sig)
+{
+    (void)sig;
+    got_sigio++;
+}
+
+static void on_usr1(int sig, siginfo_t *info, void *uctx)
+{
+    (void)sig;
+    (void)uctx;
+    got_usr1++;
+    usr1_fd = info->si_fd;
+    usr1_code = info->si_code;
+}
+
+static int check(int fds[2])
+{
+    struct sigaction sa;
+    char c;
+
+    signal(SIGIO, on_sigio);
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_sigaction = on_usr1;
+    sa.sa_flags = SA_SIGINFO;
+    sigaction(SIGUSR1, &sa, NULL);
+
+    if (fcntl(fds[0], F_SETOWN, getpid()) != 0 || fcntl(fds[0], F_GETOWN) != getpid())
+        return 1;
+    if (fcntl(fds[0], F_SETFL, O_ASYNC) != 0 || !(fcntl(fds[0], F_GETFL) & O_ASYNC))
+        return 2;
+    /* Setting up does not signal by itself. */
+    if (got_sigio)
+        return 3;
+    write(fds[1], "x", 1);
+    if (got_sigio != 1)
+        return 4;
+    read(fds[0], &c, 1);
+
+    /* A custom signal comes with the fd and the event. */
+    if (fcntl(fds[0], F_SETSIG, SIGUSR1) != 0 || fcntl(fds[0], F_GETSIG) != SIGUSR1)
+        return 5;
+    write(fds[1], "y", 1);
+    if (got_usr1 != 1 || usr1_fd != fds[0] || usr1_code != POLL_IN || got_sigio != 1)
+        return 6;
+    read(fds[0], &c, 1);
+
+    /* Nothing is sent once O_ASYNC is cleared. */
+    if (fcntl(fds[0], F_SETFL, 0) != 0)
+        return 7;
+    write(fds[1], "z", 1);
+    if (got_usr1 != 1 || got_sigio != 1)
+        return 8;
+    return 0;
+}
+
+int main()
+{
+    int fds[2];
+    int ret;
+
+    if (pipe(fds) != 0) {
+        puts("sigio: pipe failed");
+        return 1;
+    }
+    ret = check(fds);
+    if (ret) {
+        printf("sigio: failed at %d\n", ret);
+        return 1;
+    }
+    puts("sigio: ok");
+    return 0;
+}
@@ -0,0 +1,22 @@
+#include <signal.h>
+#include <stdio.h>
+#include <unistd.h>
+
+static void handler(int sig)
+{
+    if (sig == SIGSEGV)
+        printf("sigsegv: handler ok\n");
+    else
+        printf("sigsegv: wrong signal %d\n", sig);
+    fflush(stdout);
+    _exit(0);
+}
+
+int main()
+{
+    signal(SIGSEGV, handler);
+    volatile int *p = NULL;
+    *p = 1;
+    printf("sigsegv: not delivered\n");
+    return 1;
+}
@@ -0,0 +1,78 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <stddef.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <sys/un.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    struct sockaddr_un addr = { .sun_family = AF_UNIX };
+    struct sockaddr_un name;
+    socklen_t len, full;
+    int server, client, conn;
+
+    strcpy(addr.sun_path, "sockname.sock");
+    full = offsetof(struct sockaddr_un, sun_path) + strlen(addr.sun_path) + 1;
+    server = socket(AF_UNIX, SOCK_STREAM, 0);
+    if (server < 0)
+        return 1;
+    if (bind(server, (struct sockaddr *)&addr, sizeof(addr)) != 0)
+        return 2;
+
+    len = sizeof(name);
+    if (getsockname(server, (struct sockaddr *)&name, &len) != 0)
+        return 3;
+    if (len != full || name.sun_family != AF_UNIX || strcmp(name.sun_path, addr.sun_path))
+        return 4;
+
+    /* A short buffer gets a prefix, and the full length back. */
+    memset(&name, 0, sizeof(name));
+    len = offsetof(struct sockaddr_un, sun_path) + 4;
+    if (getsockname(server, (struct sockaddr *)&name, &len) != 0)
+        return 5;
+    if (len != full || strcmp(name.sun_path, "sock"))
+        return 6;
+
+    len = sizeof(name);
+    if (getpeername(server, (struct sockaddr *)&name, &len) != -1 || errno != ENOTCONN)
+        return 7;
+
+    if (listen(server, 1) != 0)
+        return 8;
+    client = socket(AF_UNIX, SOCK_STREAM, 0);
+    if (client < 0 || connect(client, (struct sockaddr *)&addr, sizeof(addr)) != 0)
+        return 9;
+    conn = accept(server, NULL, NULL);
+    if (conn < 0)
+        return 10;
+
+    /* The client sees the address it connected to. */
+    len = sizeof(name);
+    if (getpeername(client, (struct sockaddr *)&name, &len) != 0)
+        return 11;
+    if (len != full || strcmp(name.sun_path, addr.sun_path))
+        return 12;
+    /* The client is not bound, so only the family comes back. */
+    len = sizeof(name);
+    if (getpeername(conn, (struct sockaddr *)&name, &len) != 0 || len != sizeof(sa_family_t))
+        return 13;
+    close(conn);
+    close(client);
+    close(server);
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("sockname: failed at %d\n", ret);
+        return 1;
+    }
+    puts("sockname: ok");
+    return 0;
+}
@@ -0,0 +1,71 @@
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/mount.h>
+#include <sys/stat.h>
+#include <sys/statfs.h>
+#include <sys/statvfs.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    struct statfs st;
+    int fd, ret = 0;
+
+    fd = open("statfs_dev", O_WRONLY | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0)
+        return 1;
+    close(fd);
+    if (mkdirat(AT_FDCWD, "statfs_mnt", 0755) != 0) {
+        ret = 2;
+        goto out_dev;
+    }
+
+    if (statfs("statfs_mnt", &st) != 0 || (st.f_flags & ST_RDONLY)) {
+        ret = 3;
+        goto out_mnt;
+    }
+    if (mount("statfs_dev", "statfs_mnt", "vfat", MS_RDONLY | MS_NOEXEC, NULL) != 0) {
+        ret = 4;
+        goto out_mnt;
+    }
+    if (statfs("statfs_mnt", &st) != 0) {
+        ret = 5;
+        goto out_umount;
+    }
+    if (!(st.f_flags & ST_RDONLY) || !(st.f_flags & ST_NOEXEC) || (st.f_flags & ST_NOSUID)) {
+        ret = 6;
+        goto out_umount;
+    }
+    fd = open("statfs_mnt", O_RDONLY | O_DIRECTORY);
+    if (fd < 0) {
+        ret = 7;
+        goto out_umount;
+    }
+    if (fstatfs(fd, &st) != 0 || !(st.f_flags & ST_RDONLY))
+        ret = 8;
+    close(fd);
+
+out_umount:
+    if (umount2("statfs_mnt", 0) != 0 && !ret)
+        ret = 9;
+    if (!ret && (statfs("statfs_mnt", &st) != 0 || (st.f_flags & ST_RDONLY)))
+        ret = 10;
+out_mnt:
+    unlinkat(AT_FDCWD, "statfs_mnt", AT_REMOVEDIR);
+out_dev:
+    unlinkat(AT_FDCWD, "statfs_dev", 0);
+    return ret;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("statfs_ro: failed at %d\n", ret);
+        return 1;
+    }
+    puts("statfs_ro: ok");
+    return 0;
+}
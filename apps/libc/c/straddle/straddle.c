@@ -0,0 +1,58 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/socket.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define PAGE 4096
+
+static int check(void)
+{
+    char *pages;
+    int *fds;
+    unsigned char *mask;
+
+    pages = mmap(NULL, 2 * PAGE, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (pages == MAP_FAILED)
+        return 1;
+    if (munmap(pages + PAGE, PAGE))
+        return 2;
+
+    /* Both elements in the mapped page. */
+    fds = (int *)(pages + PAGE - 2 * sizeof(int));
+    if (syscall(SYS_pipe2, fds, 0) != 0)
+        return 3;
+    close(fds[0]);
+    close(fds[1]);
+
+    /* The second one is past the end of it. */
+    fds = (int *)(pages + PAGE - sizeof(int));
+    errno = 0;
+    if (syscall(SYS_pipe2, fds, 0) != -1 || errno != EFAULT)
+        return 4;
+    errno = 0;
+    if (syscall(SYS_socketpair, AF_UNIX, SOCK_STREAM, 0, fds) != -1 || errno != EFAULT)
+        return 5;
+
+    /* An array read in is checked the same way. */
+    mask = (unsigned char *)(pages + PAGE - 4);
+    mask[0] = 1;
+    errno = 0;
+    if (syscall(SYS_sched_setaffinity, 0, 8, mask) != -1 || errno != EFAULT)
+        return 6;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+
+    if (failed) {
+        printf("straddle: failed at %d\n", failed);
+        return 1;
+    }
+    puts("straddle: ok");
+    return 0;
+}
@@ -0,0 +1,71 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#define PAGE 4096
+#define BIG_ARG 200000
+
+static int check(const char *self)
+{
+    extern char **environ;
+    char *pages, *edge, *big;
+
+    /* A string running up to the end of a page with nothing after it. */
+    pages = mmap(NULL, 2 * PAGE, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (pages == MAP_FAILED || munmap(pages + PAGE, PAGE))
+        return 1;
+    memset(pages, 'x', PAGE);
+    edge = pages + PAGE - 16;
+    if (open(edge, O_RDONLY) != -1 || errno != EFAULT)
+        return 2;
+    if (mkdir(edge, 0755) != -1 || errno != EFAULT)
+        return 3;
+    {
+        char *args[] = {(char *)self, edge, NULL};
+        if (execve(self, args, environ) != -1 || errno != EFAULT)
+            return 4;
+    }
+    {
+        char *envs[] = {edge, NULL};
+        char *args[] = {(char *)self, NULL};
+        if (execve(self, args, envs) != -1 || errno != EFAULT)
+            return 5;
+    }
+
+    /* An argument longer than an exec takes. */
+    big = malloc(BIG_ARG + 1);
+    if (!big)
+        return 6;
+    memset(big, 'y', BIG_ARG);
+    big[BIG_ARG] = '\0';
+    {
+        char *args[] = {(char *)self, big, NULL};
+        if (execve(self, args, environ) != -1 || errno != E2BIG)
+            return 7;
+    }
+    free(big);
+    munmap(pages, PAGE);
+    return 0;
+}
+
+int main(int argc, char *argv[])
+{
+    int failed;
+
+    if (argc > 1) {
+        puts("str_bound: exec went through");
+        return 1;
+    }
+    failed = check(argv[0]);
+    if (failed) {
+        printf("str_bound: failed at %d\n", failed);
+        return 1;
+    }
+    puts("str_bound: ok");
+    return 0;
+}
@@ -0,0 +1,60 @@
+#include <stdio.h>
+#include <sys/prctl.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    int flag = -1;
+    if (prctl(PR_GET_CHILD_SUBREAPER, &flag) != 0 || flag != 0)
+        return 1;
+    if (prctl(PR_SET_CHILD_SUBREAPER, 1) != 0)
+        return 2;
+    if (prctl(PR_GET_CHILD_SUBREAPER, &flag) != 0 || flag != 1)
+        return 3;
+
+    pid_t self = getpid();
+    pid_t child = fork();
+    if (child < 0)
+        return 4;
+    if (child == 0) {
+        /* The child leaves a grandchild behind, orphaning it. */
+        pid_t grandchild = fork();
+        if (grandchild < 0)
+            _exit(1);
+        if (grandchild == 0) {
+            /* The grandchild is handed to the subreaper, not to init. */
+            for (int i = 0; i < 100000; i++) {
+                if (getppid() == self)
+                    _exit(0);
+                usleep(100);
+            }
+            _exit(2);
+        }
+        _exit(0);
+    }
+
+    int status;
+    if (waitpid(child, &status, 0) != child || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+        return 5;
+    /* The orphan is now a child of this process, so it can be waited for. */
+    pid_t pid = wait(&status);
+    if (pid <= 0 || pid == child)
+        return 6;
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0)
+        return 7;
+    if (wait(&status) != -1)
+        return 8;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("subreaper: failed at %d\n", failed);
+        return 1;
+    }
+    puts("subreaper: ok");
+    return 0;
+}
@@ -0,0 +1,108 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/utsname.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static int write_tunable(const char *path, const char *value)
+{
+    int fd = open(path, O_WRONLY);
+    int ret;
+
+    if (fd < 0)
+        return -1;
+    ret = write(fd, value, strlen(value));
+    close(fd);
+    return ret < 0 ? -1 : 0;
+}
+
+static int read_tunable(const char *path, char *buf, size_t len)
+{
+    int fd = open(path, O_RDONLY);
+    ssize_t n;
+
+    if (fd < 0)
+        return -1;
+    n = read(fd, buf, len - 1);
+    close(fd);
+    if (n < 0)
+        return -1;
+    buf[n] = 0;
+    return 0;
+}
+
+static int check(void)
+{
+    struct utsname uts;
+    char buf[80];
+    int status;
+    pid_t pid;
+
+    if (write_tunable("/proc/sys/kernel/hostname", "starry-test\n") != 0)
+        return 1;
+    if (uname(&uts) != 0 || strcmp(uts.nodename, "starry-test") != 0)
+        return 2;
+    if (read_tunable("/proc/sys/kernel/hostname", buf, sizeof(buf)) != 0
+        || strcmp(buf, "starry-test\n") != 0)
+        return 3;
+
+    /* The limits are the kernel's own, and cannot be set. */
+    errno = 0;
+    if (open("/proc/sys/kernel/pid_max", O_WRONLY) != -1 || errno != EACCES)
+        return 4;
+    if (read_tunable("/proc/sys/kernel/pid_max", buf, sizeof(buf)) != 0
+        || strcmp(buf, "4194304\n") != 0)
+        return 5;
+    errno = 0;
+    if (open("/proc/sys/fs/file-max", O_WRONLY) != -1 || errno != EACCES)
+        return 6;
+    if (read_tunable("/proc/sys/fs/file-max", buf, sizeof(buf)) != 0
+        || strcmp(buf, "9223372036854775807\n") != 0)
+        return 7;
+
+    errno = 0;
+    if (open("/proc/sys/kernel/ostype", O_WRONLY) != -1 || errno != EACCES)
+        return 8;
+    if (read_tunable("/proc/sys/kernel/ostype", buf, sizeof(buf)) != 0
+        || strcmp(buf, "Linux\n") != 0)
+        return 9;
+
+    /* Setting the host name takes root, however it is done. */
+    pid = fork();
+    if (pid < 0)
+        return 10;
+    if (pid == 0) {
+        if (setuid(65534) != 0)
+            _exit(1);
+        errno = 0;
+        if (write_tunable("/proc/sys/kernel/hostname", "other") == 0 || errno != EPERM)
+            _exit(2);
+        errno = 0;
+        if (sethostname("other", 5) == 0 || errno != EPERM)
+            _exit(3);
+        _exit(0);
+    }
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+        return 11;
+    if (uname(&uts) != 0 || strcmp(uts.nodename, "starry-test") != 0)
+        return 12;
+    return 0;
+}
+
+int main()
+{
+    struct utsname uts;
+    int ret;
+
+    uname(&uts);
+    ret = check();
+    sethostname(uts.nodename, strlen(uts.nodename));
+    if (ret) {
+        printf("sysctl: failed at %d\n", ret);
+        return 1;
+    }
+    puts("sysctl: ok");
+    return 0;
+}
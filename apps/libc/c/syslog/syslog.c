@@ -0,0 +1,55 @@
+#include <errno.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/klog.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    int size = klogctl(10, NULL, 0);
+    if (size <= 0)
+        return 1;
+    char *buf = malloc(size);
+    if (!buf)
+        return 2;
+    int len = klogctl(3, buf, size);
+    free(buf);
+    if (len < 0 || len > size)
+        return 3;
+
+    /* Only as much as fits is read. */
+    char small[16];
+    len = klogctl(3, small, sizeof(small));
+    if (len < 0 || len > (int)sizeof(small))
+        return 4;
+    if (klogctl(3, NULL, 16) != -1 || errno != EINVAL)
+        return 5;
+    if (klogctl(99, NULL, 0) != -1 || errno != EINVAL)
+        return 6;
+
+    /* Clearing it takes privilege. */
+    pid_t pid = fork();
+    if (pid < 0)
+        return 7;
+    if (pid == 0) {
+        if (setuid(65534) != 0)
+            _exit(1);
+        _exit(klogctl(5, NULL, 0) == -1 && errno == EPERM ? 0 : 2);
+    }
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+        return 8;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("syslog: failed at %d\n", failed);
+        return 1;
+    }
+    puts("syslog: ok");
+    return 0;
+}
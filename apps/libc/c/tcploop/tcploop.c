@@ -0,0 +1,102 @@
+#define _GNU_SOURCE
+#include <arpa/inet.h>
+#include <errno.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define PORT 5555
+
+static int echo(int server)
+{
+    char buf[64];
+    ssize_t len;
+    int conn;
+
+    conn = accept(server, NULL, NULL);
+    if (conn < 0)
+        return 1;
+    while ((len = read(conn, buf, sizeof(buf))) > 0)
+        if (write(conn, buf, len) != len)
+            return 1;
+    close(conn);
+    return len < 0;
+}
+
+static int check(void)
+{
+    struct sockaddr_in addr = {
+        .sin_family = AF_INET,
+        .sin_port = htons(PORT),
+        .sin_addr.s_addr = htonl(INADDR_LOOPBACK),
+    };
+    struct sockaddr_in name;
+    socklen_t len = sizeof(name);
+    int server, other, client, status;
+    char buf[16];
+    pid_t pid;
+
+    client = socket(AF_INET, SOCK_STREAM, 0);
+    if (client < 0)
+        return 1;
+    if (connect(client, (struct sockaddr *)&addr, sizeof(addr)) != -1 || errno != ECONNREFUSED)
+        return 2;
+    close(client);
+
+    server = socket(AF_INET, SOCK_STREAM, IPPROTO_TCP);
+    if (server < 0)
+        return 3;
+    if (bind(server, (struct sockaddr *)&addr, sizeof(addr)) != 0)
+        return 4;
+    other = socket(AF_INET, SOCK_STREAM, 0);
+    if (bind(other, (struct sockaddr *)&addr, sizeof(addr)) != -1 || errno != EADDRINUSE)
+        return 5;
+    close(other);
+    if (listen(server, 1) != 0)
+        return 6;
+
+    pid = fork();
+    if (pid < 0)
+        return 7;
+    if (pid == 0)
+        _exit(echo(server));
+    close(server);
+
+    client = socket(AF_INET, SOCK_STREAM, 0);
+    if (connect(client, (struct sockaddr *)&addr, sizeof(addr)) != 0)
+        return 8;
+    if (getpeername(client, (struct sockaddr *)&name, &len) != 0 || len != sizeof(name)
+        || name.sin_port != htons(PORT) || name.sin_addr.s_addr != htonl(INADDR_LOOPBACK))
+        return 9;
+    /* The client is bound to an ephemeral port. */
+    len = sizeof(name);
+    if (getsockname(client, (struct sockaddr *)&name, &len) != 0 || name.sin_port == 0)
+        return 10;
+
+    if (write(client, "hello", 5) != 5)
+        return 11;
+    if (read(client, buf, sizeof(buf)) != 5 || memcmp(buf, "hello", 5) != 0)
+        return 12;
+    shutdown(client, SHUT_WR);
+    if (read(client, buf, sizeof(buf)) != 0)
+        return 13;
+    close(client);
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+        return 14;
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("tcp_loopback: failed at %d\n", ret);
+        return 1;
+    }
+    puts("tcp_loopback: ok");
+    return 0;
+}
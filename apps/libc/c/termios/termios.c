@@ -0,0 +1,81 @@
+#include <stdio.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <termios.h>
+#include <unistd.h>
+
+/* Types `s` on the terminal. */
+static void type(const char *s)
+{
+    for (; *s; s++)
+        ioctl(STDIN_FILENO, TIOCSTI, s);
+}
+
+static int check_raw(const struct termios *orig)
+{
+    struct termios raw = *orig;
+    char c;
+
+    cfmakeraw(&raw);
+    raw.c_cc[VMIN] = 1;
+    raw.c_cc[VTIME] = 0;
+    if (tcsetattr(STDIN_FILENO, TCSANOW, &raw) != 0)
+        return 1;
+    /* Without a newline, and one byte at a time. */
+    type("ab");
+    if (read(STDIN_FILENO, &c, 1) != 1 || c != 'a')
+        return 2;
+    if (read(STDIN_FILENO, &c, 1) != 1 || c != 'b')
+        return 3;
+    /* With no minimum and no timeout, a read does not wait for input. */
+    raw.c_cc[VMIN] = 0;
+    if (tcsetattr(STDIN_FILENO, TCSANOW, &raw) != 0)
+        return 4;
+    if (read(STDIN_FILENO, &c, 1) != 0)
+        return 5;
+    return 0;
+}
+
+static int check_canonical(const struct termios *orig)
+{
+    struct termios cooked = *orig;
+    char buf[16];
+
+    cooked.c_lflag |= ICANON;
+    cooked.c_lflag &= ~ECHO;
+    if (tcsetattr(STDIN_FILENO, TCSAFLUSH, &cooked) != 0)
+        return 1;
+    /* Erase and kill edit the line before it is read. */
+    type("ab\x7f" "c\n" "xyz\x15" "ok\n");
+    if (read(STDIN_FILENO, buf, sizeof(buf)) != 3 || memcmp(buf, "ac\n", 3) != 0)
+        return 2;
+    if (read(STDIN_FILENO, buf, sizeof(buf)) != 3 || memcmp(buf, "ok\n", 3) != 0)
+        return 3;
+    return 0;
+}
+
+int main()
+{
+    struct termios orig;
+    int ret;
+
+    if (tcgetattr(STDIN_FILENO, &orig) != 0 || !(orig.c_lflag & ICANON)) {
+        puts("termios: tcgetattr failed");
+        return 1;
+    }
+    ret = check_raw(&orig);
+    tcsetattr(STDIN_FILENO, TCSANOW, &orig);
+    if (ret) {
+        printf("termios: raw failed at %d\n", ret);
+        return 1;
+    }
+    puts("termios: raw ok");
+    ret = check_canonical(&orig);
+    tcsetattr(STDIN_FILENO, TCSANOW, &orig);
+    if (ret) {
+        printf("termios: canonical failed at %d\n", ret);
+        return 1;
+    }
+    puts("termios: canonical ok");
+    return 0;
+}
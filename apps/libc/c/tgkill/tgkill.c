@@ -0,0 +1,108 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <sched.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static char thread_stack[65536] __attribute__((aligned(16)));
+static char sender_stack[65536] __attribute__((aligned(16)));
+
+static volatile pid_t sender;
+
+static void handler(int sig, siginfo_t *info, void *ctx)
+{
+    sender = info->si_pid;
+}
+
+/* Signals the main thread of its process, with system calls made directly. */
+static int sender_main(void *arg)
+{
+    long pid = (long)arg;
+
+    syscall(SYS_tgkill, pid, pid, SIGUSR1);
+    syscall(SYS_exit, 0);
+    return 0;
+}
+
+/* Only makes system calls directly, as it has no TLS of its own. */
+static int thread_main(void *arg)
+{
+    volatile pid_t *tid = arg;
+
+    *tid = syscall(SYS_gettid);
+    for (;;)
+        syscall(SYS_sched_yield);
+    return 0;
+}
+
+static int check(void)
+{
+    volatile pid_t *tid = mmap(NULL, sizeof(*tid), PROT_READ | PROT_WRITE,
+                               MAP_SHARED | MAP_ANONYMOUS, -1, 0);
+    int status;
+    pid_t pid;
+
+    if (tid == MAP_FAILED)
+        return __LINE__;
+
+    /* The sender is named by its process, not its thread. */
+    struct sigaction sa = { .sa_sigaction = handler, .sa_flags = SA_SIGINFO };
+    if (sigaction(SIGUSR1, &sa, NULL) != 0)
+        return __LINE__;
+    if (clone(sender_main, sender_stack + sizeof(sender_stack),
+              CLONE_VM | CLONE_SIGHAND | CLONE_THREAD, (void *)(long)getpid()) < 0)
+        return __LINE__;
+    while (sender == 0)
+        sched_yield();
+    if (sender != getpid())
+        return __LINE__;
+    pid = fork();
+    if (pid < 0)
+        return __LINE__;
+    if (pid == 0) {
+        if (clone(thread_main, thread_stack + sizeof(thread_stack),
+                  CLONE_VM | CLONE_SIGHAND | CLONE_THREAD, (void *)tid) < 0)
+            _exit(1);
+        for (;;)
+            sched_yield();
+    }
+    while (*tid == 0)
+        sched_yield();
+    if (*tid == pid)
+        return __LINE__;
+
+    if (syscall(SYS_tgkill, getpid(), *tid, 0) != -1 || errno != ESRCH)
+        return __LINE__;
+    if (syscall(SYS_tgkill, pid, *tid, 1000) != -1 || errno != EINVAL)
+        return __LINE__;
+    if (syscall(SYS_tkill, 0, SIGTERM) != -1 || errno != EINVAL)
+        return __LINE__;
+    if (syscall(SYS_tkill, 0x3fffffff, 0) != -1 || errno != ESRCH)
+        return __LINE__;
+    if (syscall(SYS_tgkill, pid, *tid, 0) != 0 || syscall(SYS_tkill, *tid, 0) != 0)
+        return __LINE__;
+
+    /* A fatal signal to one thread takes the whole process down. */
+    if (syscall(SYS_tkill, *tid, SIGTERM) != 0)
+        return __LINE__;
+    if (waitpid(pid, &status, 0) != pid || !WIFSIGNALED(status) || WTERMSIG(status) != SIGTERM)
+        return __LINE__;
+    munmap((void *)tid, sizeof(*tid));
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+
+    if (failed) {
+        printf("tgkill: failed at %d\n", failed);
+        return 1;
+    }
+    puts("tgkill: ok");
+    return 0;
+}
@@ -0,0 +1,74 @@
+#include <pthread.h>
+#include <stdio.h>
+#include <time.h>
+#include <unistd.h>
+
+#define BURN_NANOS 200000000LL
+
+static long long clock_nanos(clockid_t clock)
+{
+    struct timespec ts;
+    if (clock_gettime(clock, &ts) != 0)
+        return -1;
+    return ts.tv_sec * 1000000000LL + ts.tv_nsec;
+}
+
+/* How much CPU time each thread used over its run. */
+static long long busy_used, idle_used;
+
+static void *busy_main(void *arg)
+{
+    (void)arg;
+    long long start = clock_nanos(CLOCK_THREAD_CPUTIME_ID);
+    long long end = clock_nanos(CLOCK_MONOTONIC) + BURN_NANOS;
+    while (clock_nanos(CLOCK_MONOTONIC) < end)
+        ;
+    busy_used = clock_nanos(CLOCK_THREAD_CPUTIME_ID) - start;
+    return NULL;
+}
+
+static void *idle_main(void *arg)
+{
+    (void)arg;
+    long long start = clock_nanos(CLOCK_THREAD_CPUTIME_ID);
+    usleep(BURN_NANOS / 1000);
+    idle_used = clock_nanos(CLOCK_THREAD_CPUTIME_ID) - start;
+    return NULL;
+}
+
+static int check(void)
+{
+    pthread_t busy, idle;
+    long long process_start = clock_nanos(CLOCK_PROCESS_CPUTIME_ID);
+    if (process_start < 0)
+        return 1;
+    if (pthread_create(&busy, NULL, busy_main, NULL) != 0)
+        return 2;
+    if (pthread_create(&idle, NULL, idle_main, NULL) != 0)
+        return 3;
+    /* The process clock counts the busy thread while it runs. */
+    usleep(BURN_NANOS / 2000);
+    long long process_now = clock_nanos(CLOCK_PROCESS_CPUTIME_ID);
+    pthread_join(busy, NULL);
+    pthread_join(idle, NULL);
+
+    /* Spinning takes at least part of the wall time, even when sharing a CPU. */
+    if (busy_used < BURN_NANOS / 10)
+        return 4;
+    if (idle_used < 0 || idle_used * 4 > busy_used)
+        return 5;
+    if (process_now <= process_start)
+        return 6;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("thread_cputime: failed at %d\n", failed);
+        return 1;
+    }
+    puts("thread_cputime: ok");
+    return 0;
+}
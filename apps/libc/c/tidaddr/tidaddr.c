@@ -0,0 +1,40 @@
+#include <stdio.h>
+#include <sys/prctl.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#ifndef PR_GET_TID_ADDRESS
+#define PR_GET_TID_ADDRESS 40
+#endif
+
+static int tid_word;
+
+static int check(void)
+{
+    int *address = NULL;
+
+    if (syscall(SYS_set_tid_address, &tid_word) != syscall(SYS_gettid))
+        return 1;
+    if (prctl(PR_GET_TID_ADDRESS, &address) != 0 || address != &tid_word)
+        return 2;
+    if (syscall(SYS_set_tid_address, NULL) < 0)
+        return 3;
+    if (prctl(PR_GET_TID_ADDRESS, &address) != 0 || address != NULL)
+        return 4;
+    /* A bad pointer to store it to. */
+    if (prctl(PR_GET_TID_ADDRESS, (void *)1) != -1)
+        return 5;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+
+    if (failed) {
+        printf("tid_address: failed at %d\n", failed);
+        return 1;
+    }
+    puts("tid_address: ok");
+    return 0;
+}
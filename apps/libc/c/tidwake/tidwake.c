@@ -0,0 +1,92 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <linux/futex.h>
+#include <sched.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/syscall.h>
+#include <time.h>
+#include <unistd.h>
+
+#define THREAD_FLAGS (CLONE_VM | CLONE_SIGHAND | CLONE_THREAD)
+
+static char thread_stack[65536] __attribute__((aligned(16)));
+
+static volatile pid_t tid = -1;
+static void *unmapped;
+static volatile int stray_done;
+
+/* The threads only make system calls directly, as they share the TLS of the
+ * main thread. */
+static int sleeper(void *arg)
+{
+    struct timespec nap = {0, 100 * 1000 * 1000};
+
+    (void)arg;
+    syscall(SYS_nanosleep, &nap, NULL);
+    syscall(SYS_exit, 0);
+    return 0;
+}
+
+/* Leaves a slot that cannot be written to be cleared on exit. */
+static int stray(void *arg)
+{
+    (void)arg;
+    syscall(SYS_set_tid_address, unmapped);
+    __atomic_store_n(&stray_done, 1, __ATOMIC_RELEASE);
+    syscall(SYS_exit, 0);
+    return 0;
+}
+
+static double now(void)
+{
+    struct timespec ts;
+
+    clock_gettime(CLOCK_MONOTONIC, &ts);
+    return ts.tv_sec + ts.tv_nsec / 1e9;
+}
+
+static int check(void)
+{
+    struct timespec timeout = {5, 0};
+    double start;
+    pid_t seen;
+    long ret;
+
+    /* Joins the thread the way pthread_join does. */
+    start = now();
+    if (clone(sleeper, thread_stack + sizeof(thread_stack),
+              THREAD_FLAGS | CLONE_CHILD_SETTID | CLONE_CHILD_CLEARTID, NULL, NULL, NULL,
+              &tid) < 0)
+        return 1;
+    while ((seen = tid) != 0) {
+        ret = syscall(SYS_futex, &tid, FUTEX_WAIT, seen, &timeout, NULL, 0);
+        if (ret == -1 && errno == ETIMEDOUT)
+            return 2;
+    }
+    if (now() - start > 2)
+        return 3;
+
+    /* A slot that is gone is skipped. */
+    unmapped = mmap(NULL, 4096, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (unmapped == MAP_FAILED || munmap(unmapped, 4096))
+        return 4;
+    if (clone(stray, thread_stack + sizeof(thread_stack), THREAD_FLAGS, NULL) < 0)
+        return 5;
+    while (!__atomic_load_n(&stray_done, __ATOMIC_ACQUIRE))
+        sched_yield();
+    usleep(50 * 1000);
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+
+    if (failed) {
+        printf("tid_wake: failed at %d\n", failed);
+        return 1;
+    }
+    puts("tid_wake: ok");
+    return 0;
+}
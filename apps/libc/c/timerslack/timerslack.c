@@ -0,0 +1,53 @@
+#include <stdio.h>
+#include <sys/prctl.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+static long elapsed_ns(const struct timespec *start)
+{
+    struct timespec now;
+    clock_gettime(CLOCK_MONOTONIC, &now);
+    return (now.tv_sec - start->tv_sec) * 1000000000L + now.tv_nsec - start->tv_nsec;
+}
+
+static int check(void)
+{
+    if (prctl(PR_GET_TIMERSLACK) != 50000)
+        return 1;
+    if (prctl(PR_SET_TIMERSLACK, 2000000) != 0 || prctl(PR_GET_TIMERSLACK) != 2000000)
+        return 2;
+
+    /* A sleep lasts at least as long as asked, and may run over by the slack. */
+    struct timespec start;
+    struct timespec req = {.tv_sec = 0, .tv_nsec = 1000000};
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    if (nanosleep(&req, NULL) != 0 || elapsed_ns(&start) < 1000000)
+        return 3;
+
+    /* Children inherit it. */
+    pid_t pid = fork();
+    if (pid < 0)
+        return 4;
+    if (pid == 0)
+        _exit(prctl(PR_GET_TIMERSLACK) == 2000000 ? 0 : 1);
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+        return 5;
+
+    /* Zero puts it back to the default. */
+    if (prctl(PR_SET_TIMERSLACK, 0) != 0 || prctl(PR_GET_TIMERSLACK) != 50000)
+        return 6;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("timerslack: failed at %d\n", failed);
+        return 1;
+    }
+    puts("timerslack: ok");
+    return 0;
+}
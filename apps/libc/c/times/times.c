@@ -0,0 +1,54 @@
+#include <stdio.h>
+#include <sys/auxv.h>
+#include <sys/times.h>
+#include <time.h>
+#include <unistd.h>
+
+static double seconds(clockid_t clock)
+{
+    struct timespec ts;
+
+    clock_gettime(clock, &ts);
+    return ts.tv_sec + ts.tv_nsec / 1e9;
+}
+
+static int check(void)
+{
+    long hz = sysconf(_SC_CLK_TCK);
+    struct tms start, end;
+    clock_t start_ticks, end_ticks;
+    double start_wall, wall, cpu;
+    volatile unsigned long spin = 0;
+
+    /* libc takes the rate from the aux vector. */
+    if (getauxval(AT_CLKTCK) != (unsigned long)hz || hz != 100)
+        return 1;
+    start_wall = seconds(CLOCK_MONOTONIC);
+    start_ticks = times(&start);
+    if (start_ticks == (clock_t)-1)
+        return 2;
+    while (seconds(CLOCK_MONOTONIC) - start_wall < 0.5)
+        spin++;
+    end_ticks = times(&end);
+    wall = seconds(CLOCK_MONOTONIC) - start_wall;
+    cpu = (double)(end.tms_utime + end.tms_stime - start.tms_utime - start.tms_stime) / hz;
+    /* The return value counts wall-clock ticks, and the times CPU time. */
+    if ((double)(end_ticks - start_ticks) / hz < wall - 0.05 ||
+        (double)(end_ticks - start_ticks) / hz > wall + 0.05)
+        return 3;
+    if (cpu < wall * 0.5 || cpu > wall + 0.05)
+        return 4;
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("times: failed at %d\n", ret);
+        return 1;
+    }
+    puts("times: ok");
+    return 0;
+}
@@ -0,0 +1,88 @@
+#define _GNU_SOURCE
+#include <sched.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/resource.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define PAGE 4096
+
+static char child_stack[65536] __attribute__((aligned(16)));
+
+static int *page;
+/* How many times the child has written to the page. */
+static volatile long writes;
+
+/* Pins the caller to `cpu` if there is one. */
+static void pin(int cpu)
+{
+    cpu_set_t set;
+
+    if (cpu >= sysconf(_SC_NPROCESSORS_ONLN))
+        return;
+    CPU_ZERO(&set);
+    CPU_SET(cpu, &set);
+    syscall(SYS_sched_setaffinity, 0, sizeof(set), &set);
+}
+
+/* Writes to the page without ever trapping, until it faults. */
+static int child_main(void *arg)
+{
+    (void)arg;
+    pin(1);
+    for (;;) {
+        *(volatile int *)page = 1;
+        writes++;
+    }
+    return 0;
+}
+
+static int check(void)
+{
+    struct rlimit no_core = {0, 0};
+    long before, after;
+    int status;
+    pid_t pid;
+
+    setrlimit(RLIMIT_CORE, &no_core);
+    pin(0);
+    page = mmap(NULL, PAGE, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (page == MAP_FAILED)
+        return __LINE__;
+    pid = clone(child_main, child_stack + sizeof(child_stack), CLONE_VM | SIGCHLD, NULL);
+    if (pid < 0)
+        return __LINE__;
+    while (writes < 1000)
+        ;
+
+    /* Once munmap returns, the child cannot write to the page any more on
+     * any CPU, so it gets at most one more write counted. */
+    if (munmap(page, PAGE))
+        return __LINE__;
+    before = writes;
+    for (volatile int i = 0; i < 1000000; i++)
+        ;
+    after = writes;
+    if (after - before > 1)
+        return __LINE__;
+    if (waitpid(pid, &status, 0) != pid)
+        return __LINE__;
+    if (!WIFSIGNALED(status) || WTERMSIG(status) != SIGSEGV)
+        return __LINE__;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+
+    if (failed) {
+        printf("tlb_shootdown: failed at %d\n", failed);
+        return 1;
+    }
+    puts("tlb_shootdown: ok");
+    return 0;
+}
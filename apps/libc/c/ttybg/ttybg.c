@@ -0,0 +1,80 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/wait.h>
+#include <termios.h>
+#include <unistd.h>
+
+static volatile sig_atomic_t got;
+
+static void on_signal(int sig)
+{
+    got = sig;
+}
+
+/* Catches `sig` without SA_RESTART, so the interrupted call fails. */
+static void catch(int sig)
+{
+    struct sigaction sa = { .sa_handler = on_signal };
+
+    sigemptyset(&sa.sa_mask);
+    sigaction(sig, &sa, NULL);
+}
+
+/* Touches the terminal from a background process group. */
+static int child(void)
+{
+    struct termios t;
+    char c;
+
+    if (setpgid(0, 0) != 0)
+        return 1;
+
+    catch(SIGTTIN);
+    if (read(STDIN_FILENO, &c, 1) != -1 || errno != EINTR || got != SIGTTIN)
+        return 2;
+    signal(SIGTTIN, SIG_IGN);
+    if (read(STDIN_FILENO, &c, 1) != -1 || errno != EIO)
+        return 3;
+
+    /* The parent set TOSTOP, so writing is stopped too. */
+    got = 0;
+    catch(SIGTTOU);
+    if (write(STDOUT_FILENO, "x", 1) != -1 || errno != EINTR || got != SIGTTOU)
+        return 4;
+    got = 0;
+    tcgetattr(STDIN_FILENO, &t);
+    if (tcsetattr(STDIN_FILENO, TCSANOW, &t) != -1 || errno != EINTR || got != SIGTTOU)
+        return 5;
+
+    /* Ignoring SIGTTOU lets the process go on. */
+    signal(SIGTTOU, SIG_IGN);
+    if (tcsetattr(STDIN_FILENO, TCSANOW, &t) != 0)
+        return 6;
+    return 0;
+}
+
+int main()
+{
+    struct termios orig, tostop;
+    int status;
+    pid_t pid;
+
+    tcgetattr(STDIN_FILENO, &orig);
+    tostop = orig;
+    tostop.c_lflag |= TOSTOP;
+    tcsetattr(STDIN_FILENO, TCSANOW, &tostop);
+
+    pid = fork();
+    if (pid == 0)
+        _exit(child());
+    waitpid(pid, &status, 0);
+    tcsetattr(STDIN_FILENO, TCSANOW, &orig);
+
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        printf("tty_bg: failed at %d\n", WEXITSTATUS(status));
+        return 1;
+    }
+    puts("tty_bg: ok");
+    return 0;
+}
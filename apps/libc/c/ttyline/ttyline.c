@@ -0,0 +1,67 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <termios.h>
+#include <unistd.h>
+
+static void type(int fd, const char *s)
+{
+    for (; *s; s++)
+        ioctl(fd, TIOCSTI, s);
+}
+
+static int check(int fd, const struct termios *orig)
+{
+    struct termios t = *orig;
+    char buf[16];
+
+    /* A line is only readable once it is complete. */
+    t.c_lflag &= ~ECHO;
+    if (tcsetattr(fd, TCSANOW, &t) != 0)
+        return 1;
+    type(fd, "abc");
+    if (read(fd, buf, sizeof(buf)) != -1 || errno != EAGAIN)
+        return 2;
+    type(fd, "\x7f" "d\n");
+    if (read(fd, buf, sizeof(buf)) != 4 || memcmp(buf, "abd\n", 4) != 0)
+        return 3;
+
+    /* Raw mode hands over each keystroke as it arrives. */
+    t.c_lflag &= ~ICANON;
+    t.c_cc[VMIN] = 1;
+    t.c_cc[VTIME] = 0;
+    if (tcsetattr(fd, TCSANOW, &t) != 0)
+        return 4;
+    type(fd, "x");
+    if (read(fd, buf, sizeof(buf)) != 1 || buf[0] != 'x')
+        return 5;
+    type(fd, "\x7f");
+    if (read(fd, buf, sizeof(buf)) != 1 || buf[0] != 0x7f)
+        return 6;
+    if (read(fd, buf, sizeof(buf)) != -1 || errno != EAGAIN)
+        return 7;
+    return 0;
+}
+
+int main()
+{
+    struct termios orig;
+    int fd, ret;
+
+    fd = open("/dev/tty", O_RDWR | O_NONBLOCK);
+    if (fd < 0 || tcgetattr(fd, &orig) != 0) {
+        puts("tty_line: open failed");
+        return 1;
+    }
+    ret = check(fd, &orig);
+    tcsetattr(fd, TCSANOW, &orig);
+    close(fd);
+    if (ret) {
+        printf("tty_line: failed at %d\n", ret);
+        return 1;
+    }
+    puts("tty_line: ok");
+    return 0;
+}
@@ -0,0 +1,80 @@
+#include <signal.h>
+#include <stdio.h>
+#include <sys/ioctl.h>
+#include <sys/wait.h>
+#include <termios.h>
+#include <time.h>
+#include <unistd.h>
+
+static volatile sig_atomic_t got_sigint;
+
+static void on_sigint(int sig)
+{
+    (void)sig;
+    got_sigint = 1;
+}
+
+/* Waits in its own process group for the SIGINT from the terminal. */
+static int child(int ready)
+{
+    struct timespec ts = { .tv_sec = 0, .tv_nsec = 10000000 };
+
+    signal(SIGINT, on_sigint);
+    if (setpgid(0, 0) != 0)
+        return 1;
+    write(ready, "", 1);
+    for (int i = 0; i < 500 && !got_sigint; i++)
+        nanosleep(&ts, NULL);
+    return got_sigint ? 0 : 2;
+}
+
+int main()
+{
+    struct termios orig, noecho;
+    int fds[2], status;
+    pid_t pid;
+    char c = 0x03;
+
+    signal(SIGINT, on_sigint);
+    /* Like a shell, take the terminal back from the background unstopped. */
+    signal(SIGTTOU, SIG_IGN);
+    tcgetattr(STDIN_FILENO, &orig);
+    if (!(orig.c_lflag & ISIG) || orig.c_cc[VINTR] != c) {
+        puts("isig: bad defaults");
+        return 1;
+    }
+    /* Keep the ^C echo out of the output. */
+    noecho = orig;
+    noecho.c_lflag &= ~ECHO;
+    tcsetattr(STDIN_FILENO, TCSANOW, &noecho);
+
+    pipe(fds);
+    pid = fork();
+    if (pid == 0)
+        _exit(child(fds[1]));
+    setpgid(pid, pid);
+    read(fds[0], &c, 1);
+
+    /* Only the foreground process group gets the signal. */
+    if (ioctl(STDIN_FILENO, TIOCSPGRP, &pid) != 0) {
+        puts("isig: TIOCSPGRP failed");
+        return 1;
+    }
+    c = 0x03;
+    ioctl(STDIN_FILENO, TIOCSTI, &c);
+    waitpid(pid, &status, 0);
+    pid = getpgrp();
+    ioctl(STDIN_FILENO, TIOCSPGRP, &pid);
+    tcsetattr(STDIN_FILENO, TCSANOW, &orig);
+
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 0) {
+        puts("isig: foreground group missed SIGINT");
+        return 1;
+    }
+    if (got_sigint) {
+        puts("isig: background process got SIGINT");
+        return 1;
+    }
+    puts("isig: ok");
+    return 0;
+}
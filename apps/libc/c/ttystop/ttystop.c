@@ -0,0 +1,91 @@
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/ioctl.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+/* Reads from the terminal, starting in a background process group. */
+static int reader(int tty)
+{
+    char buf[8];
+
+    setpgid(0, 0);
+    if (read(tty, buf, sizeof(buf)) != 2 || buf[0] != 'x')
+        return 1;
+    return 0;
+}
+
+/* Leads a session with the slave of `master` as its terminal. */
+static int leader(int master, const char *name)
+{
+    int tty, status;
+    pid_t pid;
+
+    if (setsid() < 0)
+        return 1;
+    tty = open(name, O_RDWR);
+    if (tty < 0 || ioctl(tty, TIOCSCTTY, 0) != 0)
+        return 2;
+    pid = fork();
+    if (pid < 0)
+        return 3;
+    if (pid == 0)
+        _exit(reader(tty));
+    setpgid(pid, pid);
+
+    /* Reading in the background with SIGTTIN left alone stops it. */
+    if (waitpid(pid, &status, WUNTRACED) != pid || !WIFSTOPPED(status)
+        || WSTOPSIG(status) != SIGTTIN)
+        return 4;
+
+    /* Brought to the foreground and continued, it reads what is typed. */
+    if (tcsetpgrp(tty, pid) != 0 || kill(pid, SIGCONT) != 0)
+        return 5;
+    if (waitpid(pid, &status, WCONTINUED) != pid || !WIFCONTINUED(status))
+        return 6;
+    if (write(master, "x\n", 2) != 2)
+        return 7;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 0)
+        return 8;
+    return 0;
+}
+
+static int check(void)
+{
+    int master, status;
+    char *name;
+    pid_t pid;
+
+    master = posix_openpt(O_RDWR | O_NOCTTY);
+    if (master < 0 || grantpt(master) != 0 || unlockpt(master) != 0)
+        return 1;
+    name = ptsname(master);
+    if (!name)
+        return 2;
+    pid = fork();
+    if (pid < 0)
+        return 3;
+    if (pid == 0)
+        _exit(leader(master, name));
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status))
+        return 4;
+    if (WEXITSTATUS(status) != 0)
+        return 10 + WEXITSTATUS(status);
+    close(master);
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("tty_stop: failed at %d\n", ret);
+        return 1;
+    }
+    puts("tty_stop: ok");
+    return 0;
+}
@@ -0,0 +1,111 @@
+#include <arpa/inet.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+#define PORT_A 6000
+#define PORT_B 6001
+#define PORT_UNBOUND 6002
+
+static int bound_socket(int port)
+{
+    struct sockaddr_in addr = {
+        .sin_family = AF_INET,
+        .sin_port = htons(port),
+        .sin_addr.s_addr = htonl(INADDR_LOOPBACK),
+    };
+    int fd = socket(AF_INET, SOCK_DGRAM, 0);
+
+    if (fd < 0)
+        return -1;
+    if (bind(fd, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+        close(fd);
+        return -1;
+    }
+    return fd;
+}
+
+static int check(void)
+{
+    struct sockaddr_in to = {
+        .sin_family = AF_INET,
+        .sin_port = htons(PORT_B),
+        .sin_addr.s_addr = htonl(INADDR_LOOPBACK),
+    };
+    struct sockaddr_in nowhere = to;
+    struct sockaddr_in from;
+    socklen_t len;
+    char buf[16];
+    int a, b, c;
+
+    a = bound_socket(PORT_A);
+    b = bound_socket(PORT_B);
+    if (a < 0 || b < 0)
+        return 1;
+    if (bound_socket(PORT_B) != -1 || errno != EADDRINUSE)
+        return 2;
+
+    /* Nothing has been sent yet. */
+    if (recvfrom(b, buf, sizeof(buf), MSG_DONTWAIT, NULL, NULL) != -1 || errno != EAGAIN)
+        return 3;
+
+    if (sendto(a, "hello", 5, 0, (struct sockaddr *)&to, sizeof(to)) != 5)
+        return 4;
+    if (sendto(a, "world!", 6, 0, (struct sockaddr *)&to, sizeof(to)) != 6)
+        return 5;
+
+    /* Each datagram is received on its own, with where it came from. */
+    len = sizeof(from);
+    memset(&from, 0, sizeof(from));
+    if (recvfrom(b, buf, sizeof(buf), 0, (struct sockaddr *)&from, &len) != 5)
+        return 6;
+    if (memcmp(buf, "hello", 5) != 0)
+        return 7;
+    if (len != sizeof(from) || from.sin_family != AF_INET || ntohs(from.sin_port) != PORT_A)
+        return 8;
+    if (from.sin_addr.s_addr != htonl(INADDR_LOOPBACK))
+        return 9;
+    if (recvfrom(b, buf, sizeof(buf), 0, NULL, NULL) != 6 || memcmp(buf, "world!", 6) != 0)
+        return 10;
+
+    /* An unbound sender is bound to the loopback address it sends from. */
+    c = socket(AF_INET, SOCK_DGRAM, 0);
+    if (c < 0 || sendto(c, "hi", 2, 0, (struct sockaddr *)&to, sizeof(to)) != 2)
+        return 11;
+    len = sizeof(from);
+    memset(&from, 0, sizeof(from));
+    if (recvfrom(b, buf, sizeof(buf), 0, (struct sockaddr *)&from, &len) != 2
+        || from.sin_addr.s_addr != htonl(INADDR_LOOPBACK) || from.sin_port == 0)
+        return 12;
+    close(c);
+
+    /* No one is bound to the port, so the datagram is dropped. */
+    nowhere.sin_port = htons(PORT_UNBOUND);
+    if (sendto(a, "lost", 4, 0, (struct sockaddr *)&nowhere, sizeof(nowhere)) != 4)
+        return 13;
+
+    if (fcntl(b, F_SETFL, O_NONBLOCK) < 0)
+        return 14;
+    if (recvfrom(b, buf, sizeof(buf), 0, NULL, NULL) != -1 || errno != EAGAIN)
+        return 15;
+
+    close(a);
+    close(b);
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("udp_loopback: failed at %d\n", ret);
+        return 1;
+    }
+    puts("udp_loopback: ok");
+    return 0;
+}
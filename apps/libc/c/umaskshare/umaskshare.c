@@ -0,0 +1,76 @@
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <sched.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static char thread_stack[65536] __attribute__((aligned(16)));
+static volatile int thread_fd;
+static volatile int thread_done;
+
+/* Only makes system calls directly, as it shares the TLS of its parent. */
+static int thread_main(void *arg)
+{
+    syscall(SYS_umask, 077);
+    thread_fd = syscall(SYS_openat, AT_FDCWD, "/", O_RDONLY | O_DIRECTORY);
+    thread_done = 1;
+    syscall(SYS_exit, 0);
+    return 0;
+}
+
+static int check(void)
+{
+    umask(022);
+    int flags = CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND | CLONE_THREAD;
+    if (clone(thread_main, thread_stack + sizeof(thread_stack), flags, NULL) < 0)
+        return __LINE__;
+    while (!thread_done)
+        sched_yield();
+
+    /* The umask set by the other thread holds here. */
+    if (umask(022) != 077)
+        return __LINE__;
+    umask(077);
+    const char *path = "umaskshare.tmp";
+    int fd = open(path, O_CREAT | O_TRUNC | O_WRONLY, 0666);
+    struct stat st;
+    if (fd < 0 || fstat(fd, &st) != 0 || (st.st_mode & 0777) != 0600)
+        return __LINE__;
+    close(fd);
+    unlink(path);
+
+    /* So does the fd it opened. */
+    if (thread_fd < 0 || fstat(thread_fd, &st) != 0 || !S_ISDIR(st.st_mode))
+        return __LINE__;
+    if (close(thread_fd) != 0)
+        return __LINE__;
+
+    /* A forked child has its own. */
+    pid_t pid = fork();
+    if (pid < 0)
+        return __LINE__;
+    if (pid == 0) {
+        umask(0);
+        _exit(0);
+    }
+    int status;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status))
+        return __LINE__;
+    if (umask(022) != 077)
+        return __LINE__;
+    return 0;
+}
+
+int main(void)
+{
+    int failed = check();
+    if (failed) {
+        printf("umask_share: failed at %d\n", failed);
+        return 1;
+    }
+    puts("umask_share: ok");
+    return 0;
+}
@@ -0,0 +1,52 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <stdio.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    int sv[2], val;
+    socklen_t len = sizeof(val);
+    struct ucred cred;
+
+    if (socketpair(AF_UNIX, SOCK_STREAM, 0, sv) != 0)
+        return 1;
+
+    /* The size set is doubled, as on Linux. */
+    val = 8192;
+    if (setsockopt(sv[0], SOL_SOCKET, SO_RCVBUF, &val, sizeof(val)) != 0)
+        return 2;
+    val = 0;
+    if (getsockopt(sv[0], SOL_SOCKET, SO_RCVBUF, &val, &len) != 0)
+        return 3;
+    if (len != sizeof(val) || val != 16384)
+        return 4;
+
+    /* Both ends of a pair see the process that created it. */
+    len = sizeof(cred);
+    if (getsockopt(sv[1], SOL_SOCKET, SO_PEERCRED, &cred, &len) != 0)
+        return 5;
+    if (len != sizeof(cred) || cred.pid != getpid() || cred.uid != getuid())
+        return 6;
+
+    len = sizeof(val);
+    if (getsockopt(sv[0], SOL_SOCKET, SO_ERROR, &val, &len) != 0 || val != 0)
+        return 7;
+    if (getsockopt(sv[0], SOL_SOCKET, 0x7fff, &val, &len) != -1
+        || errno != ENOPROTOOPT)
+        return 8;
+    return 0;
+}
+
+int main()
+{
+    int ret = check();
+
+    if (ret) {
+        printf("unix_opt: failed at %d\n", ret);
+        return 1;
+    }
+    puts("unix_opt: ok");
+    return 0;
+}
@@ -0,0 +1,18 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+int main()
+{
+    char a[32], b[32];
+    int fd, ok;
+
+    fd = open("/dev/urandom", O_RDONLY);
+    ok = fd >= 0 && read(fd, a, sizeof(a)) == sizeof(a) &&
+         read(fd, b, sizeof(b)) == sizeof(b) && memcmp(a, b, sizeof(a)) != 0;
+    ok = ok && write(fd, a, sizeof(a)) == sizeof(a);
+    close(fd);
+    printf("dev_urandom: %s\n", ok ? "ok" : "failed");
+    return 0;
+}
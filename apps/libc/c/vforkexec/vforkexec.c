@@ -0,0 +1,66 @@
+#define _GNU_SOURCE
+#include <spawn.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static volatile int touched;
+
+static int check(char *argv[])
+{
+    char *const args[] = {argv[0], "child", NULL};
+    char *const envs[] = {NULL};
+    int status;
+    pid_t pid;
+
+    /* posix_spawn makes the child with CLONE_VM and CLONE_VFORK. */
+    if (posix_spawn(&pid, argv[0], NULL, NULL, args, envs) != 0)
+        return __LINE__;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 42)
+        return __LINE__;
+
+    /* The parent only goes on once the child has exec'd, and sees what it
+     * wrote before that, as they share the memory until then. */
+    pid = vfork();
+    if (pid < 0)
+        return __LINE__;
+    if (pid == 0) {
+        touched = 1;
+        execve(argv[0], args, envs);
+        _exit(1);
+    }
+    if (!touched)
+        return __LINE__;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 42)
+        return __LINE__;
+
+    /* Or once it has exited. */
+    pid = vfork();
+    if (pid < 0)
+        return __LINE__;
+    if (pid == 0) {
+        touched = 2;
+        _exit(7);
+    }
+    if (touched != 2)
+        return __LINE__;
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 7)
+        return __LINE__;
+    return 0;
+}
+
+int main(int argc, char *argv[])
+{
+    int failed;
+
+    if (argc > 1 && strcmp(argv[1], "child") == 0)
+        return 42;
+    failed = check(argv);
+    if (failed) {
+        printf("vfork_exec: failed at %d\n", failed);
+        return 1;
+    }
+    puts("vfork_exec: ok");
+    return 0;
+}
@@ -0,0 +1,58 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/uio.h>
+#include <unistd.h>
+
+static int check(int fds[2])
+{
+    static char page[4096];
+    char tail[] = "tail";
+    char buf[sizeof(page) + sizeof(tail)];
+    struct iovec iov[2];
+
+    for (int i = 0; i < (int)sizeof(page); i++)
+        page[i] = (char)i;
+    iov[0].iov_base = page;
+    iov[0].iov_len = sizeof(page);
+    iov[1].iov_base = tail;
+    iov[1].iov_len = sizeof(tail);
+    if (vmsplice(fds[1], iov, 2, SPLICE_F_GIFT) != sizeof(buf))
+        return 1;
+    if (read(fds[0], buf, sizeof(buf)) != sizeof(buf))
+        return 2;
+    if (memcmp(buf, page, sizeof(page)) || memcmp(buf + sizeof(page), tail, sizeof(tail)))
+        return 3;
+    /* Only pipes take it, and bad flags are refused. */
+    if (vmsplice(100, iov, 1, 0) != -1 || errno != EBADF)
+        return 4;
+    if (vmsplice(fds[1], iov, 1, 0x100) != -1 || errno != EINVAL)
+        return 5;
+    /* A full pipe does not block with SPLICE_F_NONBLOCK. */
+    fcntl(fds[1], F_SETPIPE_SZ, sizeof(page));
+    if (write(fds[1], page, sizeof(page)) != sizeof(page))
+        return 6;
+    if (vmsplice(fds[1], iov, 1, SPLICE_F_NONBLOCK) != -1 || errno != EAGAIN)
+        return 7;
+    return 0;
+}
+
+int main()
+{
+    int fds[2];
+    int ret;
+
+    if (pipe(fds) != 0) {
+        puts("vmsplice: pipe failed");
+        return 1;
+    }
+    ret = check(fds);
+    if (ret) {
+        printf("vmsplice: failed at %d\n", ret);
+        return 1;
+    }
+    puts("vmsplice: ok");
+    return 0;
+}
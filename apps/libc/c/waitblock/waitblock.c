@@ -0,0 +1,40 @@
+#include <stdio.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+int main()
+{
+    int status;
+    pid_t pid;
+
+    /*
+     * The child never gives up the CPU, so on a single CPU it only gets to
+     * run once the parent sleeps in wait4.
+     */
+    pid = fork();
+    if (pid == 0) {
+        volatile unsigned long n = 0;
+        for (unsigned long i = 0; i < 20000000; i++)
+            n += i;
+        _exit(7);
+    }
+    if (waitpid(pid, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 7) {
+        puts("wait4: busy child failed");
+        return 1;
+    }
+    puts("wait4: busy child ok");
+
+    pid = fork();
+    if (pid == 0) {
+        struct timespec ts = { .tv_sec = 0, .tv_nsec = 50000000 };
+        nanosleep(&ts, NULL);
+        _exit(3);
+    }
+    if (waitpid(-1, &status, 0) != pid || !WIFEXITED(status) || WEXITSTATUS(status) != 3) {
+        puts("wait4: sleeping child failed");
+        return 1;
+    }
+    puts("wait4: sleeping child ok");
+    return 0;
+}
@@ -0,0 +1,77 @@
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/resource.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static int check(void)
+{
+    int release[2];
+    if (pipe(release) != 0)
+        return __LINE__;
+    // One child goes into a group of its own and waits to be let go, the
+    // other exits in the group of the parent.
+    pid_t alone = fork();
+    if (alone < 0)
+        return __LINE__;
+    if (alone == 0) {
+        char c;
+        setpgid(0, 0);
+        // Burn some time for the parent to find in the usage.
+        for (volatile unsigned long i = 0; i < 10000000; i++)
+            ;
+        _exit(read(release[0], &c, 1) == 1 ? 7 : 1);
+    }
+    if (setpgid(alone, alone) != 0 && errno != EACCES)
+        return __LINE__;
+    pid_t same = fork();
+    if (same < 0)
+        return __LINE__;
+    if (same == 0)
+        _exit(5);
+
+    int status;
+    struct rusage usage;
+    if (wait4(0, &status, 0, NULL) != same || !WIFEXITED(status) || WEXITSTATUS(status) != 5)
+        return __LINE__;
+    // Nothing is left in the group of the parent, nor in one no child is in,
+    // but the group of the other child has it running.
+    if (wait4(0, &status, WNOHANG, NULL) != -1 || errno != ECHILD)
+        return __LINE__;
+    if (wait4(-(alone + 1000), &status, WNOHANG, NULL) != -1 || errno != ECHILD)
+        return __LINE__;
+    memset(&usage, 0xff, sizeof(usage));
+    if (wait4(-alone, &status, WNOHANG, &usage) != 0)
+        return __LINE__;
+
+    if (write(release[1], "x", 1) != 1)
+        return __LINE__;
+    if (wait4(-alone, &status, 0, &usage) != alone || !WIFEXITED(status) ||
+        WEXITSTATUS(status) != 7)
+        return __LINE__;
+    if (usage.ru_utime.tv_sec < 0 || usage.ru_utime.tv_usec < 0 ||
+        usage.ru_utime.tv_usec >= 1000000 || usage.ru_stime.tv_usec < 0 ||
+        usage.ru_stime.tv_usec >= 1000000)
+        return __LINE__;
+    if (usage.ru_utime.tv_sec == 0 && usage.ru_utime.tv_usec == 0 &&
+        usage.ru_stime.tv_sec == 0 && usage.ru_stime.tv_usec == 0)
+        return __LINE__;
+    if (wait4(-1, &status, 0, &usage) != -1 || errno != ECHILD)
+        return __LINE__;
+    // Options it does not know of are refused.
+    if (wait4(-1, &status, 0x100000, NULL) != -1 || errno != EINVAL)
+        return __LINE__;
+    return 0;
+}
+
+int main(void)
+{
+    int line = check();
+    if (line) {
+        printf("wait_group: failed at %d\n", line);
+        return 1;
+    }
+    puts("wait_group: ok");
+    return 0;
+}
@@ -0,0 +1,102 @@
+//! Process accounting, turned on by `acct`.
+//!
+//! While it is on, a record in the layout of `struct acct_v3` is appended to
+//! the accounting file each time a process exits.
+
+use axfs::api::File;
+use axhal::time::NANOS_PER_SEC;
+use axio::Write;
+use axsync::Mutex;
+use axtask::{TaskExtRef, current};
+
+/// The version of the record layout, in `ac_version`.
+const ACCT_VERSION: u8 = 3;
+/// The rate of the clock that times in a record are counted in.
+const AHZ: u64 = 100;
+
+/// `struct acct_v3`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct AcctV3 {
+    ac_flag: u8,
+    ac_version: u8,
+    ac_tty: u16,
+    ac_exitcode: u32,
+    ac_uid: u32,
+    ac_gid: u32,
+    ac_pid: u32,
+    ac_ppid: u32,
+    ac_btime: u32,
+    ac_etime: f32,
+    ac_utime: u16,
+    ac_stime: u16,
+    ac_mem: u16,
+    ac_io: u16,
+    ac_rw: u16,
+    ac_minflt: u16,
+    ac_majflt: u16,
+    ac_swaps: u16,
+    ac_comm: [u8; 16],
+}
+
+/// The file records are appended to, if accounting is on.
+static ACCT_FILE: Mutex<Option<File>> = Mutex::new(None);
+
+/// Turns accounting on with records going to `file`, or off if it is `None`.
+pub fn set_acct_file(file: Option<File>) {
+    *ACCT_FILE.lock() = file;
+}
+
+/// Encodes `value` as a `comp_t`: a 13-bit mantissa with a 3-bit exponent of
+/// base 8.
+fn encode_comp(mut value: u64) -> u16 {
+    let mut exp = 0;
+    while value > 0x1fff {
+        // Round to the nearest on dropping the low 3 bits.
+        value = (value + 4) >> 3;
+        exp += 1;
+    }
+    if exp > 7 {
+        return 0xffff;
+    }
+    (exp << 13) as u16 | value as u16
+}
+
+fn nanos_to_ticks(nanos: usize) -> u64 {
+    nanos as u64 * AHZ / NANOS_PER_SEC
+}
+
+/// Appends the record of the current process exiting with `exit_code` to the
+/// accounting file, if accounting is on.
+pub(crate) fn account_exit(exit_code: i32) {
+    let mut acct_file = ACCT_FILE.lock();
+    let Some(file) = acct_file.as_mut() else {
+        return;
+    };
+    let curr = current();
+    let ext = curr.task_ext();
+    let cred = ext.cred();
+    let (utime, stime) = ext.time_stat_output();
+    let mut record = AcctV3 {
+        ac_version: ACCT_VERSION,
+        ac_exitcode: ext.wait_status(exit_code) as u32,
+        ac_uid: cred.uid,
+        ac_gid: cred.gid,
+        ac_pid: curr.tgid() as u32,
+        ac_ppid: ext.get_parent() as u32,
+        ac_utime: encode_comp(nanos_to_ticks(utime)),
+        ac_stime: encode_comp(nanos_to_ticks(stime)),
+        ..Default::default()
+    };
+    // The name is the path the program was run from.
+    let name = curr.name().rsplit('/').next().unwrap().as_bytes();
+    let len = name.len().min(record.ac_comm.len() - 1);
+    record.ac_comm[..len].copy_from_slice(&name[..len]);
+
+    let bytes = unsafe {
+        core::slice::from_raw_parts(&record as *const _ as *const u8, size_of::<AcctV3>())
+    };
+    if let Err(err) = file.write_all(bytes) {
+        warn!("Failed to write accounting record: {:?}", err);
+    }
+}
@@ -0,0 +1,384 @@
+//! Core dumps of processes killed by signals.
+//!
+//! The core is an ELF file named `core` in the current directory, with the
+//! registers and the signal in a `NT_PRSTATUS` note and a `PT_LOAD` segment
+//! for each mapped area.
+
+use alloc::vec::Vec;
+
+use axfs::api::File;
+use axhal::{arch::TrapFrame, paging::MappingFlags, time::NANOS_PER_MICROS};
+use axio::Write;
+use axtask::{TaskExtRef, current};
+use memory_addr::{PAGE_SIZE_4K, VirtAddr};
+
+use crate::{ctypes::RLIMIT_CORE, task::read_trapframe_from_kstack};
+
+const ET_CORE: u16 = 4;
+#[cfg(target_arch = "x86_64")]
+const EM_ARCH: u16 = 62;
+#[cfg(target_arch = "aarch64")]
+const EM_ARCH: u16 = 183;
+#[cfg(target_arch = "riscv64")]
+const EM_ARCH: u16 = 243;
+#[cfg(target_arch = "loongarch64")]
+const EM_ARCH: u16 = 258;
+
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+
+const NT_PRSTATUS: u32 = 1;
+const NT_PRPSINFO: u32 = 3;
+
+/// The number of registers in `elf_gregset_t`.
+#[cfg(target_arch = "x86_64")]
+const ELF_NGREG: usize = 27;
+#[cfg(target_arch = "aarch64")]
+const ELF_NGREG: usize = 34;
+#[cfg(target_arch = "riscv64")]
+const ELF_NGREG: usize = 32;
+#[cfg(target_arch = "loongarch64")]
+const ELF_NGREG: usize = 45;
+
+#[repr(C)]
+struct Elf64Ehdr {
+    e_ident: [u8; 16],
+    e_type: u16,
+    e_machine: u16,
+    e_version: u32,
+    e_entry: u64,
+    e_phoff: u64,
+    e_shoff: u64,
+    e_flags: u32,
+    e_ehsize: u16,
+    e_phentsize: u16,
+    e_phnum: u16,
+    e_shentsize: u16,
+    e_shnum: u16,
+    e_shstrndx: u16,
+}
+
+#[repr(C)]
+struct Elf64Phdr {
+    p_type: u32,
+    p_flags: u32,
+    p_offset: u64,
+    p_vaddr: u64,
+    p_paddr: u64,
+    p_filesz: u64,
+    p_memsz: u64,
+    p_align: u64,
+}
+
+#[repr(C)]
+struct Elf64Nhdr {
+    n_namesz: u32,
+    n_descsz: u32,
+    n_type: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Timeval {
+    tv_sec: i64,
+    tv_usec: i64,
+}
+
+impl Timeval {
+    fn from_nanos(nanos: usize) -> Self {
+        let micros = nanos as u64 / NANOS_PER_MICROS;
+        Self {
+            tv_sec: (micros / 1_000_000) as _,
+            tv_usec: (micros % 1_000_000) as _,
+        }
+    }
+}
+
+/// `struct elf_prstatus`.
+#[repr(C)]
+struct ElfPrStatus {
+    si_signo: i32,
+    si_code: i32,
+    si_errno: i32,
+    pr_cursig: u16,
+    pr_sigpend: u64,
+    pr_sighold: u64,
+    pr_pid: u32,
+    pr_ppid: u32,
+    pr_pgrp: u32,
+    pr_sid: u32,
+    pr_utime: Timeval,
+    pr_stime: Timeval,
+    pr_cutime: Timeval,
+    pr_cstime: Timeval,
+    pr_reg: [u64; ELF_NGREG],
+    pr_fpvalid: i32,
+}
+
+/// `struct elf_prpsinfo`.
+#[repr(C)]
+struct ElfPrPsInfo {
+    pr_state: u8,
+    pr_sname: u8,
+    pr_zomb: u8,
+    pr_nice: i8,
+    pr_flag: u64,
+    pr_uid: u32,
+    pr_gid: u32,
+    pr_pid: u32,
+    pr_ppid: u32,
+    pr_pgrp: u32,
+    pr_sid: u32,
+    pr_fname: [u8; 16],
+    pr_psargs: [u8; 80],
+}
+
+fn as_bytes<T>(value: &T) -> &[u8] {
+    unsafe { core::slice::from_raw_parts(value as *const T as *const u8, size_of::<T>()) }
+}
+
+/// The registers of `tf` in the order of `elf_gregset_t`.
+#[cfg(target_arch = "x86_64")]
+#[rustfmt::skip]
+fn elf_gregs(tf: &TrapFrame) -> [u64; ELF_NGREG] {
+    let fs_base = axhal::arch::read_thread_pointer() as u64;
+    // `orig_rax` is -1, as the task is not in a syscall.
+    [
+        tf.r15, tf.r14, tf.r13, tf.r12, tf.rbp, tf.rbx, tf.r11, tf.r10, tf.r9, tf.r8,
+        tf.rax, tf.rcx, tf.rdx, tf.rsi, tf.rdi, u64::MAX, tf.rip, tf.cs, tf.rflags, tf.rsp,
+        tf.ss, fs_base, 0, 0, 0, 0, 0,
+    ]
+}
+
+#[cfg(target_arch = "aarch64")]
+fn elf_gregs(tf: &TrapFrame) -> [u64; ELF_NGREG] {
+    let mut regs = [0; ELF_NGREG];
+    regs[..31].copy_from_slice(&tf.r);
+    regs[31] = tf.usp;
+    regs[32] = tf.elr;
+    regs[33] = tf.spsr;
+    regs
+}
+
+#[cfg(target_arch = "riscv64")]
+fn elf_gregs(tf: &TrapFrame) -> [u64; ELF_NGREG] {
+    // The general registers are kept in the order of x1 to x31.
+    let gprs: [usize; 31] = unsafe { core::mem::transmute(tf.regs) };
+    let mut regs = [0; ELF_NGREG];
+    regs[0] = tf.sepc as u64;
+    for (reg, gpr) in regs[1..].iter_mut().zip(gprs) {
+        *reg = gpr as u64;
+    }
+    regs
+}
+
+#[cfg(target_arch = "loongarch64")]
+fn elf_gregs(tf: &TrapFrame) -> [u64; ELF_NGREG] {
+    // The general registers are kept in the order of r0 to r31.
+    let gprs: [usize; 32] = unsafe { core::mem::transmute(tf.regs) };
+    let mut regs = [0; ELF_NGREG];
+    for (reg, gpr) in regs.iter_mut().zip(gprs) {
+        *reg = gpr as u64;
+    }
+    regs[32] = tf.regs.a0 as u64;
+    regs[33] = tf.era as u64;
+    regs
+}
+
+/// Appends a note with the name `CORE` to `buf`.
+fn push_note(buf: &mut Vec<u8>, n_type: u32, desc: &[u8]) {
+    let nhdr = Elf64Nhdr {
+        n_namesz: 5,
+        n_descsz: desc.len() as u32,
+        n_type,
+    };
+    buf.extend_from_slice(as_bytes(&nhdr));
+    buf.extend_from_slice(b"CORE\0\0\0\0");
+    buf.extend_from_slice(desc);
+    buf.resize(buf.len().next_multiple_of(4), 0);
+}
+
+/// Writes to the core file until the size limit is reached.
+struct CoreWriter {
+    file: File,
+    remaining: u64,
+}
+
+impl CoreWriter {
+    /// Writes what fits of `buf`, returning false once nothing more can be
+    /// written.
+    fn write(&mut self, buf: &[u8]) -> bool {
+        let len = buf.len().min(self.remaining as usize);
+        if self.file.write_all(&buf[..len]).is_err() {
+            return false;
+        }
+        self.remaining -= len as u64;
+        len == buf.len()
+    }
+}
+
+/// Dumps the core of the current process, killed by `sig`, returning whether
+/// one was written.
+///
+/// No core is dumped for a process that made itself non-dumpable, or with
+/// `RLIMIT_CORE` below a page. The core is cut short at the limit.
+pub(crate) fn dump_core(sig: usize) -> bool {
+    let curr = current();
+    let ext = curr.task_ext();
+    let limit = ext.rlimit(RLIMIT_CORE).rlim_cur;
+    if !ext.dumpable() || limit < PAGE_SIZE_4K as u64 {
+        return false;
+    }
+    let Ok(file) = File::create("core") else {
+        return false;
+    };
+    let mut writer = CoreWriter {
+        file,
+        remaining: limit,
+    };
+
+    let tf = read_trapframe_from_kstack(curr.get_kernel_stack_top().unwrap());
+    let (utime, stime) = ext.time_stat_output();
+    let (blocked, pending) = {
+        let signal = ext.signal.lock();
+        (signal.blocked.0, signal.pending().0)
+    };
+    let cred = ext.cred();
+    let prstatus = ElfPrStatus {
+        si_signo: sig as i32,
+        si_code: 0,
+        si_errno: 0,
+        pr_cursig: sig as u16,
+        pr_sigpend: pending,
+        pr_sighold: blocked,
+        pr_pid: curr.tgid() as u32,
+        pr_ppid: ext.get_parent() as u32,
+        pr_pgrp: ext.pgid() as u32,
+        pr_sid: ext.sid() as u32,
+        pr_utime: Timeval::from_nanos(utime),
+        pr_stime: Timeval::from_nanos(stime),
+        pr_cutime: Timeval::default(),
+        pr_cstime: Timeval::default(),
+        pr_reg: elf_gregs(&tf),
+        pr_fpvalid: 0,
+    };
+    let mut prpsinfo = ElfPrPsInfo {
+        pr_state: 0,
+        pr_sname: b'R',
+        pr_zomb: 0,
+        pr_nice: ext.nice() as i8,
+        pr_flag: 0,
+        pr_uid: cred.uid,
+        pr_gid: cred.gid,
+        pr_pid: curr.tgid() as u32,
+        pr_ppid: ext.get_parent() as u32,
+        pr_pgrp: ext.pgid() as u32,
+        pr_sid: ext.sid() as u32,
+        pr_fname: [0; 16],
+        pr_psargs: [0; 80],
+    };
+    let name = curr.name().rsplit('/').next().unwrap().as_bytes();
+    let len = name.len().min(prpsinfo.pr_fname.len() - 1);
+    prpsinfo.pr_fname[..len].copy_from_slice(&name[..len]);
+    let len = name.len().min(prpsinfo.pr_psargs.len() - 1);
+    prpsinfo.pr_psargs[..len].copy_from_slice(&name[..len]);
+
+    let mut notes = Vec::new();
+    push_note(&mut notes, NT_PRSTATUS, as_bytes(&prstatus));
+    push_note(&mut notes, NT_PRPSINFO, as_bytes(&prpsinfo));
+
+    let aspace = ext.aspace.lock();
+    let areas = aspace.areas().collect::<Vec<_>>();
+    let phnum = areas.len() + 1;
+    let notes_offset = size_of::<Elf64Ehdr>() + phnum * size_of::<Elf64Phdr>();
+    let data_offset = (notes_offset + notes.len()).next_multiple_of(PAGE_SIZE_4K);
+
+    let mut e_ident = [0; 16];
+    // ELF64, little-endian, version 1
+    e_ident[..7].copy_from_slice(b"\x7fELF\x02\x01\x01");
+    let ehdr = Elf64Ehdr {
+        e_ident,
+        e_type: ET_CORE,
+        e_machine: EM_ARCH,
+        e_version: 1,
+        e_entry: 0,
+        e_phoff: size_of::<Elf64Ehdr>() as u64,
+        e_shoff: 0,
+        e_flags: 0,
+        e_ehsize: size_of::<Elf64Ehdr>() as u16,
+        e_phentsize: size_of::<Elf64Phdr>() as u16,
+        e_phnum: phnum as u16,
+        e_shentsize: 0,
+        e_shnum: 0,
+        e_shstrndx: 0,
+    };
+    let mut headers = Vec::with_capacity(data_offset);
+    headers.extend_from_slice(as_bytes(&ehdr));
+    let note_phdr = Elf64Phdr {
+        p_type: PT_NOTE,
+        p_flags: 0,
+        p_offset: notes_offset as u64,
+        p_vaddr: 0,
+        p_paddr: 0,
+        p_filesz: notes.len() as u64,
+        p_memsz: 0,
+        p_align: 4,
+    };
+    headers.extend_from_slice(as_bytes(&note_phdr));
+    let mut offset = data_offset;
+    for (range, flags) in &areas {
+        // The contents of an area that cannot be read are left out.
+        let filesz = if flags.contains(MappingFlags::READ) {
+            range.size()
+        } else {
+            0
+        };
+        let mut p_flags = 0;
+        for (flag, pf) in [
+            (MappingFlags::READ, PF_R),
+            (MappingFlags::WRITE, PF_W),
+            (MappingFlags::EXECUTE, PF_X),
+        ] {
+            if flags.contains(flag) {
+                p_flags |= pf;
+            }
+        }
+        let phdr = Elf64Phdr {
+            p_type: PT_LOAD,
+            p_flags,
+            p_offset: offset as u64,
+            p_vaddr: range.start.as_usize() as u64,
+            p_paddr: 0,
+            p_filesz: filesz as u64,
+            p_memsz: range.size() as u64,
+            p_align: PAGE_SIZE_4K as u64,
+        };
+        headers.extend_from_slice(as_bytes(&phdr));
+        offset += filesz;
+    }
+    headers.extend_from_slice(&notes);
+    headers.resize(data_offset, 0);
+    if !writer.write(&headers) {
+        return true;
+    }
+
+    let mut page = [0; PAGE_SIZE_4K];
+    for (range, flags) in &areas {
+        if !flags.contains(MappingFlags::READ) {
+            continue;
+        }
+        for vaddr in (range.start.as_usize()..range.end.as_usize()).step_by(PAGE_SIZE_4K) {
+            // Pages not touched yet are not backed by memory, and read as zeros.
+            if aspace.read(VirtAddr::from(vaddr), &mut page).is_err() {
+                page.fill(0);
+            }
+            if !writer.write(&page) {
+                return true;
+            }
+        }
+    }
+    true
+}
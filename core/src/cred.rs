@@ -0,0 +1,72 @@
+//! The user and group IDs of a task, which permissions are checked against.
+
+use alloc::vec::Vec;
+
+/// The most supplementary groups a task can be in.
+pub const NGROUPS_MAX: usize = 65536;
+
+pub const R_OK: u32 = 4;
+pub const W_OK: u32 = 2;
+pub const X_OK: u32 = 1;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cred {
+    pub uid: u32,
+    pub euid: u32,
+    pub gid: u32,
+    pub egid: u32,
+    /// The supplementary groups, sorted and without duplicates.
+    pub groups: Vec<u32>,
+}
+
+impl Cred {
+    /// The credentials of the first process.
+    pub const fn root() -> Self {
+        Self {
+            uid: 0,
+            euid: 0,
+            gid: 0,
+            egid: 0,
+            groups: Vec::new(),
+        }
+    }
+
+    /// The credentials with the real IDs taking the place of the effective
+    /// ones, which is what `access` checks against.
+    pub fn real(&self) -> Self {
+        Self {
+            euid: self.uid,
+            egid: self.gid,
+            ..self.clone()
+        }
+    }
+
+    pub fn set_groups(&mut self, mut groups: Vec<u32>) {
+        groups.sort_unstable();
+        groups.dedup();
+        self.groups = groups;
+    }
+
+    /// Whether `gid` is the effective group or one of the supplementary ones.
+    pub fn in_group(&self, gid: u32) -> bool {
+        self.egid == gid || self.groups.binary_search(&gid).is_ok()
+    }
+
+    /// Whether the owner, group and permission bits of a file allow the
+    /// accesses in `mask`, a combination of [`R_OK`], [`W_OK`] and [`X_OK`].
+    ///
+    /// Root may do anything but execute a file no one can execute.
+    pub fn permits(&self, mode: u32, uid: u32, gid: u32, mask: u32) -> bool {
+        if self.euid == 0 {
+            return mask & X_OK == 0 || mode & 0o111 != 0 || mode & 0o170000 == 0o040000;
+        }
+        let bits = if self.euid == uid {
+            mode >> 6
+        } else if self.in_group(gid) {
+            mode >> 3
+        } else {
+            mode
+        };
+        bits & mask & 0o7 == mask
+    }
+}
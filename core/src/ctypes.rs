@@ -46,6 +46,7 @@ bitflags! {
         const CLONE_NEWPID = 1 << 29;
     }
 
+    #[derive(Debug, Clone, Copy)]
     pub struct WaitFlags: u32 {
         /// 不挂起当前进程，直接返回
         const WNOHANG = 1 << 0;
@@ -71,18 +72,43 @@ pub enum WaitStatus {
     /// 找不到对应的子任务
     NotExist,
 }
+/// 向用户报告时间所用的时钟频率，即 `sysconf(_SC_CLK_TCK)`，由 auxv 中的
+/// `AT_CLKTCK` 告知 libc
+pub const USER_HZ: u64 = 100;
+
+/// sys_times 的返回值，时间均以 `USER_HZ` 的时钟周期计
 #[repr(C)]
 pub struct Tms {
-    /// 进程用户态执行时间，单位为us
+    /// 进程用户态执行时间
     pub tms_utime: usize,
-    /// 进程内核态执行时间，单位为us
+    /// 进程内核态执行时间
     pub tms_stime: usize,
-    /// 子进程用户态执行时间和，单位为us
+    /// 已回收的子进程用户态执行时间和
     pub tms_cutime: usize,
-    /// 子进程内核态执行时间和，单位为us
+    /// 已回收的子进程内核态执行时间和
     pub tms_cstime: usize,
 }
 
+/// sys_getrusage 使用的选项
+/// 调用进程的所有线程
+pub const RUSAGE_SELF: i32 = 0;
+/// 已回收的子进程
+pub const RUSAGE_CHILDREN: i32 = -1;
+/// 调用线程
+pub const RUSAGE_THREAD: i32 = 1;
+
+/// sys_getrusage 的返回值
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Rusage {
+    /// 用户态执行时间
+    pub ru_utime: arceos_posix_api::ctypes::timeval,
+    /// 内核态执行时间
+    pub ru_stime: arceos_posix_api::ctypes::timeval,
+    /// 其余的统计项，均记为 0
+    pub ru_rest: [isize; 14],
+}
+
 /// sys_prlimit64 使用的数组
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
@@ -91,12 +117,22 @@ pub struct RLimit {
     pub rlim_max: u64, // 最大硬限制
 }
 // sys_prlimit64 使用的选项
+/// 数据段的最大大小
+pub const RLIMIT_DATA: i32 = 2;
 /// 用户栈大小
 pub const RLIMIT_STACK: i32 = 3;
+/// core 文件的最大大小
+pub const RLIMIT_CORE: i32 = 4;
+/// 常驻内存的最大大小
+pub const RLIMIT_RSS: i32 = 5;
 /// 可以打开的 fd 数
 pub const RLIMIT_NOFILE: i32 = 7;
 /// 用户地址空间的最大大小
 pub const RLIMIT_AS: i32 = 9;
+/// 资源的种数
+pub const RLIM_NLIMITS: usize = 16;
+/// 没有限制
+pub const RLIM_INFINITY: u64 = u64::MAX;
 
 /// syscall_info 用到的 结构体
 #[repr(C)]
@@ -193,7 +229,8 @@ impl TimeStat {
         self.kernel_timestamp = current_timestamp;
     }
 
-    pub fn switch_into_kernel_mode(&mut self, current_timestamp: usize) {
+    /// Returns the user time added, in nanoseconds.
+    pub fn switch_into_kernel_mode(&mut self, current_timestamp: usize) -> usize {
         let now_time_ns = current_timestamp;
         let delta = now_time_ns - self.kernel_timestamp;
         self.utime_ns += delta;
@@ -201,6 +238,7 @@ impl TimeStat {
         if self.timer_type != TimerType::NONE {
             self.update_timer(delta);
         };
+        delta
     }
 
     pub fn switch_into_user_mode(&mut self, current_timestamp: usize) {
@@ -0,0 +1,126 @@
+//! The kernel log buffer, read with `syslog` and from `/proc/kmsg`.
+//!
+//! Each record logged is kept in a fixed ring of bytes as a line of text, in
+//! the form `<priority>[seconds.micros] message`. The priority is the level
+//! as numbered for `syslog`, with the facility in the bits above it. When the
+//! ring is full, the oldest lines are dropped whole to make room.
+
+use core::fmt::{self, Write};
+
+use axhal::time::monotonic_time;
+use axlog::{Level, Record};
+use kspin::SpinNoIrq;
+
+/// The size of the buffer, in bytes.
+pub const LOG_BUF_LEN: usize = 1 << 17;
+
+struct LogBuffer {
+    data: [u8; LOG_BUF_LEN],
+    /// Where the oldest byte is.
+    start: usize,
+    len: usize,
+}
+
+impl LogBuffer {
+    fn push(&mut self, byte: u8) {
+        if self.len == LOG_BUF_LEN {
+            // Drop the rest of the oldest line along with its first byte, so
+            // that the buffer still starts at a line.
+            while self.len > 0 {
+                let dropped = self.data[self.start];
+                self.start = (self.start + 1) % LOG_BUF_LEN;
+                self.len -= 1;
+                if dropped == b'\n' {
+                    break;
+                }
+            }
+        }
+        self.data[(self.start + self.len) % LOG_BUF_LEN] = byte;
+        self.len += 1;
+    }
+}
+
+impl Write for LogBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        s.bytes().for_each(|byte| self.push(byte));
+        Ok(())
+    }
+}
+
+static LOG_BUF: SpinNoIrq<LogBuffer> = SpinNoIrq::new(LogBuffer {
+    data: [0; LOG_BUF_LEN],
+    start: 0,
+    len: 0,
+});
+
+const LOGLEVEL_ERR: u8 = 3;
+const LOGLEVEL_WARNING: u8 = 4;
+const LOGLEVEL_INFO: u8 = 6;
+const LOGLEVEL_DEBUG: u8 = 7;
+
+/// The facility of messages written by user space.
+const LOG_USER: u32 = 1 << 3;
+
+fn log_line(priority: u32, args: fmt::Arguments) {
+    let now = monotonic_time();
+    let _ = writeln!(
+        LOG_BUF.lock(),
+        "<{}>[{:5}.{:06}] {}",
+        priority,
+        now.as_secs(),
+        now.subsec_micros(),
+        args
+    );
+}
+
+fn log_record(record: &Record) {
+    let level = match record.level() {
+        Level::Error => LOGLEVEL_ERR,
+        Level::Warn => LOGLEVEL_WARNING,
+        Level::Info => LOGLEVEL_INFO,
+        Level::Debug | Level::Trace => LOGLEVEL_DEBUG,
+    };
+    log_line(level as u32, *record.args());
+}
+
+/// Logs a message written to `/dev/kmsg`, at the priority given by a leading
+/// `<priority>` if there is one, or else as a warning. It cannot pass for a
+/// message of the kernel itself, so with no facility it gets the user one.
+pub fn log_user(message: &str) {
+    let message = message.strip_suffix('\n').unwrap_or(message);
+    let prefixed = message.strip_prefix('<').and_then(|rest| {
+        let (prefix, rest) = rest.split_once('>')?;
+        Some((prefix.parse::<u32>().ok()?, rest))
+    });
+    let (priority, message) = prefixed.unwrap_or((LOGLEVEL_WARNING as u32, message));
+    let priority = if priority & !7 == 0 {
+        priority | LOG_USER
+    } else {
+        priority
+    };
+    log_line(priority, format_args!("{}", message));
+}
+
+/// Starts keeping the records logged from now on in the buffer.
+pub fn init() {
+    axlog::set_record_sink(log_record);
+}
+
+/// Copies the latest bytes in the buffer to `buf`, as many as fit, returning
+/// how many were copied.
+pub fn read_all(buf: &mut [u8]) -> usize {
+    let log = LOG_BUF.lock();
+    let len = log.len.min(buf.len());
+    let skip = log.len - len;
+    for (i, byte) in buf[..len].iter_mut().enumerate() {
+        *byte = log.data[(log.start + skip + i) % LOG_BUF_LEN];
+    }
+    len
+}
+
+/// Empties the buffer.
+pub fn clear() {
+    let mut log = LOG_BUF.lock();
+    log.start = 0;
+    log.len = 0;
+}
@@ -4,7 +4,14 @@
 extern crate axlog;
 extern crate alloc;
 
+pub mod acct;
+mod coredump;
+pub mod cred;
 pub mod ctypes;
 pub mod entry;
+pub mod kmsg;
 pub mod mm;
+pub mod rand;
+pub mod signal;
 pub mod task;
+pub mod tty;
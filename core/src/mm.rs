@@ -1,17 +1,32 @@
-use core::ffi::CStr;
+use core::{
+    ffi::CStr,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
 
-use alloc::{string::String, vec};
+use alloc::{string::String, sync::Arc, vec, vec::Vec};
 use axerrno::{AxError, AxResult};
 use axhal::{
-    paging::MappingFlags,
-    trap::{PAGE_FAULT, register_trap_handler},
+    arch::{TrapFrame, flush_tlb},
+    cpu::this_cpu_id,
+    mem::virt_to_phys,
+    paging::{MappingFlags, PageSize},
+    trap::{PAGE_FAULT, POST_TRAP, register_trap_handler},
 };
 use axmm::{AddrSpace, kernel_aspace};
+use axsync::Mutex;
 use axtask::TaskExtRef;
-use kernel_elf_parser::{AuxvEntry, ELFParser, app_stack_region};
-use memory_addr::{MemoryAddr, PAGE_SIZE_4K, VirtAddr};
+use kernel_elf_parser::{AuxvEntry, AuxvType, ELFParser, app_stack_region};
+use memory_addr::{MemoryAddr, PAGE_SIZE_4K, PageIter4K, VirtAddr, VirtAddrRange};
 use xmas_elf::{ElfFile, program::SegmentData};
 
+use crate::{
+    cred::Cred,
+    ctypes::USER_HZ,
+    rand::fill_random,
+    signal::{SEGV_ACCERR, SEGV_MAPERR, SIGSEGV, SigInfo, force_signal},
+    task::{all_tasks, exit_current, find_task},
+};
+
 pub fn new_user_aspace_empty() -> AxResult<AddrSpace> {
     AddrSpace::new_empty(
         VirtAddr::from_usize(axconfig::plat::USER_SPACE_BASE),
@@ -31,6 +46,105 @@ pub fn copy_from_kernel(aspace: &mut AddrSpace) -> AxResult {
     Ok(())
 }
 
+/// The bytes of `aspace` backed by frames, which for lazy mappings are only
+/// the pages that have been touched.
+pub fn resident_size(aspace: &AddrSpace) -> usize {
+    let pt = aspace.page_table();
+    aspace
+        .areas()
+        .flat_map(|(range, _)| PageIter4K::new(range.start, range.end).unwrap())
+        .filter(|&page| pt.query(page).is_ok())
+        .count()
+        * PAGE_SIZE_4K
+}
+
+/// The user address spaces, each once however many tasks share it.
+fn user_aspaces() -> Vec<Arc<Mutex<AddrSpace>>> {
+    let mut aspaces: Vec<Arc<Mutex<AddrSpace>>> = Vec::new();
+    for task in all_tasks() {
+        let aspace = &task.task_ext().aspace;
+        if !aspaces.iter().any(|seen| Arc::ptr_eq(seen, aspace)) {
+            aspaces.push(aspace.clone());
+        }
+    }
+    aspaces
+}
+
+/// The bytes committed to all the user address spaces.
+static COMMITTED: AtomicUsize = AtomicUsize::new(0);
+
+/// The bytes committed to the user address spaces: the sizes of their
+/// writable mappings, counting the pages yet to be touched.
+pub fn committed_size() -> usize {
+    COMMITTED.load(Ordering::Acquire)
+}
+
+/// What one user address space has committed, which counts towards
+/// [`committed_size`] until the last task sharing the space is gone.
+#[derive(Default)]
+pub struct Commit(AtomicUsize);
+
+impl Commit {
+    /// Counts again what `aspace` commits, leaving out `unused` bytes of its
+    /// writable mappings.
+    pub fn recount(&self, aspace: &AddrSpace, unused: usize) {
+        let size = aspace
+            .areas()
+            .filter(|(_, flags)| flags.contains(MappingFlags::WRITE))
+            .map(|(range, _)| range.size())
+            .sum::<usize>()
+            .saturating_sub(unused);
+        let old = self.0.swap(size, Ordering::AcqRel);
+        COMMITTED.fetch_add(size, Ordering::AcqRel);
+        COMMITTED.fetch_sub(old, Ordering::AcqRel);
+    }
+}
+
+impl Drop for Commit {
+    fn drop(&mut self) {
+        COMMITTED.fetch_sub(*self.0.get_mut(), Ordering::AcqRel);
+    }
+}
+
+/// The bytes of the user address spaces backed by huge pages.
+///
+/// It locks every address space, so none may be locked by the caller.
+pub fn huge_page_size() -> usize {
+    let huge = PageSize::Size2M as usize;
+    user_aspaces()
+        .iter()
+        .map(|aspace| {
+            let aspace = aspace.lock();
+            let pt = aspace.page_table();
+            aspace
+                .areas()
+                .flat_map(|(range, _)| {
+                    (range.start.align_up(huge).as_usize()..range.end.as_usize()).step_by(huge)
+                })
+                .filter(|&block| matches!(pt.query(block.into()), Ok((_, _, PageSize::Size2M))))
+                .count()
+                * huge
+        })
+        .sum()
+}
+
+/// Map the signal trampoline page to the user address space.
+///
+/// Signal handlers return to the trampoline unless the task provides its own
+/// restorer via `SA_RESTORER`.
+fn map_trampoline(uspace: &mut AddrSpace) -> AxResult {
+    unsafe extern "C" {
+        fn start_signal_trampoline();
+    }
+    let trampoline_paddr = virt_to_phys((start_signal_trampoline as usize).into());
+    uspace.map_linear(
+        VirtAddr::from_usize(axconfig::plat::SIGNAL_TRAMPOLINE),
+        trampoline_paddr.align_down_4k(),
+        PAGE_SIZE_4K,
+        MappingFlags::READ | MappingFlags::EXECUTE | MappingFlags::USER,
+    )
+}
+
 /// Map the elf file to the user address space.
 ///
 /// # Arguments
@@ -81,25 +195,88 @@ fn map_elf(uspace: &mut AddrSpace, elf: &ElfFile) -> AxResult<(VirtAddr, [AuxvEn
     ))
 }
 
+/// The `AT_HWCAP` bits of the CPU features user code can rely on.
+fn hwcap() -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        // Linux passes the `edx` of leaf 1 as is.
+        unsafe { core::arch::x86_64::__cpuid(1) }.edx as usize
+    }
+    #[cfg(target_arch = "riscv64")]
+    {
+        // A bit for each single-letter extension of RV64GC.
+        b"imafdc".iter().map(|c| 1usize << (c - b'a')).sum()
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        // HWCAP_FP | HWCAP_ASIMD, which every ARMv8-A CPU has.
+        0b11
+    }
+    #[cfg(not(any(
+        target_arch = "x86_64",
+        target_arch = "riscv64",
+        target_arch = "aarch64"
+    )))]
+    {
+        0
+    }
+}
+
+/// Fills in the entries of `auxv` the parser does not know: the IDs in
+/// `cred`, whether the app runs with IDs it was not started by, the hardware
+/// capabilities and the clock rate `sysconf(_SC_CLK_TCK)` is taken from.
+fn complete_auxv(auxv: &mut Vec<AuxvEntry>, cred: &Cred) {
+    let secure = cred.uid != cred.euid || cred.gid != cred.egid;
+    let entries = [
+        (AuxvType::HWCAP, hwcap()),
+        (AuxvType::HWCAP2, 0),
+        (AuxvType::CLKTCK, USER_HZ as usize),
+        (AuxvType::UID, cred.uid as usize),
+        (AuxvType::EUID, cred.euid as usize),
+        (AuxvType::GID, cred.gid as usize),
+        (AuxvType::EGID, cred.egid as usize),
+        (AuxvType::SECURE, secure as usize),
+    ];
+    for (ty, value) in entries {
+        let new = AuxvEntry::new(ty, value);
+        match auxv.iter_mut().find(|entry| entry.get_type() == ty) {
+            Some(entry) => *entry = new,
+            None => {
+                let end = auxv
+                    .iter()
+                    .position(|entry| entry.get_type() == AuxvType::NULL)
+                    .unwrap_or(auxv.len());
+                auxv.insert(end, new);
+            }
+        }
+    }
+}
+
 /// Load the user app to the user address space.
 ///
 /// # Arguments
 /// - `uspace`: The address space of the user app.
-/// - `args`: The arguments of the user app. The first argument is the path of the user app.
+/// - `path`: The path of the user app.
+/// - `args`: The arguments of the user app.
 /// - `envs`: The environment variables of the user app.
+/// - `cred`: The credentials the app runs with, told to it in the auxv.
 ///
 /// # Returns
 /// - The entry point of the user app.
 /// - The stack pointer of the user app.
+/// - The auxiliary vector passed to the app, as pairs of type and value
+///   ending with `AT_NULL`.
 pub fn load_user_app(
     uspace: &mut AddrSpace,
+    path: &str,
     args: &[String],
     envs: &[String],
-) -> AxResult<(VirtAddr, VirtAddr)> {
+    cred: &Cred,
+) -> AxResult<(VirtAddr, VirtAddr, Vec<(usize, usize)>)> {
     if args.is_empty() {
         return Err(AxError::InvalidInput);
     }
-    let file_data = axfs::api::read(args[0].as_str())?;
+    let file_data = axfs::api::read(path)?;
     let elf = ElfFile::new(&file_data).map_err(|_| AxError::InvalidData)?;
 
     if let Some(interp) = elf
@@ -127,13 +304,16 @@ pub fn load_user_app(
             interp_path = String::from("/musl/lib/libc.so");
         }
 
-        // Set the first argument to the path of the user app.
-        let mut new_args = vec![interp_path];
-        new_args.extend_from_slice(args);
-        return load_user_app(uspace, &new_args, envs);
+        // The interpreter is run with the path of the user app in place of
+        // its first argument.
+        let mut new_args = vec![interp_path.clone(), String::from(path)];
+        new_args.extend_from_slice(&args[1..]);
+        return load_user_app(uspace, &interp_path, &new_args, envs, cred);
     }
 
-    let (entry, mut auxv) = map_elf(uspace, &elf)?;
+    let (entry, auxv) = map_elf(uspace, &elf)?;
+    let mut auxv = auxv.to_vec();
+    complete_auxv(&mut auxv, cred);
     // The user stack is divided into two parts:
     // `ustack_start` -> `ustack_pointer`: It is the stack space that users actually read and write.
     // `ustack_pointer` -> `ustack_end`: It is the space that contains the arguments, environment variables and auxv passed to the app.
@@ -146,7 +326,7 @@ pub fn load_user_app(
         ustack_start, ustack_end
     );
 
-    let stack_data = app_stack_region(args, envs, &mut auxv, ustack_start, ustack_size);
+    let mut stack_data = app_stack_region(args, envs, &mut auxv, ustack_start, ustack_size);
     uspace.map_alloc(
         ustack_start,
         ustack_size,
@@ -154,20 +334,38 @@ pub fn load_user_app(
         true,
     )?;
 
+    // The heap is faulted in as the app grows into it.
     let heap_start = VirtAddr::from_usize(axconfig::plat::USER_HEAP_BASE);
     let heap_size = axconfig::plat::USER_HEAP_SIZE;
     uspace.map_alloc(
         heap_start,
         heap_size,
         MappingFlags::READ | MappingFlags::WRITE | MappingFlags::USER,
-        true,
+        false,
     )?;
 
+    map_trampoline(uspace)?;
+
     let user_sp = ustack_end - stack_data.len();
+    // The parser leaves fixed bytes behind `AT_RANDOM`, which libc seeds its
+    // stack protector and pointer guard with.
+    if let Some(random) = auxv
+        .iter()
+        .find(|entry| entry.get_type() == AuxvType::RANDOM)
+    {
+        let offset = random.value() - user_sp.as_usize();
+        fill_random(&mut stack_data[offset..offset + 16]);
+    }
 
     uspace.write(user_sp, stack_data.as_slice())?;
 
-    Ok((entry, user_sp))
+    let auxv = auxv
+        .iter()
+        .take_while(|entry| entry.get_type() != AuxvType::NULL)
+        .map(|entry| (entry.get_type() as usize, entry.value()))
+        .chain([(AuxvType::NULL as usize, 0)])
+        .collect();
+    Ok((entry, user_sp, auxv))
 }
 
 #[percpu::def_percpu]
@@ -184,6 +382,102 @@ pub fn access_user_memory<R>(f: impl FnOnce() -> R) -> R {
     })
 }
 
+/// Counts the flushes of address spaces shared between tasks.
+static TLB_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// The last of those flushes the TLB of each CPU has caught up with.
+static TLB_SEEN: [AtomicU64; axconfig::SMP] = [const { AtomicU64::new(0) }; axconfig::SMP];
+
+/// Removes or restricts mappings of `aspace`, the current address space,
+/// with `f` on `locked`, the space with its lock held, then flushes the TLB
+/// of this CPU.
+///
+/// No interrupt reaches across CPUs here. If the space is shared, the other
+/// CPUs flush theirs on their next trap, at the latest on their next timer
+/// tick. The [`PendingFlush`] returned waits, once dropped, until every one
+/// running a task of the space has, and the lock is to be released first.
+/// The frames unmapped from the space meanwhile are only freed after that,
+/// so that none is reused while a stale entry still leads to it.
+pub fn update_user_mappings<R>(
+    aspace: &Arc<Mutex<AddrSpace>>,
+    locked: &mut AddrSpace,
+    f: impl FnOnce(&mut AddrSpace) -> R,
+) -> (R, PendingFlush) {
+    if Arc::strong_count(aspace) == 1 {
+        let result = f(locked);
+        flush_tlb(None);
+        return (result, PendingFlush(None));
+    }
+    let held = axmm::hold_frees(locked.page_table_root());
+    let result = f(locked);
+    flush_tlb(None);
+    let generation = TLB_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let pending = PendingFlush(Some((aspace.clone(), generation, held)));
+    (result, pending)
+}
+
+/// A flush of the TLBs of the CPUs running a shared address space, waited
+/// for when this is dropped, yielding meanwhile. The frames held back are
+/// freed then.
+pub struct PendingFlush(Option<(Arc<Mutex<AddrSpace>>, u64, axmm::HeldFrees)>);
+
+impl Drop for PendingFlush {
+    fn drop(&mut self) {
+        let Some((aspace, generation, held)) = self.0.take() else {
+            return;
+        };
+        // This CPU is waited for as well, as the task may have been moved to
+        // another one, which it then catches up itself.
+        for cpu in 0..axconfig::SMP {
+            while TLB_SEEN[cpu].load(Ordering::SeqCst) < generation && runs_aspace(cpu, &aspace) {
+                if !catch_up_tlb_on(cpu, generation) {
+                    axtask::yield_now();
+                }
+            }
+        }
+        drop(held);
+    }
+}
+
+/// Whether CPU `cpu` is running a task in `aspace`. One that has switched to
+/// another space since has flushed its TLB on the way.
+fn runs_aspace(cpu: usize, aspace: &Arc<Mutex<AddrSpace>>) -> bool {
+    axtask::cpu_running_task(cpu)
+        .and_then(find_task)
+        .is_some_and(|task| Arc::ptr_eq(&task.task_ext().aspace, aspace))
+}
+
+/// Catches the TLB of this CPU up on any trap, even from the kernel, so that
+/// a CPU running kernel code for long does not hold up the flushes.
+#[register_trap_handler(POST_TRAP)]
+fn sync_tlb(_tf: &mut TrapFrame, _from_user: bool) {
+    catch_up_tlb(TLB_GENERATION.load(Ordering::SeqCst));
+}
+
+/// Flushes the TLB of this CPU if it has not caught up with `generation`.
+/// Interrupts are to be disabled, so that the task stays on the CPU.
+fn catch_up_tlb(generation: u64) {
+    let seen = &TLB_SEEN[this_cpu_id()];
+    if seen.load(Ordering::Relaxed) < generation {
+        flush_tlb(None);
+        seen.fetch_max(generation, Ordering::SeqCst);
+    }
+}
+
+/// Catches the TLB of CPU `cpu` up with `generation` if it is this one.
+fn catch_up_tlb_on(cpu: usize, generation: u64) -> bool {
+    let irqs_enabled = axhal::arch::irqs_enabled();
+    axhal::arch::disable_irqs();
+    let here = this_cpu_id() == cpu;
+    if here {
+        catch_up_tlb(generation);
+    }
+    if irqs_enabled {
+        axhal::arch::enable_irqs();
+    }
+    here
+}
+
 #[register_trap_handler(PAGE_FAULT)]
 fn handle_page_fault(vaddr: VirtAddr, access_flags: MappingFlags, is_user: bool) -> bool {
     warn!(
@@ -194,18 +488,32 @@ fn handle_page_fault(vaddr: VirtAddr, access_flags: MappingFlags, is_user: bool)
         return false;
     }
 
-    if !axtask::current()
-        .task_ext()
-        .aspace
-        .lock()
-        .handle_page_fault(vaddr, access_flags)
-    {
+    let curr = axtask::current();
+    let mut aspace = curr.task_ext().aspace.lock();
+    if !aspace.handle_page_fault(vaddr, access_flags) {
+        if is_user {
+            // The address is inside a mapping that does not allow this access.
+            let mapped = aspace.check_region_access(
+                VirtAddrRange::from_start_size(vaddr, 1),
+                MappingFlags::empty(),
+            );
+            drop(aspace);
+            info!(
+                "{}: segmentation fault at {:#x}, sending SIGSEGV",
+                curr.id_name(),
+                vaddr
+            );
+            let code = if mapped { SEGV_ACCERR } else { SEGV_MAPERR };
+            force_signal(SigInfo::new_fault(SIGSEGV, code, vaddr.as_usize()));
+            return true;
+        }
         warn!(
             "{}: segmentation fault at {:#x}, exit!",
-            axtask::current().id_name(),
+            curr.id_name(),
             vaddr
         );
-        axtask::exit(-1);
+        drop(aspace);
+        exit_current(-1);
     }
     true
 }
@@ -0,0 +1,87 @@
+//! The kernel random number generator, shared by `getrandom` and the random
+//! devices.
+//!
+//! It is a xoshiro256** generator seeded from the clocks at first use, which
+//! every caller can stir with additional input. It is not cryptographically
+//! secure, but never blocks.
+
+use axhal::time::{monotonic_time_nanos, wall_time_nanos};
+use spin::Mutex;
+
+struct Rng {
+    state: [u64; 4],
+    seeded: bool,
+}
+
+static RNG: Mutex<Rng> = Mutex::new(Rng {
+    state: [0; 4],
+    seeded: false,
+});
+
+fn splitmix64(x: &mut u64) -> u64 {
+    *x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *x;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl Rng {
+    fn seed(&mut self) {
+        let mut seed = wall_time_nanos() ^ monotonic_time_nanos().rotate_left(32);
+        for word in self.state.iter_mut() {
+            *word = splitmix64(&mut seed);
+        }
+        self.seeded = true;
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        if !self.seeded {
+            self.seed();
+        }
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    fn mix(&mut self, mut value: u64) {
+        if !self.seeded {
+            self.seed();
+        }
+        let word = splitmix64(&mut value);
+        self.state[0] ^= word;
+        // The all-zero state is a fixed point of the generator.
+        if self.state.iter().all(|&w| w == 0) {
+            self.state[0] = 1;
+        }
+    }
+}
+
+/// Fills `buf` with random bytes.
+pub fn fill_random(buf: &mut [u8]) {
+    let mut rng = RNG.lock();
+    // Every request perturbs the state with the current time.
+    rng.mix(monotonic_time_nanos());
+    for chunk in buf.chunks_mut(8) {
+        let bytes = rng.next_u64().to_ne_bytes();
+        chunk.copy_from_slice(&bytes[..chunk.len()]);
+    }
+}
+
+/// Stirs `data` into the generator state.
+pub fn add_entropy(data: &[u8]) {
+    let mut rng = RNG.lock();
+    for chunk in data.chunks(8) {
+        let mut bytes = [0; 8];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+        rng.mix(u64::from_ne_bytes(bytes));
+        rng.next_u64();
+    }
+}
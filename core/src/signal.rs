@@ -0,0 +1,919 @@
+//! Signal management for user tasks.
+
+use alloc::{
+    collections::{BTreeSet, VecDeque},
+    vec::Vec,
+};
+use core::{mem::size_of, time::Duration};
+
+use axhal::{
+    arch::TrapFrame,
+    paging::MappingFlags,
+    trap::{POST_TRAP, USER_EXCEPTION, UserException, register_trap_handler},
+};
+use axtask::{AxTaskRef, TaskExtRef, TaskState, current};
+use memory_addr::{MemoryAddr, VirtAddr, VirtAddrRange};
+
+use crate::task::{all_tasks, exit_current, exit_group, find_task};
+
+/// The number of signals supported.
+pub const NSIG: usize = 64;
+
+pub const SIGHUP: usize = 1;
+pub const SIGINT: usize = 2;
+pub const SIGQUIT: usize = 3;
+pub const SIGILL: usize = 4;
+pub const SIGTRAP: usize = 5;
+pub const SIGABRT: usize = 6;
+pub const SIGBUS: usize = 7;
+pub const SIGFPE: usize = 8;
+pub const SIGKILL: usize = 9;
+pub const SIGUSR1: usize = 10;
+pub const SIGSEGV: usize = 11;
+pub const SIGUSR2: usize = 12;
+pub const SIGPIPE: usize = 13;
+pub const SIGALRM: usize = 14;
+pub const SIGTERM: usize = 15;
+pub const SIGSTKFLT: usize = 16;
+pub const SIGCHLD: usize = 17;
+pub const SIGCONT: usize = 18;
+pub const SIGSTOP: usize = 19;
+pub const SIGTSTP: usize = 20;
+pub const SIGTTIN: usize = 21;
+pub const SIGTTOU: usize = 22;
+pub const SIGURG: usize = 23;
+pub const SIGXCPU: usize = 24;
+pub const SIGXFSZ: usize = 25;
+pub const SIGVTALRM: usize = 26;
+pub const SIGPROF: usize = 27;
+pub const SIGWINCH: usize = 28;
+pub const SIGIO: usize = 29;
+pub const SIGPWR: usize = 30;
+pub const SIGSYS: usize = 31;
+/// The first real-time signal. Real-time signals are queued, standard ones are not.
+pub const SIGRTMIN: usize = 32;
+
+/// Default signal handler.
+pub const SIG_DFL: usize = 0;
+/// Ignore the signal.
+pub const SIG_IGN: usize = 1;
+
+/// `si_code` values.
+pub const SI_USER: i32 = 0;
+pub const SI_KERNEL: i32 = 0x80;
+pub const SI_TKILL: i32 = -6;
+pub const SEGV_MAPERR: i32 = 1;
+pub const SEGV_ACCERR: i32 = 2;
+pub const BUS_ADRALN: i32 = 1;
+pub const FPE_INTDIV: i32 = 1;
+pub const ILL_ILLOPC: i32 = 1;
+
+bitflags::bitflags! {
+    /// Flags of `struct sigaction`.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct SigActionFlags: usize {
+        const SA_NOCLDSTOP = 1;
+        const SA_NOCLDWAIT = 2;
+        const SA_SIGINFO = 4;
+        const SA_RESTORER = 0x0400_0000;
+        const SA_ONSTACK = 0x0800_0000;
+        const SA_RESTART = 0x1000_0000;
+        const SA_NODEFER = 0x4000_0000;
+        const SA_RESETHAND = 0x8000_0000;
+    }
+}
+
+/// A set of signals, in the layout of the kernel `sigset_t`.
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SignalSet(pub u64);
+
+impl SignalSet {
+    /// Signals that can be neither caught, blocked nor ignored.
+    pub const UNBLOCKABLE: Self = Self((1 << (SIGKILL - 1)) | (1 << (SIGSTOP - 1)));
+    /// Signals that stop the process by default.
+    pub const STOP: Self = Self(
+        (1 << (SIGSTOP - 1)) | (1 << (SIGTSTP - 1)) | (1 << (SIGTTIN - 1)) | (1 << (SIGTTOU - 1)),
+    );
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn contains(&self, sig: usize) -> bool {
+        sig >= 1 && sig <= NSIG && self.0 & (1 << (sig - 1)) != 0
+    }
+
+    pub fn add(&mut self, sig: usize) {
+        if (1..=NSIG).contains(&sig) {
+            self.0 |= 1 << (sig - 1);
+        }
+    }
+
+    pub fn remove(&mut self, sig: usize) {
+        if (1..=NSIG).contains(&sig) {
+            self.0 &= !(1 << (sig - 1));
+        }
+    }
+
+    /// Returns the lowest-numbered signal in the set that is not in `mask`.
+    pub fn first_unmasked(&self, mask: SignalSet) -> Option<usize> {
+        let bits = self.0 & !mask.0;
+        (bits != 0).then(|| bits.trailing_zeros() as usize + 1)
+    }
+}
+
+/// Signal information, in the layout of `siginfo_t`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SigInfo {
+    pub signo: i32,
+    pub errno: i32,
+    pub code: i32,
+    _pad: i32,
+    /// The signal-specific union, e.g. `si_addr` for faults or `si_pid` and
+    /// `si_uid` for signals sent by a process.
+    pub fields: [u64; 14],
+}
+
+impl SigInfo {
+    pub const fn new(signo: usize, code: i32) -> Self {
+        Self {
+            signo: signo as i32,
+            errno: 0,
+            code,
+            _pad: 0,
+            fields: [0; 14],
+        }
+    }
+
+    /// Creates the information of a synchronous fault at `addr`.
+    pub const fn new_fault(signo: usize, code: i32, addr: usize) -> Self {
+        let mut info = Self::new(signo, code);
+        info.fields[0] = addr as u64;
+        info
+    }
+
+    /// Creates the information of a signal for the I/O event `code` on `fd`,
+    /// with `band` the poll events that occurred.
+    pub const fn new_poll(signo: usize, code: i32, band: u64, fd: i32) -> Self {
+        let mut info = Self::new(signo, code);
+        info.fields[0] = band;
+        info.fields[1] = fd as u32 as u64;
+        info
+    }
+
+    /// Creates the information of a signal sent by process `pid`.
+    pub const fn new_user(signo: usize, code: i32, pid: u32, uid: u32) -> Self {
+        let mut info = Self::new(signo, code);
+        info.fields[0] = (pid as u64) | ((uid as u64) << 32);
+        info
+    }
+
+    pub const fn signo(&self) -> usize {
+        self.signo as usize
+    }
+}
+
+/// A signal disposition, in the layout of the kernel `struct sigaction`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SigAction {
+    pub handler: usize,
+    pub flags: usize,
+    /// Only present on architectures that define `SA_RESTORER`.
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    pub restorer: usize,
+    pub mask: SignalSet,
+}
+
+impl SigAction {
+    pub fn flags(&self) -> SigActionFlags {
+        SigActionFlags::from_bits_truncate(self.flags)
+    }
+
+    /// The user function that the handler returns to, if the task provided one.
+    pub fn restorer(&self) -> Option<usize> {
+        #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+        if self.flags().contains(SigActionFlags::SA_RESTORER) && self.restorer != 0 {
+            return Some(self.restorer);
+        }
+        None
+    }
+}
+
+/// Signal dispositions of a task, indexed by `signo - 1`.
+#[derive(Clone)]
+pub struct SignalActions(pub [SigAction; NSIG]);
+
+impl Default for SignalActions {
+    fn default() -> Self {
+        Self([SigAction::default(); NSIG])
+    }
+}
+
+impl SignalActions {
+    pub fn get(&self, sig: usize) -> SigAction {
+        self.0[sig - 1]
+    }
+
+    pub fn set(&mut self, sig: usize, action: SigAction) {
+        self.0[sig - 1] = action;
+    }
+}
+
+/// What happens to a task when a signal with the default disposition is
+/// delivered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefaultAction {
+    Terminate,
+    CoreDump,
+    Ignore,
+    Stop,
+    Continue,
+}
+
+impl DefaultAction {
+    pub fn of(sig: usize) -> Self {
+        match sig {
+            SIGQUIT | SIGILL | SIGTRAP | SIGABRT | SIGBUS | SIGFPE | SIGSEGV | SIGXCPU
+            | SIGXFSZ | SIGSYS => Self::CoreDump,
+            SIGCHLD | SIGURG | SIGWINCH => Self::Ignore,
+            SIGSTOP | SIGTSTP | SIGTTIN | SIGTTOU => Self::Stop,
+            SIGCONT => Self::Continue,
+            _ => Self::Terminate,
+        }
+    }
+}
+
+/// Flags of [`SignalStack`].
+pub const SS_ONSTACK: i32 = 1;
+pub const SS_DISABLE: i32 = 2;
+/// The minimum size of an alternate signal stack.
+pub const MINSIGSTKSZ: usize = 2048;
+
+/// An alternate signal stack, in the layout of `stack_t`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SignalStack {
+    pub sp: usize,
+    pub flags: i32,
+    pub size: usize,
+}
+
+impl Default for SignalStack {
+    fn default() -> Self {
+        Self {
+            sp: 0,
+            flags: SS_DISABLE,
+            size: 0,
+        }
+    }
+}
+
+impl SignalStack {
+    pub fn enabled(&self) -> bool {
+        self.flags & SS_DISABLE == 0
+    }
+
+    /// Whether `sp` is on this stack.
+    pub fn contains(&self, sp: usize) -> bool {
+        self.enabled() && sp > self.sp && sp <= self.sp + self.size
+    }
+}
+
+/// What `restart_syscall` resumes, for a syscall that a signal interrupted
+/// with some time left to wait.
+#[derive(Debug, Clone, Copy)]
+pub enum RestartBlock {
+    /// `nanosleep` until `deadline` on the monotonic clock, storing the time
+    /// left to `rem` if interrupted again.
+    Nanosleep { deadline: Duration, rem: usize },
+}
+
+/// How a syscall that a signal has just interrupted is restarted.
+#[derive(Debug, Clone, Copy)]
+enum Restart {
+    /// Entered again as it was if no handler runs or the handler has
+    /// `SA_RESTART`, with the value to put back in the return value register.
+    Again(usize),
+    /// Resumed with `restart_syscall` from the restart block if no handler
+    /// runs. A handler always sees it fail with `EINTR`.
+    Block,
+}
+
+/// Per-thread signal state.
+#[derive(Default)]
+pub struct SignalState {
+    /// Signals that have been sent but not yet delivered.
+    pending: SignalSet,
+    /// The information of pending signals, in the order they were sent.
+    queue: VecDeque<SigInfo>,
+    /// Signals that are currently blocked.
+    pub blocked: SignalSet,
+    /// The alternate stack for handlers installed with `SA_ONSTACK`.
+    pub altstack: SignalStack,
+    /// How to restart the syscall that a signal has just interrupted.
+    restart: Option<Restart>,
+    /// What `restart_syscall` resumes.
+    restart_block: Option<RestartBlock>,
+    /// The mask to go back to when the current syscall returns, if it waits
+    /// with a temporary one.
+    saved_blocked: Option<SignalSet>,
+}
+
+impl SignalState {
+    /// Inherits the blocked mask and the alternate stack of `parent`; no
+    /// pending signal is inherited.
+    pub fn inherit(parent: &SignalState) -> Self {
+        Self {
+            blocked: parent.blocked,
+            altstack: parent.altstack,
+            ..Default::default()
+        }
+    }
+
+    pub fn pending(&self) -> SignalSet {
+        self.pending
+    }
+
+    /// Blocks `mask` instead until the current syscall returns, as `ppoll`
+    /// does. A handler run on the way back to user space still sees `mask`,
+    /// but returns to the original one.
+    pub fn set_temporary_mask(&mut self, mut mask: SignalSet) {
+        mask.0 &= !SignalSet::UNBLOCKABLE.0;
+        self.saved_blocked.get_or_insert(self.blocked);
+        self.blocked = mask;
+    }
+
+    /// Queues a signal. A standard signal that is already pending is dropped.
+    pub fn send(&mut self, info: SigInfo) {
+        let sig = info.signo();
+        if sig < SIGRTMIN && self.pending.contains(sig) {
+            return;
+        }
+        self.pending.add(sig);
+        self.queue.push_back(info);
+    }
+
+    /// Takes the lowest-numbered pending signal that is not blocked.
+    pub fn dequeue(&mut self) -> Option<SigInfo> {
+        let sig = self.pending.first_unmasked(self.blocked)?;
+        self.take(sig)
+    }
+
+    /// Takes the lowest-numbered pending signal in `set`, whether blocked or
+    /// not, as reading a signalfd does.
+    pub fn dequeue_from(&mut self, set: SignalSet) -> Option<SigInfo> {
+        let sig = self.pending.first_unmasked(SignalSet(!set.0))?;
+        self.take(sig)
+    }
+
+    /// Takes the first queued instance of the pending signal `sig`.
+    fn take(&mut self, sig: usize) -> Option<SigInfo> {
+        let index = self.queue.iter().position(|info| info.signo() == sig)?;
+        let info = self.queue.remove(index)?;
+        if !self.queue.iter().any(|info| info.signo() == sig) {
+            self.pending.remove(sig);
+        }
+        Some(info)
+    }
+
+    /// Drops the pending signals in `set`.
+    fn discard(&mut self, set: SignalSet) {
+        self.pending.0 &= !set.0;
+        self.queue.retain(|info| !set.contains(info.signo()));
+    }
+
+    /// Whether there is a pending signal that can be delivered.
+    pub fn has_deliverable(&self) -> bool {
+        self.pending.first_unmasked(self.blocked).is_some()
+    }
+}
+
+/// Sends a signal to `task`.
+///
+/// A signal the task ignores is discarded right away, unless blocked since the
+/// disposition may change before it is unblocked.
+pub fn send_signal(task: &AxTaskRef, info: SigInfo) {
+    let sig = info.signo();
+    let ext = task.task_ext();
+    // Continuing and stopping cancel each other out, whatever the
+    // disposition.
+    if sig == SIGCONT {
+        ext.continue_process();
+        discard_in_process(task, SignalSet::STOP);
+    } else if SignalSet::STOP.contains(sig) {
+        discard_in_process(task, SignalSet(1 << (SIGCONT - 1)));
+    }
+    let action = ext.signal_actions.lock().get(sig);
+    let mut state = ext.signal.lock();
+    let ignored = match action.handler {
+        SIG_IGN => true,
+        SIG_DFL => DefaultAction::of(sig) == DefaultAction::Ignore,
+        _ => false,
+    };
+    if ignored && !state.blocked.contains(sig) {
+        return;
+    }
+    state.send(info);
+}
+
+/// Drops the signals in `set` pending in any thread of the process of `task`.
+fn discard_in_process(task: &AxTaskRef, set: SignalSet) {
+    for thread in all_tasks() {
+        if thread.tgid() == task.tgid() {
+            thread.task_ext().signal.lock().discard(set);
+        }
+    }
+}
+
+/// Sends a signal to the process `tgid`, to be taken by one of its threads
+/// that are still running: the main thread if it does not block the signal,
+/// or else another that does not, or else any of them.
+pub fn send_signal_to_process(tgid: u64, info: SigInfo) {
+    let sig = info.signo();
+    let threads: Vec<_> = all_tasks()
+        .into_iter()
+        .filter(|task| task.tgid() == tgid && task.state() != TaskState::Exited)
+        .collect();
+    let blocks = |task: &AxTaskRef| task.task_ext().signal.lock().blocked.contains(sig);
+    let is_main = |task: &AxTaskRef| task.id().as_u64() == tgid;
+    let target = threads
+        .iter()
+        .find(|task| is_main(task) && !blocks(task))
+        .or_else(|| threads.iter().find(|task| !blocks(task)))
+        .or_else(|| threads.first());
+    if let Some(task) = target {
+        send_signal(task, info);
+    }
+}
+
+/// The processes with a thread that `filter` accepts.
+pub fn processes_with(mut filter: impl FnMut(&AxTaskRef) -> bool) -> BTreeSet<u64> {
+    all_tasks()
+        .into_iter()
+        .filter(|task| filter(task))
+        .map(|task| task.tgid())
+        .collect()
+}
+
+/// Sends a signal to every process in the process group `pgid`, once each.
+pub fn send_signal_to_group(pgid: u64, info: SigInfo) {
+    for tgid in processes_with(|task| task.task_ext().pgid() == pgid) {
+        send_signal_to_process(tgid, info);
+    }
+}
+
+/// Whether the current task has a signal to deliver, which interrupts a
+/// blocking syscall.
+///
+/// A thread whose group is exiting is taken to have one, so that it returns
+/// to exit.
+pub fn signal_pending() -> bool {
+    let curr = current();
+    let ext = curr.task_ext();
+    ext.group_exit().is_some() || ext.signal.lock().has_deliverable()
+}
+
+struct SignalIfImpl;
+
+#[crate_interface::impl_interface]
+impl arceos_posix_api::SignalIf for SignalIfImpl {
+    fn signal_pending() -> bool {
+        signal_pending()
+    }
+}
+
+/// Marks the syscall `syscall_num` that `tf` entered with as interrupted by a
+/// signal, so that it gets restarted if the handler of the signal has
+/// `SA_RESTART`.
+///
+/// It must be called before the syscall return value is written to `tf`.
+pub fn syscall_interrupted(tf: &TrapFrame, syscall_num: usize) {
+    // x86_64 returns in the register holding the syscall number, the other
+    // architectures in the one holding the first argument.
+    let orig = if cfg!(target_arch = "x86_64") {
+        syscall_num
+    } else {
+        tf.arg0()
+    };
+    current().task_ext().signal.lock().restart = Some(Restart::Again(orig));
+}
+
+/// Marks the current syscall as interrupted by a signal, to be resumed from
+/// `block` by `restart_syscall` unless a handler runs.
+pub fn syscall_interrupted_with_block(block: RestartBlock) {
+    let mut state = current().task_ext().signal.lock();
+    state.restart = Some(Restart::Block);
+    state.restart_block = Some(block);
+}
+
+/// Takes the restart block that `restart_syscall` resumes, if any.
+pub fn take_restart_block() -> Option<RestartBlock> {
+    current().task_ext().signal.lock().restart_block.take()
+}
+
+/// The length of the syscall instruction.
+const SYSCALL_INSN_LEN: usize = if cfg!(target_arch = "x86_64") { 2 } else { 4 };
+
+/// The number of `restart_syscall`.
+const SYS_RESTART_SYSCALL: usize = if cfg!(target_arch = "x86_64") {
+    219
+} else {
+    128
+};
+
+/// Makes `tf` execute the syscall instruction it has just returned from again.
+fn restart_syscall(tf: &mut TrapFrame, orig: usize) {
+    tf.set_retval(orig);
+    tf.set_ip(tf.ip() - SYSCALL_INSN_LEN);
+}
+
+/// Makes `tf` go back to the syscall instruction it has just returned from
+/// to enter `restart_syscall` instead.
+fn restart_with_block(tf: &mut TrapFrame) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        tf.rax = SYS_RESTART_SYSCALL as _;
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        tf.r[8] = SYS_RESTART_SYSCALL as _;
+    }
+    #[cfg(any(target_arch = "riscv64", target_arch = "loongarch64"))]
+    {
+        tf.regs.a7 = SYS_RESTART_SYSCALL;
+    }
+    tf.set_ip(tf.ip() - SYSCALL_INSN_LEN);
+}
+
+/// Sends a signal to the current task.
+pub fn send_signal_to_current(info: SigInfo) {
+    let curr = current();
+    curr.task_ext().signal.lock().send(info);
+}
+
+/// Sends a signal raised by the current instruction of the current task.
+///
+/// Such a signal cannot be deferred: if it is blocked or ignored, the
+/// disposition is reset to the default so the task is not re-executing the
+/// faulting instruction forever.
+pub fn force_signal(info: SigInfo) {
+    let curr = current();
+    let sig = info.signo();
+    let ext = curr.task_ext();
+    let mut state = ext.signal.lock();
+    let mut actions = ext.signal_actions.lock();
+    if state.blocked.contains(sig) || actions.get(sig).handler == SIG_IGN {
+        state.blocked.remove(sig);
+        actions.set(sig, SigAction::default());
+    }
+    state.send(info);
+}
+
+/// Terminates the process of the current task as if it was killed by `sig`,
+/// dumping core if `core_dumped` is set. It is only reported to have dumped
+/// core if one was written.
+pub fn exit_with_signal(sig: usize, core_dumped: bool) -> ! {
+    let curr = current();
+    let core_dumped = core_dumped && crate::coredump::dump_core(sig);
+    warn!(
+        "{}: killed by signal {}{}",
+        curr.id_name(),
+        sig,
+        if core_dumped { " (core dumped)" } else { "" }
+    );
+    // The status is reported to `wait4` by the thread group leader.
+    let leader = find_task(curr.tgid()).unwrap_or_else(|| curr.clone());
+    leader.task_ext().set_term_signal(sig, core_dumped);
+    exit_group(128 + sig as i32);
+}
+
+/// The user context saved by the kernel at signal delivery, in the layout of
+/// `ucontext_t` up to `uc_sigmask`.
+///
+/// The machine context is the trap frame; its layout is private to the kernel
+/// and only meant to be handed back by `rt_sigreturn`.
+#[repr(C, align(16))]
+#[derive(Clone, Copy)]
+pub struct UContext {
+    pub flags: usize,
+    pub link: usize,
+    pub stack: SignalStack,
+    pub sigmask: SignalSet,
+    /// Room for a larger `sigset_t`, as reserved by the kernel ABI.
+    _unused: [u8; 120],
+    pub mcontext: TrapFrame,
+}
+
+/// The frame pushed onto the user stack when a handler is invoked.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SignalFrame {
+    info: SigInfo,
+    ucontext: UContext,
+}
+
+fn copy_to_user<T: Copy>(addr: usize, value: &T) -> bool {
+    let curr = current();
+    let mut aspace = curr.task_ext().aspace.lock();
+    let start = VirtAddr::from(addr);
+    let size = size_of::<T>();
+    if !aspace.check_region_access(
+        VirtAddrRange::from_start_size(start, size),
+        MappingFlags::READ | MappingFlags::WRITE,
+    ) {
+        return false;
+    }
+    let page_start = start.align_down_4k();
+    let page_end = (start + size).align_up_4k();
+    if aspace
+        .populate_area(page_start, page_end - page_start)
+        .is_err()
+    {
+        return false;
+    }
+    // SAFETY: `T` is a plain-old-data type with no padding requirements here.
+    let bytes = unsafe { core::slice::from_raw_parts(value as *const T as *const u8, size) };
+    aspace.write(start, bytes).is_ok()
+}
+
+fn copy_from_user<T: Copy>(addr: usize) -> Option<T> {
+    let curr = current();
+    let mut aspace = curr.task_ext().aspace.lock();
+    let start = VirtAddr::from(addr);
+    let size = size_of::<T>();
+    if !aspace.check_region_access(
+        VirtAddrRange::from_start_size(start, size),
+        MappingFlags::READ,
+    ) {
+        return None;
+    }
+    let page_start = start.align_down_4k();
+    let page_end = (start + size).align_up_4k();
+    aspace
+        .populate_area(page_start, page_end - page_start)
+        .ok()?;
+    let mut value = core::mem::MaybeUninit::<T>::uninit();
+    // SAFETY: the buffer covers exactly `size_of::<T>()` bytes of `value`.
+    let bytes = unsafe { core::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, size) };
+    aspace.read(start, bytes).ok()?;
+    // SAFETY: all bytes have been initialized by the read above.
+    Some(unsafe { value.assume_init() })
+}
+
+/// Redirects the user context in `tf` to the handler of `info`.
+///
+/// The frame goes onto the alternate stack if the handler asks for it and the
+/// task is not already running on it, otherwise onto the current user stack.
+/// Returns false if the frame cannot be written.
+fn setup_frame(
+    tf: &mut TrapFrame,
+    info: &SigInfo,
+    action: &SigAction,
+    mask: SignalSet,
+    altstack: SignalStack,
+) -> bool {
+    let frame = SignalFrame {
+        info: *info,
+        ucontext: UContext {
+            flags: 0,
+            link: 0,
+            stack: SignalStack {
+                flags: if altstack.contains(tf.sp()) {
+                    SS_ONSTACK
+                } else {
+                    altstack.flags
+                },
+                ..altstack
+            },
+            sigmask: mask,
+            _unused: [0; 120],
+            mcontext: *tf,
+        },
+    };
+
+    let mut sp = tf.sp();
+    if action.flags().contains(SigActionFlags::SA_ONSTACK)
+        && altstack.enabled()
+        && !altstack.contains(sp)
+    {
+        sp = altstack.sp + altstack.size;
+    } else {
+        // Skip the red zone below the stack pointer.
+        #[cfg(target_arch = "x86_64")]
+        {
+            sp -= 128;
+        }
+    }
+    sp = (sp - size_of::<SignalFrame>()) & !0xf;
+    let frame_addr = sp;
+    if !copy_to_user(frame_addr, &frame) {
+        return false;
+    }
+
+    let restorer = action
+        .restorer()
+        .unwrap_or(axconfig::plat::SIGNAL_TRAMPOLINE);
+    #[cfg(target_arch = "x86_64")]
+    {
+        // The handler is entered as if it was called, with the return address
+        // on the stack.
+        sp -= size_of::<usize>();
+        if !copy_to_user(sp, &restorer) {
+            return false;
+        }
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    tf.set_ra(restorer);
+
+    tf.set_sp(sp);
+    tf.set_ip(action.handler);
+    tf.set_arg0(info.signo());
+    tf.set_arg1(frame_addr + core::mem::offset_of!(SignalFrame, info));
+    tf.set_arg2(frame_addr + core::mem::offset_of!(SignalFrame, ucontext));
+    true
+}
+
+/// Restores the user context saved by [`setup_frame`] when the handler
+/// returns through `rt_sigreturn`.
+///
+/// Returns false if the frame on the user stack is inaccessible.
+pub fn restore_frame(tf: &mut TrapFrame) -> bool {
+    let frame_addr = tf.sp();
+    let Some(frame) = copy_from_user::<SignalFrame>(frame_addr) else {
+        return false;
+    };
+    let ucontext = frame.ucontext;
+
+    let mut saved = ucontext.mcontext;
+    // Never let the user change the privileged part of the context.
+    #[cfg(target_arch = "riscv64")]
+    {
+        saved.sstatus = tf.sstatus;
+    }
+    #[cfg(target_arch = "x86_64")]
+    {
+        // CF, PF, AF, ZF, SF, TF, DF and OF
+        const USER_RFLAGS: u64 = 0xdd5;
+        saved.cs = tf.cs;
+        saved.ss = tf.ss;
+        saved.vector = tf.vector;
+        saved.error_code = tf.error_code;
+        saved.rflags = (saved.rflags & USER_RFLAGS) | (tf.rflags & !USER_RFLAGS);
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        // The NZCV condition flags
+        const USER_SPSR: u64 = 0xf000_0000;
+        saved.spsr = (saved.spsr & USER_SPSR) | (tf.spsr & !USER_SPSR);
+    }
+    #[cfg(target_arch = "loongarch64")]
+    {
+        saved.prmd = tf.prmd;
+    }
+    // The syscall return path advances the instruction pointer past `ecall`
+    // on these architectures, compensate for it.
+    #[cfg(any(target_arch = "riscv64", target_arch = "loongarch64"))]
+    saved.set_ip(saved.ip() - 4);
+
+    *tf = saved;
+    let mut mask = ucontext.sigmask;
+    mask.0 &= !SignalSet::UNBLOCKABLE.0;
+    let curr = current();
+    let mut state = curr.task_ext().signal.lock();
+    state.blocked = mask;
+    // The handler may have changed the alternate stack in the saved context.
+    if !state.altstack.contains(tf.sp()) {
+        let stack = ucontext.stack;
+        if stack.flags & SS_DISABLE != 0 {
+            state.altstack = SignalStack::default();
+        } else if stack.size >= MINSIGSTKSZ {
+            state.altstack = SignalStack { flags: 0, ..stack };
+        }
+    }
+    true
+}
+
+/// How long a stopped thread sleeps at most before it checks for `SIGKILL`,
+/// which does not wake it.
+const STOP_SLICE: Duration = Duration::from_millis(10);
+
+/// Keeps the current thread from returning to user space while its process
+/// is stopped, until `SIGCONT` continues it or it is killed.
+fn wait_while_stopped() {
+    let curr = current();
+    let ext = curr.task_ext();
+    while ext.stop_signal() != 0 {
+        if let Some(exit_code) = ext.group_exit() {
+            exit_current(exit_code);
+        }
+        if ext.signal.lock().pending().contains(SIGKILL) {
+            return;
+        }
+        ext.wait_continued(STOP_SLICE);
+    }
+}
+
+/// Delivers the pending signals of the current task before it returns to
+/// user space.
+#[register_trap_handler(POST_TRAP)]
+fn handle_pending_signals(tf: &mut TrapFrame, from_user: bool) {
+    if !from_user {
+        return;
+    }
+    let curr = current();
+    let ext = curr.task_ext();
+    if let Some(exit_code) = ext.group_exit() {
+        exit_current(exit_code);
+    }
+    wait_while_stopped();
+    let (mut restart, saved_blocked) = {
+        let mut state = ext.signal.lock();
+        (state.restart.take(), state.saved_blocked.take())
+    };
+    loop {
+        let Some((info, blocked, altstack)) = ({
+            let mut state = ext.signal.lock();
+            state
+                .dequeue()
+                .map(|info| (info, state.blocked, state.altstack))
+        }) else {
+            // No handler has run, so the interruption is invisible to the task.
+            match restart {
+                Some(Restart::Again(orig)) => restart_syscall(tf, orig),
+                Some(Restart::Block) => restart_with_block(tf),
+                None => {}
+            }
+            if let Some(saved) = saved_blocked {
+                ext.signal.lock().blocked = saved;
+            }
+            return;
+        };
+        let sig = info.signo();
+        let action = ext.signal_actions.lock().get(sig);
+        match action.handler {
+            SIG_IGN => continue,
+            SIG_DFL => match DefaultAction::of(sig) {
+                DefaultAction::Terminate => exit_with_signal(sig, false),
+                DefaultAction::CoreDump => exit_with_signal(sig, true),
+                DefaultAction::Ignore | DefaultAction::Continue => continue,
+                DefaultAction::Stop => {
+                    ext.stop_process(sig);
+                    wait_while_stopped();
+                    continue;
+                }
+            },
+            _ => {
+                let mut new_blocked = blocked;
+                new_blocked.0 |= action.mask.0;
+                if !action.flags().contains(SigActionFlags::SA_NODEFER) {
+                    new_blocked.add(sig);
+                }
+                match restart.take() {
+                    Some(Restart::Again(orig)) => {
+                        if action.flags().contains(SigActionFlags::SA_RESTART) {
+                            restart_syscall(tf, orig);
+                        }
+                    }
+                    Some(Restart::Block) => {
+                        ext.signal.lock().restart_block = None;
+                    }
+                    None => {}
+                }
+                let old_blocked = saved_blocked.unwrap_or(blocked);
+                if !setup_frame(tf, &info, &action, old_blocked, altstack) {
+                    // The user stack is unusable, so the handler cannot run.
+                    exit_with_signal(SIGSEGV, true);
+                }
+                if action.flags().contains(SigActionFlags::SA_RESETHAND) {
+                    ext.signal_actions.lock().set(sig, SigAction::default());
+                }
+                new_blocked.0 &= !SignalSet::UNBLOCKABLE.0;
+                ext.signal.lock().blocked = new_blocked;
+                return;
+            }
+        }
+    }
+}
+
+/// Turns CPU exceptions raised in user mode into synchronous signals.
+#[register_trap_handler(USER_EXCEPTION)]
+fn handle_user_exception(kind: UserException, addr: VirtAddr) -> bool {
+    let info = match kind {
+        UserException::IllegalInstruction => SigInfo::new_fault(SIGILL, ILL_ILLOPC, addr.into()),
+        UserException::Misaligned => SigInfo::new_fault(SIGBUS, BUS_ADRALN, addr.into()),
+        UserException::Arithmetic => SigInfo::new_fault(SIGFPE, FPE_INTDIV, addr.into()),
+        UserException::AccessFault => SigInfo::new_fault(SIGSEGV, SI_KERNEL, addr.into()),
+    };
+    info!(
+        "{}: {:?} at {:#x}, sending signal {}",
+        current().id_name(),
+        kind,
+        addr,
+        info.signo()
+    );
+    force_signal(info);
+    true
+}
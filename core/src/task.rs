@@ -1,41 +1,129 @@
 use core::{
     alloc::Layout,
     cell::UnsafeCell,
-    sync::atomic::{AtomicU64, Ordering},
+    sync::atomic::{AtomicBool, AtomicI32, AtomicU32, AtomicU64, AtomicUsize, Ordering},
+    time::Duration,
 };
 
 use alloc::{
+    collections::btree_map::BTreeMap,
     string::{String, ToString},
     sync::Arc,
     vec::Vec,
 };
-use arceos_posix_api::FD_TABLE;
+use arceos_posix_api::{FD_TABLE, FileLike};
 use axerrno::{AxError, AxResult};
 use axfs::{CURRENT_DIR, CURRENT_DIR_PATH};
 use axhal::{
     arch::{TrapFrame, UspaceContext},
-    time::{NANOS_PER_MICROS, NANOS_PER_SEC, monotonic_time_nanos},
+    paging::MappingFlags,
+    time::{NANOS_PER_MICROS, NANOS_PER_SEC},
 };
 use axmm::{AddrSpace, kernel_aspace};
 use axns::{AxNamespace, AxNamespaceIf};
 use axsync::Mutex;
-use axtask::{AxTaskRef, TaskExtRef, TaskInner, current};
-use memory_addr::VirtAddrRange;
-use spin::Once;
+use axtask::{AxTaskRef, TaskExtRef, TaskInner, WaitQueue, WeakAxTaskRef, current};
+use memory_addr::{MemoryAddr, PAGE_SIZE_4K, VirtAddr, VirtAddrRange};
+use spin::{Once, RwLock};
 
 use crate::{
-    ctypes::{CloneFlags, TimeStat, WaitStatus},
-    mm::copy_from_kernel,
+    cred::Cred,
+    ctypes::{
+        CloneFlags, RLIM_INFINITY, RLIM_NLIMITS, RLIMIT_NOFILE, RLIMIT_STACK, RLimit, TimeStat,
+        WaitFlags, WaitStatus,
+    },
+    mm::{Commit, copy_from_kernel, new_user_aspace_empty},
+    signal::{SignalActions, SignalState},
+    tty::{CONSOLE, TtyFile},
 };
 
+/// The highest capability number.
+pub const CAP_LAST_CAP: u32 = 40;
+/// All capabilities, which every process has as it runs as root.
+const CAP_FULL_SET: u64 = u64::MAX >> (63 - CAP_LAST_CAP);
+/// The timer slack a task starts with, in nanoseconds.
+pub const DEFAULT_TIMER_SLACK: u64 = 50_000;
+/// How high the hard limit on fds may be raised, the size of the fd table.
+pub const NR_OPEN: u64 = 1024;
+
+/// The bounds of the user heap, shared by the tasks that share an address
+/// space, as the break `brk` moves is that of the space.
+struct Heap {
+    bottom: AtomicU64,
+    top: AtomicU64,
+}
+
+impl Heap {
+    fn new(bottom: u64, top: u64) -> Self {
+        Self {
+            bottom: AtomicU64::new(bottom),
+            top: AtomicU64::new(top),
+        }
+    }
+}
+
+/// A change of a process between stopped and running that its parent has
+/// yet to learn of from `wait4`.
+#[derive(Clone, Copy)]
+enum JobChange {
+    Stopped(usize),
+    Continued,
+}
+
+/// Whether a process is stopped by a signal, shared by its threads.
+struct JobState {
+    /// The signal that stopped the process, or 0 if it is running.
+    stop_signal: AtomicUsize,
+    change: Mutex<Option<JobChange>>,
+    /// The threads waiting for the process to be continued.
+    wq: WaitQueue,
+}
+
+impl JobState {
+    fn new() -> Self {
+        Self {
+            stop_signal: AtomicUsize::new(0),
+            change: Mutex::new(None),
+            wq: WaitQueue::new(),
+        }
+    }
+}
+
+/// The threads of a process that have yet to exit, shared by them.
+struct Threads {
+    live: AtomicUsize,
+    /// Where the main thread waits for the others to exit.
+    wq: WaitQueue,
+}
+
+/// Where the parent of a `CLONE_VFORK` child waits for it to exec or exit,
+/// and so to be done with the memory they share.
+struct VforkDone {
+    done: AtomicBool,
+    wq: WaitQueue,
+}
+
 /// Task extended data for the monolithic kernel.
 pub struct TaskExt {
-    /// The process ID.
+    /// The thread ID, which is the process ID only for the main thread. The
+    /// process ID of any thread is its `tgid()`.
     pub proc_id: usize,
     /// The parent process ID.
     pub parent_id: AtomicU64,
-    /// children process
-    pub children: Mutex<Vec<AxTaskRef>>,
+    /// The process group ID.
+    pgid: AtomicU64,
+    /// The session ID.
+    sid: AtomicU64,
+    /// Whether the process has the terminal of its session as its controlling
+    /// terminal. See [`crate::tty::current_tty`].
+    has_tty: AtomicBool,
+    /// The children of the process, shared by its threads, as any of them
+    /// may wait for a child that another forked.
+    pub children: Arc<Mutex<Vec<AxTaskRef>>>,
+    /// The number of children that have exited so far.
+    child_exits: Arc<AtomicU64>,
+    /// Where the threads sleep in `wait4` until a child exits.
+    child_exit_wq: Arc<WaitQueue>,
     /// The clear thread tid field
     ///
     /// See <https://manpages.debian.org/unstable/manpages-dev/set_tid_address.2.en.html#clear_child_tid>
@@ -50,14 +138,71 @@ pub struct TaskExt {
     pub ns: AxNamespace,
     /// The time statistics
     pub time: UnsafeCell<TimeStat>,
-    /// The user heap bottom
-    pub heap_bottom: AtomicU64,
-    /// The user heap top
-    pub heap_top: AtomicU64,
-    /// The user stack size
-    pub stack_size: AtomicU64,
-    /// The limit of fd
-    pub fd_limit: AtomicU64,
+    /// The user heap of the address space.
+    heap: Arc<Heap>,
+    /// What the address space has committed.
+    commit: Arc<Commit>,
+    /// The resource limits of the process, by resource.
+    rlimits: Arc<Mutex<[RLimit; RLIM_NLIMITS]>>,
+    /// The nice value, from -20 for the most favorable scheduling to 19.
+    nice: AtomicI32,
+    /// The nice value lent by the waiters of the PI futexes the task owns, or
+    /// `i32::MAX` if there are none.
+    pi_boost: AtomicI32,
+    /// Whether a core is dumped when a signal kills the process.
+    dumpable: AtomicBool,
+    /// How far past their deadline the timeouts of the thread may run, in
+    /// nanoseconds, so that wakeups can be batched.
+    timer_slack: AtomicU64,
+    /// Whether orphaned descendants are reparented to this process rather
+    /// than to init. Not inherited by children.
+    child_subreaper: AtomicBool,
+    /// The signal dispositions
+    pub signal_actions: Arc<Mutex<SignalActions>>,
+    /// The pending and blocked signals of this thread
+    pub signal: Mutex<SignalState>,
+    /// The signal that terminated the task in the low 7 bits, with 0x80 set if
+    /// a core was dumped, or 0 if the task exited normally
+    term_signal: AtomicU64,
+    /// The code its thread group is exiting with, once a thread of it has
+    /// called `exit_group`.
+    group_exit: Mutex<Option<i32>>,
+    /// Whether the process is stopped.
+    job: Arc<JobState>,
+    /// The threads of the process still running.
+    threads: Arc<Threads>,
+    /// What the parent waits on if this is a `CLONE_VFORK` child that has
+    /// yet to exec or exit.
+    vfork_done: Mutex<Option<Arc<VforkDone>>>,
+    /// The capability bounding set, with bit `n` for capability `n`.
+    cap_bset: AtomicU64,
+    /// The user and group IDs.
+    cred: Mutex<Cred>,
+    /// The permission bits taken away from the mode files are created with,
+    /// shared along with the working directory.
+    umask: Arc<AtomicU32>,
+    /// The arguments the running program was started with.
+    args: Mutex<Vec<String>>,
+    /// The environment the running program was started with.
+    envs: Mutex<Vec<String>>,
+    /// The auxiliary vector the running program was started with, as pairs of
+    /// type and value.
+    auxv: Mutex<Vec<(usize, usize)>>,
+}
+
+/// The resource limits the first process starts with: none but on fds and
+/// a soft one on the stack.
+fn default_rlimits() -> [RLimit; RLIM_NLIMITS] {
+    let mut rlimits = [RLimit {
+        rlim_cur: RLIM_INFINITY,
+        rlim_max: RLIM_INFINITY,
+    }; RLIM_NLIMITS];
+    rlimits[RLIMIT_STACK as usize].rlim_cur = axconfig::plat::USER_STACK_SIZE as u64;
+    rlimits[RLIMIT_NOFILE as usize] = RLimit {
+        rlim_cur: NR_OPEN,
+        rlim_max: NR_OPEN,
+    };
+    rlimits
 }
 
 impl TaskExt {
@@ -70,16 +215,41 @@ impl TaskExt {
         Self {
             proc_id,
             parent_id: AtomicU64::new(1),
-            children: Mutex::new(Vec::new()),
+            pgid: AtomicU64::new(proc_id as u64),
+            sid: AtomicU64::new(proc_id as u64),
+            has_tty: AtomicBool::new(false),
+            children: Arc::new(Mutex::new(Vec::new())),
+            child_exits: Arc::new(AtomicU64::new(0)),
+            child_exit_wq: Arc::new(WaitQueue::new()),
             uctx,
             clear_child_tid: AtomicU64::new(0),
             aspace,
             ns: AxNamespace::new_thread_local(),
             time: TimeStat::new().into(),
-            heap_bottom: AtomicU64::new(heap_bottom),
-            heap_top: AtomicU64::new(heap_bottom),
-            stack_size: AtomicU64::new(axconfig::plat::USER_STACK_SIZE as u64),
-            fd_limit: AtomicU64::new(1024_u64),
+            heap: Arc::new(Heap::new(heap_bottom, heap_bottom)),
+            commit: Arc::new(Commit::default()),
+            rlimits: Arc::new(Mutex::new(default_rlimits())),
+            nice: AtomicI32::new(0),
+            pi_boost: AtomicI32::new(i32::MAX),
+            dumpable: AtomicBool::new(true),
+            timer_slack: AtomicU64::new(DEFAULT_TIMER_SLACK),
+            child_subreaper: AtomicBool::new(false),
+            signal_actions: Arc::new(Mutex::new(SignalActions::default())),
+            signal: Mutex::new(SignalState::default()),
+            term_signal: AtomicU64::new(0),
+            group_exit: Mutex::new(None),
+            job: Arc::new(JobState::new()),
+            threads: Arc::new(Threads {
+                live: AtomicUsize::new(1),
+                wq: WaitQueue::new(),
+            }),
+            vfork_done: Mutex::new(None),
+            cap_bset: AtomicU64::new(CAP_FULL_SET),
+            cred: Mutex::new(Cred::root()),
+            umask: Arc::new(AtomicU32::new(0o022)),
+            args: Mutex::new(Vec::new()),
+            envs: Mutex::new(Vec::new()),
+            auxv: Mutex::new(Vec::new()),
         }
     }
 
@@ -87,16 +257,31 @@ impl TaskExt {
         &self,
         flags: usize,
         stack: Option<usize>,
-        _ptid: usize,
-        _tls: usize,
-        _ctid: usize,
+        ptid: usize,
+        tls: usize,
+        ctid: usize,
     ) -> AxResult<u64> {
         info!("clone task: flags={:#x}, stack={:?}", flags, stack);
-        let _clone_flags = CloneFlags::from_bits((flags & !0x3f) as u32).unwrap();
+        let clone_flags = CloneFlags::from_bits((flags & !0x3f) as u32).unwrap();
+        // A thread shares the signal handlers of its group, which only make
+        // sense in the same address space.
+        let thread = clone_flags.contains(CloneFlags::CLONE_THREAD);
+        if thread && !clone_flags.contains(CloneFlags::CLONE_SIGHAND)
+            || clone_flags.contains(CloneFlags::CLONE_SIGHAND)
+                && !clone_flags.contains(CloneFlags::CLONE_VM)
+        {
+            return Err(AxError::InvalidInput);
+        }
 
+        let set_child_tid = clone_flags.contains(CloneFlags::CLONE_CHILD_SETTID);
         let mut new_task = TaskInner::new(
-            || {
+            move || {
                 let curr = axtask::current();
+                // It goes to the memory of the child, which is only its own
+                // once it runs.
+                if set_child_tid {
+                    put_user_u32(ctid, curr.id().as_u64() as u32);
+                }
                 let kstack_top = curr.kernel_stack_top().unwrap();
                 info!(
                     "Enter user space: entry={:#x}, ustack={:#x}, kstack={:#x}",
@@ -114,16 +299,27 @@ impl TaskExt {
             .ctx_mut()
             .set_tls(axhal::arch::read_thread_pointer().into());        
         let current_task = current();
-        let mut current_aspace = current_task.task_ext().aspace.lock();
-        let mut new_aspace = current_aspace.clone_or_err()?;
-        copy_from_kernel(&mut new_aspace)?;
+        // With `CLONE_VM` the child runs on the same page table, so that a
+        // mapping made by either is there for both.
+        let aspace = if clone_flags.contains(CloneFlags::CLONE_VM) {
+            current_task.task_ext().aspace.clone()
+        } else {
+            let mut new_aspace = current_task.task_ext().aspace.lock().clone_or_err()?;
+            copy_from_kernel(&mut new_aspace)?;
+            Arc::new(Mutex::new(new_aspace))
+        };
         new_task
             .ctx_mut()
-            .set_page_table_root(new_aspace.page_table_root());
+            .set_page_table_root(aspace.lock().page_table_root());
+        // The thread pointer of user space is switched along with the task on
+        // these, and is in the trap frame on the others.
+        let tls = clone_flags
+            .contains(CloneFlags::CLONE_SETTLS)
+            .then_some(tls);
         #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
         new_task
             .ctx_mut()
-            .set_tls(axhal::arch::read_thread_pointer().into());
+            .set_tls(tls.unwrap_or_else(axhal::arch::read_thread_pointer).into());
 
         let trap_frame = read_trapframe_from_kstack(current_task.get_kernel_stack_top().unwrap());
         let mut new_uctx = UspaceContext::from(&trap_frame);
@@ -138,15 +334,101 @@ impl TaskExt {
             new_uctx.set_ip(new_uctx_ip + 4);
         }
 
+        #[cfg(any(target_arch = "riscv64", target_arch = "loongarch64"))]
+        if let Some(tls) = tls {
+            new_uctx.regs.tp = tls;
+        }
+
         new_uctx.set_retval(0);
         let return_id: u64 = new_task.id().as_u64();
-        let new_task_ext = TaskExt::new(
+        if clone_flags.contains(CloneFlags::CLONE_PARENT_SETTID) {
+            put_user_u32(ptid, return_id as u32);
+        }
+        let mut new_task_ext = TaskExt::new(
             return_id as usize,
             new_uctx,
-            Arc::new(Mutex::new(new_aspace)),
+            aspace,
             axconfig::plat::USER_HEAP_BASE as _,
         );
-        new_task_ext.ns_init_new();
+        new_task_ext.heap = if clone_flags.contains(CloneFlags::CLONE_VM) {
+            current_task.task_ext().heap.clone()
+        } else {
+            let heap = &current_task.task_ext().heap;
+            Arc::new(Heap::new(
+                heap.bottom.load(Ordering::Acquire),
+                heap.top.load(Ordering::Acquire),
+            ))
+        };
+        if clone_flags.contains(CloneFlags::CLONE_VM) {
+            new_task_ext.commit = current_task.task_ext().commit.clone();
+        } else {
+            new_task_ext.recount_commit(&new_task_ext.aspace.lock());
+        }
+        if clone_flags.contains(CloneFlags::CLONE_CHILD_CLEARTID) {
+            new_task_ext.set_clear_child_tid(ctid as u64);
+        }
+        // The threads of a process all have its parent and children.
+        if thread {
+            new_task_ext.set_parent(current_task.task_ext().get_parent());
+            new_task_ext.children = current_task.task_ext().children.clone();
+            new_task_ext.child_exits = current_task.task_ext().child_exits.clone();
+            new_task_ext.child_exit_wq = current_task.task_ext().child_exit_wq.clone();
+        } else {
+            new_task_ext.set_parent(current_task.tgid());
+        }
+        new_task_ext.set_pgid(current_task.task_ext().pgid());
+        new_task_ext.set_sid(current_task.task_ext().sid());
+        new_task_ext.set_has_tty(current_task.task_ext().has_tty());
+        new_task_ext.cap_bset = AtomicU64::new(current_task.task_ext().cap_bset());
+        new_task_ext.cred = Mutex::new(current_task.task_ext().cred());
+        if thread {
+            new_task_ext.job = current_task.task_ext().job.clone();
+            new_task_ext.threads = current_task.task_ext().threads.clone();
+            new_task_ext.threads.live.fetch_add(1, Ordering::AcqRel);
+            // One made as the group is exiting exits along with it.
+            new_task_ext.group_exit = Mutex::new(current_task.task_ext().group_exit());
+        }
+        let vfork_done = clone_flags.contains(CloneFlags::CLONE_VFORK).then(|| {
+            Arc::new(VforkDone {
+                done: AtomicBool::new(false),
+                wq: WaitQueue::new(),
+            })
+        });
+        new_task_ext.vfork_done = Mutex::new(vfork_done.clone());
+        new_task_ext.rlimits = if thread {
+            current_task.task_ext().rlimits.clone()
+        } else {
+            Arc::new(Mutex::new(*current_task.task_ext().rlimits.lock()))
+        };
+        new_task_ext.nice = AtomicI32::new(current_task.task_ext().nice());
+        new_task_ext.dumpable = AtomicBool::new(current_task.task_ext().dumpable());
+        new_task_ext.timer_slack = AtomicU64::new(current_task.task_ext().timer_slack());
+        new_task_ext.umask = if clone_flags.contains(CloneFlags::CLONE_FS) {
+            current_task.task_ext().umask.clone()
+        } else {
+            Arc::new(AtomicU32::new(current_task.task_ext().umask()))
+        };
+        new_task_ext.args = Mutex::new(current_task.task_ext().args());
+        new_task_ext.envs = Mutex::new(current_task.task_ext().envs());
+        new_task_ext.auxv = Mutex::new(current_task.task_ext().auxv());
+        new_task_ext.signal_actions = if clone_flags.contains(CloneFlags::CLONE_SIGHAND) {
+            current_task.task_ext().signal_actions.clone()
+        } else {
+            Arc::new(Mutex::new(
+                current_task.task_ext().signal_actions.lock().clone(),
+            ))
+        };
+        new_task_ext.signal =
+            Mutex::new(SignalState::inherit(&current_task.task_ext().signal.lock()));
+        new_task_ext.ns_init(
+            clone_flags.contains(CloneFlags::CLONE_FILES),
+            clone_flags.contains(CloneFlags::CLONE_FS),
+        );
+        new_task.set_tgid(if thread {
+            current_task.tgid()
+        } else {
+            new_task_ext.proc_id as u64
+        });
         new_task.init_task_ext(new_task_ext);
         let new_task_ref = axtask::spawn_task(new_task);
         info!(
@@ -154,10 +436,30 @@ impl TaskExt {
             current_task.id_name(),
             new_task_ref.id_name()
         );
-        current_task.task_ext().children.lock().push(new_task_ref);
+        register_task(&new_task_ref);
+        // A thread is not waited for, but goes away once it has exited.
+        if !thread {
+            current_task.task_ext().children.lock().push(new_task_ref);
+        }
+        // The child may be running on the memory of the parent, its stack
+        // included, which is left to it until it is done.
+        if let Some(vfork_done) = vfork_done {
+            vfork_done
+                .wq
+                .wait_until(|| vfork_done.done.load(Ordering::Acquire));
+        }
         Ok(return_id)
     }
 
+    /// Lets the parent go on if this is a `CLONE_VFORK` child, as it execs or
+    /// exits.
+    fn release_vfork(&self) {
+        if let Some(vfork_done) = self.vfork_done.lock().take() {
+            vfork_done.done.store(true, Ordering::Release);
+            vfork_done.wq.notify_all(false);
+        }
+    }
+
     pub fn clear_child_tid(&self) -> u64 {
         self.clear_child_tid
             .load(core::sync::atomic::Ordering::Relaxed)
@@ -176,16 +478,95 @@ impl TaskExt {
         self.parent_id.store(parent_id, Ordering::Release);
     }
 
-    fn ns_init_new(&self) {
-        FD_TABLE
-            .deref_from(&self.ns)
-            .init_new(FD_TABLE.copy_inner());
-        CURRENT_DIR
-            .deref_from(&self.ns)
-            .init_new(CURRENT_DIR.copy_inner());
-        CURRENT_DIR_PATH
-            .deref_from(&self.ns)
-            .init_new(CURRENT_DIR_PATH.copy_inner());
+    pub fn pgid(&self) -> u64 {
+        self.pgid.load(Ordering::Acquire)
+    }
+
+    pub fn set_pgid(&self, pgid: u64) {
+        self.pgid.store(pgid, Ordering::Release);
+    }
+
+    pub fn sid(&self) -> u64 {
+        self.sid.load(Ordering::Acquire)
+    }
+
+    pub fn set_sid(&self, sid: u64) {
+        self.sid.store(sid, Ordering::Release);
+    }
+
+    pub fn has_tty(&self) -> bool {
+        self.has_tty.load(Ordering::Acquire)
+    }
+
+    pub fn set_has_tty(&self, has_tty: bool) {
+        self.has_tty.store(has_tty, Ordering::Release);
+    }
+
+    pub fn cap_bset(&self) -> u64 {
+        self.cap_bset.load(Ordering::Acquire)
+    }
+
+    /// Removes capability `cap` from the bounding set, for good.
+    pub fn drop_cap(&self, cap: u32) {
+        self.cap_bset.fetch_and(!(1 << cap), Ordering::AcqRel);
+    }
+
+    pub fn cred(&self) -> Cred {
+        self.cred.lock().clone()
+    }
+
+    pub fn set_cred(&self, cred: Cred) {
+        *self.cred.lock() = cred;
+    }
+
+    pub fn umask(&self) -> u32 {
+        self.umask.load(Ordering::Acquire)
+    }
+
+    /// Sets the umask to `umask`, returning the old one.
+    pub fn swap_umask(&self, umask: u32) -> u32 {
+        self.umask.swap(umask, Ordering::AcqRel)
+    }
+
+    pub fn args(&self) -> Vec<String> {
+        self.args.lock().clone()
+    }
+
+    pub fn envs(&self) -> Vec<String> {
+        self.envs.lock().clone()
+    }
+
+    pub fn auxv(&self) -> Vec<(usize, usize)> {
+        self.auxv.lock().clone()
+    }
+
+    /// Records what a program being started was given.
+    fn set_program(&self, args: &[String], envs: &[String], auxv: Vec<(usize, usize)>) {
+        *self.args.lock() = args.to_vec();
+        *self.envs.lock() = envs.to_vec();
+        *self.auxv.lock() = auxv;
+    }
+
+    /// Sets up the resources of the task from those of the current one. The
+    /// fd table is shared if `files` is set, as `CLONE_FILES` asks, and the
+    /// working directory if `fs` is, as `CLONE_FS` does; otherwise they are
+    /// copied.
+    fn ns_init(&self, files: bool, fs: bool) {
+        let fd_table = FD_TABLE.deref_from(&self.ns);
+        if files {
+            fd_table.init_shared(FD_TABLE.share());
+        } else {
+            fd_table.init_new(FD_TABLE.copy_inner());
+        }
+        let dir = CURRENT_DIR.deref_from(&self.ns);
+        let dir_path = CURRENT_DIR_PATH.deref_from(&self.ns);
+        if fs {
+            dir.init_shared(CURRENT_DIR.share());
+            dir_path.init_shared(CURRENT_DIR_PATH.share());
+        } else {
+            dir.init_new(CURRENT_DIR.copy_inner());
+            dir_path.init_new(CURRENT_DIR_PATH.copy_inner());
+        }
     }
 
     pub(crate) fn time_stat_from_kernel_to_user(&self, current_tick: usize) {
@@ -195,48 +576,210 @@ impl TaskExt {
         }
     }
 
-    pub(crate) fn time_stat_from_user_to_kernel(&self, current_tick: usize) {
+    /// Returns the user time added, in nanoseconds.
+    pub(crate) fn time_stat_from_user_to_kernel(&self, current_tick: usize) -> usize {
         let time = self.time.get();
-        unsafe {
-            (*time).switch_into_kernel_mode(current_tick);
-        }
+        unsafe { (*time).switch_into_kernel_mode(current_tick) }
     }
 
-    pub(crate) fn time_stat_output(&self) -> (usize, usize) {
+    /// The CPU time the task has used so far, in nanoseconds.
+    pub fn cpu_time(&self) -> usize {
+        let (utime, stime) = self.time_stat_output();
+        utime + stime
+    }
+
+    /// The user and system time the task has used so far, in nanoseconds.
+    pub fn time_stat_output(&self) -> (usize, usize) {
         let time = self.time.get();
         unsafe { (*time).output() }
     }
 
     pub fn get_heap_bottom(&self) -> u64 {
-        self.heap_bottom.load(Ordering::Acquire)
+        self.heap.bottom.load(Ordering::Acquire)
     }
 
     pub fn set_heap_bottom(&self, bottom: u64) {
-        self.heap_bottom.store(bottom, Ordering::Release)
+        self.heap.bottom.store(bottom, Ordering::Release)
     }
 
     pub fn get_heap_top(&self) -> u64 {
-        self.heap_top.load(Ordering::Acquire)
+        self.heap.top.load(Ordering::Acquire)
     }
 
     pub fn set_heap_top(&self, top: u64) {
-        self.heap_top.store(top, Ordering::Release)
+        self.heap.top.store(top, Ordering::Release)
     }
 
-    pub fn get_stack_size(&self) -> u64 {
-        self.stack_size.load(Ordering::Acquire)
+    /// Counts again what the address space commits after its mappings or the
+    /// break have changed, given `aspace`, its locked contents. The heap above
+    /// the break is mapped ahead of time, but only committed once the break
+    /// moves into it.
+    pub fn recount_commit(&self, aspace: &AddrSpace) {
+        let heap_end = self.get_heap_bottom() as usize + axconfig::plat::USER_HEAP_SIZE;
+        let unused = heap_end.saturating_sub(self.get_heap_top() as usize);
+        self.commit.recount(aspace, unused);
     }
 
-    pub fn set_stack_size(&self, size: u64) {
-        self.stack_size.store(size, Ordering::Release)
+    /// The soft limit on the stack size.
+    pub fn get_stack_size(&self) -> u64 {
+        self.rlimit(RLIMIT_STACK).rlim_cur
     }
 
+    /// The soft limit on fds, which every fd must be below.
     pub fn get_fd_limit(&self) -> u64 {
-        self.fd_limit.load(Ordering::Acquire)
+        self.rlimit(RLIMIT_NOFILE).rlim_cur
+    }
+
+    /// The limit on `resource`, one of the `RLIMIT_*` below [`RLIM_NLIMITS`].
+    pub fn rlimit(&self, resource: i32) -> RLimit {
+        self.rlimits.lock()[resource as usize]
     }
-    
-    pub fn set_fd_limit(&self, limit: u64) {
-        self.fd_limit.store(limit, Ordering::Release)
+
+    pub fn set_rlimit(&self, resource: i32, limit: RLimit) {
+        self.rlimits.lock()[resource as usize] = limit;
+    }
+
+    pub fn nice(&self) -> i32 {
+        self.nice.load(Ordering::Acquire)
+    }
+
+    pub fn set_nice(&self, nice: i32) {
+        self.nice.store(nice, Ordering::Release)
+    }
+
+    /// The nice value with priority inheritance, the more favorable of the
+    /// task's own and the boost from its PI futex waiters.
+    pub fn effective_nice(&self) -> i32 {
+        self.nice().min(self.pi_boost.load(Ordering::Acquire))
+    }
+
+    pub fn set_pi_boost(&self, boost: Option<i32>) {
+        self.pi_boost
+            .store(boost.unwrap_or(i32::MAX), Ordering::Release)
+    }
+
+    pub fn dumpable(&self) -> bool {
+        self.dumpable.load(Ordering::Acquire)
+    }
+
+    pub fn set_dumpable(&self, dumpable: bool) {
+        self.dumpable.store(dumpable, Ordering::Release)
+    }
+
+    pub fn timer_slack(&self) -> u64 {
+        self.timer_slack.load(Ordering::Acquire)
+    }
+
+    pub fn set_timer_slack(&self, slack: u64) {
+        self.timer_slack.store(slack, Ordering::Release)
+    }
+
+    pub fn child_subreaper(&self) -> bool {
+        self.child_subreaper.load(Ordering::Acquire)
+    }
+
+    pub fn set_child_subreaper(&self, child_subreaper: bool) {
+        self.child_subreaper
+            .store(child_subreaper, Ordering::Release)
+    }
+
+    pub fn term_signal(&self) -> u64 {
+        self.term_signal.load(Ordering::Acquire)
+    }
+
+    pub fn set_term_signal(&self, sig: usize, core_dumped: bool) {
+        let status = sig as u64 | if core_dumped { 0x80 } else { 0 };
+        self.term_signal.store(status, Ordering::Release)
+    }
+
+    /// The code the thread group is exiting with, if `exit_group` has been
+    /// called by one of its threads.
+    pub fn group_exit(&self) -> Option<i32> {
+        *self.group_exit.lock()
+    }
+
+    /// Marks the thread group as exiting with `exit_code`, returning the code
+    /// it exits with: that of the first thread to call `exit_group`.
+    fn set_group_exit(&self, exit_code: i32) -> i32 {
+        *self.group_exit.lock().get_or_insert(exit_code)
+    }
+
+    pub fn child_exits(&self) -> u64 {
+        self.child_exits.load(Ordering::Acquire)
+    }
+
+    fn notify_child_exit(&self) {
+        self.child_exits.fetch_add(1, Ordering::AcqRel);
+        self.child_exit_wq.notify_all(false);
+    }
+
+    /// Sleeps until a child exits after [`Self::child_exits`] returned `seen`,
+    /// or until `timeout` elapses.
+    ///
+    /// A child exits only after telling its parent, so the wakeup can come a
+    /// little too early. Callers should check their children again and go
+    /// back to sleep if none has exited yet.
+    pub fn wait_child_exit(&self, seen: u64, timeout: Duration) {
+        self.child_exit_wq.wait_timeout_until(timeout, || {
+            self.child_exits() != seen || self.group_exit().is_some()
+        });
+    }
+
+    /// The signal that stopped the process, or 0 if it is running.
+    pub fn stop_signal(&self) -> usize {
+        self.job.stop_signal.load(Ordering::Acquire)
+    }
+
+    /// Stops the process on `sig`, telling its parent.
+    pub(crate) fn stop_process(&self, sig: usize) {
+        self.job.stop_signal.store(sig, Ordering::Release);
+        *self.job.change.lock() = Some(JobChange::Stopped(sig));
+        self.notify_parent();
+    }
+
+    /// Continues the process if it is stopped, telling its parent.
+    pub(crate) fn continue_process(&self) {
+        if self.job.stop_signal.swap(0, Ordering::AcqRel) != 0 {
+            *self.job.change.lock() = Some(JobChange::Continued);
+            self.job.wq.notify_all(false);
+            self.notify_parent();
+        }
+    }
+
+    /// Sleeps while the process is stopped, or until `timeout` elapses.
+    pub(crate) fn wait_continued(&self, timeout: Duration) {
+        self.job.wq.wait_timeout_until(timeout, || {
+            self.stop_signal() == 0 || self.group_exit().is_some()
+        });
+    }
+
+    /// Takes the last stop or continuation of the process if `wait4` with
+    /// `flags` reports it, returning its wait status.
+    fn take_job_change(&self, flags: WaitFlags) -> Option<i32> {
+        let mut change = self.job.change.lock();
+        let status = match (*change)? {
+            JobChange::Stopped(sig) if flags.contains(WaitFlags::WIMTRACED) => {
+                ((sig as i32) << 8) | 0x7f
+            }
+            JobChange::Continued if flags.contains(WaitFlags::WCONTINUED) => 0xffff,
+            _ => return None,
+        };
+        *change = None;
+        Some(status)
+    }
+
+    fn notify_parent(&self) {
+        if let Some(parent) = find_task(self.get_parent()) {
+            parent.task_ext().notify_child_exit();
+        }
+    }
+
+    /// The status reported to `wait4` once the task has exited with `exit_code`.
+    pub fn wait_status(&self, exit_code: i32) -> i32 {
+        match self.term_signal() {
+            0 => exit_code << 8,
+            status => status as i32,
+        }
     }
 }
 
@@ -265,7 +808,12 @@ impl AxNamespaceIf for AxNamespaceImpl {
 
 impl Drop for TaskExt {
     fn drop(&mut self) {
-        if !cfg!(target_arch = "aarch64") && !cfg!(target_arch = "loongarch64") {
+        TASK_TABLE.write().remove(&(self.proc_id as u64));
+        // The kernel mappings are left to the others sharing the space.
+        if !cfg!(target_arch = "aarch64")
+            && !cfg!(target_arch = "loongarch64")
+            && Arc::strong_count(&self.aspace) == 1
+        {
             // See [`crate::new_user_aspace`]
             let kernel = kernel_aspace().lock();
             self.aspace
@@ -277,10 +825,222 @@ impl Drop for TaskExt {
 
 axtask::def_task_ext!(TaskExt);
 
+/// The user tasks that have not been freed yet, by ID.
+static TASK_TABLE: RwLock<BTreeMap<u64, WeakAxTaskRef>> = RwLock::new(BTreeMap::new());
+
+fn register_task(task: &AxTaskRef) {
+    TASK_TABLE
+        .write()
+        .insert(task.id().as_u64(), Arc::downgrade(task));
+}
+
+/// Finds the user task with ID `tid`, which may have exited but not yet been
+/// reaped.
+pub fn find_task(tid: u64) -> Option<AxTaskRef> {
+    TASK_TABLE.read().get(&tid).and_then(WeakAxTaskRef::upgrade)
+}
+
+/// Returns all the user tasks.
+pub fn all_tasks() -> Vec<AxTaskRef> {
+    TASK_TABLE
+        .read()
+        .values()
+        .filter_map(WeakAxTaskRef::upgrade)
+        .collect()
+}
+
+/// Returns the threads of the process `tgid`, which each keep their own copy
+/// of its process group, session and controlling terminal.
+pub fn threads_of(tgid: u64) -> Vec<AxTaskRef> {
+    all_tasks()
+        .into_iter()
+        .filter(|task| task.tgid() == tgid)
+        .collect()
+}
+
+/// Returns the user and system time used so far by the threads of the
+/// process `tgid` that have not been freed, in nanoseconds.
+pub fn process_time_stat(tgid: u64) -> (usize, usize) {
+    all_tasks()
+        .iter()
+        .filter(|task| task.tgid() == tgid)
+        .map(|task| task.task_ext().time_stat_output())
+        .fold((0, 0), |(utime, stime), (u, s)| (utime + u, stime + s))
+}
+
+/// Whether the process group `pgid` is orphaned: no member has a parent in
+/// another group of the same session, which could continue it if it stopped.
+pub fn is_orphaned_group(pgid: u64) -> bool {
+    !all_tasks()
+        .iter()
+        .filter(|task| task.task_ext().pgid() == pgid)
+        .any(|task| {
+            find_task(task.task_ext().get_parent()).is_some_and(|parent| {
+                let parent = parent.task_ext();
+                parent.pgid() != pgid && parent.sid() == task.task_ext().sid()
+            })
+        })
+}
+
+/// Finds the process that the orphaned children of the current one go to:
+/// the nearest living ancestor that is a child subreaper, or else init.
+fn find_reaper() -> (u64, Option<AxTaskRef>) {
+    let mut pid = current().task_ext().get_parent();
+    while let Some(task) = find_task(pid) {
+        if task.task_ext().child_subreaper() && task.state() != axtask::TaskState::Exited {
+            return (pid, Some(task));
+        }
+        pid = task.task_ext().get_parent();
+    }
+    (1, find_task(1))
+}
+
+/// Hands the children of the current process over to its reaper, which is
+/// told of any that have already exited so it can wait for them.
+fn reparent_children() {
+    let children = core::mem::take(&mut *current().task_ext().children.lock());
+    if children.is_empty() {
+        return;
+    }
+    let (reaper_pid, reaper) = find_reaper();
+    let exited = children
+        .iter()
+        .any(|child| child.state() == axtask::TaskState::Exited);
+    for child in &children {
+        child.task_ext().set_parent(reaper_pid);
+    }
+    if let Some(reaper) = reaper {
+        reaper.task_ext().children.lock().extend(children);
+        if exited {
+            reaper.task_ext().notify_child_exit();
+        }
+    }
+}
+
+/// Exits the current task with `exit_code`, waking its parent if it is
+/// waiting for a child.
+///
+/// The exit of the main thread is taken as that of the process, to be
+/// accounted for.
+pub fn exit_current(exit_code: i32) -> ! {
+    {
+        let curr = current();
+        clear_child_tid();
+        curr.task_ext().release_vfork();
+        let threads = &curr.task_ext().threads;
+        if curr.id().as_u64() == curr.tgid() {
+            // The process is only gone with the last of its threads.
+            threads
+                .wq
+                .wait_until(|| threads.live.load(Ordering::Acquire) == 1);
+            crate::acct::account_exit(exit_code);
+            crate_interface::call_interface!(ProcessIf::process_exit(curr.tgid()));
+            reparent_children();
+            if let Some(parent) = find_task(curr.task_ext().get_parent()) {
+                parent.task_ext().notify_child_exit();
+            }
+        } else {
+            threads.live.fetch_sub(1, Ordering::AcqRel);
+            threads.wq.notify_all(false);
+        }
+    }
+    axtask::exit(exit_code)
+}
+
+/// The threads of the current process other than the current one.
+fn other_threads() -> impl Iterator<Item = AxTaskRef> {
+    let curr = current();
+    let tgid = curr.tgid();
+    let id = curr.id();
+    all_tasks()
+        .into_iter()
+        .filter(move |task| task.tgid() == tgid && task.id() != id)
+}
+
+/// Stores `value` to the word at `addr` in the memory of the current task,
+/// returning whether it could: the address must be non-null, aligned and
+/// writable.
+fn put_user_u32(addr: usize, value: u32) -> bool {
+    let addr = VirtAddr::from(addr);
+    if addr.as_usize() == 0 || !addr.is_aligned(align_of::<u32>()) {
+        return false;
+    }
+    let mut aspace = current().task_ext().aspace.lock();
+    let range = VirtAddrRange::from_start_size(addr, size_of::<u32>());
+    if !aspace.check_region_access(range, MappingFlags::WRITE)
+        || aspace
+            .populate_area(addr.align_down_4k(), PAGE_SIZE_4K)
+            .is_err()
+    {
+        return false;
+    }
+    unsafe { (addr.as_mut_ptr() as *mut u32).write_volatile(value) };
+    true
+}
+
+/// Futex hooks of the syscall layer running on top of this crate.
+#[crate_interface::def_interface]
+pub trait FutexIf {
+    /// Wakes up to `count` of the waiters of the futex at `uaddr` in the
+    /// address space of the current task.
+    fn futex_wake(uaddr: usize, count: u32);
+}
+
+/// Process hooks of the syscall layer running on top of this crate.
+#[crate_interface::def_interface]
+pub trait ProcessIf {
+    /// Releases what the syscall layer keeps for the process `tgid` once all
+    /// its threads have exited.
+    fn process_exit(tgid: u64);
+}
+
+/// Zeroes the word at the `clear_child_tid` address of the current thread as
+/// it exits, and wakes a waiter of the futex there, as `pthread_join` waits
+/// for it. Nothing is done if the address is null or not writable.
+fn clear_child_tid() {
+    let addr = current().task_ext().clear_child_tid() as usize;
+    if put_user_u32(addr, 0) {
+        crate_interface::call_interface!(FutexIf::futex_wake(addr, 1));
+    }
+}
+
+/// Exits the whole thread group of the current task with `exit_code`.
+///
+/// The other threads are marked as exiting, which they see as a pending
+/// signal: blocking syscalls return and they exit on the way back to user
+/// space. The current thread exits last if it leads the group, so the process
+/// is only reported to have exited once all of them have.
+///
+/// If another thread has called it first, the group exits with its code.
+pub fn exit_group(exit_code: i32) -> ! {
+    let exit_code = current().task_ext().set_group_exit(exit_code);
+    exit_other_threads(exit_code);
+    exit_current(exit_code)
+}
+
+/// Marks the threads of the current process other than the current one as
+/// exiting with `exit_code`, without waiting for them.
+fn exit_other_threads(exit_code: i32) {
+    for task in other_threads() {
+        // One that has exited already is left alone.
+        if task.state() != axtask::TaskState::Exited {
+            task.task_ext().set_group_exit(exit_code);
+        }
+    }
+    // Those asleep where the process keeps them are woken to see it. The
+    // others sleep in slices, and see it when one ends.
+    let curr = current();
+    curr.task_ext().child_exit_wq.notify_all(false);
+    curr.task_ext().job.wq.notify_all(false);
+}
+
 pub fn spawn_user_task(
     aspace: Arc<Mutex<AddrSpace>>,
     uctx: UspaceContext,
     heap_bottom: u64,
+    args: &[String],
+    envs: &[String],
+    auxv: Vec<(usize, usize)>,
 ) -> AxTaskRef {
     let mut task = TaskInner::new(
         || {
@@ -305,8 +1065,24 @@ pub fn spawn_user_task(
         aspace,
         heap_bottom,
     ));
-    task.task_ext().ns_init_new();
-    axtask::spawn_task(task)
+    task.task_ext().ns_init(false, false);
+    task.task_ext().set_program(args, envs, auxv);
+    task.task_ext()
+        .recount_commit(&task.task_ext().aspace.lock());
+    // The boot task leads a new session, with the console as its terminal.
+    let pid = task.task_ext().proc_id as u64;
+    CONSOLE.attach(pid, pid);
+    task.task_ext().set_has_tty(true);
+    let console: Arc<dyn FileLike> = Arc::new(TtyFile::new(&CONSOLE));
+    let mut fd_table = FD_TABLE.deref_from(&task.task_ext().ns).write();
+    for fd in 0..3 {
+        fd_table.remove(fd);
+        let _ = fd_table.add_at(fd, console.clone());
+    }
+    drop(fd_table);
+    let task = axtask::spawn_task(task);
+    register_task(&task);
+    task
 }
 
 #[allow(unused)]
@@ -324,98 +1100,138 @@ pub fn read_trapframe_from_kstack(kstack_top: usize) -> TrapFrame {
     unsafe { *trap_frame_ptr }
 }
 
+/// Whether `wait4` with `pid` takes `child`: any child if it is -1, those in
+/// the caller's process group if it is 0, those in the group `-pid` if it is
+/// below -1, or else the child `pid` alone.
+pub fn waits_for(pid: i32, child: &AxTaskRef) -> bool {
+    match pid {
+        -1 => true,
+        0 => child.task_ext().pgid() == current().task_ext().pgid(),
+        i32::MIN..-1 => child.task_ext().pgid() == pid.unsigned_abs() as u64,
+        _ => child.id().as_u64() == pid as u64,
+    }
+}
+
+/// Reaps a child that [`waits_for`] `pid` takes and has exited, returning
+/// its ID along with the user and system time it used.
+///
 /// # Safety
 ///
 /// The caller must ensure that the pointer is valid and properly aligned if it's not null.
-pub unsafe fn wait_pid(pid: i32, exit_code_ptr: *mut i32) -> Result<u64, WaitStatus> {
+pub unsafe fn wait_pid(
+    pid: i32,
+    exit_code_ptr: *mut i32,
+    flags: WaitFlags,
+) -> Result<(u64, (usize, usize)), WaitStatus> {
     let curr_task = current();
-    let mut exit_task_id: usize = 0;
-    let mut answer_id: u64 = 0;
-    let mut answer_status = WaitStatus::NotExist;
     info!(
         "wait pid _{}_ with exit_code_ptr _{:?}_",
         pid, exit_code_ptr
     );
-    for (index, child) in curr_task.task_ext().children.lock().iter().enumerate() {
-        if pid <= 0 {
-            if pid == 0 {
-                warn!("Don't support for process group.");
-            }
-
-            answer_status = WaitStatus::Running;
-            if child.state() == axtask::TaskState::Exited {
-                let exit_code = child.exit_code();
-                answer_status = WaitStatus::Exited;
-                info!(
-                    "wait pid _{}_ with code _{}_",
-                    child.id().as_u64(),
-                    exit_code
-                );
-                exit_task_id = index;
-                if !exit_code_ptr.is_null() {
-                    unsafe {
-                        *exit_code_ptr = exit_code << 8;
-                    }
-                }
-                answer_id = child.id().as_u64();
-                break;
-            }
-        } else if child.id().as_u64() == pid as u64 {
-            if let Some(exit_code) = child.join() {
-                answer_status = WaitStatus::Exited;
-                info!(
-                    "wait pid _{}_ with code _{:?}_",
-                    child.id().as_u64(),
-                    exit_code
-                );
-                exit_task_id = index;
-                if !exit_code_ptr.is_null() {
-                    unsafe {
-                        *exit_code_ptr = exit_code << 8;
-                    }
-                }
-                answer_id = child.id().as_u64();
-            } else {
-                answer_status = WaitStatus::Running;
-            }
+    let mut children = curr_task.task_ext().children.lock();
+    let mut answer_status = WaitStatus::NotExist;
+    let mut exited = None;
+    let mut changed = None;
+    for (index, child) in children.iter().enumerate() {
+        if !waits_for(pid, child) {
+            continue;
+        }
+        if child.state() == axtask::TaskState::Exited {
+            exited = Some(index);
+            break;
+        }
+        answer_status = WaitStatus::Running;
+        // A stopped or continued child is reported but stays a child.
+        if let Some(status) = child.task_ext().take_job_change(flags) {
+            changed = Some((child.id().as_u64(), status));
             break;
         }
     }
-
-    if answer_status == WaitStatus::Running {
-        axtask::yield_now();
+    if let Some((child_id, status)) = changed {
+        drop(children);
+        if !exit_code_ptr.is_null() {
+            unsafe { *exit_code_ptr = status };
+        }
+        return Ok((child_id, process_time_stat(child_id)));
     }
+    let Some(index) = exited else {
+        return Err(answer_status);
+    };
+    let child = children.remove(index);
+    drop(children);
 
-    if answer_status == WaitStatus::Exited {
-        curr_task.task_ext().children.lock().remove(exit_task_id);
-        return Ok(answer_id);
+    let exit_code = child.exit_code();
+    info!(
+        "wait pid _{}_ with code _{}_",
+        child.id().as_u64(),
+        exit_code
+    );
+    if !exit_code_ptr.is_null() {
+        unsafe {
+            *exit_code_ptr = child.task_ext().wait_status(exit_code);
+        }
     }
-    Err(answer_status)
+    Ok((child.id().as_u64(), process_time_stat(child.id().as_u64())))
 }
 
+/// Runs the program `name` in place of that of the current process.
+///
+/// If the address space is shared, with the other threads of the process or
+/// with a `CLONE_VFORK` parent, the program is loaded into a new one, which
+/// the current task switches to once the other threads have exited. Only the
+/// main thread can exec then, as the process ID is that of its task.
 pub fn exec(name: &str, args: &[String], envs: &[String]) -> AxResult<()> {
     let current_task = current();
 
     let program_name = name.to_string();
-
-    let mut aspace = current_task.task_ext().aspace.lock();
-    if Arc::strong_count(&current_task.task_ext().aspace) != 1 {
-        warn!("Address space is shared by multiple tasks, exec is not supported.");
+    let threads = &current_task.task_ext().threads;
+    if threads.live.load(Ordering::Acquire) > 1 && current_task.id().as_u64() != current_task.tgid()
+    {
+        warn!("Only the main thread of a process with others can exec.");
         return Err(AxError::Unsupported);
     }
 
-    aspace.unmap_user_areas()?;
-    axhal::arch::flush_tlb(None);
-
-    let (entry_point, user_stack_base) = crate::mm::load_user_app(&mut aspace, args, envs)
-        .map_err(|_| {
-            error!("Failed to load app {}", program_name);
-            AxError::NotFound
-        })?;
+    let cred = current_task.task_ext().cred();
+    let load_failed = |_| {
+        error!("Failed to load app {}", program_name);
+        AxError::NotFound
+    };
+    let task_ext = unsafe { &mut *(current_task.task_ext_ptr() as *mut TaskExt) };
+    let (entry_point, user_stack_base, auxv) = if Arc::strong_count(&task_ext.aspace) == 1 {
+        let mut aspace = task_ext.aspace.lock();
+        aspace.unmap_user_areas()?;
+        axhal::arch::flush_tlb(None);
+        let loaded =
+            crate::mm::load_user_app(&mut aspace, name, args, envs, &cred).map_err(load_failed)?;
+        task_ext.recount_commit(&aspace);
+        loaded
+    } else {
+        // The old space is left as it is if the program cannot be loaded.
+        let mut aspace = new_user_aspace_empty()?;
+        copy_from_kernel(&mut aspace)?;
+        let loaded =
+            crate::mm::load_user_app(&mut aspace, name, args, envs, &cred).map_err(load_failed)?;
+        exit_other_threads(0);
+        threads
+            .wq
+            .wait_until(|| threads.live.load(Ordering::Acquire) == 1);
+        if let Some(exit_code) = task_ext.group_exit() {
+            // Another thread called `exit_group` first.
+            drop(aspace);
+            exit_current(exit_code);
+        }
+        let heap_base = axconfig::plat::USER_HEAP_BASE as u64;
+        task_ext.heap = Arc::new(Heap::new(heap_base, heap_base));
+        task_ext.commit = Arc::new(Commit::default());
+        task_ext.recount_commit(&aspace);
+        switch_aspace(&current_task, task_ext, aspace);
+        loaded
+    };
     current_task.set_name(&program_name);
-    drop(aspace);
+    current_task.task_ext().set_program(args, envs, auxv);
+    drop(FD_TABLE.write().close_on_exec());
+    current_task.task_ext().release_vfork();
 
-    let task_ext = unsafe { &mut *(current_task.task_ext_ptr() as *mut TaskExt) };
     task_ext.uctx = UspaceContext::new(entry_point.as_usize(), user_stack_base, 0);
 
     unsafe {
@@ -427,18 +1243,49 @@ pub fn exec(name: &str, args: &[String], envs: &[String]) -> AxResult<()> {
     }
 }
 
+/// Switches the current task, `task` with `task_ext` as its extended data,
+/// to `aspace`, a new address space of its own.
+fn switch_aspace(task: &TaskInner, task_ext: &mut TaskExt, aspace: AddrSpace) {
+    let root = aspace.page_table_root();
+    let old = core::mem::replace(&mut task_ext.aspace, Arc::new(Mutex::new(aspace)));
+    let irqs_enabled = axhal::arch::irqs_enabled();
+    axhal::arch::disable_irqs();
+    // The context keeps the root the task is switched back to.
+    unsafe {
+        (*task.ctx_mut_ptr()).set_page_table_root(root);
+        #[cfg(any(target_arch = "aarch64", target_arch = "loongarch64"))]
+        axhal::arch::write_page_table_root0(root);
+        #[cfg(not(any(target_arch = "aarch64", target_arch = "loongarch64")))]
+        axhal::arch::write_page_table_root(root);
+    }
+    if irqs_enabled {
+        axhal::arch::enable_irqs();
+    }
+    drop(old);
+}
+
 pub fn time_stat_from_kernel_to_user() {
     let curr_task = current();
     curr_task
         .task_ext()
-        .time_stat_from_kernel_to_user(monotonic_time_nanos() as usize);
+        .time_stat_from_kernel_to_user(curr_task.cpu_time_nanos() as usize);
+}
+
+/// The time each CPU has spent running user code, in nanoseconds.
+static CPU_USER_NANOS: [AtomicU64; axconfig::SMP] = [const { AtomicU64::new(0) }; axconfig::SMP];
+
+/// Returns the time CPU `cpu` has spent running user code since boot, in
+/// nanoseconds, up to the last time it entered the kernel.
+pub fn cpu_user_nanos(cpu: usize) -> u64 {
+    CPU_USER_NANOS[cpu].load(Ordering::Relaxed)
 }
 
 pub fn time_stat_from_user_to_kernel() {
     let curr_task = current();
-    curr_task
+    let utime = curr_task
         .task_ext()
-        .time_stat_from_user_to_kernel(monotonic_time_nanos() as usize);
+        .time_stat_from_user_to_kernel(curr_task.cpu_time_nanos() as usize);
+    CPU_USER_NANOS[axhal::cpu::this_cpu_id()].fetch_add(utime as u64, Ordering::Relaxed);
 }
 
 pub fn time_stat_output() -> (usize, usize, usize, usize) {
@@ -0,0 +1,185 @@
+//! The line discipline, which turns the bytes typed on a terminal into the
+//! input its readers see.
+
+use alloc::{collections::VecDeque, vec::Vec};
+
+use super::termios::*;
+use crate::signal::{SIGINT, SIGQUIT, SIGTSTP};
+
+/// The most input a terminal buffers, including the line being edited.
+const MAX_INPUT: usize = 4096;
+
+#[derive(Default)]
+pub struct LineDiscipline {
+    /// Input that readers can take.
+    ready: VecDeque<u8>,
+    /// In canonical mode, the lengths of the lines in `ready`, including their
+    /// delimiter. A line ended by end-of-file has no delimiter and may be
+    /// empty, which makes a read return 0.
+    lines: VecDeque<usize>,
+    /// The line being edited, in canonical mode.
+    line: Vec<u8>,
+}
+
+impl LineDiscipline {
+    pub const fn new() -> Self {
+        Self {
+            ready: VecDeque::new(),
+            lines: VecDeque::new(),
+            line: Vec::new(),
+        }
+    }
+
+    /// Processes the byte `c` received from the terminal, appending what it
+    /// echoes to `echo`. Returns the signal to send to the foreground process
+    /// group if `c` is a signal character.
+    pub fn receive(&mut self, termios: &Termios, mut c: u8, echo: &mut Vec<u8>) -> Option<usize> {
+        if c == b'\r' {
+            if termios.iflag(IGNCR) {
+                return None;
+            }
+            if termios.iflag(ICRNL) {
+                c = b'\n';
+            }
+        } else if c == b'\n' && termios.iflag(INLCR) {
+            c = b'\r';
+        }
+
+        if termios.lflag(ISIG) {
+            let sig = if termios.is_cc(VINTR, c) {
+                Some(SIGINT)
+            } else if termios.is_cc(VQUIT, c) {
+                Some(SIGQUIT)
+            } else if termios.is_cc(VSUSP, c) {
+                Some(SIGTSTP)
+            } else {
+                None
+            };
+            if sig.is_some() {
+                if !termios.lflag(NOFLSH) {
+                    self.flush();
+                }
+                if termios.lflag(ECHO) {
+                    echo_char(termios, c, echo);
+                }
+                return sig;
+            }
+        }
+
+        if !termios.canonical() {
+            if self.ready.len() < MAX_INPUT {
+                self.ready.push_back(c);
+                if termios.lflag(ECHO) {
+                    echo_char(termios, c, echo);
+                }
+            }
+            return None;
+        }
+
+        if termios.is_cc(VERASE, c) {
+            if self.line.pop().is_some() && termios.lflag(ECHO) {
+                if termios.lflag(ECHOE) {
+                    echo.extend_from_slice(b"\x08 \x08");
+                } else {
+                    echo_char(termios, c, echo);
+                }
+            }
+        } else if termios.is_cc(VKILL, c) {
+            let erased = self.line.len();
+            self.line.clear();
+            if termios.lflag(ECHO) {
+                if termios.lflag(ECHOKE) {
+                    for _ in 0..erased {
+                        echo.extend_from_slice(b"\x08 \x08");
+                    }
+                } else {
+                    echo_char(termios, c, echo);
+                    if termios.lflag(ECHOK) {
+                        echo.push(b'\n');
+                    }
+                }
+            }
+        } else if termios.is_cc(VEOF, c) {
+            self.finish_line();
+        } else if c == b'\n' || termios.is_cc(VEOL, c) || termios.is_cc(VEOL2, c) {
+            self.line.push(c);
+            if termios.lflag(ECHO) || (c == b'\n' && termios.lflag(ECHONL)) {
+                echo_char(termios, c, echo);
+            }
+            self.finish_line();
+        } else if self.ready.len() + self.line.len() < MAX_INPUT - 1 {
+            // The last byte is kept for the line delimiter.
+            self.line.push(c);
+            if termios.lflag(ECHO) {
+                echo_char(termios, c, echo);
+            }
+        }
+        None
+    }
+
+    fn finish_line(&mut self) {
+        self.lines.push_back(self.line.len());
+        self.ready.extend(self.line.drain(..));
+    }
+
+    /// Applies the change of attributes from `old` to `new`.
+    pub fn set_termios(&mut self, old: &Termios, new: &Termios) {
+        if old.canonical() && !new.canonical() {
+            // The line being edited becomes readable as is.
+            self.ready.extend(self.line.drain(..));
+            self.lines.clear();
+        } else if !old.canonical() && new.canonical() {
+            // Raw input is treated as an unfinished line.
+            self.line = self.ready.drain(..).collect();
+        }
+    }
+
+    /// Whether a read in the mode of `termios` would return right away.
+    pub fn readable(&self, termios: &Termios) -> bool {
+        if termios.canonical() {
+            !self.lines.is_empty()
+        } else {
+            !self.ready.is_empty()
+        }
+    }
+
+    /// Takes at most one line in canonical mode, or anything available in
+    /// non-canonical mode. Returns `None` if there is nothing to read yet.
+    pub fn read(&mut self, termios: &Termios, buf: &mut [u8]) -> Option<usize> {
+        let len = if termios.canonical() {
+            let line = self.lines.front_mut()?;
+            let len = (*line).min(buf.len());
+            *line -= len;
+            if *line == 0 {
+                self.lines.pop_front();
+            }
+            len
+        } else {
+            if self.ready.is_empty() {
+                return None;
+            }
+            self.ready.len().min(buf.len())
+        };
+        for (dst, src) in buf.iter_mut().zip(self.ready.drain(..len)) {
+            *dst = src;
+        }
+        Some(len)
+    }
+
+    /// Discards all input.
+    pub fn flush(&mut self) {
+        self.ready.clear();
+        self.lines.clear();
+        self.line.clear();
+    }
+}
+
+/// Echoes `c`, showing control characters as `^X` with `ECHOCTL`.
+fn echo_char(termios: &Termios, c: u8, echo: &mut Vec<u8>) {
+    if termios.lflag(ECHOCTL) && (c < 0x20 || c == 0x7f) && c != b'\n' && c != b'\t' {
+        echo.push(b'^');
+        echo.push(c ^ 0x40);
+    } else {
+        echo.push(c);
+    }
+}
@@ -0,0 +1,420 @@
+//! Terminals and their association with sessions.
+//!
+//! The terminals are the console and the slaves of pseudoterminals. A session
+//! has one as its controlling terminal when the terminal records that session
+//! as its owner, and each process of the session shares it unless it has given
+//! it up.
+
+mod ldisc;
+mod pty;
+pub mod termios;
+
+pub use self::pty::{Pty, PtyMaster, open_master, open_slave};
+
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
+
+use arceos_posix_api::{FileLike, ctypes};
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::monotonic_time;
+use axio::PollState;
+use axtask::{TaskExtRef, current};
+use spin::Mutex;
+
+use self::{
+    ldisc::LineDiscipline,
+    termios::{TOSTOP, Termios, VMIN, VTIME},
+};
+use crate::{
+    signal::{SI_KERNEL, SIG_IGN, SIGTTIN, SIGTTOU, SigInfo, send_signal_to_group, signal_pending},
+    task::is_orphaned_group,
+};
+
+/// The hardware side of a terminal.
+pub trait TtyDriver: Send + Sync {
+    /// Reads the input that has arrived, without blocking.
+    fn read(&self, buf: &mut [u8]) -> usize;
+    /// Writes `buf` out, translating newlines as the device needs.
+    fn write(&self, buf: &[u8]);
+
+    /// Called when a file is opened on the terminal.
+    fn open(&self) {}
+
+    /// Called when a file opened on the terminal is closed.
+    fn close(&self) {}
+
+    /// Whether the other side of the device is gone, so that no more input
+    /// will arrive.
+    fn hung_up(&self) -> bool {
+        false
+    }
+}
+
+struct ConsoleDriver;
+
+impl TtyDriver for ConsoleDriver {
+    fn read(&self, buf: &mut [u8]) -> usize {
+        axhal::console::read_bytes(buf)
+    }
+
+    fn write(&self, buf: &[u8]) {
+        axhal::console::write_bytes(buf);
+    }
+}
+
+/// The size of a terminal window, in the layout of `struct winsize`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct WinSize {
+    pub ws_row: u16,
+    pub ws_col: u16,
+    pub ws_xpixel: u16,
+    pub ws_ypixel: u16,
+}
+
+const DEFAULT_WINSIZE: WinSize = WinSize {
+    ws_row: 24,
+    ws_col: 80,
+    ws_xpixel: 0,
+    ws_ypixel: 0,
+};
+
+/// A terminal device.
+pub struct Tty {
+    driver: &'static dyn TtyDriver,
+    /// The device number.
+    rdev: u64,
+    /// The session this is the controlling terminal of, or 0 if none.
+    session: AtomicU64,
+    /// The foreground process group.
+    foreground: AtomicU64,
+    termios: Mutex<Termios>,
+    winsize: Mutex<WinSize>,
+    ldisc: Mutex<LineDiscipline>,
+    /// Serializes output, so that writes and echoes do not interleave.
+    output: Mutex<()>,
+}
+
+impl Tty {
+    const fn new(driver: &'static dyn TtyDriver, rdev: u64) -> Self {
+        Self {
+            driver,
+            rdev,
+            session: AtomicU64::new(0),
+            foreground: AtomicU64::new(0),
+            termios: Mutex::new(Termios::new()),
+            winsize: Mutex::new(DEFAULT_WINSIZE),
+            ldisc: Mutex::new(LineDiscipline::new()),
+            output: Mutex::new(()),
+        }
+    }
+
+    /// The ID of the session this terminal controls, or 0 if none.
+    pub fn session(&self) -> u64 {
+        self.session.load(Ordering::Acquire)
+    }
+
+    /// The ID of the foreground process group.
+    pub fn foreground(&self) -> u64 {
+        self.foreground.load(Ordering::Acquire)
+    }
+
+    pub fn set_foreground(&self, pgid: u64) {
+        self.foreground.store(pgid, Ordering::Release);
+    }
+
+    /// Makes this the controlling terminal of session `sid`, with `pgid` in
+    /// the foreground.
+    pub fn attach(&self, sid: u64, pgid: u64) {
+        self.foreground.store(pgid, Ordering::Release);
+        self.session.store(sid, Ordering::Release);
+    }
+
+    /// Dissociates this terminal from its session.
+    pub fn detach(&self) {
+        self.session.store(0, Ordering::Release);
+        self.foreground.store(0, Ordering::Release);
+    }
+
+    /// Brings the terminal back to the state it had when it was created.
+    fn reset(&self) {
+        self.detach();
+        *self.termios.lock() = Termios::new();
+        *self.winsize.lock() = DEFAULT_WINSIZE;
+        self.ldisc.lock().flush();
+    }
+
+    pub fn rdev(&self) -> u64 {
+        self.rdev
+    }
+
+    pub fn termios(&self) -> Termios {
+        *self.termios.lock()
+    }
+
+    /// Changes the attributes, discarding all pending input first if `flush`.
+    pub fn set_termios(&self, new: Termios, flush: bool) {
+        let mut termios = self.termios.lock();
+        let mut ldisc = self.ldisc.lock();
+        if flush {
+            ldisc.flush();
+        }
+        ldisc.set_termios(&termios, &new);
+        *termios = new;
+    }
+
+    pub fn winsize(&self) -> WinSize {
+        *self.winsize.lock()
+    }
+
+    pub fn set_winsize(&self, winsize: WinSize) {
+        *self.winsize.lock() = winsize;
+    }
+
+    /// Feeds `input` to the line discipline as if it was typed.
+    pub fn receive(&self, input: &[u8]) {
+        let termios = self.termios();
+        let mut echo = Vec::new();
+        let mut signals = Vec::new();
+        let mut ldisc = self.ldisc.lock();
+        for &c in input {
+            signals.extend(ldisc.receive(&termios, c, &mut echo));
+        }
+        drop(ldisc);
+        if !echo.is_empty() {
+            let _guard = self.output.lock();
+            self.driver.write(&echo);
+        }
+        let foreground = self.foreground();
+        if foreground != 0 {
+            for sig in signals {
+                send_signal_to_group(foreground, SigInfo::new(sig, SI_KERNEL));
+            }
+        }
+    }
+
+    /// Moves the input that has arrived at the device to the line discipline.
+    fn pump(&self) {
+        let mut buf = [0; 64];
+        loop {
+            let len = self.driver.read(&mut buf);
+            if len == 0 {
+                break;
+            }
+            self.receive(&buf[..len]);
+        }
+    }
+
+    /// Whether the other side of the terminal is gone.
+    pub fn hung_up(&self) -> bool {
+        self.driver.hung_up()
+    }
+
+    /// Whether a read would return right away.
+    pub fn readable(&self) -> bool {
+        self.pump();
+        let termios = self.termios();
+        self.ldisc.lock().readable(&termios)
+    }
+
+    /// Reads a line in canonical mode, or in non-canonical mode as much input
+    /// as `VMIN` and `VTIME` ask for.
+    pub fn read(&self, buf: &mut [u8], nonblocking: bool) -> LinuxResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let start = monotonic_time();
+        let mut last_input = start;
+        let mut read = 0;
+        loop {
+            self.pump();
+            let termios = self.termios();
+            let taken = self.ldisc.lock().read(&termios, &mut buf[read..]);
+            if termios.canonical() {
+                if let Some(len) = taken {
+                    return Ok(len);
+                }
+            } else {
+                if let Some(len) = taken {
+                    read += len;
+                    last_input = monotonic_time();
+                }
+                let min = termios.c_cc[VMIN] as usize;
+                let time = Duration::from_millis(100 * termios.c_cc[VTIME] as u64);
+                let now = monotonic_time();
+                let done = read == buf.len()
+                    || match (min, time.is_zero()) {
+                        (0, true) => true,
+                        (0, false) => read > 0 || now - start >= time,
+                        (_, true) => read >= min,
+                        // `VTIME` is an inter-byte timer once input has started.
+                        (_, false) => read >= min || (read > 0 && now - last_input >= time),
+                    };
+                if done {
+                    return Ok(read);
+                }
+            }
+            if read > 0 && (nonblocking || signal_pending()) {
+                return Ok(read);
+            }
+            if self.hung_up() {
+                return Err(LinuxError::EIO);
+            }
+            if nonblocking {
+                return Err(LinuxError::EAGAIN);
+            }
+            if signal_pending() {
+                return Err(LinuxError::EINTR);
+            }
+            axtask::yield_now();
+        }
+    }
+
+    /// Stops a background process from using the terminal: when the calling
+    /// process is in a background process group of the session this terminal
+    /// controls, sends `sig` (`SIGTTIN` or `SIGTTOU`) to its group and fails
+    /// with `EINTR`.
+    ///
+    /// With the default action, the signal stops the group until it is
+    /// continued. A process that blocks or ignores `SIGTTOU` may go on;
+    /// reading fails with `EIO` instead. So does anything from an orphaned
+    /// process group, which nobody could continue.
+    pub fn check_background(&self, sig: usize) -> LinuxResult {
+        let curr = current();
+        let ext = curr.task_ext();
+        let foreground = self.foreground();
+        let pgid = ext.pgid();
+        if !ext.has_tty() || self.session() != ext.sid() || foreground == 0 || pgid == foreground {
+            return Ok(());
+        }
+        let handler = ext.signal_actions.lock().get(sig).handler;
+        if handler == SIG_IGN || ext.signal.lock().blocked.contains(sig) {
+            return if sig == SIGTTIN {
+                Err(LinuxError::EIO)
+            } else {
+                Ok(())
+            };
+        }
+        if is_orphaned_group(pgid) {
+            return Err(LinuxError::EIO);
+        }
+        send_signal_to_group(pgid, SigInfo::new(sig, SI_KERNEL));
+        Err(LinuxError::EINTR)
+    }
+
+    pub fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        if self.hung_up() {
+            return Err(LinuxError::EIO);
+        }
+        let _guard = self.output.lock();
+        self.driver.write(buf);
+        Ok(buf.len())
+    }
+}
+
+const fn makedev(major: u32, minor: u32) -> u64 {
+    (((major & 0xfff) << 8) | (minor & 0xff)) as u64
+}
+
+/// The system console.
+pub static CONSOLE: Tty = Tty::new(&ConsoleDriver, makedev(5, 1));
+
+/// Returns the controlling terminal of session `sid`, if it has one.
+pub fn controlling_tty(sid: u64) -> Option<&'static Tty> {
+    if sid == 0 {
+        return None;
+    }
+    if CONSOLE.session() == sid {
+        return Some(&CONSOLE);
+    }
+    pty::find_slave(|tty| tty.session() == sid)
+}
+
+/// Returns the controlling terminal of the current process, if it has one.
+pub fn current_tty() -> Option<&'static Tty> {
+    let curr = current();
+    let ext = curr.task_ext();
+    if ext.has_tty() {
+        controlling_tty(ext.sid())
+    } else {
+        None
+    }
+}
+
+/// An open terminal.
+pub struct TtyFile {
+    tty: &'static Tty,
+    nonblocking: AtomicBool,
+}
+
+impl TtyFile {
+    pub fn new(tty: &'static Tty) -> Self {
+        tty.driver.open();
+        Self {
+            tty,
+            nonblocking: AtomicBool::new(false),
+        }
+    }
+
+    pub fn tty(&self) -> &'static Tty {
+        self.tty
+    }
+}
+
+impl Drop for TtyFile {
+    fn drop(&mut self) {
+        self.tty.driver.close();
+    }
+}
+
+impl FileLike for TtyFile {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        self.tty.check_background(SIGTTIN)?;
+        self.tty.read(buf, self.nonblocking.load(Ordering::Acquire))
+    }
+
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        if self.tty.termios().lflag(TOSTOP) {
+            self.tty.check_background(SIGTTOU)?;
+        }
+        self.tty.write(buf)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode: 0o020000 | 0o620, // S_IFCHR | rw--w----
+            st_rdev: self.tty.rdev() as _,
+            st_blksize: 1024,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: self.tty.readable() || self.tty.hung_up(),
+            writable: true,
+        })
+    }
+
+    fn hung_up(&self) -> bool {
+        self.tty.hung_up()
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Release);
+        Ok(())
+    }
+
+    fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Acquire)
+    }
+}
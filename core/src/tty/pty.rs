@@ -0,0 +1,260 @@
+//! Pseudoterminals: pairs of a master, held by a program such as a terminal
+//! emulator, and a slave terminal under `/dev/pts`.
+//!
+//! Pseudoterminals are allocated once and never freed. One whose master and
+//! slave are all closed is reused by the next `/dev/ptmx` open.
+
+use alloc::{boxed::Box, collections::VecDeque, sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use arceos_posix_api::{FileLike, ctypes};
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use spin::Mutex;
+
+use super::{Tty, TtyDriver, TtyFile, makedev};
+use crate::signal::{SI_KERNEL, SIGCONT, SIGHUP, SigInfo, send_signal_to_group, signal_pending};
+
+/// The most pseudoterminals there can be, as many as slave minor numbers.
+const MAX_PTYS: usize = 256;
+
+const PTY_SLAVE_MAJOR: u32 = 136;
+
+/// The device a slave terminal runs on. What the slave outputs is read from
+/// the master, and what is written to the master is typed on the slave.
+struct PtyLink {
+    /// The output of the slave, waiting to be read from the master.
+    output: Mutex<VecDeque<u8>>,
+    master_open: AtomicBool,
+    /// The number of files open on the slave.
+    slaves: AtomicUsize,
+    /// Whether the slave has been opened since the master was, so that the
+    /// master is hung up once they are all closed.
+    slave_opened: AtomicBool,
+    /// Whether opening the slave is forbidden, until `unlockpt`.
+    locked: AtomicBool,
+}
+
+impl PtyLink {
+    const fn new() -> Self {
+        Self {
+            output: Mutex::new(VecDeque::new()),
+            master_open: AtomicBool::new(false),
+            slaves: AtomicUsize::new(0),
+            slave_opened: AtomicBool::new(false),
+            locked: AtomicBool::new(true),
+        }
+    }
+
+    /// Whether all the slaves opened have been closed.
+    fn slave_closed(&self) -> bool {
+        self.slave_opened.load(Ordering::Acquire) && self.slaves.load(Ordering::Acquire) == 0
+    }
+}
+
+impl TtyDriver for PtyLink {
+    fn read(&self, _buf: &mut [u8]) -> usize {
+        // Writes to the master are passed to the slave right away.
+        0
+    }
+
+    fn write(&self, buf: &[u8]) {
+        self.output.lock().extend(buf);
+    }
+
+    fn open(&self) {
+        self.slaves.fetch_add(1, Ordering::AcqRel);
+        self.slave_opened.store(true, Ordering::Release);
+    }
+
+    fn close(&self) {
+        self.slaves.fetch_sub(1, Ordering::AcqRel);
+    }
+
+    fn hung_up(&self) -> bool {
+        !self.master_open.load(Ordering::Acquire)
+    }
+}
+
+/// A pseudoterminal.
+pub struct Pty {
+    index: usize,
+    link: &'static PtyLink,
+    slave: Tty,
+}
+
+impl Pty {
+    /// The number of the slave, as in `/dev/pts/N`.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn slave(&'static self) -> &'static Tty {
+        &self.slave
+    }
+
+    pub fn locked(&self) -> bool {
+        self.link.locked.load(Ordering::Acquire)
+    }
+
+    pub fn set_locked(&self, locked: bool) {
+        self.link.locked.store(locked, Ordering::Release);
+    }
+
+    fn in_use(&self) -> bool {
+        self.link.master_open.load(Ordering::Acquire)
+            || self.link.slaves.load(Ordering::Acquire) > 0
+    }
+}
+
+static PTYS: Mutex<Vec<&'static Pty>> = Mutex::new(Vec::new());
+
+/// Returns the slave terminal that satisfies `pred`, if any.
+pub(super) fn find_slave(pred: impl Fn(&Tty) -> bool) -> Option<&'static Tty> {
+    PTYS.lock()
+        .iter()
+        .find(|pty| pred(&pty.slave))
+        .map(|pty| pty.slave())
+}
+
+/// Allocates a pseudoterminal and opens its master, as opening `/dev/ptmx`
+/// does. The slave starts out locked.
+pub fn open_master() -> LinuxResult<PtyMaster> {
+    let mut ptys = PTYS.lock();
+    let pty = match ptys.iter().copied().find(|pty| !pty.in_use()) {
+        Some(pty) => {
+            pty.slave.reset();
+            pty.link.output.lock().clear();
+            pty.link.slave_opened.store(false, Ordering::Release);
+            pty
+        }
+        None if ptys.len() < MAX_PTYS => {
+            let index = ptys.len();
+            let link: &'static PtyLink = Box::leak(Box::new(PtyLink::new()));
+            let pty: &'static Pty = Box::leak(Box::new(Pty {
+                index,
+                link,
+                slave: Tty::new(link, makedev(PTY_SLAVE_MAJOR, index as u32)),
+            }));
+            ptys.push(pty);
+            pty
+        }
+        None => return Err(LinuxError::ENOSPC),
+    };
+    pty.set_locked(true);
+    pty.link.master_open.store(true, Ordering::Release);
+    Ok(PtyMaster {
+        pty,
+        nonblocking: AtomicBool::new(false),
+    })
+}
+
+/// Opens the slave of the pseudoterminal `index`, as opening `/dev/pts/N`
+/// does.
+pub fn open_slave(index: usize) -> LinuxResult<TtyFile> {
+    let ptys = PTYS.lock();
+    let pty = ptys
+        .get(index)
+        .copied()
+        .filter(|pty| pty.link.master_open.load(Ordering::Acquire))
+        .ok_or(LinuxError::ENOENT)?;
+    if pty.locked() {
+        return Err(LinuxError::EIO);
+    }
+    Ok(TtyFile::new(pty.slave()))
+}
+
+/// The master of a pseudoterminal.
+pub struct PtyMaster {
+    pty: &'static Pty,
+    nonblocking: AtomicBool,
+}
+
+impl PtyMaster {
+    pub fn pty(&self) -> &'static Pty {
+        self.pty
+    }
+}
+
+impl Drop for PtyMaster {
+    /// Hangs up the slave, and the session it controls.
+    fn drop(&mut self) {
+        let slave = &self.pty.slave;
+        self.pty.link.master_open.store(false, Ordering::Release);
+        if slave.session() != 0 {
+            let foreground = slave.foreground();
+            slave.detach();
+            send_signal_to_group(foreground, SigInfo::new(SIGHUP, SI_KERNEL));
+            send_signal_to_group(foreground, SigInfo::new(SIGCONT, SI_KERNEL));
+        }
+    }
+}
+
+impl FileLike for PtyMaster {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let mut output = self.pty.link.output.lock();
+            if !output.is_empty() {
+                let len = output.len().min(buf.len());
+                for (dst, src) in buf.iter_mut().zip(output.drain(..len)) {
+                    *dst = src;
+                }
+                return Ok(len);
+            }
+            drop(output);
+            if self.pty.link.slave_closed() {
+                return Err(LinuxError::EIO);
+            }
+            if self.nonblocking.load(Ordering::Acquire) {
+                return Err(LinuxError::EAGAIN);
+            }
+            if signal_pending() {
+                return Err(LinuxError::EINTR);
+            }
+            axtask::yield_now();
+        }
+    }
+
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        self.pty.slave.receive(buf);
+        Ok(buf.len())
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode: 0o020000 | 0o666, // S_IFCHR | rw-rw-rw-
+            st_rdev: makedev(5, 2) as _,
+            st_blksize: 1024,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: !self.pty.link.output.lock().is_empty() || self.pty.link.slave_closed(),
+            writable: true,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Release);
+        Ok(())
+    }
+
+    fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Acquire)
+    }
+
+    fn hung_up(&self) -> bool {
+        self.pty.link.slave_closed()
+    }
+}